@@ -1,2 +1,285 @@
+pub mod ablate;
 pub mod analyze;
+pub mod annotate;
+pub mod bench;
+pub mod bench_eval;
+pub mod calibrate;
+pub mod check;
+pub mod compare_eval;
+pub mod correspond;
+pub mod epd;
+pub mod export_features;
+pub mod index_games;
+pub mod jsonrpc;
+pub mod opening_stats;
+pub mod options;
 pub mod play;
+pub mod prep;
+pub mod show_pst;
+pub mod split;
+pub mod stats;
+pub mod suite;
+pub mod training_data;
+pub mod tune;
+pub mod uci;
+
+use clap::{Arg, ArgMatches, ErrorKind};
+use rad1::eval;
+use rad1::eval::registry::BoxedEvaluator;
+use rad1::fen;
+use rad1::notation::Notation;
+use rad1::options::EngineOptions;
+use rad1::tt::TranspositionTable;
+use rad1::winprob::WinModel;
+use rad1::Action;
+use rad1::ChessGame;
+use rad1::ChessMove;
+use std::fs;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Exit code of a command stopped with Ctrl-C
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// FEN of the standard starting position
+pub const START_POSITION: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Install a Ctrl-C handler that sets the returned flag
+/// so a running search can stop and report what it found so far
+pub fn interrupt_flag() -> Arc<AtomicBool> {
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&interrupted);
+    ctrlc::set_handler(move || flag.store(true, Ordering::SeqCst))
+        .expect("Failed to install the Ctrl-C handler");
+    interrupted
+}
+
+/// The default transposition table, or a smaller one with a warning
+/// when there isn't enough memory for it
+pub fn transposition_table() -> TranspositionTable<i16> {
+    let (table, error) = TranspositionTable::default_or_smaller();
+    if let Some(error) = error {
+        eprintln!(
+            "Warning: {}, using {} MB instead",
+            error,
+            table.memory_usage() / (1024 * 1024)
+        );
+    }
+    table
+}
+
+/// The `--eval` argument selecting an evaluator from the registry
+pub fn eval_arg() -> Arg<'static, 'static> {
+    Arg::with_name("eval")
+        .long("eval")
+        .short("e")
+        .required(false)
+        .takes_value(true)
+        .default_value("naive")
+        .validator(is_evaluator)
+        .help("The evaluator used by the engine")
+}
+
+/// Validator for keys of the evaluator registry
+pub fn is_evaluator(key: String) -> Result<(), String> {
+    if eval::registry().contains(&key) {
+        Ok(())
+    } else {
+        let keys: Vec<String> = eval::registry()
+            .keys()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        Err(format!("'{}' isn't one of: {}", key, keys.join(", ")))
+    }
+}
+
+/// The `--disable` argument switching off search features, to compare
+/// the engine with and without them
+pub fn disable_arg() -> Arg<'static, 'static> {
+    Arg::with_name("disable")
+        .long("disable")
+        .required(false)
+        .takes_value(true)
+        .multiple(true)
+        .use_delimiter(true)
+        .possible_values(&[
+            "null-move",
+            "check-extensions",
+            "killer-moves",
+            "tablebases",
+        ])
+        .help("Search features to switch off, e.g. --disable null-move,killer-moves")
+}
+
+/// The `--config` argument loading engine options from a file, e.g. one written by `tune`
+pub fn config_arg() -> Arg<'static, 'static> {
+    Arg::with_name("config")
+        .long("config")
+        .required(false)
+        .takes_value(true)
+        .help("A file of engine options with one \"name = value\" line per option")
+}
+
+/// The `--set` argument, search features and evaluation weights set by
+/// path on top of the [`config_arg`] file and the weight file, see
+/// [`EngineOptions::set_path`]
+pub fn set_arg() -> Arg<'static, 'static> {
+    Arg::with_name("set")
+        .long("set")
+        .required(false)
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .value_name("PATH=VALUE")
+        .validator(|assignment| {
+            EngineOptions::default()
+                .set_path(&assignment)
+                .map_err(|error| error.to_string())
+        })
+        .help("Set a search feature or evaluation weight, e.g. --set eval.opening.knight=31 --set search.null_move_reduction=3")
+}
+
+/// The `--notation` argument, how pieces are written in moves, variations,
+/// PGN and on boards
+pub fn notation_arg() -> Arg<'static, 'static> {
+    Arg::with_name("notation")
+        .long("notation")
+        .required(false)
+        .takes_value(true)
+        .default_value("english")
+        .possible_values(&["english", "figurine", "german", "french", "spanish", "italian"])
+        .help("How pieces are written: with English letters, figurines or the letters of another language")
+}
+
+/// The notation of the [`notation_arg`]
+pub fn notation(matches: &ArgMatches) -> Notation {
+    matches.value_of("notation").unwrap().parse().unwrap()
+}
+
+/// The `--win-model` argument with a model written by `calibrate`
+pub fn win_model_arg() -> Arg<'static, 'static> {
+    Arg::with_name("win-model")
+        .long("win-model")
+        .required(false)
+        .takes_value(true)
+        .value_name("FILE")
+        .help("A win probability model written by calibrate, instead of the built-in one")
+}
+
+/// The model of the [`win_model_arg`] file or the default one, exiting with
+/// an error if the file can't be loaded
+pub fn win_model(matches: &ArgMatches) -> WinModel {
+    match matches.value_of("win-model") {
+        None => WinModel::default(),
+        Some(path) => fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| WinModel::from_str(&text).map_err(|error| error.to_string()))
+            .unwrap_or_else(|error| {
+                let message = format!("Failed to load '{}': {}", path, error);
+                clap::Error::with_description(&message, ErrorKind::InvalidValue).exit()
+            }),
+    }
+}
+
+/// Validator for FEN arguments explaining what's wrong with the position
+pub fn is_fen(value: String) -> Result<(), String> {
+    fen::validate(&value).map_err(|error| format!("invalid FEN, {}", error))
+}
+
+/// Validator for moves in UCI notation like e2e4 or e7e8q
+pub fn is_move(value: String) -> Result<(), String> {
+    ChessMove::from_str(&value)
+        .map(|_| ())
+        .map_err(|_| format!("'{}' is not a move like e2e4", value))
+}
+
+/// The `--moves` argument, a line played from the starting FEN like the
+/// moves of a UCI `position` command
+pub fn moves_arg() -> Arg<'static, 'static> {
+    Arg::with_name("moves")
+        .long("moves")
+        .short("m")
+        .required(false)
+        .takes_value(true)
+        .validator(|moves| {
+            moves
+                .split_whitespace()
+                .try_for_each(|value| is_move(value.to_string()))
+        })
+        .help("Moves played from the FEN before starting, e.g. --moves \"e2e4 e7e5 g1f3\"")
+}
+
+/// Set up the game from a FEN argument and the moves of [`moves_arg`],
+/// exiting with an error if one of the moves is illegal
+pub fn game(matches: &ArgMatches, fen_arg: &str) -> ChessGame {
+    let fen = matches.value_of(fen_arg).unwrap();
+    let mut game = ChessGame::from_str(fen).expect("Failed to parse FEN");
+    let moves = matches.value_of("moves").unwrap_or_default();
+    for value in moves.split_whitespace() {
+        let chess_move = ChessMove::from_str(value).unwrap();
+        let position = game.current_position();
+        if !position.legal(chess_move) {
+            let message = format!("'{}' is not a legal move in {}", value, position);
+            clap::Error::with_description(&message, ErrorKind::InvalidValue).exit();
+        }
+        game.take_action(Action::MakeMove(chess_move));
+    }
+    game
+}
+
+/// Create the evaluator chosen with [`eval_arg`], exiting with an error if
+/// it can't be loaded
+pub fn evaluator(matches: &clap::ArgMatches) -> BoxedEvaluator {
+    let key = matches.value_of("eval").unwrap();
+    eval::registry().create(key).unwrap_or_else(|error| {
+        clap::Error::with_description(&error.to_string(), ErrorKind::InvalidValue).exit()
+    })
+}
+
+/// Name the evaluator chosen with [`eval_arg`] in the options, when it was
+/// given, for engines that build their evaluators from the options. Exits
+/// with an error if it can't be loaded.
+pub fn set_eval_file(matches: &ArgMatches, options: &mut EngineOptions) {
+    if matches.occurrences_of("eval") > 0 {
+        let key = matches.value_of("eval").unwrap();
+        if let Err(error) = options.set("EvalFile", key) {
+            clap::Error::with_description(&error.to_string(), ErrorKind::InvalidValue).exit();
+        }
+    }
+}
+
+/// The engine options of the [`config_arg`] file with the features of
+/// [`disable_arg`] switched off and the values of [`set_arg`], exiting with
+/// an error if the file can't be loaded
+pub fn engine_options(matches: &ArgMatches) -> EngineOptions {
+    let mut options = EngineOptions::default();
+    if let Some(path) = matches.value_of("config") {
+        let loaded = fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|config| {
+                options
+                    .load_config(&config)
+                    .map_err(|error| error.to_string())
+            });
+        if let Err(error) = loaded {
+            let message = format!("Failed to load '{}': {}", path, error);
+            clap::Error::with_description(&message, ErrorKind::InvalidValue).exit();
+        }
+    }
+    for feature in matches.values_of("disable").into_iter().flatten() {
+        match feature {
+            "null-move" => options.search.null_move = false,
+            "check-extensions" => options.search.check_extensions = false,
+            "killer-moves" => options.search.killer_moves = false,
+            "tablebases" => options.search.tablebases = false,
+            _ => unreachable!(),
+        }
+    }
+    for assignment in matches.values_of("set").into_iter().flatten() {
+        // the assignments were checked by the validator
+        options.set_path(assignment).unwrap();
+    }
+    options
+}
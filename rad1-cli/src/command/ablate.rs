@@ -0,0 +1,89 @@
+use clap::{App, Arg, ArgMatches};
+use rad1::eval::naive::EvaluationConfig;
+use rad1::tuning;
+use std::fs;
+use std::process;
+
+pub fn ablate_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Measure what each evaluation term is worth by playing against the evaluation without it")
+        .arg(
+            Arg::with_name("weights")
+                .long("weights")
+                .short("w")
+                .required(false)
+                .takes_value(true)
+                .help("A weight file written by tune --genetic [default: the built-in weights]"),
+        )
+        .arg(
+            Arg::with_name("games")
+                .long("games")
+                .short("g")
+                .required(false)
+                .takes_value(true)
+                .default_value("8")
+                .validator(|value| match value.parse::<usize>() {
+                    Ok(n) if n > 0 => Ok(()),
+                    _ => Err(format!("'{}' isn't a positive number", value)),
+                })
+                .help("How many openings are played with both colors for every term"),
+        )
+        .arg(
+            Arg::with_name("depth")
+                .long("depth")
+                .short("d")
+                .required(false)
+                .takes_value(true)
+                .default_value("3")
+                .possible_values(&["1", "2", "3", "4", "5", "6"])
+                .hide_possible_values(true)
+                .help("The depth every move of the games is searched to"),
+        )
+}
+
+pub fn exec(matches: &ArgMatches) {
+    let games: usize = matches.value_of("games").unwrap().parse().unwrap();
+    let depth: u8 = matches.value_of("depth").unwrap().parse().unwrap();
+    let config = match matches.value_of("weights") {
+        Some(path) => fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| {
+                text.parse::<EvaluationConfig>()
+                    .map_err(|error| error.to_string())
+            })
+            .unwrap_or_else(|error| {
+                eprintln!("Failed to read '{}': {}", path, error);
+                process::exit(1);
+            }),
+        None => EvaluationConfig::DEFAULT,
+    };
+
+    let weight = |term: &str| {
+        let index = EvaluationConfig::NAMES.iter().position(|&n| n == term);
+        config.weights()[index.unwrap()]
+    };
+    let ranked = tuning::ablate(config, depth, games, |ablation| {
+        println!(
+            "{:<20} weight {:>4}  score {:.2}",
+            ablation.term,
+            weight(ablation.term),
+            ablation.score
+        );
+    });
+    println!();
+    println!(
+        "Terms ranked by what the evaluation loses without them, {} games each",
+        2 * games
+    );
+    for (rank, ablation) in ranked.iter().enumerate() {
+        println!(
+            "{:>2}. {:<20} score {:.2}  {:>+5.0} Elo",
+            rank + 1,
+            ablation.term,
+            ablation.score,
+            ablation.elo(2 * games)
+        );
+    }
+}
@@ -1,10 +1,33 @@
 use clap::{App, Arg, ArgMatches};
 use rad1::agent;
-use rad1::agent::ChessAgent;
-use rad1::tt::TranspositionTable;
-use rad1::Action;
+use rad1::agent::AlphaBetaChessAgent;
+use rad1::analysis::{AnalysisLine, InfiniteAnalysis};
+use rad1::analysis_cache::AnalysisCache;
+use rad1::arrows;
+use rad1::fmt;
+use rad1::notation::Notation;
+use rad1::score::Score;
+use rad1::search::pns::{Proof, ProofNumberSearch};
+use rad1::search::progress::{ProgressSnapshot, SearchProgress};
+use rad1::search::SearchResult;
+use rad1::winprob::WinModel;
 use rad1::ChessGame;
+use rad1::ChessMove;
+use rad1::Position;
+use rad1::PositionStatus;
+use std::cmp::Reverse;
+use std::fs;
+use std::io::{self, Write};
+use std::process;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How many root moves the live panel shows, the ones with most nodes
+const LIVE_ROOT_MOVES: usize = 8;
+const LIVE_BAR_WIDTH: usize = 30;
 
 pub fn analyze_app(command_name: &str) -> App<'static, 'static> {
     App::new(command_name)
@@ -26,29 +49,547 @@ pub fn analyze_app(command_name: &str) -> App<'static, 'static> {
             Arg::with_name("fen")
                 .long("fen")
                 .short("f")
-                .required(true)
+                .required(false)
                 .takes_value(true)
+                .default_value(super::START_POSITION)
+                .hide_default_value(true)
+                .validator(super::is_fen)
                 .help("The Forsyth-Edwards Notation (FEN) of the position to be analyzed"),
         )
+        .arg(super::moves_arg())
+        .arg(super::eval_arg())
+        .arg(super::disable_arg())
+        .arg(super::config_arg())
+        .arg(super::set_arg())
+        .arg(
+            Arg::with_name("only-moves")
+                .long("only-moves")
+                .required(false)
+                .takes_value(true)
+                .multiple(true)
+                .use_delimiter(true)
+                .validator(super::is_move)
+                .help("Only consider these moves, e.g. --only-moves e2e4,d2d4"),
+        )
+        .arg(
+            Arg::with_name("exclude-moves")
+                .long("exclude-moves")
+                .required(false)
+                .takes_value(true)
+                .multiple(true)
+                .use_delimiter(true)
+                .validator(super::is_move)
+                .help("Never consider these moves to find the best alternative, e.g. --exclude-moves e2e4"),
+        )
+        .arg(
+            Arg::with_name("uci-info")
+                .long("uci-info")
+                .required(false)
+                .takes_value(false)
+                .help(
+                    "Print a UCI info line after every iteration and finish with a bestmove line",
+                ),
+        )
+        .arg(
+            Arg::with_name("mate")
+                .long("mate")
+                .required(false)
+                .takes_value(true)
+                .conflicts_with_all(&["uci-info", "only-moves", "exclude-moves"])
+                .validator(|value| match value.parse::<u8>() {
+                    Ok(moves) if moves > 0 => Ok(()),
+                    _ => Err(format!("'{}' is not a number of moves from 1 to 255", value)),
+                })
+                .help("Look for a forced mate in at most this many moves with a proof-number search instead"),
+        )
+        .arg(
+            Arg::with_name("arrows")
+                .long("arrows")
+                .required(false)
+                .takes_value(true)
+                .conflicts_with_all(&["uci-info", "mate", "exclude-moves"])
+                .validator(|value| match value.parse::<usize>() {
+                    Ok(count) if count > 0 => Ok(()),
+                    _ => Err(format!("'{}' isn't a positive number", value)),
+                })
+                .help("Print this many top moves with their scores as JSON arrows for board GUIs"),
+        )
+        .arg(
+            Arg::with_name("root-moves")
+                .long("root-moves")
+                .required(false)
+                .takes_value(false)
+                .conflicts_with_all(&["uci-info", "mate", "arrows"])
+                .help("Print the nodes and time spent on every root move, most nodes first"),
+        )
+        .arg(
+            Arg::with_name("trace")
+                .long("trace")
+                .required(false)
+                .takes_value(true)
+                .value_name("MOVES")
+                .conflicts_with_all(&[
+                    "uci-info",
+                    "mate",
+                    "arrows",
+                    "root-moves",
+                    "only-moves",
+                    "exclude-moves",
+                ])
+                .help("Force the search down these moves, e.g. --trace \"e2e4 e7e5\", and print the window, evaluation, table hits and pruning at every node of the line"),
+        )
+        .arg(
+            Arg::with_name("live")
+                .long("live")
+                .required(false)
+                .takes_value(false)
+                .conflicts_with_all(&["uci-info", "mate", "arrows", "trace"])
+                .help("Show the depth, the root move being searched, the nodes of every root move and the best line on the terminal while searching"),
+        )
+        .arg(
+            Arg::with_name("wdl")
+                .long("wdl")
+                .required(false)
+                .takes_value(false)
+                .conflicts_with_all(&["uci-info", "mate", "arrows", "trace", "infinite"])
+                .help("Show the chances of winning, drawing and losing the score gives the side to move"),
+        )
+        .arg(super::win_model_arg())
+        .arg(
+            Arg::with_name("infinite")
+                .long("infinite")
+                .required(false)
+                .takes_value(false)
+                .conflicts_with_all(&[
+                    "depth",
+                    "uci-info",
+                    "mate",
+                    "arrows",
+                    "root-moves",
+                    "trace",
+                    "live",
+                    "only-moves",
+                    "exclude-moves",
+                ])
+                .help("Analyze deeper and deeper until Ctrl-C, printing the best moves of every iteration. Once the best move settles a quarter of the threads keeps searching the alternatives deeper"),
+        )
+        .arg(
+            Arg::with_name("lines")
+                .long("lines")
+                .required(false)
+                .takes_value(true)
+                .requires("infinite")
+                .validator(|value| match value.parse::<usize>() {
+                    Ok(count) if count > 0 => Ok(()),
+                    _ => Err(format!("'{}' isn't a positive number", value)),
+                })
+                .help("How many of the best moves --infinite keeps, 3 by default"),
+        )
+        .arg(super::notation_arg())
+        .arg(
+            Arg::with_name("cache")
+                .long("cache")
+                .required(false)
+                .takes_value(true)
+                .value_name("FILE")
+                .conflicts_with_all(&[
+                    "only-moves",
+                    "exclude-moves",
+                    "uci-info",
+                    "mate",
+                    "arrows",
+                    "root-moves",
+                    "trace",
+                    "live",
+                    "infinite",
+                ])
+                .help("Answer from this file of earlier analyses when it has the position searched at least as deep, and add the new ones to it. Keep a file for every evaluator and set of options"),
+        )
+}
+
+fn moves(matches: &ArgMatches, name: &str) -> Vec<ChessMove> {
+    matches
+        .values_of(name)
+        .map(|values| {
+            values
+                .map(|value| ChessMove::from_str(value).unwrap())
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
 pub fn exec(matches: &ArgMatches) {
-    let fen = matches.value_of("fen").unwrap();
-    let game = ChessGame::from_str(fen).expect("Failed to parse FEN");
+    let game = super::game(matches, "fen");
+    let notation = super::notation(matches);
+    let position = game.current_position();
+    if !position.has_legal_moves() {
+        let reason = match position.status() {
+            PositionStatus::Stalemate => "stalemate",
+            _ => "checkmate",
+        };
+        println!("Game over, {}", reason);
+        return;
+    }
+    if let Some(moves) = matches.value_of("mate") {
+        search_mate(&game.current_position(), moves.parse().unwrap(), notation);
+        return;
+    }
+    if matches.is_present("infinite") {
+        analyze_infinite(&game, matches, notation);
+        return;
+    }
     let depth: u8 = matches.value_of("depth").unwrap().parse().unwrap();
-    analyze_position(&game, depth);
+    let interrupted = super::interrupt_flag();
+    let mut agent = agent::alpha_beta_agent(depth, super::transposition_table());
+    agent.set_options(super::engine_options(matches));
+    let mut agent = agent
+        .with_stop_flag(interrupted.clone())
+        .with_evaluator(super::evaluator(matches))
+        .with_search_moves(moves(matches, "only-moves"))
+        .with_excluded_moves(moves(matches, "exclude-moves"));
+    if let Some(count) = matches.value_of("arrows") {
+        let arrows =
+            arrows::top_moves(&mut agent, &game.current_position(), count.parse().unwrap());
+        arrows::write_json(&mut io::stdout(), &arrows).expect("Failed to write the arrows");
+    } else if let Some(line) = matches.value_of("trace") {
+        trace_line(&game.current_position(), &agent, line, notation);
+    } else if matches.is_present("uci-info") {
+        analyze_with_uci_info(&game, &agent);
+    } else if matches.is_present("live") {
+        let progress = Arc::new(SearchProgress::default());
+        let agent = agent.with_progress(progress.clone());
+        let panel = LivePanel::start(progress, game.current_position(), notation);
+        let result = agent.search_game(&game);
+        panel.stop();
+        print_result(&game.current_position(), &result, wdl(matches), notation);
+        if matches.is_present("root-moves") {
+            print_root_moves(&result);
+        }
+    } else {
+        match matches.value_of("cache") {
+            Some(path) => analyze_cached(&game, &agent, depth, path, wdl(matches), notation),
+            None => analyze_position(
+                &game,
+                &agent,
+                matches.is_present("root-moves"),
+                wdl(matches),
+                notation,
+            ),
+        }
+    }
+    if interrupted.load(Ordering::SeqCst) {
+        eprintln!("Interrupted, the move above is the best one found so far");
+        process::exit(super::INTERRUPTED_EXIT_CODE);
+    }
 }
 
-fn analyze_position(game: &ChessGame, depth: u8) {
-    let agent = agent::alpha_beta_agent(depth, TranspositionTable::default());
+fn analyze_with_uci_info(game: &ChessGame, agent: &AlphaBetaChessAgent) {
+    let position = game.current_position();
+    println!(
+        "info string phase {} {:.2}",
+        position.game_phase(),
+        position.phase()
+    );
+    let result = agent.search_with_info(&position, |info| println!("{}", info.uci_info()));
+    let best_moves: Vec<String> = result.best_moves.iter().map(|m| m.to_string()).collect();
     println!(
-        "{}",
-        match agent.get_action(game) {
-            Action::MakeMove(chess_move) => chess_move.to_string(),
-            Action::OfferDraw(_) => String::from("Offer Draw"),
-            Action::AcceptDraw => String::from("Accept Draw"),
-            Action::DeclareDraw => String::from("Declare Draw"),
-            Action::Resign(_) => String::from("Resign"),
+        "info string best moves by depth {} changes {} explosions {}",
+        best_moves.join(" "),
+        result.best_move_changes(),
+        result.stats.explosions
+    );
+    println!("bestmove {}", result.best_move);
+}
+
+// The win probability model if the chances are shown
+fn wdl(matches: &ArgMatches) -> Option<WinModel> {
+    if matches.is_present("wdl") {
+        Some(super::win_model(matches))
+    } else {
+        None
+    }
+}
+
+fn analyze_position(
+    game: &ChessGame,
+    agent: &AlphaBetaChessAgent,
+    root_moves: bool,
+    wdl: Option<WinModel>,
+    notation: Notation,
+) {
+    let result = agent.search_game(game);
+    print_result(&game.current_position(), &result, wdl, notation);
+    if root_moves {
+        print_root_moves(&result);
+    }
+}
+
+// Print the analysis of the cache file if it's deep enough, or search and
+// add the result to the file
+fn analyze_cached(
+    game: &ChessGame,
+    agent: &AlphaBetaChessAgent,
+    depth: u8,
+    path: &str,
+    wdl: Option<WinModel>,
+    notation: Notation,
+) {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(error) => fail(format!("Failed to read '{}': {}", path, error)),
+    };
+    let mut cache = AnalysisCache::from_text(&text)
+        .unwrap_or_else(|error| fail(format!("Failed to read '{}': {}", path, error)));
+    let position = game.current_position();
+    let hash = position.get_hash();
+    if let Some(result) = cache.result(hash, depth) {
+        print_result(&position, &result, wdl, notation);
+        return;
+    }
+    let result = agent.search_game(game);
+    print_result(&position, &result, wdl, notation);
+    if cache.insert(hash, &result) {
+        if let Err(error) = fs::write(path, cache.to_text()) {
+            fail(format!("Failed to write '{}': {}", path, error));
+        }
+    }
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn print_result(
+    position: &Position,
+    result: &SearchResult,
+    wdl: Option<WinModel>,
+    notation: Notation,
+) {
+    let chances = wdl.map_or(String::new(), |model| {
+        format!("  ({})", model.position_wdl(position, result.score))
+    });
+    println!(
+        "{}  {}{}  {}{}",
+        result.best_move,
+        fmt::score(result.score),
+        chances,
+        fmt::pv_in(position, &result.pv, notation),
+        if result.is_unstable() {
+            "  (unstable)"
+        } else {
+            ""
         }
     );
 }
+
+// Redraws the progress of a search in place on stderr until stopped, the
+// panel is erased then so only the result stays on the terminal
+struct LivePanel {
+    done: Arc<AtomicBool>,
+    drawer: thread::JoinHandle<()>,
+}
+
+impl LivePanel {
+    fn start(progress: Arc<SearchProgress>, position: Position, notation: Notation) -> Self {
+        let done = Arc::new(AtomicBool::new(false));
+        let drawer = {
+            let done = done.clone();
+            thread::spawn(move || {
+                let start = Instant::now();
+                let mut drawn = 0;
+                while !done.load(Ordering::SeqCst) {
+                    let lines =
+                        live_lines(&position, &progress.snapshot(), start.elapsed(), notation);
+                    redraw(drawn, &lines);
+                    drawn = lines.len();
+                    thread::sleep(Duration::from_millis(150));
+                }
+                redraw(drawn, &[]);
+            })
+        };
+        Self { done, drawer }
+    }
+
+    fn stop(self) {
+        self.done.store(true, Ordering::SeqCst);
+        self.drawer.join().expect("The live panel failed");
+    }
+}
+
+// Move the cursor up over the lines drawn before, erase them and draw these
+fn redraw(drawn: usize, lines: &[String]) {
+    let mut stderr = io::stderr();
+    if drawn > 0 {
+        let _ = write!(stderr, "\x1b[{}A", drawn);
+    }
+    let _ = write!(stderr, "\x1b[J");
+    for line in lines {
+        let _ = writeln!(stderr, "{}", line);
+    }
+    let _ = stderr.flush();
+}
+
+fn live_lines(
+    position: &Position,
+    snapshot: &ProgressSnapshot,
+    elapsed: Duration,
+    notation: Notation,
+) -> Vec<String> {
+    let nps = snapshot.nodes as f64 / elapsed.as_secs_f64().max(0.001);
+    let current = snapshot
+        .current_move
+        .map(|chess_move| chess_move.to_string())
+        .unwrap_or_else(|| "-".to_string());
+    let mut lines = vec![format!(
+        "depth {}  move {}/{} {}  nodes {}  nps {:.0}  time {:.1}s",
+        snapshot.depth,
+        snapshot.moves_started,
+        snapshot.move_count,
+        current,
+        snapshot.nodes,
+        nps,
+        elapsed.as_secs_f64()
+    )];
+    lines.push(match &snapshot.last_iteration {
+        Some(result) => format!(
+            "depth {}  {}  {}",
+            result.depth,
+            fmt::score(result.score),
+            fmt::pv_in(position, &result.pv, notation)
+        ),
+        None => "no completed iteration yet".to_string(),
+    });
+    let mut root_moves = snapshot.root_moves.clone();
+    root_moves.sort_by_key(|root_move| Reverse(root_move.nodes));
+    let total: u64 = root_moves.iter().map(|root_move| root_move.nodes).sum();
+    for root_move in root_moves.iter().take(LIVE_ROOT_MOVES) {
+        let share = root_move.nodes as f64 / total.max(1) as f64;
+        let bar = "#".repeat((share * LIVE_BAR_WIDTH as f64).round() as usize);
+        lines.push(format!(
+            "{:<6}{:<width$}{:>6.1}%{:>12}",
+            root_move.chess_move.to_string(),
+            bar,
+            100.0 * share,
+            root_move.nodes,
+            width = LIVE_BAR_WIDTH + 1
+        ));
+    }
+    lines
+}
+
+// Analyze until Ctrl-C, then print the deepest line of every rank again
+fn analyze_infinite(game: &ChessGame, matches: &ArgMatches, notation: Notation) {
+    let options = super::engine_options(matches);
+    let lines = matches.value_of("lines").unwrap_or("3").parse().unwrap();
+    let analysis = InfiniteAnalysis::new(lines, options.threads);
+    let position = game.current_position();
+    let agent = || {
+        let mut agent = agent::alpha_beta_agent(1, super::transposition_table());
+        agent.set_options(options.clone());
+        agent.with_evaluator(super::evaluator(matches))
+    };
+    let lines = {
+        let position = position.clone();
+        analysis.run(
+            &game.current_position(),
+            agent(),
+            agent(),
+            super::interrupt_flag(),
+            move |line| println!("{}", analysis_line(&position, line, notation)),
+        )
+    };
+    println!();
+    for line in &lines {
+        println!("{}", analysis_line(&position, line, notation));
+    }
+}
+
+fn analysis_line(position: &Position, line: &AnalysisLine, notation: Notation) -> String {
+    format!(
+        "depth {:>2}  {}. {}  {}  {}",
+        line.result.depth,
+        line.rank,
+        line.result.best_move,
+        fmt::score(line.result.score),
+        fmt::pv_in(position, &line.result.pv, notation)
+    )
+}
+
+// Search only down the line and print what happened at its nodes
+fn trace_line(position: &Position, agent: &AlphaBetaChessAgent, line: &str, notation: Notation) {
+    let mut after = position.clone();
+    let mut moves = Vec::new();
+    for text in line.split_whitespace() {
+        match ChessMove::from_str(text) {
+            Ok(chess_move) if after.legal(chess_move) => {
+                after = after.make_move_new(chess_move);
+                moves.push(chess_move);
+            }
+            _ => {
+                eprintln!(
+                    "'{}' is not a legal move after {}",
+                    text,
+                    fmt::pv_in(position, &moves, notation)
+                );
+                process::exit(1);
+            }
+        }
+    }
+    if moves.is_empty() {
+        eprintln!("The line to trace has no moves");
+        process::exit(1);
+    }
+    let result = agent.search_line(position, &moves, |event| println!("{}", event));
+    println!(
+        "\n{}  {}  {}",
+        result.best_move,
+        fmt::score(result.score),
+        fmt::pv_in(position, &result.pv, notation)
+    );
+}
+
+// The share of the nodes and time of the search every root move took, to
+// spot moves that take far more effort than they are worth
+fn print_root_moves(result: &SearchResult) {
+    let mut root_moves = result.root_moves.clone();
+    root_moves.sort_by_key(|root_move| Reverse(root_move.nodes));
+    let nodes: u64 = root_moves.iter().map(|root_move| root_move.nodes).sum();
+    let elapsed: Duration = root_moves.iter().map(|root_move| root_move.elapsed).sum();
+    let share = |part: f64, total: f64| {
+        if total > 0.0 {
+            100.0 * part / total
+        } else {
+            0.0
+        }
+    };
+    println!(
+        "\n{:<6}{:>12}{:>8}{:>10}{:>8}",
+        "move", "nodes", "%", "ms", "%"
+    );
+    for root_move in &root_moves {
+        println!(
+            "{:<6}{:>12}{:>7.1}%{:>10}{:>7.1}%",
+            root_move.chess_move.to_string(),
+            root_move.nodes,
+            share(root_move.nodes as f64, nodes as f64),
+            root_move.elapsed.as_millis(),
+            share(root_move.elapsed.as_secs_f64(), elapsed.as_secs_f64())
+        );
+    }
+}
+
+fn search_mate(position: &Position, max_moves: u8, notation: Notation) {
+    let result = ProofNumberSearch::new(max_moves).solve(position);
+    match result.proof {
+        Proof::Mate(line) => println!(
+            "{}  {}  {}",
+            line[0],
+            fmt::score(Score::Mate(line.len().min(u8::MAX as usize) as u8)),
+            fmt::pv_in(position, &line, notation)
+        ),
+        Proof::NoMate => println!("No mate in {} moves", max_moves),
+        Proof::Unknown => println!("No mate found in {} nodes", result.nodes),
+    }
+}
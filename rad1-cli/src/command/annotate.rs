@@ -0,0 +1,224 @@
+use clap::{App, Arg, ArgMatches};
+use rad1::agent;
+use rad1::binpack;
+use rad1::dataset::Sample;
+use rad1::evalgraph::{self, Point};
+use rad1::fmt;
+use rad1::game_database::GameDatabase;
+use rad1::winprob::{self, Wdl};
+use rad1::{Color, Position, PositionStatus};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::process;
+use std::str::FromStr;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+pub fn annotate_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Analyze every position of a FEN file")
+        .arg(
+            Arg::with_name("file")
+                .required(true)
+                .help("A file with a FEN per line, dataset lines with an outcome work too"),
+        )
+        .arg(
+            Arg::with_name("depth")
+                .long("depth")
+                .short("d")
+                .required(false)
+                .takes_value(true)
+                .default_value("6")
+                .possible_values(&["1", "2", "3", "4", "5", "6", "7", "8", "9", "10"])
+                .hide_possible_values(true)
+                .help("The depth every position is searched to"),
+        )
+        .arg(
+            Arg::with_name("linked")
+                .long("linked")
+                .required(false)
+                .takes_value(false)
+                .help("The positions come from one game in the order they were played, keep the transposition table between them so later positions reuse the work on earlier ones, and rate the accuracy of both sides"),
+        )
+        .arg(
+            Arg::with_name("graph")
+                .long("graph")
+                .required(false)
+                .takes_value(true)
+                .help("Write the move number, score and best move of every position to this file for plotting an evaluation graph"),
+        )
+        .arg(
+            Arg::with_name("graph-format")
+                .long("graph-format")
+                .required(false)
+                .takes_value(true)
+                .default_value("csv")
+                .possible_values(&["csv", "json"])
+                .help("The format of the evaluation graph file"),
+        )
+        .arg(
+            Arg::with_name("sparkline")
+                .long("sparkline")
+                .required(false)
+                .takes_value(false)
+                .help("Draw the evaluation of the game as a line of blocks, higher the better white stands"),
+        )
+        .arg(
+            Arg::with_name("games")
+                .long("games")
+                .required(false)
+                .takes_value(true)
+                .value_name("DATABASE")
+                .help("A game database built by index-games, count the games that reached every position"),
+        )
+        .arg(super::win_model_arg())
+        .arg(super::eval_arg())
+        .arg(super::disable_arg())
+        .arg(super::config_arg())
+        .arg(super::set_arg())
+        .arg(super::notation_arg())
+}
+
+pub fn exec(matches: &ArgMatches) {
+    let path = matches.value_of("file").unwrap();
+    let depth: u8 = matches.value_of("depth").unwrap().parse().unwrap();
+    let linked = matches.is_present("linked");
+    let notation = super::notation(matches);
+    let text = fs::read_to_string(path).unwrap_or_else(|error| {
+        eprintln!("Failed to read '{}': {}", path, error);
+        process::exit(1);
+    });
+    let mut positions = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let position = Position::from_str(line)
+            .ok()
+            .or_else(|| Sample::parse(line).map(|sample| sample.position));
+        match position {
+            Some(position) => positions.push((binpack::fen_ply(line), position)),
+            None => {
+                eprintln!("Invalid position on line {} of '{}'", number + 1, path);
+                process::exit(1);
+            }
+        }
+    }
+
+    let database = matches.value_of("games").map(|path| {
+        fs::read(path)
+            .map_err(|error| error.to_string())
+            .and_then(|bytes| GameDatabase::from_bytes(&bytes).map_err(|error| error.to_string()))
+            .unwrap_or_else(|error| {
+                eprintln!("Failed to read '{}': {}", path, error);
+                process::exit(1);
+            })
+    });
+
+    let interrupted = super::interrupt_flag();
+    let mut agent = agent::alpha_beta_agent(depth, super::transposition_table());
+    agent.set_options(super::engine_options(matches));
+    let agent = agent
+        .with_stop_flag(interrupted.clone())
+        .with_evaluator(super::evaluator(matches));
+    let model = super::win_model(matches);
+    let start = Instant::now();
+    let mut nodes = 0;
+    let mut points = Vec::new();
+    // the chances of every position with the ply it was reached on, for the accuracy
+    let mut chances: Vec<(u32, Color, Wdl)> = Vec::new();
+    for (index, (ply, position)) in positions.iter().enumerate() {
+        if position.status() != PositionStatus::Ongoing {
+            println!("{:>4}  game over", index + 1);
+            continue;
+        }
+        // independent positions start from an empty table so their order doesn't matter
+        if !linked {
+            agent.clear_hash();
+        }
+        let result = agent.search(position);
+        if interrupted.load(Ordering::SeqCst) {
+            eprintln!("Interrupted");
+            process::exit(super::INTERRUPTED_EXIT_CODE);
+        }
+        nodes += result.nodes;
+        chances.push((
+            *ply,
+            position.side_to_move(),
+            model.position_wdl(position, result.score),
+        ));
+        points.push(Point::new(
+            *ply,
+            position.side_to_move(),
+            result.score,
+            result.best_move,
+        ));
+        let games = database.as_ref().map_or(String::new(), |database| {
+            match database.games_reaching(position).len() {
+                1 => String::from("  (1 game)"),
+                games => format!("  ({} games)", games),
+            }
+        });
+        println!(
+            "{:>4}  {}  {}  {}{}",
+            index + 1,
+            result.best_move,
+            fmt::score(result.score),
+            fmt::pv_in(position, &result.pv, notation),
+            games
+        );
+    }
+    eprintln!(
+        "Searched {} positions{} with {} nodes in {:.1}s",
+        positions.len(),
+        if linked { " sharing the table" } else { "" },
+        nodes,
+        start.elapsed().as_secs_f64()
+    );
+    if linked {
+        print_accuracy(&chances);
+    }
+    if matches.is_present("sparkline") {
+        println!("{}", evalgraph::sparkline(&points));
+    }
+    if let Some(graph) = matches.value_of("graph") {
+        let written = File::create(graph).and_then(|file| {
+            let mut out = BufWriter::new(file);
+            match matches.value_of("graph-format").unwrap() {
+                "json" => evalgraph::write_json(&mut out, &points),
+                _ => evalgraph::write_csv(&mut out, &points),
+            }
+        });
+        if let Err(error) = written {
+            eprintln!("Failed to write '{}': {}", graph, error);
+            process::exit(1);
+        }
+    }
+}
+
+// How much of their expected score both sides kept with their moves on
+// average, positions that don't follow each other are skipped
+fn print_accuracy(chances: &[(u32, Color, Wdl)]) {
+    let mut accuracy = [Vec::new(), Vec::new()];
+    for pair in chances.windows(2) {
+        let ((ply, side, before), (next_ply, _, after)) = (pair[0], pair[1]);
+        if next_ply == ply + 1 {
+            accuracy[side.to_index()].push(winprob::move_accuracy(before, after));
+        }
+    }
+    let average = |moves: &[f64]| {
+        if moves.is_empty() {
+            String::from("-")
+        } else {
+            format!("{:.1}%", moves.iter().sum::<f64>() / moves.len() as f64)
+        }
+    };
+    println!(
+        "Accuracy  white {}  black {}",
+        average(&accuracy[Color::White.to_index()]),
+        average(&accuracy[Color::Black.to_index()])
+    );
+}
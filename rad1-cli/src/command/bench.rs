@@ -0,0 +1,156 @@
+use super::bench_eval::BENCH_POSITIONS;
+use clap::{App, Arg, ArgMatches};
+use rad1::agent;
+use rad1::baseline::{Baseline, Record};
+use rad1::tt::TranspositionTable;
+use rad1::Position;
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::str::FromStr;
+use std::time::Duration;
+
+const TT_SIZE: usize = 1_000_000;
+
+pub fn bench_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Search the bench positions and compare the node counts and moves with a baseline")
+        .arg(super::eval_arg())
+        .arg(super::config_arg())
+        .arg(
+            Arg::with_name("depth")
+                .long("depth")
+                .short("d")
+                .required(false)
+                .takes_value(true)
+                .default_value("5")
+                .possible_values(&["1", "2", "3", "4", "5", "6", "7", "8", "9", "10"])
+                .hide_possible_values(true)
+                .help("The depth each bench position is searched to"),
+        )
+        .arg(
+            Arg::with_name("baseline")
+                .long("baseline")
+                .required(false)
+                .takes_value(true)
+                .help("JSON file of an earlier run, written if it doesn't exist and compared with otherwise"),
+        )
+        .arg(
+            Arg::with_name("update")
+                .long("update")
+                .required(false)
+                .takes_value(false)
+                .requires("baseline")
+                .help("Overwrite the baseline with this run instead of comparing with it"),
+        )
+        .arg(
+            Arg::with_name("tolerance")
+                .long("tolerance")
+                .required(false)
+                .takes_value(true)
+                .default_value("20")
+                .validator(|value| match value.parse::<f64>() {
+                    Ok(percent) if percent >= 0.0 => Ok(()),
+                    _ => Err(format!("'{}' isn't a percentage", value)),
+                })
+                .help("How many percent more nodes than the baseline are allowed"),
+        )
+}
+
+pub fn exec(matches: &ArgMatches) {
+    let depth: u8 = matches.value_of("depth").unwrap().parse().unwrap();
+    let evaluator = matches.value_of("eval").unwrap();
+    let tolerance: f64 = matches.value_of("tolerance").unwrap().parse().unwrap();
+    let current = run(matches, evaluator, depth);
+
+    let path = match matches.value_of("baseline") {
+        Some(path) => Path::new(path),
+        None => return,
+    };
+    if matches.is_present("update") || !path.exists() {
+        if let Err(error) = fs::write(path, current.to_json()) {
+            eprintln!("Failed to write '{}': {}", path.display(), error);
+            process::exit(1);
+        }
+        println!("Wrote the baseline to {}", path.display());
+        return;
+    }
+
+    let baseline = fs::read_to_string(path)
+        .map_err(|error| error.to_string())
+        .and_then(|json| Baseline::from_json(&json).map_err(|error| error.to_string()))
+        .unwrap_or_else(|error| {
+            eprintln!("Failed to read '{}': {}", path.display(), error);
+            process::exit(1);
+        });
+    if baseline.evaluator != current.evaluator || baseline.depth != current.depth {
+        eprintln!(
+            "The baseline searched with {} to depth {}, pass --eval {} --depth {} or --update",
+            baseline.evaluator, baseline.depth, baseline.evaluator, baseline.depth
+        );
+        process::exit(1);
+    }
+    let before: u64 = baseline.records.iter().map(|record| record.nodes).sum();
+    let after: u64 = current.records.iter().map(|record| record.nodes).sum();
+    println!("\nbaseline nodes {}, now {}", before, after);
+    let regressions = baseline.regressions(&current, 1.0 + tolerance / 100.0);
+    if regressions.is_empty() {
+        println!("No regressions");
+    } else {
+        for regression in &regressions {
+            println!("REGRESSION {}", regression);
+        }
+        process::exit(1);
+    }
+}
+
+// Search every bench position, printing what was found. The searches are
+// deterministic unless the config asks for more than one thread, whose
+// node counts and moves vary from run to run.
+fn run(matches: &ArgMatches, evaluator: &str, depth: u8) -> Baseline {
+    let mut options = super::engine_options(matches);
+    options.deterministic = options.threads == 1;
+    println!(
+        "{:>3}  {:<6}{:>12}{:>12}  fen",
+        "#", "move", "score", "nodes"
+    );
+    let mut records = Vec::new();
+    let mut elapsed = Duration::default();
+    for (index, fen) in BENCH_POSITIONS.iter().enumerate() {
+        let position = Position::from_str(fen).unwrap();
+        let mut agent = agent::alpha_beta_agent(depth, TranspositionTable::new(TT_SIZE))
+            .with_evaluator(super::evaluator(matches));
+        agent.set_options(options.clone());
+        let result = agent.search(&position);
+        elapsed += result.elapsed;
+        let record = Record {
+            fen: fen.to_string(),
+            best_move: result.best_move.to_string(),
+            score: result.score.to_uci(),
+            nodes: result.nodes,
+        };
+        println!(
+            "{:>3}  {:<6}{:>12}{:>12}  {}",
+            index + 1,
+            record.best_move,
+            record.score,
+            record.nodes,
+            record.fen
+        );
+        records.push(record);
+    }
+    let nodes: u64 = records.iter().map(|record| record.nodes).sum();
+    println!(
+        "total nodes {}, time {} ms, nps {}",
+        nodes,
+        elapsed.as_millis(),
+        nodes as u128 * 1_000_000 / elapsed.as_micros().max(1)
+    );
+    Baseline {
+        evaluator: evaluator.to_string(),
+        depth,
+        records,
+    }
+}
@@ -0,0 +1,112 @@
+use clap::{App, Arg, ArgMatches};
+use rad1::agent;
+use rad1::eval;
+use rad1::eval::registry::Registry;
+use rad1::eval::Evaluator;
+use rad1::options::EngineOptions;
+use rad1::tt::TranspositionTable;
+use rad1::Position;
+use std::str::FromStr;
+use std::time::Instant;
+
+const TT_SIZE: usize = 1_000_000;
+
+// A mix of opening, middlegame, tactical and endgame positions
+pub const BENCH_POSITIONS: [&str; 8] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "r3k2r/1p3pp1/p1p4p/3pP3/1PP5/P2P1P2/2qnKQ1P/8 b kq - 7 28",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "8/8/4k3/8/2p5/8/B2K4/8 w - - 0 1",
+];
+
+pub fn bench_eval_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Measure the speed of each evaluator and of searching with it")
+        .arg(
+            Arg::with_name("depth")
+                .long("depth")
+                .short("d")
+                .required(false)
+                .takes_value(true)
+                .default_value("5")
+                .possible_values(&["1", "2", "3", "4", "5", "6", "7", "8", "9", "10"])
+                .hide_possible_values(true)
+                .help("The depth each benchmark position is searched to"),
+        )
+        .arg(
+            Arg::with_name("iterations")
+                .long("iterations")
+                .short("n")
+                .required(false)
+                .takes_value(true)
+                .default_value("100000")
+                .help("How many times each benchmark position is evaluated"),
+        )
+}
+
+pub fn exec(matches: &ArgMatches) {
+    let depth: u8 = matches.value_of("depth").unwrap().parse().unwrap();
+    let iterations: u32 = matches
+        .value_of("iterations")
+        .unwrap()
+        .parse()
+        .expect("Failed to parse iterations");
+    let positions: Vec<Position> = BENCH_POSITIONS
+        .iter()
+        .map(|fen| Position::from_str(fen).unwrap())
+        .collect();
+
+    println!(
+        "{:<12}{:>16}{:>16}{:>16}",
+        "evaluator", "evals/s", "nodes", "nps"
+    );
+    let registry = eval::registry();
+    for name in registry.plain_names() {
+        let evaluator = registry.create(name).unwrap();
+        let eps = evaluations_per_second(evaluator.as_ref(), &positions, iterations);
+        let (nodes, nps) = search_speed(&registry, name, &positions, depth);
+        println!("{:<12}{:>16}{:>16}{:>16}", name, eps, nodes, nps);
+    }
+}
+
+fn evaluations_per_second(
+    evaluator: &dyn Evaluator<Result = i16>,
+    positions: &[Position],
+    iterations: u32,
+) -> u64 {
+    let start = Instant::now();
+    let mut checksum: i64 = 0;
+    for position in positions {
+        for _ in 0..iterations {
+            checksum += evaluator.evaluate(position) as i64;
+        }
+    }
+    let micros = start.elapsed().as_micros().max(1);
+    // keep the evaluations from being optimized away
+    assert!(checksum != i64::MIN);
+    (positions.len() as u128 * iterations as u128 * 1_000_000 / micros) as u64
+}
+
+fn search_speed(registry: &Registry, name: &str, positions: &[Position], depth: u8) -> (u64, u64) {
+    let mut nodes = 0;
+    let mut micros = 0;
+    for position in positions {
+        let mut agent = agent::alpha_beta_agent(depth, TranspositionTable::new(TT_SIZE))
+            .with_evaluator(registry.create(name).unwrap());
+        // so the node counts can be compared between runs
+        agent.set_options(EngineOptions {
+            deterministic: true,
+            ..EngineOptions::default()
+        });
+        let result = agent.search(position);
+        nodes += result.nodes;
+        micros += result.elapsed.as_micros();
+    }
+    (nodes, (nodes as u128 * 1_000_000 / micros.max(1)) as u64)
+}
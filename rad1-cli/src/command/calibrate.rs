@@ -0,0 +1,114 @@
+use clap::{App, Arg, ArgMatches};
+use rad1::agent;
+use rad1::binpack;
+use rad1::tuning::OPENINGS;
+use rad1::winprob::WinModel;
+use rad1::{GamePhase, Position};
+use std::fs;
+use std::process;
+use std::str::FromStr;
+
+pub fn calibrate_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Fit the win probability model to the results of self-play games, for --win-model")
+        .arg(
+            Arg::with_name("games")
+                .long("games")
+                .short("g")
+                .required(false)
+                .takes_value(true)
+                .default_value("64")
+                .validator(|value| match value.parse::<usize>() {
+                    Ok(games) if games > 0 => Ok(()),
+                    _ => Err(format!("'{}' isn't a number of games", value)),
+                })
+                .help("How many self-play games to play, starting from a set of openings in turn with two more moves that differ from round to round"),
+        )
+        .arg(
+            Arg::with_name("depth")
+                .long("depth")
+                .short("d")
+                .required(false)
+                .takes_value(true)
+                .default_value("4")
+                .possible_values(&["1", "2", "3", "4", "5", "6", "7", "8"])
+                .hide_possible_values(true)
+                .help("The depth every position is searched to"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .required(false)
+                .takes_value(true)
+                .help("The file the model is written to, it is printed otherwise"),
+        )
+        .arg(super::eval_arg())
+}
+
+pub fn exec(matches: &ArgMatches) {
+    let depth: u8 = matches.value_of("depth").unwrap().parse().unwrap();
+    let games: usize = matches.value_of("games").unwrap().parse().unwrap();
+    let agent = agent::alpha_beta_agent(depth, super::transposition_table())
+        .with_evaluator(super::evaluator(matches));
+    let mut entries = Vec::new();
+    for game in 0..games {
+        let played = binpack::self_play(&agent, &start(game)).unwrap();
+        eprintln!(
+            "game {} of {}: {} positions, {}",
+            game + 1,
+            games,
+            played.len(),
+            match played.first().map(|entry| entry.result) {
+                Some(0) | None => "draw",
+                Some(_) => "decisive",
+            }
+        );
+        entries.extend(played);
+    }
+    let model = WinModel::fit(&entries);
+    for &phase in &[
+        GamePhase::Opening,
+        GamePhase::Middlegame,
+        GamePhase::Endgame,
+    ] {
+        eprintln!(
+            "{:<12}{} at 0.00, 50% wins at {:+.2}, 90% at {:+.2}",
+            phase.to_string(),
+            model.wdl(phase, 0),
+            f64::from(model.centipawns_for(phase, 0.5)) / 100.0,
+            f64::from(model.centipawns_for(phase, 0.9)) / 100.0
+        );
+    }
+    match matches.value_of("output") {
+        Some(output) => {
+            if let Err(error) = fs::write(output, model.to_string()) {
+                eprintln!("Failed to write '{}': {}", output, error);
+                process::exit(1);
+            }
+        }
+        None => print!("{}", model),
+    }
+}
+
+// The games would repeat every round of openings, every round after the
+// first one plays two other moves from them, good or bad, before the game starts
+fn start(game: usize) -> String {
+    let mut position = Position::from_str(OPENINGS[game % OPENINGS.len()]).unwrap();
+    let round = game / OPENINGS.len();
+    if round > 0 {
+        let mut choice = round - 1;
+        for _ in 0..2 {
+            let moves = position.legal_moves();
+            if moves.is_empty() {
+                break;
+            }
+            position = position.make_move_new(moves[choice % moves.len()]);
+            choice /= moves.len();
+            choice += round;
+        }
+    }
+    position.to_string()
+}
@@ -0,0 +1,96 @@
+use clap::{App, Arg, ArgMatches, ErrorKind};
+use rad1::agent;
+use rad1::fmt;
+use rad1::search::blunder::MoveCheck;
+use rad1::ChessMove;
+use std::process;
+use std::str::FromStr;
+
+/// Exit code when the checked move is a blunder, so scripts can tell it from errors
+pub const BLUNDER_EXIT_CODE: i32 = 2;
+
+// Evaluation units in one pawn
+const UNITS_PER_PAWN: f64 = 10.0;
+
+pub fn check_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Check whether a move is a blunder compared to the engine's best move")
+        .arg(
+            Arg::with_name("move")
+                .required(true)
+                .validator(super::is_move)
+                .help("The move to check in UCI notation, e.g. e2e4"),
+        )
+        .arg(
+            Arg::with_name("fen")
+                .long("fen")
+                .short("f")
+                .required(false)
+                .takes_value(true)
+                .default_value(super::START_POSITION)
+                .hide_default_value(true)
+                .validator(super::is_fen)
+                .help("The Forsyth-Edwards Notation (FEN) of the position the move is played in"),
+        )
+        .arg(super::moves_arg())
+        .arg(super::eval_arg())
+        .arg(super::disable_arg())
+        .arg(super::config_arg())
+        .arg(super::set_arg())
+        .arg(
+            Arg::with_name("depth")
+                .long("depth")
+                .short("d")
+                .required(false)
+                .takes_value(true)
+                .default_value("6")
+                .possible_values(&["1", "2", "3", "4", "5", "6", "7", "8", "9", "10"])
+                .hide_possible_values(true)
+                .help("The depth the position is searched to"),
+        )
+        .arg(
+            Arg::with_name("threshold")
+                .long("threshold")
+                .short("t")
+                .required(false)
+                .takes_value(true)
+                .default_value("1.0")
+                .validator(|value| match value.parse::<f64>() {
+                    Ok(pawns) if pawns > 0.0 => Ok(()),
+                    _ => Err(format!("'{}' isn't a positive number of pawns", value)),
+                })
+                .help("How many pawns worse than the best move count as a blunder"),
+        )
+}
+
+pub fn exec(matches: &ArgMatches) {
+    let game = super::game(matches, "fen");
+    let value = matches.value_of("move").unwrap();
+    let chess_move = ChessMove::from_str(value).unwrap();
+    let position = game.current_position();
+    if !position.legal(chess_move) {
+        let message = format!("'{}' is not a legal move in {}", value, position);
+        clap::Error::with_description(&message, ErrorKind::InvalidValue).exit();
+    }
+    let depth: u8 = matches.value_of("depth").unwrap().parse().unwrap();
+    let threshold: f64 = matches.value_of("threshold").unwrap().parse().unwrap();
+
+    let mut agent = agent::alpha_beta_agent(depth, super::transposition_table());
+    agent.set_options(super::engine_options(matches));
+    let mut agent = agent.with_evaluator(super::evaluator(matches));
+    let check = MoveCheck::new(&mut agent, &game, chess_move);
+    let blunder = check.is_blunder((threshold * UNITS_PER_PAWN).round() as i32);
+    println!(
+        "{} {} {}  best {} {}",
+        if blunder { "blunder" } else { "ok" },
+        chess_move,
+        fmt::score(check.played),
+        check.best_move,
+        fmt::score(check.best)
+    );
+    if blunder {
+        process::exit(BLUNDER_EXIT_CODE);
+    }
+}
@@ -0,0 +1,122 @@
+use clap::{App, Arg, ArgMatches};
+use rad1::dataset::Sample;
+use rad1::eval;
+use rad1::eval::compare::Comparison;
+use rad1::Position;
+use std::fs;
+use std::process;
+use std::str::FromStr;
+
+pub fn compare_eval_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Compare two evaluators over the positions of a FEN file")
+        .arg(
+            Arg::with_name("file")
+                .required(true)
+                .help("A file with a FEN per line, dataset lines with an outcome work too"),
+        )
+        .arg(
+            Arg::with_name("baseline")
+                .long("baseline")
+                .short("b")
+                .required(false)
+                .takes_value(true)
+                .default_value("naive")
+                .validator(super::is_evaluator)
+                .help("The evaluator compared against"),
+        )
+        .arg(
+            Arg::with_name("candidate")
+                .long("candidate")
+                .short("c")
+                .required(true)
+                .takes_value(true)
+                .validator(super::is_evaluator)
+                .help("The evaluator under review, e.g. hybrid:<path>"),
+        )
+        .arg(
+            Arg::with_name("count")
+                .long("count")
+                .short("n")
+                .required(false)
+                .takes_value(true)
+                .default_value("5")
+                .validator(|value| {
+                    value
+                        .parse::<usize>()
+                        .map(|_| ())
+                        .map_err(|_| format!("'{}' isn't a number", value))
+                })
+                .help("How many of the largest disagreements are printed"),
+        )
+        .arg(super::notation_arg().default_value("figurine"))
+}
+
+pub fn exec(matches: &ArgMatches) {
+    let path = matches.value_of("file").unwrap();
+    let baseline_key = matches.value_of("baseline").unwrap();
+    let candidate_key = matches.value_of("candidate").unwrap();
+    let count: usize = matches.value_of("count").unwrap().parse().unwrap();
+    let text = fs::read_to_string(path).unwrap_or_else(|error| {
+        eprintln!("Failed to read '{}': {}", path, error);
+        process::exit(1);
+    });
+    let mut invalid = 0;
+    let positions: Vec<Position> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let position = Position::from_str(line)
+                .ok()
+                .or_else(|| Sample::parse(line).map(|sample| sample.position));
+            if position.is_none() {
+                invalid += 1;
+            }
+            position
+        })
+        .collect();
+
+    let create = |key| {
+        eval::registry().create(key).unwrap_or_else(|error| {
+            eprintln!("{}", error);
+            process::exit(1);
+        })
+    };
+    let (baseline, candidate) = (create(baseline_key), create(candidate_key));
+    let comparison = Comparison::new(baseline.as_ref(), candidate.as_ref(), &positions);
+    let scores = comparison.scores();
+    println!(
+        "{:<24}{:>10}  ({} checkmates or stalemates and {} invalid lines skipped)",
+        "positions",
+        scores.len(),
+        positions.len() - scores.len(),
+        invalid
+    );
+    match comparison.correlation() {
+        Some(correlation) => println!("{:<24}{:>10.3}", "correlation", correlation),
+        None => println!("{:<24}{:>10}", "correlation", "-"),
+    }
+    println!(
+        "{:<24}{:>10.2}",
+        "mean absolute difference",
+        comparison.mean_absolute_difference()
+    );
+
+    for (rank, scores) in comparison.largest_disagreements(count).iter().enumerate() {
+        let position = &positions[scores.index];
+        println!(
+            "\n#{}  {} {}  {} {}  difference {}",
+            rank + 1,
+            baseline_key,
+            scores.first,
+            candidate_key,
+            scores.second,
+            scores.difference()
+        );
+        super::play::print_board(position, false, super::notation(matches));
+        println!("{}", position);
+    }
+}
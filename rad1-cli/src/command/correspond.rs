@@ -0,0 +1,309 @@
+use clap::{App, AppSettings, Arg, ArgMatches};
+use rad1::agent;
+use rad1::correspondence::{Analysis, Correspondence};
+use rad1::fmt;
+use rad1::notation::Notation;
+use rad1::{ChessMove, Color};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::sync::atomic::Ordering;
+
+const NEW_COMMAND: &str = "new";
+const MOVE_COMMAND: &str = "move";
+const ANALYZE_COMMAND: &str = "analyze";
+const LIST_COMMAND: &str = "list";
+
+// Extensions of the files a game is kept in
+const GAME_EXTENSION: &str = "game";
+const HASH_EXTENSION: &str = "hash";
+
+pub fn correspond_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Keep correspondence games on disk and analyze them over several sessions")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::with_name("dir")
+                .long("dir")
+                .required(false)
+                .takes_value(true)
+                .default_value("correspondence")
+                .help("The directory the games are kept in"),
+        )
+        .arg(super::notation_arg())
+        .subcommand(
+            App::new(NEW_COMMAND)
+                .about("Start a new game")
+                .arg(name_arg())
+                .arg(
+                    Arg::with_name("opponent")
+                        .long("opponent")
+                        .short("o")
+                        .required(true)
+                        .takes_value(true)
+                        .help("The name of the opponent"),
+                )
+                .arg(
+                    Arg::with_name("color")
+                        .long("color")
+                        .short("c")
+                        .required(true)
+                        .takes_value(true)
+                        .possible_values(&["white", "black"])
+                        .help("The side you play"),
+                )
+                .arg(
+                    Arg::with_name("fen")
+                        .long("fen")
+                        .short("f")
+                        .required(false)
+                        .takes_value(true)
+                        .default_value(super::START_POSITION)
+                        .hide_default_value(true)
+                        .validator(super::is_fen)
+                        .help("The Forsyth-Edwards Notation (FEN) of the position the game starts from"),
+                )
+                .arg(super::moves_arg()),
+        )
+        .subcommand(
+            App::new(MOVE_COMMAND)
+                .about("Play the moves of either side, e.g. the one the opponent sent")
+                .arg(name_arg())
+                .arg(
+                    Arg::with_name("moves")
+                        .required(true)
+                        .multiple(true)
+                        .validator(super::is_move)
+                        .help("The moves in UCI notation, e.g. e7e5"),
+                ),
+        )
+        .subcommand(
+            App::new(ANALYZE_COMMAND)
+                .about("Analyze the current position, continuing from the saved transposition table. Stop with Ctrl-C at any time, the progress is saved")
+                .arg(name_arg())
+                .arg(
+                    Arg::with_name("depth")
+                        .long("depth")
+                        .short("d")
+                        .required(false)
+                        .takes_value(true)
+                        .default_value("12")
+                        .validator(|value| match value.parse::<u8>() {
+                            Ok(depth) if depth > 0 && depth <= 64 => Ok(()),
+                            _ => Err(format!("'{}' is not a depth from 1 to 64", value)),
+                        })
+                        .help("The depth the position is searched to"),
+                )
+                .arg(super::eval_arg())
+                .arg(super::disable_arg())
+                .arg(super::config_arg())
+                .arg(super::set_arg()),
+        )
+        .subcommand(App::new(LIST_COMMAND).about("Show every game and its analysis"))
+}
+
+fn name_arg() -> Arg<'static, 'static> {
+    Arg::with_name("name")
+        .required(true)
+        .validator(|name| {
+            if !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                Ok(())
+            } else {
+                Err(format!(
+                    "'{}' isn't a name of letters, digits, '-' and '_'",
+                    name
+                ))
+            }
+        })
+        .help("The name of the game")
+}
+
+pub fn exec(matches: &ArgMatches) {
+    let dir = Path::new(matches.value_of("dir").unwrap());
+    let notation = super::notation(matches);
+    match matches.subcommand() {
+        (NEW_COMMAND, Some(sub_matches)) => new_game(dir, sub_matches, notation),
+        (MOVE_COMMAND, Some(sub_matches)) => play_moves(dir, sub_matches, notation),
+        (ANALYZE_COMMAND, Some(sub_matches)) => analyze(dir, sub_matches, notation),
+        (LIST_COMMAND, Some(_)) => list(dir, notation),
+        _ => unreachable!(),
+    }
+}
+
+fn path(dir: &Path, name: &str, extension: &str) -> PathBuf {
+    dir.join(name).with_extension(extension)
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn load(dir: &Path, name: &str) -> Correspondence {
+    let path = path(dir, name, GAME_EXTENSION);
+    let text = fs::read_to_string(&path)
+        .unwrap_or_else(|error| fail(format!("Failed to read '{}': {}", path.display(), error)));
+    Correspondence::from_text(&text)
+        .unwrap_or_else(|error| fail(format!("Failed to read '{}': {}", path.display(), error)))
+}
+
+fn save(path: &Path, text: &str) {
+    if let Err(error) = fs::write(path, text) {
+        fail(format!("Failed to write '{}': {}", path.display(), error));
+    }
+}
+
+fn new_game(dir: &Path, matches: &ArgMatches, notation: Notation) {
+    let name = matches.value_of("name").unwrap();
+    let path = path(dir, name, GAME_EXTENSION);
+    if path.exists() {
+        fail(format!("A game called '{}' already exists", name));
+    }
+    let color = match matches.value_of("color").unwrap() {
+        "white" => Color::White,
+        _ => Color::Black,
+    };
+    let correspondence = Correspondence::new(
+        matches.value_of("opponent").unwrap(),
+        color,
+        super::game(matches, "fen"),
+    );
+    if let Err(error) = fs::create_dir_all(dir) {
+        fail(format!("Failed to create '{}': {}", dir.display(), error));
+    }
+    save(&path, &correspondence.to_text());
+    println!("{}", status(name, &correspondence, notation));
+}
+
+fn play_moves(dir: &Path, matches: &ArgMatches, notation: Notation) {
+    let name = matches.value_of("name").unwrap();
+    let mut correspondence = load(dir, name);
+    for value in matches.values_of("moves").unwrap() {
+        let chess_move = ChessMove::from_str(value).unwrap();
+        if let Err(error) = correspondence.play(chess_move) {
+            fail(format!(
+                "{} in {}",
+                error,
+                correspondence.game.current_position()
+            ));
+        }
+    }
+    save(&path(dir, name, GAME_EXTENSION), &correspondence.to_text());
+    println!("{}", status(name, &correspondence, notation));
+}
+
+fn analyze(dir: &Path, matches: &ArgMatches, notation: Notation) {
+    let name = matches.value_of("name").unwrap();
+    let mut correspondence = load(dir, name);
+    if correspondence.game.result().is_some() {
+        fail(format!("The game '{}' is over", name));
+    }
+    let depth: u8 = matches.value_of("depth").unwrap().parse().unwrap();
+    if let Some(analysis) = &correspondence.analysis {
+        if analysis.depth >= depth {
+            println!("Already analyzed to depth {}", analysis.depth);
+            println!("{}", status(name, &correspondence, notation));
+            return;
+        }
+    }
+
+    let interrupted = super::interrupt_flag();
+    let mut agent = agent::alpha_beta_agent(depth, super::transposition_table());
+    agent.set_options(super::engine_options(matches));
+    let agent = agent
+        .with_stop_flag(interrupted.clone())
+        .with_evaluator(super::evaluator(matches));
+    let hash_path = path(dir, name, HASH_EXTENSION);
+    if let Ok(snapshot) = fs::read(&hash_path) {
+        match agent.transposition_table().restore(&snapshot) {
+            Ok(entries) => println!("Restored {} positions", entries),
+            Err(error) => eprintln!("Ignoring '{}': {}", hash_path.display(), error),
+        }
+    }
+
+    // the game is saved after every iteration with the analysis so far
+    let earlier_nodes = correspondence.analysis.take().map_or(0, |a| a.nodes);
+    let game_path = path(dir, name, GAME_EXTENSION);
+    let header = correspondence.to_text();
+    let position = correspondence.game.current_position();
+    agent.search_game_with_info(&correspondence.game, |info| {
+        let analysis = Analysis {
+            depth: info.depth,
+            best_move: info.best_move,
+            score: info.score,
+            pv: info.pv.clone(),
+            nodes: earlier_nodes + info.nodes,
+        };
+        println!(
+            "depth {:>2}  {}  {}",
+            analysis.depth,
+            fmt::score(analysis.score),
+            fmt::pv_in(&position, &analysis.pv, notation)
+        );
+        save(&game_path, &(header.clone() + &analysis.to_text()));
+    });
+    if let Err(error) = fs::write(&hash_path, agent.transposition_table().snapshot()) {
+        fail(format!(
+            "Failed to write '{}': {}",
+            hash_path.display(),
+            error
+        ));
+    }
+    if interrupted.load(Ordering::SeqCst) {
+        eprintln!("Interrupted, the analysis so far is saved");
+        process::exit(super::INTERRUPTED_EXIT_CODE);
+    }
+}
+
+fn list(dir: &Path, notation: Notation) {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().map_or(false, |e| e == GAME_EXTENSION))
+                .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    if names.is_empty() {
+        println!("No games in '{}'", dir.display());
+    }
+    for name in names {
+        println!("{}", status(&name, &load(dir, &name), notation));
+    }
+}
+
+// A line about the game: the opponent, whose move it is and the analysis
+fn status(name: &str, correspondence: &Correspondence, notation: Notation) -> String {
+    let game = &correspondence.game;
+    let turn = match game.result() {
+        Some(result) => format!("{:?}", result),
+        None if correspondence.is_our_turn() => "your move".to_string(),
+        None => "their move".to_string(),
+    };
+    let analysis = match &correspondence.analysis {
+        Some(analysis) => format!(
+            "depth {}  {}  {}",
+            analysis.depth,
+            fmt::score(analysis.score),
+            fmt::pv_in(&game.current_position(), &analysis.pv, notation)
+        ),
+        None => "not analyzed".to_string(),
+    };
+    format!(
+        "{}  vs {}  {} plies  {}  {}",
+        name,
+        correspondence.opponent,
+        game.moves().len(),
+        turn,
+        analysis
+    )
+}
@@ -0,0 +1,62 @@
+use clap::{App, Arg, ArgMatches};
+use rad1::epd::{EpdError, EpdPosition};
+use std::fs;
+use std::process;
+use std::str::FromStr;
+
+pub fn epd_to_fen_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Print the positions of an EPD file as FENs, one per line, with the hmvc and fmvn move counters")
+        .arg(
+            Arg::with_name("file")
+                .required(true)
+                .help("An EPD file with a position per line"),
+        )
+}
+
+pub fn fen_to_epd_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Print the FENs of a file as EPD, one per line, keeping the move counters as hmvc and fmvn")
+        .arg(
+            Arg::with_name("file")
+                .required(true)
+                .help("A file with a FEN per line"),
+        )
+}
+
+pub fn exec_epd_to_fen(matches: &ArgMatches) {
+    convert(matches, |line| {
+        EpdPosition::from_str(line).map(|epd| epd.to_fen())
+    });
+}
+
+pub fn exec_fen_to_epd(matches: &ArgMatches) {
+    convert(matches, |line| {
+        EpdPosition::from_fen(line).map(|epd| epd.to_string())
+    });
+}
+
+// Print every line of the file converted, skipping empty lines and comments
+fn convert(matches: &ArgMatches, line_to_string: fn(&str) -> Result<String, EpdError>) {
+    let path = matches.value_of("file").unwrap();
+    let text = fs::read_to_string(path).unwrap_or_else(|error| {
+        eprintln!("Failed to read '{}': {}", path, error);
+        process::exit(1);
+    });
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line_to_string(line) {
+            Ok(converted) => println!("{}", converted),
+            Err(error) => {
+                eprintln!("Line {} of '{}': {}", index + 1, path, error);
+                process::exit(1);
+            }
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use clap::{App, Arg, ArgMatches};
+use rad1::dataset;
+use rad1::features;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process;
+
+pub fn export_features_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about(
+            "Write the evaluation terms of labeled positions as feature vectors for other ML tools",
+        )
+        .arg(
+            Arg::with_name("file")
+                .required(true)
+                .help("Dataset file with a FEN and the game's outcome like [0.5] or 1-0 per line"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .short("f")
+                .required(false)
+                .takes_value(true)
+                .default_value("csv")
+                .possible_values(&["csv", "npy"])
+                .help("CSV with a header line, or a NumPy array of 32 bit floats"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .required(false)
+                .takes_value(true)
+                .help("The file written [default: the dataset file with the format's extension]"),
+        )
+}
+
+pub fn exec(matches: &ArgMatches) {
+    let path = Path::new(matches.value_of("file").unwrap());
+    let format = matches.value_of("format").unwrap();
+    let output = matches
+        .value_of("output")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| path.with_extension(format));
+    let samples = dataset::load_samples(path).unwrap_or_else(|error| {
+        eprintln!("Failed to read '{}': {}", path.display(), error);
+        process::exit(1);
+    });
+    let rows = features::rows(&samples);
+
+    let written = File::create(&output).and_then(|file| {
+        let mut out = BufWriter::new(file);
+        match format {
+            "npy" => features::write_npy(&mut out, &rows),
+            _ => features::write_csv(&mut out, &rows),
+        }
+    });
+    if let Err(error) = written {
+        eprintln!("Failed to write '{}': {}", output.display(), error);
+        process::exit(1);
+    }
+    println!(
+        "Wrote {} positions with {} features and the outcome to {}",
+        rows.len(),
+        features::FEATURE_NAMES.len(),
+        output.display()
+    );
+}
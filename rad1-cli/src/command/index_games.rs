@@ -0,0 +1,49 @@
+use clap::{App, Arg, ArgMatches};
+use rad1::game_database::GameDatabaseBuilder;
+use std::fs;
+use std::process;
+
+pub fn index_games_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Build a game database indexed by position from PGN databases, for annotate --games")
+        .arg(
+            Arg::with_name("files")
+                .required(true)
+                .multiple(true)
+                .help("PGN databases, games with an illegal move are skipped"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .required(true)
+                .takes_value(true)
+                .help("The game database file written"),
+        )
+}
+
+pub fn exec(matches: &ArgMatches) {
+    let mut builder = GameDatabaseBuilder::new();
+    for path in matches.values_of("files").unwrap() {
+        let text = fs::read_to_string(path).unwrap_or_else(|error| {
+            eprintln!("Failed to read '{}': {}", path, error);
+            process::exit(1);
+        });
+        builder.add_pgn(&text);
+    }
+    let skipped = builder.skipped;
+    let database = builder.build();
+    let output = matches.value_of("output").unwrap();
+    if let Err(error) = fs::write(output, database.to_bytes()) {
+        eprintln!("Failed to write '{}': {}", output, error);
+        process::exit(1);
+    }
+    println!(
+        "Indexed {} games with {} positions, skipped {}",
+        database.len(),
+        database.positions(),
+        skipped
+    );
+}
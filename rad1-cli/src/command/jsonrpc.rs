@@ -0,0 +1,73 @@
+use clap::{App, Arg, ArgMatches};
+use rad1::agent;
+use rad1::jsonrpc::Server;
+use rad1::tt::TranspositionTable;
+use std::io::{self, BufRead};
+
+pub fn jsonrpc_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Run the engine as a JSON-RPC 2.0 server over stdin and stdout, one JSON message per line")
+        .arg(
+            Arg::with_name("depth")
+                .long("depth")
+                .short("d")
+                .required(false)
+                .takes_value(true)
+                .default_value("8")
+                .possible_values(&["1", "2", "3", "4", "5", "6", "7", "8", "9", "10"])
+                .hide_possible_values(true)
+                .help("The depth of analyze and play requests that don't give one"),
+        )
+        .arg(
+            Arg::with_name("max-games")
+                .long("max-games")
+                .required(false)
+                .takes_value(true)
+                .default_value("1")
+                .validator(|value| match value.parse::<usize>() {
+                    Ok(n) if n > 0 => Ok(()),
+                    _ => Err(format!("'{}' isn't a positive number", value)),
+                })
+                .help("The most games clients can keep open at once, each searched on its own thread with a transposition table of the Hash option's size"),
+        )
+        .arg(super::eval_arg())
+        .arg(super::disable_arg())
+        .arg(super::config_arg())
+        .arg(super::set_arg())
+}
+
+pub fn exec(matches: &ArgMatches) {
+    let depth: u8 = matches.value_of("depth").unwrap().parse().unwrap();
+    let mut options = super::engine_options(matches);
+    // loaded once here, so every game's agent can load it from the options
+    super::set_eval_file(matches, &mut options);
+    let max_games: usize = matches.value_of("max-games").unwrap().parse().unwrap();
+    let server = Server::new(
+        depth,
+        move || {
+            // a single game keeps the large default table
+            let table = if max_games > 1 {
+                TranspositionTable::with_memory(options.hash_mb, options.table_memory())
+            } else {
+                super::transposition_table()
+            };
+            let mut agent = agent::alpha_beta_agent(depth, table);
+            agent.set_options(options.clone());
+            agent
+        },
+        io::stdout(),
+    )
+    .with_max_games(max_games);
+    for line in io::stdin().lock().lines() {
+        match line {
+            Ok(line) => server.handle(&line),
+            Err(error) => {
+                eprintln!("Failed to read a request: {}", error);
+                break;
+            }
+        }
+    }
+    server.finish();
+}
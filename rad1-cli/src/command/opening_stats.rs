@@ -0,0 +1,157 @@
+use clap::{App, Arg, ArgMatches};
+use rad1::opening_tree::OpeningTree;
+use rad1::pgn;
+use rad1::{ChessMove, Position};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::process;
+use std::str::FromStr;
+
+pub fn opening_stats_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Count how often the opening moves of PGN databases were played and how they scored")
+        .arg(
+            Arg::with_name("files").required(true).multiple(true).help(
+                "PGN databases, games that don't start from the starting position are skipped",
+            ),
+        )
+        .arg(
+            Arg::with_name("plies")
+                .long("plies")
+                .short("p")
+                .required(false)
+                .takes_value(true)
+                .default_value("12")
+                .validator(is_count)
+                .help("How many plies of every game are counted"),
+        )
+        .arg(
+            Arg::with_name("min-games")
+                .long("min-games")
+                .required(false)
+                .takes_value(true)
+                .default_value("1")
+                .validator(is_count)
+                .help("Hide moves played in fewer games when walking the tree"),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .required(false)
+                .takes_value(false)
+                .help("Print the whole tree as JSON instead of walking it"),
+        )
+}
+
+fn is_count(value: String) -> Result<(), String> {
+    value
+        .parse::<usize>()
+        .map(|_| ())
+        .map_err(|_| format!("'{}' is not a positive number", value))
+}
+
+pub fn exec(matches: &ArgMatches) {
+    let plies = matches.value_of("plies").unwrap().parse().unwrap();
+    let mut tree = OpeningTree::new(plies);
+    for path in matches.values_of("files").unwrap() {
+        let text = fs::read_to_string(path).unwrap_or_else(|error| {
+            eprintln!("Failed to read '{}': {}", path, error);
+            process::exit(1);
+        });
+        tree.add_pgn(&text);
+    }
+    if matches.is_present("json") {
+        println!("{}", tree.to_json());
+        return;
+    }
+    println!("{} games, {} skipped", tree.games, tree.skipped);
+    walk(
+        &tree,
+        matches.value_of("min-games").unwrap().parse().unwrap(),
+    );
+}
+
+// Show the moves after the current line and follow the user's choice:
+// a number or a move in SAN or UCI notation goes deeper, `back` goes up a
+// move, `top` to the start and `quit` or the end of the input stops
+fn walk(tree: &OpeningTree, min_games: u32) {
+    let mut line: Vec<ChessMove> = Vec::new();
+    let stdin = io::stdin();
+    let mut input = stdin.lock().lines();
+    loop {
+        let position = position_after(&tree.start, &line);
+        let moves: Vec<_> = tree
+            .moves_after(&line)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|node| node.games >= min_games)
+            .collect();
+        println!(
+            "\n{}",
+            if line.is_empty() {
+                String::from("start")
+            } else {
+                pgn::movetext(&tree.start, &line)
+            }
+        );
+        let total: u32 = moves.iter().map(|node| node.games).sum();
+        println!(
+            "{:>4}  {:<8}{:>8}{:>8}{:>8}{:>8}{:>8}{:>8}",
+            "", "move", "games", "share", "white", "draws", "black", "score"
+        );
+        for (index, node) in moves.iter().enumerate() {
+            let score = node
+                .score(position.side_to_move())
+                .map_or(String::from("-"), |score| format!("{:.0}%", 100.0 * score));
+            println!(
+                "{:>4}  {:<8}{:>8}{:>7.0}%{:>8}{:>8}{:>8}{:>8}",
+                index + 1,
+                node.san,
+                node.games,
+                100.0 * f64::from(node.games) / f64::from(total.max(1)),
+                node.white_wins,
+                node.draws,
+                node.black_wins,
+                score
+            );
+        }
+        print!("move, number, back, top or quit> ");
+        io::stdout().flush().unwrap();
+        let command = match input.next() {
+            Some(Ok(command)) => command,
+            _ => break,
+        };
+        match command.trim() {
+            "quit" => break,
+            "back" => {
+                line.pop();
+            }
+            "top" => line.clear(),
+            command => {
+                let chosen = command
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|number| moves.get(number.wrapping_sub(1)))
+                    .map(|node| node.chess_move)
+                    .or_else(|| pgn::parse_san(&position, command))
+                    .or_else(|| {
+                        ChessMove::from_str(command)
+                            .ok()
+                            .filter(|&chess_move| position.legal(chess_move))
+                    });
+                match chosen {
+                    Some(chess_move) => line.push(chess_move),
+                    None => println!("'{}' is not a legal move here", command),
+                }
+            }
+        }
+    }
+}
+
+fn position_after(start: &Position, line: &[ChessMove]) -> Position {
+    line.iter().fold(start.clone(), |position, &chess_move| {
+        position.make_move_new(chess_move)
+    })
+}
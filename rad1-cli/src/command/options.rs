@@ -0,0 +1,80 @@
+use clap::{App, Arg, ArgMatches};
+use rad1::options::{OptionInfo, OptionType};
+
+pub fn options_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("List every engine option with its value, type and range")
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .required(false)
+                .takes_value(false)
+                .help("Print the options as a JSON array, one object per option"),
+        )
+        .arg(super::config_arg())
+        .arg(super::set_arg())
+}
+
+pub fn exec(matches: &ArgMatches) {
+    let options = super::engine_options(matches).describe();
+    if matches.is_present("json") {
+        let objects: Vec<String> = options.iter().map(OptionInfo::to_json).collect();
+        println!("[{}]", objects.join(","));
+        return;
+    }
+    let name_width = options
+        .iter()
+        .map(|info| info.name.len())
+        .max()
+        .unwrap_or(0);
+    let value_width = options
+        .iter()
+        .map(|info| info.value.len())
+        .max()
+        .unwrap_or(0);
+    // the groups in the order their first option is announced
+    let mut groups: Vec<&str> = Vec::new();
+    for info in &options {
+        if !groups.contains(&info.group) {
+            groups.push(info.group);
+        }
+    }
+    for (index, group) in groups.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        println!("{}", group);
+        for info in options.iter().filter(|info| info.group == *group) {
+            println!(
+                "  {:name_width$}  {:6}  {:value_width$}  {:14}  {}",
+                info.name,
+                type_name(&info.option_type),
+                info.value,
+                accepted(&info.option_type),
+                info.description,
+                name_width = name_width,
+                value_width = value_width,
+            );
+        }
+    }
+}
+
+fn type_name(option_type: &OptionType) -> &'static str {
+    match option_type {
+        OptionType::Check => "check",
+        OptionType::Spin { .. } => "spin",
+        OptionType::Combo(_) => "combo",
+        OptionType::String => "string",
+    }
+}
+
+// The values the option accepts, spelled out for spin and combo options
+fn accepted(option_type: &OptionType) -> String {
+    match option_type {
+        OptionType::Spin { min, max } => format!("{}..={}", min, max),
+        OptionType::Combo(values) => values.join("|"),
+        OptionType::Check | OptionType::String => String::new(),
+    }
+}
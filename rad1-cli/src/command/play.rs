@@ -2,15 +2,33 @@ use ansi_term::{Colour, Style};
 use clap::{App, Arg, ArgMatches};
 use itertools::Either;
 use rad1::agent;
-use rad1::agent::ChessAgent;
+use rad1::agent::{AlphaBetaChessAgent, ChessAgent};
+use rad1::eval;
+use rad1::fmt;
+use rad1::learning::OpeningLearning;
+use rad1::notation::Notation;
+use rad1::options::EngineOptions;
+use rad1::pgn;
+use rad1::search::prefetch;
+use rad1::search::SearchResult;
+use rad1::time::TimeControl;
 use rad1::tt::TranspositionTable;
+use rad1::Action;
 use rad1::ChessGame;
 use rad1::Color;
-use rad1::Piece;
 use rad1::Position;
 use rad1::Rank;
 use rad1::Square;
-use std::str::FromStr;
+use std::fs;
+use std::io::{self, Write};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// Depth of the search that picks the player's likely replies to prefetch
+const LIKELY_REPLY_DEPTH: u8 = 3;
 
 pub fn play_app(command_name: &str) -> App<'static, 'static> {
     App::new(command_name)
@@ -34,8 +52,9 @@ pub fn play_app(command_name: &str) -> App<'static, 'static> {
                 .short("f")
                 .required(false)
                 .takes_value(true)
-                .default_value("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+                .default_value(super::START_POSITION)
                 .hide_default_value(true)
+                .validator(super::is_fen)
                 .help("The Forsyth-Edwards Notation (FEN) of the position to be from"),
         )
         .arg(
@@ -47,22 +66,339 @@ pub fn play_app(command_name: &str) -> App<'static, 'static> {
                 .possible_values(&["White", "Black"])
                 .help("The color you want to play as"),
         )
+        .arg(
+            Arg::with_name("time")
+                .long("time")
+                .short("t")
+                .required(false)
+                .takes_value(true)
+                .validator(is_number)
+                .help("The engine's time for the whole game in seconds. Searches to a fixed depth if not given."),
+        )
+        .arg(
+            Arg::with_name("increment")
+                .long("increment")
+                .short("i")
+                .required(false)
+                .takes_value(true)
+                .default_value("0")
+                .validator(is_number)
+                .help("The time in seconds added to the engine's clock after each of its moves"),
+        )
+        .arg(super::moves_arg())
+        .arg(super::eval_arg())
+        .arg(super::disable_arg())
+        .arg(super::config_arg())
+        .arg(super::set_arg())
+        .arg(
+            Arg::with_name("move-overhead")
+                .long("move-overhead")
+                .required(false)
+                .takes_value(true)
+                .default_value("0")
+                .validator(is_number)
+                .help("Time in milliseconds kept in reserve on every move to make up for network or GUI lag"),
+        )
+        .arg(
+            Arg::with_name("nps")
+                .long("nps")
+                .required(false)
+                .takes_value(true)
+                .default_value("0")
+                .validator(is_number)
+                .help("The most positions the engine looks at per second, 0 for no limit. Lower values make it weaker."),
+        )
+        .arg(
+            Arg::with_name("move-delay")
+                .long("move-delay")
+                .required(false)
+                .takes_value(true)
+                .default_value("0")
+                .validator(is_number)
+                .help("The least time in milliseconds the engine takes for every move"),
+        )
+        .arg(
+            Arg::with_name("prefetch")
+                .long("prefetch")
+                .required(false)
+                .takes_value(true)
+                .default_value("0")
+                .validator(is_number)
+                .help("While you think, search the positions after this many of your likely replies in the background so the engine answers them faster, 0 to switch it off"),
+        )
+        .arg(
+            Arg::with_name("learn")
+                .long("learn")
+                .required(false)
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Learn from the engine's games in this file, created if missing: the engine avoids the openings it kept losing with and adds the result of this game"),
+        )
+        .arg(
+            Arg::with_name("pgn")
+                .long("pgn")
+                .required(false)
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Save the game to this PGN file after every move"),
+        )
+        .arg(
+            Arg::with_name("resume")
+                .long("resume")
+                .required(false)
+                .takes_value(false)
+                .requires("pgn")
+                .conflicts_with_all(&["start-position", "moves"])
+                .help("Continue the last game of the --pgn file instead of starting a new one, with the history of its positions for repetitions"),
+        )
+        .arg(super::notation_arg().default_value("figurine"))
+}
+
+fn is_number(value: String) -> Result<(), String> {
+    value
+        .parse::<u64>()
+        .map(|_| ())
+        .map_err(|_| format!("'{}' is not a positive number", value))
+}
+
+fn time_control(matches: &ArgMatches) -> Option<TimeControl> {
+    matches.value_of("time").map(|time| {
+        let base = Duration::from_secs(time.parse().unwrap());
+        let increment =
+            Duration::from_secs(matches.value_of("increment").unwrap().parse().unwrap());
+        let move_overhead =
+            Duration::from_millis(matches.value_of("move-overhead").unwrap().parse().unwrap());
+        TimeControl::new(base, increment).with_move_overhead(move_overhead)
+    })
+}
+
+// The learning file of `--learn`, empty when it doesn't exist yet
+fn load_learning(path: &str) -> OpeningLearning {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return OpeningLearning::default(),
+        Err(error) => fail(format!("Failed to read '{}': {}", path, error)),
+    };
+    OpeningLearning::from_text(&text)
+        .unwrap_or_else(|error| fail(format!("Failed to read '{}': {}", path, error)))
+}
+
+// The last game of the PGN file, to be continued
+fn resume(path: &str) -> ChessGame {
+    let text = fs::read_to_string(path)
+        .unwrap_or_else(|error| fail(format!("Failed to read '{}': {}", path, error)));
+    let last = pgn::read_games(&text)
+        .pop()
+        .unwrap_or_else(|| fail(format!("There is no game in '{}'", path)));
+    if last.tag("Result").map_or(false, |result| result != "*") {
+        fail(format!("The game in '{}' is already over", path));
+    }
+    last.to_game().unwrap_or_else(|| {
+        fail(format!(
+            "The game in '{}' has an invalid start or an illegal move",
+            path
+        ))
+    })
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn engine(
+    matches: &ArgMatches,
+    depth: u8,
+    interrupt: &Interrupt,
+    learning: Option<&OpeningLearning>,
+) -> ThinkingEngine {
+    let mut engine = agent::alpha_beta_agent(depth, super::transposition_table())
+        .with_evaluator(super::evaluator(matches))
+        .with_stop_flag(interrupt.stop.clone());
+    if let Some(learning) = learning {
+        engine = engine.with_learning(learning.clone());
+    }
+    engine.set_options(EngineOptions {
+        nps_limit: matches.value_of("nps").unwrap().parse().unwrap(),
+        move_delay: Duration::from_millis(matches.value_of("move-delay").unwrap().parse().unwrap()),
+        ..super::engine_options(matches)
+    });
+    ThinkingEngine {
+        agent: match time_control(matches) {
+            Some(time_control) => engine.with_time_control(time_control),
+            None => engine,
+        },
+        searching: interrupt.searching.clone(),
+        notation: interrupt.notation,
+    }
+}
+
+// Shows what the engine is thinking on a single line while it searches
+struct ThinkingEngine {
+    agent: AlphaBetaChessAgent,
+    searching: Arc<AtomicBool>,
+    notation: Notation,
+}
+
+impl ChessAgent for ThinkingEngine {
+    fn get_action(&self, game: &ChessGame) -> Action {
+        self.searching.store(true, Ordering::SeqCst);
+        let position = game.current_position();
+        let result = self.agent.search_game_with_info(game, |result| {
+            print_thinking(&position, result, self.notation)
+        });
+        self.searching.store(false, Ordering::SeqCst);
+        // clear the status line
+        print!("\r\x1b[K");
+        io::stdout().flush().unwrap();
+        if self.agent.accepts_draw(game, result.score) {
+            Action::AcceptDraw
+        } else if self.agent.resigns(game, result.score) {
+            Action::Resign(game.side_to_move())
+        } else {
+            Action::MakeMove(result.best_move)
+        }
+    }
+}
+
+// Waits for the player's move while the positions after their likely
+// replies are searched in the background, filling the engine's table
+struct PrefetchingPlayer {
+    table: Arc<TranspositionTable<i16>>,
+    eval: String,
+    options: EngineOptions,
+    depth: u8,
+    replies: usize,
+}
+
+impl ChessAgent for PrefetchingPlayer {
+    fn get_action(&self, game: &ChessGame) -> Action {
+        if self.replies == 0 {
+            return agent::command_line_agent().get_action(game);
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let agent = |depth| {
+            AlphaBetaChessAgent::sharing_table(depth, self.table.clone(), self.options.clone())
+                .with_stop_flag(stop.clone())
+                .with_evaluator(eval::registry().create(&self.eval).unwrap())
+        };
+        let background = {
+            let (mut likely, prefetcher) = (agent(LIKELY_REPLY_DEPTH), agent(self.depth));
+            let (stop, replies) = (stop.clone(), self.replies);
+            let game = game.clone();
+            thread::spawn(move || {
+                // the player's own programs come first
+                prefetch::lower_thread_priority();
+                let likely_replies = prefetch::likely_replies(&mut likely, &game, replies);
+                prefetch::prefetch(&prefetcher, &game, &likely_replies, &stop);
+            })
+        };
+        let action = agent::command_line_agent().get_action(game);
+        stop.store(true, Ordering::SeqCst);
+        let _ = background.join();
+        action
+    }
+}
+
+// What Ctrl-C needs to stop the game without losing it
+struct Interrupt {
+    stop: Arc<AtomicBool>,
+    searching: Arc<AtomicBool>,
+    pgn: Arc<Mutex<String>>,
+    notation: Notation,
+}
+
+impl Interrupt {
+    // While the engine searches Ctrl-C stops the search and the game ends after
+    // its move, while waiting for the player's move the game ends right away
+    fn install(game: &ChessGame, notation: Notation) -> Self {
+        let interrupt = Self {
+            stop: Arc::new(AtomicBool::new(false)),
+            searching: Arc::new(AtomicBool::new(false)),
+            pgn: Arc::new(Mutex::new(pgn::to_pgn_in(game, notation))),
+            notation,
+        };
+        let stop = interrupt.stop.clone();
+        let searching = interrupt.searching.clone();
+        let pgn = interrupt.pgn.clone();
+        ctrlc::set_handler(move || {
+            stop.store(true, Ordering::SeqCst);
+            if !searching.load(Ordering::SeqCst) {
+                exit_with_pgn(&pgn.lock().unwrap());
+            }
+        })
+        .expect("Failed to install the Ctrl-C handler");
+        interrupt
+    }
+
+    fn update(&self, game: &ChessGame) {
+        *self.pgn.lock().unwrap() = pgn::to_pgn_in(game, self.notation);
+    }
+
+    fn check(&self, game: &ChessGame, action: &Action) {
+        if self.stop.load(Ordering::SeqCst) {
+            let pgn = self.pgn.lock().unwrap();
+            if let Action::MakeMove(chess_move) = action {
+                let position = game.current_position();
+                println!(
+                    "\nBest move found so far: {}",
+                    self.notation
+                        .translate(&pgn::san(&position, *chess_move), position.side_to_move())
+                );
+            }
+            exit_with_pgn(&pgn);
+        }
+    }
+}
+
+fn exit_with_pgn(pgn: &str) -> ! {
+    println!("\nGame interrupted\n\n{}", pgn);
+    process::exit(super::INTERRUPTED_EXIT_CODE);
+}
+
+fn print_thinking(position: &Position, result: &SearchResult, notation: Notation) {
+    print!(
+        "\r\x1b[KThinking... depth {}  score {}  pv {}",
+        result.depth,
+        fmt::score(result.score),
+        fmt::pv_in(position, &result.pv, notation)
+    );
+    io::stdout().flush().unwrap();
 }
 
 pub fn exec(matches: &ArgMatches) {
-    let start_position = matches.value_of("start-position").unwrap();
-    let mut game = ChessGame::from_str(start_position).expect("Failed to parse FEN");
+    let pgn_path = matches.value_of("pgn");
+    let mut game = match pgn_path {
+        Some(path) if matches.is_present("resume") => resume(path),
+        _ => super::game(matches, "start-position"),
+    };
     let color = matches.value_of("color").unwrap();
     let depth: u8 = matches.value_of("depth").unwrap().parse().unwrap();
+    let notation = super::notation(matches);
+    let interrupt = Interrupt::install(&game, notation);
+    let learn = matches.value_of("learn");
+    let mut learning = learn.map(load_learning);
+    let engine = engine(matches, depth, &interrupt, learning.as_ref());
+    let player = PrefetchingPlayer {
+        table: engine.agent.shared_table(),
+        eval: matches.value_of("eval").unwrap().to_string(),
+        options: super::engine_options(matches),
+        depth,
+        replies: matches.value_of("prefetch").unwrap().parse().unwrap(),
+    };
 
-    if color == "White" {
-        let white_player = agent::command_line_agent();
-        let black_player = agent::alpha_beta_agent(depth, TranspositionTable::default());
-        play_game(&mut game, &white_player, &black_player, false);
+    let engine_color = if color == "White" {
+        play_game(&mut game, &player, &engine, false, &interrupt, pgn_path);
+        Color::Black
     } else {
-        let white_player = agent::alpha_beta_agent(depth, TranspositionTable::default());
-        let black_player = agent::command_line_agent();
-        play_game(&mut game, &white_player, &black_player, true);
+        play_game(&mut game, &engine, &player, true, &interrupt, pgn_path);
+        Color::White
+    };
+    if let (Some(path), Some(learning)) = (learn, learning.as_mut()) {
+        learning.record_game(&game, engine_color);
+        if let Err(error) = fs::write(path, learning.to_text()) {
+            fail(format!("Failed to write '{}': {}", path, error));
+        }
     }
 }
 
@@ -71,20 +407,31 @@ fn play_game(
     white_player: &dyn ChessAgent,
     black_player: &dyn ChessAgent,
     reverse_board: bool,
+    interrupt: &Interrupt,
+    pgn_path: Option<&str>,
 ) {
-    print_board(&game.current_position(), reverse_board);
-    while game.result() == None {
+    print_board(&game.current_position(), reverse_board, interrupt.notation);
+    while game.result().is_none() {
         let action = match game.side_to_move() {
             Color::White => white_player.get_action(game),
             Color::Black => black_player.get_action(game),
         };
+        interrupt.check(game, &action);
         game.take_action(action);
-        print_board(&game.current_position(), reverse_board);
+        interrupt.update(game);
+        if let Some(path) = pgn_path {
+            if let Err(error) = fs::write(path, pgn::to_pgn(game)) {
+                eprintln!("Failed to write '{}': {}", path, error);
+            }
+        }
+        print_board(&game.current_position(), reverse_board, interrupt.notation);
     }
     println!("{:?}", game.result().unwrap());
 }
 
-fn print_board(position: &Position, reverse_board: bool) {
+/// Print the board with ANSI colors in the notation, from black's side with
+/// `reverse_board`
+pub fn print_board(position: &Position, reverse_board: bool, notation: Notation) {
     #[cfg(target_os = "windows")]
     ansi_term::enable_ansi_support().expect("ANSI colors not supported");
 
@@ -98,7 +445,15 @@ fn print_board(position: &Position, reverse_board: bool) {
         Either::Right(rad1::ALL_RANKS.iter().rev())
     };
     for rank in ranks {
-        print_rank(rank, italic, bg_black, bg_white, position, reverse_board);
+        print_rank(
+            rank,
+            italic,
+            bg_black,
+            bg_white,
+            position,
+            reverse_board,
+            notation,
+        );
     }
     if reverse_board {
         println!("{}", italic.paint("    H  G  F  E  D  C  B  A"));
@@ -114,6 +469,7 @@ fn print_rank(
     bg_white: Style,
     position: &Position,
     reverse_board: bool,
+    notation: Notation,
 ) {
     let mut line: String = String::new();
     let mut background = if rank.to_index() % 2 == 1 {
@@ -133,7 +489,10 @@ fn print_rank(
     };
     for file in files {
         let square = Square::make_square(*rank, *file);
-        let piece_char = get_piece_char(position.color_on(square), position.piece_on(square));
+        let piece_char = match (position.color_on(square), position.piece_on(square)) {
+            (Some(color), Some(piece)) => notation.board_piece(piece, color),
+            _ => String::from(" "),
+        };
         line.push_str(&background.paint(format!(" {} ", piece_char)).to_string());
         background = if background == bg_white {
             bg_black
@@ -143,21 +502,3 @@ fn print_rank(
     }
     println!("{}", line);
 }
-
-fn get_piece_char(color: Option<Color>, piece: Option<Piece>) -> &'static str {
-    match (color, piece) {
-        (Some(Color::White), Some(Piece::Pawn)) => "♙",
-        (Some(Color::White), Some(Piece::Knight)) => "♘",
-        (Some(Color::White), Some(Piece::Bishop)) => "♗",
-        (Some(Color::White), Some(Piece::Rook)) => "♖",
-        (Some(Color::White), Some(Piece::Queen)) => "♕",
-        (Some(Color::White), Some(Piece::King)) => "♔",
-        (Some(Color::Black), Some(Piece::Pawn)) => "♟︎",
-        (Some(Color::Black), Some(Piece::Knight)) => "♞",
-        (Some(Color::Black), Some(Piece::Bishop)) => "♝",
-        (Some(Color::Black), Some(Piece::Rook)) => "♜",
-        (Some(Color::Black), Some(Piece::Queen)) => "♛",
-        (Some(Color::Black), Some(Piece::King)) => "♚",
-        (_, _) => " ",
-    }
-}
@@ -0,0 +1,111 @@
+use clap::{App, Arg, ArgMatches};
+use rad1::agent;
+use rad1::repertoire::{Repertoire, RepertoireMove};
+use std::fs;
+use std::process;
+
+pub fn prep_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Build an opening repertoire of the engine's best moves and replies as PGN")
+        .arg(
+            Arg::with_name("fen")
+                .long("fen")
+                .short("f")
+                .required(false)
+                .takes_value(true)
+                .default_value(super::START_POSITION)
+                .hide_default_value(true)
+                .validator(super::is_fen)
+                .help(
+                    "The Forsyth-Edwards Notation (FEN) of the position the repertoire starts from",
+                ),
+        )
+        .arg(super::moves_arg())
+        .arg(super::eval_arg())
+        .arg(super::disable_arg())
+        .arg(super::config_arg())
+        .arg(super::set_arg())
+        .arg(
+            Arg::with_name("depth")
+                .long("depth")
+                .short("d")
+                .required(false)
+                .takes_value(true)
+                .default_value("6")
+                .possible_values(&["1", "2", "3", "4", "5", "6", "7", "8", "9", "10"])
+                .hide_possible_values(true)
+                .help("The depth every position of the repertoire is searched to"),
+        )
+        .arg(
+            Arg::with_name("breadth")
+                .long("breadth")
+                .short("b")
+                .required(false)
+                .takes_value(true)
+                .default_value("3")
+                .validator(is_positive)
+                .help("How many of the best moves are kept in every position"),
+        )
+        .arg(
+            Arg::with_name("plies")
+                .long("plies")
+                .short("p")
+                .required(false)
+                .takes_value(true)
+                .default_value("4")
+                .validator(is_positive)
+                .help("How many moves of both sides the repertoire goes deep"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .required(false)
+                .takes_value(true)
+                .default_value("repertoire.pgn")
+                .help("The PGN file the repertoire is written to"),
+        )
+}
+
+fn is_positive(value: String) -> Result<(), String> {
+    match value.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(()),
+        _ => Err(format!("'{}' isn't a positive number", value)),
+    }
+}
+
+pub fn exec(matches: &ArgMatches) {
+    let game = super::game(matches, "fen");
+    let depth: u8 = matches.value_of("depth").unwrap().parse().unwrap();
+    let breadth: usize = matches.value_of("breadth").unwrap().parse().unwrap();
+    let plies: usize = matches.value_of("plies").unwrap().parse().unwrap();
+    let output = matches.value_of("output").unwrap();
+
+    let mut agent = agent::alpha_beta_agent(depth, super::transposition_table());
+    agent.set_options(super::engine_options(matches));
+    let mut agent = agent.with_evaluator(super::evaluator(matches));
+    let repertoire = Repertoire::build(&mut agent, &game.current_position(), breadth, plies);
+    if let Err(error) = fs::write(output, repertoire.to_pgn()) {
+        eprintln!("Failed to write '{}': {}", output, error);
+        process::exit(1);
+    }
+    println!(
+        "Wrote {} positions, {} of them transpositions, to {}",
+        count(&repertoire.moves, false),
+        count(&repertoire.moves, true),
+        output
+    );
+}
+
+// The moves of the repertoire, or only the ones that transpose
+fn count(moves: &[RepertoireMove], transpositions: bool) -> usize {
+    moves
+        .iter()
+        .map(|m| {
+            let counted = !transpositions || m.transposes_to.is_some();
+            usize::from(counted) + count(&m.replies, transpositions)
+        })
+        .sum()
+}
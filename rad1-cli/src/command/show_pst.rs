@@ -0,0 +1,99 @@
+use ansi_term::{Colour, Style};
+use clap::{App, Arg, ArgMatches};
+use rad1::eval::pst::{self, Backdrop};
+use rad1::Piece;
+
+const PIECES: [(&str, Piece); 6] = [
+    ("pawn", Piece::Pawn),
+    ("knight", Piece::Knight),
+    ("bishop", Piece::Bishop),
+    ("rook", Piece::Rook),
+    ("queen", Piece::Queen),
+    ("king", Piece::King),
+];
+
+// Shades of the heatmap from the lowest value of a table to the highest,
+// red through yellow to green in the 256 color palette
+const SHADES: [u8; 11] = [196, 202, 208, 214, 220, 226, 190, 154, 118, 82, 46];
+
+pub fn show_pst_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Show the piece-square tables an evaluator effectively uses as heatmaps")
+        .arg(
+            Arg::with_name("piece")
+                .long("piece")
+                .short("p")
+                .required(false)
+                .takes_value(true)
+                .multiple(true)
+                .use_delimiter(true)
+                .possible_values(&["pawn", "knight", "bishop", "rook", "queen", "king"])
+                .help("The pieces whose tables are shown [default: all]"),
+        )
+        .arg(super::eval_arg())
+}
+
+pub fn exec(matches: &ArgMatches) {
+    #[cfg(target_os = "windows")]
+    ansi_term::enable_ansi_support().expect("ANSI colors not supported");
+
+    let evaluator = super::evaluator(matches);
+    let selected: Vec<&str> = matches.values_of("piece").map_or_else(
+        || PIECES.iter().map(|(name, _)| *name).collect(),
+        Iterator::collect,
+    );
+    println!("Evaluation of a white piece on each square in evaluation units, material included");
+    for (name, piece) in PIECES.iter().filter(|(name, _)| selected.contains(name)) {
+        let opening = pst::piece_square_table(evaluator.as_ref(), *piece, Backdrop::Opening);
+        let endgame = pst::piece_square_table(evaluator.as_ref(), *piece, Backdrop::Endgame);
+        println!();
+        let heading = format!("{} opening  {}", name, range(&opening));
+        println!("{:<36}{} endgame  {}", heading, name, range(&endgame));
+        for rank in (0..8).rev() {
+            println!(
+                "{} {}   {} {}",
+                rank + 1,
+                heat_rank(&opening, rank),
+                rank + 1,
+                heat_rank(&endgame, rank)
+            );
+        }
+        let files = "   a   b   c   d   e   f   g   h";
+        println!("  {}     {}", files, files);
+    }
+}
+
+// The lowest and highest value of the table
+fn range(table: &[Option<i16>; 64]) -> String {
+    let values = table.iter().flatten();
+    match (values.clone().min(), values.max()) {
+        (Some(min), Some(max)) => format!("{} to {}", min, max),
+        _ => String::from("no legal squares"),
+    }
+}
+
+fn heat_rank(table: &[Option<i16>; 64], rank: usize) -> String {
+    let values = table.iter().flatten();
+    let min = values.clone().min().copied().unwrap_or(0);
+    let max = values.max().copied().unwrap_or(0);
+    table[rank * 8..rank * 8 + 8]
+        .iter()
+        .map(|value| match value {
+            Some(value) => {
+                let shade = if max > min {
+                    (i32::from(*value - min) * (SHADES.len() as i32 - 1) / i32::from(max - min))
+                        as usize
+                } else {
+                    SHADES.len() / 2
+                };
+                Colour::Fixed(16)
+                    .on(Colour::Fixed(SHADES[shade]))
+                    .paint(format!("{:>4}", value))
+                    .to_string()
+            }
+            None => Style::new().dimmed().paint("   ·").to_string(),
+        })
+        .collect()
+}
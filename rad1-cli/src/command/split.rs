@@ -0,0 +1,115 @@
+use clap::{App, Arg, ArgMatches};
+use rad1::dataset::{self, Manifest};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+pub fn split_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Split a dataset into training, validation and test sets with the same mix of outcomes and phases")
+        .arg(
+            Arg::with_name("file")
+                .required(true)
+                .help("Dataset file with a FEN and the game's outcome like [0.5] or 1-0 per line"),
+        )
+        .arg(
+            Arg::with_name("train")
+                .long("train")
+                .required(false)
+                .takes_value(true)
+                .default_value("0.8")
+                .validator(is_fraction)
+                .help("Fraction of the positions in the training set"),
+        )
+        .arg(
+            Arg::with_name("validation")
+                .long("validation")
+                .required(false)
+                .takes_value(true)
+                .default_value("0.1")
+                .validator(is_fraction)
+                .help("Fraction of the positions in the validation set, the test set gets the rest"),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .required(false)
+                .takes_value(true)
+                .default_value("0")
+                .validator(|value| {
+                    value
+                        .parse::<u64>()
+                        .map(|_| ())
+                        .map_err(|_| format!("'{}' isn't a seed", value))
+                })
+                .help("Seed of the shuffle, the same seed gives the same split"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .required(false)
+                .takes_value(true)
+                .help("Prefix of the files written, <output>.train.epd and so on [default: the dataset file without its extension]"),
+        )
+}
+
+fn is_fraction(value: String) -> Result<(), String> {
+    match value.parse::<f64>() {
+        Ok(fraction) if (0.0..=1.0).contains(&fraction) => Ok(()),
+        _ => Err(format!("'{}' isn't a fraction from 0 to 1", value)),
+    }
+}
+
+pub fn exec(matches: &ArgMatches) {
+    let path = Path::new(matches.value_of("file").unwrap());
+    let train: f64 = matches.value_of("train").unwrap().parse().unwrap();
+    let validation: f64 = matches.value_of("validation").unwrap().parse().unwrap();
+    if train + validation > 1.0 {
+        eprintln!("The training and validation sets can't have more than all the positions");
+        process::exit(1);
+    }
+    let seed: u64 = matches.value_of("seed").unwrap().parse().unwrap();
+    let output = matches
+        .value_of("output")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| path.with_extension(""));
+    let text = fs::read_to_string(path).unwrap_or_else(|error| {
+        eprintln!("Failed to read '{}': {}", path.display(), error);
+        process::exit(1);
+    });
+
+    let split = dataset::split(text.lines(), train, validation, seed);
+    let file = |set: &str| PathBuf::from(format!("{}.{}.epd", output.display(), set));
+    let sets = [
+        ("train", &split.train),
+        ("validation", &split.validation),
+        ("test", &split.test),
+    ];
+    for (set, lines) in sets.iter() {
+        write(&file(set), &lines.join("\n"));
+        println!("{:<12}{:>12}  {}", set, lines.len(), file(set).display());
+    }
+    if split.invalid > 0 {
+        println!("{:<12}{:>12}  left out", "invalid", split.invalid);
+    }
+    // the manifest sits next to the sets and refers to them by name
+    let name = |set: &str| PathBuf::from(file(set).file_name().unwrap());
+    let manifest = Manifest {
+        train: name("train"),
+        validation: name("validation"),
+        test: name("test"),
+    };
+    let manifest_path = PathBuf::from(format!("{}.manifest", output.display()));
+    write(&manifest_path, &manifest.to_string());
+    println!("{:<24}  {}", "manifest", manifest_path.display());
+}
+
+fn write(path: &Path, contents: &str) {
+    if let Err(error) = fs::write(path, contents) {
+        eprintln!("Failed to write '{}': {}", path.display(), error);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,101 @@
+use clap::{App, Arg, ArgMatches};
+use rad1::dataset::{self, DatasetStats, Outcome};
+use rad1::GamePhase;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::process;
+
+// Width of the longest histogram bar
+const BAR_WIDTH: f64 = 40.0;
+
+pub fn stats_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Report the composition of training datasets and what looks biased")
+        .arg(
+            Arg::with_name("files")
+                .required(true)
+                .multiple(true)
+                .help("Dataset files with a FEN and the game's outcome like [0.5] or 1-0 per line"),
+        )
+}
+
+pub fn exec(matches: &ArgMatches) {
+    let mut stats = DatasetStats::default();
+    println!("{:<40}{:>12}{:>12}", "file", "positions", "invalid");
+    for path in matches.values_of("files").unwrap() {
+        let (positions, invalid) = (stats.positions(), stats.invalid());
+        let file = File::open(path).unwrap_or_else(|error| {
+            eprintln!("Failed to open '{}': {}", path, error);
+            process::exit(1);
+        });
+        for line in BufReader::new(file).lines() {
+            let line = line.unwrap_or_else(|error| {
+                eprintln!("Failed to read '{}': {}", path, error);
+                process::exit(1);
+            });
+            stats.add_line(&line);
+        }
+        println!(
+            "{:<40}{:>12}{:>12}",
+            path,
+            stats.positions() - positions,
+            stats.invalid() - invalid
+        );
+    }
+    println!(
+        "{:<40}{:>12}{:>12}",
+        "total",
+        stats.positions(),
+        stats.invalid()
+    );
+    print_count(&stats, "duplicates", stats.duplicates());
+
+    println!("\noutcome");
+    for &outcome in [Outcome::WhiteWins, Outcome::Draw, Outcome::BlackWins].iter() {
+        print_count(
+            &stats,
+            dataset::outcome_name(outcome),
+            stats.outcome_count(outcome),
+        );
+    }
+    println!("\nphase");
+    for &phase in [
+        GamePhase::Opening,
+        GamePhase::Middlegame,
+        GamePhase::Endgame,
+    ]
+    .iter()
+    {
+        print_count(&stats, &phase.to_string(), stats.phase_count(phase));
+    }
+    println!("\nmaterial balance (white - black, in pawns)");
+    for (&balance, &count) in stats.material_balances() {
+        let bar = "#".repeat((stats.rate(count) * BAR_WIDTH).round() as usize);
+        println!(
+            "  {:<38}{:>12}{:>11.1}%  {}",
+            format!("{:+}", balance),
+            count,
+            100.0 * stats.rate(count),
+            bar
+        );
+    }
+
+    let warnings = stats.warnings();
+    if !warnings.is_empty() {
+        println!();
+    }
+    for warning in warnings {
+        println!("warning: {}", warning);
+    }
+}
+
+fn print_count(stats: &DatasetStats, label: &str, count: usize) {
+    println!(
+        "  {:<38}{:>12}{:>11.1}%",
+        label,
+        count,
+        100.0 * stats.rate(count)
+    );
+}
@@ -0,0 +1,282 @@
+use clap::{App, Arg, ArgMatches};
+use rad1::agent;
+use rad1::epd::EpdPosition;
+use rad1::ChessMove;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{self, Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+// Deepest iteration of rad1's searches, the time limit stops them first
+const MAX_DEPTH: u8 = 64;
+
+pub fn suite_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Run an EPD test suite and count the positions solved, optionally next to another engine")
+        .arg(
+            Arg::with_name("file")
+                .required(true)
+                .help("An EPD file with a bm or am operation on every line"),
+        )
+        .arg(
+            Arg::with_name("time")
+                .long("time")
+                .short("t")
+                .required(false)
+                .takes_value(true)
+                .default_value("1000")
+                .validator(|value| match value.parse::<u64>() {
+                    Ok(millis) if millis > 0 => Ok(()),
+                    _ => Err(format!("'{}' is not a positive number", value)),
+                })
+                .help("The time in milliseconds every position is searched for"),
+        )
+        .arg(
+            Arg::with_name("engine")
+                .long("engine")
+                .required(false)
+                .takes_value(true)
+                .value_name("PATH")
+                .help("A UCI engine to run on the same positions with the same time, for a side-by-side comparison"),
+        )
+        .arg(super::eval_arg())
+        .arg(super::disable_arg())
+        .arg(super::config_arg())
+        .arg(super::set_arg())
+}
+
+// How an engine did on one position
+struct Attempt {
+    best_move: ChessMove,
+    solved_in: Option<Duration>,
+}
+
+// The totals of an engine over the suite
+#[derive(Default)]
+struct Tally {
+    solved: usize,
+    time: Duration,
+}
+
+impl Tally {
+    fn add(&mut self, attempt: &Attempt) {
+        if let Some(time) = attempt.solved_in {
+            self.solved += 1;
+            self.time += time;
+        }
+    }
+
+    // Average time to solution of the solved positions
+    fn average(&self) -> String {
+        match self.time.checked_div(self.solved as u32) {
+            Some(average) => format!("{:.3}s", average.as_secs_f64()),
+            None => String::from("-"),
+        }
+    }
+}
+
+pub fn exec(matches: &ArgMatches) {
+    let path = matches.value_of("file").unwrap();
+    let text = fs::read_to_string(path)
+        .unwrap_or_else(|error| fail(format!("Failed to read '{}': {}", path, error)));
+    let suite: Vec<EpdPosition> = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(index, line)| {
+            EpdPosition::from_str(line)
+                .and_then(EpdPosition::require_solution)
+                .unwrap_or_else(|error| {
+                    fail(format!("Line {} of '{}': {}", index + 1, path, error))
+                })
+        })
+        .collect();
+    let time = Duration::from_millis(matches.value_of("time").unwrap().parse().unwrap());
+    let mut engine = matches.value_of("engine").map(UciEngine::start);
+
+    let mut rad1 = agent::alpha_beta_agent(MAX_DEPTH, super::transposition_table())
+        .with_evaluator(super::evaluator(matches))
+        .with_move_time(time);
+    rad1.set_options(super::engine_options(matches));
+
+    let other_name = engine.as_ref().map(|engine| engine.name.clone());
+    print!("{:<24}{:>10}{:>10}", "id", "rad1", "time");
+    if let Some(name) = &other_name {
+        print!("  {:>10}{:>10}", truncate(name, 10), "time");
+    }
+    println!();
+    let (mut rad1_tally, mut other_tally) = (Tally::default(), Tally::default());
+    for epd in &suite {
+        rad1.clear_hash();
+        let mut iterations = Vec::new();
+        let result = rad1.search_with_info(&epd.position, |result| {
+            iterations.push((result.elapsed, result.best_move))
+        });
+        iterations.push((result.elapsed, result.best_move));
+        let attempt = Attempt {
+            best_move: result.best_move,
+            solved_in: epd.time_to_solution(&iterations),
+        };
+        rad1_tally.add(&attempt);
+        print!("{:<24}{}", truncate(&epd.name(), 23), cells(&attempt));
+        if let Some(engine) = engine.as_mut() {
+            let attempt = engine.solve(epd, time);
+            other_tally.add(&attempt);
+            print!("  {}", cells(&attempt));
+        }
+        println!();
+    }
+
+    println!();
+    print!(
+        "{:<24}{:>10}{:>10}",
+        "solved",
+        format!("{}/{}", rad1_tally.solved, suite.len()),
+        rad1_tally.average()
+    );
+    if other_name.is_some() {
+        print!(
+            "  {:>10}{:>10}",
+            format!("{}/{}", other_tally.solved, suite.len()),
+            other_tally.average()
+        );
+    }
+    println!();
+    if let Some(engine) = engine {
+        engine.quit();
+    }
+}
+
+// The move of the attempt, marked when it doesn't solve the position, and
+// the time it took to find the solution
+fn cells(attempt: &Attempt) -> String {
+    match attempt.solved_in {
+        Some(time) => format!(
+            "{:>10}{:>9.3}s",
+            attempt.best_move.to_string(),
+            time.as_secs_f64()
+        ),
+        None => format!("{:>10}{:>10}", format!("{}?", attempt.best_move), "-"),
+    }
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+// An external engine spoken to over the UCI protocol
+struct UciEngine {
+    name: String,
+    child: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+}
+
+impl UciEngine {
+    fn start(path: &str) -> Self {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap_or_else(|error| fail(format!("Failed to start '{}': {}", path, error)));
+        let mut engine = Self {
+            name: path.to_string(),
+            input: child.stdin.take().unwrap(),
+            output: BufReader::new(child.stdout.take().unwrap()),
+            child,
+        };
+        engine.send("uci");
+        loop {
+            let line = engine.read_line();
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            }
+            if line.trim() == "uciok" {
+                break;
+            }
+        }
+        engine
+    }
+
+    fn send(&mut self, command: &str) {
+        if let Err(error) = writeln!(self.input, "{}", command).and_then(|_| self.input.flush()) {
+            fail(format!("Failed to talk to '{}': {}", self.name, error));
+        }
+    }
+
+    fn read_line(&mut self) -> String {
+        let mut line = String::new();
+        match self.output.read_line(&mut line) {
+            Ok(0) => fail(format!("'{}' stopped unexpectedly", self.name)),
+            Ok(_) => line,
+            Err(error) => fail(format!("Failed to talk to '{}': {}", self.name, error)),
+        }
+    }
+
+    fn wait_until_ready(&mut self) {
+        self.send("isready");
+        while self.read_line().trim() != "readyok" {}
+    }
+
+    // Search the position for the time, following the first move of the pv
+    // of every info line to tell when the engine found the solution
+    fn solve(&mut self, epd: &EpdPosition, time: Duration) -> Attempt {
+        self.send("ucinewgame");
+        self.wait_until_ready();
+        self.send(&format!("position fen {}", epd.to_fen()));
+        self.send(&format!("go movetime {}", time.as_millis()));
+        let start = Instant::now();
+        let mut iterations = Vec::new();
+        loop {
+            let line = self.read_line();
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("info") => {
+                    let tokens: Vec<&str> = tokens.collect();
+                    let pv_move = tokens
+                        .iter()
+                        .position(|&token| token == "pv")
+                        .and_then(|index| tokens.get(index + 1))
+                        .and_then(|text| ChessMove::from_str(text).ok());
+                    // the time the engine reports, or ours when it doesn't
+                    let elapsed = tokens
+                        .iter()
+                        .position(|&token| token == "time")
+                        .and_then(|index| tokens.get(index + 1))
+                        .and_then(|text| text.parse().ok())
+                        .map_or_else(|| start.elapsed(), Duration::from_millis);
+                    if let Some(chess_move) = pv_move {
+                        iterations.push((elapsed, chess_move));
+                    }
+                }
+                Some("bestmove") => {
+                    let best_move = tokens
+                        .next()
+                        .and_then(|text| ChessMove::from_str(text).ok())
+                        .unwrap_or_else(|| {
+                            fail(format!("'{}' sent an invalid best move", self.name))
+                        });
+                    iterations.push((start.elapsed(), best_move));
+                    return Attempt {
+                        best_move,
+                        solved_in: epd.time_to_solution(&iterations),
+                    };
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn quit(mut self) {
+        self.send("quit");
+        let _ = self.child.wait();
+    }
+}
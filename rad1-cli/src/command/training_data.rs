@@ -0,0 +1,132 @@
+use clap::{App, Arg, ArgMatches};
+use rad1::agent::{self, AlphaBetaChessAgent};
+use rad1::binpack::{self, BinpackWriter, Entry};
+use rad1::dataset::Sample;
+use rad1::tuning::OPENINGS;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::process;
+
+pub fn training_data_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Write self-play games or dataset positions as NNUE training data in Stockfish's binpack format")
+        .arg(
+            Arg::with_name("dataset")
+                .long("dataset")
+                .required(false)
+                .takes_value(true)
+                .help("Search the positions of this dataset instead of playing games, the results come from its outcomes"),
+        )
+        .arg(
+            Arg::with_name("games")
+                .long("games")
+                .short("g")
+                .required(false)
+                .takes_value(true)
+                .default_value("10")
+                .conflicts_with("dataset")
+                .validator(|value| {
+                    value
+                        .parse::<usize>()
+                        .map(|_| ())
+                        .map_err(|_| format!("'{}' isn't a number of games", value))
+                })
+                .help("How many self-play games to play, starting from a set of openings in turn"),
+        )
+        .arg(
+            Arg::with_name("depth")
+                .long("depth")
+                .short("d")
+                .required(false)
+                .takes_value(true)
+                .default_value("4")
+                .possible_values(&["1", "2", "3", "4", "5", "6", "7", "8"])
+                .hide_possible_values(true)
+                .help("The depth every position is searched to"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .required(false)
+                .takes_value(true)
+                .default_value("rad1.binpack")
+                .help("The file the training data is written to"),
+        )
+}
+
+pub fn exec(matches: &ArgMatches) {
+    let depth: u8 = matches.value_of("depth").unwrap().parse().unwrap();
+    let output = matches.value_of("output").unwrap();
+    let agent = agent::alpha_beta_agent(depth, super::transposition_table());
+    let file = File::create(output).unwrap_or_else(|error| {
+        eprintln!("Failed to create '{}': {}", output, error);
+        process::exit(1);
+    });
+    let mut out = BinpackWriter::new(BufWriter::new(file));
+    let entries = match matches.value_of("dataset") {
+        Some(path) => write_dataset(&agent, path, &mut out),
+        None => {
+            let games: usize = matches.value_of("games").unwrap().parse().unwrap();
+            write_self_play(&agent, games, &mut out)
+        }
+    };
+    if let Err(error) = out.finish() {
+        eprintln!("Failed to write '{}': {}", output, error);
+        process::exit(1);
+    }
+    println!("Wrote {} positions to {}", entries, output);
+}
+
+fn write_self_play(
+    agent: &AlphaBetaChessAgent,
+    games: usize,
+    out: &mut BinpackWriter<impl Write>,
+) -> usize {
+    let mut count = 0;
+    for game in 0..games {
+        let opening = OPENINGS[game % OPENINGS.len()];
+        let entries = binpack::self_play(agent, opening).unwrap();
+        write_entries(&entries, out);
+        count += entries.len();
+        eprintln!(
+            "game {} of {}: {} positions",
+            game + 1,
+            games,
+            entries.len()
+        );
+    }
+    count
+}
+
+fn write_dataset(
+    agent: &AlphaBetaChessAgent,
+    path: &str,
+    out: &mut BinpackWriter<impl Write>,
+) -> usize {
+    let text = fs::read_to_string(path).unwrap_or_else(|error| {
+        eprintln!("Failed to read '{}': {}", path, error);
+        process::exit(1);
+    });
+    let entries: Vec<Entry> = text
+        .lines()
+        .filter_map(|line| {
+            let sample = Sample::parse(line)?;
+            let (ply, halfmove_clock) = (binpack::fen_ply(line), binpack::fen_halfmove_clock(line));
+            binpack::annotate(agent, &sample, ply, halfmove_clock)
+        })
+        .collect();
+    write_entries(&entries, out);
+    entries.len()
+}
+
+fn write_entries(entries: &[Entry], out: &mut BinpackWriter<impl Write>) {
+    for entry in entries {
+        if let Err(error) = out.write(entry) {
+            eprintln!("Failed to write training data: {}", error);
+            process::exit(1);
+        }
+    }
+}
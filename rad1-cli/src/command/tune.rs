@@ -0,0 +1,186 @@
+use clap::{App, Arg, ArgMatches};
+use rad1::dataset::{self, Manifest, Sample};
+use rad1::eval::naive::NaiveEvaluator;
+use rad1::tuning::{self, GeneticTuner, Spsa};
+use std::fs;
+use std::path::Path;
+use std::process;
+
+pub fn tune_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Tune the search parameters with SPSA, or the evaluation with a genetic algorithm")
+        .arg(
+            Arg::with_name("iterations")
+                .long("iterations")
+                .short("n")
+                .required(false)
+                .takes_value(true)
+                .default_value("100")
+                .validator(is_positive)
+                .help("How many times the parameters are adjusted, or generations with --genetic"),
+        )
+        .arg(
+            Arg::with_name("genetic")
+                .long("genetic")
+                .required(false)
+                .takes_value(false)
+                .help("Evolve the weights of the naive evaluator instead of tuning the search"),
+        )
+        .arg(
+            Arg::with_name("population")
+                .long("population")
+                .short("p")
+                .required(false)
+                .takes_value(true)
+                .default_value("8")
+                .validator(is_positive)
+                .help("How many evaluation configs play a round robin every generation"),
+        )
+        .arg(
+            Arg::with_name("manifest")
+                .long("manifest")
+                .required(false)
+                .takes_value(true)
+                .requires("genetic")
+                .help("Manifest of a split dataset, reports how well the best weights predict its validation and test sets"),
+        )
+        .arg(
+            Arg::with_name("games")
+                .long("games")
+                .short("g")
+                .required(false)
+                .takes_value(true)
+                .default_value("2")
+                .validator(is_positive)
+                .help("How many openings SPSA plays with both colors every iteration"),
+        )
+        .arg(
+            Arg::with_name("depth")
+                .long("depth")
+                .short("d")
+                .required(false)
+                .takes_value(true)
+                .default_value("3")
+                .possible_values(&["1", "2", "3", "4", "5", "6"])
+                .hide_possible_values(true)
+                .help("The depth every move of the games is searched to"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .required(false)
+                .takes_value(true)
+                .default_value("rad1.conf")
+                .help("The config file the parameters are written to after every iteration"),
+        )
+}
+
+fn is_positive(value: String) -> Result<(), String> {
+    match value.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(()),
+        _ => Err(format!("'{}' isn't a positive number", value)),
+    }
+}
+
+pub fn exec(matches: &ArgMatches) {
+    let iterations: usize = matches.value_of("iterations").unwrap().parse().unwrap();
+    let depth: u8 = matches.value_of("depth").unwrap().parse().unwrap();
+    let output = matches.value_of("output").unwrap();
+    if matches.is_present("genetic") {
+        let population: usize = matches.value_of("population").unwrap().parse().unwrap();
+        let held_out = matches.value_of("manifest").map(held_out_samples);
+        tune_evaluation(iterations, population, depth, output, held_out);
+    } else {
+        let games: usize = matches.value_of("games").unwrap().parse().unwrap();
+        tune_search(iterations, games, depth, output);
+    }
+}
+
+fn tune_search(iterations: usize, games: usize, depth: u8, output: &str) {
+    let mut spsa = Spsa::new(tuning::search_parameters(), depth)
+        .unwrap()
+        .with_game_pairs(games);
+    for iteration in 1..=iterations {
+        let score = spsa.step();
+        let values: Vec<String> = spsa
+            .parameters()
+            .iter()
+            .map(|p| format!("{} {:.2}", p.name, p.value))
+            .collect();
+        println!(
+            "{:>4}  score {:.2}  {}",
+            iteration,
+            score,
+            values.join("  ")
+        );
+        if let Err(error) = fs::write(output, spsa.config()) {
+            eprintln!("Failed to write '{}': {}", output, error);
+        }
+    }
+    println!(
+        "Wrote the tuned parameters to {}, load them with --config",
+        output
+    );
+}
+
+// The validation and test samples of the manifest's split
+fn held_out_samples(path: &str) -> (Vec<Sample>, Vec<Sample>) {
+    let manifest = Manifest::load(Path::new(path)).unwrap_or_else(|error| {
+        eprintln!("Failed to load '{}': {}", path, error);
+        process::exit(1);
+    });
+    let load = |set: &Path| {
+        dataset::load_samples(set).unwrap_or_else(|error| {
+            eprintln!("Failed to read '{}': {}", set.display(), error);
+            process::exit(1);
+        })
+    };
+    (load(&manifest.validation), load(&manifest.test))
+}
+
+fn tune_evaluation(
+    generations: usize,
+    population: usize,
+    depth: u8,
+    output: &str,
+    held_out: Option<(Vec<Sample>, Vec<Sample>)>,
+) {
+    let mut tuner = GeneticTuner::new(population, depth);
+    let mut best = tuner.population()[0];
+    for generation in 1..=generations {
+        let ranked = tuner.generation();
+        let share = ranked[0].1;
+        best = ranked[0].0;
+        let weights: Vec<String> = best.weights().iter().map(|w| w.to_string()).collect();
+        let validation = match &held_out {
+            Some((validation, _)) => format!(
+                "  validation error {:.5}",
+                dataset::prediction_error(validation, &NaiveEvaluator::with_config(best))
+            ),
+            None => String::new(),
+        };
+        println!(
+            "{:>4}  best {:.2}  weights {}{}",
+            generation,
+            share,
+            weights.join(" "),
+            validation
+        );
+        if let Err(error) = fs::write(output, best.to_string()) {
+            eprintln!("Failed to write '{}': {}", output, error);
+        }
+    }
+    if let Some((_, test)) = &held_out {
+        println!(
+            "test error {:.5}",
+            dataset::prediction_error(test, &NaiveEvaluator::with_config(best))
+        );
+    }
+    println!(
+        "Wrote the best weights to {}, load them with --eval tuned:{}",
+        output, output
+    );
+}
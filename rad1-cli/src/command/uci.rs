@@ -0,0 +1,53 @@
+use clap::{App, Arg, ArgMatches};
+use rad1::agent;
+use rad1::uci::Engine;
+use std::io::{self, BufRead};
+
+pub fn uci_app(command_name: &str) -> App<'static, 'static> {
+    App::new(command_name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Run the engine over the UCI protocol on stdin and stdout, for GUIs like Cute Chess and Arena")
+        .arg(
+            Arg::with_name("depth")
+                .long("depth")
+                .short("d")
+                .required(false)
+                .takes_value(true)
+                .default_value("8")
+                .possible_values(&["1", "2", "3", "4", "5", "6", "7", "8", "9", "10"])
+                .hide_possible_values(true)
+                .help("The depth of go commands without a depth, time or infinite"),
+        )
+        .arg(super::eval_arg())
+        .arg(super::disable_arg())
+        .arg(super::config_arg())
+        .arg(super::set_arg())
+}
+
+pub fn exec(matches: &ArgMatches) {
+    let depth: u8 = matches.value_of("depth").unwrap().parse().unwrap();
+    let mut options = super::engine_options(matches);
+    // the agent gets its evaluator from the options, which the GUI is told
+    super::set_eval_file(matches, &mut options);
+    let mut engine = Engine::new(
+        depth,
+        options,
+        agent::alpha_beta_agent(depth, super::transposition_table()),
+        io::stdout(),
+    );
+    for line in io::stdin().lock().lines() {
+        match line {
+            Ok(line) => {
+                if !engine.handle(&line) {
+                    break;
+                }
+            }
+            Err(error) => {
+                eprintln!("Failed to read a command: {}", error);
+                break;
+            }
+        }
+    }
+    engine.finish();
+}
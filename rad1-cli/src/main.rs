@@ -2,29 +2,141 @@ use clap::{App, AppSettings};
 
 mod command;
 
+use command::ablate;
 use command::analyze;
+use command::annotate;
+use command::bench;
+use command::bench_eval;
+use command::calibrate;
+use command::check;
+use command::compare_eval;
+use command::correspond;
+use command::epd;
+use command::export_features;
+use command::index_games;
+use command::jsonrpc;
+use command::opening_stats;
+use command::options;
 use command::play;
+use command::prep;
+use command::show_pst;
+use command::split;
+use command::stats;
+use command::suite;
+use command::training_data;
+use command::tune;
+use command::uci;
 
+const ABLATE_COMMAND: &str = "ablate";
 const ANALYZE_COMMAND: &str = "analyze";
+const ANNOTATE_COMMAND: &str = "annotate";
+const BENCH_COMMAND: &str = "bench";
+const BENCH_EVAL_COMMAND: &str = "bench-eval";
+const CALIBRATE_COMMAND: &str = "calibrate";
+const CHECK_COMMAND: &str = "check";
+const COMPARE_EVAL_COMMAND: &str = "compare-eval";
+const CORRESPOND_COMMAND: &str = "correspond";
+const EPD_TO_FEN_COMMAND: &str = "epd-to-fen";
+const EXPORT_FEATURES_COMMAND: &str = "export-features";
+const FEN_TO_EPD_COMMAND: &str = "fen-to-epd";
+const INDEX_GAMES_COMMAND: &str = "index-games";
+const JSONRPC_COMMAND: &str = "jsonrpc";
+const OPENING_STATS_COMMAND: &str = "opening-stats";
+const OPTIONS_COMMAND: &str = "options";
 const PLAY_COMMAND: &str = "play";
+const PREP_COMMAND: &str = "prep";
+const SHOW_PST_COMMAND: &str = "show-pst";
+const SPLIT_COMMAND: &str = "split";
+const STATS_COMMAND: &str = "stats";
+const SUITE_COMMAND: &str = "suite";
+const TRAINING_DATA_COMMAND: &str = "training-data";
+const TUNE_COMMAND: &str = "tune";
+const UCI_COMMAND: &str = "uci";
 
 fn main() {
+    let ablate_app = ablate::ablate_app(ABLATE_COMMAND);
     let analyze_app = analyze::analyze_app(ANALYZE_COMMAND);
+    let annotate_app = annotate::annotate_app(ANNOTATE_COMMAND);
+    let bench_app = bench::bench_app(BENCH_COMMAND);
+    let bench_eval_app = bench_eval::bench_eval_app(BENCH_EVAL_COMMAND);
+    let calibrate_app = calibrate::calibrate_app(CALIBRATE_COMMAND);
+    let check_app = check::check_app(CHECK_COMMAND);
+    let compare_eval_app = compare_eval::compare_eval_app(COMPARE_EVAL_COMMAND);
+    let correspond_app = correspond::correspond_app(CORRESPOND_COMMAND);
+    let epd_to_fen_app = epd::epd_to_fen_app(EPD_TO_FEN_COMMAND);
+    let export_features_app = export_features::export_features_app(EXPORT_FEATURES_COMMAND);
+    let fen_to_epd_app = epd::fen_to_epd_app(FEN_TO_EPD_COMMAND);
+    let index_games_app = index_games::index_games_app(INDEX_GAMES_COMMAND);
+    let jsonrpc_app = jsonrpc::jsonrpc_app(JSONRPC_COMMAND);
+    let opening_stats_app = opening_stats::opening_stats_app(OPENING_STATS_COMMAND);
+    let options_app = options::options_app(OPTIONS_COMMAND);
     let play_app = play::play_app(PLAY_COMMAND);
+    let prep_app = prep::prep_app(PREP_COMMAND);
+    let show_pst_app = show_pst::show_pst_app(SHOW_PST_COMMAND);
+    let split_app = split::split_app(SPLIT_COMMAND);
+    let stats_app = stats::stats_app(STATS_COMMAND);
+    let suite_app = suite::suite_app(SUITE_COMMAND);
+    let training_data_app = training_data::training_data_app(TRAINING_DATA_COMMAND);
+    let tune_app = tune::tune_app(TUNE_COMMAND);
+    let uci_app = uci::uci_app(UCI_COMMAND);
     let matches = App::new("Rad1 Chess Engine CLI")
         .version(env!("CARGO_PKG_VERSION"))
         .author(env!("CARGO_PKG_AUTHORS"))
         .about(env!("CARGO_PKG_DESCRIPTION"))
         .setting(AppSettings::SubcommandRequired)
+        .subcommand(ablate_app)
         .subcommand(analyze_app)
+        .subcommand(annotate_app)
+        .subcommand(bench_app)
+        .subcommand(bench_eval_app)
+        .subcommand(calibrate_app)
+        .subcommand(check_app)
+        .subcommand(compare_eval_app)
+        .subcommand(correspond_app)
+        .subcommand(epd_to_fen_app)
+        .subcommand(export_features_app)
+        .subcommand(fen_to_epd_app)
+        .subcommand(index_games_app)
+        .subcommand(jsonrpc_app)
+        .subcommand(opening_stats_app)
+        .subcommand(options_app)
         .subcommand(play_app)
+        .subcommand(prep_app)
+        .subcommand(show_pst_app)
+        .subcommand(split_app)
+        .subcommand(stats_app)
+        .subcommand(suite_app)
+        .subcommand(training_data_app)
+        .subcommand(tune_app)
+        .subcommand(uci_app)
         .get_matches();
 
-    if let Some(subcommand) = matches.subcommand_name() {
-        if subcommand == ANALYZE_COMMAND {
-            analyze::exec(matches.subcommand_matches(ANALYZE_COMMAND).unwrap());
-        } else {
-            play::exec(matches.subcommand_matches(PLAY_COMMAND).unwrap());
-        }
+    match matches.subcommand() {
+        (ABLATE_COMMAND, Some(sub_matches)) => ablate::exec(sub_matches),
+        (ANALYZE_COMMAND, Some(sub_matches)) => analyze::exec(sub_matches),
+        (ANNOTATE_COMMAND, Some(sub_matches)) => annotate::exec(sub_matches),
+        (BENCH_COMMAND, Some(sub_matches)) => bench::exec(sub_matches),
+        (BENCH_EVAL_COMMAND, Some(sub_matches)) => bench_eval::exec(sub_matches),
+        (CALIBRATE_COMMAND, Some(sub_matches)) => calibrate::exec(sub_matches),
+        (CHECK_COMMAND, Some(sub_matches)) => check::exec(sub_matches),
+        (COMPARE_EVAL_COMMAND, Some(sub_matches)) => compare_eval::exec(sub_matches),
+        (CORRESPOND_COMMAND, Some(sub_matches)) => correspond::exec(sub_matches),
+        (EPD_TO_FEN_COMMAND, Some(sub_matches)) => epd::exec_epd_to_fen(sub_matches),
+        (EXPORT_FEATURES_COMMAND, Some(sub_matches)) => export_features::exec(sub_matches),
+        (FEN_TO_EPD_COMMAND, Some(sub_matches)) => epd::exec_fen_to_epd(sub_matches),
+        (INDEX_GAMES_COMMAND, Some(sub_matches)) => index_games::exec(sub_matches),
+        (JSONRPC_COMMAND, Some(sub_matches)) => jsonrpc::exec(sub_matches),
+        (OPENING_STATS_COMMAND, Some(sub_matches)) => opening_stats::exec(sub_matches),
+        (OPTIONS_COMMAND, Some(sub_matches)) => options::exec(sub_matches),
+        (PLAY_COMMAND, Some(sub_matches)) => play::exec(sub_matches),
+        (PREP_COMMAND, Some(sub_matches)) => prep::exec(sub_matches),
+        (SHOW_PST_COMMAND, Some(sub_matches)) => show_pst::exec(sub_matches),
+        (SPLIT_COMMAND, Some(sub_matches)) => split::exec(sub_matches),
+        (STATS_COMMAND, Some(sub_matches)) => stats::exec(sub_matches),
+        (SUITE_COMMAND, Some(sub_matches)) => suite::exec(sub_matches),
+        (TRAINING_DATA_COMMAND, Some(sub_matches)) => training_data::exec(sub_matches),
+        (TUNE_COMMAND, Some(sub_matches)) => tune::exec(sub_matches),
+        (UCI_COMMAND, Some(sub_matches)) => uci::exec(sub_matches),
+        _ => unreachable!("a subcommand is required"),
     }
 }
@@ -0,0 +1,97 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rad1::agent;
+use rad1::eval;
+use rad1::eval::Evaluator;
+use rad1::tt::TranspositionTable;
+use rad1::{ChessMove, Position, Square};
+use std::str::FromStr;
+
+const TT_SIZE: usize = 1_000_000;
+
+const MIDDLEGAME: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+const TACTICAL: [&str; 3] = [
+    KIWIPETE,
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "r3k2r/1p3pp1/p1p4p/3pP3/1PP5/P2P1P2/2qnKQ1P/8 b kq - 7 28",
+];
+
+fn position(fen: &str) -> Position {
+    Position::from_str(fen).unwrap()
+}
+
+fn sorted_moves(c: &mut Criterion) {
+    let position = position(KIWIPETE);
+    let hint = Some(ChessMove::new(Square::E2, Square::A6, None));
+    c.bench_function("sorted_moves", |b| {
+        b.iter(|| black_box(&position).sorted_moves(black_box(hint)))
+    });
+    c.bench_function("sorted_captures", |b| {
+        b.iter(|| black_box(&position).sorted_captures())
+    });
+}
+
+fn transposition_table(c: &mut Criterion) {
+    let tt = TranspositionTable::<i16>::new(TT_SIZE);
+    let position = position(MIDDLEGAME);
+    let chess_move = ChessMove::new(Square::C4, Square::F7, None);
+    c.bench_function("tt_store", |b| {
+        b.iter(|| {
+            tt.update_evaluation_and_best_move(
+                black_box(&position),
+                4,
+                rad1::tt::NodeValue::pv_node(12),
+                Some(chess_move),
+            )
+        })
+    });
+    c.bench_function("tt_probe", |b| {
+        b.iter(|| tt.get_evaluation_and_depth(black_box(&position)))
+    });
+    c.bench_function("tt_best_move", |b| {
+        b.iter(|| tt.best_move(black_box(&position)))
+    });
+}
+
+fn evaluate(c: &mut Criterion) {
+    let evaluator = eval::naive_evaluator();
+    let position = position(MIDDLEGAME);
+    c.bench_function("naive_evaluate", |b| {
+        b.iter(|| evaluator.evaluate(black_box(&position)))
+    });
+}
+
+fn quiescence(c: &mut Criterion) {
+    let positions: Vec<Position> = TACTICAL.iter().map(|fen| position(fen)).collect();
+    let agent = agent::alpha_beta_agent(1, TranspositionTable::new(TT_SIZE));
+    c.bench_function("q_search", |b| {
+        b.iter(|| {
+            for position in positions.iter() {
+                black_box(agent.quiescence_search(position));
+            }
+        })
+    });
+}
+
+fn search(c: &mut Criterion) {
+    let position = position(MIDDLEGAME);
+    let mut group = c.benchmark_group("search");
+    group.sample_size(10);
+    group.bench_function("depth_6", |b| {
+        b.iter(|| {
+            let agent = agent::alpha_beta_agent(6, TranspositionTable::new(TT_SIZE));
+            agent.search(black_box(&position))
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    sorted_moves,
+    transposition_table,
+    evaluate,
+    quiescence,
+    search
+);
+criterion_main!(benches);
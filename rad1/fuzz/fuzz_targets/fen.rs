@@ -0,0 +1,23 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use rad1::{fen, pgn};
+
+fuzz_target!(|data: &[u8]| {
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+    let position = match fen::parse(text) {
+        Ok(position) => position,
+        Err(_) => return,
+    };
+    // a parsed position is written as a FEN that reads back the same
+    let written = position.to_string();
+    let reread = fen::parse(&written).expect("a written FEN doesn't parse");
+    assert_eq!(reread.to_string(), written);
+    assert_eq!(reread.get_hash(), position.get_hash());
+    for chess_move in position.legal_moves_iter() {
+        let san = pgn::san(&position, chess_move);
+        assert_eq!(pgn::parse_san(&position, &san), Some(chess_move));
+    }
+});
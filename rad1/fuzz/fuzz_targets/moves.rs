@@ -0,0 +1,33 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use rad1::{fen, pgn};
+
+// A FEN on the first line, or the starting position if it isn't one, and
+// moves in UCI or SAN on the next ones
+fuzz_target!(|data: &[u8]| {
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+    let mut lines = text.lines();
+    let mut position = lines
+        .next()
+        .and_then(|line| fen::parse(line).ok())
+        .unwrap_or_default();
+    for line in lines {
+        let chess_move = match position.parse_move(line) {
+            Some(chess_move) => chess_move,
+            None => continue,
+        };
+        assert!(position.legal(chess_move));
+        assert_eq!(
+            position.parse_move(&pgn::san(&position, chess_move)),
+            Some(chess_move)
+        );
+        assert_eq!(
+            position.parse_move(&chess_move.to_string()),
+            Some(chess_move)
+        );
+        position = position.make_move_new(chess_move);
+    }
+});
@@ -0,0 +1,25 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use rad1::pgn;
+
+fuzz_target!(|data: &[u8]| {
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+    for game in pgn::read_games(text) {
+        let _ = game.outcome();
+        let chess_game = match game.to_game() {
+            Some(chess_game) => chess_game,
+            None => continue,
+        };
+        // a game that was read is written as PGN that reads back the same moves
+        let written = pgn::to_pgn(&chess_game);
+        let reread = pgn::read_games(&written);
+        assert_eq!(reread.len(), 1, "{}", written);
+        let reread = reread[0]
+            .to_game()
+            .expect("a written game doesn't read back");
+        assert_eq!(reread.moves(), chess_game.moves());
+    }
+});
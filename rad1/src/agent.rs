@@ -1,10 +1,23 @@
+use crate::options::EngineOptions;
 use crate::tt::TranspositionTable;
 use crate::Action;
 use crate::ChessGame;
+use crate::ParseError;
+use crate::Position;
+use std::str::FromStr;
+use std::time::Duration;
 
 mod ab;
+mod affinity;
 mod cli;
 mod random;
+mod stack;
+
+pub use ab::AlphaBetaChessAgent;
+pub(crate) use ab::MAX_DEPTH;
+
+// Depth cap of quick_best_move, the time limit usually stops the search first
+const QUICK_MAX_DEPTH: u8 = 32;
 
 /// A ChessAgent determines what [`Action`] to take given the
 /// current state of the chess game
@@ -37,10 +50,55 @@ pub fn random_chess_agent() -> random::RandomChessAgent {
 ///
 /// This is mainly used for playing against the computer from the terminal.
 pub fn command_line_agent() -> cli::CommandLineAgent {
-    cli::CommandLineAgent::default()
+    cli::CommandLineAgent
 }
 
 /// Returns the main [`ChessAgent`] used by this Chess Engine.
-pub fn alpha_beta_agent(depth: u8, tt: TranspositionTable<i16>) -> ab::AlphaBetaChessAgent {
-    ab::AlphaBetaChessAgent::new(depth, tt)
+pub fn alpha_beta_agent(depth: u8, tt: TranspositionTable<i16>) -> AlphaBetaChessAgent {
+    AlphaBetaChessAgent::new(depth, tt)
+}
+
+/// Find a good move for the position given as a FEN without setting up an agent.
+///
+/// The search stops after at most `millis` milliseconds and the move
+/// is returned in UCI notation (e.g. `e2e4` or `e7e8q`).
+/// Returns `Ok(None)` if the game is already over.
+///
+/// # Arguments
+///
+/// * `fen` - The Forsyth-Edwards Notation (FEN) of the position
+/// * `millis` - The time limit of the search in milliseconds
+pub fn quick_best_move(fen: &str, millis: u64) -> Result<Option<String>, ParseError> {
+    let position = Position::from_str(fen)?;
+    if position.status() != crate::PositionStatus::Ongoing {
+        return Ok(None);
+    }
+    let agent = AlphaBetaChessAgent::new(
+        QUICK_MAX_DEPTH,
+        TranspositionTable::with_megabytes(EngineOptions::default().hash_mb),
+    )
+    .with_move_time(Duration::from_millis(millis));
+    Ok(Some(agent.search(&position).best_move.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::quick_best_move;
+
+    #[test]
+    fn quick_best_move_finds_mate() {
+        let best_move = quick_best_move("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 200).unwrap();
+        assert_eq!(best_move.as_deref(), Some("a1a8"));
+    }
+
+    #[test]
+    fn quick_best_move_when_game_is_over() {
+        let best_move = quick_best_move("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", 200).unwrap();
+        assert_eq!(best_move, None);
+    }
+
+    #[test]
+    fn quick_best_move_invalid_fen() {
+        assert!(quick_best_move("not a fen", 200).is_err());
+    }
 }
@@ -1,46 +1,1417 @@
+use super::affinity;
+use super::stack::{SearchStack, MAX_PLY};
 use super::ChessAgent;
+use crate::eval::Evaluator;
+#[cfg(feature = "gaviota")]
+use crate::gaviota::Gaviota;
+use crate::learning::OpeningLearning;
 use crate::node::NodeValue;
+use crate::options::{EngineOptions, SplitMode, MAX_SKILL_LEVEL};
+use crate::polyglot::{self, Book};
+use crate::score::Score;
+use crate::search::progress::SearchProgress;
+use crate::search::trace::{LineTrace, TraceEvent};
+use crate::search::{PvLine, RootMove, ScoreBound, SearchOptions, SearchResult, SearchStats};
+use crate::strength::StrengthLimit;
+#[cfg(feature = "syzygy")]
+use crate::syzygy::Syzygy;
+use crate::tbgen;
+use crate::time::{MoveTimer, TimeControl, TimeManager};
 use crate::tt::*;
 use crate::Action;
 use crate::ChessGame;
 use crate::ChessMove;
 use crate::Position;
 use crate::PositionStatus;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::cell::{Cell, RefCell};
 use std::cmp;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Maximum depth of iterative deepening when searching on a clock
+pub(crate) const MAX_DEPTH: u8 = 64;
+// How many nodes are searched between checking the clock
+const NODES_BETWEEN_TIME_CHECKS: u64 = 1024;
+// Longest sleep while waiting for the move delay, so the stop flag is still noticed
+const MAX_DELAY_SLEEP: Duration = Duration::from_millis(10);
+// An iteration taking this many times the nodes its predecessors predicted
+// is a search explosion
+const EXPLOSION_FACTOR: u64 = 4;
+// Iterations below this many nodes are too noisy to call explosions
+const MIN_EXPLOSION_NODES: u64 = 100_000;
+// Shallowest iteration whose root moves are split between threads
+const MIN_SPLIT_DEPTH: u8 = 2;
+// Shallowest iteration searched with a window around the score of the one
+// before it, and how far the window reaches either way at first
+const ASPIRATION_MIN_DEPTH: u8 = 4;
+const ASPIRATION_WINDOW: i16 = 5;
+// Shallowest node below the root whose moves are split between threads in
+// YBWC mode, smaller subtrees are searched faster than they are handed over
+const MIN_NODE_SPLIT_DEPTH: u8 = 4;
 
 pub struct AlphaBetaChessAgent {
     depth: u8,
     tt: Arc<TranspositionTable<i16>>,
+    evaluator: Box<dyn Evaluator<Result = i16> + Send + Sync>,
+    // threads that search root moves next to the one calling `search`
+    pool: Option<ThreadPool>,
+    // CPU the thread calling `search` is pinned to
+    pinned_cpu: Option<usize>,
+    time_manager: Option<RefCell<TimeManager>>,
+    move_time: Option<Duration>,
+    stop: Option<Arc<AtomicBool>>,
+    progress: Option<Arc<SearchProgress>>,
+    stack: RefCell<SearchStack>,
+    options: EngineOptions,
+    search_moves: Vec<ChessMove>,
+    excluded_moves: Vec<ChessMove>,
+    learning: Option<OpeningLearning>,
+    book: Option<Book>,
+    #[cfg(feature = "syzygy")]
+    syzygy: Option<Syzygy>,
+    #[cfg(feature = "gaviota")]
+    gaviota: Option<Gaviota>,
+    // ply of the game at the last move and how many moves in a row scored
+    // low enough to resign
+    resign_streak: Cell<(usize, u8)>,
 }
 
 impl AlphaBetaChessAgent {
     pub fn new(depth: u8, tt: TranspositionTable<i16>) -> Self {
+        Self::from_table(depth, Arc::new(tt))
+    }
+
+    /// An agent configured with `options` that stores positions in a table
+    /// shared with other agents. The table is taken to have the size the
+    /// options ask for, so none is allocated for them.
+    pub fn sharing_table(
+        depth: u8,
+        tt: Arc<TranspositionTable<i16>>,
+        options: EngineOptions,
+    ) -> Self {
+        let mut agent = Self::from_table(depth, tt);
+        agent.options.hash_mb = options.hash_mb;
+        agent.options.large_pages = options.large_pages;
+        agent.options.numa_interleave = options.numa_interleave;
+        agent.set_options(options);
+        agent
+    }
+
+    fn from_table(depth: u8, tt: Arc<TranspositionTable<i16>>) -> Self {
         AlphaBetaChessAgent {
             depth,
-            tt: Arc::new(tt),
+            tt,
+            evaluator: Box::new(crate::eval::naive_evaluator()),
+            pool: None,
+            pinned_cpu: None,
+            time_manager: None,
+            move_time: None,
+            stop: None,
+            progress: None,
+            stack: RefCell::new(SearchStack::default()),
+            options: EngineOptions::default(),
+            search_moves: Vec::new(),
+            excluded_moves: Vec::new(),
+            learning: None,
+            book: None,
+            #[cfg(feature = "syzygy")]
+            syzygy: None,
+            #[cfg(feature = "gaviota")]
+            gaviota: None,
+            resign_streak: Cell::new((0, 0)),
+        }
+    }
+
+    /// Only consider the given moves at the root of the search.
+    pub fn with_search_moves(mut self, search_moves: Vec<ChessMove>) -> Self {
+        self.search_moves = search_moves;
+        self
+    }
+
+    /// Restrict the root moves of the following searches, an empty list allows every move.
+    ///
+    /// Moves that aren't legal in the searched position are ignored and if none
+    /// of them are legal the whole list is.
+    pub fn set_search_moves(&mut self, search_moves: Vec<ChessMove>) {
+        self.search_moves = search_moves;
+    }
+
+    /// Never play the given moves at the root of the search.
+    pub fn with_excluded_moves(mut self, excluded_moves: Vec<ChessMove>) -> Self {
+        self.excluded_moves = excluded_moves;
+        self
+    }
+
+    /// Ban moves at the root of the following searches to find the best alternative.
+    ///
+    /// If every legal move is excluded the list is ignored.
+    pub fn set_excluded_moves(&mut self, excluded_moves: Vec<ChessMove>) {
+        self.excluded_moves = excluded_moves;
+    }
+
+    /// Steer away from the openings the engine kept losing with: root moves
+    /// into positions with a [learned penalty](OpeningLearning::penalty) are
+    /// scored that much worse.
+    pub fn with_learning(mut self, learning: OpeningLearning) -> Self {
+        self.learning = Some(learning);
+        self
+    }
+
+    /// Play the moves of the opening book instead of searching while the
+    /// `OwnBook` option is set and the book has a move for the position.
+    /// Setting [`EngineOptions::book_file`] replaces the book.
+    pub fn with_book(mut self, book: Book) -> Self {
+        self.book = Some(book);
+        self
+    }
+
+    /// Change the deepest iteration of the following searches.
+    pub fn set_depth(&mut self, depth: u8) {
+        self.depth = depth;
+    }
+
+    /// Configure the agent with the given [`EngineOptions`].
+    pub fn with_options(mut self, options: EngineOptions) -> Self {
+        self.tt = Arc::new(TranspositionTable::with_memory(
+            options.hash_mb,
+            options.table_memory(),
+        ));
+        self.set_options(options);
+        self
+    }
+
+    pub fn options(&self) -> &EngineOptions {
+        &self.options
+    }
+
+    /// Forget every position in the transposition table,
+    /// so the next search doesn't depend on the earlier ones.
+    pub fn clear_hash(&self) {
+        self.tt.clear();
+    }
+
+    /// The table the agent's searches store positions in, for example to
+    /// save a snapshot of a long analysis and restore it later
+    pub fn transposition_table(&self) -> &TranspositionTable<i16> {
+        &self.tt
+    }
+
+    /// The table the agent's searches store positions in, to share it with
+    /// another agent through [`AlphaBetaChessAgent::with_shared_table`] or
+    /// [`AlphaBetaChessAgent::sharing_table`]
+    pub fn shared_table(&self) -> Arc<TranspositionTable<i16>> {
+        self.tt.clone()
+    }
+
+    /// Store positions in a table shared with other agents, for example one
+    /// searching in the background so this one finds its work later.
+    ///
+    /// Options that change the hash size replace the table with a new one,
+    /// [`AlphaBetaChessAgent::sharing_table`] keeps it.
+    pub fn with_shared_table(mut self, tt: Arc<TranspositionTable<i16>>) -> Self {
+        self.tt = tt;
+        self
+    }
+
+    /// Reconfigure the agent between searches.
+    ///
+    /// The transposition table is only reallocated (and so cleared)
+    /// when its size or the way its memory is allocated changes. An evaluator
+    /// that fails to load leaves the current one in place, and its key in
+    /// [`EngineOptions::eval_file`]. So does a book that can't be read.
+    ///
+    /// With the `OwnBook` option the move of the opening book with the
+    /// highest weight is played without a search, as long as the book has
+    /// one for the position.
+    ///
+    /// In deterministic mode the evaluator is made deterministic and the
+    /// transposition table is cleared before every search, so searching a
+    /// position to the same depth always visits the same nodes and finds the
+    /// same move. Searches cut short by a clock or a stop flag still depend on
+    /// when they were stopped.
+    ///
+    /// With more than one thread the moves at the root are split between a
+    /// thread pool after the first one. Which thread finds what first changes
+    /// from search to search, so deterministic searches and searches limited
+    /// to a number of nodes still run on a single thread.
+    ///
+    /// Pinned threads stay on the CPUs they are given, one per physical core
+    /// as long as there are enough, starting with the thread calling
+    /// [`AlphaBetaChessAgent::search`]. That thread stays pinned afterwards.
+    pub fn set_options(&mut self, mut options: EngineOptions) {
+        if options.hash_mb != self.options.hash_mb
+            || options.table_memory() != self.options.table_memory()
+        {
+            self.tt = Arc::new(TranspositionTable::with_memory(
+                options.hash_mb,
+                options.table_memory(),
+            ));
+        }
+        if options.threads != self.options.threads
+            || options.pin_threads != self.options.pin_threads
+        {
+            let cpus = if options.pin_threads {
+                affinity::search_cpus(options.threads)
+            } else {
+                Vec::new()
+            };
+            self.pinned_cpu = cpus.first().copied();
+            // a pool that can't be started leaves the search on one thread
+            self.pool = Some(options.threads)
+                .filter(|&threads| threads > 1)
+                .and_then(|threads| thread_pool(threads, cpus));
+        }
+        if options.eval_file != self.options.eval_file {
+            match crate::eval::registry().create(&options.eval_file) {
+                Ok(evaluator) => self.evaluator = evaluator,
+                // the options keep naming the evaluator in use
+                Err(_) => options.eval_file = self.options.eval_file.clone(),
+            }
+        }
+        if options.book_file != self.options.book_file {
+            match options.book_file.as_deref().map(Book::open) {
+                None => self.book = None,
+                Some(Ok(book)) => self.book = Some(book),
+                Some(Err(_)) => options.book_file = self.options.book_file.clone(),
+            }
+        }
+        #[cfg(feature = "syzygy")]
+        if options.syzygy_path != self.options.syzygy_path {
+            match options.syzygy_path.as_deref().map(Syzygy::open) {
+                None => self.syzygy = None,
+                Some(Ok(syzygy)) => self.syzygy = Some(syzygy),
+                Some(Err(_)) => options.syzygy_path = self.options.syzygy_path.clone(),
+            }
+        }
+        #[cfg(feature = "gaviota")]
+        if options.gaviota_tb_path != self.options.gaviota_tb_path {
+            match options.gaviota_tb_path.as_deref().map(Gaviota::open) {
+                None => self.gaviota = None,
+                Some(Ok(gaviota)) => self.gaviota = Some(gaviota),
+                Some(Err(_)) => options.gaviota_tb_path = self.options.gaviota_tb_path.clone(),
+            }
+        }
+        self.evaluator.configure(&options);
+        if options.deterministic {
+            self.evaluator.make_deterministic();
+        }
+        if let Some(time_manager) = &self.time_manager {
+            time_manager
+                .borrow_mut()
+                .set_move_overhead(options.move_overhead);
+        }
+        self.options = options;
+    }
+
+    // The allowed moves at the root, or None if every move is allowed. In a
+    // tablebase position only the moves keeping its best outcome are.
+    fn root_moves(&self, position: &Position, halfmove_clock: u32) -> Option<Vec<ChessMove>> {
+        let allowed = self.allowed_moves(position);
+        let best = match self.tablebase_moves(position, halfmove_clock) {
+            Some(best) => best,
+            None => return allowed,
+        };
+        let moves = allowed.unwrap_or_else(|| position.sorted_moves(None));
+        let kept: Vec<ChessMove> = moves.iter().copied().filter(|m| best.contains(m)).collect();
+        if kept.is_empty() {
+            Some(moves)
+        } else {
+            Some(kept)
+        }
+    }
+
+    // The moves of the `SyzygyPath` tables that keep the position's best
+    // outcome under the fifty-move rule, None outside the tables
+    #[cfg(feature = "syzygy")]
+    fn tablebase_moves(&self, position: &Position, halfmove_clock: u32) -> Option<Vec<ChessMove>> {
+        self.syzygy.as_ref()?.root_moves(position, halfmove_clock)
+    }
+
+    #[cfg(not(feature = "syzygy"))]
+    fn tablebase_moves(
+        &self,
+        _position: &Position,
+        _halfmove_clock: u32,
+    ) -> Option<Vec<ChessMove>> {
+        None
+    }
+
+    // The moves `searchmoves` and the excluded moves allow, or None if every
+    // move is allowed
+    fn allowed_moves(&self, position: &Position) -> Option<Vec<ChessMove>> {
+        if self.search_moves.is_empty() && self.excluded_moves.is_empty() {
+            return None;
+        }
+        let mut moves = position.sorted_moves(None);
+        if moves.iter().any(|m| self.search_moves.contains(m)) {
+            moves.retain(|m| self.search_moves.contains(m));
+        }
+        moves.retain(|m| !self.excluded_moves.contains(m));
+        if moves.is_empty() {
+            None
+        } else {
+            Some(moves)
+        }
+    }
+
+    // The opening book's move for the position among the allowed moves, when
+    // the options ask for the book
+    fn book_move(&self, position: &Position, moves: &[ChessMove]) -> Option<ChessMove> {
+        let book = self.book.as_ref().filter(|_| self.options.own_book)?;
+        let book_moves = book.moves(position);
+        polyglot::best_of(
+            book_moves
+                .into_iter()
+                .filter(|book_move| moves.contains(&book_move.chess_move)),
+        )
+    }
+
+    // The deepest iteration allowed at the configured skill level and strength
+    fn max_depth(&self) -> u8 {
+        let depth = if self.options.skill_level >= MAX_SKILL_LEVEL {
+            self.depth
+        } else {
+            cmp::min(self.depth, 1 + self.options.skill_level / 2)
+        };
+        match self.strength_limit() {
+            Some(limit) => cmp::min(depth, limit.depth),
+            None => depth,
+        }
+    }
+
+    fn strength_limit(&self) -> Option<StrengthLimit> {
+        if self.options.limit_strength {
+            Some(StrengthLimit::for_elo(self.options.elo))
+        } else {
+            None
+        }
+    }
+
+    // At a limited strength, sometimes replace the best move with a random one.
+    // In deterministic mode the choice only depends on the position.
+    fn inject_error(&self, position: &Position, moves: &[ChessMove], result: &mut SearchResult) {
+        let limit = match self.strength_limit() {
+            Some(limit) => limit,
+            None => return,
+        };
+        let mut rng = if self.options.deterministic {
+            StdRng::seed_from_u64(position.get_hash())
+        } else {
+            StdRng::from_entropy()
+        };
+        if rng.gen_range(0..100) < limit.error_percent {
+            let chess_move = moves[rng.gen_range(0..moves.len())];
+            if chess_move != result.best_move {
+                result.best_move = chess_move;
+                result.pv = vec![chess_move];
+            }
+        }
+    }
+
+    /// Switch search features off or adjust them, see [`SearchOptions`].
+    pub fn with_search_options(mut self, search: SearchOptions) -> Self {
+        self.options.search = search;
+        self
+    }
+
+    /// Evaluate leaf nodes with the given [`Evaluator`] instead of the default one.
+    pub fn with_evaluator(
+        mut self,
+        evaluator: Box<dyn Evaluator<Result = i16> + Send + Sync>,
+    ) -> Self {
+        self.evaluator = evaluator;
+        self.evaluator.configure(&self.options);
+        self
+    }
+
+    /// Search on a clock instead of to a fixed depth.
+    ///
+    /// The depth given when creating the agent is no longer used, the search
+    /// deepens until the [`TimeManager`] decides the move has taken long enough.
+    pub fn with_time_control(mut self, time_control: TimeControl) -> Self {
+        self.depth = MAX_DEPTH;
+        self.time_manager = Some(RefCell::new(TimeManager::new(time_control)));
+        self
+    }
+
+    /// Spend at most `move_time` on every search.
+    ///
+    /// Unlike [`AlphaBetaChessAgent::with_time_control`] the depth given when
+    /// creating the agent still caps the search, so it can finish sooner.
+    pub fn with_move_time(mut self, move_time: Duration) -> Self {
+        self.move_time = Some(move_time);
+        self
+    }
+
+    /// Change the clock of the following searches, or search without one.
+    ///
+    /// Unlike [`AlphaBetaChessAgent::with_time_control`] this leaves the
+    /// depth alone, the clock starts over with the base time of the control.
+    pub fn set_time_control(&mut self, time_control: Option<TimeControl>) {
+        self.time_manager = time_control.map(|control| RefCell::new(TimeManager::new(control)));
+    }
+
+    /// Change the most time spent on every following search, or let them
+    /// take as long as their depth needs.
+    pub fn set_move_time(&mut self, move_time: Option<Duration>) {
+        self.move_time = move_time;
+    }
+
+    /// Stop searching as soon as the flag is set.
+    ///
+    /// The search returns the best move of the last completed iteration.
+    /// The flag is never cleared by the agent.
+    pub fn with_stop_flag(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    /// Keep the progress up to date while searching: the iteration, the
+    /// root move being searched, the nodes below every root move and the
+    /// result of the last completed iteration, for another thread to show.
+    pub fn with_progress(mut self, progress: Arc<SearchProgress>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Whether to accept the draw the opponent offered after a search of the
+    /// current position scored `score`: the score is at or below the
+    /// [`EngineOptions::draw_accept_threshold`]. Without an offer there is
+    /// nothing to accept.
+    pub fn accepts_draw(&self, game: &ChessGame, score: Score) -> bool {
+        game.draw_offered() && i16::from(score) <= self.options.draw_accept_threshold
+    }
+
+    /// Whether to give up the game after a search of its current position
+    /// scored `score`, following the [`ResignPolicy`] of the options: the
+    /// score has stayed low enough for the last moves of the agent.
+    /// Every call counts as a move of the game.
+    ///
+    /// [`ResignPolicy`]: crate::options::ResignPolicy
+    pub fn resigns(&self, game: &ChessGame, score: Score) -> bool {
+        let policy = self.options.resign;
+        let ply = game.moves().len();
+        let (last_ply, streak) = self.resign_streak.get();
+        // a game shorter than the last one is a new game
+        let streak = if ply < last_ply { 0 } else { streak };
+        let streak = if i16::from(score) <= -policy.threshold {
+            streak.saturating_add(1)
+        } else {
+            0
+        };
+        self.resign_streak.set((ply, streak));
+        policy.moves > 0 && streak >= policy.moves && game.fullmove_number() >= policy.min_move
+    }
+
+    /// Search the position and return the best move found
+    /// along with its evaluation and some statistics about the search.
+    ///
+    /// A position without legal moves is scored as mated or drawn at
+    /// depth 0, with an empty principal variation and a null best move.
+    pub fn search(&self, position: &Position) -> SearchResult {
+        self.search_with_info(position, |_| {})
+    }
+
+    /// Same as [`AlphaBetaChessAgent::search`] but `on_iteration` is called
+    /// with the intermediate result after every completed iteration.
+    pub fn search_with_info<F>(&self, position: &Position, on_iteration: F) -> SearchResult
+    where
+        F: FnMut(&SearchResult),
+    {
+        self.search_after(position, &[], 0, None, on_iteration)
+    }
+
+    /// Search the current position of the game.
+    ///
+    /// Unlike [`AlphaBetaChessAgent::search`] this knows the positions played
+    /// before, so moves that repeat one of them are scored as draws.
+    pub fn search_game(&self, game: &ChessGame) -> SearchResult {
+        self.search_game_with_info(game, |_| {})
+    }
+
+    /// Same as [`AlphaBetaChessAgent::search_game`] but `on_iteration` is called
+    /// with the intermediate result after every completed iteration.
+    pub fn search_game_with_info<F>(&self, game: &ChessGame, on_iteration: F) -> SearchResult
+    where
+        F: FnMut(&SearchResult),
+    {
+        let history = game.previous_hashes();
+        let position = game.current_position();
+        self.search_after(
+            &position,
+            &history,
+            game.halfmove_clock(),
+            None,
+            on_iteration,
+        )
+    }
+
+    /// Search the position like [`AlphaBetaChessAgent::search`] but only
+    /// down the moves of `line`, to see why the engine doesn't like it.
+    ///
+    /// Every node on the line has only the line's move searched and skips
+    /// the transposition table, the node at its end is searched as usual.
+    /// `on_event` gets what the search did at the nodes of the line, see
+    /// [`TraceEvent`]. The line is cut at its first illegal move and the
+    /// search runs on a single thread.
+    pub fn search_line<F>(
+        &self,
+        position: &Position,
+        line: &[ChessMove],
+        mut on_event: F,
+    ) -> SearchResult
+    where
+        F: FnMut(&TraceEvent),
+    {
+        let trace = LineTrace::new(position, line);
+        let result = self.search_after(position, &[], 0, Some(&trace), |_| {
+            trace.flush(&mut on_event)
+        });
+        // the events of an iteration that was stopped
+        trace.flush(&mut on_event);
+        result
+    }
+
+    // Search the position reached after the positions with the given hashes,
+    // the last `halfmove_clock` of them without a capture or pawn move
+    fn search_after<F>(
+        &self,
+        position: &Position,
+        history: &[u64],
+        halfmove_clock: u32,
+        trace: Option<&LineTrace>,
+        mut on_iteration: F,
+    ) -> SearchResult
+    where
+        F: FnMut(&SearchResult),
+    {
+        match &self.time_manager {
+            None => {
+                let timer = self.move_time.map(|t| MoveTimer::new(t, t));
+                self.iterative_deepening(
+                    position,
+                    history,
+                    halfmove_clock,
+                    timer,
+                    trace,
+                    &mut on_iteration,
+                )
+            }
+            Some(time_manager) => {
+                let timer = time_manager.borrow().start_move();
+                let result = self.iterative_deepening(
+                    position,
+                    history,
+                    halfmove_clock,
+                    Some(timer),
+                    trace,
+                    &mut on_iteration,
+                );
+                time_manager.borrow_mut().finish_move(result.elapsed);
+                result
+            }
+        }
+    }
+
+    /// Run only the quiescence search on the position with a full window
+    #[cfg(feature = "bench")]
+    #[doc(hidden)]
+    pub fn quiescence_search(&self, position: &Position) -> i16 {
+        let search = Search::new(&self.tt, self.evaluator.as_ref(), &self.stack, None, 0);
+        search.q_search(position, 0, Score::Mated(0).into(), Score::Mate(0).into())
+    }
+
+    fn iterative_deepening(
+        &self,
+        position: &Position,
+        history: &[u64],
+        halfmove_clock: u32,
+        mut timer: Option<MoveTimer>,
+        trace: Option<&LineTrace>,
+        on_iteration: &mut dyn FnMut(&SearchResult),
+    ) -> SearchResult {
+        let start = Instant::now();
+        if let Some(cpu) = self.pinned_cpu {
+            affinity::pin_current_thread(cpu);
+        }
+        let position = &self.evaluator.prepare(position);
+        if self.options.deterministic {
+            self.tt.clear();
+        }
+        self.tt.new_search();
+        self.stack.borrow_mut().clear();
+        let root_moves = self.root_moves(position, halfmove_clock);
+        let search = Search::new(
+            &self.tt,
+            self.evaluator.as_ref(),
+            &self.stack,
+            timer.as_ref().map(|t| t.deadline()),
+            self.options.contempt,
+        )
+        .with_root_moves(root_moves.as_deref().filter(|_| trace.is_none()))
+        .with_stop_flag(self.stop.as_deref())
+        .with_nps_limit(self.options.nps_limit)
+        .with_node_limit(self.strength_limit().map_or(0, |limit| limit.nodes))
+        .with_history(history, self.options.repetition_contempt)
+        .with_qsearch_limits(
+            self.options.qsearch_max_ply,
+            self.options.qsearch_node_limit,
+        )
+        .with_options(self.options.search)
+        .with_pool(
+            self.split_pool().filter(|_| trace.is_none()),
+            self.options.split_mode == SplitMode::Ybwc,
+        )
+        .with_learning(self.learning.as_ref())
+        .with_trace(trace)
+        .with_progress(self.progress.as_deref());
+        #[cfg(feature = "gaviota")]
+        let search = search.with_gaviota(self.gaviota.as_ref());
+        search.root_score.set(search.evaluate(position, 0));
+
+        let moves = match &root_moves {
+            Some(moves) => moves.clone(),
+            None => position.sorted_moves(self.tt.best_move(position)),
+        };
+        if let Some(progress) = &self.progress {
+            progress.start_search(moves.len());
+        }
+        let mut result = SearchResult {
+            best_move: moves.first().copied().unwrap_or_default(),
+            score: Score::DRAW,
+            bound: ScoreBound::Exact,
+            depth: 0,
+            seldepth: 0,
+            pv: moves.first().copied().into_iter().collect(),
+            nodes: 0,
+            elapsed: start.elapsed(),
+            stats: SearchStats::default(),
+            best_moves: Vec::new(),
+            root_moves: Vec::new(),
+            lines: Vec::new(),
+        };
+        if moves.is_empty() {
+            if position.status() == PositionStatus::Checkmate {
+                result.score = Score::Mated(0);
+            }
+            result.elapsed = start.elapsed();
+            return result;
+        }
+        // no need to think about forced moves when on a clock
+        if timer.is_some() && moves.len() == 1 {
+            self.delay_move(start, &timer);
+            result.elapsed = start.elapsed();
+            return result;
+        }
+        if let Some(book_move) = self.book_move(position, &moves).filter(|_| trace.is_none()) {
+            result.best_move = book_move;
+            result.pv = vec![book_move];
+            result.score = Score::from(search.root_score.get());
+            self.delay_move(start, &timer);
+            result.elapsed = start.elapsed();
+            return result;
+        }
+
+        let mut iteration_nodes = Vec::new();
+        for depth in 1..=self.max_depth() {
+            let nodes = search.nodes.get();
+            search.start_iteration(&iteration_nodes);
+            if let Some(trace) = trace {
+                trace.record(TraceEvent::Iteration { depth });
+            }
+            if let Some(progress) = &self.progress {
+                progress.start_iteration(depth);
+            }
+            let previous_score = result.score;
+            let (value, bound) =
+                match self.aspiration_search(&search, position, depth, &mut result, &mut timer) {
+                    Some(found) => found,
+                    None => break,
+                };
+            iteration_nodes.push(search.nodes.get() - nodes);
+            search.root_score.set(value);
+            result.best_move = search.best_root_move(position);
+            result.score = Score::from(value);
+            result.bound = bound;
+            result.depth = depth;
+            result.seldepth = search.seldepth.get() as u8;
+            result.pv = search.principal_variation(result.best_move);
+            search.complete_pv(position, &mut result.pv);
+            result.nodes = search.nodes.get();
+            result.elapsed = start.elapsed();
+            result.stats = search.stats.get();
+            result.best_moves.push(result.best_move);
+            result.root_moves = search.root_effort.borrow().clone();
+            if trace.is_none() {
+                self.search_lines(&search, position, depth, moves.len(), &mut result);
+            }
+            if let Some(progress) = &self.progress {
+                progress.finish_iteration(&result);
+            }
+            on_iteration(&result);
+            // a forced mate that held up for another iteration won't get any better
+            if let Score::Mate(_) = result.score {
+                if result.score == previous_score {
+                    break;
+                }
+            }
+            if let Some(timer) = timer.as_mut() {
+                timer.record_iteration(result.best_move, value);
+                if timer.should_stop() {
+                    break;
+                }
+            }
         }
+        result.nodes = search.nodes.get();
+        result.stats = search.stats.get();
+        result.root_moves = search.root_effort.borrow().clone();
+        self.inject_error(position, &moves, &mut result);
+        self.delay_move(start, &timer);
+        result.elapsed = start.elapsed();
+        result
+    }
+
+    // Search the root to the depth with a window around the score of the last
+    // iteration, widened towards the side the score fell out of until it
+    // falls inside. Shallow iterations and mate scores get the full window.
+    //
+    // A failed search leaves its bound in the result, with the move that
+    // failed high, in case the next one is stopped. Returns the value and
+    // its bound, or None when stopped.
+    fn aspiration_search(
+        &self,
+        search: &Search,
+        position: &Position,
+        depth: u8,
+        result: &mut SearchResult,
+        timer: &mut Option<MoveTimer>,
+    ) -> Option<(i16, ScoreBound)> {
+        let (min, max): (i16, i16) = (Score::Mated(0).into(), Score::Mate(0).into());
+        let mut delta = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = match result.score {
+            Score::Eval(_) if depth >= ASPIRATION_MIN_DEPTH => {
+                let score = i16::from(result.score);
+                (
+                    cmp::max(min, score.saturating_sub(delta)),
+                    cmp::min(max, score.saturating_add(delta)),
+                )
+            }
+            _ => (min, max),
+        };
+        loop {
+            let value = search.root_search(position, depth, alpha, beta);
+            if search.is_stopped() {
+                return None;
+            }
+            let bound = ScoreBound::of(value, alpha, beta);
+            if bound.is_exact() || (alpha == min && beta == max) {
+                return Some((value, bound));
+            }
+            let mut stats = search.stats.get();
+            stats.aspiration_researches += 1;
+            search.stats.set(stats);
+            delta = delta.saturating_mul(2);
+            result.score = Score::from(value);
+            result.bound = bound;
+            if bound == ScoreBound::Lower {
+                result.best_move = search.best_root_move(position);
+                result.pv = search.principal_variation(result.best_move);
+                beta = cmp::min(max, value.saturating_add(delta));
+            } else {
+                if let Some(timer) = timer.as_mut() {
+                    timer.record_fail_low();
+                }
+                alpha = cmp::max(min, value.saturating_sub(delta));
+            }
+        }
+    }
+
+    // Search the other lines the `MultiPV` option asks for to the depth of
+    // the iteration, each without the first moves of the lines before it.
+    // A stopped search keeps the lines of the last iteration it didn't get to.
+    fn search_lines(
+        &self,
+        search: &Search,
+        position: &Position,
+        depth: u8,
+        move_count: usize,
+        result: &mut SearchResult,
+    ) {
+        let count = cmp::min(usize::from(self.options.multi_pv), move_count);
+        let (min, max) = (Score::Mated(0).into(), Score::Mate(0).into());
+        let mut excluded = vec![result.best_move];
+        for index in 0..count.saturating_sub(1) {
+            *search.excluded_root_moves.borrow_mut() = excluded.clone();
+            let value = search.root_search(position, depth, min, max);
+            if search.is_stopped() {
+                break;
+            }
+            let best_move = search.best_root_move(position);
+            let line = PvLine {
+                score: Score::from(value),
+                pv: search.principal_variation(best_move),
+            };
+            match result.lines.get_mut(index) {
+                Some(old) => *old = line,
+                None => result.lines.push(line),
+            }
+            excluded.push(best_move);
+        }
+        search.excluded_root_moves.borrow_mut().clear();
+    }
+
+    // The pool to split the root moves between, unless the search has to be
+    // reproducible or count its nodes exactly
+    fn split_pool(&self) -> Option<&ThreadPool> {
+        let single_threaded = self.options.deterministic
+            || self.options.nps_limit > 0
+            || self.strength_limit().is_some();
+        self.pool.as_ref().filter(|_| !single_threaded)
+    }
+
+    // Wait until the move has taken at least the configured move delay,
+    // without running past the deadline or ignoring the stop flag
+    fn delay_move(&self, start: Instant, timer: &Option<MoveTimer>) {
+        let mut until = start + self.options.move_delay;
+        if let Some(timer) = timer {
+            until = cmp::min(until, timer.deadline());
+        }
+        loop {
+            let now = Instant::now();
+            let stopped = self
+                .stop
+                .as_ref()
+                .map_or(false, |stop| stop.load(Ordering::Relaxed));
+            if now >= until || stopped {
+                break;
+            }
+            thread::sleep(cmp::min(until - now, MAX_DELAY_SLEEP));
+        }
+    }
+}
+
+// The threads beside the one calling `search`, the first of `cpus` is that
+// thread's and the others are the pool's in order
+fn thread_pool(threads: usize, cpus: Vec<usize>) -> Option<ThreadPool> {
+    ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .start_handler(move |index| {
+            if let Some(&cpu) = cpus.get(index + 1) {
+                affinity::pin_current_thread(cpu);
+            }
+        })
+        .build()
+        .ok()
+}
+
+impl ChessAgent for AlphaBetaChessAgent {
+    fn get_action(&self, game: &ChessGame) -> Action {
+        let result = self.search_game(game);
+        if self.accepts_draw(game, result.score) {
+            Action::AcceptDraw
+        } else if self.resigns(game, result.score) {
+            Action::Resign(game.side_to_move())
+        } else {
+            Action::MakeMove(result.best_move)
+        }
+    }
+}
+
+// State shared by every node of a single search
+struct Search<'a> {
+    tt: &'a TranspositionTable<i16>,
+    evaluator: &'a (dyn Evaluator<Result = i16> + Sync),
+    stack: &'a RefCell<SearchStack>,
+    deadline: Option<Instant>,
+    contempt: i16,
+    root_moves: Option<&'a [ChessMove]>,
+    // root moves left out to search the other lines of a MultiPV search
+    excluded_root_moves: RefCell<Vec<ChessMove>>,
+    stop_flag: Option<&'a AtomicBool>,
+    // hashes of the positions played before the root
+    history: &'a [u64],
+    repetition_contempt: i16,
+    // value of the root for its side to move, from the last completed iteration
+    root_score: Cell<i16>,
+    nps_limit: u64,
+    // most nodes of the search, 0 for no limit
+    node_limit: u64,
+    start: Instant,
+    qsearch_max_ply: usize,
+    qsearch_node_limit: u64,
+    // ply and node count where the running quiescence search started
+    qsearch_start: Cell<(usize, u64)>,
+    stats: Cell<SearchStats>,
+    options: SearchOptions,
+    // threads to split the root moves between
+    pool: Option<&'a ThreadPool>,
+    // also split the moves of nodes below the root, see `split_moves`
+    split_nodes: bool,
+    // results of earlier games that make some root moves worse
+    learning: Option<&'a OpeningLearning>,
+    #[cfg(feature = "gaviota")]
+    gaviota: Option<&'a Gaviota>,
+    // the line the search is forced down, see `search_line`
+    trace: Option<&'a LineTrace>,
+    progress: Option<&'a SearchProgress>,
+    // nodes and time spent below every root move so far
+    root_effort: RefCell<Vec<RootMove>>,
+    // node count past which the running iteration is a search explosion, 0 for none
+    explosion_limit: Cell<u64>,
+    // the running iteration exploded, so the rest of it skips null windows
+    exploded: Cell<bool>,
+    nodes: Cell<u64>,
+    seldepth: Cell<usize>,
+    stopped: Cell<bool>,
+}
+
+// The parts of a search shared with the threads of a split root search,
+// which search their root moves with their own stacks
+struct Helper<'a> {
+    tt: &'a TranspositionTable<i16>,
+    evaluator: &'a (dyn Evaluator<Result = i16> + Sync),
+    deadline: Option<Instant>,
+    contempt: i16,
+    stop_flag: Option<&'a AtomicBool>,
+    history: &'a [u64],
+    repetition_contempt: i16,
+    root_score: i16,
+    qsearch_max_ply: usize,
+    qsearch_node_limit: u64,
+    options: SearchOptions,
+    pool: Option<&'a ThreadPool>,
+    split_nodes: bool,
+    learning: Option<&'a OpeningLearning>,
+    #[cfg(feature = "gaviota")]
+    gaviota: Option<&'a Gaviota>,
+    progress: Option<&'a SearchProgress>,
+    exploded: bool,
+}
+
+impl<'a> Helper<'a> {
+    fn search<'b>(&'b self, stack: &'b RefCell<SearchStack>) -> Search<'b> {
+        let mut search = Search::new(self.tt, self.evaluator, stack, self.deadline, self.contempt)
+            .with_stop_flag(self.stop_flag)
+            .with_history(self.history, self.repetition_contempt)
+            .with_options(self.options)
+            .with_pool(self.pool, self.split_nodes)
+            .with_learning(self.learning)
+            .with_progress(self.progress);
+        #[cfg(feature = "gaviota")]
+        {
+            search.gaviota = self.gaviota;
+        }
+        search.qsearch_max_ply = self.qsearch_max_ply;
+        search.qsearch_node_limit = self.qsearch_node_limit;
+        search.root_score.set(self.root_score);
+        search.exploded.set(self.exploded);
+        search
+    }
+}
+
+// The best root move found by the threads of a split root search so far,
+// and what they counted on the way
+struct SplitState {
+    alpha: i16,
+    best_move: ChessMove,
+    pv: Vec<ChessMove>,
+    nodes: u64,
+    seldepth: usize,
+    stats: SearchStats,
+    stopped: bool,
+    // the effort of every root move searched, when splitting the root
+    root_moves: Vec<RootMove>,
+}
+
+impl<'a> Search<'a> {
+    fn new(
+        tt: &'a TranspositionTable<i16>,
+        evaluator: &'a (dyn Evaluator<Result = i16> + Sync),
+        stack: &'a RefCell<SearchStack>,
+        deadline: Option<Instant>,
+        contempt: i16,
+    ) -> Self {
+        Self {
+            tt,
+            evaluator,
+            stack,
+            deadline,
+            contempt,
+            root_moves: None,
+            excluded_root_moves: RefCell::new(Vec::new()),
+            stop_flag: None,
+            history: &[],
+            repetition_contempt: 0,
+            root_score: Cell::new(0),
+            nps_limit: 0,
+            node_limit: 0,
+            start: Instant::now(),
+            qsearch_max_ply: MAX_PLY,
+            qsearch_node_limit: 0,
+            qsearch_start: Cell::new((0, 0)),
+            stats: Cell::new(SearchStats::default()),
+            options: SearchOptions::default(),
+            pool: None,
+            split_nodes: false,
+            learning: None,
+            #[cfg(feature = "gaviota")]
+            gaviota: None,
+            trace: None,
+            progress: None,
+            root_effort: RefCell::new(Vec::new()),
+            explosion_limit: Cell::new(0),
+            exploded: Cell::new(false),
+            nodes: Cell::new(0),
+            seldepth: Cell::new(0),
+            stopped: Cell::new(false),
+        }
+    }
+
+    fn with_root_moves(mut self, root_moves: Option<&'a [ChessMove]>) -> Self {
+        self.root_moves = root_moves;
+        self
+    }
+
+    fn with_stop_flag(mut self, stop_flag: Option<&'a AtomicBool>) -> Self {
+        self.stop_flag = stop_flag;
+        self
+    }
+
+    fn with_history(mut self, history: &'a [u64], repetition_contempt: i16) -> Self {
+        self.history = history;
+        self.repetition_contempt = repetition_contempt;
+        self
+    }
+
+    // Stop quiescence searches after `max_ply` plies or `node_limit` nodes, 0 for no node limit
+    fn with_qsearch_limits(mut self, max_ply: u8, node_limit: u64) -> Self {
+        self.qsearch_max_ply = max_ply as usize;
+        self.qsearch_node_limit = node_limit;
+        self
+    }
+
+    fn with_options(mut self, options: SearchOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    // Search at most `nps_limit` nodes per second, 0 for no limit
+    fn with_nps_limit(mut self, nps_limit: u64) -> Self {
+        self.nps_limit = nps_limit;
+        self
+    }
+
+    fn with_node_limit(mut self, node_limit: u64) -> Self {
+        self.node_limit = node_limit;
+        self
+    }
+
+    fn with_pool(mut self, pool: Option<&'a ThreadPool>, split_nodes: bool) -> Self {
+        self.pool = pool;
+        self.split_nodes = split_nodes;
+        self
+    }
+
+    fn with_learning(mut self, learning: Option<&'a OpeningLearning>) -> Self {
+        self.learning = learning;
+        self
+    }
+
+    #[cfg(feature = "gaviota")]
+    fn with_gaviota(mut self, gaviota: Option<&'a Gaviota>) -> Self {
+        self.gaviota = gaviota;
+        self
+    }
+
+    fn with_trace(mut self, trace: Option<&'a LineTrace>) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    fn with_progress(mut self, progress: Option<&'a SearchProgress>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    // The trace when the node at the ply is on the traced line. The node's
+    // position has to be recorded on the stack already.
+    fn trace_at(&self, ply: usize) -> Option<&'a LineTrace> {
+        self.trace
+            .filter(|trace| trace.follows(self.stack.borrow().hashes(ply)))
+    }
+
+    // The move a node on the traced line is forced to search
+    fn forced_move(&self, ply: usize) -> Option<ChessMove> {
+        self.trace_at(ply).and_then(|trace| trace.move_at(ply))
+    }
+
+    fn helper(&self) -> Helper<'a> {
+        Helper {
+            tt: self.tt,
+            evaluator: self.evaluator,
+            deadline: self.deadline,
+            contempt: self.contempt,
+            stop_flag: self.stop_flag,
+            history: self.history,
+            repetition_contempt: self.repetition_contempt,
+            root_score: self.root_score.get(),
+            qsearch_max_ply: self.qsearch_max_ply,
+            qsearch_node_limit: self.qsearch_node_limit,
+            options: self.options,
+            pool: self.pool,
+            split_nodes: self.split_nodes,
+            learning: self.learning,
+            #[cfg(feature = "gaviota")]
+            gaviota: self.gaviota,
+            progress: self.progress,
+            exploded: self.exploded.get(),
+        }
+    }
+
+    // Get ready for the next iteration, which should take about as many
+    // times the nodes of the last one as that one took of the one before
+    fn start_iteration(&self, iteration_nodes: &[u64]) {
+        self.exploded.set(false);
+        let limit = match iteration_nodes {
+            [.., before, last] if *before > 0 => {
+                let expected = last.saturating_mul(*last) / before;
+                let allowed = cmp::max(
+                    MIN_EXPLOSION_NODES,
+                    expected.saturating_mul(EXPLOSION_FACTOR),
+                );
+                self.nodes.get().saturating_add(allowed)
+            }
+            _ => 0,
+        };
+        self.explosion_limit.set(limit);
+    }
+
+    // Sleep until the search is no longer ahead of its nodes per second limit.
+    // Never sleeps past the deadline, which is checked right after.
+    fn throttle(&self, nodes: u64) {
+        let target = Duration::from_micros(nodes.saturating_mul(1_000_000) / self.nps_limit);
+        let now = Instant::now();
+        let mut until = self.start + target;
+        if let Some(deadline) = self.deadline {
+            until = cmp::min(until, deadline);
+        }
+        if until > now {
+            thread::sleep(until - now);
+        }
+    }
+
+    // A search restricted to some of the root moves, or to the move of a
+    // traced line, can't use or fill the transposition table entry of the node
+    fn is_restricted(&self, ply: usize) -> bool {
+        ply == 0 && (self.root_moves.is_some() || !self.excluded_root_moves.borrow().is_empty())
+            || self.forced_move(ply).is_some()
+    }
+
+    // The best move at the root after a completed iteration
+    fn best_root_move(&self, position: &Position) -> ChessMove {
+        match self.stack.borrow().pv(0).first() {
+            Some(&chess_move) => chess_move,
+            None => position.sorted_moves(self.tt.best_move(position))[0],
+        }
+    }
+
+    // Add the nodes and time spent on a root move to its total
+    fn record_root_move(&self, chess_move: ChessMove, nodes: u64, elapsed: Duration) {
+        let mut root_effort = self.root_effort.borrow_mut();
+        match root_effort.iter_mut().find(|m| m.chess_move == chess_move) {
+            Some(root_move) => {
+                root_move.nodes += nodes;
+                root_move.elapsed += elapsed;
+            }
+            None => root_effort.push(RootMove {
+                chess_move,
+                nodes,
+                elapsed,
+            }),
+        }
+    }
+
+    // Search a move of the node, keeping track of the effort when the node is the root
+    fn search_move(&self, ply: usize, chess_move: ChessMove, search: impl FnOnce() -> i16) -> i16 {
+        if ply > 0 {
+            return search();
+        }
+        if let Some(progress) = self.progress {
+            progress.start_root_move(chess_move);
+        }
+        let (nodes, start) = (self.nodes.get(), Instant::now());
+        let value = search();
+        let (nodes, elapsed) = (self.nodes.get() - nodes, start.elapsed());
+        self.record_root_move(chess_move, nodes, elapsed);
+        if let Some(progress) = self.progress {
+            progress.finish_root_move(chess_move, nodes, elapsed);
+        }
+        value
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.stopped.get()
+    }
+
+    // The principal variation of the last completed iteration
+    fn principal_variation(&self, best_move: ChessMove) -> Vec<ChessMove> {
+        let stack = self.stack.borrow();
+        match stack.pv(0).first() {
+            Some(&first) if first == best_move => stack.pv(0).to_vec(),
+            _ => vec![best_move],
+        }
+    }
+
+    // Play a principal variation ending in a position of the Gaviota tables
+    // on to mate, the way the tables do
+    #[cfg(feature = "gaviota")]
+    fn complete_pv(&self, position: &Position, pv: &mut Vec<ChessMove>) {
+        if let Some(gaviota) = self.gaviota {
+            let end = pv
+                .iter()
+                .fold(position.clone(), |position, &m| position.make_move_new(m));
+            pv.extend(gaviota.line(&end));
+        }
+    }
+
+    #[cfg(not(feature = "gaviota"))]
+    fn complete_pv(&self, _position: &Position, _pv: &mut Vec<ChessMove>) {}
+
+    // Count the node and check if the search has run out of time or was stopped.
+    // Values returned from a stopped search are meaningless and must
+    // not be stored in the transposition table.
+    fn visit_node(&self, ply: usize) -> bool {
+        if ply > self.seldepth.get() {
+            self.seldepth.set(ply);
+        }
+        if let Some(stop_flag) = self.stop_flag {
+            if stop_flag.load(Ordering::Relaxed) {
+                self.stopped.set(true);
+            }
+        }
+        let nodes = self.nodes.get() + 1;
+        self.nodes.set(nodes);
+        if self.nps_limit > 0 {
+            self.throttle(nodes);
+        }
+        if self.node_limit > 0 && nodes >= self.node_limit {
+            self.stopped.set(true);
+        }
+        if nodes % NODES_BETWEEN_TIME_CHECKS == 0 {
+            if let Some(progress) = self.progress {
+                progress.add_nodes(NODES_BETWEEN_TIME_CHECKS);
+            }
+            if let Some(deadline) = self.deadline {
+                if Instant::now() >= deadline {
+                    self.stopped.set(true);
+                }
+            }
+            let limit = self.explosion_limit.get();
+            if limit > 0 && nodes >= limit && !self.exploded.get() {
+                self.exploded.set(true);
+                let mut stats = self.stats.get();
+                stats.explosions += 1;
+                self.stats.set(stats);
+            }
+        }
+        self.stopped.get()
+    }
+
+    // Static evaluation with checkmates scored by their distance from the root
+    fn evaluate(&self, position: &Position, ply: usize) -> i16 {
+        let value = self.evaluator.evaluate(position);
+        Score::from_evaluation(self.evaluator, value, ply).into()
+    }
+
+    // The score of a position the built-in tablebases hold, when they are
+    // enabled, or the Gaviota tables of the `GaviotaTbPath` option
+    fn probe_tablebases(&self, position: &Position) -> Option<Score> {
+        let built_in = Some(position)
+            .filter(|_| self.options.tablebases)
+            .and_then(tbgen::probe);
+        built_in.or_else(|| self.probe_gaviota(position))
+    }
+
+    #[cfg(feature = "gaviota")]
+    fn probe_gaviota(&self, position: &Position) -> Option<Score> {
+        self.gaviota?.probe(position)
+    }
+
+    #[cfg(not(feature = "gaviota"))]
+    fn probe_gaviota(&self, _position: &Position) -> Option<Score> {
+        None
+    }
+
+    // Value of a draw for the side to move, the side to move at the root
+    // treats draws as slightly losing when contempt is positive
+    fn draw_score(&self, ply: usize) -> i16 {
+        Self::for_side_to_move(ply, -self.contempt)
+    }
+
+    // Value of a draw by repetition for the side to move. On top of contempt
+    // the side to move at the root avoids repeating while it is ahead
+    // and looks for a repetition while it is behind.
+    fn repetition_score(&self, ply: usize) -> i16 {
+        let advantage = self.root_score.get().signum();
+        Self::for_side_to_move(ply, -self.contempt - self.repetition_contempt * advantage)
+    }
+
+    // Turn a value for the side to move at the root into one for the side to move at the ply
+    fn for_side_to_move(ply: usize, root_value: i16) -> i16 {
+        if ply % 2 == 0 {
+            root_value
+        } else {
+            -root_value
+        }
+    }
+
+    // Record the position on the current path and return true if it was already
+    // reached before, either earlier on the path or in the game before the root
+    fn is_repetition(&self, position: &Position, ply: usize) -> bool {
+        let hash = position.get_hash();
+        let mut stack = self.stack.borrow_mut();
+        stack.set_hash(ply, hash);
+        stack.repeats(ply) || self.history.contains(&hash)
     }
 
     fn cached_evaluation(
-        tt: &TranspositionTable<i16>,
+        &self,
         position: &Position,
+        ply: usize,
         depth: u8,
         alpha: &mut i16,
         beta: &mut i16,
     ) -> Option<i16> {
-        match tt.get_evaluation_and_depth(position) {
+        // cut off with the deepest search, the shallower one only helps move ordering
+        match self.tt.get_evaluation_and_depth(position).deeper {
             None => None,
-            Some((cached_eval, evaluation_depth)) => {
-                if evaluation_depth >= depth {
-                    match cached_eval {
-                        NodeValue::Principal { value } => Some(value),
+            Some(entry) => {
+                if entry.depth >= depth {
+                    let decode = |value| i16::from(Score::from_tt(value, ply));
+                    match entry.value {
+                        NodeValue::Principal { value } => Some(decode(value)),
+                        // an upper bound, the node is worth at most the value
                         NodeValue::All { value } => {
-                            *alpha = cmp::max(*alpha, value);
+                            let value = decode(value);
+                            if value <= *alpha {
+                                return Some(value);
+                            }
+                            *beta = cmp::min(*beta, value);
                             None
                         }
+                        // a lower bound, the node is worth at least the value
                         NodeValue::Cut { value } => {
-                            *beta = cmp::min(*beta, value);
+                            let value = decode(value);
+                            if value >= *beta {
+                                return Some(value);
+                            }
+                            *alpha = cmp::max(*alpha, value);
                             None
                         }
                     }
@@ -51,26 +1422,66 @@ impl AlphaBetaChessAgent {
         }
     }
 
+    // Record the transposition table entry of a traced node, if it is deep
+    // enough to be used, and whether it ends the node like in
+    // `cached_evaluation`
+    fn trace_table(
+        &self,
+        trace: &LineTrace,
+        position: &Position,
+        ply: usize,
+        depth: u8,
+        alpha: i16,
+        beta: i16,
+    ) {
+        let entry = match self.tt.get_evaluation_and_depth(position).deeper {
+            Some(entry) if entry.depth >= depth => entry,
+            _ => return,
+        };
+        let (bound, value) = match entry.value {
+            NodeValue::Principal { value } => (ScoreBound::Exact, value),
+            NodeValue::All { value } => (ScoreBound::Upper, value),
+            NodeValue::Cut { value } => (ScoreBound::Lower, value),
+        };
+        let value = Score::from_tt(value, ply);
+        let cutoff = match bound {
+            ScoreBound::Exact => true,
+            ScoreBound::Upper => i16::from(value) <= alpha,
+            ScoreBound::Lower => i16::from(value) >= beta,
+        };
+        trace.record(TraceEvent::TableHit {
+            ply,
+            depth: entry.depth,
+            bound,
+            value,
+            cutoff: cutoff && !self.is_restricted(ply),
+        });
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn update_cache(
-        tt: &TranspositionTable<i16>,
+        &self,
         position: &Position,
+        ply: usize,
         depth: u8,
         alpha: i16,
         beta: i16,
         value: i16,
-        best_move: ChessMove,
+        best_move: Option<ChessMove>,
     ) {
+        let encoded = Score::from(value).to_tt(ply);
         let node = if value <= alpha {
             // Beta
-            NodeValue::all_node(value)
+            NodeValue::all_node(encoded)
         } else if value >= beta {
             // Alpha
-            NodeValue::cut_node(value)
+            NodeValue::cut_node(encoded)
         } else {
             // Exact
-            NodeValue::pv_node(value)
+            NodeValue::pv_node(encoded)
         };
-        tt.update_evaluation_and_best_move(position, depth, node, Some(best_move));
+        self.tt
+            .update_evaluation_and_best_move(position, depth, node, best_move);
     }
 
     fn check_extension(position: &Position, depth: &mut u8, check_extension_enabled: &mut bool) {
@@ -81,21 +1492,119 @@ impl AlphaBetaChessAgent {
         }
     }
 
-    fn expand(tt: &TranspositionTable<i16>, position: &Position) -> Vec<ChessMove> {
-        position.sorted_moves(tt.best_move(position))
+    // Whether the position after the move may still be extended. Checks
+    // that give away material by static exchange are rarely worth the extra
+    // depth, so neither they nor the line below them get extended.
+    fn extends_after(
+        position: &Position,
+        chess_move: ChessMove,
+        check_extension_enabled: bool,
+    ) -> bool {
+        check_extension_enabled
+            && !(position.gives_check(chess_move) && position.see(chess_move) < 0)
+    }
+
+    // Ordered moves of the position in the ply's move list buffer.
+    // The buffer has to be handed back to the stack once the node is done.
+    fn expand(&self, position: &Position, ply: usize) -> Vec<ChessMove> {
+        let forced_move = self.forced_move(ply);
+        let mut stack = self.stack.borrow_mut();
+        let mut moves = stack.take_moves(ply);
+        let best_move = self.tt.get_evaluation_and_depth(position).best_move();
+        position.sorted_moves_into(best_move, &mut moves);
+        if ply == 0 {
+            if let Some(root_moves) = self.root_moves {
+                moves.retain(|m| root_moves.contains(m));
+            }
+            let excluded = self.excluded_root_moves.borrow();
+            moves.retain(|m| !excluded.contains(m));
+        }
+        if let Some(forced_move) = forced_move {
+            moves.retain(|&m| m == forced_move);
+        }
+        // try killer moves right after the captures and promotions
+        let skip = if best_move.is_some() && moves.first() == best_move.as_ref() {
+            1
+        } else {
+            0
+        };
+        if let Some(quiet_start) = moves
+            .iter()
+            .skip(skip)
+            .position(|&m| position.is_quiet(m))
+            .map(|index| index + skip)
+        {
+            let mut insert_at = quiet_start;
+            for killer in stack.killers(ply).iter().flatten() {
+                if let Some(index) = moves[insert_at..].iter().position(|m| m == killer) {
+                    moves[insert_at..=insert_at + index].rotate_right(1);
+                    insert_at += 1;
+                }
+            }
+            // then the quiet moves that check, in their order
+            let rest = &mut moves[insert_at..];
+            let mut checks = 0;
+            for index in 0..rest.len() {
+                if position.is_quiet(rest[index]) && position.gives_check(rest[index]) {
+                    rest[checks..=index].rotate_right(1);
+                    checks += 1;
+                }
+            }
+        }
+        moves
+    }
+
+    // quiescence search from a leaf of the main search
+    fn q_search(&self, position: &Position, ply: usize, alpha: i16, beta: i16) -> i16 {
+        self.qsearch_start.set((ply, self.nodes.get()));
+        self.quiesce(position, ply, alpha, beta)
     }
 
-    // quiescence search
-    fn q_search(position: &Position, mut alpha: i16, beta: i16) -> i16 {
-        let evaluation = position.evaluate();
+    // Returns true if the running quiescence search has gone on for too long
+    // and should settle for the static evaluation, counting why in the stats
+    fn qsearch_limit_reached(&self, ply: usize) -> bool {
+        let (start_ply, start_nodes) = self.qsearch_start.get();
+        let mut stats = self.stats.get();
+        if ply - start_ply >= self.qsearch_max_ply {
+            stats.q_ply_cutoffs += 1;
+        } else if self.qsearch_node_limit > 0
+            && self.nodes.get() - start_nodes >= self.qsearch_node_limit
+        {
+            stats.q_node_cutoffs += 1;
+        } else {
+            return false;
+        }
+        self.stats.set(stats);
+        true
+    }
+
+    fn quiesce(&self, position: &Position, ply: usize, mut alpha: i16, beta: i16) -> i16 {
+        if self.visit_node(ply) {
+            return 0;
+        }
+        let evaluation = self.evaluate(position, ply);
+        if ply >= MAX_PLY || self.qsearch_limit_reached(ply) {
+            return evaluation;
+        }
+        {
+            let mut stack = self.stack.borrow_mut();
+            stack.clear_pv(ply);
+            stack.set_evaluation(ply, evaluation);
+        }
         if evaluation >= beta {
             beta
         } else {
             if alpha < evaluation {
                 alpha = evaluation;
             }
-            for m in position.sorted_captures().into_iter() {
-                let score = -Self::q_search(&position.make_move_new(m), -beta, -alpha);
+            let mut captures = self.stack.borrow_mut().take_moves(ply);
+            position.sorted_captures_into(&mut captures);
+            for &m in captures.iter() {
+                let score = -self.quiesce(&position.make_move_new(m), ply + 1, -beta, -alpha);
+                if self.is_stopped() {
+                    alpha = 0;
+                    break;
+                }
                 if score >= beta {
                     alpha = beta;
                     break;
@@ -104,6 +1613,7 @@ impl AlphaBetaChessAgent {
                     alpha = score;
                 }
             }
+            self.stack.borrow_mut().give_moves(ply, captures);
             alpha
         }
     }
@@ -111,50 +1621,82 @@ impl AlphaBetaChessAgent {
     // this is really just a pure alpha beta search
     // with no caching or storing evaluations in nodes
     // used for the null move heursitic
-    fn null_alpha_beta(position: &Position, depth: u8, mut alpha: i16, beta: i16) -> i16 {
-        if depth == 0 {
-            position.evaluate()
+    fn null_alpha_beta(
+        &self,
+        position: &Position,
+        ply: usize,
+        depth: u8,
+        mut alpha: i16,
+        beta: i16,
+    ) -> i16 {
+        if self.visit_node(ply) {
+            return 0;
+        }
+        if depth == 0 || ply >= MAX_PLY {
+            self.evaluate(position, ply)
         } else {
-            for child_move in position.sorted_moves(None) {
-                let val = -Self::null_alpha_beta(
+            let mut moves = self.stack.borrow_mut().take_moves(ply);
+            position.sorted_moves_into(None, &mut moves);
+            for &child_move in moves.iter() {
+                let val = -self.null_alpha_beta(
                     &position.make_move_new(child_move),
+                    ply + 1,
                     depth - 1,
                     -beta,
                     -alpha,
                 );
+                if self.is_stopped() {
+                    alpha = 0;
+                    break;
+                }
                 if val >= beta {
-                    return beta;
+                    alpha = beta;
+                    break;
                 }
                 if val > alpha {
                     alpha = val;
                 }
             }
+            self.stack.borrow_mut().give_moves(ply, moves);
             alpha
         }
     }
 
     fn null_window_search(
-        tt: &TranspositionTable<i16>,
+        &self,
         position: &Position,
+        ply: usize,
         depth: u8,
         alpha: i16,
         beta: i16,
         check_extension_enabled: bool,
     ) -> i16 {
+        // In an exploding iteration the null windows keep failing high and
+        // every move gets searched twice, so search with the full window once
+        if self.exploded.get() {
+            return -self.alpha_beta(
+                position,
+                ply,
+                depth - 1,
+                -beta,
+                -alpha,
+                check_extension_enabled,
+            );
+        }
         // Search with null window at first
-        let value = -Self::alpha_beta(
-            tt,
+        let value = -self.alpha_beta(
             position,
+            ply,
             depth - 1,
             -alpha - 1,
             -alpha,
             check_extension_enabled,
         );
         // Re-search the path with regular window if alpha < value < beta
-        if alpha < value && value < beta {
-            -Self::alpha_beta(
-                tt,
+        if alpha < value && value < beta && !self.is_stopped() {
+            -self.alpha_beta(
                 position,
+                ply,
                 depth - 1,
                 -beta,
                 -alpha,
@@ -166,115 +1708,1088 @@ impl AlphaBetaChessAgent {
     }
 
     fn principal_variation_search(
-        tt: &TranspositionTable<i16>,
+        &self,
         position: &Position,
+        ply: usize,
         depth: u8,
         mut alpha: i16,
         beta: i16,
         check_extension_enabled: bool,
     ) -> (i16, ChessMove) {
-        let moves = Self::expand(tt, position);
+        let moves = self.expand(position, ply);
         let mut best_move = moves[0];
 
         // Search down the principal variation path first with regular window
-        let value = -Self::alpha_beta(
-            tt,
-            &position.make_move_new(moves[0]),
-            depth - 1,
-            -beta,
-            -alpha,
-            check_extension_enabled,
-        );
+        let value = self.search_move(ply, moves[0], || {
+            -self.alpha_beta(
+                &position.make_move_new(moves[0]),
+                ply + 1,
+                depth - 1,
+                -beta,
+                -alpha,
+                Self::extends_after(position, moves[0], check_extension_enabled),
+            )
+        });
         if value > alpha {
             alpha = value;
-        }
-        if alpha >= beta {
-            return (alpha, best_move);
+            self.stack.borrow_mut().update_pv(ply, best_move);
         }
 
         // Search the rest of the paths with null windows
-        for &child_move in moves.iter().skip(1) {
-            let value = Self::null_window_search(
-                tt,
-                &position.make_move_new(child_move),
+        let split_pool = self
+            .pool
+            .filter(|_| self.split_nodes && depth >= MIN_NODE_SPLIT_DEPTH && moves.len() > 2);
+        if let (Some(pool), true) = (split_pool, alpha < beta && !self.is_stopped()) {
+            let (value, split_best_move) = self.split_moves(
+                pool,
+                position,
+                ply,
                 depth,
-                alpha,
-                beta,
+                &moves[1..],
+                (alpha, beta),
+                best_move,
                 check_extension_enabled,
             );
-            if value > alpha {
-                alpha = value;
-                best_move = child_move;
-            }
-            if alpha >= beta {
-                break;
+            alpha = value;
+            best_move = split_best_move;
+        } else if alpha < beta && !self.is_stopped() {
+            for &child_move in moves.iter().skip(1) {
+                let value = self.search_move(ply, child_move, || {
+                    self.null_window_search(
+                        &position.make_move_new(child_move),
+                        ply + 1,
+                        depth,
+                        alpha,
+                        beta,
+                        Self::extends_after(position, child_move, check_extension_enabled),
+                    )
+                });
+                if self.is_stopped() {
+                    break;
+                }
+                if value > alpha {
+                    alpha = value;
+                    best_move = child_move;
+                    self.stack.borrow_mut().update_pv(ply, best_move);
+                }
+                if alpha >= beta {
+                    break;
+                }
             }
         }
+        if alpha >= beta && self.options.killer_moves && position.is_quiet(best_move) {
+            self.stack.borrow_mut().store_killer(ply, best_move);
+        }
+        self.stack.borrow_mut().give_moves(ply, moves);
         (alpha, best_move)
     }
 
+    // Search the root for one iteration, splitting its moves between the
+    // threads of the pool once the iterations are deep enough to be worth it
+    fn root_search(&self, position: &Position, depth: u8, alpha: i16, beta: i16) -> i16 {
+        match self.pool {
+            Some(pool)
+                if depth >= MIN_SPLIT_DEPTH && position.status() == PositionStatus::Ongoing =>
+            {
+                self.split_root(pool, position, depth, alpha, beta)
+            }
+            _ => self.alpha_beta(position, 0, depth, alpha, beta, true),
+        }
+    }
+
+    // The principal variation search of the root with its moves split between
+    // threads: the first move is searched here with the full window, then the
+    // others in parallel
+    fn split_root(
+        &self,
+        pool: &ThreadPool,
+        position: &Position,
+        mut depth: u8,
+        alpha: i16,
+        beta: i16,
+    ) -> i16 {
+        if self.visit_node(0) {
+            return 0;
+        }
+        self.is_repetition(position, 0);
+        self.stack.borrow_mut().clear_pv(0);
+        let mut check_extension_enabled = true;
+        if self.options.check_extensions {
+            Self::check_extension(position, &mut depth, &mut check_extension_enabled);
+        }
+        let moves = self.expand(position, 0);
+        let value = self.search_move(0, moves[0], || {
+            -self.alpha_beta(
+                &position.make_move_new(moves[0]),
+                1,
+                depth - 1,
+                -beta,
+                -alpha,
+                Self::extends_after(position, moves[0], check_extension_enabled),
+            )
+        });
+        if value > alpha && !self.is_stopped() {
+            self.stack.borrow_mut().update_pv(0, moves[0]);
+        }
+        let (value, best_move) = if self.is_stopped() {
+            (0, moves[0])
+        } else {
+            self.split_moves(
+                pool,
+                position,
+                0,
+                depth,
+                &moves[1..],
+                (cmp::max(alpha, value), beta),
+                moves[0],
+                check_extension_enabled,
+            )
+        };
+        self.stack.borrow_mut().give_moves(0, moves);
+        if self.is_stopped() {
+            return 0;
+        }
+        if !self.is_restricted(0) {
+            self.update_cache(position, 0, depth, alpha, beta, value, Some(best_move));
+        }
+        value
+    }
+
+    // Search the younger brothers of a node's first move in parallel on the
+    // pool, with null windows against the best value any thread has found so
+    // far, re-searching the ones that beat it. The threads steal moves from
+    // each other as they run out. Once a move fails high the ones not started
+    // yet are skipped, the running ones still finish.
+    #[allow(clippy::too_many_arguments)]
+    fn split_moves(
+        &self,
+        pool: &ThreadPool,
+        position: &Position,
+        ply: usize,
+        depth: u8,
+        moves: &[ChessMove],
+        (alpha, beta): (i16, i16),
+        best_move: ChessMove,
+        check_extension_enabled: bool,
+    ) -> (i16, ChessMove) {
+        let state = Mutex::new(SplitState {
+            alpha,
+            best_move,
+            pv: self.stack.borrow().pv(ply).to_vec(),
+            nodes: 0,
+            seldepth: 0,
+            stats: SearchStats::default(),
+            stopped: false,
+            root_moves: Vec::new(),
+        });
+        let path = self.stack.borrow().path(ply);
+        let helper = self.helper();
+        pool.install(|| {
+            moves.par_iter().for_each_init(
+                || RefCell::new(SearchStack::default()),
+                |stack, &chess_move| {
+                    let alpha = {
+                        let state = state.lock().unwrap();
+                        if state.stopped || state.alpha >= beta {
+                            return;
+                        }
+                        state.alpha
+                    };
+                    let stack = &*stack;
+                    stack.borrow_mut().set_path(&path);
+                    let search = helper.search(stack);
+                    if let (0, Some(progress)) = (ply, helper.progress) {
+                        progress.start_root_move(chess_move);
+                    }
+                    let start = Instant::now();
+                    let value = search.null_window_search(
+                        &position.make_move_new(chess_move),
+                        ply + 1,
+                        depth,
+                        alpha,
+                        beta,
+                        Self::extends_after(position, chess_move, check_extension_enabled),
+                    );
+                    let mut state = state.lock().unwrap();
+                    state.nodes += search.nodes.get();
+                    state.seldepth = cmp::max(state.seldepth, search.seldepth.get());
+                    state.stats += search.stats.get();
+                    if ply == 0 {
+                        state.root_moves.push(RootMove {
+                            chess_move,
+                            nodes: search.nodes.get(),
+                            elapsed: start.elapsed(),
+                        });
+                        if let Some(progress) = helper.progress {
+                            progress.finish_root_move(
+                                chess_move,
+                                search.nodes.get(),
+                                start.elapsed(),
+                            );
+                        }
+                    }
+                    if search.is_stopped() {
+                        state.stopped = true;
+                    } else if value > state.alpha {
+                        state.alpha = value;
+                        state.best_move = chess_move;
+                        state.pv.clear();
+                        state.pv.push(chess_move);
+                        state.pv.extend_from_slice(stack.borrow().pv(ply + 1));
+                    }
+                },
+            )
+        });
+        let state = state.into_inner().unwrap();
+        self.nodes.set(self.nodes.get() + state.nodes);
+        self.seldepth
+            .set(cmp::max(self.seldepth.get(), state.seldepth));
+        let mut stats = self.stats.get();
+        stats += state.stats;
+        self.stats.set(stats);
+        for root_move in &state.root_moves {
+            self.record_root_move(root_move.chess_move, root_move.nodes, root_move.elapsed);
+        }
+        if state.stopped {
+            self.stopped.set(true);
+        }
+        self.stack.borrow_mut().set_pv(ply, &state.pv);
+        (state.alpha, state.best_move)
+    }
+
+    // The children of the root the engine kept losing with are searched as
+    // they are, then made worse for the root's side by their learned penalty
     fn alpha_beta(
-        tt: &TranspositionTable<i16>,
+        &self,
+        position: &Position,
+        ply: usize,
+        depth: u8,
+        alpha: i16,
+        beta: i16,
+        check_extension_enabled: bool,
+    ) -> i16 {
+        let penalty = match (ply, self.learning) {
+            (1, Some(learning)) => learning.penalty(position.get_hash()),
+            _ => 0,
+        };
+        if penalty == 0 {
+            return self.search_node(position, ply, depth, alpha, beta, check_extension_enabled);
+        }
+        let value = self.search_node(
+            position,
+            ply,
+            depth,
+            alpha.saturating_sub(penalty),
+            beta.saturating_sub(penalty),
+            check_extension_enabled,
+        );
+        // a forced mate stays what it is, however the opening went before
+        if Score::from(value).is_mate() {
+            value
+        } else {
+            value + penalty
+        }
+    }
+
+    fn search_node(
+        &self,
         position: &Position,
+        ply: usize,
         mut depth: u8,
         mut alpha: i16,
         mut beta: i16,
         mut check_extension_enabled: bool,
     ) -> i16 {
-        Self::check_extension(position, &mut depth, &mut check_extension_enabled);
+        if self.visit_node(ply) {
+            return 0;
+        }
+        if ply >= MAX_PLY {
+            return self.evaluate(position, ply);
+        }
+        let repeats = self.is_repetition(position, ply);
+        let trace = self.trace_at(ply);
+        if let Some(trace) = trace {
+            trace.record(TraceEvent::Node {
+                ply,
+                chess_move: trace.move_to(ply),
+                depth,
+                alpha: Score::from(alpha),
+                beta: Score::from(beta),
+                evaluation: Score::from(self.evaluate(position, ply)),
+            });
+        }
+        // the root always needs a move, even if it repeats an earlier position
+        if repeats && ply > 0 {
+            let value = self.repetition_score(ply);
+            if let Some(trace) = trace {
+                trace.record(TraceEvent::Repetition {
+                    ply,
+                    value: Score::from(value),
+                });
+            }
+            return value;
+        }
+        // the root always needs a move, below it tablebase positions are known exactly
+        if ply > 0 {
+            let value = match self.probe_tablebases(position) {
+                Some(Score::Eval(_)) => Some(self.draw_score(ply)),
+                Some(score) => Some(Score::from_tt(score.into(), ply).into()),
+                None => None,
+            };
+            if let Some(value) = value {
+                if let Some(trace) = trace {
+                    trace.record(TraceEvent::Tablebase {
+                        ply,
+                        value: Score::from(value),
+                    });
+                }
+                return value;
+            }
+        }
+        self.stack.borrow_mut().clear_pv(ply);
+        if self.options.check_extensions {
+            let unextended = depth;
+            Self::check_extension(position, &mut depth, &mut check_extension_enabled);
+            if let (Some(trace), true) = (trace, depth > unextended) {
+                trace.record(TraceEvent::CheckExtension { ply, depth });
+            }
+        }
         let status = position.status();
         let alpha_orig = alpha;
+        if let Some(trace) = trace {
+            self.trace_table(trace, position, ply, depth, alpha, beta);
+        }
         // Get cached evaluation if it exists and update alpha/beta accordingly
         // If an exact value is already cached, return that immediately
-        if let Some(value) = Self::cached_evaluation(tt, position, depth, &mut alpha, &mut beta) {
-            return value;
+        if !self.is_restricted(ply) {
+            if let Some(value) = self.cached_evaluation(position, ply, depth, &mut alpha, &mut beta)
+            {
+                return value;
+            }
         }
         // If game is over, return evaluation
         if status != PositionStatus::Ongoing {
-            return position.evaluate();
+            let value = if status == PositionStatus::Stalemate {
+                self.draw_score(ply)
+            } else {
+                self.evaluate(position, ply)
+            };
+            if let Some(trace) = trace {
+                trace.record(TraceEvent::GameOver {
+                    ply,
+                    value: Score::from(value),
+                });
+            }
+            return value;
         }
-        // If depth is 0, evaluate after quiesence search, cache and return
+        // If depth is 0, evaluate after quiesence search, cache and return.
+        // The quiescence search fails hard, so its value is only a bound
+        // when it ends up on the window.
         if depth == 0 {
-            let value = Self::q_search(position, alpha, beta);
-            tt.update_evaluation_and_best_move(position, depth, NodeValue::pv_node(value), None);
+            let value = self.q_search(position, ply, alpha, beta);
+            if !self.is_stopped() {
+                self.update_cache(position, ply, depth, alpha, beta, value, None);
+                if let Some(trace) = trace {
+                    trace.record(TraceEvent::Quiescence {
+                        ply,
+                        value: Score::from(value),
+                    });
+                }
+            }
             return value;
         }
-        // depth >= 3, try null-move pruning
-        if depth >= 3 {
+        // depth >= 3, try null-move pruning unless passing might be the best move
+        // reduce further when the position is getting better for the side to move
+        if self.options.null_move && depth >= 3 && !position.is_zugzwang_likely() {
+            let improving = {
+                let mut stack = self.stack.borrow_mut();
+                stack.set_evaluation(ply, self.evaluate(position, ply));
+                stack.improving(ply)
+            };
             if let Some(null_move_game) = position.null_move() {
-                let score = -Self::null_alpha_beta(&null_move_game, depth - 3, -beta, -beta + 1);
+                let reduction = self.options.null_move_reduction + u8::from(improving);
+                let null_depth = depth.saturating_sub(reduction);
+                let score =
+                    -self.null_alpha_beta(&null_move_game, ply + 1, null_depth, -beta, -beta + 1);
+                if self.is_stopped() {
+                    return 0;
+                }
+                if let Some(trace) = trace {
+                    trace.record(TraceEvent::NullMove {
+                        ply,
+                        depth: null_depth,
+                        value: Score::from(score),
+                        cutoff: score >= beta,
+                    });
+                }
                 if score >= beta {
                     return beta;
                 }
             }
         }
         // perform principal search
-        let (value, best_move) = Self::principal_variation_search(
-            tt,
+        let (value, best_move) = self.principal_variation_search(
             position,
+            ply,
             depth,
             alpha,
             beta,
             check_extension_enabled,
         );
+        if self.is_stopped() {
+            return 0;
+        }
+        if let Some(trace) = trace {
+            trace.record(TraceEvent::Value {
+                ply,
+                value: Score::from(value),
+                bound: ScoreBound::of(value, alpha_orig, beta),
+            });
+        }
+        if self.is_restricted(ply) {
+            return value;
+        }
         // update value/best_move in transpostion tables
-        Self::update_cache(tt, position, depth, alpha_orig, beta, value, best_move);
+        self.update_cache(
+            position,
+            ply,
+            depth,
+            alpha_orig,
+            beta,
+            value,
+            Some(best_move),
+        );
         value
     }
 }
 
-impl ChessAgent for AlphaBetaChessAgent {
-    fn get_action(&self, game: &ChessGame) -> Action {
-        let alpha = ChessGame::min_evaluation();
-        let beta = ChessGame::max_evaluation();
+#[cfg(test)]
+mod tests {
+    use super::{AlphaBetaChessAgent, Search, MIN_EXPLOSION_NODES};
+    use crate::agent::ChessAgent;
+    use crate::learning::OpeningLearning;
+    use crate::node::NodeValue;
+    use crate::options::{EngineOptions, ResignPolicy};
+    use crate::polyglot::{self, Book};
+    use crate::score::Score;
+    use crate::search::progress::SearchProgress;
+    use crate::search::trace::{LineTrace, TraceEvent};
+    use crate::search::{RootMove, SearchOptions, SearchResult, SearchStats};
+    use crate::tt::TranspositionTable;
+    use crate::{Action, ChessGame, ChessMove, Color, Position, Square};
+    use std::str::FromStr;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn mate_is_scored_by_distance() {
+        let agent = AlphaBetaChessAgent::new(4, TranspositionTable::new(100_000));
+        // back rank mate in one
+        let position = Position::from_str("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let result = agent.search(&position);
+        assert_eq!(
+            result.best_move,
+            ChessMove::new(Square::A1, Square::A8, None)
+        );
+        assert_eq!(result.score, Score::Mate(1));
+    }
+
+    #[test]
+    fn stop_once_mate_is_confirmed() {
+        let agent = AlphaBetaChessAgent::new(10, TranspositionTable::new(100_000));
+        // mate in two with the queen and rook
+        let position = Position::from_str("6k1/8/6K1/8/8/8/8/Q6R w - - 0 1").unwrap();
+        let result = agent.search(&position);
+        assert!(matches!(result.score, Score::Mate(_)));
+        assert!(result.depth < 10);
+        assert_eq!(result.pv.first(), Some(&result.best_move));
+    }
+
+    #[test]
+    fn search_moves_restrict_root() {
+        let mate = ChessMove::new(Square::A1, Square::A8, None);
+        let other = ChessMove::new(Square::A1, Square::A2, None);
+        let position = Position::from_str("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let agent = AlphaBetaChessAgent::new(3, TranspositionTable::new(100_000));
+        assert_eq!(agent.search(&position).best_move, mate);
+        let agent = agent.with_search_moves(vec![other]);
+        let result = agent.search(&position);
+        assert_eq!(result.best_move, other);
+        assert_eq!(result.pv.first(), Some(&other));
+    }
+
+    #[test]
+    fn excluded_moves_find_alternative() {
+        let mate = ChessMove::new(Square::A1, Square::A8, None);
+        let position = Position::from_str("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let agent = AlphaBetaChessAgent::new(3, TranspositionTable::new(100_000))
+            .with_excluded_moves(vec![mate]);
+        let result = agent.search(&position);
+        assert_ne!(result.best_move, mate);
+        assert!(!result.score.is_mate());
+    }
+
+    #[test]
+    fn learning_avoids_lost_openings() {
+        let position = Position::default();
+        let agent = AlphaBetaChessAgent::new(3, TranspositionTable::new(100_000));
+        let usual = agent.search(&position);
+        let lost = position.make_move_new(usual.best_move).get_hash();
+        let learning = OpeningLearning::from_text(&format!("{:016x} 0 1 3", lost)).unwrap();
+        let agent =
+            AlphaBetaChessAgent::new(3, TranspositionTable::new(100_000)).with_learning(learning);
+        let result = agent.search(&position);
+        assert_ne!(result.best_move, usual.best_move);
+        assert!(result.score <= usual.score);
+    }
+
+    #[test]
+    fn resigns_lost_games() {
+        let mut game = ChessGame::from_str("k7/8/8/8/8/8/1Q6/1R4K1 b - - 0 1").unwrap();
+        let mut agent = AlphaBetaChessAgent::new(2, TranspositionTable::new(100_000));
+        let resign = ResignPolicy {
+            threshold: 60,
+            moves: 2,
+            min_move: 1,
+        };
+        agent.set_options(EngineOptions {
+            resign,
+            ..EngineOptions::default()
+        });
+        let action = agent.get_action(&game);
+        assert!(matches!(action, Action::MakeMove(_)));
+        game.take_action(action);
+        game.take_action(Action::MakeMove(ChessMove::new(
+            Square::G1,
+            Square::F2,
+            None,
+        )));
+        assert!(matches!(
+            agent.get_action(&game),
+            Action::Resign(Color::Black)
+        ));
+
+        let mut agent = AlphaBetaChessAgent::new(2, TranspositionTable::new(100_000));
+        agent.set_options(EngineOptions {
+            resign: ResignPolicy {
+                min_move: 3,
+                ..resign
+            },
+            ..EngineOptions::default()
+        });
+        agent.get_action(&game);
+        assert!(matches!(agent.get_action(&game), Action::MakeMove(_)));
+    }
+
+    #[test]
+    fn draw_offers() {
+        let mut game = ChessGame::from_str("k7/8/8/8/8/8/1Q6/1R4K1 b - - 0 1").unwrap();
+        let agent = AlphaBetaChessAgent::new(2, TranspositionTable::new(100_000));
+        game.take_action(Action::OfferDraw(Color::Black));
+        game.take_action(Action::MakeMove(ChessMove::new(
+            Square::A8,
+            Square::A7,
+            None,
+        )));
+        assert!(game.draw_offered());
+        assert!(matches!(agent.get_action(&game), Action::MakeMove(_)));
+
+        game.take_action(Action::OfferDraw(Color::White));
+        game.take_action(Action::MakeMove(ChessMove::new(
+            Square::G1,
+            Square::F2,
+            None,
+        )));
+        assert!(matches!(agent.get_action(&game), Action::AcceptDraw));
+        game.take_action(Action::MakeMove(ChessMove::new(
+            Square::A7,
+            Square::A8,
+            None,
+        )));
+        assert!(!game.draw_offered());
+    }
+
+    #[test]
+    fn traced_searches_follow_the_line() {
+        let position = Position::default();
+        let line: Vec<ChessMove> = ["g1h3", "e7e5", "h3g5"]
+            .iter()
+            .map(|m| ChessMove::from_str(m).unwrap())
+            .collect();
+        let agent = AlphaBetaChessAgent::new(3, TranspositionTable::new(100_000));
+        let mut events = Vec::new();
+        let result = agent.search_line(&position, &line, |event| events.push(event.clone()));
+        assert_eq!(result.pv[..2], line[..2]);
+        let iterations = events
+            .iter()
+            .filter(|event| matches!(event, TraceEvent::Iteration { .. }))
+            .count();
+        assert_eq!(iterations, 3);
+        assert!(events.iter().all(|event| event.ply() <= line.len()));
+        assert!(events.iter().any(|event| matches!(
+            event,
+            TraceEvent::Node { ply: 3, chess_move: Some(m), .. } if *m == line[2]
+        )));
+        // the node at the end of the line is searched as usual
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, TraceEvent::Quiescence { ply: 3, .. })));
 
-        for i in 1..=self.depth {
-            Self::alpha_beta(&self.tt, &game.current_position(), i, alpha, beta, true);
+        // an illegal move cuts the line
+        let mut plies = Vec::new();
+        agent.search_line(&position, &[line[1]], |event| plies.push(event.ply()));
+        assert!(plies.iter().all(|&ply| ply == 0));
+    }
+
+    #[test]
+    fn scores_outside_the_aspiration_window_are_searched_again() {
+        let position = Position::from_str(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let agent = AlphaBetaChessAgent::new(6, TranspositionTable::new(100_000));
+        let mut bounds = Vec::new();
+        let result = agent.search_with_info(&position, |result| bounds.push(result.bound));
+        assert!(result.stats.aspiration_researches > 0);
+        // only the re-searches that found the score are reported
+        assert_eq!(bounds.len(), 6);
+        assert!(bounds.iter().all(|bound| bound.is_exact()));
+    }
+
+    #[test]
+    fn traced_bounds_outside_the_window_cut_off() {
+        let position = Position::default();
+        let agent = AlphaBetaChessAgent::new(3, TranspositionTable::new(100_000));
+        let search = Search::new(&agent.tt, agent.evaluator.as_ref(), &agent.stack, None, 0);
+        let e4 = Some(ChessMove::from_str("e2e4").unwrap());
+        let trace = LineTrace::new(&position, &[]);
+        let mut cutoffs = Vec::new();
+        let mut record = |node| {
+            agent
+                .tt
+                .update_evaluation_and_best_move(&position, 5, node, e4);
+            for &alpha in &[-120, -90, -50] {
+                search.trace_table(&trace, &position, 2, 5, alpha, alpha + 20);
+            }
+            trace.flush(&mut |event| {
+                if let TraceEvent::TableHit { cutoff, .. } = event {
+                    cutoffs.push(*cutoff);
+                }
+            });
+        };
+        // worth at most -90, at least -90 and exactly -90
+        record(NodeValue::all_node(-90));
+        record(NodeValue::cut_node(-90));
+        record(NodeValue::pv_node(-90));
+        assert_eq!(
+            cutoffs,
+            vec![false, true, true, true, false, false, true, true, true]
+        );
+    }
+
+    #[test]
+    fn searches_are_reproducible() {
+        let position = Position::from_str(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let search =
+            || AlphaBetaChessAgent::new(4, TranspositionTable::new(100_000)).search(&position);
+        let (first, second) = (search(), search());
+        assert_eq!(first.best_move, second.best_move);
+        assert_eq!(first.score, second.score);
+        assert_eq!(first.nodes, second.nodes);
+    }
+
+    #[test]
+    fn deterministic_mode_repeats_searches() {
+        let position = Position::from_str("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+        let options = EngineOptions {
+            eval_file: String::from("random"),
+            deterministic: true,
+            ..EngineOptions::default()
+        };
+        let agent = AlphaBetaChessAgent::new(4, TranspositionTable::new(100_000))
+            .with_options(options.clone());
+        let other =
+            AlphaBetaChessAgent::new(4, TranspositionTable::new(100_000)).with_options(options);
+        // the second search of the same agent can't reuse the first one's table
+        let (first, second, third) = (
+            agent.search(&position),
+            agent.search(&position),
+            other.search(&position),
+        );
+        let untimed = |result: SearchResult| SearchResult {
+            elapsed: Duration::default(),
+            root_moves: result
+                .root_moves
+                .iter()
+                .map(|root_move| RootMove {
+                    elapsed: Duration::default(),
+                    ..*root_move
+                })
+                .collect(),
+            ..result
+        };
+        let first = untimed(first);
+        assert_eq!(first, untimed(second));
+        assert_eq!(first, untimed(third));
+    }
+
+    #[test]
+    fn plays_book_moves_with_own_book() {
+        let position = Position::default();
+        let mut entry = polyglot::hash(&position).to_be_bytes().to_vec();
+        // b1a3, a move no search plays
+        entry.extend_from_slice(&[0, 0x50, 0, 1, 0, 0, 0, 0]);
+        let book = Book::from_bytes(&entry).unwrap();
+        let mut agent =
+            AlphaBetaChessAgent::new(3, TranspositionTable::new(100_000)).with_book(book);
+        assert!(agent.search(&position).depth > 0);
+        agent.set_options(EngineOptions {
+            own_book: true,
+            ..EngineOptions::default()
+        });
+        let result = agent.search(&position);
+        assert_eq!(result.best_move, ChessMove::from_str("b1a3").unwrap());
+        assert_eq!(result.depth, 0);
+        // positions the book doesn't have are searched
+        let after = position.make_move_new(result.best_move);
+        assert!(agent.search(&after).depth > 0);
+    }
+
+    #[test]
+    #[cfg(feature = "gaviota")]
+    fn announces_the_distance_to_mate_of_gaviota_tables() {
+        let position = Position::from_str("8/8/8/4k3/8/8/8/R3K3 w - - 0 1").unwrap();
+        let tables = crate::gaviota::Gaviota::open(crate::gaviota::TEST_TABLES).unwrap();
+        let distance = tables.probe(&position).unwrap();
+        let mut agent = AlphaBetaChessAgent::new(2, TranspositionTable::new(100_000));
+        agent.set_options(EngineOptions {
+            search: SearchOptions {
+                tablebases: false,
+                ..SearchOptions::default()
+            },
+            gaviota_tb_path: Some(crate::gaviota::TEST_TABLES.to_string()),
+            ..EngineOptions::default()
+        });
+        let result = agent.search(&position);
+        assert!(matches!(distance, Score::Mate(_)));
+        assert_eq!(result.score, distance);
+        assert_eq!(
+            result.pv.len() as i16,
+            2 * distance.mate_in_moves().unwrap() - 1
+        );
+    }
+
+    #[test]
+    fn agents_sharing_a_table_keep_it() {
+        let options = EngineOptions {
+            hash_mb: 1,
+            ..EngineOptions::default()
+        };
+        let table = Arc::new(TranspositionTable::with_megabytes(1));
+        let agent = AlphaBetaChessAgent::sharing_table(3, table.clone(), options);
+        assert!(Arc::ptr_eq(&agent.shared_table(), &table));
+        let game = ChessGame::default();
+        agent.search_game(&game);
+        assert!(table.stats().occupied > 0);
+    }
+
+    #[test]
+    fn evaluators_that_fail_to_load_are_not_named() {
+        let mut agent = AlphaBetaChessAgent::new(1, TranspositionTable::new(100_000));
+        agent.set_options(EngineOptions {
+            eval_file: String::from("nnue:/nonexistent"),
+            ..EngineOptions::default()
+        });
+        assert_eq!(agent.options().eval_file, "naive");
+    }
+
+    #[test]
+    fn multi_pv_searches_the_next_best_lines() {
+        let position = Position::from_str("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let mut agent = AlphaBetaChessAgent::new(3, TranspositionTable::new(100_000));
+        agent.set_options(EngineOptions {
+            multi_pv: 3,
+            ..EngineOptions::default()
+        });
+        let mut iterations = Vec::new();
+        let result =
+            agent.search_with_info(&position, |result| iterations.push(result.lines.len()));
+        // the mate ends the search after two iterations
+        assert_eq!(iterations, vec![2, 2]);
+        assert_eq!(result.best_move, ChessMove::from_str("a1a8").unwrap());
+        let (second, third) = (&result.lines[0], &result.lines[1]);
+        assert!(second.score < result.score && third.score <= second.score);
+        let firsts = [result.pv[0], second.pv[0], third.pv[0]];
+        assert!(firsts[0] != firsts[1] && firsts[1] != firsts[2] && firsts[0] != firsts[2]);
+        // the lines don't change the best move or its score
+        let single =
+            AlphaBetaChessAgent::new(3, TranspositionTable::new(100_000)).search(&position);
+        assert_eq!(
+            (single.best_move, single.score),
+            (result.best_move, result.score)
+        );
+    }
+
+    #[test]
+    fn positions_without_moves_are_scored() {
+        let agent = AlphaBetaChessAgent::new(2, TranspositionTable::new(100_000));
+        let mated =
+            ChessGame::from_str("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+                .unwrap();
+        let result = agent.search_game(&mated);
+        assert_eq!((result.score, result.depth), (Score::Mated(0), 0));
+        assert!(result.pv.is_empty());
+        let stalemate = Position::from_str("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(agent.search(&stalemate).score, Score::DRAW);
+    }
+
+    #[test]
+    fn root_moves_account_for_the_nodes() {
+        let agent = AlphaBetaChessAgent::new(4, TranspositionTable::new(100_000));
+        let position = Position::default();
+        let result = agent.search(&position);
+        assert_eq!(result.root_moves.len(), 20);
+        assert!(result
+            .root_moves
+            .iter()
+            .all(|m| position.legal(m.chess_move)));
+        let below: u64 = result.root_moves.iter().map(|m| m.nodes).sum();
+        assert!(below < result.nodes && below > result.nodes * 9 / 10);
+    }
+
+    #[test]
+    fn progress_follows_the_search() {
+        let progress = Arc::new(SearchProgress::default());
+        let agent = AlphaBetaChessAgent::new(4, TranspositionTable::new(100_000))
+            .with_progress(progress.clone());
+        let result = agent.search(&Position::default());
+        let snapshot = progress.snapshot();
+        assert_eq!((snapshot.depth, snapshot.move_count), (4, 20));
+        assert_eq!(snapshot.moves_started, 20);
+        assert_eq!(snapshot.nodes, result.nodes);
+        let last = snapshot.last_iteration.unwrap();
+        assert_eq!((last.depth, last.pv), (result.depth, result.pv));
+        assert_eq!(snapshot.root_moves, result.root_moves);
+    }
+
+    #[test]
+    fn nps_limit_slows_search() {
+        let mut agent = AlphaBetaChessAgent::new(3, TranspositionTable::new(100_000));
+        agent.set_options(EngineOptions {
+            nps_limit: 5000,
+            ..EngineOptions::default()
+        });
+        let result = agent.search(&Position::default());
+        assert!(result.nodes > 0);
+        assert!(result.nps() <= 5000, "{} nps", result.nps());
+    }
+
+    #[test]
+    fn records_best_move_of_every_iteration() {
+        let agent = AlphaBetaChessAgent::new(4, TranspositionTable::new(100_000));
+        let result = agent.search(&Position::default());
+        assert_eq!(result.best_moves.len(), usize::from(result.depth));
+        assert_eq!(result.best_moves.last(), Some(&result.best_move));
+    }
+
+    #[test]
+    fn search_features_can_be_disabled() {
+        let position = Position::default();
+        let agent = AlphaBetaChessAgent::new(5, TranspositionTable::new(100_000));
+        let nodes = agent.search(&position).nodes;
+        let agent = AlphaBetaChessAgent::new(5, TranspositionTable::new(100_000))
+            .with_search_options(SearchOptions {
+                null_move: false,
+                check_extensions: false,
+                killer_moves: false,
+                ..SearchOptions::default()
+            });
+        let result = agent.search(&position);
+        assert_ne!(result.nodes, nodes);
+        assert!(position.legal(result.best_move));
+    }
+
+    #[test]
+    fn move_delay_paces_moves() {
+        let mut agent = AlphaBetaChessAgent::new(1, TranspositionTable::new(100_000));
+        agent.set_options(EngineOptions {
+            move_delay: Duration::from_millis(200),
+            ..EngineOptions::default()
+        });
+        let result = agent.search(&Position::default());
+        assert!(result.elapsed >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn repetition_is_a_draw() {
+        // the king shuffled back and forth and can repeat a position from the game
+        let mut game = ChessGame::from_str("4k3/8/8/8/8/8/q7/4K3 w - - 0 1").unwrap();
+        for chess_move in ["e1f1", "e8d8", "f1e1", "d8e8"].iter() {
+            game.take_action(Action::MakeMove(ChessMove::from_str(chess_move).unwrap()));
         }
+        let agent = AlphaBetaChessAgent::new(3, TranspositionTable::new(100_000));
+        let result = agent.search_game(&game);
+        assert_eq!(
+            result.best_move,
+            ChessMove::new(Square::E1, Square::F1, None)
+        );
+        assert_eq!(result.score, Score::DRAW);
+        // without the game the repetition isn't known
+        let agent = AlphaBetaChessAgent::new(3, TranspositionTable::new(100_000));
+        let result = agent.search(&game.current_position());
+        assert!(result.score < Score::DRAW);
+    }
+
+    #[test]
+    fn explosions_switch_to_full_windows() {
+        let position = Position::from_str(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let agent = AlphaBetaChessAgent::new(3, TranspositionTable::new(100_000));
+        let search = Search::new(&agent.tt, agent.evaluator.as_ref(), &agent.stack, None, 0);
+        search.start_iteration(&[]);
+        assert_eq!(search.explosion_limit.get(), 0);
+        search.start_iteration(&[100, 1_000]);
+        assert_eq!(search.explosion_limit.get(), MIN_EXPLOSION_NODES);
+        search.start_iteration(&[1_000, 100_000]);
+        assert_eq!(search.explosion_limit.get(), 40_000_000);
+
+        search.explosion_limit.set(1);
+        let (alpha, beta) = (Score::Mated(0).into(), Score::Mate(0).into());
+        search.alpha_beta(&position, 0, 3, alpha, beta, true);
+        assert!(search.exploded.get());
+        assert_eq!(search.stats.get().explosions, 1);
+    }
+
+    #[test]
+    fn repetition_contempt_is_asymmetric() {
+        let agent = AlphaBetaChessAgent::new(3, TranspositionTable::new(100_000));
+        let position = Position::default();
+        let search = Search::new(&agent.tt, agent.evaluator.as_ref(), &agent.stack, None, 5)
+            .with_history(&[], 20);
+        search.root_score.set(50);
+        assert_eq!(search.repetition_score(0), -25);
+        assert_eq!(search.repetition_score(1), 25);
+        search.root_score.set(-50);
+        assert_eq!(search.repetition_score(0), 15);
+        assert_eq!(search.draw_score(0), -5);
+        assert!(!search.is_repetition(&position, 0));
+    }
+
+    #[test]
+    fn qsearch_limits_fall_back_to_evaluation() {
+        // lots of captures available to both sides
+        let position =
+            Position::from_str("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1")
+                .unwrap();
+        // a single iteration, so the capped search can't pay for its worse
+        // evaluations with worse move ordering in the next one
+        let mut agent = AlphaBetaChessAgent::new(1, TranspositionTable::new(100_000));
+        let default = agent.search(&position);
+        assert_eq!(default.stats, SearchStats::default());
+
+        agent.set_options(EngineOptions {
+            qsearch_max_ply: 1,
+            ..EngineOptions::default()
+        });
+        agent.tt.clear();
+        let capped = agent.search(&position);
+        assert!(capped.stats.q_ply_cutoffs > 0);
+        assert!(capped.nodes < default.nodes);
+
+        agent.set_options(EngineOptions {
+            qsearch_node_limit: 2,
+            ..EngineOptions::default()
+        });
+        agent.tt.clear();
+        assert!(agent.search(&position).stats.q_node_cutoffs > 0);
+    }
+
+    #[test]
+    fn stop_flag_ends_search() {
+        let stop = Arc::new(AtomicBool::new(true));
+        let agent =
+            AlphaBetaChessAgent::new(20, TranspositionTable::new(100_000)).with_stop_flag(stop);
+        let result = agent.search(&Position::default());
+        assert_eq!(result.depth, 0);
+        assert!(Position::default().legal(result.best_move));
+    }
+
+    #[test]
+    fn options_resize_transposition_table() {
+        let mut agent = AlphaBetaChessAgent::new(4, TranspositionTable::new(100));
+        let mut options = EngineOptions::default();
+        options.set("Hash", "2").unwrap();
+        agent.set_options(options.clone());
+        let capacity = agent.tt.capacity();
+        assert!(capacity > 100);
+        options.set("Contempt", "20").unwrap();
+        agent.set_options(options);
+        assert_eq!(agent.tt.capacity(), capacity);
+    }
+
+    #[test]
+    fn skill_level_limits_depth() {
+        let mut agent = AlphaBetaChessAgent::new(8, TranspositionTable::new(1000));
+        let mut options = EngineOptions::default();
+        options.set("Skill Level", "4").unwrap();
+        agent.set_options(options);
+        assert_eq!(agent.search(&Position::default()).depth, 3);
+    }
+
+    #[test]
+    fn limited_strength_searches_less() {
+        let mut agent = AlphaBetaChessAgent::new(8, TranspositionTable::new(100_000));
+        let mut options = EngineOptions::default();
+        options.set("UCI_LimitStrength", "true").unwrap();
+        options.set("UCI_Elo", "1400").unwrap();
+        options.set("Deterministic", "true").unwrap();
+        agent.set_options(options);
+        let position = Position::default();
+        let result = agent.search(&position);
+        assert!(result.depth <= 3);
+        assert!(result.nodes <= 5_000);
+        assert!(position.legal(result.best_move));
+        assert_eq!(agent.search(&position).best_move, result.best_move);
+    }
 
-        // get best move
-        let best_move = Self::expand(&self.tt, &game.current_position())[0];
+    #[test]
+    fn threads_split_the_root() {
+        let mut agent = AlphaBetaChessAgent::new(4, TranspositionTable::new(100_000));
+        let mut options = EngineOptions::default();
+        options.set("Threads", "4").unwrap();
+        agent.set_options(options);
+        let position = Position::from_str("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let result = agent.search(&position);
+        assert_eq!(result.best_move, ChessMove::from_str("a1a8").unwrap());
+        assert_eq!(result.score, Score::Mate(1));
+        let position = Position::default();
+        let result = agent.search(&position);
+        assert_eq!(result.depth, 4);
+        assert_eq!(result.pv[0], result.best_move);
+        assert!(position.legal(result.best_move));
+    }
 
-        Action::MakeMove(best_move)
+    #[test]
+    fn threads_split_every_node_in_ybwc_mode() {
+        let mut agent = AlphaBetaChessAgent::new(5, TranspositionTable::new(100_000));
+        let mut options = EngineOptions::default();
+        options.set("Threads", "4").unwrap();
+        options.set("Split Mode", "YBWC").unwrap();
+        agent.set_options(options);
+        let position = Position::from_str("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let result = agent.search(&position);
+        assert_eq!(result.best_move, ChessMove::from_str("a1a8").unwrap());
+        assert_eq!(result.score, Score::Mate(1));
+        let position = Position::default();
+        let result = agent.search(&position);
+        assert_eq!(result.depth, 5);
+        assert_eq!(result.pv[0], result.best_move);
+        assert!(position.legal(result.best_move));
     }
 }
@@ -0,0 +1,124 @@
+/// The CPUs to pin the threads of a search to: the thread calling the search
+/// first, then every thread of the pool. Every physical core gets a thread
+/// before any core gets a second one on its SMT siblings, and the sibling
+/// of the calling thread's core is handed out last. Empty when the topology
+/// of the machine isn't known.
+pub(crate) fn search_cpus(threads: usize) -> Vec<usize> {
+    assign(&physical_cores(), threads + 1)
+}
+
+/// Pin the calling thread to a CPU, returns false if the system refused or
+/// doesn't support it
+#[cfg(target_os = "linux")]
+pub(crate) fn pin_current_thread(cpu: usize) -> bool {
+    // SAFETY: the set is zeroed before use and `CPU_SET` checks its bounds,
+    // pid 0 is the calling thread
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == 0
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn pin_current_thread(_cpu: usize) -> bool {
+    false
+}
+
+// The logical CPUs of every physical core, in the order of their first CPU
+#[cfg(target_os = "linux")]
+fn physical_cores() -> Vec<Vec<usize>> {
+    use std::fs;
+
+    let online = match fs::read_to_string("/sys/devices/system/cpu/online") {
+        Ok(online) => parse_cpu_list(&online),
+        Err(_) => return Vec::new(),
+    };
+    let mut cores: Vec<Vec<usize>> = Vec::new();
+    for cpu in online {
+        if cores.iter().any(|core| core.contains(&cpu)) {
+            continue;
+        }
+        let path = format!(
+            "/sys/devices/system/cpu/cpu{}/topology/thread_siblings_list",
+            cpu
+        );
+        let siblings = fs::read_to_string(path)
+            .map(|siblings| parse_cpu_list(&siblings))
+            .unwrap_or_default();
+        cores.push(if siblings.contains(&cpu) {
+            siblings
+        } else {
+            vec![cpu]
+        });
+    }
+    cores
+}
+
+#[cfg(not(target_os = "linux"))]
+fn physical_cores() -> Vec<Vec<usize>> {
+    Vec::new()
+}
+
+// The CPUs of a list like `0-3,8,10-11`, skipping what can't be read
+fn parse_cpu_list(list: &str) -> Vec<usize> {
+    let mut cpus = Vec::new();
+    for range in list.trim().split(',') {
+        let (first, last) = match range.split_once('-') {
+            Some((first, last)) => (first.parse::<usize>(), last.parse::<usize>()),
+            None => (range.parse::<usize>(), range.parse::<usize>()),
+        };
+        if let (Ok(first), Ok(last)) = (first, last) {
+            cpus.extend(first..=last);
+        }
+    }
+    cpus
+}
+
+// The CPU of every thread, starting over once each logical CPU has one
+fn assign(cores: &[Vec<usize>], threads: usize) -> Vec<usize> {
+    let mut order: Vec<usize> = cores
+        .iter()
+        .filter_map(|core| core.first())
+        .copied()
+        .collect();
+    let deepest = cores.iter().map(Vec::len).max().unwrap_or(0);
+    for sibling in 1..deepest {
+        for core in cores.iter().skip(1) {
+            order.extend(core.get(sibling));
+        }
+    }
+    if let Some(first) = cores.first() {
+        order.extend(first.iter().skip(1));
+    }
+    if order.is_empty() {
+        return Vec::new();
+    }
+    order.iter().copied().cycle().take(threads).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assign, parse_cpu_list};
+    use test_case::test_case;
+
+    #[test_case("0-3", vec![0, 1, 2, 3]; "range")]
+    #[test_case("0,2-3,8\n", vec![0, 2, 3, 8]; "mixed")]
+    #[test_case("", vec![]; "empty")]
+    fn cpu_lists(list: &str, cpus: Vec<usize>) {
+        assert_eq!(parse_cpu_list(list), cpus);
+    }
+
+    #[test_case(3, vec![0, 1, 2]; "physical cores first")]
+    #[test_case(6, vec![0, 1, 2, 4, 5, 3]; "main sibling last")]
+    #[test_case(8, vec![0, 1, 2, 4, 5, 3, 0, 1]; "more threads than cpus")]
+    fn smt_siblings_come_last(threads: usize, cpus: Vec<usize>) {
+        let cores = vec![vec![0, 3], vec![1, 4], vec![2, 5]];
+        assert_eq!(assign(&cores, threads), cpus);
+    }
+
+    #[test]
+    fn unknown_topology() {
+        assert!(assign(&[], 4).is_empty());
+    }
+}
@@ -1,8 +1,6 @@
 use super::ChessAgent;
 use crate::Action;
 use crate::ChessGame;
-use crate::ChessMove;
-use core::str::FromStr;
 use std::io;
 
 #[derive(Default)]
@@ -13,8 +11,8 @@ impl ChessAgent for CommandLineAgent {
         let action: Action;
         let board = game.current_position();
         loop {
-            println!("Please enter move (Long algebraic notation) or enter 'resign' to resign");
-            println!("Examples:  e2e4, e7e5, e1g1 (white short castling), e7e8q (for promotion)");
+            println!("Please enter move (Long algebraic notation), 'draw' to offer a draw or 'resign' to resign");
+            println!("Examples:  e2e4, e7e5, e1g1 (white short castling), e7e8q (for promotion), or Nf3 in SAN");
             let mut uci_move = String::new();
             io::stdin()
                 .read_line(&mut uci_move)
@@ -25,16 +23,16 @@ impl ChessAgent for CommandLineAgent {
                     action = Action::Resign(game.side_to_move());
                     break;
                 }
-                uci_move => match ChessMove::from_str(uci_move) {
-                    Ok(uci) => {
-                        if board.legal(uci) {
-                            action = Action::MakeMove(uci);
-                            break;
-                        } else {
-                            println!("Illegal Move for current position");
-                        }
+                "draw" => {
+                    action = Action::OfferDraw(game.side_to_move());
+                    break;
+                }
+                uci_move => match board.parse_move(uci_move) {
+                    Some(chess_move) => {
+                        action = Action::MakeMove(chess_move);
+                        break;
                     }
-                    Err(_) => println!("Failed to parse move format"),
+                    None => println!("Not a legal move in UCI or SAN notation"),
                 },
             }
         }
@@ -19,7 +19,8 @@ impl Default for RandomChessAgent {
 
 impl ChessAgent for RandomChessAgent {
     fn get_action(&self, game: &ChessGame) -> Action {
-        let moves = game.current_position().legal_moves();
-        Action::MakeMove(moves[self.rng.borrow_mut().gen_range(0..moves.len())])
+        let mut moves = game.current_position().legal_moves_iter();
+        let index = self.rng.borrow_mut().gen_range(0..moves.len());
+        Action::MakeMove(moves.nth(index).unwrap())
     }
 }
@@ -0,0 +1,219 @@
+use crate::ChessMove;
+
+/// The deepest ply from the root the search keeps track of
+pub const MAX_PLY: usize = 128;
+
+// Enough room for the legal moves of any position
+const MOVE_LIST_CAPACITY: usize = 256;
+
+/// The positions and static evaluations on the path from the root to a ply,
+/// handed to the threads that help searching the ply's moves
+#[derive(Debug, Clone)]
+pub struct SearchPath {
+    hashes: Vec<u64>,
+    evaluations: Vec<Option<i16>>,
+}
+
+/// Ply-indexed buffers used by a single search thread.
+///
+/// The stack is allocated once and cleared at the start of every search
+/// so nodes don't have to allocate their own move lists, and heuristics
+/// that need to remember something about a ply (killer moves, the
+/// principal variation, static evaluations) have a place to live.
+pub struct SearchStack {
+    killers: [[Option<ChessMove>; 2]; MAX_PLY],
+    evaluations: [Option<i16>; MAX_PLY],
+    // hashes of the positions on the path from the root
+    hashes: [u64; MAX_PLY],
+    // triangular principal variation table
+    pv: Vec<Vec<ChessMove>>,
+    move_lists: Vec<Vec<ChessMove>>,
+}
+
+impl Default for SearchStack {
+    fn default() -> Self {
+        Self {
+            killers: [[None; 2]; MAX_PLY],
+            evaluations: [None; MAX_PLY],
+            hashes: [0; MAX_PLY],
+            pv: (0..MAX_PLY)
+                .map(|ply| Vec::with_capacity(MAX_PLY - ply))
+                .collect(),
+            move_lists: (0..MAX_PLY)
+                .map(|_| Vec::with_capacity(MOVE_LIST_CAPACITY))
+                .collect(),
+        }
+    }
+}
+
+impl SearchStack {
+    /// Forget everything from the previous search while keeping the allocations
+    pub fn clear(&mut self) {
+        self.killers = [[None; 2]; MAX_PLY];
+        self.evaluations = [None; MAX_PLY];
+        self.hashes = [0; MAX_PLY];
+        for pv in self.pv.iter_mut() {
+            pv.clear();
+        }
+        for moves in self.move_lists.iter_mut() {
+            moves.clear();
+        }
+    }
+
+    /// Borrow the move list buffer for a ply.
+    /// It must be given back with [`SearchStack::give_moves`] once the node is done with it.
+    pub fn take_moves(&mut self, ply: usize) -> Vec<ChessMove> {
+        let mut moves = std::mem::take(&mut self.move_lists[ply]);
+        moves.clear();
+        moves
+    }
+
+    pub fn give_moves(&mut self, ply: usize, moves: Vec<ChessMove>) {
+        self.move_lists[ply] = moves;
+    }
+
+    pub fn killers(&self, ply: usize) -> [Option<ChessMove>; 2] {
+        self.killers[ply]
+    }
+
+    /// Remember a quiet move that caused a beta cutoff at this ply
+    pub fn store_killer(&mut self, ply: usize, chess_move: ChessMove) {
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(chess_move) {
+            killers[1] = killers[0];
+            killers[0] = Some(chess_move);
+        }
+    }
+
+    /// Returns true if the static evaluation at this ply is better than
+    /// it was on the side to move's previous turn
+    pub fn improving(&self, ply: usize) -> bool {
+        ply >= 2
+            && match (self.evaluations[ply], self.evaluations[ply - 2]) {
+                (Some(current), Some(previous)) => current > previous,
+                _ => false,
+            }
+    }
+
+    pub fn set_evaluation(&mut self, ply: usize, evaluation: i16) {
+        self.evaluations[ply] = Some(evaluation);
+    }
+
+    /// Record the position searched at this ply, replacing the one of the previous path
+    pub fn set_hash(&mut self, ply: usize, hash: u64) {
+        self.hashes[ply] = hash;
+    }
+
+    /// Returns true if the position at this ply already occurred
+    /// on the path from the root
+    pub fn repeats(&self, ply: usize) -> bool {
+        self.hashes[..ply].contains(&self.hashes[ply])
+    }
+
+    /// The hashes of the positions on the path from the root to this ply, including it
+    pub fn hashes(&self, ply: usize) -> &[u64] {
+        &self.hashes[..=ply]
+    }
+
+    /// The path from the root to this ply, including it
+    pub fn path(&self, ply: usize) -> SearchPath {
+        SearchPath {
+            hashes: self.hashes[..=ply].to_vec(),
+            evaluations: self.evaluations[..=ply].to_vec(),
+        }
+    }
+
+    /// Continue from the path of another thread's stack, so repetitions of
+    /// the positions on it are still found
+    pub fn set_path(&mut self, path: &SearchPath) {
+        self.hashes[..path.hashes.len()].copy_from_slice(&path.hashes);
+        self.evaluations[..path.evaluations.len()].copy_from_slice(&path.evaluations);
+    }
+
+    pub fn clear_pv(&mut self, ply: usize) {
+        self.pv[ply].clear();
+    }
+
+    /// Replace the principal variation of a ply, for example with one found
+    /// by another search thread
+    pub fn set_pv(&mut self, ply: usize, pv: &[ChessMove]) {
+        self.pv[ply].clear();
+        self.pv[ply].extend_from_slice(pv);
+    }
+
+    /// Set the principal variation of a ply to the move followed by
+    /// the principal variation of the next ply
+    pub fn update_pv(&mut self, ply: usize, chess_move: ChessMove) {
+        let (current, rest) = self.pv.split_at_mut(ply + 1);
+        let pv = &mut current[ply];
+        pv.clear();
+        pv.push(chess_move);
+        if let Some(child_pv) = rest.first() {
+            pv.extend_from_slice(child_pv);
+        }
+    }
+
+    pub fn pv(&self, ply: usize) -> &[ChessMove] {
+        &self.pv[ply]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SearchStack;
+    use crate::{ChessMove, Square};
+
+    #[test]
+    fn pv_is_built_from_child_pv() {
+        let mut stack = SearchStack::default();
+        let e4 = ChessMove::new(Square::E2, Square::E4, None);
+        let e5 = ChessMove::new(Square::E7, Square::E5, None);
+        stack.update_pv(1, e5);
+        stack.update_pv(0, e4);
+        assert_eq!(stack.pv(0), &[e4, e5]);
+        stack.clear();
+        assert!(stack.pv(0).is_empty());
+    }
+
+    #[test]
+    fn killers_keep_two_most_recent() {
+        let mut stack = SearchStack::default();
+        let a = ChessMove::new(Square::E2, Square::E4, None);
+        let b = ChessMove::new(Square::D2, Square::D4, None);
+        let c = ChessMove::new(Square::C2, Square::C4, None);
+        stack.store_killer(3, a);
+        stack.store_killer(3, a);
+        assert_eq!(stack.killers(3), [Some(a), None]);
+        stack.store_killer(3, b);
+        stack.store_killer(3, c);
+        assert_eq!(stack.killers(3), [Some(c), Some(b)]);
+    }
+
+    #[test]
+    fn improving_compares_same_side_evaluations() {
+        let mut stack = SearchStack::default();
+        stack.set_evaluation(2, 10);
+        stack.set_evaluation(3, 50);
+        stack.set_evaluation(4, 20);
+        assert!(stack.improving(4));
+        assert!(!stack.improving(3));
+        stack.set_evaluation(5, -50);
+        assert!(!stack.improving(5));
+    }
+
+    #[test]
+    fn repetitions_on_the_current_path() {
+        let mut stack = SearchStack::default();
+        stack.set_hash(0, 1);
+        stack.set_hash(1, 2);
+        stack.set_hash(2, 3);
+        assert!(!stack.repeats(2));
+        stack.set_hash(3, 2);
+        assert!(stack.repeats(3));
+        // a new path replaces the positions of the previous one
+        stack.set_hash(1, 4);
+        stack.set_hash(2, 5);
+        stack.set_hash(3, 2);
+        assert!(!stack.repeats(3));
+    }
+}
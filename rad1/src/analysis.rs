@@ -0,0 +1,267 @@
+use crate::agent::{AlphaBetaChessAgent, MAX_DEPTH};
+use crate::search::SearchResult;
+use crate::{ChessMove, Position};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+// Iterations in a row with the same best move before the main line counts as stable
+const STABLE_ITERATIONS: usize = 3;
+// The share of the threads searching the alternatives once the main line is stable
+const REFINE_SHARE: f64 = 0.25;
+
+/// One of the lines of an [`InfiniteAnalysis`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnalysisLine {
+    /// 1 for the best move, 2 for the best move without it and so on
+    pub rank: usize,
+    pub result: SearchResult,
+}
+
+/// Analysis that goes deeper and deeper until it's stopped, keeping the
+/// best move and the next best ones.
+///
+/// Every thread searches the best move at first. Once it has stayed the same
+/// for a few iterations, a share of the threads moves to a second agent that
+/// searches the position without the best move, then without the two best
+/// and so on, iteration after iteration, so the alternatives get deeper
+/// while the main line does instead of staying at the depth they had when
+/// it settled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InfiniteAnalysis {
+    /// How many lines are kept, the best move included
+    pub lines: usize,
+    /// The threads of both searches together, at least one each
+    pub threads: usize,
+    /// The share of the threads given to the alternatives
+    pub refine_share: f64,
+    /// The analysis ends once both searches completed this iteration, if
+    /// it isn't stopped before
+    pub max_depth: u8,
+}
+
+impl InfiniteAnalysis {
+    pub fn new(lines: usize, threads: usize) -> Self {
+        Self {
+            lines,
+            threads,
+            refine_share: REFINE_SHARE,
+            max_depth: MAX_DEPTH,
+        }
+    }
+
+    /// Analyze the position until `stop` is set, calling `on_line` with
+    /// every line completed one iteration deeper, from either search's
+    /// thread. Returns the deepest line of every rank, best first.
+    ///
+    /// `main` searches the best move and `alternatives` the others on a
+    /// thread of its own. The searches change the depth, threads and
+    /// excluded moves of the agents, which should get a transposition table
+    /// each: the alternatives leave other scores for the position in the table.
+    pub fn run<G>(
+        &self,
+        position: &Position,
+        main: AlphaBetaChessAgent,
+        alternatives: AlphaBetaChessAgent,
+        stop: Arc<AtomicBool>,
+        on_line: G,
+    ) -> Vec<AnalysisLine>
+    where
+        G: Fn(&AnalysisLine) + Send + Sync + 'static,
+    {
+        let lines = self.lines.min(position.count_moves());
+        if lines == 0 {
+            return Vec::new();
+        }
+        let shared = Arc::new(Shared {
+            lines: Mutex::new(vec![None; lines]),
+            on_line,
+        });
+        let mut alternatives = Some(alternatives);
+        let refine_threads = ((self.threads as f64 * self.refine_share).round() as usize).max(1);
+        let mut refinement = None;
+        let mut main = main.with_stop_flag(stop.clone());
+        set_threads(&mut main, self.threads);
+        let mut best_moves: Vec<ChessMove> = Vec::new();
+        for depth in 1..=self.max_depth {
+            main.set_depth(depth);
+            let result = main.search(position);
+            if stop.load(Ordering::SeqCst) {
+                break;
+            }
+            best_moves.push(result.best_move);
+            shared.report(AnalysisLine { rank: 1, result });
+            let stable = best_moves.len() >= STABLE_ITERATIONS
+                && best_moves[best_moves.len() - STABLE_ITERATIONS..]
+                    .windows(2)
+                    .all(|pair| pair[0] == pair[1]);
+            if stable && lines > 1 {
+                if let Some(agent) = alternatives.take() {
+                    set_threads(
+                        &mut main,
+                        self.threads.saturating_sub(refine_threads).max(1),
+                    );
+                    refinement = Some(self.refine(position, refine_threads, &stop, agent, &shared));
+                }
+            }
+        }
+        // the alternatives of a main line that never settled are searched after it
+        if let Some(agent) = alternatives.filter(|_| lines > 1 && !stop.load(Ordering::SeqCst)) {
+            refinement = Some(self.refine(position, self.threads, &stop, agent, &shared));
+        }
+        if let Some(refinement) = refinement {
+            refinement.join().expect("The refinement search failed");
+        }
+        let lines = shared.lines.lock().unwrap();
+        lines.iter().flatten().cloned().collect()
+    }
+
+    fn refine<G>(
+        &self,
+        position: &Position,
+        threads: usize,
+        stop: &Arc<AtomicBool>,
+        agent: AlphaBetaChessAgent,
+        shared: &Arc<Shared<G>>,
+    ) -> JoinHandle<()>
+    where
+        G: Fn(&AnalysisLine) + Send + Sync + 'static,
+    {
+        let refinement = Refinement {
+            position: position.clone(),
+            threads,
+            max_depth: self.max_depth,
+            stop: stop.clone(),
+        };
+        refinement.start(agent, shared.clone())
+    }
+}
+
+// The lines found so far and who is told about new ones
+struct Shared<G> {
+    lines: Mutex<Vec<Option<AnalysisLine>>>,
+    on_line: G,
+}
+
+impl<G: Fn(&AnalysisLine)> Shared<G> {
+    fn report(&self, line: AnalysisLine) {
+        let mut lines = self.lines.lock().unwrap();
+        (self.on_line)(&line);
+        let rank = line.rank;
+        lines[rank - 1] = Some(line);
+    }
+
+    fn best_move(&self) -> Option<ChessMove> {
+        self.lines.lock().unwrap()[0]
+            .as_ref()
+            .map(|line| line.result.best_move)
+    }
+}
+
+// Searches the alternatives to the best move on a thread of its own
+struct Refinement {
+    position: Position,
+    threads: usize,
+    max_depth: u8,
+    stop: Arc<AtomicBool>,
+}
+
+impl Refinement {
+    fn start<G>(self, agent: AlphaBetaChessAgent, shared: Arc<Shared<G>>) -> JoinHandle<()>
+    where
+        G: Fn(&AnalysisLine) + Send + Sync + 'static,
+    {
+        thread::spawn(move || {
+            let mut agent = agent.with_stop_flag(self.stop.clone());
+            set_threads(&mut agent, self.threads);
+            let lines = shared.lines.lock().unwrap().len();
+            for depth in 1..=self.max_depth {
+                agent.set_depth(depth);
+                // the main line may have changed its mind since the last iteration
+                let mut excluded: Vec<ChessMove> = shared.best_move().into_iter().collect();
+                for rank in 2..=lines {
+                    agent.set_excluded_moves(excluded.clone());
+                    let result = agent.search(&self.position);
+                    if self.stop.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    excluded.push(result.best_move);
+                    shared.report(AnalysisLine { rank, result });
+                }
+            }
+        })
+    }
+}
+
+fn set_threads(agent: &mut AlphaBetaChessAgent, threads: usize) {
+    let mut options = agent.options().clone();
+    options.threads = threads;
+    agent.set_options(options);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InfiniteAnalysis;
+    use crate::agent;
+    use crate::score::Score;
+    use crate::tt::TranspositionTable;
+    use crate::{ChessMove, Position};
+    use std::str::FromStr;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn alternatives_are_searched_as_deep() {
+        let position = Position::from_str("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let analysis = InfiniteAnalysis {
+            max_depth: 5,
+            ..InfiniteAnalysis::new(3, 2)
+        };
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let lines = {
+            let reported = reported.clone();
+            analysis.run(
+                &position,
+                agent::alpha_beta_agent(1, TranspositionTable::new(100_000)),
+                agent::alpha_beta_agent(1, TranspositionTable::new(100_000)),
+                Arc::new(AtomicBool::new(false)),
+                move |line| {
+                    reported
+                        .lock()
+                        .unwrap()
+                        .push((line.rank, line.result.depth))
+                },
+            )
+        };
+        let ranks: Vec<usize> = lines.iter().map(|line| line.rank).collect();
+        assert_eq!(ranks, vec![1, 2, 3]);
+        assert_eq!(
+            lines[0].result.best_move,
+            ChessMove::from_str("a1a8").unwrap()
+        );
+        assert_eq!(lines[0].result.score, Score::Mate(1));
+        assert!(lines[1].result.score < lines[0].result.score);
+        assert_ne!(lines[1].result.best_move, lines[2].result.best_move);
+        assert!(lines[1..]
+            .iter()
+            .all(|line| line.result.best_move != lines[0].result.best_move));
+        // every rank went through every iteration
+        let reported = reported.lock().unwrap();
+        for rank in 1..=3 {
+            assert_eq!(reported.iter().filter(|&&(r, _)| r == rank).count(), 5);
+        }
+    }
+
+    #[test]
+    fn stopped_analysis_keeps_nothing_new() {
+        let analysis = InfiniteAnalysis::new(2, 1);
+        let lines = analysis.run(
+            &Position::default(),
+            agent::alpha_beta_agent(1, TranspositionTable::new(100_000)),
+            agent::alpha_beta_agent(1, TranspositionTable::new(100_000)),
+            Arc::new(AtomicBool::new(true)),
+            |_| panic!("a stopped analysis reported a line"),
+        );
+        assert!(lines.is_empty());
+    }
+}
@@ -0,0 +1,200 @@
+use crate::correspondence::Analysis;
+use crate::score::Score;
+use crate::search::{ScoreBound, SearchResult, SearchStats};
+use crate::ChessMove;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Analyses of earlier sessions, kept in a file so positions analyzed
+/// before are answered right away instead of searched again. They are keyed
+/// by the hash of the position and the depth it was searched to, and a
+/// deeper analysis answers for a shallower one.
+///
+/// The cache doesn't know the evaluator and options the analyses were made
+/// with, nor the moves that led to the position, so every engine setup
+/// should keep a file of its own.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnalysisCache {
+    analyses: BTreeMap<(u64, u8), Analysis>,
+}
+
+/// The reason an analysis cache file couldn't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnalysisCacheError {
+    /// A line that isn't a hash followed by the depth, best move, score,
+    /// nodes and principal variation
+    InvalidLine(String),
+}
+
+impl fmt::Display for AnalysisCacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnalysisCacheError::InvalidLine(line) => write!(f, "invalid line '{}'", line),
+        }
+    }
+}
+
+impl Error for AnalysisCacheError {}
+
+impl AnalysisCache {
+    /// The deepest analysis of the position with the hash that went at
+    /// least to the depth
+    pub fn get(&self, hash: u64, depth: u8) -> Option<&Analysis> {
+        self.analyses
+            .range((hash, depth)..=(hash, u8::MAX))
+            .next_back()
+            .map(|(_, analysis)| analysis)
+    }
+
+    /// The analysis of [`get`](Self::get) as the result of a search to its
+    /// depth, which took no time
+    pub fn result(&self, hash: u64, depth: u8) -> Option<SearchResult> {
+        self.get(hash, depth).map(|analysis| SearchResult {
+            best_move: analysis.best_move,
+            score: analysis.score,
+            bound: ScoreBound::Exact,
+            depth: analysis.depth,
+            seldepth: analysis.depth,
+            pv: analysis.pv.clone(),
+            nodes: analysis.nodes,
+            elapsed: Duration::default(),
+            stats: SearchStats::default(),
+            best_moves: vec![analysis.best_move],
+            root_moves: Vec::new(),
+            lines: Vec::new(),
+        })
+    }
+
+    /// Keep the result of searching the position with the hash, dropping
+    /// the shallower analyses of the position. Results that are only a
+    /// bound aren't kept, returns whether it was.
+    pub fn insert(&mut self, hash: u64, result: &SearchResult) -> bool {
+        if !result.bound.is_exact() {
+            return false;
+        }
+        let shallower: Vec<(u64, u8)> = self
+            .analyses
+            .range((hash, 0)..(hash, result.depth))
+            .map(|(&key, _)| key)
+            .collect();
+        for key in shallower {
+            self.analyses.remove(&key);
+        }
+        self.analyses.insert(
+            (hash, result.depth),
+            Analysis {
+                depth: result.depth,
+                best_move: result.best_move,
+                score: result.score,
+                pv: result.pv.clone(),
+                nodes: result.nodes,
+            },
+        );
+        true
+    }
+
+    /// Number of analyses
+    pub fn len(&self) -> usize {
+        self.analyses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.analyses.is_empty()
+    }
+
+    /// The analyses as lines of a hexadecimal hash followed by the depth,
+    /// best move, score, nodes and principal variation
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("# hash depth best_move score nodes pv\n");
+        for ((hash, depth), analysis) in &self.analyses {
+            let pv: Vec<String> = analysis.pv.iter().map(|m| m.to_string()).collect();
+            text.push_str(&format!(
+                "{:016x} {} {} {} {} {}\n",
+                hash,
+                depth,
+                analysis.best_move,
+                i16::from(analysis.score),
+                analysis.nodes,
+                pv.join(" ")
+            ));
+        }
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, AnalysisCacheError> {
+        let mut cache = Self::default();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || AnalysisCacheError::InvalidLine(line.to_string());
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (hash, depth, best_move, score, nodes, pv) = match fields.as_slice() {
+                [hash, depth, best_move, score, nodes, pv @ ..] => {
+                    (hash, depth, best_move, score, nodes, pv)
+                }
+                _ => return Err(invalid()),
+            };
+            let hash = u64::from_str_radix(hash, 16).map_err(|_| invalid())?;
+            let analysis = Analysis {
+                depth: depth.parse().map_err(|_| invalid())?,
+                best_move: ChessMove::from_str(best_move).map_err(|_| invalid())?,
+                score: Score::from(score.parse::<i16>().map_err(|_| invalid())?),
+                pv: pv
+                    .iter()
+                    .map(|value| ChessMove::from_str(value).map_err(|_| invalid()))
+                    .collect::<Result<_, _>>()?,
+                nodes: nodes.parse().map_err(|_| invalid())?,
+            };
+            cache.analyses.insert((hash, analysis.depth), analysis);
+        }
+        Ok(cache)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnalysisCache, AnalysisCacheError};
+    use crate::agent;
+    use crate::tt::TranspositionTable;
+    use crate::Position;
+    use std::str::FromStr;
+
+    #[test]
+    fn deeper_analyses_answer_for_shallower_ones() {
+        let position = Position::default();
+        let hash = position.get_hash();
+        let mut cache = AnalysisCache::default();
+        let shallow = agent::alpha_beta_agent(2, TranspositionTable::new(10_000)).search(&position);
+        let deep = agent::alpha_beta_agent(4, TranspositionTable::new(10_000)).search(&position);
+        assert!(cache.insert(hash, &shallow));
+        assert_eq!(cache.result(hash, 2).unwrap().depth, 2);
+        assert_eq!(cache.result(hash, 3), None);
+        assert!(cache.insert(hash, &deep));
+        // the shallow analysis made way for the deep one
+        assert_eq!(cache.len(), 1);
+        let cached = cache.result(hash, 3).unwrap();
+        assert_eq!(cached.depth, 4);
+        assert_eq!(cached.best_move, deep.best_move);
+        assert_eq!(cached.score, deep.score);
+        assert_eq!(cached.pv, deep.pv);
+        assert_eq!(cache.result(hash + 1, 1), None);
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let position = Position::from_str("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let result = agent::alpha_beta_agent(3, TranspositionTable::new(10_000)).search(&position);
+        let mut cache = AnalysisCache::default();
+        cache.insert(position.get_hash(), &result);
+        let text = cache.to_text();
+        assert_eq!(AnalysisCache::from_text(&text), Ok(cache));
+        assert_eq!(
+            AnalysisCache::from_text("00ff 3 e2e4"),
+            Err(AnalysisCacheError::InvalidLine(String::from("00ff 3 e2e4")))
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use crate::agent::AlphaBetaChessAgent;
+use crate::score::Score;
+use crate::{ChessMove, Position};
+use std::io::{self, Write};
+
+// Chessground brush of the best move and of the alternatives
+const BEST_BRUSH: &str = "green";
+const ALTERNATIVE_BRUSH: &str = "paleBlue";
+
+/// One of the top moves of a position, drawn as an arrow on a board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arrow {
+    pub chess_move: ChessMove,
+    /// The score of the move from the side to move's point of view
+    pub score: Score,
+}
+
+/// The best `count` moves of the position with their scores, best first:
+/// the best move of a search, then the best move of a search without it and
+/// so on. Fewer are returned when the position has fewer legal moves.
+pub fn top_moves(agent: &mut AlphaBetaChessAgent, position: &Position, count: usize) -> Vec<Arrow> {
+    let mut arrows: Vec<Arrow> = Vec::new();
+    for _ in 0..count.min(position.count_moves()) {
+        agent.set_excluded_moves(arrows.iter().map(|arrow| arrow.chess_move).collect());
+        let result = agent.search(position);
+        arrows.push(Arrow {
+            chess_move: result.best_move,
+            score: result.score,
+        });
+    }
+    agent.set_excluded_moves(Vec::new());
+    arrows
+}
+
+/// Write the arrows as a JSON array of shapes that chessground-based boards
+/// draw as they are, with `orig`, `dest` and `brush`, plus the move in UCI
+/// notation and its score as `{"cp": <centipawns>}` or `{"mate": <moves>}`.
+/// The best move gets a green arrow and the others pale blue ones.
+pub fn write_json(out: &mut impl Write, arrows: &[Arrow]) -> io::Result<()> {
+    writeln!(out, "[")?;
+    for (index, arrow) in arrows.iter().enumerate() {
+        writeln!(
+            out,
+            "  {{\"orig\": \"{}\", \"dest\": \"{}\", \"brush\": \"{}\", \"move\": \"{}\", \"score\": {}}}{}",
+            arrow.chess_move.get_source(),
+            arrow.chess_move.get_dest(),
+            if index == 0 { BEST_BRUSH } else { ALTERNATIVE_BRUSH },
+            arrow.chess_move,
+            arrow.score.to_json(),
+            if index + 1 < arrows.len() { "," } else { "" }
+        )?;
+    }
+    writeln!(out, "]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{top_moves, write_json, Arrow};
+    use crate::agent;
+    use crate::score::Score;
+    use crate::tt::TranspositionTable;
+    use crate::{ChessMove, Position, Square};
+    use std::str::FromStr;
+
+    #[test]
+    fn best_moves_come_first() {
+        let position = Position::from_str("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let mut agent = agent::alpha_beta_agent(3, TranspositionTable::new(100_000));
+        let arrows = top_moves(&mut agent, &position, 3);
+        assert_eq!(arrows.len(), 3);
+        assert_eq!(arrows[0].chess_move, ChessMove::from_str("a1a8").unwrap());
+        assert_eq!(arrows[0].score, Score::Mate(1));
+        assert!(arrows[1].score < arrows[0].score);
+        assert!(arrows[2].score <= arrows[1].score);
+        assert_ne!(arrows[1].chess_move, arrows[2].chess_move);
+    }
+
+    #[test]
+    fn json() {
+        let arrows = [
+            Arrow {
+                chess_move: ChessMove::new(Square::E2, Square::E4, None),
+                score: Score::Eval(3),
+            },
+            Arrow {
+                chess_move: ChessMove::new(Square::D1, Square::H5, None),
+                score: Score::Mate(5),
+            },
+        ];
+        let mut out = Vec::new();
+        write_json(&mut out, &arrows).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[\n  {\"orig\": \"e2\", \"dest\": \"e4\", \"brush\": \"green\", \"move\": \"e2e4\", \"score\": {\"cp\": 30}},\n  {\"orig\": \"d1\", \"dest\": \"h5\", \"brush\": \"paleBlue\", \"move\": \"d1h5\", \"score\": {\"mate\": 3}}\n]\n"
+        );
+    }
+}
@@ -0,0 +1,323 @@
+use crate::{BitBoard, CastleRights, ChessMove, Color, ParseError, Piece, PositionStatus, Square};
+use chess::MoveGen;
+use std::fmt;
+use std::str::FromStr;
+
+/// The board representation [`Position`](crate::Position) is built on.
+///
+/// Position queries, move generation and attack lookups go through this
+/// trait, so another library can generate the moves by implementing it and
+/// changing [`DefaultBackend`]. Squares, pieces, colors, moves and bitboards
+/// stay the `chess` crate's types, which rad1 exports under its own names,
+/// so a backend built on another library converts to and from them.
+/// [`ChessGame`](crate::ChessGame) still keeps its history with the `chess`
+/// crate.
+pub trait Backend: Sized + Clone + Default + FromStr<Err = ParseError> + fmt::Display {
+    /// An iterator over legal moves that knows how many are left
+    type Moves: ExactSizeIterator<Item = ChessMove>;
+
+    /// The Zobrist hash of the position
+    fn get_hash(&self) -> u64;
+    fn side_to_move(&self) -> Color;
+    fn status(&self) -> PositionStatus;
+    fn piece_on(&self, square: Square) -> Option<Piece>;
+    fn color_on(&self, square: Square) -> Option<Color>;
+    /// The squares of the pieces of the given kind, of both colors
+    fn pieces(&self, piece: Piece) -> BitBoard;
+    fn color_combined(&self, color: Color) -> BitBoard;
+    /// Every occupied square
+    fn combined(&self) -> BitBoard;
+    /// The pieces giving check to the side to move
+    fn checkers(&self) -> BitBoard;
+    /// The side to move's pieces that are pinned to its king
+    fn pinned(&self) -> BitBoard;
+    fn castle_rights(&self, color: Color) -> CastleRights;
+    /// The square of the pawn that can be captured en passant
+    fn en_passant(&self) -> Option<Square>;
+    /// The pieces of the given color attacking the square
+    fn attackers_of(&self, square: Square, color: Color) -> BitBoard {
+        self.attackers_through(square, self.combined()) & self.color_combined(color)
+    }
+    /// The pieces of both colors on the occupied squares attacking the
+    /// square when only those squares are occupied, so sliders attack
+    /// through the pieces that were taken off
+    fn attackers_through(&self, square: Square, occupied: BitBoard) -> BitBoard;
+    fn legal(&self, chess_move: ChessMove) -> bool;
+    /// Whether the legal move checks the other king, worked out from the
+    /// board where possible instead of by making the move
+    fn gives_check(&self, chess_move: ChessMove) -> bool {
+        self.make_move_new(chess_move).checkers().popcnt() > 0
+    }
+    fn legal_moves(&self) -> Self::Moves;
+    /// The legal moves ending on one of the target squares
+    fn legal_moves_to(&self, targets: BitBoard) -> Self::Moves;
+    fn make_move_new(&self, chess_move: ChessMove) -> Self;
+    /// The position after passing the turn, None when in check
+    fn null_move(&self) -> Option<Self>;
+
+    /// The squares a king on the square attacks
+    fn king_attacks(square: Square) -> BitBoard;
+    fn knight_attacks(square: Square) -> BitBoard;
+    /// The squares a bishop on the square attacks, up to and including the
+    /// first occupied square in each direction
+    fn bishop_attacks(square: Square, occupied: BitBoard) -> BitBoard;
+    /// The squares a rook on the square attacks, up to and including the
+    /// first occupied square in each direction
+    fn rook_attacks(square: Square, occupied: BitBoard) -> BitBoard;
+    /// The squares a pawn of the color on the square attacks
+    fn pawn_attacks(square: Square, color: Color) -> BitBoard;
+}
+
+/// The backend used by [`Position`](crate::Position)
+pub type DefaultBackend = chess::Board;
+
+impl Backend for chess::Board {
+    type Moves = MoveGen;
+
+    #[inline]
+    fn get_hash(&self) -> u64 {
+        chess::Board::get_hash(self)
+    }
+
+    #[inline]
+    fn side_to_move(&self) -> Color {
+        chess::Board::side_to_move(self)
+    }
+
+    #[inline]
+    fn status(&self) -> PositionStatus {
+        chess::Board::status(self)
+    }
+
+    #[inline]
+    fn piece_on(&self, square: Square) -> Option<Piece> {
+        chess::Board::piece_on(self, square)
+    }
+
+    #[inline]
+    fn color_on(&self, square: Square) -> Option<Color> {
+        chess::Board::color_on(self, square)
+    }
+
+    #[inline]
+    fn pieces(&self, piece: Piece) -> BitBoard {
+        *chess::Board::pieces(self, piece)
+    }
+
+    #[inline]
+    fn color_combined(&self, color: Color) -> BitBoard {
+        *chess::Board::color_combined(self, color)
+    }
+
+    #[inline]
+    fn combined(&self) -> BitBoard {
+        *chess::Board::combined(self)
+    }
+
+    #[inline]
+    fn checkers(&self) -> BitBoard {
+        *chess::Board::checkers(self)
+    }
+
+    #[inline]
+    fn pinned(&self) -> BitBoard {
+        *chess::Board::pinned(self)
+    }
+
+    #[inline]
+    fn castle_rights(&self, color: Color) -> CastleRights {
+        chess::Board::castle_rights(self, color)
+    }
+
+    #[inline]
+    fn en_passant(&self) -> Option<Square> {
+        chess::Board::en_passant(self)
+    }
+
+    fn attackers_through(&self, square: Square, occupied: BitBoard) -> BitBoard {
+        let queens = self.pieces(Piece::Queen);
+        let diagonal = self.pieces(Piece::Bishop) | queens;
+        let straight = self.pieces(Piece::Rook) | queens;
+        let pawns = self.pieces(Piece::Pawn);
+        // a pawn of the other color on the square attacks where the attacking pawns are
+        let pawn_attackers =
+            (Self::pawn_attacks(square, Color::Black) & pawns & self.color_combined(Color::White))
+                | (Self::pawn_attacks(square, Color::White)
+                    & pawns
+                    & self.color_combined(Color::Black));
+        let attackers = pawn_attackers
+            | (Self::knight_attacks(square) & self.pieces(Piece::Knight))
+            | (Self::bishop_attacks(square, occupied) & diagonal)
+            | (Self::rook_attacks(square, occupied) & straight)
+            | (Self::king_attacks(square) & self.pieces(Piece::King));
+        attackers & occupied
+    }
+
+    #[inline]
+    fn legal(&self, chess_move: ChessMove) -> bool {
+        chess::Board::legal(self, chess_move)
+    }
+
+    fn gives_check(&self, chess_move: ChessMove) -> bool {
+        let source = chess_move.get_source();
+        let dest = chess_move.get_dest();
+        let piece = match self.piece_on(source) {
+            Some(piece) => piece,
+            None => return false,
+        };
+        let sideways = source.get_file() != dest.get_file();
+        let castles = piece == Piece::King
+            && (source.get_file().to_index() as i8 - dest.get_file().to_index() as i8).abs() == 2;
+        let en_passant = piece == Piece::Pawn && sideways && self.piece_on(dest).is_none();
+        // the rook of castling and the pawn taken en passant move too, both are rare
+        if castles || en_passant {
+            return chess::Board::make_move_new(self, chess_move)
+                .checkers()
+                .popcnt()
+                > 0;
+        }
+        let color = self.side_to_move();
+        let king = self.pieces(Piece::King) & self.color_combined(!color);
+        let king_square = king.to_square();
+        let occupied =
+            (self.combined() ^ BitBoard::from_square(source)) | BitBoard::from_square(dest);
+        let direct = match chess_move.get_promotion().unwrap_or(piece) {
+            Piece::Pawn => Self::pawn_attacks(dest, color) & king,
+            Piece::Knight => Self::knight_attacks(dest) & king,
+            Piece::Bishop => Self::bishop_attacks(dest, occupied) & king,
+            Piece::Rook => Self::rook_attacks(dest, occupied) & king,
+            Piece::Queen => {
+                (Self::bishop_attacks(dest, occupied) | Self::rook_attacks(dest, occupied)) & king
+            }
+            Piece::King => chess::EMPTY,
+        };
+        // the sliders the moving piece stood in front of
+        let ours = self.color_combined(color) & !BitBoard::from_square(source);
+        let queens = self.pieces(Piece::Queen);
+        let discovered = (Self::bishop_attacks(king_square, occupied)
+            & (self.pieces(Piece::Bishop) | queens)
+            & ours)
+            | (Self::rook_attacks(king_square, occupied)
+                & (self.pieces(Piece::Rook) | queens)
+                & ours);
+        (direct | discovered).popcnt() > 0
+    }
+
+    #[inline]
+    fn legal_moves(&self) -> MoveGen {
+        MoveGen::new_legal(self)
+    }
+
+    #[inline]
+    fn legal_moves_to(&self, targets: BitBoard) -> MoveGen {
+        let mut moves = MoveGen::new_legal(self);
+        moves.set_iterator_mask(targets);
+        moves
+    }
+
+    #[inline]
+    fn make_move_new(&self, chess_move: ChessMove) -> Self {
+        chess::Board::make_move_new(self, chess_move)
+    }
+
+    #[inline]
+    fn null_move(&self) -> Option<Self> {
+        chess::Board::null_move(self)
+    }
+
+    #[inline]
+    fn king_attacks(square: Square) -> BitBoard {
+        chess::get_king_moves(square)
+    }
+
+    #[inline]
+    fn knight_attacks(square: Square) -> BitBoard {
+        chess::get_knight_moves(square)
+    }
+
+    #[inline]
+    fn bishop_attacks(square: Square, occupied: BitBoard) -> BitBoard {
+        chess::get_bishop_moves(square, occupied)
+    }
+
+    #[inline]
+    fn rook_attacks(square: Square, occupied: BitBoard) -> BitBoard {
+        chess::get_rook_moves(square, occupied)
+    }
+
+    #[inline]
+    fn pawn_attacks(square: Square, color: Color) -> BitBoard {
+        chess::get_pawn_attacks(square, color, !chess::EMPTY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Backend, DefaultBackend};
+    use crate::{BitBoard, Color, Square};
+    use std::str::FromStr;
+
+    #[test]
+    fn moves_to_targets() {
+        let board = DefaultBackend::default();
+        let targets = BitBoard::from_square(Square::E4) | BitBoard::from_square(Square::F3);
+        let moves: Vec<String> = board
+            .legal_moves_to(targets)
+            .map(|m| m.to_string())
+            .collect();
+        assert_eq!(moves.len(), 3);
+        assert!(moves.contains(&String::from("e2e4")));
+        assert!(moves.contains(&String::from("f2f3")));
+        assert!(moves.contains(&String::from("g1f3")));
+        assert_eq!(board.legal_moves().len(), 20);
+    }
+
+    #[test]
+    fn checks_without_making_the_move() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "4k3/8/8/2KPp2r/8/8/8/8 w - e6 0 1",
+            "5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+        ];
+        for fen in fens.iter() {
+            let board = DefaultBackend::from_str(fen).unwrap();
+            for chess_move in board.legal_moves() {
+                let made = board.make_move_new(chess_move).checkers().popcnt() > 0;
+                assert_eq!(
+                    board.gives_check(chess_move),
+                    made,
+                    "{} in {}",
+                    chess_move,
+                    fen
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn attack_lookups() {
+        let a3 = BitBoard::from_square(Square::A3);
+        let rook = DefaultBackend::rook_attacks(Square::A1, a3);
+        assert_eq!(rook.popcnt(), 9);
+        assert!(rook & a3 == a3);
+        assert!(rook & BitBoard::from_square(Square::A4) == BitBoard::new(0));
+        assert_eq!(DefaultBackend::bishop_attacks(Square::A1, a3).popcnt(), 7);
+        assert_eq!(DefaultBackend::knight_attacks(Square::A1).popcnt(), 2);
+        assert_eq!(DefaultBackend::king_attacks(Square::E4).popcnt(), 8);
+        let pawn = DefaultBackend::pawn_attacks(Square::E4, Color::Black);
+        assert_eq!(
+            pawn,
+            BitBoard::from_square(Square::D3) | BitBoard::from_square(Square::F3)
+        );
+    }
+
+    #[test]
+    fn round_trips_through_fen() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let board = DefaultBackend::from_str(fen).unwrap();
+        assert_eq!(board.to_string(), fen);
+    }
+}
@@ -0,0 +1,249 @@
+use crate::json::{self, quote, Json};
+use std::error::Error;
+use std::fmt;
+
+/// What a fixed depth search found in one position
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub fen: String,
+    /// The best move in UCI notation
+    pub best_move: String,
+    /// The score in the format of a UCI `info score`
+    pub score: String,
+    pub nodes: u64,
+}
+
+/// Search results of the bench positions, saved as JSON to catch searches
+/// that change by accident in later versions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Baseline {
+    /// Key of the evaluator the positions were searched with
+    pub evaluator: String,
+    pub depth: u8,
+    pub records: Vec<Record>,
+}
+
+/// A position that searches differently than in the baseline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Regression {
+    /// The search visited more nodes than the baseline allows
+    NodesExploded {
+        fen: String,
+        before: u64,
+        after: u64,
+    },
+    /// The search picked another move
+    MoveChanged {
+        fen: String,
+        before: String,
+        after: String,
+    },
+    /// The position was searched for the baseline but not anymore
+    Missing { fen: String },
+}
+
+impl fmt::Display for Regression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Regression::NodesExploded { fen, before, after } => {
+                write!(f, "nodes went from {} to {} in {}", before, after, fen)
+            }
+            Regression::MoveChanged { fen, before, after } => {
+                write!(
+                    f,
+                    "best move changed from {} to {} in {}",
+                    before, after, fen
+                )
+            }
+            Regression::Missing { fen } => write!(f, "{} wasn't searched", fen),
+        }
+    }
+}
+
+/// The reason a baseline couldn't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BaselineError {
+    /// The text isn't JSON, or not the JSON of a baseline
+    InvalidJson(String),
+    MissingField(&'static str),
+}
+
+impl fmt::Display for BaselineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BaselineError::InvalidJson(reason) => write!(f, "invalid baseline, {}", reason),
+            BaselineError::MissingField(field) => write!(f, "baseline is missing '{}'", field),
+        }
+    }
+}
+
+impl Error for BaselineError {}
+
+impl Baseline {
+    /// The baseline as pretty printed JSON with one position per line
+    pub fn to_json(&self) -> String {
+        let records: Vec<String> = self
+            .records
+            .iter()
+            .map(|record| {
+                format!(
+                    "    {{\"fen\": {}, \"best_move\": {}, \"score\": {}, \"nodes\": {}}}",
+                    quote(&record.fen),
+                    quote(&record.best_move),
+                    quote(&record.score),
+                    record.nodes
+                )
+            })
+            .collect();
+        format!(
+            "{{\n  \"evaluator\": {},\n  \"depth\": {},\n  \"positions\": [\n{}\n  ]\n}}\n",
+            quote(&self.evaluator),
+            self.depth,
+            records.join(",\n")
+        )
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, BaselineError> {
+        let json = json::parse(text).map_err(BaselineError::InvalidJson)?;
+        let records = field(&json, "positions")?
+            .as_array()
+            .ok_or(BaselineError::MissingField("positions"))?
+            .iter()
+            .map(|record| {
+                Ok(Record {
+                    fen: string(record, "fen")?,
+                    best_move: string(record, "best_move")?,
+                    score: string(record, "score")?,
+                    nodes: number(record, "nodes")?,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            evaluator: string(&json, "evaluator")?,
+            depth: number(&json, "depth")? as u8,
+            records,
+        })
+    }
+
+    /// The positions that search differently in `current`: more than
+    /// `tolerance` times the nodes, another best move or not at all
+    pub fn regressions(&self, current: &Baseline, tolerance: f64) -> Vec<Regression> {
+        let mut regressions = Vec::new();
+        for before in &self.records {
+            let after = match current.records.iter().find(|r| r.fen == before.fen) {
+                Some(after) => after,
+                None => {
+                    regressions.push(Regression::Missing {
+                        fen: before.fen.clone(),
+                    });
+                    continue;
+                }
+            };
+            if after.nodes as f64 > before.nodes as f64 * tolerance {
+                regressions.push(Regression::NodesExploded {
+                    fen: before.fen.clone(),
+                    before: before.nodes,
+                    after: after.nodes,
+                });
+            }
+            if after.best_move != before.best_move {
+                regressions.push(Regression::MoveChanged {
+                    fen: before.fen.clone(),
+                    before: before.best_move.clone(),
+                    after: after.best_move.clone(),
+                });
+            }
+        }
+        regressions
+    }
+}
+
+fn field<'a>(json: &'a Json, name: &'static str) -> Result<&'a Json, BaselineError> {
+    json.field(name).ok_or(BaselineError::MissingField(name))
+}
+
+fn string(json: &Json, name: &'static str) -> Result<String, BaselineError> {
+    field(json, name)?
+        .as_str()
+        .map(String::from)
+        .ok_or(BaselineError::MissingField(name))
+}
+
+fn number(json: &Json, name: &'static str) -> Result<u64, BaselineError> {
+    field(json, name)?
+        .as_u64()
+        .ok_or(BaselineError::MissingField(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Baseline, BaselineError, Record, Regression};
+
+    fn record(fen: &str, best_move: &str, nodes: u64) -> Record {
+        Record {
+            fen: fen.to_string(),
+            best_move: best_move.to_string(),
+            score: "cp 20".to_string(),
+            nodes,
+        }
+    }
+
+    fn baseline(records: Vec<Record>) -> Baseline {
+        Baseline {
+            evaluator: "naive".to_string(),
+            depth: 5,
+            records,
+        }
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let baseline = baseline(vec![
+            record("8/8/4k3/8/2p5/8/B2K4/8 w - - 0 1", "a2c4", 1234),
+            record("a \"quoted\" \\ name", "e2e4", 0),
+        ]);
+        let json = baseline.to_json();
+        assert_eq!(Baseline::from_json(&json), Ok(baseline));
+    }
+
+    #[test]
+    fn invalid_json() {
+        assert!(matches!(
+            Baseline::from_json("{\"depth\": 5"),
+            Err(BaselineError::InvalidJson(_))
+        ));
+        assert_eq!(
+            Baseline::from_json("{\"depth\": 5, \"positions\": []}"),
+            Err(BaselineError::MissingField("evaluator"))
+        );
+    }
+
+    #[test]
+    fn regressions() {
+        let before = baseline(vec![
+            record("a", "e2e4", 1000),
+            record("b", "d2d4", 1000),
+            record("c", "g1f3", 1000),
+        ]);
+        let after = baseline(vec![record("a", "e2e4", 1100), record("b", "c2c4", 2000)]);
+        assert_eq!(
+            before.regressions(&after, 1.2),
+            vec![
+                Regression::NodesExploded {
+                    fen: "b".to_string(),
+                    before: 1000,
+                    after: 2000
+                },
+                Regression::MoveChanged {
+                    fen: "b".to_string(),
+                    before: "d2d4".to_string(),
+                    after: "c2c4".to_string()
+                },
+                Regression::Missing {
+                    fen: "c".to_string()
+                },
+            ]
+        );
+        assert!(before.regressions(&before, 1.0).is_empty());
+    }
+}
@@ -0,0 +1,645 @@
+use crate::agent::AlphaBetaChessAgent;
+use crate::backend::{Backend, DefaultBackend as Board};
+use crate::dataset::{Outcome, Sample};
+use crate::winprob::WinModel;
+use crate::{
+    Action, BitBoard, ChessGame, ChessMove, Color, File, GameResult, ParseError, Piece, Position,
+    PositionStatus, Rank, RelativeSquare, Square,
+};
+use std::io::{self, Write};
+use std::str::FromStr;
+
+// Self-play games still going after this many plies are stopped as draws
+const MAX_PLIES: usize = 400;
+// Self-play games are adjudicated as won once the win probability model
+// gives the same side this chance of winning for this many plies in a row
+const ADJUDICATION_WIN: f64 = 0.98;
+const ADJUDICATION_PLIES: usize = 8;
+// A chunk is written out once it holds this many bytes, like Stockfish's
+// tools do, the next position starts a new one
+const CHUNK_SIZE: usize = 1 << 20;
+// Bits per block of the variable length score changes of the movetext
+const SCORE_BLOCK_BITS: usize = 4;
+// The kinds of moves in the top two bits of a packed move
+const NORMAL: u16 = 0;
+const PROMOTION: u16 = 1;
+const CASTLING: u16 = 2;
+const EN_PASSANT: u16 = 3;
+
+/// A position of NNUE training data with the engine's move and score
+#[derive(Clone)]
+pub struct Entry {
+    pub position: Position,
+    /// The move the engine chose
+    pub best_move: ChessMove,
+    /// The engine's score in centipawns for the side to move, see [`Score::to_centipawns`]
+    ///
+    /// [`Score::to_centipawns`]: crate::score::Score::to_centipawns
+    pub score: i32,
+    /// Plies played in the game before the position
+    pub ply: u32,
+    /// Plies since the last capture or pawn move
+    pub halfmove_clock: u32,
+    /// 1 if the side to move went on to win the game, 0 for a draw and -1 for a loss
+    pub result: i8,
+}
+
+/// Writes entries in the binpack format of Stockfish's NNUE trainer and
+/// its data tools.
+///
+/// An entry continuing the game of the one before it, with the position
+/// after its move and the next ply, is stored as the move and the score
+/// change in a few bits instead of as a whole position, so self-play
+/// games take a couple of bytes per position. The data is only complete
+/// after [`finish`](BinpackWriter::finish).
+pub struct BinpackWriter<W: Write> {
+    out: W,
+    // the chunk being filled
+    chunk: Vec<u8>,
+    // the entry the next one may continue
+    last: Option<Entry>,
+    // the moves and scores of the entries continuing the last full one
+    movetext: BitWriter,
+    plies: u16,
+    // the score the next continuing entry is stored relative to
+    last_score: i16,
+}
+
+impl<W: Write> BinpackWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            chunk: Vec::new(),
+            last: None,
+            movetext: BitWriter::default(),
+            plies: 0,
+            last_score: 0,
+        }
+    }
+
+    pub fn write(&mut self, entry: &Entry) -> io::Result<()> {
+        let continued = self.plies < u16::MAX
+            && self
+                .last
+                .as_ref()
+                .map_or(false, |last| continues(last, entry));
+        let score = clamp_score(entry.score);
+        if continued {
+            let position = &entry.position;
+            let ours = position.color_combined(position.side_to_move());
+            let (index, moves) = move_index(position, entry.best_move);
+            self.movetext.add(
+                (ours & squares_before(entry.best_move.get_source())).popcnt() as u8,
+                bits_for(ours.popcnt()),
+            );
+            self.movetext.add(index as u8, bits_for(moves));
+            let change = signed_to_unsigned(score.wrapping_sub(self.last_score));
+            self.movetext.add_variable(change, SCORE_BLOCK_BITS);
+            self.plies += 1;
+        } else {
+            if self.last.is_some() {
+                self.end_movetext();
+            }
+            if self.chunk.len() >= CHUNK_SIZE {
+                self.write_chunk()?;
+            }
+            self.chunk.extend_from_slice(&pack(entry));
+        }
+        self.last_score = -score;
+        self.last = Some(entry.clone());
+        Ok(())
+    }
+
+    /// Writes what's left and flushes the output, which is returned
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.last.is_some() {
+            self.end_movetext();
+        }
+        if !self.chunk.is_empty() {
+            self.write_chunk()?;
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    // Closes the last full entry with the count and the bits of the ones
+    // continuing it
+    fn end_movetext(&mut self) {
+        self.chunk.extend_from_slice(&self.plies.to_be_bytes());
+        self.chunk.extend_from_slice(&self.movetext.bytes);
+        self.movetext = BitWriter::default();
+        self.plies = 0;
+    }
+
+    fn write_chunk(&mut self) -> io::Result<()> {
+        self.out.write_all(b"BINP")?;
+        self.out
+            .write_all(&(self.chunk.len() as u32).to_le_bytes())?;
+        self.out.write_all(&self.chunk)?;
+        self.chunk.clear();
+        Ok(())
+    }
+}
+
+// Whether the entry is the position after the last one's move, which
+// readers of the movetext get by making the move. Positions are compared
+// the way binpacks store them, which keeps the en passant square only
+// when the capture is legal.
+fn continues(last: &Entry, entry: &Entry) -> bool {
+    let position = &last.position;
+    let chess_move = last.best_move;
+    let zeroing = position.piece_on(chess_move.get_source()) == Some(Piece::Pawn)
+        || position.piece_on(chess_move.get_dest()).is_some();
+    let halfmove_clock = if zeroing { 0 } else { last.halfmove_clock + 1 };
+    last.result == -entry.result
+        && last.ply + 1 == entry.ply
+        && halfmove_clock == entry.halfmove_clock
+        && pack_position(&position.make_move_new(chess_move)) == pack_position(&entry.position)
+}
+
+// An entry on its own in 32 bytes: the position, the move, the score, the
+// ply and result, and the halfmove clock
+fn pack(entry: &Entry) -> [u8; 32] {
+    let position = &entry.position;
+    let mut packed = [0; 32];
+    packed[..24].copy_from_slice(&pack_position(position));
+    let ply_and_result =
+        (entry.ply as u16 & 0x3FFF) | (signed_to_unsigned(i16::from(entry.result)) << 14);
+    packed[24..26].copy_from_slice(&pack_move(position, entry.best_move).to_be_bytes());
+    packed[26..28].copy_from_slice(&signed_to_unsigned(clamp_score(entry.score)).to_be_bytes());
+    packed[28..30].copy_from_slice(&ply_and_result.to_be_bytes());
+    packed[30..].copy_from_slice(&(entry.halfmove_clock as u16).to_be_bytes());
+    packed
+}
+
+// The occupied squares and four bits for the piece on each of them
+fn pack_position(position: &Position) -> [u8; 24] {
+    let occupied = position.color_combined(Color::White) | position.color_combined(Color::Black);
+    let mut packed = [0; 24];
+    packed[..8].copy_from_slice(&occupied.0.to_be_bytes());
+    for (index, square) in occupied.into_iter().enumerate() {
+        packed[8 + index / 2] |= piece_code(position, square) << (4 * (index % 2));
+    }
+    packed
+}
+
+// The piece on the square as four bits, 0 to 11 for the piece and its
+// color, with codes of their own for the rest of the position: 12 for a
+// pawn that can be captured en passant, 13 and 14 for white and black
+// rooks that can still castle and 15 for the black king with black to move
+fn piece_code(position: &Position, square: Square) -> u8 {
+    let (piece, color) = match (position.piece_on(square), position.color_on(square)) {
+        (Some(piece), Some(color)) => (piece, color),
+        _ => return 0,
+    };
+    let castles = match (square.get_file(), square.relative_rank(color)) {
+        (File::A, Rank::First) => position.castle_rights(color).has_queenside(),
+        (File::H, Rank::First) => position.castle_rights(color).has_kingside(),
+        _ => false,
+    };
+    let en_passant = en_passant_target(position).is_some() && position.en_passant() == Some(square);
+    match (piece, color) {
+        (Piece::Pawn, _) if en_passant => 12,
+        (Piece::Rook, Color::White) if castles => 13,
+        (Piece::Rook, Color::Black) if castles => 14,
+        (Piece::King, Color::Black) if position.side_to_move() == Color::Black => 15,
+        _ => (piece.to_index() as u8) << 1 | u8::from(color == Color::Black),
+    }
+}
+
+// The move in 16 bits: its kind, the source and the destination, and
+// the promotion piece counted from the knight. Castling moves go to the
+// rook's square.
+fn pack_move(position: &Position, chess_move: ChessMove) -> u16 {
+    let source = chess_move.get_source();
+    let mut dest = chess_move.get_dest();
+    let mut promotion = 0;
+    let kind = if let Some(piece) = chess_move.get_promotion() {
+        promotion = piece.to_index() as u16 - 1;
+        PROMOTION
+    } else if is_castling(position, chess_move) {
+        dest = castling_rook(chess_move);
+        CASTLING
+    } else if Some(dest) == en_passant_target(position)
+        && position.piece_on(source) == Some(Piece::Pawn)
+    {
+        EN_PASSANT
+    } else {
+        NORMAL
+    };
+    kind << 14 | (source.to_index() as u16) << 8 | (dest.to_index() as u16) << 2 | promotion
+}
+
+fn is_castling(position: &Position, chess_move: ChessMove) -> bool {
+    let files = chess_move.get_source().get_file().to_index() as i8
+        - chess_move.get_dest().get_file().to_index() as i8;
+    position.piece_on(chess_move.get_source()) == Some(Piece::King) && files.abs() == 2
+}
+
+fn castling_rook(chess_move: ChessMove) -> Square {
+    let file = if chess_move.get_dest().get_file() == File::G {
+        File::H
+    } else {
+        File::A
+    };
+    Square::make_square(chess_move.get_source().get_rank(), file)
+}
+
+// The square behind the pawn that can be captured en passant, when a
+// capture there is legal. Binpacks keep the square only then.
+fn en_passant_target(position: &Position) -> Option<Square> {
+    let target = position.en_passant()?.uforward(position.side_to_move());
+    position
+        .legal_moves_iter()
+        .any(|m| m.get_dest() == target && position.piece_on(m.get_source()) == Some(Piece::Pawn))
+        .then(|| target)
+}
+
+// The index of the move among the moves of its piece and how many there
+// are, counting pseudo-legal moves in the order of their destinations the
+// way readers of the movetext do
+fn move_index(position: &Position, chess_move: ChessMove) -> (u32, u32) {
+    let side = position.side_to_move();
+    let ours = position.color_combined(side);
+    let theirs = position.color_combined(!side);
+    let occupied = ours | theirs;
+    let source = chess_move.get_source();
+    let before = squares_before(chess_move.get_dest());
+    let empty = BitBoard::new(0);
+    match position.piece_on(source) {
+        Some(Piece::Pawn) => {
+            let mut targets = theirs;
+            if let Some(square) = en_passant_target(position) {
+                targets |= BitBoard::from_square(square);
+            }
+            let mut destinations = Board::pawn_attacks(source, side) & targets;
+            let push = source.uforward(side);
+            if occupied & BitBoard::from_square(push) == empty {
+                destinations |= BitBoard::from_square(push);
+                let double = push.uforward(side);
+                if source.relative_rank(side) == Rank::Second
+                    && occupied & BitBoard::from_square(double) == empty
+                {
+                    destinations |= BitBoard::from_square(double);
+                }
+            }
+            let index = (destinations & before).popcnt();
+            match chess_move.get_promotion() {
+                Some(piece) => (
+                    index * 4 + piece.to_index() as u32 - 1,
+                    destinations.popcnt() * 4,
+                ),
+                None => (index, destinations.popcnt()),
+            }
+        }
+        Some(Piece::King) => {
+            let attacks = Board::king_attacks(source) & !ours;
+            let rights = position.castle_rights(side);
+            let kingside = u32::from(rights.has_kingside());
+            let queenside = u32::from(rights.has_queenside());
+            let moves = attacks.popcnt() + kingside + queenside;
+            if is_castling(position, chess_move) {
+                let short = u32::from(chess_move.get_dest().get_file() == File::G);
+                (attacks.popcnt() - 1 + queenside + short, moves)
+            } else {
+                ((attacks & before).popcnt(), moves)
+            }
+        }
+        piece => {
+            let attacks = match piece {
+                Some(Piece::Knight) => Board::knight_attacks(source),
+                Some(Piece::Bishop) => Board::bishop_attacks(source, occupied),
+                Some(Piece::Rook) => Board::rook_attacks(source, occupied),
+                _ => {
+                    Board::bishop_attacks(source, occupied) | Board::rook_attacks(source, occupied)
+                }
+            } & !ours;
+            ((attacks & before).popcnt(), attacks.popcnt())
+        }
+    }
+}
+
+fn squares_before(square: Square) -> BitBoard {
+    BitBoard::new((1 << square.to_index()) - 1)
+}
+
+// The bits needed to tell apart this many values
+fn bits_for(values: u32) -> usize {
+    match values {
+        0 => 0,
+        _ => (32 - (values - 1).leading_zeros()) as usize,
+    }
+}
+
+fn clamp_score(score: i32) -> i16 {
+    score.clamp(i16::MIN.into(), i16::MAX.into()) as i16
+}
+
+// Binpacks store signed numbers with the sign in the lowest bit, so small
+// negative numbers stay small
+fn signed_to_unsigned(value: i16) -> u16 {
+    let mut bits = value as u16;
+    if bits & 0x8000 != 0 {
+        bits ^= 0x7FFF;
+    }
+    bits.rotate_left(1)
+}
+
+// Bits packed from the highest bit of each byte on
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    // the bits still free in the last byte
+    free: usize,
+}
+
+impl BitWriter {
+    // The lowest `count` bits of `bits`
+    fn add(&mut self, bits: u8, count: usize) {
+        if count == 0 {
+            return;
+        }
+        if self.free == 0 {
+            self.bytes.push(bits << (8 - count));
+            self.free = 8;
+        } else if count <= self.free {
+            *self.bytes.last_mut().unwrap() |= bits << (self.free - count);
+        } else {
+            let spill = count - self.free;
+            *self.bytes.last_mut().unwrap() |= bits >> spill;
+            self.bytes.push(bits << (8 - spill));
+            self.free += 8;
+        }
+        self.free -= count;
+    }
+
+    // The value in blocks of `block` bits from the lowest on, each
+    // followed by a bit telling whether another block comes
+    fn add_variable(&mut self, mut value: u16, block: usize) {
+        let mask = (1 << block) - 1;
+        loop {
+            let more = u8::from(value > mask);
+            self.add((value & mask) as u8 | more << block, block + 1);
+            value >>= block;
+            if value == 0 {
+                break;
+            }
+        }
+    }
+}
+
+/// Let the agent play the game from the FEN against itself and record every
+/// position with its move and score, the results are filled in at the end.
+///
+/// Games one side is clearly winning are adjudicated early, by the chances
+/// of the default [`WinModel`].
+pub fn self_play(agent: &AlphaBetaChessAgent, fen: &str) -> Result<Vec<Entry>, ParseError> {
+    let mut game = ChessGame::from_str(fen)?;
+    let start_ply = fen_ply(fen);
+    let mut entries: Vec<Entry> = Vec::new();
+    let model = WinModel::default();
+    // the side the model has seen winning and for how many plies
+    let mut leader: (Option<Color>, usize) = (None, 0);
+    let winner = loop {
+        match game.result() {
+            Some(GameResult::WhiteCheckmates) | Some(GameResult::BlackResigns) => {
+                break Some(Color::White)
+            }
+            Some(GameResult::BlackCheckmates) | Some(GameResult::WhiteResigns) => {
+                break Some(Color::Black)
+            }
+            Some(_) => break None,
+            None if game.can_declare_draw() || entries.len() >= MAX_PLIES => break None,
+            None => {}
+        }
+        let result = agent.search_game(&game);
+        let position = game.current_position();
+        let chances = model.position_wdl(&position, result.score);
+        let side = position.side_to_move();
+        let winning = if chances.win >= ADJUDICATION_WIN {
+            Some(side)
+        } else if chances.loss >= ADJUDICATION_WIN {
+            Some(!side)
+        } else {
+            None
+        };
+        leader = match (winning, leader) {
+            (Some(color), (Some(previous), plies)) if color == previous => (Some(color), plies + 1),
+            (winning, _) => (winning, 1),
+        };
+        entries.push(Entry {
+            position,
+            best_move: result.best_move,
+            score: result.score.to_centipawns(),
+            ply: start_ply + entries.len() as u32,
+            halfmove_clock: game.halfmove_clock(),
+            result: 0,
+        });
+        if let (Some(color), ADJUDICATION_PLIES) = leader {
+            break Some(color);
+        }
+        game.take_action(Action::MakeMove(result.best_move));
+    };
+    for entry in entries.iter_mut() {
+        entry.result = match winner {
+            Some(color) if color == entry.position.side_to_move() => 1,
+            Some(_) => -1,
+            None => 0,
+        };
+    }
+    Ok(entries)
+}
+
+/// Search a dataset position for the agent's move and score, the result is
+/// the sample's outcome. The ply and halfmove clock come from the FEN, see
+/// [`fen_ply`] and [`fen_halfmove_clock`]. Returns `None` when the game is
+/// already over.
+pub fn annotate(
+    agent: &AlphaBetaChessAgent,
+    sample: &Sample,
+    ply: u32,
+    halfmove_clock: u32,
+) -> Option<Entry> {
+    if sample.position.status() != PositionStatus::Ongoing {
+        return None;
+    }
+    let result = agent.search(&sample.position);
+    let white = match sample.outcome {
+        Outcome::WhiteWins => 1,
+        Outcome::Draw => 0,
+        Outcome::BlackWins => -1,
+    };
+    Some(Entry {
+        position: sample.position.clone(),
+        best_move: result.best_move,
+        score: result.score.to_centipawns(),
+        ply,
+        halfmove_clock,
+        result: match sample.position.side_to_move() {
+            Color::White => white,
+            Color::Black => -white,
+        },
+    })
+}
+
+/// The plies played before the position of a FEN, from its side to move and
+/// move counter. A missing counter counts as the first move.
+pub fn fen_ply(fen: &str) -> u32 {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    let fullmove: u32 = fields.get(5).and_then(|f| f.parse().ok()).unwrap_or(1);
+    let black = fields.get(1) == Some(&"b");
+    fullmove.saturating_sub(1) * 2 + u32::from(black)
+}
+
+/// The halfmove clock of a FEN, 0 when it's missing
+pub fn fen_halfmove_clock(fen: &str) -> u32 {
+    fen.split_whitespace()
+        .nth(4)
+        .and_then(|f| f.parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        annotate, fen_halfmove_clock, fen_ply, pack_move, self_play, BinpackWriter, Entry,
+    };
+    use crate::agent::AlphaBetaChessAgent;
+    use crate::dataset::Sample;
+    use crate::tt::TranspositionTable;
+    use crate::{ChessMove, Position};
+    use std::str::FromStr;
+    use test_case::test_case;
+
+    const MATE_IN_ONE: &str = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+
+    fn entry(fen: &str, chess_move: &str, score: i32) -> Entry {
+        Entry {
+            position: Position::from_str(fen).unwrap(),
+            best_move: ChessMove::from_str(chess_move).unwrap(),
+            score,
+            ply: fen_ply(fen),
+            halfmove_clock: fen_halfmove_clock(fen),
+            result: 0,
+        }
+    }
+
+    fn binpack(entries: &[Entry]) -> Vec<u8> {
+        let mut writer = BinpackWriter::new(Vec::new());
+        for entry in entries {
+            writer.write(entry).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    // The test data of the sfbinpack crate, written by Stockfish's tools
+    #[test]
+    fn writes_binpacks_like_stockfish() {
+        let entries = [
+            entry(
+                "1q5b/1r5k/4p2p/1b2P1pN/3p4/6PP/1nP3B1/1Q2B1K1 w - - 0 35",
+                "c2c4",
+                -201,
+            ),
+            entry(
+                "1q5b/1r5k/4p2p/1b2P1pN/2Pp4/6PP/1n4B1/1Q2B1K1 b - - 0 35",
+                "d4d3",
+                254,
+            ),
+            entry(
+                "1q5b/1r5k/4p2p/1b2P1pN/2P5/3p2PP/1n4B1/1Q2B1K1 w - - 0 36",
+                "g2b7",
+                -220,
+            ),
+        ];
+        assert_eq!(
+            binpack(&entries),
+            [
+                66, 73, 78, 80, 38, 0, 0, 0, 130, 130, 144, 210, 8, 192, 70, 82, 72, 58, 64, 0, 81,
+                16, 18, 113, 155, 5, 0, 0, 0, 0, 0, 0, 10, 104, 1, 145, 0, 68, 0, 0, 0, 2, 29, 24,
+                245, 8,
+            ]
+        );
+        let mut mated = entries[..2].to_vec();
+        mated[0].score = -31999;
+        mated[1].score = -1500;
+        assert_eq!(
+            binpack(&mated),
+            [
+                66, 73, 78, 80, 37, 0, 0, 0, 130, 130, 144, 210, 8, 192, 70, 82, 72, 58, 64, 0, 81,
+                16, 18, 113, 155, 5, 0, 0, 0, 0, 0, 0, 10, 104, 249, 253, 0, 68, 0, 0, 0, 1, 29,
+                83, 79,
+            ]
+        );
+    }
+
+    #[test]
+    fn positions_of_other_games_are_written_whole() {
+        let first = entry(MATE_IN_ONE, "a1a8", 31999);
+        let mut second = first.clone();
+        second.ply = 1;
+        second.result = 1;
+        // one chunk with two positions and their empty movetext
+        assert_eq!(binpack(&[first, second]).len(), 8 + 2 * (32 + 2));
+        assert!(binpack(&[]).is_empty());
+    }
+
+    #[test_case("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1g1", 0x841c)]
+    #[test_case("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "e8c8", 0xbce0)]
+    #[test_case("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 0xe4ac)]
+    #[test_case("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8n", 0x71e4)]
+    #[test_case("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8q", 0x71e7)]
+    fn packs_moves(fen: &str, chess_move: &str, packed: u16) {
+        let position = Position::from_str(fen).unwrap();
+        let chess_move = ChessMove::from_str(chess_move).unwrap();
+        assert_eq!(pack_move(&position, chess_move), packed);
+    }
+
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 0, 0)]
+    #[test_case("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1", 1, 0)]
+    #[test_case(
+        "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+        4,
+        2
+    )]
+    #[test_case("8/8/4k3/8/8/4K3/4P3/8 b - -", 1, 0)]
+    fn plies(fen: &str, ply: u32, halfmove_clock: u32) {
+        assert_eq!(fen_ply(fen), ply);
+        assert_eq!(fen_halfmove_clock(fen), halfmove_clock);
+    }
+
+    #[test]
+    fn self_play_records_every_position() {
+        let agent = AlphaBetaChessAgent::new(2, TranspositionTable::new(1000));
+        let entries = self_play(&agent, "6k1/5ppp/8/8/8/8/8/R5K1 w - - 7 12").unwrap();
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!(entry.best_move, ChessMove::from_str("a1a8").unwrap());
+        assert_eq!(
+            (entry.score, entry.ply, entry.halfmove_clock, entry.result),
+            (31999, 22, 7, 1)
+        );
+    }
+
+    #[test]
+    fn self_play_games_are_written_as_moves() {
+        let agent = AlphaBetaChessAgent::new(1, TranspositionTable::new(1000));
+        let entries =
+            self_play(&agent, "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1").unwrap();
+        assert!(entries.len() > 10);
+        let written = binpack(&entries);
+        // a single position, then a byte or two per move
+        assert!(written.len() < 8 + 32 + 2 + 2 * entries.len());
+        assert_eq!(&written[40..42], &(entries.len() as u16 - 1).to_be_bytes());
+    }
+
+    #[test]
+    fn results_are_for_the_side_to_move() {
+        let agent = AlphaBetaChessAgent::new(1, TranspositionTable::new(1000));
+        let sample = Sample::parse("6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1 1-0").unwrap();
+        let entry = annotate(&agent, &sample, 1, 0).unwrap();
+        assert_eq!((entry.result, entry.ply), (-1, 1));
+        let mated = Sample::parse("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1 1-0").unwrap();
+        assert!(annotate(&agent, &mated, 1, 0).is_none());
+    }
+}
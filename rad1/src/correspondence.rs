@@ -0,0 +1,292 @@
+use crate::score::Score;
+use crate::{fen, Action, ChessGame, ChessMove, Color};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// A correspondence game kept on disk between moves, with the deepest
+/// analysis of its current position so far
+pub struct Correspondence {
+    pub opponent: String,
+    /// The side the user plays, the opponent plays the other one
+    pub color: Color,
+    pub game: ChessGame,
+    pub analysis: Option<Analysis>,
+}
+
+/// The last completed iteration of analyzing the current position
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    pub depth: u8,
+    pub best_move: ChessMove,
+    pub score: Score,
+    pub pv: Vec<ChessMove>,
+    /// Nodes searched for the analysis over every session
+    pub nodes: u64,
+}
+
+/// The reason a correspondence game couldn't be read or a move couldn't be played
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CorrespondenceError {
+    /// A line that isn't a `name = value` pair
+    InvalidLine(String),
+    UnknownField(String),
+    MissingField(&'static str),
+    InvalidValue {
+        field: String,
+        value: String,
+    },
+    IllegalMove(ChessMove),
+}
+
+impl fmt::Display for CorrespondenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CorrespondenceError::InvalidLine(line) => write!(f, "invalid line '{}'", line),
+            CorrespondenceError::UnknownField(field) => write!(f, "unknown field '{}'", field),
+            CorrespondenceError::MissingField(field) => write!(f, "missing field '{}'", field),
+            CorrespondenceError::InvalidValue { field, value } => {
+                write!(f, "invalid value '{}' for '{}'", value, field)
+            }
+            CorrespondenceError::IllegalMove(chess_move) => {
+                write!(f, "'{}' is not a legal move", chess_move)
+            }
+        }
+    }
+}
+
+impl Error for CorrespondenceError {}
+
+impl Analysis {
+    /// The lines [`Correspondence::to_text`] writes for the analysis
+    pub fn to_text(&self) -> String {
+        format!(
+            "Depth = {}\nBest Move = {}\nScore = {}\nPV = {}\nNodes = {}\n",
+            self.depth,
+            self.best_move,
+            i16::from(self.score),
+            moves_text(&self.pv),
+            self.nodes
+        )
+    }
+}
+
+impl Correspondence {
+    pub fn new(opponent: &str, color: Color, game: ChessGame) -> Self {
+        Self {
+            opponent: opponent.to_string(),
+            color,
+            game,
+            analysis: None,
+        }
+    }
+
+    /// Whether the user has to move, false once the game is over
+    pub fn is_our_turn(&self) -> bool {
+        self.game.result().is_none() && self.game.side_to_move() == self.color
+    }
+
+    /// Play a move of either side, the analysis of the old position is dropped
+    pub fn play(&mut self, chess_move: ChessMove) -> Result<(), CorrespondenceError> {
+        if self.game.result().is_some() || !self.game.current_position().legal(chess_move) {
+            return Err(CorrespondenceError::IllegalMove(chess_move));
+        }
+        self.game.take_action(Action::MakeMove(chess_move));
+        self.analysis = None;
+        Ok(())
+    }
+
+    /// The game as `name = value` lines, the way engine option files are written
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "Opponent = {}\nColor = {}\nFEN = {}\nMoves = {}\n",
+            self.opponent,
+            match self.color {
+                Color::White => "white",
+                Color::Black => "black",
+            },
+            self.game.start_position(),
+            moves_text(&self.game.moves())
+        );
+        if let Some(analysis) = &self.analysis {
+            text.push_str(&analysis.to_text());
+        }
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, CorrespondenceError> {
+        let mut fields = Fields::default();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| CorrespondenceError::InvalidLine(line.to_string()))?;
+            let value = value.trim().to_string();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "opponent" => fields.opponent = Some(value),
+                "color" => fields.color = Some(value),
+                "fen" => fields.fen = Some(value),
+                "moves" => fields.moves = Some(value),
+                "depth" => fields.depth = Some(value),
+                "best move" => fields.best_move = Some(value),
+                "score" => fields.score = Some(value),
+                "pv" => fields.pv = Some(value),
+                "nodes" => fields.nodes = Some(value),
+                _ => return Err(CorrespondenceError::UnknownField(name.trim().to_string())),
+            }
+        }
+
+        let color = match required("Color", &fields.color)?.as_str() {
+            "white" => Color::White,
+            "black" => Color::Black,
+            value => return Err(invalid("Color", value)),
+        };
+        let fen = required("FEN", &fields.fen)?;
+        let mut correspondence = Self::new(
+            required("Opponent", &fields.opponent)?,
+            color,
+            fen::parse_game(fen).map_err(|_| invalid("FEN", fen))?,
+        );
+        for chess_move in parse_moves("Moves", fields.moves.as_deref().unwrap_or_default())? {
+            correspondence.play(chess_move)?;
+        }
+        if let Some(depth) = &fields.depth {
+            let best_move = required("Best Move", &fields.best_move)?;
+            let score = required("Score", &fields.score)?;
+            let nodes = required("Nodes", &fields.nodes)?;
+            correspondence.analysis = Some(Analysis {
+                depth: depth.parse().map_err(|_| invalid("Depth", depth))?,
+                best_move: ChessMove::from_str(best_move)
+                    .map_err(|_| invalid("Best Move", best_move))?,
+                score: Score::from(score.parse::<i16>().map_err(|_| invalid("Score", score))?),
+                pv: parse_moves("PV", fields.pv.as_deref().unwrap_or_default())?,
+                nodes: nodes.parse().map_err(|_| invalid("Nodes", nodes))?,
+            });
+        }
+        Ok(correspondence)
+    }
+}
+
+#[derive(Default)]
+struct Fields {
+    opponent: Option<String>,
+    color: Option<String>,
+    fen: Option<String>,
+    moves: Option<String>,
+    depth: Option<String>,
+    best_move: Option<String>,
+    score: Option<String>,
+    pv: Option<String>,
+    nodes: Option<String>,
+}
+
+fn required<'a>(
+    field: &'static str,
+    value: &'a Option<String>,
+) -> Result<&'a String, CorrespondenceError> {
+    value
+        .as_ref()
+        .ok_or(CorrespondenceError::MissingField(field))
+}
+
+fn invalid(field: &str, value: &str) -> CorrespondenceError {
+    CorrespondenceError::InvalidValue {
+        field: field.to_string(),
+        value: value.to_string(),
+    }
+}
+
+fn moves_text(moves: &[ChessMove]) -> String {
+    let moves: Vec<String> = moves.iter().map(|m| m.to_string()).collect();
+    moves.join(" ")
+}
+
+fn parse_moves(field: &str, moves: &str) -> Result<Vec<ChessMove>, CorrespondenceError> {
+    moves
+        .split_whitespace()
+        .map(|value| ChessMove::from_str(value).map_err(|_| invalid(field, value)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Analysis, Correspondence, CorrespondenceError};
+    use crate::score::Score;
+    use crate::{ChessGame, ChessMove, Color, Square};
+
+    #[test]
+    fn text_round_trip() {
+        let e4 = ChessMove::new(Square::E2, Square::E4, None);
+        let e5 = ChessMove::new(Square::E7, Square::E5, None);
+        let nf3 = ChessMove::new(Square::G1, Square::F3, None);
+        let mut correspondence =
+            Correspondence::new("Jane Doe", Color::White, ChessGame::default());
+        correspondence.play(e4).unwrap();
+        assert!(!correspondence.is_our_turn());
+        correspondence.play(e5).unwrap();
+        assert!(correspondence.is_our_turn());
+        correspondence.analysis = Some(Analysis {
+            depth: 12,
+            best_move: nf3,
+            score: Score::Mate(9),
+            pv: vec![nf3, e4],
+            nodes: 123_456,
+        });
+
+        let text = correspondence.to_text();
+        let read = Correspondence::from_text(&text).unwrap();
+        assert_eq!(read.opponent, "Jane Doe");
+        assert_eq!(read.color, Color::White);
+        assert_eq!(read.game.moves(), vec![e4, e5]);
+        assert_eq!(read.analysis, correspondence.analysis);
+        assert_eq!(read.to_text(), text);
+    }
+
+    #[test]
+    fn moves_drop_the_analysis() {
+        let mut correspondence = Correspondence::new("Bob", Color::Black, ChessGame::default());
+        let e4 = ChessMove::new(Square::E2, Square::E4, None);
+        correspondence.analysis = Some(Analysis {
+            depth: 1,
+            best_move: e4,
+            score: Score::DRAW,
+            pv: vec![e4],
+            nodes: 20,
+        });
+        assert_eq!(
+            correspondence.play(ChessMove::new(Square::E2, Square::E5, None)),
+            Err(CorrespondenceError::IllegalMove(ChessMove::new(
+                Square::E2,
+                Square::E5,
+                None
+            )))
+        );
+        assert!(correspondence.analysis.is_some());
+        correspondence.play(e4).unwrap();
+        assert!(correspondence.analysis.is_none());
+        assert!(correspondence.is_our_turn());
+    }
+
+    #[test]
+    fn invalid_text() {
+        assert!(matches!(
+            Correspondence::from_text("Opponent = Bob\nColor = white\n"),
+            Err(CorrespondenceError::MissingField("FEN"))
+        ));
+        assert!(matches!(
+            Correspondence::from_text("Opponent = Bob\nColor = green\n"),
+            Err(CorrespondenceError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            Correspondence::from_text("Rating = 2000"),
+            Err(CorrespondenceError::UnknownField(_))
+        ));
+        let illegal = "Opponent = Bob\nColor = white\nFEN = rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1\nMoves = e2e5\n";
+        assert!(matches!(
+            Correspondence::from_text(illegal),
+            Err(CorrespondenceError::IllegalMove(_))
+        ));
+    }
+}
@@ -0,0 +1,474 @@
+use crate::eval::Evaluator;
+use crate::{fen, Color, GamePhase, Piece, Position, ALL_PIECES};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Piece values in pawns for the material balance, indexed by `Piece as usize`
+const PAWN_UNITS: [i16; 6] = [1, 3, 3, 5, 9, 0];
+// Material balances beyond this many pawns either way share a bucket
+const MAX_BALANCE: i16 = 5;
+// Share of duplicate positions above which a dataset is flagged
+const MAX_DUPLICATE_RATE: f64 = 0.05;
+// Share of one outcome above which a dataset is flagged
+const MAX_OUTCOME_RATE: f64 = 0.6;
+// Evaluation that gives 10 to 1 odds of winning in the expected score, four pawns
+const ODDS_SCALE: f64 = 40.0;
+
+/// The result of the game a position was taken from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    WhiteWins,
+    Draw,
+    BlackWins,
+}
+
+impl Outcome {
+    /// Parses `1-0`, `1/2-1/2` and `0-1` as well as `1.0`, `0.5` and `0.0`,
+    /// with or without the brackets, quotes and semicolon datasets wrap them in
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim_matches(|c| "[]\";".contains(c)) {
+            "1-0" | "1.0" => Some(Outcome::WhiteWins),
+            "1/2-1/2" | "0.5" => Some(Outcome::Draw),
+            "0-1" | "0.0" => Some(Outcome::BlackWins),
+            _ => None,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Points white got from the game
+    pub fn score(self) -> f64 {
+        match self {
+            Outcome::WhiteWins => 1.0,
+            Outcome::Draw => 0.5,
+            Outcome::BlackWins => 0.0,
+        }
+    }
+}
+
+/// A position of a dataset and the outcome of its game
+#[derive(Clone)]
+pub struct Sample {
+    pub position: Position,
+    pub outcome: Outcome,
+}
+
+impl Sample {
+    /// Parses a dataset line, a FEN followed by the outcome like
+    /// `<fen> [0.5]` or the EPD style `<fen> c9 "1/2-1/2";`
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut fields: Vec<&str> = line.split_whitespace().collect();
+        let outcome = Outcome::parse(fields.pop()?)?;
+        if fields.last() == Some(&"c9") {
+            fields.pop();
+        }
+        // EPD leaves out the move counters
+        if fields.len() == 4 {
+            fields.extend(&["0", "1"]);
+        }
+        let position = fen::parse(&fields.join(" ")).ok()?;
+        Some(Self { position, outcome })
+    }
+}
+
+/// The composition of a dataset, to spot biases before tuning on it
+#[derive(Debug, Clone, Default)]
+pub struct DatasetStats {
+    positions: usize,
+    invalid: usize,
+    outcomes: [usize; 3],
+    phases: [usize; 3],
+    balances: BTreeMap<i16, usize>,
+    hashes: HashSet<u64>,
+}
+
+impl DatasetStats {
+    /// Count a line of a dataset, returns false if it isn't a valid sample
+    pub fn add_line(&mut self, line: &str) -> bool {
+        if line.trim().is_empty() {
+            return true;
+        }
+        match Sample::parse(line) {
+            Some(sample) => {
+                self.add(&sample);
+                true
+            }
+            None => {
+                self.invalid += 1;
+                false
+            }
+        }
+    }
+
+    pub fn add(&mut self, sample: &Sample) {
+        let position = &sample.position;
+        self.positions += 1;
+        self.outcomes[sample.outcome.index()] += 1;
+        self.phases[phase_index(position.game_phase())] += 1;
+        let balance = material_balance(position).clamp(-MAX_BALANCE, MAX_BALANCE);
+        *self.balances.entry(balance).or_insert(0) += 1;
+        self.hashes.insert(position.get_hash());
+    }
+
+    pub fn positions(&self) -> usize {
+        self.positions
+    }
+
+    /// Lines that couldn't be parsed
+    pub fn invalid(&self) -> usize {
+        self.invalid
+    }
+
+    pub fn outcome_count(&self, outcome: Outcome) -> usize {
+        self.outcomes[outcome.index()]
+    }
+
+    pub fn phase_count(&self, phase: GamePhase) -> usize {
+        self.phases[phase_index(phase)]
+    }
+
+    /// Positions by white's material minus black's in pawns, balances beyond
+    /// five pawns are counted as five
+    pub fn material_balances(&self) -> &BTreeMap<i16, usize> {
+        &self.balances
+    }
+
+    /// Positions that appeared before
+    pub fn duplicates(&self) -> usize {
+        self.positions - self.hashes.len()
+    }
+
+    /// The share of the positions, 0 for an empty dataset
+    pub fn rate(&self, count: usize) -> f64 {
+        if self.positions == 0 {
+            0.0
+        } else {
+            count as f64 / self.positions as f64
+        }
+    }
+
+    /// What looks wrong with the dataset: no positions, invalid lines, many
+    /// duplicates, outcomes or phases that are missing or dominate
+    pub fn warnings(&self) -> Vec<String> {
+        if self.positions == 0 {
+            return vec![String::from("the dataset has no positions")];
+        }
+        let mut warnings = Vec::new();
+        if self.invalid > 0 {
+            warnings.push(format!("{} lines couldn't be parsed", self.invalid));
+        }
+        if self.rate(self.duplicates()) > MAX_DUPLICATE_RATE {
+            warnings.push(format!(
+                "{:.1}% of the positions are duplicates",
+                100.0 * self.rate(self.duplicates())
+            ));
+        }
+        let outcomes = [Outcome::WhiteWins, Outcome::Draw, Outcome::BlackWins];
+        for &outcome in outcomes.iter() {
+            let rate = self.rate(self.outcome_count(outcome));
+            if rate == 0.0 || rate > MAX_OUTCOME_RATE {
+                warnings.push(format!(
+                    "{:.1}% of the positions are {}",
+                    100.0 * rate,
+                    outcome_name(outcome)
+                ));
+            }
+        }
+        let phases = [
+            GamePhase::Opening,
+            GamePhase::Middlegame,
+            GamePhase::Endgame,
+        ];
+        for &phase in phases.iter() {
+            if self.phase_count(phase) == 0 {
+                warnings.push(format!("there are no {} positions", phase));
+            }
+        }
+        warnings
+    }
+}
+
+/// A dataset divided into training, validation and test sets
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Split {
+    pub train: Vec<String>,
+    pub validation: Vec<String>,
+    pub test: Vec<String>,
+    /// Lines that aren't samples and were left out
+    pub invalid: usize,
+}
+
+/// Divide the lines of a dataset into training, validation and test sets.
+///
+/// Every combination of outcome and game phase is divided on its own, so
+/// each set has about the same mix as the whole dataset. `train` and
+/// `validation` are the fractions of the first two sets, the test set gets
+/// the rest. The same seed always gives the same split.
+pub fn split<'a>(
+    lines: impl Iterator<Item = &'a str>,
+    train: f64,
+    validation: f64,
+    seed: u64,
+) -> Split {
+    let mut strata: BTreeMap<(usize, usize), Vec<&str>> = BTreeMap::new();
+    let mut result = Split::default();
+    for line in lines.filter(|line| !line.trim().is_empty()) {
+        match Sample::parse(line) {
+            Some(sample) => {
+                let key = (
+                    sample.outcome.index(),
+                    phase_index(sample.position.game_phase()),
+                );
+                strata.entry(key).or_default().push(line);
+            }
+            None => result.invalid += 1,
+        }
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    for lines in strata.values_mut() {
+        lines.shuffle(&mut rng);
+        let train_end = (lines.len() as f64 * train).round() as usize;
+        let validation_end =
+            (train_end + (lines.len() as f64 * validation).round() as usize).min(lines.len());
+        for (index, line) in lines.iter().enumerate() {
+            let set = if index < train_end {
+                &mut result.train
+            } else if index < validation_end {
+                &mut result.validation
+            } else {
+                &mut result.test
+            };
+            set.push(line.to_string());
+        }
+    }
+    result
+}
+
+/// The files of a split dataset, written next to them by the `split` command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub train: PathBuf,
+    pub validation: PathBuf,
+    pub test: PathBuf,
+}
+
+/// The reason a manifest couldn't be loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManifestError {
+    Read(String),
+    InvalidLine(String),
+    MissingSet(&'static str),
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestError::Read(reason) => write!(f, "failed to read the manifest: {}", reason),
+            ManifestError::InvalidLine(line) => write!(f, "invalid manifest line '{}'", line),
+            ManifestError::MissingSet(set) => write!(f, "the manifest has no {} set", set),
+        }
+    }
+}
+
+impl Error for ManifestError {}
+
+impl Manifest {
+    /// Read a manifest, the paths in it are relative to its own directory
+    pub fn load(path: &Path) -> Result<Self, ManifestError> {
+        let text =
+            fs::read_to_string(path).map_err(|error| ManifestError::Read(error.to_string()))?;
+        Self::parse(&text, path.parent().unwrap_or_else(|| Path::new("")))
+    }
+
+    /// Parse the `set = path` lines of a manifest, joining the paths to `dir`
+    pub fn parse(text: &str, dir: &Path) -> Result<Self, ManifestError> {
+        let (mut train, mut validation, mut test) = (None, None, None);
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let invalid = || ManifestError::InvalidLine(line.to_string());
+            let (set, path) = line.split_once('=').ok_or_else(invalid)?;
+            let path = Some(dir.join(path.trim()));
+            match set.trim() {
+                "train" => train = path,
+                "validation" => validation = path,
+                "test" => test = path,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(Self {
+            train: train.ok_or(ManifestError::MissingSet("train"))?,
+            validation: validation.ok_or(ManifestError::MissingSet("validation"))?,
+            test: test.ok_or(ManifestError::MissingSet("test"))?,
+        })
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "train = {}", self.train.display())?;
+        writeln!(f, "validation = {}", self.validation.display())?;
+        writeln!(f, "test = {}", self.test.display())
+    }
+}
+
+/// The samples of a dataset file, lines that aren't samples are skipped
+pub fn load_samples(path: &Path) -> Result<Vec<Sample>, std::io::Error> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter_map(Sample::parse)
+        .collect())
+}
+
+/// How badly the evaluation predicts the outcomes: the mean squared error
+/// between the outcomes and the expected scores of the evaluations
+pub fn prediction_error(samples: &[Sample], evaluator: &dyn Evaluator<Result = i16>) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let total: f64 = samples
+        .iter()
+        .map(|sample| {
+            let evaluation = f64::from(evaluator.evaluate(&sample.position));
+            let white = match sample.position.side_to_move() {
+                Color::White => evaluation,
+                Color::Black => -evaluation,
+            };
+            let expected = 1.0 / (1.0 + 10f64.powf(-white / ODDS_SCALE));
+            (sample.outcome.score() - expected).powi(2)
+        })
+        .sum();
+    total / samples.len() as f64
+}
+
+/// How the outcome is described in reports, e.g. `white wins`
+pub fn outcome_name(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::WhiteWins => "white wins",
+        Outcome::Draw => "draws",
+        Outcome::BlackWins => "black wins",
+    }
+}
+
+fn phase_index(phase: GamePhase) -> usize {
+    match phase {
+        GamePhase::Opening => 0,
+        GamePhase::Middlegame => 1,
+        GamePhase::Endgame => 2,
+    }
+}
+
+// White's material minus black's in pawns
+fn material_balance(position: &Position) -> i16 {
+    ALL_PIECES
+        .iter()
+        .map(|&piece: &Piece| {
+            let pieces = position.pieces(piece);
+            let white = (pieces & position.color_combined(Color::White)).popcnt() as i16;
+            let black = (pieces & position.color_combined(Color::Black)).popcnt() as i16;
+            PAWN_UNITS[piece as usize] * (white - black)
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prediction_error, split, DatasetStats, Manifest, ManifestError, Outcome, Sample};
+    use crate::eval::naive_evaluator;
+    use crate::GamePhase;
+    use std::path::Path;
+    use test_case::test_case;
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test_case("[1.0]", Some(Outcome::WhiteWins))]
+    #[test_case("\"1/2-1/2\";", Some(Outcome::Draw))]
+    #[test_case("0-1", Some(Outcome::BlackWins))]
+    #[test_case("1-1", None)]
+    fn outcomes(value: &str, expected: Option<Outcome>) {
+        assert_eq!(Outcome::parse(value), expected);
+    }
+
+    #[test]
+    fn samples() {
+        let sample = Sample::parse(&format!("{} [0.5]", START)).unwrap();
+        assert_eq!(sample.outcome, Outcome::Draw);
+        let epd = "8/8/4k3/8/8/4K3/4P3/8 w - - c9 \"1-0\";";
+        assert_eq!(Sample::parse(epd).unwrap().outcome, Outcome::WhiteWins);
+        assert!(Sample::parse(START).is_none());
+        assert!(Sample::parse("not a fen 1-0").is_none());
+    }
+
+    #[test]
+    fn composition() {
+        let mut stats = DatasetStats::default();
+        assert!(stats.add_line(&format!("{} 1-0", START)));
+        assert!(stats.add_line(&format!("{} 0-1", START)));
+        assert!(stats.add_line("4k3/8/8/8/8/8/8/3QK3 b - - 0 1 1-0"));
+        assert!(stats.add_line(""));
+        assert!(!stats.add_line("garbage"));
+        assert_eq!(stats.positions(), 3);
+        assert_eq!(stats.invalid(), 1);
+        assert_eq!(stats.duplicates(), 1);
+        assert_eq!(stats.outcome_count(Outcome::WhiteWins), 2);
+        assert_eq!(stats.phase_count(GamePhase::Opening), 2);
+        assert_eq!(stats.phase_count(GamePhase::Endgame), 1);
+        assert_eq!(stats.material_balances().get(&0), Some(&2));
+        assert_eq!(stats.material_balances().get(&5), Some(&1));
+        let warnings = stats.warnings();
+        assert!(warnings.contains(&String::from("1 lines couldn't be parsed")));
+        assert!(warnings.contains(&String::from("0.0% of the positions are draws")));
+        assert!(warnings.contains(&String::from("there are no middlegame positions")));
+    }
+
+    #[test]
+    fn stratified_split() {
+        let mut lines = Vec::new();
+        for _ in 0..10 {
+            lines.push(format!("{} 1-0", START));
+            lines.push(format!("{} 0-1", START));
+        }
+        lines.push(String::from("garbage"));
+        let result = split(lines.iter().map(String::as_str), 0.6, 0.2, 7);
+        assert_eq!(result.invalid, 1);
+        assert_eq!(
+            (
+                result.train.len(),
+                result.validation.len(),
+                result.test.len()
+            ),
+            (12, 4, 4)
+        );
+        // every set gets both outcomes in the same proportion
+        let wins = result.test.iter().filter(|line| line.ends_with("1-0"));
+        assert_eq!(wins.count(), 2);
+        assert_eq!(split(lines.iter().map(String::as_str), 0.6, 0.2, 7), result);
+    }
+
+    #[test]
+    fn manifest() {
+        let text = "train = a.train.epd\nvalidation = a.validation.epd\ntest = a.test.epd\n";
+        let manifest = Manifest::parse(text, Path::new("data")).unwrap();
+        assert_eq!(manifest.test, Path::new("data").join("a.test.epd"));
+        assert_eq!(
+            Manifest::parse("train = a\ntest = b", Path::new("")),
+            Err(ManifestError::MissingSet("validation"))
+        );
+    }
+
+    #[test]
+    fn evaluation_predicts_outcomes() {
+        let winning = "4k3/8/8/8/8/8/8/3QK3 w - - 0 1";
+        let good = [Sample::parse(&format!("{} 1-0", winning)).unwrap()];
+        let bad = [Sample::parse(&format!("{} 0-1", winning)).unwrap()];
+        let evaluator = naive_evaluator();
+        assert!(prediction_error(&good, &evaluator) < 0.05);
+        assert!(prediction_error(&bad, &evaluator) > 0.9);
+    }
+}
@@ -0,0 +1,406 @@
+use crate::{fen, pgn};
+use crate::{ChessMove, Position};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// A position in the Extended Position Description format: the first four
+/// FEN fields and the operations rad1 understands
+#[derive(Clone)]
+pub struct EpdPosition {
+    pub position: Position,
+    /// The `id` operation
+    pub id: Option<String>,
+    /// Moves of the `bm` operation, any of them solves the position
+    pub best_moves: Vec<ChessMove>,
+    /// Moves of the `am` operation, any other move solves the position
+    pub avoid_moves: Vec<ChessMove>,
+    /// The `ce` operation, an evaluation in centipawns for the side to move
+    pub centipawns: Option<i32>,
+    /// Moves of the `pv` operation, a line played from the position
+    pub pv: Vec<ChessMove>,
+    /// The `hmvc` operation, plies since the last capture or pawn move
+    pub halfmove_clock: u32,
+    /// The `fmvn` operation
+    pub fullmove_number: u32,
+}
+
+/// The reason a line of EPD or FEN couldn't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EpdError {
+    InvalidPosition(String),
+    /// A `bm`, `am` or `pv` move that isn't legal in the position
+    IllegalMove(String),
+    /// A `ce`, `hmvc` or `fmvn` operand that isn't a number
+    InvalidNumber(String),
+    /// Neither a `bm` nor an `am` operation, so nothing solves the position
+    NoSolution,
+}
+
+impl fmt::Display for EpdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EpdError::InvalidPosition(fen) => write!(f, "invalid position '{}'", fen),
+            EpdError::IllegalMove(chess_move) => write!(f, "'{}' is not a legal move", chess_move),
+            EpdError::InvalidNumber(number) => write!(f, "'{}' is not a number", number),
+            EpdError::NoSolution => write!(f, "no bm or am operation"),
+        }
+    }
+}
+
+impl Error for EpdError {}
+
+impl FromStr for EpdPosition {
+    type Err = EpdError;
+
+    /// Parses an EPD line: the first four FEN fields followed by operations
+    /// like `bm Nf3 e4;`, `am Qxb2;`, `ce 35;`, `pv e4 e5 Nf3;` or
+    /// `id "WAC.001";`. Moves are in SAN, UCI notation works too. Other
+    /// operations are skipped.
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        let fields: Vec<&str> = line.split_whitespace().take(4).collect();
+        let mut epd = Self::from_fen(&fields.join(" "))?;
+        let operations = line
+            .splitn(5, char::is_whitespace)
+            .nth(4)
+            .unwrap_or_default();
+        for operation in split_operations(operations) {
+            let (opcode, operands) = operation.split_once(' ').unwrap_or((operation, ""));
+            let operands = operands.trim();
+            match opcode {
+                "bm" => epd.best_moves = epd.moves(operands)?,
+                "am" => epd.avoid_moves = epd.moves(operands)?,
+                "ce" => epd.centipawns = Some(number(operands)?),
+                "pv" => epd.pv = epd.line(operands)?,
+                "hmvc" => epd.halfmove_clock = number(operands)?,
+                "fmvn" => epd.fullmove_number = number(operands)?,
+                "id" => epd.id = Some(operands.trim_matches('"').to_string()),
+                _ => {}
+            }
+        }
+        Ok(epd)
+    }
+}
+
+impl fmt::Display for EpdPosition {
+    /// Formats the position as an EPD line with its operations in the order
+    /// `bm`, `am`, `ce`, `pv`, `hmvc`, `fmvn`, `id`, moves in SAN. The move
+    /// counters are left out when they are the FEN defaults, `0 1`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", fen_fields(&self.position))?;
+        if !self.best_moves.is_empty() {
+            write!(f, " bm {};", self.sans(&self.best_moves))?;
+        }
+        if !self.avoid_moves.is_empty() {
+            write!(f, " am {};", self.sans(&self.avoid_moves))?;
+        }
+        if let Some(centipawns) = self.centipawns {
+            write!(f, " ce {};", centipawns)?;
+        }
+        if !self.pv.is_empty() {
+            let mut position = self.position.clone();
+            let mut sans = Vec::with_capacity(self.pv.len());
+            for &chess_move in &self.pv {
+                sans.push(pgn::san(&position, chess_move));
+                position = position.make_move_new(chess_move);
+            }
+            write!(f, " pv {};", sans.join(" "))?;
+        }
+        if self.halfmove_clock != 0 {
+            write!(f, " hmvc {};", self.halfmove_clock)?;
+        }
+        if self.fullmove_number != 1 {
+            write!(f, " fmvn {};", self.fullmove_number)?;
+        }
+        if let Some(id) = &self.id {
+            write!(f, " id \"{}\";", id)?;
+        }
+        Ok(())
+    }
+}
+
+impl EpdPosition {
+    /// The position of a FEN without any operations, its move counters kept
+    /// as `hmvc` and `fmvn`. The counters may be left out.
+    pub fn from_fen(fen: &str) -> Result<Self, EpdError> {
+        let fen = fen.trim();
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        let invalid = || EpdError::InvalidPosition(fen.to_string());
+        if fields.len() < 4 || fields.len() > 6 {
+            return Err(invalid());
+        }
+        let position =
+            fen::parse(&format!("{} 0 1", fields[..4].join(" "))).map_err(|_| invalid())?;
+        let counter = |index: usize, default: u32| match fields.get(index) {
+            Some(text) => text.parse().map_err(|_| invalid()),
+            None => Ok(default),
+        };
+        Ok(Self {
+            position,
+            id: None,
+            best_moves: Vec::new(),
+            avoid_moves: Vec::new(),
+            centipawns: None,
+            pv: Vec::new(),
+            halfmove_clock: counter(4, 0)?,
+            fullmove_number: counter(5, 1)?,
+        })
+    }
+
+    /// The position of a test suite, an error when it has neither a `bm`
+    /// nor an `am` operation
+    pub fn require_solution(self) -> Result<Self, EpdError> {
+        if self.best_moves.is_empty() && self.avoid_moves.is_empty() {
+            Err(EpdError::NoSolution)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// The `id` operation, or the FEN fields when there is none
+    pub fn name(&self) -> String {
+        self.id
+            .clone()
+            .unwrap_or_else(|| fen_fields(&self.position))
+    }
+
+    /// The position as a full FEN with the `hmvc` and `fmvn` move counters
+    pub fn to_fen(&self) -> String {
+        format!(
+            "{} {} {}",
+            fen_fields(&self.position),
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+
+    fn moves(&self, operands: &str) -> Result<Vec<ChessMove>, EpdError> {
+        operands
+            .split_whitespace()
+            .map(|text| parse_move(&self.position, text))
+            .collect()
+    }
+
+    // The moves of a line, each played in the position the ones before it lead to
+    fn line(&self, operands: &str) -> Result<Vec<ChessMove>, EpdError> {
+        let mut position = self.position.clone();
+        let mut line = Vec::new();
+        for text in operands.split_whitespace() {
+            let chess_move = parse_move(&position, text)?;
+            position = position.make_move_new(chess_move);
+            line.push(chess_move);
+        }
+        Ok(line)
+    }
+
+    fn sans(&self, moves: &[ChessMove]) -> String {
+        moves
+            .iter()
+            .map(|&chess_move| pgn::san(&self.position, chess_move))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    /// Whether playing the move solves the position
+    pub fn is_solution(&self, chess_move: ChessMove) -> bool {
+        if self.best_moves.is_empty() {
+            !self.avoid_moves.contains(&chess_move)
+        } else {
+            self.best_moves.contains(&chess_move) && !self.avoid_moves.contains(&chess_move)
+        }
+    }
+
+    /// How long a search took to find a solution and stick to it: the time
+    /// of the first of the iterations, given as their time and best move in
+    /// order, after which every best move solved the position. None when
+    /// the last best move doesn't.
+    pub fn time_to_solution(&self, iterations: &[(Duration, ChessMove)]) -> Option<Duration> {
+        let mut solved_at = None;
+        for &(elapsed, best_move) in iterations {
+            if !self.is_solution(best_move) {
+                solved_at = None;
+            } else if solved_at.is_none() {
+                solved_at = Some(elapsed);
+            }
+        }
+        solved_at
+    }
+}
+
+// The operations of an EPD line, split at the semicolons outside of quotes
+fn split_operations(operations: &str) -> Vec<&str> {
+    let mut split = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (index, c) in operations.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                split.push(&operations[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    split.push(&operations[start..]);
+    split
+        .into_iter()
+        .map(str::trim)
+        .filter(|operation| !operation.is_empty())
+        .collect()
+}
+
+fn parse_move(position: &Position, text: &str) -> Result<ChessMove, EpdError> {
+    pgn::parse_san(position, text)
+        .or_else(|| {
+            ChessMove::from_str(text)
+                .ok()
+                .filter(|&chess_move| position.legal(chess_move))
+        })
+        .ok_or_else(|| EpdError::IllegalMove(text.to_string()))
+}
+
+fn number<T: FromStr>(operand: &str) -> Result<T, EpdError> {
+    operand
+        .parse()
+        .map_err(|_| EpdError::InvalidNumber(operand.to_string()))
+}
+
+// The placement, side to move, castling rights and en passant square
+fn fen_fields(position: &Position) -> String {
+    position
+        .to_string()
+        .split_whitespace()
+        .take(4)
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EpdError, EpdPosition};
+    use crate::ChessMove;
+    use std::str::FromStr;
+    use std::time::Duration;
+    use test_case::test_case;
+
+    const MATE: &str = "6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; id \"back rank\";";
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn moves(moves: &str) -> Vec<ChessMove> {
+        moves
+            .split_whitespace()
+            .map(|text| ChessMove::from_str(text).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn operations() {
+        let epd = EpdPosition::from_str(MATE).unwrap();
+        let mate = ChessMove::from_str("a1a8").unwrap();
+        assert_eq!(epd.id.as_deref(), Some("back rank"));
+        assert_eq!(epd.name(), "back rank");
+        assert_eq!(epd.best_moves, vec![mate]);
+        assert!(epd.is_solution(mate));
+        assert!(!epd.is_solution(ChessMove::from_str("a1a2").unwrap()));
+
+        let epd = EpdPosition::from_str("6k1/5ppp/8/8/8/8/8/R5K1 w - - am a1a2;").unwrap();
+        assert_eq!(epd.name(), "6k1/5ppp/8/8/8/8/8/R5K1 w - -");
+        assert!(epd.is_solution(mate));
+
+        let epd = EpdPosition::from_str(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - ce -12; pv e4 e5 Nf3; id \"a;b\"; c0 \"x\";",
+        )
+        .unwrap();
+        assert_eq!(epd.centipawns, Some(-12));
+        assert_eq!(epd.pv, moves("e2e4 e7e5 g1f3"));
+        assert_eq!(epd.id.as_deref(), Some("a;b"));
+    }
+
+    #[test]
+    fn invalid_lines() {
+        assert!(matches!(
+            EpdPosition::from_str("6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Qh7;"),
+            Err(EpdError::IllegalMove(_))
+        ));
+        assert!(matches!(
+            EpdPosition::from_str("4k3/8/8/8/8/8/8/4K3 w - - pv Kd1 Kd1;"),
+            Err(EpdError::IllegalMove(_))
+        ));
+        assert!(matches!(
+            EpdPosition::from_str("6k1/5ppp/8/8/8/8/8/R5K1 w - - ce +M3;"),
+            Err(EpdError::InvalidNumber(_))
+        ));
+        assert!(matches!(
+            EpdPosition::from_str("6k1/5ppp/8/8/8/8/8/R5K1 w - - id \"x\";")
+                .and_then(EpdPosition::require_solution),
+            Err(EpdError::NoSolution)
+        ));
+        assert!(matches!(
+            EpdPosition::from_str("not a position"),
+            Err(EpdError::InvalidPosition(_))
+        ));
+    }
+
+    #[test_case(MATE; "best move")]
+    #[test_case("6k1/5ppp/8/8/8/8/8/R5K1 w - - am Ra2 Kf1;"; "avoid moves")]
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - ce 35; pv e4 e5 Nf3;"; "pv from white")]
+    #[test_case("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 pv e5 Nf3 Nc6; hmvc 0; fmvn 1;"; "pv from black")]
+    #[test_case("8/8/8/4k3/8/8/8/4K3 w - - hmvc 12; fmvn 40;"; "move counters")]
+    #[test_case("8/8/8/4k3/8/8/8/4K3 w - -"; "no operations")]
+    fn written_as_read(line: &str) {
+        let epd = EpdPosition::from_str(line).unwrap();
+        let written = EpdPosition::from_str(&epd.to_string()).unwrap();
+        assert_eq!(written.to_string(), epd.to_string());
+        assert_eq!(written.best_moves, epd.best_moves);
+        assert_eq!(written.avoid_moves, epd.avoid_moves);
+        assert_eq!(written.pv, epd.pv);
+        assert_eq!(written.centipawns, epd.centipawns);
+        assert_eq!(written.id, epd.id);
+    }
+
+    #[test]
+    fn writing() {
+        let mut epd =
+            EpdPosition::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1")
+                .unwrap();
+        assert_eq!(
+            epd.to_string(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq -"
+        );
+        epd.best_moves = moves("c7c5");
+        epd.centipawns = Some(20);
+        epd.pv = moves("c7c5 g1f3");
+        epd.id = Some(String::from("sicilian"));
+        assert_eq!(
+            epd.to_string(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - bm c5; ce 20; pv c5 Nf3; id \"sicilian\";"
+        );
+    }
+
+    #[test_case("8/8/8/4k3/8/8/8/4K3 w - - 12 40", "8/8/8/4k3/8/8/8/4K3 w - - hmvc 12; fmvn 40;"; "counters")]
+    #[test_case("8/8/8/4k3/8/8/8/4K3 w - -", "8/8/8/4k3/8/8/8/4K3 w - -"; "no counters")]
+    fn fen_conversion(fen: &str, epd: &str) {
+        let from_fen = EpdPosition::from_fen(fen).unwrap();
+        assert_eq!(from_fen.to_string(), epd);
+        let from_epd = EpdPosition::from_str(epd).unwrap();
+        assert_eq!(from_epd.to_fen(), from_fen.to_fen());
+        assert!(EpdPosition::from_fen("8/8/8/4k3/8/8/8/4K3 w - - x 1").is_err());
+    }
+
+    #[test]
+    fn solutions_have_to_stick() {
+        let epd = EpdPosition::from_str(MATE).unwrap();
+        let mate = ChessMove::from_str("a1a8").unwrap();
+        let other = ChessMove::from_str("g1f1").unwrap();
+        let iterations = [(ms(1), other), (ms(2), mate), (ms(5), other), (ms(9), mate)];
+        assert_eq!(epd.time_to_solution(&iterations), Some(ms(9)));
+        assert_eq!(epd.time_to_solution(&iterations[..2]), Some(ms(2)));
+        assert_eq!(epd.time_to_solution(&iterations[..3]), None);
+    }
+}
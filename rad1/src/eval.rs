@@ -1,14 +1,111 @@
+use crate::options::EngineOptions;
 use crate::Position;
+use std::fs;
 
+pub mod compare;
+pub mod hybrid;
+pub mod material;
 pub mod naive;
+pub mod nnue;
+pub mod pst;
+pub mod random;
+pub mod registry;
 
 pub trait Evaluator {
     type Result;
     fn min_value(&self) -> Self::Result;
     fn max_value(&self) -> Self::Result;
     fn evaluate(&self, position: &Position) -> Self::Result;
+
+    /// Always return the same value for a position, from now on and in any
+    /// other evaluator of the same kind that was made deterministic.
+    /// Only evaluators with some randomness need to do anything here.
+    fn make_deterministic(&mut self) {}
+
+    /// The position a search of the given root starts from. Evaluators that
+    /// keep state in positions, updated with every move, attach it here.
+    fn prepare(&self, position: &Position) -> Position {
+        position.clone()
+    }
+
+    /// Adjust the evaluator to the engine options, called whenever they change.
+    /// Only evaluators with settings of their own need to do anything here.
+    fn configure(&mut self, _options: &EngineOptions) {}
 }
 
 pub fn naive_evaluator() -> naive::NaiveEvaluator {
-    naive::NaiveEvaluator
+    naive::NaiveEvaluator::default()
+}
+
+/// An evaluator that only counts material, a baseline for match testing
+pub fn material_evaluator() -> material::MaterialEvaluator {
+    material::MaterialEvaluator
+}
+
+/// An evaluator that returns random values, the weakest baseline for match testing
+pub fn random_evaluator() -> random::RandomEvaluator {
+    random::RandomEvaluator::default()
+}
+
+/// The evaluators that can be selected by name, see [`registry::Registry`]
+pub fn registry() -> registry::Registry {
+    let mut registry = registry::Registry::default();
+    registry.register("naive", "Material and piece-square tables", |_| {
+        Ok(Box::new(naive_evaluator()))
+    });
+    registry.register_with_argument(
+        "tuned",
+        "Material and piece-square tables with weights from a file written by tune --genetic",
+        |path| {
+            let config = fs::read_to_string(path)
+                .map_err(|error| error.to_string())
+                .and_then(|config| {
+                    config
+                        .parse::<naive::EvaluationConfig>()
+                        .map_err(|error| error.to_string())
+                })
+                .map_err(|reason| registry::RegistryError::Load {
+                    key: format!("tuned:{}", path),
+                    reason,
+                })?;
+            Ok(Box::new(naive::NaiveEvaluator::with_config(config)))
+        },
+    );
+    registry.register_with_argument(
+        "nnue",
+        "A quantized network from a file, see nnue::Network for the format",
+        |path| {
+            let network = fs::read(path)
+                .map_err(|error| error.to_string())
+                .and_then(|bytes| nnue::Network::read(&bytes).map_err(|error| error.to_string()))
+                .map_err(|reason| registry::RegistryError::Load {
+                    key: format!("nnue:{}", path),
+                    reason,
+                })?;
+            Ok(Box::new(nnue::NnueEvaluator::new(network)))
+        },
+    );
+    registry.register_with_argument(
+        "hybrid",
+        "The naive evaluator blended with a network from a file by the NNUE Blend option",
+        |path| {
+            let network = fs::read(path)
+                .map_err(|error| error.to_string())
+                .and_then(|bytes| nnue::Network::read(&bytes).map_err(|error| error.to_string()))
+                .map_err(|reason| registry::RegistryError::Load {
+                    key: format!("hybrid:{}", path),
+                    reason,
+                })?;
+            Ok(Box::new(hybrid::HybridEvaluator::new(network)))
+        },
+    );
+    registry.register("material-only", "Material only", |_| {
+        Ok(Box::new(material_evaluator()))
+    });
+    registry.register(
+        "random",
+        "Random values, checkmates are still found",
+        |_| Ok(Box::new(random_evaluator())),
+    );
+    registry
 }
@@ -0,0 +1,154 @@
+use super::Evaluator;
+use crate::{Position, PositionStatus};
+
+/// What two evaluators think of one position, from the side to move's point of view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scores {
+    /// Index of the position in the compared positions
+    pub index: usize,
+    pub first: i16,
+    pub second: i16,
+}
+
+impl Scores {
+    /// How much higher the second evaluator scores the position
+    pub fn difference(&self) -> i32 {
+        i32::from(self.second) - i32::from(self.first)
+    }
+}
+
+/// The evaluations of two evaluators over the same positions, to review
+/// how an evaluation change plays out before testing it in matches.
+///
+/// Checkmates and stalemates are left out, every evaluator agrees on them
+/// and their extreme values would swamp the statistics.
+#[derive(Debug, Clone, Default)]
+pub struct Comparison {
+    scores: Vec<Scores>,
+}
+
+impl Comparison {
+    pub fn new(
+        first: &dyn Evaluator<Result = i16>,
+        second: &dyn Evaluator<Result = i16>,
+        positions: &[Position],
+    ) -> Self {
+        let scores = positions
+            .iter()
+            .enumerate()
+            .filter(|(_, position)| matches!(position.status(), PositionStatus::Ongoing))
+            .map(|(index, position)| Scores {
+                index,
+                first: first.evaluate(position),
+                second: second.evaluate(position),
+            })
+            .collect();
+        Self { scores }
+    }
+
+    /// The scores of every compared position in the order they were given
+    pub fn scores(&self) -> &[Scores] {
+        &self.scores
+    }
+
+    /// The Pearson correlation of the two evaluations, `None` if there are
+    /// no positions or either evaluator gives all of them the same score
+    pub fn correlation(&self) -> Option<f64> {
+        let count = self.scores.len() as f64;
+        let mean = |score: fn(&Scores) -> i16| {
+            self.scores.iter().map(|s| f64::from(score(s))).sum::<f64>() / count
+        };
+        let (first_mean, second_mean) = (mean(|s| s.first), mean(|s| s.second));
+        let (mut covariance, mut first_variance, mut second_variance) = (0.0, 0.0, 0.0);
+        for scores in &self.scores {
+            let first = f64::from(scores.first) - first_mean;
+            let second = f64::from(scores.second) - second_mean;
+            covariance += first * second;
+            first_variance += first * first;
+            second_variance += second * second;
+        }
+        if first_variance == 0.0 || second_variance == 0.0 {
+            None
+        } else {
+            Some(covariance / (first_variance * second_variance).sqrt())
+        }
+    }
+
+    /// The mean absolute difference of the evaluations, 0 without positions
+    pub fn mean_absolute_difference(&self) -> f64 {
+        if self.scores.is_empty() {
+            return 0.0;
+        }
+        let total: f64 = self
+            .scores
+            .iter()
+            .map(|scores| f64::from(scores.difference().abs()))
+            .sum();
+        total / self.scores.len() as f64
+    }
+
+    /// The positions the evaluators disagree on the most, largest difference first
+    pub fn largest_disagreements(&self, count: usize) -> Vec<Scores> {
+        let mut scores = self.scores.clone();
+        scores.sort_by_key(|scores| std::cmp::Reverse(scores.difference().abs()));
+        scores.truncate(count);
+        scores
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Comparison;
+    use crate::eval::{material_evaluator, naive_evaluator};
+    use crate::Position;
+    use std::str::FromStr;
+
+    fn positions() -> Vec<Position> {
+        [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "4k3/8/8/4n3/8/8/8/4RK2 w - - 0 1",
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+            "4k3/8/8/8/8/8/8/QQ2K3 b - - 0 1",
+        ]
+        .iter()
+        .map(|fen| Position::from_str(fen).unwrap())
+        .collect()
+    }
+
+    #[test]
+    fn identical_evaluators_agree() {
+        let comparison = Comparison::new(&naive_evaluator(), &naive_evaluator(), &positions());
+        // the checkmate is left out
+        assert_eq!(comparison.scores().len(), 3);
+        assert_eq!(comparison.mean_absolute_difference(), 0.0);
+        assert!((comparison.correlation().unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn disagreements_are_ranked() {
+        let positions = positions();
+        let naive = naive_evaluator();
+        let material = material_evaluator();
+        let comparison = Comparison::new(&material, &naive, &positions);
+        let disagreements = comparison.largest_disagreements(2);
+        assert_eq!(disagreements.len(), 2);
+        assert!(disagreements[0].difference().abs() >= disagreements[1].difference().abs());
+        let largest = comparison
+            .scores()
+            .iter()
+            .map(|scores| scores.difference().abs())
+            .max()
+            .unwrap();
+        assert_eq!(disagreements[0].difference().abs(), largest);
+        assert!(comparison.mean_absolute_difference() > 0.0);
+        assert!(comparison.correlation().unwrap() > 0.9);
+    }
+
+    #[test]
+    fn no_positions() {
+        let comparison = Comparison::new(&naive_evaluator(), &material_evaluator(), &[]);
+        assert_eq!(comparison.correlation(), None);
+        assert_eq!(comparison.mean_absolute_difference(), 0.0);
+        assert!(comparison.largest_disagreements(5).is_empty());
+    }
+}
@@ -0,0 +1,132 @@
+use super::naive::NaiveEvaluator;
+use super::nnue::{Network, NnueEvaluator};
+use super::Evaluator;
+use crate::options::EngineOptions;
+use crate::{Position, PositionStatus};
+
+/// Blends the naive evaluator with a network.
+///
+/// The network's share of the evaluation is the `NNUE Blend` option in
+/// percent. Positions where the material and piece-square total is further
+/// from even than [`HybridEvaluator::LOPSIDED`] are left to the naive
+/// evaluator alone, the way engines that rolled out NNUE gradually kept their
+/// classical evaluation for lopsided positions.
+#[derive(Debug, Clone)]
+pub struct HybridEvaluator {
+    classical: NaiveEvaluator,
+    nnue: NnueEvaluator,
+    blend: u8,
+}
+
+impl HybridEvaluator {
+    /// Material and piece-square imbalance above which the network isn't asked
+    pub const LOPSIDED: i16 = 50;
+
+    pub fn new(network: Network) -> Self {
+        Self {
+            classical: NaiveEvaluator::default(),
+            nnue: NnueEvaluator::new(network),
+            blend: EngineOptions::default().nnue_blend,
+        }
+    }
+
+    /// The network's share of the evaluation in percent
+    pub fn blend(&self) -> u8 {
+        self.blend
+    }
+
+    pub fn with_blend(mut self, blend: u8) -> Self {
+        self.blend = blend.min(100);
+        self
+    }
+}
+
+impl Evaluator for HybridEvaluator {
+    type Result = i16;
+
+    #[inline]
+    fn min_value(&self) -> Self::Result {
+        self.classical.min_value()
+    }
+
+    #[inline]
+    fn max_value(&self) -> Self::Result {
+        self.classical.max_value()
+    }
+
+    fn evaluate(&self, position: &Position) -> Self::Result {
+        let classical = self.classical.evaluate(position);
+        if self.blend == 0
+            || position.psqt().abs() > Self::LOPSIDED
+            || !matches!(position.status(), PositionStatus::Ongoing)
+        {
+            return classical;
+        }
+        let nnue = self.nnue.evaluate(position);
+        let blend = i32::from(self.blend);
+        ((i32::from(nnue) * blend + i32::from(classical) * (100 - blend)) / 100) as i16
+    }
+
+    fn prepare(&self, position: &Position) -> Position {
+        self.nnue.prepare(position)
+    }
+
+    fn configure(&mut self, options: &EngineOptions) {
+        self.blend = options.nnue_blend;
+        self.classical.configure(options);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HybridEvaluator;
+    use crate::eval::naive::NaiveEvaluator;
+    use crate::eval::nnue::{Network, NnueEvaluator};
+    use crate::eval::Evaluator;
+    use crate::Position;
+    use std::str::FromStr;
+    use test_case::test_case;
+
+    // A network that evaluates every position as 10 units for the side to move
+    fn constant_network() -> Network {
+        let mut bytes = b"RAD1NNUE\x01\x10\x00".to_vec();
+        bytes.resize(bytes.len() + 2 * 769 * 16 + 2 * 16, 0);
+        // 100 centipawns are 4080 before scaling by 400 / (255 * 64)
+        bytes.extend_from_slice(&4080i32.to_le_bytes());
+        Network::read(&bytes).unwrap()
+    }
+
+    #[test_case(0)]
+    #[test_case(50)]
+    #[test_case(100)]
+    fn blends_by_percent(blend: u8) {
+        let position = Position::from_str("4k3/pp6/8/8/8/8/5PPP/4K3 w - - 0 1").unwrap();
+        let classical = NaiveEvaluator::default().evaluate(&position);
+        let nnue = NnueEvaluator::new(constant_network()).evaluate(&position);
+        assert_eq!(nnue, 10);
+        let hybrid = HybridEvaluator::new(constant_network()).with_blend(blend);
+        let expected = (i32::from(nnue) * i32::from(blend)
+            + i32::from(classical) * (100 - i32::from(blend)))
+            / 100;
+        assert_eq!(i32::from(hybrid.evaluate(&position)), expected);
+    }
+
+    #[test]
+    fn lopsided_positions_are_classical() {
+        let position = Position::from_str("4k3/8/8/8/8/8/8/QQ2K3 w - - 0 1").unwrap();
+        let hybrid = HybridEvaluator::new(constant_network()).with_blend(100);
+        assert_eq!(
+            hybrid.evaluate(&position),
+            NaiveEvaluator::default().evaluate(&position)
+        );
+    }
+
+    #[test]
+    fn blend_is_an_engine_option() {
+        let mut options = crate::options::EngineOptions::default();
+        options.set("NNUE Blend", "30").unwrap();
+        let mut hybrid = HybridEvaluator::new(constant_network());
+        hybrid.configure(&options);
+        assert_eq!(hybrid.blend(), 30);
+    }
+}
@@ -0,0 +1,75 @@
+use super::Evaluator;
+use crate::{Piece, Position, PositionStatus};
+
+/// Counts material and nothing else.
+///
+/// Uses the same units and piece values as the naive evaluator,
+/// which makes it a baseline for how much the piece-square tables add.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaterialEvaluator;
+
+impl MaterialEvaluator {
+    const MIN: i16 = -30000;
+    const MAX: i16 = 30000;
+    const ZERO: i16 = 0;
+    const PIECE_VALUES: [i16; 6] = [10, 30, 30, 50, 90, 0];
+
+    #[inline]
+    fn piece_value(piece: Piece) -> i16 {
+        Self::PIECE_VALUES[piece.to_index()]
+    }
+}
+
+impl Evaluator for MaterialEvaluator {
+    type Result = i16;
+
+    #[inline]
+    fn min_value(&self) -> Self::Result {
+        Self::MIN
+    }
+
+    #[inline]
+    fn max_value(&self) -> Self::Result {
+        Self::MAX
+    }
+
+    fn evaluate(&self, position: &Position) -> Self::Result {
+        match position.status() {
+            PositionStatus::Stalemate => Self::ZERO,
+            PositionStatus::Checkmate => Self::MIN,
+            PositionStatus::Ongoing => {
+                let my_pieces = position.color_combined(position.side_to_move());
+                let their_pieces = position.color_combined(!position.side_to_move());
+                crate::ALL_PIECES
+                    .iter()
+                    .map(|&piece| {
+                        let pieces = position.pieces(piece);
+                        Self::piece_value(piece)
+                            * ((my_pieces & pieces).popcnt() as i16
+                                - (their_pieces & pieces).popcnt() as i16)
+                    })
+                    .sum()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MaterialEvaluator;
+    use crate::eval::Evaluator;
+    use crate::Position;
+    use std::str::FromStr;
+
+    #[test]
+    fn initial_board_eval() {
+        assert_eq!(MaterialEvaluator.evaluate(&Position::default()), 0);
+    }
+
+    #[test]
+    fn extra_queen_for_black() {
+        let position =
+            Position::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR b KQkq - 0 1").unwrap();
+        assert_eq!(MaterialEvaluator.evaluate(&position), 90);
+    }
+}
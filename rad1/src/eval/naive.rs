@@ -1,14 +1,287 @@
 use super::Evaluator;
-use crate::{Color, Piece, Position, PositionStatus, Square};
+use crate::masks;
+use crate::options::EngineOptions;
+use crate::{
+    BitBoard, Color, GamePhase, Piece, Position, PositionStatus, Rank, RelativeSquare, Square,
+    ALL_PIECES,
+};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct NaiveEvaluator;
+/// Weights of the terms the naive evaluator adds to material and
+/// piece-square values, in evaluation units
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvaluationConfig {
+    /// Bonus for a passed pawn that queens before the other king can catch it,
+    /// a queen minus the pawn that is already counted
+    pub unstoppable_passer: i16,
+    /// Bonus for attacking a piece that isn't defended
+    pub hanging_piece: i16,
+    /// Bonus for attacking a piece other than a pawn with a pawn
+    pub pawn_threat: i16,
+    /// Bonus for a safe pawn push that attacks two pieces at once
+    pub pawn_fork: i16,
+    /// The values of a pawn, knight, bishop, rook and queen in the opening,
+    /// middlegame and endgame, see [`GamePhase`]. They aren't tuned and
+    /// aren't part of weight files, only overrides change them, see
+    /// [`EvaluationConfig::set`].
+    pub piece_values: [[i16; 5]; 3],
+}
+
+/// The reason a config file couldn't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// A line that isn't a known `name = value` pair
+    InvalidLine(String),
+    /// A header line that is missing from a file with a header
+    MissingHeader(&'static str),
+    /// A `format` other than [`EvaluationConfig::FORMAT`]
+    InvalidFormat(String),
+    /// A layout version other than [`EvaluationConfig::VERSION`]
+    UnsupportedVersion(u32),
+    /// The terms the header announces, or the weights the file has, aren't
+    /// the terms of this build
+    TermCount { expected: usize, actual: usize },
+    /// The weights don't add up to the header's checksum, the file was edited
+    /// or damaged after it was written
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// A path of [`EvaluationConfig::set`] that names no weight or piece value
+    UnknownPath(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::InvalidLine(line) => write!(f, "invalid line '{}'", line),
+            ConfigError::MissingHeader(name) => write!(f, "missing header line '{}'", name),
+            ConfigError::InvalidFormat(format) => write!(
+                f,
+                "'{}' isn't a weight file format, expected '{}'",
+                format,
+                EvaluationConfig::FORMAT
+            ),
+            ConfigError::UnsupportedVersion(version) => write!(
+                f,
+                "weight file version {} can't be read, this build reads version {}",
+                version,
+                EvaluationConfig::VERSION
+            ),
+            ConfigError::TermCount { expected, actual } => write!(
+                f,
+                "weight file has {} terms but this build evaluates {}, tune the weights again",
+                actual, expected
+            ),
+            ConfigError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "weights have checksum {:08x} instead of {:08x}, the file was changed after it was written",
+                actual, expected
+            ),
+            ConfigError::UnknownPath(path) => write!(
+                f,
+                "'{}' isn't a weight or a phase and piece like opening.knight",
+                path
+            ),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+impl EvaluationConfig {
+    pub const DEFAULT: Self = Self {
+        unstoppable_passer: 80,
+        hanging_piece: 3,
+        pawn_threat: 4,
+        pawn_fork: 5,
+        piece_values: [NaiveEvaluator::BASE_PIECE_VALUES; 3],
+    };
+    /// The `format` line that starts the header of weight files
+    pub const FORMAT: &'static str = "rad1-weights";
+    /// The layout of the weights in files, raise it whenever terms are added,
+    /// removed or reordered so older files are rejected instead of misread
+    pub const VERSION: u32 = 1;
+    /// Names of the weights in config files, in the order of [`EvaluationConfig::weights`]
+    pub const NAMES: [&'static str; 4] = [
+        "unstoppable_passer",
+        "hanging_piece",
+        "pawn_threat",
+        "pawn_fork",
+    ];
+
+    pub fn weights(&self) -> [i16; 4] {
+        [
+            self.unstoppable_passer,
+            self.hanging_piece,
+            self.pawn_threat,
+            self.pawn_fork,
+        ]
+    }
+
+    /// FNV-1a hash of the names and values of the weights, written to the
+    /// header of weight files to catch files changed after they were written
+    pub fn checksum(&self) -> u32 {
+        let mut hash: u32 = 0x811c_9dc5;
+        for (name, weight) in Self::NAMES.iter().zip(self.weights().iter()) {
+            for byte in format!("{}={};", name, weight).bytes() {
+                hash ^= u32::from(byte);
+                hash = hash.wrapping_mul(0x0100_0193);
+            }
+        }
+        hash
+    }
+
+    /// The config with the weights and the default piece values
+    pub fn from_weights(weights: [i16; 4]) -> Self {
+        Self {
+            unstoppable_passer: weights[0],
+            hanging_piece: weights[1],
+            pawn_threat: weights[2],
+            pawn_fork: weights[3],
+            piece_values: Self::DEFAULT.piece_values,
+        }
+    }
+
+    /// Set a weight by its name, e.g. `hanging_piece`, or the value of a
+    /// piece in a game phase by the phase and the piece, e.g. `opening.knight`,
+    /// in evaluation units
+    pub fn set(&mut self, path: &str, value: i16) -> Result<(), ConfigError> {
+        let unknown = || ConfigError::UnknownPath(path.to_string());
+        match path.split_once('.') {
+            None => {
+                let index = Self::NAMES
+                    .iter()
+                    .position(|&name| name == path)
+                    .ok_or_else(unknown)?;
+                let mut weights = self.weights();
+                weights[index] = value;
+                *self = Self {
+                    piece_values: self.piece_values,
+                    ..Self::from_weights(weights)
+                };
+            }
+            Some((phase, piece)) => {
+                let phase = PHASE_NAMES
+                    .iter()
+                    .position(|&name| name == phase)
+                    .ok_or_else(unknown)?;
+                let piece = PIECE_NAMES
+                    .iter()
+                    .position(|&name| name == piece)
+                    .ok_or_else(unknown)?;
+                self.piece_values[phase][piece] = value;
+            }
+        }
+        Ok(())
+    }
+}
+
+// The names of the phases and pieces in the paths of `EvaluationConfig::set`,
+// in the order of `EvaluationConfig::piece_values`
+const PHASE_NAMES: [&str; 3] = ["opening", "middlegame", "endgame"];
+const PIECE_NAMES: [&str; 5] = ["pawn", "knight", "bishop", "rook", "queen"];
+
+impl Default for EvaluationConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl fmt::Display for EvaluationConfig {
+    /// Formats the weights as a config file with one `name = value` line per
+    /// weight, after a header with the format, version, number of terms and checksum
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "format = {}", Self::FORMAT)?;
+        writeln!(f, "version = {}", Self::VERSION)?;
+        writeln!(f, "terms = {}", Self::NAMES.len())?;
+        writeln!(f, "checksum = {:08x}", self.checksum())?;
+        for (name, weight) in Self::NAMES.iter().zip(self.weights().iter()) {
+            writeln!(f, "{} = {}", name, weight)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for EvaluationConfig {
+    type Err = ConfigError;
+
+    /// Parses a config file, blank lines and lines starting with `#` are skipped.
+    ///
+    /// Files written by [`fmt::Display`] start with a header that is checked:
+    /// the format, the version, that every term of this build is given and
+    /// the checksum. Hand-written files without a header may leave weights
+    /// out, those keep their defaults.
+    fn from_str(config: &str) -> Result<Self, Self::Err> {
+        let mut weights = Self::DEFAULT.weights();
+        let mut given = 0;
+        let (mut format, mut version, mut terms, mut checksum) = (None, None, None, None);
+        for line in config.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || ConfigError::InvalidLine(line.to_string());
+            let (name, value) = line.split_once('=').ok_or_else(invalid)?;
+            let value = value.trim();
+            match name.trim() {
+                "format" => format = Some(value.to_string()),
+                "version" => version = Some(value.parse::<u32>().map_err(|_| invalid())?),
+                "terms" => terms = Some(value.parse::<usize>().map_err(|_| invalid())?),
+                "checksum" => {
+                    checksum = Some(u32::from_str_radix(value, 16).map_err(|_| invalid())?)
+                }
+                name => {
+                    let index = Self::NAMES
+                        .iter()
+                        .position(|&n| n == name)
+                        .ok_or_else(invalid)?;
+                    weights[index] = value.parse().map_err(|_| invalid())?;
+                    given += 1;
+                }
+            }
+        }
+        let config = Self::from_weights(weights);
+        if format.is_none() && version.is_none() && terms.is_none() && checksum.is_none() {
+            return Ok(config);
+        }
+
+        let format = format.ok_or(ConfigError::MissingHeader("format"))?;
+        if format != Self::FORMAT {
+            return Err(ConfigError::InvalidFormat(format));
+        }
+        let version = version.ok_or(ConfigError::MissingHeader("version"))?;
+        if version != Self::VERSION {
+            return Err(ConfigError::UnsupportedVersion(version));
+        }
+        let expected = Self::NAMES.len();
+        for actual in [terms.ok_or(ConfigError::MissingHeader("terms"))?, given] {
+            if actual != expected {
+                return Err(ConfigError::TermCount { expected, actual });
+            }
+        }
+        let checksum = checksum.ok_or(ConfigError::MissingHeader("checksum"))?;
+        if checksum != config.checksum() {
+            return Err(ConfigError::ChecksumMismatch {
+                expected: checksum,
+                actual: config.checksum(),
+            });
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct NaiveEvaluator {
+    config: EvaluationConfig,
+}
 
 impl NaiveEvaluator {
     const MIN: i16 = -30000;
     const MAX: i16 = 30000;
     const ZERO: i16 = 0;
     const PIECE_VALUES: [i16; 6] = [10, 30, 30, 50, 90, 0];
+    // The values of the pieces but the king, which the position's
+    // incremental material and piece-square total already counts
+    const BASE_PIECE_VALUES: [i16; 5] = [10, 30, 30, 50, 90];
     #[rustfmt::skip]
     const _SQUARE_VALUES: [i16; 64] = [
         0, 0, 0, 0, 0, 0, 0, 0,
@@ -21,8 +294,10 @@ impl NaiveEvaluator {
         0, 0, 0, 0, 0, 0, 0, 0,
     ];
 
+    // The tables are written from white's point of view with the first rank
+    // on top, black's pieces look them up on the mirrored square
     #[rustfmt::skip]
-    const WHITE_PAWN_VALUES: [i16; 64] = [
+    const PAWN_VALUES: [i16; 64] = [
         0, 0, 0, 0, 0, 0, 0, 0,
         0, 0, 0, 0, 0, 0, 0, 0,
         0, 0, 1, 2, 2, 1, 0, 0,
@@ -33,18 +308,6 @@ impl NaiveEvaluator {
         0, 0, 0, 0, 0, 0, 0, 0,
     ];
 
-    #[rustfmt::skip]
-    const BLACK_PAWN_VALUES: [i16; 64] = [
-        0, 0, 0, 0, 0, 0, 0, 0,
-        4, 4, 4, 4, 4, 4, 4, 4,
-        3, 3, 3, 3, 3, 3, 3, 3,
-        2, 2, 3, 3, 3, 3, 2, 2,
-        1, 1, 2, 3, 3, 2, 1, 1,
-        0, 0, 1, 2, 2, 1, 0, 0,
-        0, 0, 0, 0, 0, 0, 0, 0,
-        0, 0, 0, 0, 0, 0, 0, 0,
-    ];
-
     #[rustfmt::skip]
     const KNIGHT_VALUES: [i16; 64] = [
         0, 1, 2, 2, 2, 2, 1, 0,
@@ -70,23 +333,207 @@ impl NaiveEvaluator {
     ];
 
     #[inline]
+    /// The naive evaluator with other weights for its extra terms
+    pub const fn with_config(config: EvaluationConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> EvaluationConfig {
+        self.config
+    }
+
+    // The piece values of the config's phase minus the ones the position's
+    // material total counts, for the side to move minus the other side
+    fn piece_value_adjustment(&self, position: &Position) -> i16 {
+        let values = &self.config.piece_values;
+        if values
+            .iter()
+            .all(|values| *values == Self::BASE_PIECE_VALUES)
+        {
+            return 0;
+        }
+        let phase = match position.game_phase() {
+            GamePhase::Opening => 0,
+            GamePhase::Middlegame => 1,
+            GamePhase::Endgame => 2,
+        };
+        let side = position.side_to_move();
+        let mine = position.color_combined(side);
+        let theirs = position.color_combined(!side);
+        ALL_PIECES[..5]
+            .iter()
+            .zip(values[phase].iter().zip(Self::BASE_PIECE_VALUES.iter()))
+            .map(|(&piece, (value, base))| {
+                let pieces = position.pieces(piece);
+                let count = (pieces & mine).popcnt() as i16 - (pieces & theirs).popcnt() as i16;
+                (value - base) * count
+            })
+            .sum()
+    }
+
     fn piece_value(piece: Piece) -> i16 {
         Self::PIECE_VALUES[piece.to_index()]
     }
 
     #[inline]
     fn position_value(piece: Piece, color: Color, square: Square) -> i16 {
+        let index = square.relative_to(color).to_index();
         match piece {
-            Piece::Pawn => match color {
-                Color::Black => Self::BLACK_PAWN_VALUES[square.to_index()],
-                Color::White => Self::WHITE_PAWN_VALUES[square.to_index()],
-            },
-            Piece::Knight => Self::KNIGHT_VALUES[square.to_index()],
-            Piece::Bishop => Self::BISHOP_VALUES[square.to_index()],
-            Piece::Queen => Self::BISHOP_VALUES[square.to_index()],
+            Piece::Pawn => Self::PAWN_VALUES[index],
+            Piece::Knight => Self::KNIGHT_VALUES[index],
+            Piece::Bishop => Self::BISHOP_VALUES[index],
+            Piece::Queen => Self::BISHOP_VALUES[index],
             _ => 0,
         }
     }
+
+    /// The material and square value of a piece from white's point of view
+    pub(crate) fn piece_square_value(piece: Piece, color: Color, square: Square) -> i16 {
+        let value = Self::piece_value(piece) + Self::position_value(piece, color, square);
+        match color {
+            Color::White => value,
+            Color::Black => -value,
+        }
+    }
+
+    /// How often each weighted term applies for the side to move minus the
+    /// other side, in the order of [`EvaluationConfig::weights`]. The
+    /// evaluation is the material and piece-square value plus these counts
+    /// times the weights.
+    pub fn terms(position: &Position) -> [i16; 4] {
+        let my_color = position.side_to_move();
+        let mine = Self::threat_counts(position, my_color);
+        let theirs = Self::threat_counts(position, !my_color);
+        [
+            Self::pawn_race(position),
+            mine[0] - theirs[0],
+            mine[1] - theirs[1],
+            mine[2] - theirs[2],
+        ]
+    }
+
+    /// Who wins a pawn race, 1 for the side to move, -1 for the other side.
+    ///
+    /// With only kings and pawns left a passed pawn whose promotion square the
+    /// other king can't reach in time (the rule of the square) is as good as a
+    /// queen. When both sides have one the pawn that queens first wins the race.
+    fn pawn_race(position: &Position) -> i16 {
+        let kings_and_pawns = position.pieces(Piece::King) | position.pieces(Piece::Pawn);
+        let all_pieces =
+            position.color_combined(Color::White) | position.color_combined(Color::Black);
+        if all_pieces != kings_and_pawns {
+            return 0;
+        }
+        let my_color = position.side_to_move();
+        // plies until the first unstoppable pawn of each side queens
+        let mine = Self::unstoppable_passer(position, my_color).map(|moves| moves * 2 - 1);
+        let theirs = Self::unstoppable_passer(position, !my_color).map(|moves| moves * 2);
+        match (mine, theirs) {
+            (Some(mine), Some(theirs)) if mine < theirs => 1,
+            (Some(_), Some(_)) => -1,
+            (Some(_), None) => 1,
+            (None, Some(_)) => -1,
+            (None, None) => 0,
+        }
+    }
+
+    /// The value of the threats the color makes against the other side's pieces:
+    /// pieces it attacks that aren't defended, pieces other than pawns its pawns
+    /// attack and pawn pushes to a safe square that attack two pieces.
+    pub fn threats(&self, position: &Position, color: Color) -> i16 {
+        let [hanging, pawn_threats, pawn_forks] = Self::threat_counts(position, color);
+        hanging * self.config.hanging_piece
+            + pawn_threats * self.config.pawn_threat
+            + pawn_forks * self.config.pawn_fork
+    }
+
+    // The hanging pieces, pawn threats and pawn forks of the color
+    fn threat_counts(position: &Position, color: Color) -> [i16; 3] {
+        let pawns = position.pieces(Piece::Pawn);
+        let targets = position.color_combined(!color) & !pawns;
+        let mut counts = [0; 3];
+        for square in targets {
+            let attackers = position.attackers_of(square, color);
+            if (attackers & pawns).popcnt() > 0 {
+                counts[1] += 1;
+            } else if attackers.popcnt() > 0
+                && position.attackers_of(square, !color).popcnt() == 0
+                && position.piece_on(square) != Some(Piece::King)
+            {
+                counts[0] += 1;
+            }
+        }
+        for pawn in pawns & position.color_combined(color) {
+            let push = match color {
+                Color::White => pawn.up(),
+                Color::Black => pawn.down(),
+            };
+            let push = match push {
+                Some(push) if position.piece_on(push).is_none() => push,
+                _ => continue,
+            };
+            let defenders = position.attackers_of(push, !color);
+            let safe = (defenders & pawns).popcnt() == 0
+                && (defenders.popcnt() == 0 || position.attackers_of(push, color).popcnt() > 0);
+            let forked = pawn_attacks(push, color) & targets;
+            if safe && forked.popcnt() >= 2 {
+                counts[2] += 1;
+            }
+        }
+        counts
+    }
+
+    // The fewest moves one of the color's unstoppable passed pawns needs to queen
+    fn unstoppable_passer(position: &Position, color: Color) -> Option<u8> {
+        let pawns = position.pieces(Piece::Pawn);
+        let their_king =
+            (position.pieces(Piece::King) & position.color_combined(!color)).to_square();
+        // the defending king gets one more move when it's their turn
+        let tempo = u8::from(position.side_to_move() != color);
+        (pawns & position.color_combined(color))
+            .filter(|&pawn| Self::is_unblocked_passer(position, color, pawn))
+            .filter_map(|pawn| {
+                // the first move can go two squares
+                let moves = (7 - pawn.relative_rank(color).to_index() as u8).min(5);
+                let promotion =
+                    Square::make_square(Rank::Eighth, pawn.get_file()).relative_to(color);
+                if king_distance(their_king, promotion) > moves + tempo {
+                    Some(moves)
+                } else {
+                    None
+                }
+            })
+            .min()
+    }
+
+    // No pawn stands in front of it and no enemy pawn can capture it on the way
+    fn is_unblocked_passer(position: &Position, color: Color, pawn: Square) -> bool {
+        let pawns = position.pieces(Piece::Pawn);
+        let their_pawns = pawns & position.color_combined(!color);
+        (pawns & masks::forward_span(color, pawn)).popcnt() == 0
+            && (their_pawns & masks::passed_pawn_span(color, pawn)).popcnt() == 0
+    }
+}
+
+// The squares a pawn of the color on the square attacks
+fn pawn_attacks(square: Square, color: Color) -> BitBoard {
+    let forward = match color {
+        Color::White => square.up(),
+        Color::Black => square.down(),
+    };
+    forward
+        .into_iter()
+        .flat_map(|square| square.left().into_iter().chain(square.right()))
+        .fold(BitBoard::new(0), |attacks, square| {
+            attacks | BitBoard::from_square(square)
+        })
+}
+
+// The number of king moves between the squares
+fn king_distance(a: Square, b: Square) -> u8 {
+    let ranks = (a.get_rank().to_index() as i8 - b.get_rank().to_index() as i8).abs();
+    let files = (a.get_file().to_index() as i8 - b.get_file().to_index() as i8).abs();
+    ranks.max(files) as u8
 }
 
 impl Evaluator for NaiveEvaluator {
@@ -107,69 +554,45 @@ impl Evaluator for NaiveEvaluator {
             PositionStatus::Stalemate => Self::ZERO,
             PositionStatus::Checkmate => Self::MIN,
             PositionStatus::Ongoing => {
-                let mut evaluation = 0;
-                let my_color = position.side_to_move();
-                let my_pieces = position.color_combined(my_color);
-                let their_pieces = position.color_combined(!my_color);
-                let pawns = position.pieces(Piece::Pawn);
-                let knights = position.pieces(Piece::Knight);
-                let bishops = position.pieces(Piece::Bishop);
-                let queens = position.pieces(Piece::Queen);
-
-                // Piece Values
-                for &piece in crate::ALL_PIECES.iter() {
-                    let pieces = position.pieces(piece);
-                    let value = Self::piece_value(piece);
-                    evaluation += value
-                        * ((my_pieces & pieces).popcnt() as i16
-                            - (their_pieces & pieces).popcnt() as i16);
-                }
-
-                // Position Values
-                // Pawns:
-                for square in *pawns & *my_pieces {
-                    evaluation += Self::position_value(Piece::Pawn, my_color, square);
-                }
-                for square in *pawns & *their_pieces {
-                    evaluation -= Self::position_value(Piece::Pawn, !my_color, square);
-                }
-                // Knights:
-                for square in *knights & *my_pieces {
-                    evaluation += Self::position_value(Piece::Knight, my_color, square);
-                }
-                for square in *knights & *their_pieces {
-                    evaluation -= Self::position_value(Piece::Knight, !my_color, square);
-                }
-                // Bishops:
-                for square in *bishops & *my_pieces {
-                    evaluation += Self::position_value(Piece::Bishop, my_color, square);
-                }
-                for square in *bishops & *their_pieces {
-                    evaluation -= Self::position_value(Piece::Bishop, !my_color, square);
-                }
-                // Queens:
-                for square in *queens & *my_pieces {
-                    evaluation += Self::position_value(Piece::Queen, my_color, square);
-                }
-                for square in *queens & *their_pieces {
-                    evaluation -= Self::position_value(Piece::Queen, !my_color, square);
-                }
+                // Material and position values, kept up to date by the position
+                let evaluation = match position.side_to_move() {
+                    Color::White => position.psqt(),
+                    Color::Black => -position.psqt(),
+                };
                 evaluation
+                    + self.piece_value_adjustment(position)
+                    + Self::terms(position)
+                        .iter()
+                        .zip(self.config.weights().iter())
+                        .map(|(count, weight)| count * weight)
+                        .sum::<i16>()
             }
         }
     }
+
+    /// Apply the evaluation overrides of the options on top of the weights,
+    /// see [`EngineOptions::set_path`]
+    fn configure(&mut self, options: &EngineOptions) {
+        for (path, value) in &options.eval_overrides {
+            // the paths were checked when the overrides were set
+            let _ = self.config.set(path, *value);
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::NaiveEvaluator;
+    use super::{ConfigError, EvaluationConfig, NaiveEvaluator};
     use crate::eval::Evaluator;
-    use crate::{ChessMove, Position, Square};
+    use crate::options::EngineOptions;
+    use crate::{ChessMove, Color, Position, Square};
+    use std::str::FromStr;
+    use test_case::test_case;
 
     #[test]
     fn initial_board_eval() {
         let position = Position::default();
-        let evaluator = NaiveEvaluator;
+        let evaluator = NaiveEvaluator::default();
         let evaluation = evaluator.evaluate(&position);
         assert_eq!(evaluation, 0);
     }
@@ -179,8 +602,125 @@ mod tests {
         let position = Position::default();
         let chess_move = ChessMove::new(Square::E2, Square::E4, None);
         let position = position.make_move_new(chess_move);
-        let evaluator = NaiveEvaluator;
+        let evaluator = NaiveEvaluator::default();
         let evaluation = evaluator.evaluate(&position);
         assert_eq!(evaluation, -3);
     }
+
+    #[test_case("8/8/8/P3k3/8/8/8/6K1 w - - 0 1", true; "outside the square")]
+    #[test_case("8/8/8/P3k3/8/8/8/6K1 b - - 0 1", false; "king moves into the square")]
+    #[test_case("8/8/8/P2k4/8/8/8/6K1 w - - 0 1", false; "inside the square")]
+    #[test_case("8/8/8/P3k3/8/8/8/5NK1 w - - 0 1", false; "other pieces left")]
+    #[test_case("8/1p6/8/P3k3/8/8/8/6K1 w - - 0 1", false; "not a passed pawn")]
+    #[test_case("8/8/8/P3k3/8/8/1K5p/8 w - - 0 1", false; "black queens first")]
+    #[test_case("8/7p/8/P3k3/8/8/1K6/8 w - - 0 1", true; "white queens first")]
+    fn unstoppable_passers(fen: &str, white_wins_race: bool) {
+        let position = Position::from_str(fen).unwrap();
+        let race = NaiveEvaluator::pawn_race(&position);
+        let white_race = match position.side_to_move() {
+            Color::White => race,
+            Color::Black => -race,
+        };
+        assert_eq!(white_race == 1, white_wins_race);
+    }
+
+    #[test_case("4k3/8/8/4n3/8/8/8/4RK2 w - - 0 1", EvaluationConfig::DEFAULT.hanging_piece; "hanging piece")]
+    #[test_case("4k3/8/3p4/4n3/8/8/8/4RK2 w - - 0 1", 0; "defended piece")]
+    #[test_case("4k3/8/8/8/2n5/1P6/8/4K3 w - - 0 1", EvaluationConfig::DEFAULT.pawn_threat; "pawn threat")]
+    #[test_case("4k3/8/8/2n1r3/8/3P4/8/4K3 w - - 0 1", EvaluationConfig::DEFAULT.pawn_fork; "pawn fork")]
+    #[test_case("4k3/8/8/2n1b3/8/3P4/8/4K3 w - - 0 1", 0; "unsafe pawn fork")]
+    fn threats(fen: &str, expected: i16) {
+        let position = Position::from_str(fen).unwrap();
+        let evaluator = NaiveEvaluator::default();
+        assert_eq!(evaluator.threats(&position, Color::White), expected);
+        assert_eq!(evaluator.threats(&position, Color::Black), 0);
+    }
+
+    #[test]
+    fn config_file() {
+        let config = EvaluationConfig {
+            pawn_fork: 9,
+            ..EvaluationConfig::DEFAULT
+        };
+        assert_eq!(config.to_string().parse(), Ok(config));
+        assert_eq!("# tuned\npawn_fork = 9\n".parse(), Ok(config));
+        assert!("pawn_fork = x".parse::<EvaluationConfig>().is_err());
+        assert!("rook_on_seventh = 2".parse::<EvaluationConfig>().is_err());
+    }
+
+    #[test]
+    fn paths() {
+        let mut config = EvaluationConfig::DEFAULT;
+        config.set("pawn_fork", 7).unwrap();
+        config.set("endgame.rook", 55).unwrap();
+        assert_eq!(config.pawn_fork, 7);
+        assert_eq!(config.piece_values[2], [10, 30, 30, 55, 90]);
+        assert_eq!(config.piece_values[0], [10, 30, 30, 50, 90]);
+        assert_eq!(
+            config.set("endgame.king", 0),
+            Err(ConfigError::UnknownPath("endgame.king".to_string()))
+        );
+        assert!(config.set("late.rook", 0).is_err());
+        assert!(config.set("rook_on_seventh", 2).is_err());
+    }
+
+    #[test]
+    fn overrides_change_piece_values() {
+        // white is a knight up with everything else even
+        let position = Position::from_str("4k3/pppp4/8/8/8/8/PPPP4/4KN2 w - - 0 1").unwrap();
+        let mut evaluator = NaiveEvaluator::default();
+        let before = evaluator.evaluate(&position);
+        let mut options = EngineOptions::default();
+        options.set_path("eval.endgame.knight=40").unwrap();
+        evaluator.configure(&options);
+        assert_eq!(evaluator.evaluate(&position), before + 10);
+        let black = position.null_move().unwrap();
+        let default = NaiveEvaluator::default().evaluate(&black);
+        assert_eq!(evaluator.evaluate(&black), default - 10);
+    }
+
+    #[test_case("format = rad1-eval", ConfigError::InvalidFormat("rad1-eval".to_string()); "format")]
+    #[test_case("version = 2", ConfigError::UnsupportedVersion(2); "version")]
+    #[test_case("terms = 3", ConfigError::TermCount { expected: 4, actual: 3 }; "terms")]
+    #[test_case("pawn_fork = 6", ConfigError::ChecksumMismatch {
+        expected: EvaluationConfig::DEFAULT.checksum(),
+        actual: EvaluationConfig { pawn_fork: 6, ..EvaluationConfig::DEFAULT }.checksum(),
+    }; "checksum")]
+    fn versioned_header(changed: &str, expected: ConfigError) {
+        let config = EvaluationConfig::DEFAULT.to_string();
+        let name = changed.split('=').next().unwrap();
+        let changed: String = config
+            .lines()
+            .map(|line| {
+                if line.starts_with(name) {
+                    changed
+                } else {
+                    line
+                }
+            })
+            .map(|line| format!("{}\n", line))
+            .collect();
+        assert_eq!(changed.parse::<EvaluationConfig>(), Err(expected));
+    }
+
+    #[test]
+    fn missing_terms_in_versioned_file() {
+        let config: String = EvaluationConfig::DEFAULT
+            .to_string()
+            .lines()
+            .filter(|line| !line.starts_with("pawn_fork"))
+            .map(|line| format!("{}\n", line))
+            .collect();
+        assert_eq!(
+            config.parse::<EvaluationConfig>(),
+            Err(ConfigError::TermCount {
+                expected: 4,
+                actual: 3
+            })
+        );
+        assert_eq!(
+            "version = 1\npawn_fork = 9\n".parse::<EvaluationConfig>(),
+            Err(ConfigError::MissingHeader("format"))
+        );
+    }
 }
@@ -0,0 +1,630 @@
+use super::Evaluator;
+use crate::{Color, Piece, Position, PositionStatus, Square, ALL_PIECES};
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+// Clipped activations of the feature transformer lie in 0..=QA
+const QA: i16 = 255;
+// The output weights are the network's weights times QB
+const QB: i32 = 64;
+// Centipawns in one unit of the network's output
+const SCALE: i32 = 400;
+// Centipawns in one evaluation unit, a pawn is worth 10 units
+const CENTIPAWNS_PER_UNIT: i32 = 10;
+// One input per piece kind and color on every square
+const INPUTS: usize = 768;
+// The most neurons the feature transformer can have, keeps the
+// accumulators on the stack
+const MAX_HIDDEN: usize = 1024;
+// Neurons come in blocks of one 256 bit register of 16 bit values
+const LANES: usize = 16;
+const MAGIC: &[u8; 8] = b"RAD1NNUE";
+
+/// How the output weights are stored in a network file,
+/// the feature transformer always uses 16 bit weights
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precision {
+    Int8 = 1,
+    Int16 = 2,
+}
+
+/// The reason a network file couldn't be loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetworkError {
+    InvalidMagic,
+    InvalidPrecision(u8),
+    InvalidHiddenSize(usize),
+    InvalidLength { expected: usize, actual: usize },
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkError::InvalidMagic => write!(f, "not a rad1 network file"),
+            NetworkError::InvalidPrecision(precision) => {
+                write!(f, "unknown weight precision {}", precision)
+            }
+            NetworkError::InvalidHiddenSize(hidden) => write!(
+                f,
+                "{} hidden neurons, expected a multiple of {} up to {}",
+                hidden, LANES, MAX_HIDDEN
+            ),
+            NetworkError::InvalidLength { expected, actual } => {
+                write!(f, "expected {} bytes but got {}", expected, actual)
+            }
+        }
+    }
+}
+
+impl Error for NetworkError {}
+
+/// A quantized network of the form `(768 -> N) x 2 -> 1`.
+///
+/// The feature transformer turns the pieces into one accumulator for each
+/// side's perspective, the side to move's accumulator comes first in the
+/// output layer. Activations are clipped to `0..=255` and the output weights
+/// are scaled by 64, so the evaluation is `output * 400 / (255 * 64)` centipawns.
+///
+/// Files start with `RAD1NNUE`, the precision of the output weights (1 for
+/// 8 bit, 2 for 16 bit) and the number of hidden neurons as a 16 bit integer,
+/// followed by the feature weights, feature biases, output weights and the
+/// 32 bit output bias, all little endian.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Network {
+    hidden: usize,
+    feature_weights: Vec<i16>,
+    feature_biases: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i32,
+}
+
+impl Network {
+    pub fn read(bytes: &[u8]) -> Result<Self, NetworkError> {
+        if bytes.len() < MAGIC.len() + 3 || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(NetworkError::InvalidMagic);
+        }
+        let precision = match bytes[8] {
+            1 => Precision::Int8,
+            2 => Precision::Int16,
+            precision => return Err(NetworkError::InvalidPrecision(precision)),
+        };
+        let hidden = usize::from(u16::from_le_bytes([bytes[9], bytes[10]]));
+        if hidden == 0 || hidden % LANES != 0 || hidden > MAX_HIDDEN {
+            return Err(NetworkError::InvalidHiddenSize(hidden));
+        }
+        let expected = 11 + 2 * (INPUTS + 1) * hidden + 2 * hidden * precision as usize + 4;
+        if bytes.len() != expected {
+            return Err(NetworkError::InvalidLength {
+                expected,
+                actual: bytes.len(),
+            });
+        }
+
+        let mut reader = Reader { bytes, offset: 11 };
+        let feature_weights = reader.i16s(INPUTS * hidden);
+        let feature_biases = reader.i16s(hidden);
+        let output_weights = match precision {
+            Precision::Int8 => reader.i8s(2 * hidden),
+            Precision::Int16 => reader.i16s(2 * hidden),
+        };
+        let output_bias = reader.i32();
+        Ok(Self {
+            hidden,
+            feature_weights,
+            feature_biases,
+            output_weights,
+            output_bias,
+        })
+    }
+
+    /// The network in the file format [`Network::read`] loads.
+    /// Output weights outside of the `i8` range are saturated by [`Precision::Int8`].
+    pub fn to_bytes(&self, precision: Precision) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(precision as u8);
+        bytes.extend_from_slice(&(self.hidden as u16).to_le_bytes());
+        for weight in self.feature_weights.iter().chain(&self.feature_biases) {
+            bytes.extend_from_slice(&weight.to_le_bytes());
+        }
+        for &weight in &self.output_weights {
+            match precision {
+                Precision::Int8 => bytes.push(weight.clamp(-128, 127) as i8 as u8),
+                Precision::Int16 => bytes.extend_from_slice(&weight.to_le_bytes()),
+            }
+        }
+        bytes.extend_from_slice(&self.output_bias.to_le_bytes());
+        bytes
+    }
+
+    /// The number of neurons in the feature transformer
+    pub fn hidden(&self) -> usize {
+        self.hidden
+    }
+
+    /// The evaluation of the position in centipawns from the side to move's point of view
+    pub fn evaluate(&self, position: &Position) -> i32 {
+        let mut white = [0; MAX_HIDDEN];
+        let mut black = [0; MAX_HIDDEN];
+        let white = &mut white[..self.hidden];
+        let black = &mut black[..self.hidden];
+        self.refresh(position, white, black);
+        match position.side_to_move() {
+            Color::White => self.output(white, black),
+            Color::Black => self.output(black, white),
+        }
+    }
+
+    // Fill the accumulators of both perspectives from every piece on the board
+    fn refresh(&self, position: &Position, white: &mut [i16], black: &mut [i16]) {
+        white.copy_from_slice(&self.feature_biases);
+        black.copy_from_slice(&self.feature_biases);
+        for &piece in ALL_PIECES.iter() {
+            for &color in [Color::White, Color::Black].iter() {
+                let pieces = position.pieces(piece) & position.color_combined(color);
+                for square in pieces {
+                    let (white_input, black_input) = inputs(piece, color, square);
+                    simd::add_weights(white, self.feature_weights(white_input));
+                    simd::add_weights(black, self.feature_weights(black_input));
+                }
+            }
+        }
+    }
+
+    // The output layer for the side to move's and the other side's accumulators
+    fn output(&self, us: &[i16], them: &[i16]) -> i32 {
+        let (our_weights, their_weights) = self.output_weights.split_at(self.hidden);
+        let output = simd::clipped_dot(us, our_weights, QA)
+            .wrapping_add(simd::clipped_dot(them, their_weights, QA))
+            .wrapping_add(self.output_bias);
+        (i64::from(output) * i64::from(SCALE) / i64::from(i32::from(QA) * QB)) as i32
+    }
+
+    fn feature_weights(&self, input: usize) -> &[i16] {
+        &self.feature_weights[input * self.hidden..(input + 1) * self.hidden]
+    }
+}
+
+// The inputs of a piece for white's and black's perspective,
+// black sees the board flipped with its own pieces first
+fn inputs(piece: Piece, color: Color, square: Square) -> (usize, usize) {
+    let index = piece.to_index() * 64 + square.to_index();
+    match color {
+        Color::White => (index, 384 + (index ^ 56)),
+        Color::Black => (384 + index, index ^ 56),
+    }
+}
+
+/// The feature transformer's output for both perspectives of a position.
+///
+/// Positions that carry one update it with the pieces every move adds and
+/// removes, so the first layer doesn't have to be computed from every
+/// piece again for each evaluation.
+#[derive(Debug, Clone)]
+pub struct Accumulator {
+    network: Arc<Network>,
+    white: Vec<i16>,
+    black: Vec<i16>,
+}
+
+impl Accumulator {
+    pub fn new(network: Arc<Network>, position: &Position) -> Self {
+        let mut white = vec![0; network.hidden];
+        let mut black = vec![0; network.hidden];
+        network.refresh(position, &mut white, &mut black);
+        Self {
+            network,
+            white,
+            black,
+        }
+    }
+
+    pub fn network(&self) -> &Arc<Network> {
+        &self.network
+    }
+
+    /// Account for a piece put on the square
+    pub fn add(&mut self, piece: Piece, color: Color, square: Square) {
+        let (white_input, black_input) = inputs(piece, color, square);
+        simd::add_weights(&mut self.white, self.network.feature_weights(white_input));
+        simd::add_weights(&mut self.black, self.network.feature_weights(black_input));
+    }
+
+    /// Account for a piece taken off the square
+    pub fn remove(&mut self, piece: Piece, color: Color, square: Square) {
+        let (white_input, black_input) = inputs(piece, color, square);
+        simd::sub_weights(&mut self.white, self.network.feature_weights(white_input));
+        simd::sub_weights(&mut self.black, self.network.feature_weights(black_input));
+    }
+
+    /// The evaluation in centipawns from the given side's point of view
+    pub fn evaluate(&self, side_to_move: Color) -> i32 {
+        match side_to_move {
+            Color::White => self.network.output(&self.white, &self.black),
+            Color::Black => self.network.output(&self.black, &self.white),
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> &'a [u8] {
+        let bytes = &self.bytes[self.offset..self.offset + count];
+        self.offset += count;
+        bytes
+    }
+
+    fn i8s(&mut self, count: usize) -> Vec<i16> {
+        self.take(count)
+            .iter()
+            .map(|&byte| i16::from(byte as i8))
+            .collect()
+    }
+
+    fn i16s(&mut self, count: usize) -> Vec<i16> {
+        self.take(2 * count)
+            .chunks(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect()
+    }
+
+    fn i32(&mut self) -> i32 {
+        let bytes = self.take(4);
+        i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+}
+
+/// Evaluates positions with a quantized [`Network`].
+///
+/// Searches attach an [`Accumulator`] to their root position that is kept up
+/// to date with every move, other positions are evaluated from scratch.
+/// The feature transformer and output layer use AVX2 when the `simd`
+/// feature is enabled and the CPU supports it.
+#[derive(Debug, Clone)]
+pub struct NnueEvaluator {
+    network: Arc<Network>,
+}
+
+impl NnueEvaluator {
+    const MIN: i16 = -30000;
+    const MAX: i16 = 30000;
+    const ZERO: i16 = 0;
+
+    pub fn new(network: Network) -> Self {
+        Self {
+            network: Arc::new(network),
+        }
+    }
+
+    pub fn network(&self) -> &Network {
+        &self.network
+    }
+}
+
+impl Evaluator for NnueEvaluator {
+    type Result = i16;
+
+    #[inline]
+    fn min_value(&self) -> Self::Result {
+        Self::MIN
+    }
+
+    #[inline]
+    fn max_value(&self) -> Self::Result {
+        Self::MAX
+    }
+
+    fn evaluate(&self, position: &Position) -> Self::Result {
+        match position.status() {
+            PositionStatus::Stalemate => Self::ZERO,
+            PositionStatus::Checkmate => Self::MIN,
+            PositionStatus::Ongoing => {
+                let centipawns = match position.accumulator() {
+                    Some(accumulator) if Arc::ptr_eq(accumulator.network(), &self.network) => {
+                        accumulator.evaluate(position.side_to_move())
+                    }
+                    _ => self.network.evaluate(position),
+                };
+                (centipawns / CENTIPAWNS_PER_UNIT)
+                    .clamp(i32::from(Self::MIN) + 1, i32::from(Self::MAX) - 1)
+                    as i16
+            }
+        }
+    }
+
+    fn prepare(&self, position: &Position) -> Position {
+        position
+            .clone()
+            .with_accumulator(Accumulator::new(self.network.clone(), position))
+    }
+}
+
+mod simd {
+    /// Add a column of the feature transformer to an accumulator
+    pub fn add_weights(accumulator: &mut [i16], weights: &[i16]) {
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        {
+            if is_x86_feature_detected!("avx2") {
+                // SAFETY: AVX2 is available and the network keeps the
+                // lengths equal and a multiple of 16
+                return unsafe { avx2::add_weights(accumulator, weights) };
+            }
+        }
+        scalar::add_weights(accumulator, weights)
+    }
+
+    /// Subtract a column of the feature transformer from an accumulator
+    pub fn sub_weights(accumulator: &mut [i16], weights: &[i16]) {
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        {
+            if is_x86_feature_detected!("avx2") {
+                // SAFETY: as above
+                return unsafe { avx2::sub_weights(accumulator, weights) };
+            }
+        }
+        scalar::sub_weights(accumulator, weights)
+    }
+
+    /// The dot product of the clipped accumulator and the output weights
+    pub fn clipped_dot(accumulator: &[i16], weights: &[i16], max: i16) -> i32 {
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        {
+            if is_x86_feature_detected!("avx2") {
+                // SAFETY: as above
+                return unsafe { avx2::clipped_dot(accumulator, weights, max) };
+            }
+        }
+        scalar::clipped_dot(accumulator, weights, max)
+    }
+
+    pub mod scalar {
+        pub fn add_weights(accumulator: &mut [i16], weights: &[i16]) {
+            for (value, &weight) in accumulator.iter_mut().zip(weights) {
+                *value = value.wrapping_add(weight);
+            }
+        }
+
+        pub fn sub_weights(accumulator: &mut [i16], weights: &[i16]) {
+            for (value, &weight) in accumulator.iter_mut().zip(weights) {
+                *value = value.wrapping_sub(weight);
+            }
+        }
+
+        pub fn clipped_dot(accumulator: &[i16], weights: &[i16], max: i16) -> i32 {
+            accumulator
+                .iter()
+                .zip(weights)
+                .fold(0i32, |sum, (&value, &weight)| {
+                    sum.wrapping_add(i32::from(value.clamp(0, max)) * i32::from(weight))
+                })
+        }
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    pub mod avx2 {
+        use std::arch::x86_64::*;
+
+        #[target_feature(enable = "avx2")]
+        pub unsafe fn add_weights(accumulator: &mut [i16], weights: &[i16]) {
+            for (values, weights) in accumulator
+                .chunks_exact_mut(16)
+                .zip(weights.chunks_exact(16))
+            {
+                let sum = _mm256_add_epi16(
+                    _mm256_loadu_si256(values.as_ptr() as *const __m256i),
+                    _mm256_loadu_si256(weights.as_ptr() as *const __m256i),
+                );
+                _mm256_storeu_si256(values.as_mut_ptr() as *mut __m256i, sum);
+            }
+        }
+
+        #[target_feature(enable = "avx2")]
+        pub unsafe fn sub_weights(accumulator: &mut [i16], weights: &[i16]) {
+            for (values, weights) in accumulator
+                .chunks_exact_mut(16)
+                .zip(weights.chunks_exact(16))
+            {
+                let difference = _mm256_sub_epi16(
+                    _mm256_loadu_si256(values.as_ptr() as *const __m256i),
+                    _mm256_loadu_si256(weights.as_ptr() as *const __m256i),
+                );
+                _mm256_storeu_si256(values.as_mut_ptr() as *mut __m256i, difference);
+            }
+        }
+
+        #[target_feature(enable = "avx2")]
+        pub unsafe fn clipped_dot(accumulator: &[i16], weights: &[i16], max: i16) -> i32 {
+            let zero = _mm256_setzero_si256();
+            let max = _mm256_set1_epi16(max);
+            let mut sum = _mm256_setzero_si256();
+            for (values, weights) in accumulator.chunks_exact(16).zip(weights.chunks_exact(16)) {
+                let values = _mm256_loadu_si256(values.as_ptr() as *const __m256i);
+                let clipped = _mm256_min_epi16(_mm256_max_epi16(values, zero), max);
+                let weights = _mm256_loadu_si256(weights.as_ptr() as *const __m256i);
+                // pairs of products fit in 32 bits since activations are small
+                sum = _mm256_add_epi32(sum, _mm256_madd_epi16(clipped, weights));
+            }
+            let mut lanes = [0i32; 8];
+            _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, sum);
+            lanes
+                .iter()
+                .fold(0i32, |total, &lane| total.wrapping_add(lane))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{simd, Accumulator, Network, NetworkError, NnueEvaluator, Precision, INPUTS, QA};
+    use crate::eval::Evaluator;
+    use crate::Position;
+    use std::str::FromStr;
+    use test_case::test_case;
+
+    // One neuron per piece kind that counts the pieces from its perspective
+    fn material_network() -> Network {
+        let hidden = 16;
+        let mut feature_weights = vec![0; INPUTS * hidden];
+        for input in 0..INPUTS {
+            let neuron = if input < 384 {
+                input / 64
+            } else {
+                6 + (input - 384) / 64
+            };
+            feature_weights[input * hidden + neuron] = 1;
+        }
+        let values = [1, 3, 3, 5, 9, 0];
+        let mut output_weights = vec![0; 2 * hidden];
+        for (piece, &value) in values.iter().enumerate() {
+            // each pawn is an activation of 1 out of 255, both perspectives add
+            // 50 centipawns for it after scaling by 400 / (255 * 64)
+            let weight = value * 2040;
+            output_weights[piece] = weight;
+            output_weights[6 + piece] = -weight;
+            output_weights[hidden + piece] = -weight;
+            output_weights[hidden + 6 + piece] = weight;
+        }
+        Network {
+            hidden,
+            feature_weights,
+            feature_biases: vec![0; hidden],
+            output_weights,
+            output_bias: 0,
+        }
+    }
+
+    // Weights that differ for every input and neuron
+    fn scrambled_network() -> Network {
+        let hidden = 32;
+        let scramble =
+            |i: usize, modulus: usize| (i * 7919 % modulus) as i16 - (modulus / 2) as i16;
+        Network {
+            hidden,
+            feature_weights: (0..INPUTS * hidden).map(|i| scramble(i, 61)).collect(),
+            feature_biases: (0..hidden).map(|i| scramble(i, 41)).collect(),
+            output_weights: (0..2 * hidden).map(|i| scramble(i, 127)).collect(),
+            output_bias: 100,
+        }
+    }
+
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 0)]
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR b KQkq - 0 1", 90)]
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1", -90)]
+    #[test_case("4k3/8/8/4n3/8/8/8/4RK2 w - - 0 1", 20)]
+    fn evaluates_material(fen: &str, expected: i16) {
+        let evaluator = NnueEvaluator::new(material_network());
+        let position = Position::from_str(fen).unwrap();
+        assert_eq!(evaluator.evaluate(&position), expected);
+    }
+
+    #[test_case("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"; "castling")]
+    #[test_case("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3"; "en passant")]
+    #[test_case("n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1"; "promotions")]
+    fn incremental_accumulator(fen: &str) {
+        let evaluator = NnueEvaluator::new(scrambled_network());
+        let position = evaluator.prepare(&Position::from_str(fen).unwrap());
+        for first in position.legal_moves_iter() {
+            let child = position.make_move_new(first);
+            for second in child.legal_moves_iter() {
+                let grandchild = child.make_move_new(second);
+                let accumulator = grandchild.accumulator().unwrap();
+                let fresh = Accumulator::new(evaluator.network.clone(), &grandchild);
+                assert_eq!(accumulator.white, fresh.white);
+                assert_eq!(accumulator.black, fresh.black);
+                assert_eq!(
+                    accumulator.evaluate(grandchild.side_to_move()),
+                    evaluator.network().evaluate(&grandchild)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn null_move_keeps_accumulator() {
+        let evaluator = NnueEvaluator::new(scrambled_network());
+        let position = evaluator.prepare(&Position::default());
+        let null_move = position.null_move().unwrap();
+        assert_eq!(
+            null_move
+                .accumulator()
+                .unwrap()
+                .evaluate(null_move.side_to_move()),
+            evaluator.network().evaluate(&null_move)
+        );
+    }
+
+    #[test_case(Precision::Int8)]
+    #[test_case(Precision::Int16)]
+    fn file_round_trip(precision: Precision) {
+        let mut network = material_network();
+        network.output_weights.iter_mut().for_each(|w| *w /= 256);
+        network.output_bias = -7;
+        let bytes = network.to_bytes(precision);
+        assert_eq!(Network::read(&bytes), Ok(network));
+    }
+
+    #[test]
+    fn invalid_files() {
+        let bytes = material_network().to_bytes(Precision::Int16);
+        assert_eq!(Network::read(b"NNUE"), Err(NetworkError::InvalidMagic));
+        let mut precision = bytes.clone();
+        precision[8] = 4;
+        assert_eq!(
+            Network::read(&precision),
+            Err(NetworkError::InvalidPrecision(4))
+        );
+        let mut hidden = bytes.clone();
+        hidden[9] = 15;
+        assert_eq!(
+            Network::read(&hidden),
+            Err(NetworkError::InvalidHiddenSize(15))
+        );
+        assert_eq!(
+            Network::read(&bytes[..bytes.len() - 1]),
+            Err(NetworkError::InvalidLength {
+                expected: bytes.len(),
+                actual: bytes.len() - 1
+            })
+        );
+    }
+
+    #[test]
+    fn clipped_dot() {
+        let accumulator: Vec<i16> = (0..32).map(|i| i * 20 - 100).collect();
+        let weights: Vec<i16> = (0..32).map(|i| 3 - i).collect();
+        let expected: i32 = accumulator
+            .iter()
+            .zip(&weights)
+            .map(|(&a, &w)| i32::from(a.clamp(0, QA)) * i32::from(w))
+            .sum();
+        assert_eq!(simd::clipped_dot(&accumulator, &weights, QA), expected);
+        assert_eq!(
+            simd::scalar::clipped_dot(&accumulator, &weights, QA),
+            expected
+        );
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[test]
+    fn avx2_matches_scalar() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+        let weights: Vec<i16> = (0..64).map(|i| (i * 997 % 601) as i16 - 300).collect();
+        let mut scalar: Vec<i16> = (0..64).map(|i| (i * 31 % 513) as i16 - 100).collect();
+        let mut avx2 = scalar.clone();
+        simd::scalar::add_weights(&mut scalar, &weights);
+        unsafe { simd::avx2::add_weights(&mut avx2, &weights) };
+        assert_eq!(scalar, avx2);
+        assert_eq!(simd::scalar::clipped_dot(&scalar, &weights, QA), unsafe {
+            simd::avx2::clipped_dot(&avx2, &weights, QA)
+        });
+        simd::scalar::sub_weights(&mut scalar, &weights[32..]);
+        unsafe { simd::avx2::sub_weights(&mut avx2, &weights[32..]) };
+        assert_eq!(scalar, avx2);
+    }
+}
@@ -0,0 +1,139 @@
+use super::Evaluator;
+use crate::{Color, Piece, Position, Rank, Square, ALL_SQUARES};
+use std::str::FromStr;
+
+// The squares of the starting position from a1 to h8
+const START_SQUARES: &str = "RNBQKBNRPPPPPPPP................................pppppppprnbqkbnr";
+
+/// The material around the piece whose values are measured, since an
+/// evaluator may value a square differently depending on what else is on the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backdrop {
+    /// The starting position without white's other pieces of the kind
+    Opening,
+    /// Only the kings on their starting squares
+    Endgame,
+}
+
+/// The piece-square table an evaluator effectively uses for a white piece,
+/// indexed by square from a1 to h8.
+///
+/// Every entry is the evaluation with the piece on the square minus the one
+/// with the square empty, so it includes the material value of the piece.
+/// The king's entries are the evaluation with the white king on the square
+/// minus the one with it on e1. Squares the piece can't stand on, the
+/// other king's square, those where it would give check and pawns on the
+/// first or last rank, are `None`.
+pub fn piece_square_table(
+    evaluator: &dyn Evaluator<Result = i16>,
+    piece: Piece,
+    backdrop: Backdrop,
+) -> [Option<i16>; 64] {
+    let symbol = piece.to_string(Color::White).chars().next().unwrap();
+    let mut squares: Vec<char> = START_SQUARES.chars().collect();
+    for square in squares.iter_mut() {
+        let other_piece = backdrop == Backdrop::Endgame && !square.eq_ignore_ascii_case(&'k');
+        if other_piece || *square == symbol {
+            *square = '.';
+        }
+    }
+    let evaluate = |squares: &[char]| {
+        Position::from_str(&fen(squares))
+            .ok()
+            .map(|position| evaluator.evaluate(&evaluator.prepare(&position)))
+    };
+    let mut table = [None; 64];
+    if piece == Piece::King {
+        let home = {
+            let mut squares = squares.clone();
+            squares[Square::E1.to_index()] = 'K';
+            evaluate(&squares)
+        };
+        for square in ALL_SQUARES.iter() {
+            if squares[square.to_index()] != '.' {
+                continue;
+            }
+            let mut squares = squares.clone();
+            squares[square.to_index()] = 'K';
+            table[square.to_index()] = evaluate(&squares).zip(home).map(|(on, home)| on - home);
+        }
+        return table;
+    }
+    for square in ALL_SQUARES.iter() {
+        let index = square.to_index();
+        let back_rank = square.get_rank() == Rank::First || square.get_rank() == Rank::Eighth;
+        if squares[index].eq_ignore_ascii_case(&'k') || (piece == Piece::Pawn && back_rank) {
+            continue;
+        }
+        let mut empty = squares.clone();
+        empty[index] = '.';
+        let mut with_piece = empty.clone();
+        with_piece[index] = symbol;
+        table[index] = evaluate(&with_piece)
+            .zip(evaluate(&empty))
+            .map(|(on, off)| on - off);
+    }
+    table
+}
+
+// The FEN of the squares from a1 to h8 with white to move
+fn fen(squares: &[char]) -> String {
+    let ranks: Vec<String> = squares
+        .chunks(8)
+        .rev()
+        .map(|rank| {
+            let mut text = String::new();
+            let mut empty = 0;
+            for &square in rank {
+                if square == '.' {
+                    empty += 1;
+                    continue;
+                }
+                if empty > 0 {
+                    text.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                text.push(square);
+            }
+            if empty > 0 {
+                text.push_str(&empty.to_string());
+            }
+            text
+        })
+        .collect();
+    format!("{} w - - 0 1", ranks.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{piece_square_table, Backdrop};
+    use crate::eval::naive_evaluator;
+    use crate::{Piece, Square};
+    use test_case::test_case;
+
+    #[test_case(Backdrop::Opening)]
+    #[test_case(Backdrop::Endgame)]
+    fn knights_like_the_center(backdrop: Backdrop) {
+        let table = piece_square_table(&naive_evaluator(), Piece::Knight, backdrop);
+        let center = table[Square::D5.to_index()].unwrap();
+        let corner = table[Square::H1.to_index()].unwrap();
+        assert!(center > corner);
+        assert_eq!(table[Square::E1.to_index()], None);
+    }
+
+    #[test]
+    fn pawns_never_stand_on_the_back_ranks() {
+        let table = piece_square_table(&naive_evaluator(), Piece::Pawn, Backdrop::Endgame);
+        assert_eq!(table[Square::A1.to_index()], None);
+        assert_eq!(table[Square::H8.to_index()], None);
+        // a pawn on d7 would give check
+        assert_eq!(table[Square::D7.to_index()], None);
+    }
+
+    #[test]
+    fn king_is_measured_against_its_home_square() {
+        let table = piece_square_table(&naive_evaluator(), Piece::King, Backdrop::Endgame);
+        assert_eq!(table[Square::E1.to_index()], Some(0));
+        assert_eq!(table[Square::E8.to_index()], None);
+    }
+}
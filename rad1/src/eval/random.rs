@@ -0,0 +1,106 @@
+use super::Evaluator;
+use crate::{Position, PositionStatus};
+
+/// Returns a random evaluation for every position that isn't over.
+///
+/// Searching with it still finds checkmates, so it is the weakest baseline
+/// that doesn't blunder into a mate in one. The evaluation is derived from the
+/// position's hash and a seed picked when the evaluator is created, so a
+/// position keeps its value for as long as the evaluator lives.
+pub struct RandomEvaluator {
+    seed: u64,
+}
+
+impl Default for RandomEvaluator {
+    fn default() -> Self {
+        Self::with_seed(rand::random())
+    }
+}
+
+impl RandomEvaluator {
+    /// An evaluator that always gives the same values for the same seed
+    pub fn with_seed(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    // splitmix64 finalizer, spreads the bits of the hash over the whole value
+    fn mix(&self, hash: u64) -> u64 {
+        let mut x = hash ^ self.seed;
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        x ^ (x >> 31)
+    }
+
+    const MIN: i16 = -30000;
+    const MAX: i16 = 30000;
+    const ZERO: i16 = 0;
+    // about a pawn either way
+    const RANGE: i16 = 10;
+}
+
+impl Evaluator for RandomEvaluator {
+    type Result = i16;
+
+    #[inline]
+    fn min_value(&self) -> Self::Result {
+        Self::MIN
+    }
+
+    #[inline]
+    fn max_value(&self) -> Self::Result {
+        Self::MAX
+    }
+
+    fn evaluate(&self, position: &Position) -> Self::Result {
+        match position.status() {
+            PositionStatus::Stalemate => Self::ZERO,
+            PositionStatus::Checkmate => Self::MIN,
+            PositionStatus::Ongoing => {
+                let span = (2 * Self::RANGE + 1) as u64;
+                (self.mix(position.get_hash()) % span) as i16 - Self::RANGE
+            }
+        }
+    }
+
+    fn make_deterministic(&mut self) {
+        self.seed = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RandomEvaluator;
+    use crate::eval::Evaluator;
+    use crate::Position;
+    use std::str::FromStr;
+
+    #[test]
+    fn evaluation_in_range() {
+        let evaluator = RandomEvaluator::default();
+        for _ in 0..100 {
+            let evaluation = evaluator.evaluate(&Position::default());
+            assert!((-10..=10).contains(&evaluation));
+        }
+    }
+
+    #[test]
+    fn deterministic_evaluators_agree() {
+        let mut first = RandomEvaluator::default();
+        let mut second = RandomEvaluator::default();
+        first.make_deterministic();
+        second.make_deterministic();
+        let position = Position::from_str(
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        )
+        .unwrap();
+        assert_eq!(first.evaluate(&position), second.evaluate(&position));
+        assert_eq!(first.evaluate(&position), first.evaluate(&position));
+    }
+
+    #[test]
+    fn checkmate_is_min() {
+        let evaluator = RandomEvaluator::default();
+        let position = Position::from_str("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert_eq!(evaluator.evaluate(&position), evaluator.min_value());
+    }
+}
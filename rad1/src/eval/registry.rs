@@ -0,0 +1,207 @@
+use super::Evaluator;
+use std::error::Error;
+use std::fmt;
+
+pub type BoxedEvaluator = Box<dyn Evaluator<Result = i16> + Send + Sync>;
+
+/// Creates an evaluator from the argument of its key,
+/// the `<path>` in `file:<path>`, or an empty string for keys without one.
+pub type EvaluatorFactory = fn(&str) -> Result<BoxedEvaluator, RegistryError>;
+
+/// The reason an evaluator couldn't be created from its key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryError {
+    UnknownEvaluator(String),
+    MissingArgument(String),
+    Load { key: String, reason: String },
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::UnknownEvaluator(key) => write!(f, "unknown evaluator '{}'", key),
+            RegistryError::MissingArgument(key) => {
+                write!(
+                    f,
+                    "evaluator '{}' needs an argument like '{}:<path>'",
+                    key, key
+                )
+            }
+            RegistryError::Load { key, reason } => {
+                write!(f, "failed to load evaluator '{}': {}", key, reason)
+            }
+        }
+    }
+}
+
+impl Error for RegistryError {}
+
+struct Entry {
+    name: &'static str,
+    description: &'static str,
+    takes_argument: bool,
+    factory: EvaluatorFactory,
+}
+
+/// Evaluators that can be selected by a string key.
+///
+/// Keys are either a plain name like `naive` or a name and an argument
+/// separated by a colon like `file:<path>` for evaluators that load their
+/// parameters from somewhere.
+#[derive(Default)]
+pub struct Registry {
+    entries: Vec<Entry>,
+}
+
+impl Registry {
+    /// Register an evaluator selected by its name alone
+    pub fn register(
+        &mut self,
+        name: &'static str,
+        description: &'static str,
+        factory: EvaluatorFactory,
+    ) {
+        self.insert(name, description, false, factory);
+    }
+
+    /// Register an evaluator selected by `name:<argument>`
+    pub fn register_with_argument(
+        &mut self,
+        name: &'static str,
+        description: &'static str,
+        factory: EvaluatorFactory,
+    ) {
+        self.insert(name, description, true, factory);
+    }
+
+    fn insert(
+        &mut self,
+        name: &'static str,
+        description: &'static str,
+        takes_argument: bool,
+        factory: EvaluatorFactory,
+    ) {
+        let entry = Entry {
+            name,
+            description,
+            takes_argument,
+            factory,
+        };
+        match self.entries.iter().position(|e| e.name == name) {
+            Some(index) => self.entries[index] = entry,
+            None => self.entries.push(entry),
+        }
+    }
+
+    /// Every key with its description, keys taking an argument are shown as `name:<path>`
+    pub fn keys(&self) -> Vec<(String, &'static str)> {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let key = if entry.takes_argument {
+                    format!("{}:<path>", entry.name)
+                } else {
+                    entry.name.to_string()
+                };
+                (key, entry.description)
+            })
+            .collect()
+    }
+
+    /// The names of the evaluators that don't need an argument
+    pub fn plain_names(&self) -> Vec<&'static str> {
+        self.entries
+            .iter()
+            .filter(|entry| !entry.takes_argument)
+            .map(|entry| entry.name)
+            .collect()
+    }
+
+    /// Returns true if the key refers to a registered evaluator.
+    /// This doesn't check that an argument actually loads.
+    pub fn contains(&self, key: &str) -> bool {
+        self.lookup(key).is_ok()
+    }
+
+    /// Create the evaluator the key refers to
+    pub fn create(&self, key: &str) -> Result<BoxedEvaluator, RegistryError> {
+        let (entry, argument) = self.lookup(key)?;
+        (entry.factory)(argument)
+    }
+
+    fn lookup<'a>(&self, key: &'a str) -> Result<(&Entry, &'a str), RegistryError> {
+        let mut parts = key.splitn(2, ':');
+        let name = parts.next().unwrap_or_default();
+        let argument = parts.next();
+        let entry = self
+            .entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .ok_or_else(|| RegistryError::UnknownEvaluator(key.to_string()))?;
+        match (entry.takes_argument, argument) {
+            (false, None) => Ok((entry, "")),
+            (true, Some(argument)) if !argument.is_empty() => Ok((entry, argument)),
+            (true, _) => Err(RegistryError::MissingArgument(name.to_string())),
+            (false, Some(_)) => Err(RegistryError::UnknownEvaluator(key.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BoxedEvaluator, Registry, RegistryError};
+    use crate::eval::naive_evaluator;
+    use crate::Position;
+
+    fn naive(_: &str) -> Result<BoxedEvaluator, RegistryError> {
+        Ok(Box::new(naive_evaluator()))
+    }
+
+    fn from_file(path: &str) -> Result<BoxedEvaluator, RegistryError> {
+        Err(RegistryError::Load {
+            key: format!("file:{}", path),
+            reason: String::from("not found"),
+        })
+    }
+
+    fn test_registry() -> Registry {
+        let mut registry = Registry::default();
+        registry.register("naive", "naive evaluator", naive);
+        registry.register_with_argument("file", "evaluator from a file", from_file);
+        registry
+    }
+
+    #[test]
+    fn create_by_name() {
+        let registry = test_registry();
+        let evaluator = registry.create("naive").unwrap();
+        assert_eq!(evaluator.evaluate(&Position::default()), 0);
+        assert!(registry.contains("file:weights.txt"));
+    }
+
+    #[test]
+    fn keys_and_names() {
+        let registry = test_registry();
+        let keys: Vec<String> = registry.keys().into_iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec!["naive", "file:<path>"]);
+        assert_eq!(registry.plain_names(), vec!["naive"]);
+    }
+
+    #[test]
+    fn invalid_keys() {
+        let registry = test_registry();
+        assert_eq!(
+            registry.create("tapered").err(),
+            Some(RegistryError::UnknownEvaluator(String::from("tapered")))
+        );
+        assert_eq!(
+            registry.create("file").err(),
+            Some(RegistryError::MissingArgument(String::from("file")))
+        );
+        assert!(!registry.contains("naive:extra"));
+        assert!(matches!(
+            registry.create("file:missing.txt").err(),
+            Some(RegistryError::Load { .. })
+        ));
+    }
+}
@@ -0,0 +1,165 @@
+use crate::score::Score;
+use crate::{ChessMove, Color};
+use std::io::{self, Write};
+
+// Blocks of the sparkline from the lowest score to the highest
+const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+// Centipawns where the sparkline is cut off, beyond that the game is decided
+const SPARKLINE_LIMIT: i32 = 500;
+
+/// The evaluation of one position of a game, from white's point of view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Point {
+    /// Plies played before the position
+    pub ply: u32,
+    pub score: Score,
+    pub best_move: ChessMove,
+}
+
+impl Point {
+    /// The point of a search result, whose score is from the side to move's point of view
+    pub fn new(ply: u32, side_to_move: Color, score: Score, best_move: ChessMove) -> Self {
+        let score = match side_to_move {
+            Color::White => score,
+            Color::Black => -score,
+        };
+        Self {
+            ply,
+            score,
+            best_move,
+        }
+    }
+
+    /// The move number of the position like in a PGN
+    pub fn move_number(&self) -> u32 {
+        self.ply / 2 + 1
+    }
+
+    pub fn side_to_move(&self) -> Color {
+        if self.ply % 2 == 0 {
+            Color::White
+        } else {
+            Color::Black
+        }
+    }
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "white",
+        Color::Black => "black",
+    }
+}
+
+/// Write the points as CSV with a header line, the scores in centipawns
+/// and mates the way [`Score::to_centipawns`] writes them
+pub fn write_csv(out: &mut impl Write, points: &[Point]) -> io::Result<()> {
+    writeln!(out, "move,side,centipawns,best_move")?;
+    for point in points {
+        writeln!(
+            out,
+            "{},{},{},{}",
+            point.move_number(),
+            color_name(point.side_to_move()),
+            point.score.to_centipawns(),
+            point.best_move
+        )?;
+    }
+    Ok(())
+}
+
+/// Write the points as a JSON array of objects with the fields of [`write_csv`]
+pub fn write_json(out: &mut impl Write, points: &[Point]) -> io::Result<()> {
+    writeln!(out, "[")?;
+    for (index, point) in points.iter().enumerate() {
+        writeln!(
+            out,
+            "  {{\"move\": {}, \"side\": \"{}\", \"centipawns\": {}, \"best_move\": \"{}\"}}{}",
+            point.move_number(),
+            color_name(point.side_to_move()),
+            point.score.to_centipawns(),
+            point.best_move,
+            if index + 1 < points.len() { "," } else { "" }
+        )?;
+    }
+    writeln!(out, "]")
+}
+
+/// A block per point, higher the better white stands. Scores beyond five
+/// pawns either way get the lowest or highest block.
+pub fn sparkline(points: &[Point]) -> String {
+    let levels = BLOCKS.len() as i32;
+    points
+        .iter()
+        .map(|point| {
+            let centipawns = point
+                .score
+                .to_centipawns()
+                .clamp(-SPARKLINE_LIMIT, SPARKLINE_LIMIT);
+            let level = (centipawns + SPARKLINE_LIMIT) * levels / (2 * SPARKLINE_LIMIT + 1);
+            BLOCKS[level as usize]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sparkline, write_csv, write_json, Point};
+    use crate::score::Score;
+    use crate::{ChessMove, Color, Square};
+
+    fn points() -> Vec<Point> {
+        let e4 = ChessMove::new(Square::E2, Square::E4, None);
+        let e5 = ChessMove::new(Square::E7, Square::E5, None);
+        let qh5 = ChessMove::new(Square::D1, Square::H5, None);
+        vec![
+            Point::new(0, Color::White, Score::Eval(3), e4),
+            Point::new(1, Color::Black, Score::Eval(2), e5),
+            Point::new(2, Color::White, Score::Mate(5), qh5),
+        ]
+    }
+
+    #[test]
+    fn scores_are_from_whites_point_of_view() {
+        let points = points();
+        assert_eq!(points[1].score, Score::Eval(-2));
+        assert_eq!(points[1].move_number(), 1);
+        assert_eq!(points[1].side_to_move(), Color::Black);
+        assert_eq!(points[2].move_number(), 2);
+    }
+
+    #[test]
+    fn csv() {
+        let mut out = Vec::new();
+        write_csv(&mut out, &points()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "move,side,centipawns,best_move\n1,white,30,e2e4\n1,black,-20,e7e5\n2,white,31995,d1h5\n"
+        );
+    }
+
+    #[test]
+    fn json() {
+        let mut out = Vec::new();
+        write_json(&mut out, &points()[..2]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[\n  {\"move\": 1, \"side\": \"white\", \"centipawns\": 30, \"best_move\": \"e2e4\"},\n  {\"move\": 1, \"side\": \"black\", \"centipawns\": -20, \"best_move\": \"e7e5\"}\n]\n"
+        );
+    }
+
+    #[test]
+    fn sparkline_clamps_decided_games() {
+        let e4 = ChessMove::new(Square::E2, Square::E4, None);
+        let points: Vec<Point> = [
+            Score::Mated(4),
+            Score::Eval(-20),
+            Score::DRAW,
+            Score::Eval(50),
+        ]
+        .iter()
+        .map(|&score| Point::new(0, Color::White, score, e4))
+        .collect();
+        assert_eq!(sparkline(&points), "▁▃▄█");
+    }
+}
@@ -0,0 +1,159 @@
+use crate::dataset::Sample;
+use crate::eval::naive::NaiveEvaluator;
+use crate::{Color, Piece, Position};
+use std::io::{self, Write};
+
+/// Names of the features in the order of [`features`]
+pub const FEATURE_NAMES: [&str; 12] = [
+    "psqt",
+    "pawns",
+    "knights",
+    "bishops",
+    "rooks",
+    "queens",
+    "unstoppable_passer",
+    "hanging_piece",
+    "pawn_threat",
+    "pawn_fork",
+    "white_to_move",
+    "phase",
+];
+
+const MATERIAL: [Piece; 5] = [
+    Piece::Pawn,
+    Piece::Knight,
+    Piece::Bishop,
+    Piece::Rook,
+    Piece::Queen,
+];
+
+/// The terms of the naive evaluator for the position, all from white's
+/// point of view: the material and piece-square total, white's pieces minus
+/// black's of each kind and the counts of the weighted terms of
+/// [`NaiveEvaluator::terms`], followed by who is to move and the game phase.
+pub fn features(position: &Position) -> [f32; 12] {
+    let white = position.side_to_move() == Color::White;
+    let sign = if white { 1.0 } else { -1.0 };
+    let mut features = [0.0; 12];
+    features[0] = f32::from(position.psqt());
+    for (feature, &piece) in features[1..6].iter_mut().zip(MATERIAL.iter()) {
+        let pieces = position.pieces(piece);
+        let count = |color| (pieces & position.color_combined(color)).popcnt() as f32;
+        *feature = count(Color::White) - count(Color::Black);
+    }
+    for (feature, &count) in features[6..10]
+        .iter_mut()
+        .zip(NaiveEvaluator::terms(position).iter())
+    {
+        *feature = sign * f32::from(count);
+    }
+    features[10] = if white { 1.0 } else { 0.0 };
+    features[11] = position.phase();
+    features
+}
+
+/// The features of every sample followed by the points white got from the game
+pub fn rows(samples: &[Sample]) -> Vec<Vec<f32>> {
+    samples
+        .iter()
+        .map(|sample| {
+            let mut row = features(&sample.position).to_vec();
+            row.push(sample.outcome.score() as f32);
+            row
+        })
+        .collect()
+}
+
+/// Write the rows of [`rows`] as CSV with a header line
+pub fn write_csv(out: &mut impl Write, rows: &[Vec<f32>]) -> io::Result<()> {
+    writeln!(out, "{},outcome", FEATURE_NAMES.join(","))?;
+    for row in rows {
+        let values: Vec<String> = row.iter().map(|value| value.to_string()).collect();
+        writeln!(out, "{}", values.join(","))?;
+    }
+    Ok(())
+}
+
+/// Write the rows of [`rows`] as a NumPy `.npy` file of little endian
+/// 32 bit floats, one row per sample
+pub fn write_npy(out: &mut impl Write, rows: &[Vec<f32>]) -> io::Result<()> {
+    let columns = FEATURE_NAMES.len() + 1;
+    let mut header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+        rows.len(),
+        columns
+    );
+    // the magic string, version and header length take 10 bytes and the
+    // data has to start at a multiple of 64, the header ends with a newline
+    let padding = (64 - (10 + header.len() + 1) % 64) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+    out.write_all(b"\x93NUMPY\x01\x00")?;
+    out.write_all(&(header.len() as u16).to_le_bytes())?;
+    out.write_all(header.as_bytes())?;
+    for row in rows {
+        for value in row {
+            out.write_all(&value.to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{features, rows, write_csv, write_npy, FEATURE_NAMES};
+    use crate::dataset::Sample;
+    use crate::eval::naive::{EvaluationConfig, NaiveEvaluator};
+    use crate::eval::Evaluator;
+    use crate::{Color, Position};
+    use std::str::FromStr;
+    use test_case::test_case;
+
+    #[test_case("4k3/8/8/4n3/8/8/8/4RK2 w - - 0 1")]
+    #[test_case("4k3/8/8/4n3/8/8/8/4RK2 b - - 0 1")]
+    #[test_case("8/7p/8/P3k3/8/8/1K6/8 w - - 0 1")]
+    fn features_add_up_to_the_evaluation(fen: &str) {
+        let position = Position::from_str(fen).unwrap();
+        let values = features(&position);
+        let weights = EvaluationConfig::DEFAULT.weights();
+        let white: f32 = values[0]
+            + values[6..10]
+                .iter()
+                .zip(weights.iter())
+                .map(|(value, &weight)| value * f32::from(weight))
+                .sum::<f32>();
+        let evaluation = f32::from(NaiveEvaluator::default().evaluate(&position));
+        match position.side_to_move() {
+            Color::White => assert_eq!(white, evaluation),
+            Color::Black => assert_eq!(white, -evaluation),
+        }
+    }
+
+    #[test]
+    fn material() {
+        let position = Position::from_str("4k3/8/8/4n3/8/8/8/4RK2 w - - 0 1").unwrap();
+        assert_eq!(features(&position)[1..6], [0.0, -1.0, 0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn csv_and_npy() {
+        let sample = Sample::parse("4k3/8/8/4n3/8/8/8/4RK2 w - - 0 1 [1.0]").unwrap();
+        let rows = rows(&[sample]);
+        assert_eq!(rows[0].len(), FEATURE_NAMES.len() + 1);
+        assert_eq!(rows[0].last(), Some(&1.0));
+
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &rows).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("psqt,pawns,"));
+        assert_eq!(csv.lines().count(), 2);
+
+        let mut npy = Vec::new();
+        write_npy(&mut npy, &rows).unwrap();
+        assert!(npy.starts_with(b"\x93NUMPY\x01\x00"));
+        let header_end = 10 + usize::from(u16::from_le_bytes([npy[8], npy[9]]));
+        assert_eq!(header_end % 64, 0);
+        assert_eq!(npy[header_end - 1], b'\n');
+        assert_eq!(npy.len() - header_end, 4 * rows[0].len());
+    }
+}
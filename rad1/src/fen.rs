@@ -0,0 +1,372 @@
+use crate::{ChessGame, Color, Piece, Position};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+const KNIGHT_STEPS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const DIAGONALS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const STRAIGHTS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// What's wrong with a FEN string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    /// The FEN has fewer than the 4 required fields or more than 6
+    FieldCount(usize),
+    /// The piece placement doesn't have 8 ranks
+    RankCount(usize),
+    /// A rank, numbered from 1, doesn't describe exactly 8 squares
+    RankLength {
+        rank: usize,
+        squares: usize,
+    },
+    InvalidPiece(char),
+    SideToMove(String),
+    CastlingRights(String),
+    /// A castling right without the king and rook on their starting squares
+    ImpossibleCastling(char),
+    EnPassant(String),
+    MoveCounter(String),
+    KingCount {
+        color: Color,
+        count: usize,
+    },
+    /// A pawn on the first or eighth rank
+    PawnOnBackRank,
+    /// The side that just moved left its king in check
+    OpponentInCheck,
+    /// The FEN is valid but the move generator can't set up the position
+    Unsupported,
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::FieldCount(count) => write!(
+                f,
+                "expected 4 to 6 space separated fields but found {}",
+                count
+            ),
+            FenError::RankCount(count) => {
+                write!(f, "piece placement has {} ranks instead of 8", count)
+            }
+            FenError::RankLength { rank, squares } => {
+                write!(f, "rank {} has {} squares instead of 8", rank, squares)
+            }
+            FenError::InvalidPiece(piece) => {
+                write!(f, "'{}' is not a piece, use one of PNBRQKpnbrqk", piece)
+            }
+            FenError::SideToMove(side) => {
+                write!(f, "side to move '{}' is neither 'w' nor 'b'", side)
+            }
+            FenError::CastlingRights(rights) => write!(
+                f,
+                "castling rights '{}' are not '-' or a combination of KQkq",
+                rights
+            ),
+            FenError::ImpossibleCastling(right) => write!(
+                f,
+                "castling right '{}' needs the king and rook on their starting squares",
+                right
+            ),
+            FenError::EnPassant(square) => write!(
+                f,
+                "en passant square '{}' is not behind a pawn that just moved two squares",
+                square
+            ),
+            FenError::MoveCounter(counter) => {
+                write!(f, "move counter '{}' is not a number", counter)
+            }
+            FenError::KingCount { color, count } => {
+                write!(f, "{:?} has {} kings instead of 1", color, count)
+            }
+            FenError::PawnOnBackRank => write!(f, "pawns can't be on the first or eighth rank"),
+            FenError::OpponentInCheck => {
+                write!(f, "the side that isn't moving is in check")
+            }
+            FenError::Unsupported => write!(f, "the position can't be set up"),
+        }
+    }
+}
+
+impl Error for FenError {}
+
+type Placement = [Option<(Piece, Color)>; 64];
+
+/// Checks that a FEN describes a legal position, with an explanation
+/// of the first problem found otherwise.
+pub fn validate(fen: &str) -> Result<(), FenError> {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    if fields.len() < 4 || fields.len() > 6 {
+        return Err(FenError::FieldCount(fields.len()));
+    }
+    let placement = parse_placement(fields[0])?;
+    let side_to_move = match fields[1] {
+        "w" => Color::White,
+        "b" => Color::Black,
+        side => return Err(FenError::SideToMove(side.to_string())),
+    };
+    validate_castling(&placement, fields[2])?;
+    validate_en_passant(&placement, side_to_move, fields[3])?;
+    if let Some(counter) = fields[4..].iter().find(|c| c.parse::<u32>().is_err()) {
+        return Err(FenError::MoveCounter(counter.to_string()));
+    }
+    for &color in &[Color::White, Color::Black] {
+        let count = placement
+            .iter()
+            .filter(|&&square| square == Some((Piece::King, color)))
+            .count();
+        if count != 1 {
+            return Err(FenError::KingCount { color, count });
+        }
+    }
+    let back_ranks = (0..8).chain(56..64);
+    if back_ranks
+        .filter_map(|index| placement[index])
+        .any(|(piece, _)| piece == Piece::Pawn)
+    {
+        return Err(FenError::PawnOnBackRank);
+    }
+    if king_attacked(&placement, !side_to_move) {
+        return Err(FenError::OpponentInCheck);
+    }
+    Ok(())
+}
+
+/// The position of a FEN. It's checked with [`validate`] first, so a
+/// malformed FEN is rejected with its problem instead of being misread, and
+/// the fields may be separated by any whitespace.
+pub fn parse(fen: &str) -> Result<Position, FenError> {
+    let fen = normalize(fen)?;
+    Position::from_str(&fen).map_err(|_| FenError::Unsupported)
+}
+
+/// The game starting from the position of a FEN, see [`parse`]
+pub fn parse_game(fen: &str) -> Result<ChessGame, FenError> {
+    let fen = normalize(fen)?;
+    ChessGame::from_str(&fen).map_err(|_| FenError::Unsupported)
+}
+
+// The valid FEN with its fields separated by single spaces
+fn normalize(fen: &str) -> Result<String, FenError> {
+    validate(fen)?;
+    Ok(fen.split_whitespace().collect::<Vec<&str>>().join(" "))
+}
+
+fn parse_placement(field: &str) -> Result<Placement, FenError> {
+    let ranks: Vec<&str> = field.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::RankCount(ranks.len()));
+    }
+    let mut placement = [None; 64];
+    for (row, rank) in ranks.iter().enumerate() {
+        let rank_index = 7 - row;
+        let mut squares = 0;
+        for c in rank.chars() {
+            if let Some(empty) = c.to_digit(10).filter(|&n| (1..=8).contains(&n)) {
+                squares += empty as usize;
+                continue;
+            }
+            let piece = match c.to_ascii_lowercase() {
+                'p' => Piece::Pawn,
+                'n' => Piece::Knight,
+                'b' => Piece::Bishop,
+                'r' => Piece::Rook,
+                'q' => Piece::Queen,
+                'k' => Piece::King,
+                _ => return Err(FenError::InvalidPiece(c)),
+            };
+            let color = if c.is_ascii_uppercase() {
+                Color::White
+            } else {
+                Color::Black
+            };
+            if squares < 8 {
+                placement[rank_index * 8 + squares] = Some((piece, color));
+            }
+            squares += 1;
+        }
+        if squares != 8 {
+            return Err(FenError::RankLength {
+                rank: rank_index + 1,
+                squares,
+            });
+        }
+    }
+    Ok(placement)
+}
+
+fn validate_castling(placement: &Placement, rights: &str) -> Result<(), FenError> {
+    if rights == "-" {
+        return Ok(());
+    }
+    let repeated = |(i, right): (usize, char)| rights[..i].contains(right);
+    if rights.chars().any(|right| !"KQkq".contains(right)) || rights.char_indices().any(repeated) {
+        return Err(FenError::CastlingRights(rights.to_string()));
+    }
+    for right in rights.chars() {
+        let (color, back_rank) = if right.is_ascii_uppercase() {
+            (Color::White, 0)
+        } else {
+            (Color::Black, 56)
+        };
+        let rook_file = if right.eq_ignore_ascii_case(&'k') {
+            7
+        } else {
+            0
+        };
+        if placement[back_rank + 4] != Some((Piece::King, color))
+            || placement[back_rank + rook_file] != Some((Piece::Rook, color))
+        {
+            return Err(FenError::ImpossibleCastling(right));
+        }
+    }
+    Ok(())
+}
+
+fn validate_en_passant(
+    placement: &Placement,
+    side_to_move: Color,
+    square: &str,
+) -> Result<(), FenError> {
+    if square == "-" {
+        return Ok(());
+    }
+    let invalid = || FenError::EnPassant(square.to_string());
+    let mut chars = square.chars();
+    let (file, rank) = match (chars.next(), chars.next(), chars.next()) {
+        (Some(file @ 'a'..='h'), Some(rank), None) => (file as usize - 'a' as usize, rank),
+        _ => return Err(invalid()),
+    };
+    // the pawn that moved two squares stands in front of the en passant square
+    let (expected_rank, pawn_rank, pawn_color) = match side_to_move {
+        Color::White => ('6', 4, Color::Black),
+        Color::Black => ('3', 3, Color::White),
+    };
+    if rank != expected_rank || placement[pawn_rank * 8 + file] != Some((Piece::Pawn, pawn_color)) {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+fn king_attacked(placement: &Placement, color: Color) -> bool {
+    let king = match placement
+        .iter()
+        .position(|&square| square == Some((Piece::King, color)))
+    {
+        Some(index) => ((index / 8) as i8, (index % 8) as i8),
+        None => return false,
+    };
+    let attacker = !color;
+    let piece_at = |rank: i8, file: i8| {
+        if (0..8).contains(&rank) && (0..8).contains(&file) {
+            placement[(rank * 8 + file) as usize]
+        } else {
+            None
+        }
+    };
+    let pawn_rank = match color {
+        Color::White => king.0 + 1,
+        Color::Black => king.0 - 1,
+    };
+    let pawns = [
+        piece_at(pawn_rank, king.1 - 1),
+        piece_at(pawn_rank, king.1 + 1),
+    ];
+    let mut steps = KNIGHT_STEPS
+        .iter()
+        .map(|&step| (step, Piece::Knight))
+        .chain(
+            DIAGONALS
+                .iter()
+                .chain(STRAIGHTS.iter())
+                .map(|&step| (step, Piece::King)),
+        );
+    let mut slides = DIAGONALS
+        .iter()
+        .map(|&ray| (ray, Piece::Bishop))
+        .chain(STRAIGHTS.iter().map(|&ray| (ray, Piece::Rook)));
+
+    pawns.contains(&Some((Piece::Pawn, attacker)))
+        || steps.any(|((ranks, files), piece)| {
+            piece_at(king.0 + ranks, king.1 + files) == Some((piece, attacker))
+        })
+        || slides.any(|((ranks, files), slider)| {
+            let mut rank = king.0 + ranks;
+            let mut file = king.1 + files;
+            while (0..8).contains(&rank) && (0..8).contains(&file) {
+                if let Some((piece, color)) = piece_at(rank, file) {
+                    return color == attacker && (piece == slider || piece == Piece::Queen);
+                }
+                rank += ranks;
+                file += files;
+            }
+            false
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, parse_game, validate, FenError};
+    use crate::Color;
+    use test_case::test_case;
+
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")]
+    #[test_case("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2")]
+    #[test_case("r3k2r/8/8/8/8/8/8/R3K2R b Qk -")]
+    #[test_case("4k3/8/8/8/8/8/8/4K2R w K - 0 1")]
+    fn accepts_legal_positions(fen: &str) {
+        assert_eq!(validate(fen), Ok(()));
+    }
+
+    #[test_case("4k3/8/8/8/8/8/8/4K3 w -", FenError::FieldCount(3))]
+    #[test_case("4k3/8/8/8/8/8/4K3 w - -", FenError::RankCount(7))]
+    #[test_case("4k3/8/8/8/8/8/8/4K4 w - -", FenError::RankLength { rank: 1, squares: 9 })]
+    #[test_case("4k3/8/8/8/8/8/8/4X3 w - -", FenError::InvalidPiece('X'))]
+    #[test_case("4k3/8/8/8/8/8/8/4K3 x - -", FenError::SideToMove(String::from("x")))]
+    #[test_case(
+        "4k3/8/8/8/8/8/8/4K3 w KX -",
+        FenError::CastlingRights(String::from("KX"))
+    )]
+    #[test_case("4k3/8/8/8/8/8/8/4K3 w K -", FenError::ImpossibleCastling('K'))]
+    #[test_case("4k3/8/8/8/8/8/8/4K3 w - e6", FenError::EnPassant(String::from("e6")))]
+    #[test_case(
+        "4k3/8/8/8/8/8/8/4K3 w - - x 1",
+        FenError::MoveCounter(String::from("x"))
+    )]
+    #[test_case("8/8/8/8/8/8/8/4K3 w - -", FenError::KingCount { color: Color::Black, count: 0 })]
+    #[test_case("P3k3/8/8/8/8/8/8/4K3 w - -", FenError::PawnOnBackRank)]
+    #[test_case("4k3/8/8/8/4R3/8/8/4K3 w - -", FenError::OpponentInCheck)]
+    #[test_case("4k3/8/8/8/8/5n2/8/4K3 b - -", FenError::OpponentInCheck)]
+    #[test_case("4k3/3P4/8/8/8/8/8/4K3 w - -", FenError::OpponentInCheck)]
+    fn reports_the_problem(fen: &str, error: FenError) {
+        assert_eq!(validate(fen), Err(error));
+    }
+
+    #[test]
+    fn parsing() {
+        let position = parse(" 4k3/8/8/8/8/8/8/4K3\tw  -  - 0 1 ").unwrap();
+        assert_eq!(position.to_string(), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(
+            parse_game("4k3/8/8/8/8/8/8/4K3 b - -")
+                .unwrap()
+                .current_position()
+                .to_string(),
+            "4k3/8/8/8/8/8/8/4K3 b - - 0 1"
+        );
+        // nine empty squares don't fit in a rank
+        assert_eq!(
+            parse("4k3/9/8/8/8/8/8/4K3 w - - 0 1").err(),
+            Some(FenError::InvalidPiece('9'))
+        );
+        assert_eq!(parse("").err(), Some(FenError::FieldCount(0)));
+    }
+}
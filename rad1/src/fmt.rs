@@ -0,0 +1,58 @@
+use crate::notation::Notation;
+use crate::pgn;
+use crate::score::Score;
+use crate::{ChessMove, Position};
+
+// Evaluation units in one pawn
+const UNITS_PER_PAWN: f32 = 10.0;
+
+/// A score in pawns like `+1.30` or `-0.20`, or a mate like `#5` or `#-3`
+pub fn score(score: Score) -> String {
+    match score {
+        Score::Eval(0) => String::from("0.00"),
+        Score::Eval(value) => format!("{:+.2}", f32::from(value) / UNITS_PER_PAWN),
+        mate => mate.to_string(),
+    }
+}
+
+/// A principal variation from the position in SAN with move numbers,
+/// e.g. `1. e4 e5 2. Nf3` or `1... e5 2. Nf3`
+pub fn pv(position: &Position, moves: &[ChessMove]) -> String {
+    pgn::movetext(position, moves)
+}
+
+/// A principal variation like [`pv`] in the notation, e.g. `1... ♟e5 2. ♘f3`
+pub fn pv_in(position: &Position, moves: &[ChessMove], notation: Notation) -> String {
+    pgn::movetext_in(position, moves, notation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pv, score};
+    use crate::score::Score;
+    use crate::{ChessMove, Position, Square};
+    use std::str::FromStr;
+    use test_case::test_case;
+
+    #[test_case(Score::Eval(13), "+1.30")]
+    #[test_case(Score::Eval(-2), "-0.20")]
+    #[test_case(Score::DRAW, "0.00")]
+    #[test_case(Score::Mate(9), "#5")]
+    #[test_case(Score::Mated(6), "#-3")]
+    fn scores(value: Score, expected: &str) {
+        assert_eq!(score(value), expected);
+    }
+
+    #[test]
+    fn pv_with_move_numbers() {
+        let position =
+            Position::from_str("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1")
+                .unwrap();
+        let moves = [
+            ChessMove::new(Square::E7, Square::E5, None),
+            ChessMove::new(Square::G1, Square::F3, None),
+            ChessMove::new(Square::B8, Square::C6, None),
+        ];
+        assert_eq!(pv(&position, &moves), "1... e5 2. Nf3 Nc6");
+    }
+}
@@ -0,0 +1,308 @@
+use crate::move_hash;
+use crate::pgn::{self, PgnGame};
+use crate::polyglot;
+use crate::Position;
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+
+const MAGIC: &[u8; 8] = b"RAD1GDB1";
+
+/// Games of PGN databases indexed by the positions of their main lines, to
+/// look up the games that reached a position without replaying them all.
+///
+/// Positions are keyed by their [`polyglot::hash`]. The database is kept on
+/// disk as the bytes of [`GameDatabase::to_bytes`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GameDatabase {
+    games: Vec<PgnGame>,
+    // the hash of every position of every game with the game's number,
+    // sorted, a position repeated in a game is listed once
+    index: Vec<(u64, u32)>,
+}
+
+/// Collects the games of a [`GameDatabase`]
+#[derive(Default)]
+pub struct GameDatabaseBuilder {
+    database: GameDatabase,
+    /// Games left out for an invalid `FEN` tag or an illegal move
+    pub skipped: u32,
+}
+
+/// The reason a game database couldn't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameDatabaseError {
+    InvalidMagic,
+    /// The bytes end in the middle of a game or of the index
+    Truncated,
+    /// A game, numbered from 0, with an invalid tag or an illegal move
+    InvalidGame(u32),
+    /// The index refers to a game that isn't in the database
+    InvalidIndex,
+}
+
+impl fmt::Display for GameDatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameDatabaseError::InvalidMagic => write!(f, "not a rad1 game database"),
+            GameDatabaseError::Truncated => write!(f, "the game database is truncated"),
+            GameDatabaseError::InvalidGame(number) => write!(f, "game {} is invalid", number),
+            GameDatabaseError::InvalidIndex => write!(f, "the index refers to a missing game"),
+        }
+    }
+}
+
+impl Error for GameDatabaseError {}
+
+impl GameDatabaseBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add every game of a PGN database
+    pub fn add_pgn(&mut self, text: &str) {
+        for game in pgn::read_games(text) {
+            self.add_game(game);
+        }
+    }
+
+    /// Add the game and index the positions of its main line, returns
+    /// false when it was skipped for an invalid start or an illegal move
+    pub fn add_game(&mut self, mut game: PgnGame) -> bool {
+        let (mut position, moves) = match (game.start(), game.moves(usize::MAX)) {
+            (Some(start), Some(moves)) => (start, moves),
+            _ => {
+                self.skipped += 1;
+                return false;
+            }
+        };
+        let number = self.database.games.len() as u32;
+        let mut hashes = vec![polyglot::hash(&position)];
+        game.sans.clear();
+        for chess_move in moves {
+            // the moves are written the way they are read back
+            game.sans.push(pgn::san(&position, chess_move));
+            position = position.make_move_new(chess_move);
+            hashes.push(polyglot::hash(&position));
+        }
+        hashes.sort_unstable();
+        hashes.dedup();
+        self.database
+            .index
+            .extend(hashes.into_iter().map(|hash| (hash, number)));
+        self.database.games.push(game);
+        true
+    }
+
+    pub fn build(mut self) -> GameDatabase {
+        self.database.index.sort_unstable();
+        self.database
+    }
+}
+
+impl GameDatabase {
+    /// The number of games
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    /// The number of different positions of every game added up
+    pub fn positions(&self) -> usize {
+        self.index.len()
+    }
+
+    /// The game with the number, numbered from 0 in the order they were added
+    pub fn game(&self, number: u32) -> Option<&PgnGame> {
+        self.games.get(number as usize)
+    }
+
+    /// The numbers of the games that reached the position with the
+    /// Polyglot hash, in the order they were added
+    pub fn games_containing(&self, position_hash: u64) -> Vec<u32> {
+        let start = self
+            .index
+            .partition_point(|&(hash, _)| hash < position_hash);
+        self.index[start..]
+            .iter()
+            .take_while(|&&(hash, _)| hash == position_hash)
+            .map(|&(_, number)| number)
+            .collect()
+    }
+
+    /// The numbers of the games that reached the position
+    pub fn games_reaching(&self, position: &Position) -> Vec<u32> {
+        self.games_containing(polyglot::hash(position))
+    }
+
+    /// The database as bytes: a magic number, the number of games, every
+    /// game as its tags and its moves, then the size of the index and its
+    /// entries, little-endian. Tags are counted and every string is
+    /// prefixed with its length, moves are two bytes each.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&(self.games.len() as u32).to_le_bytes());
+        for game in &self.games {
+            bytes.extend_from_slice(&(game.tags.len() as u16).to_le_bytes());
+            for (name, value) in &game.tags {
+                write_string(&mut bytes, name);
+                write_string(&mut bytes, value);
+            }
+            // the games were checked when they were added
+            let moves = game.moves(usize::MAX).unwrap_or_default();
+            bytes.extend_from_slice(&(moves.len() as u32).to_le_bytes());
+            for chess_move in moves {
+                bytes.extend_from_slice(&move_hash::get_hash(chess_move).to_le_bytes());
+            }
+        }
+        bytes.extend_from_slice(&(self.index.len() as u32).to_le_bytes());
+        for &(hash, number) in &self.index {
+            bytes.extend_from_slice(&hash.to_le_bytes());
+            bytes.extend_from_slice(&number.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GameDatabaseError> {
+        if !bytes.starts_with(MAGIC) {
+            return Err(GameDatabaseError::InvalidMagic);
+        }
+        let mut reader = Reader {
+            bytes: &bytes[MAGIC.len()..],
+        };
+        let game_count = reader.u32()?;
+        let mut games = Vec::new();
+        for number in 0..game_count {
+            let mut game = PgnGame::default();
+            for _ in 0..reader.u16()? {
+                game.tags
+                    .push((reader.string(number)?, reader.string(number)?));
+            }
+            let mut position = game.start().ok_or(GameDatabaseError::InvalidGame(number))?;
+            for _ in 0..reader.u32()? {
+                let chess_move = move_hash::checked_get_move(reader.u16()?)
+                    .filter(|&chess_move| position.legal(chess_move))
+                    .ok_or(GameDatabaseError::InvalidGame(number))?;
+                game.sans.push(pgn::san(&position, chess_move));
+                position = position.make_move_new(chess_move);
+            }
+            games.push(game);
+        }
+        let mut index = Vec::new();
+        for _ in 0..reader.u32()? {
+            let entry = (reader.u64()?, reader.u32()?);
+            if entry.1 >= game_count {
+                return Err(GameDatabaseError::InvalidIndex);
+            }
+            index.push(entry);
+        }
+        // lookups need the index sorted, whoever wrote the file
+        index.sort_unstable();
+        Ok(Self { games, index })
+    }
+}
+
+fn write_string(bytes: &mut Vec<u8>, text: &str) {
+    bytes.extend_from_slice(&(text.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(text.as_bytes());
+}
+
+// Takes little-endian numbers and strings off the front of the bytes
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], GameDatabaseError> {
+        if self.bytes.len() < length {
+            return Err(GameDatabaseError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u16(&mut self) -> Result<u16, GameDatabaseError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, GameDatabaseError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, GameDatabaseError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    // A string of the game with the number
+    fn string(&mut self, number: u32) -> Result<String, GameDatabaseError> {
+        let length = self.u16()? as usize;
+        String::from_utf8(self.take(length)?.to_vec())
+            .map_err(|_| GameDatabaseError::InvalidGame(number))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GameDatabase, GameDatabaseBuilder, GameDatabaseError};
+    use crate::polyglot;
+    use crate::{ChessMove, Position};
+    use std::str::FromStr;
+
+    const GAMES: &str = "[White \"A\"]\n[Result \"1-0\"]\n\n1. e4 e5 2. Nf3 Nc6 1-0\n\n[White \"B\"]\n[Result \"0-1\"]\n\n1. Nf3 Nc6 2. e4 e5 3. Ng1 Nb8 4. Nf3 0-1\n\n[White \"C\"]\n\n1. d4 Ke7 2. Kd2 Qxd2 *\n\n[White \"D\"]\n[FEN \"4k3/8/8/8/8/8/8/4K3 w - - 0 1\"]\n\n1. Kd2 *\n";
+
+    fn database() -> (GameDatabase, u32) {
+        let mut builder = GameDatabaseBuilder::new();
+        builder.add_pgn(GAMES);
+        let skipped = builder.skipped;
+        (builder.build(), skipped)
+    }
+
+    fn after(moves: &str) -> Position {
+        moves
+            .split_whitespace()
+            .fold(Position::default(), |position, text| {
+                position.make_move_new(ChessMove::from_str(text).unwrap())
+            })
+    }
+
+    #[test]
+    fn games_are_found_by_position() {
+        let (database, skipped) = database();
+        assert_eq!((database.len(), skipped), (3, 1));
+        assert_eq!(database.games_reaching(&Position::default()), vec![0, 1]);
+        // reached by both games in another move order, and twice in the second
+        assert_eq!(
+            database.games_reaching(&after("e2e4 e7e5 g1f3 b8c6")),
+            vec![0, 1]
+        );
+        assert_eq!(database.games_reaching(&after("g1f3")), vec![1]);
+        assert!(database.games_reaching(&after("d2d4")).is_empty());
+        assert_eq!(
+            database.games_containing(polyglot::hash(
+                &Position::from_str("4k3/8/8/8/8/8/3K4/8 b - - 0 1").unwrap()
+            )),
+            vec![2]
+        );
+        assert_eq!(database.game(2).unwrap().tag("White"), Some("D"));
+        assert!(database.game(3).is_none());
+    }
+
+    #[test]
+    fn bytes() {
+        let (database, _) = database();
+        let bytes = database.to_bytes();
+        assert_eq!(GameDatabase::from_bytes(&bytes), Ok(database));
+        assert_eq!(
+            GameDatabase::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(GameDatabaseError::Truncated)
+        );
+        assert_eq!(
+            GameDatabase::from_bytes(b"RAD1TBGN"),
+            Err(GameDatabaseError::InvalidMagic)
+        );
+    }
+}
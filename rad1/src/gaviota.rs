@@ -0,0 +1,345 @@
+use crate::score::Score;
+use crate::{CastleRights, ChessMove, Color, Piece, Position, PositionStatus};
+use gaviota_sys::{
+    tb_is_initialized, tb_probe_hard, tb_restart, tbcache_restart, tbpaths_add, tbpaths_done,
+    tbpaths_init, TB_compression_scheme, TB_pieces, TB_return_values, TB_sides, TB_squares,
+};
+use lazy_static::lazy_static;
+use std::convert::TryFrom;
+use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::os::raw::{c_char, c_int, c_uchar, c_uint};
+use std::path::Path;
+use std::sync::RwLock;
+
+// The most pieces of a position the tables hold
+const MAX_PIECES: usize = 5;
+// The pieces in libgtb's terms
+const PIECES: [(Piece, TB_pieces); 6] = [
+    (Piece::Pawn, TB_pieces::tb_PAWN),
+    (Piece::Knight, TB_pieces::tb_KNIGHT),
+    (Piece::Bishop, TB_pieces::tb_BISHOP),
+    (Piece::Rook, TB_pieces::tb_ROOK),
+    (Piece::Queen, TB_pieces::tb_QUEEN),
+    (Piece::King, TB_pieces::tb_KING),
+];
+// Memory of the library's cache of decompressed blocks, and the 128ths of
+// it kept for win/draw/loss lookups, which the engine doesn't make
+const CACHE_BYTES: usize = 32 * 1024 * 1024;
+const WDL_FRACTION: c_int = 8;
+// Separates the directories of a path, like `SyzygyPath`
+#[cfg(windows)]
+const SEPARATOR: char = ';';
+#[cfg(not(windows))]
+const SEPARATOR: char = ':';
+
+// The three-piece tables that come with libgtb. Every test opens these, so
+// tests running at the same time don't replace each other's tables.
+#[cfg(test)]
+pub(crate) const TEST_TABLES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/gaviota");
+
+lazy_static! {
+    // libgtb keeps one set of tables for the whole process. Probes hold the
+    // read lock, so the tables can't be swapped out from under them.
+    static ref LIBRARY: RwLock<Option<Library>> = RwLock::new(None);
+}
+
+// The path libgtb was started with and its list of the directories, which
+// has to outlive the tables
+struct Library {
+    path: String,
+    paths: *mut *const c_char,
+}
+
+// the list is only handed to libgtb while holding the write lock
+unsafe impl Send for Library {}
+unsafe impl Sync for Library {}
+
+/// Gaviota endgame tablebases, probed through libgtb.
+///
+/// They hold every position of up to five pieces with its distance to mate
+/// (DTM), so unlike the Syzygy tables they tell how long a
+/// mate takes rather than how to win under the fifty-move rule.
+///
+/// libgtb can only use one set of tables at a time: opening another replaces
+/// them, and tables opened earlier stop answering probes.
+pub struct Gaviota {
+    path: String,
+}
+
+impl Gaviota {
+    /// The compressed (`.gtb.cp4`) tables in the directories of `path`,
+    /// separated by `:` (`;` on Windows). Fails when one of them can't be
+    /// read.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_string_lossy().into_owned();
+        let mut directories = Vec::new();
+        for directory in path.split(SEPARATOR).filter(|d| !d.is_empty()) {
+            fs::read_dir(directory)?;
+            directories.push(
+                CString::new(directory)
+                    .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?,
+            );
+        }
+        let mut library = LIBRARY.write().unwrap();
+        if library.as_ref().map(|library| &library.path) != Some(&path) {
+            *library = Some(Library::start(path.clone(), &directories, library.take())?);
+        }
+        Ok(Self { path })
+    }
+
+    /// The distance to mate of a position from the side to move's point of
+    /// view, or a draw.
+    ///
+    /// None when the position has castling rights or more pieces than the
+    /// tables hold, a table is missing or other tables have been opened since.
+    pub fn probe(&self, position: &Position) -> Option<Score> {
+        let occupied =
+            position.color_combined(Color::White) | position.color_combined(Color::Black);
+        if occupied.popcnt() as usize > MAX_PIECES
+            || position.castle_rights(Color::White) != CastleRights::NoRights
+            || position.castle_rights(Color::Black) != CastleRights::NoRights
+        {
+            return None;
+        }
+        let (white_squares, white_pieces) = piece_list(position, Color::White);
+        let (black_squares, black_pieces) = piece_list(position, Color::Black);
+        let side_to_move = match position.side_to_move() {
+            Color::White => TB_sides::tb_WHITE_TO_MOVE,
+            Color::Black => TB_sides::tb_BLACK_TO_MOVE,
+        };
+        // libgtb wants the square the capturing pawn moves to
+        let en_passant = position
+            .en_passant()
+            .and_then(|square| square.forward(position.side_to_move()))
+            .map_or(TB_squares::tb_NOSQUARE as c_uint, |square| {
+                square.to_index() as c_uint
+            });
+        let (mut info, mut plies): (c_uint, c_uint) = (0, 0);
+        let library = LIBRARY.read().unwrap();
+        if library.as_ref().map(|library| &library.path) != Some(&self.path) {
+            return None;
+        }
+        // SAFETY: the lists end in the markers libgtb looks for, and the
+        // read lock keeps the library from restarting during the probe
+        let found = unsafe {
+            tb_probe_hard(
+                side_to_move as c_uint,
+                en_passant,
+                0,
+                white_squares.as_ptr(),
+                black_squares.as_ptr(),
+                white_pieces.as_ptr(),
+                black_pieces.as_ptr(),
+                &mut info,
+                &mut plies,
+            )
+        };
+        if found == 0 {
+            return None;
+        }
+        let winner = match TB_return_values(info) {
+            TB_return_values::tb_DRAW => return Some(Score::DRAW),
+            TB_return_values::tb_WMATE => Color::White,
+            TB_return_values::tb_BMATE => Color::Black,
+            _ => return None,
+        };
+        let plies = u8::try_from(plies).ok()?;
+        if winner == position.side_to_move() {
+            Some(Score::Mate(plies))
+        } else {
+            Some(Score::Mated(plies))
+        }
+    }
+
+    /// The moves the tables play from a won or lost position until mate,
+    /// the quickest mate for the winner and the slowest for the loser.
+    /// Empty for draws and positions the tables don't hold.
+    pub fn line(&self, position: &Position) -> Vec<ChessMove> {
+        let mut line = Vec::new();
+        let mut position = position.clone();
+        let mut score = match self.probe(&position) {
+            Some(score) if score.is_mate() => score,
+            _ => return line,
+        };
+        while position.status() == PositionStatus::Ongoing {
+            let mut best = None;
+            for chess_move in position.legal_moves() {
+                let after = position.make_move_new(chess_move);
+                let after_score = match after.status() {
+                    PositionStatus::Checkmate => Score::Mated(0),
+                    _ => match self.probe(&after) {
+                        Some(after_score) => after_score,
+                        None => return line,
+                    },
+                };
+                // a move keeps the score when the other side's distance is
+                // one ply shorter
+                if Score::from_tt((-after_score).into(), 1) == score {
+                    best = Some((chess_move, after, after_score));
+                    break;
+                }
+            }
+            let (chess_move, after, after_score) = match best {
+                Some(best) => best,
+                None => return line,
+            };
+            line.push(chess_move);
+            position = after;
+            score = after_score;
+        }
+        line
+    }
+}
+
+impl Library {
+    // (Re)start libgtb on the directories, replacing the running tables
+    fn start(path: String, directories: &[CString], running: Option<Library>) -> io::Result<Self> {
+        // SAFETY: libgtb copies the directory names, and the old list is only
+        // freed after the restart stops using it
+        unsafe {
+            let mut paths = tbpaths_init();
+            for directory in directories {
+                paths = tbpaths_add(paths, directory.as_ptr());
+            }
+            tb_restart(0, TB_compression_scheme::tb_CP4 as c_int, paths);
+            if let Some(running) = running {
+                tbpaths_done(running.paths);
+            }
+            if tb_is_initialized() == 0 {
+                tbpaths_done(paths);
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "libgtb could not be started",
+                ));
+            }
+            tbcache_restart(CACHE_BYTES, WDL_FRACTION);
+            Ok(Self { path, paths })
+        }
+    }
+}
+
+// The squares and pieces of a side in libgtb's terms, each list ending in
+// its marker
+fn piece_list(position: &Position, color: Color) -> (Vec<c_uint>, Vec<c_uchar>) {
+    let mut squares = Vec::with_capacity(MAX_PIECES + 1);
+    let mut pieces = Vec::with_capacity(MAX_PIECES + 1);
+    for &(piece, tb_piece) in PIECES.iter() {
+        for square in position.pieces(piece) & position.color_combined(color) {
+            squares.push(square.to_index() as c_uint);
+            pieces.push(tb_piece as c_uchar);
+        }
+    }
+    squares.push(TB_squares::tb_NOSQUARE as c_uint);
+    pieces.push(TB_pieces::tb_NOPIECE as c_uchar);
+    (squares, pieces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Gaviota, TEST_TABLES as TABLES};
+    use crate::score::Score;
+    use crate::{tbgen, Position, PositionStatus};
+    use std::str::FromStr;
+    use test_case::test_case;
+
+    // The position with the pieces on the squares, None when it's illegal
+    fn position(pieces: &[(usize, char)], side_to_move: char) -> Option<Position> {
+        let mut board = [None; 64];
+        for &(square, piece) in pieces {
+            if board[square].replace(piece).is_some() {
+                return None;
+            }
+        }
+        let ranks: Vec<String> = (0..8)
+            .rev()
+            .map(|rank| {
+                let mut text = String::new();
+                let mut empty = 0;
+                for piece in &board[rank * 8..rank * 8 + 8] {
+                    match piece {
+                        Some(piece) => {
+                            if empty > 0 {
+                                text.push_str(&empty.to_string());
+                                empty = 0;
+                            }
+                            text.push(*piece);
+                        }
+                        None => empty += 1,
+                    }
+                }
+                if empty > 0 {
+                    text.push_str(&empty.to_string());
+                }
+                text
+            })
+            .collect();
+        Position::from_str(&format!("{} {} - - 0 1", ranks.join("/"), side_to_move)).ok()
+    }
+
+    #[test_case('Q'; "queen")]
+    #[test_case('R'; "rook")]
+    #[test_case('P'; "pawn")]
+    fn agrees_with_the_built_in_tables(piece: char) {
+        let tables = Gaviota::open(TABLES).unwrap();
+        let white_king = 18;
+        for black_king in 0..64 {
+            for square in 0..64 {
+                for &side_to_move in ['w', 'b'].iter() {
+                    let pieces = [(white_king, 'K'), (black_king, 'k'), (square, piece)];
+                    let position = match position(&pieces, side_to_move) {
+                        Some(position) => position,
+                        None => continue,
+                    };
+                    let built_in = match tbgen::probe(&position) {
+                        Some(Score::Eval(_)) => Score::DRAW,
+                        Some(score) => score,
+                        None => continue,
+                    };
+                    assert_eq!(tables.probe(&position), Some(built_in), "{}", position);
+                }
+            }
+        }
+    }
+
+    #[test_case("k7/8/1K6/8/8/8/8/2R5 w - - 0 1", Score::Mate(1); "rook mates in one")]
+    #[test_case("8/8/8/8/8/1k6/8/K1r5 w - - 0 1", Score::Mated(0); "black rook mates")]
+    #[test_case("8/8/8/8/8/2k5/1R6/4K3 b - - 0 1", Score::DRAW; "rook gets taken")]
+    #[test_case("8/8/8/4k3/8/8/3BK3/8 w - - 0 1", Score::DRAW; "bishop can't mate")]
+    fn probes(fen: &str, expected: Score) {
+        let tables = Gaviota::open(TABLES).unwrap();
+        let position = Position::from_str(fen).unwrap();
+        assert_eq!(tables.probe(&position), Some(expected));
+    }
+
+    #[test]
+    fn plays_mates_out() {
+        let tables = Gaviota::open(TABLES).unwrap();
+        let position = Position::from_str("8/8/8/4k3/8/8/8/R3K3 b - - 0 1").unwrap();
+        let plies = match tables.probe(&position) {
+            Some(Score::Mated(plies)) => plies,
+            score => panic!("{:?}", score),
+        };
+        let line = tables.line(&position);
+        assert_eq!(line.len(), usize::from(plies));
+        let end = line
+            .iter()
+            .fold(position, |position, &m| position.make_move_new(m));
+        assert_eq!(end.status(), PositionStatus::Checkmate);
+        let draw = Position::from_str("8/8/8/4k3/8/8/3BK3/8 w - - 0 1").unwrap();
+        assert!(tables.line(&draw).is_empty());
+    }
+
+    #[test]
+    fn positions_outside_the_tables_are_not_probed() {
+        let tables = Gaviota::open(TABLES).unwrap();
+        let castling = Position::from_str("8/8/8/4k3/8/8/8/R3K3 w Q - 0 1").unwrap();
+        assert_eq!(tables.probe(&castling), None);
+        // only the three-piece tables are there
+        let four_pieces = Position::from_str("8/8/8/4k3/8/8/3P4/R3K3 w - - 0 1").unwrap();
+        assert_eq!(tables.probe(&four_pieces), None);
+        assert_eq!(tables.probe(&Position::default()), None);
+        assert!(Gaviota::open("/nonexistent").is_err());
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// The subset of JSON the crate reads, whose numbers are whole
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    Object(BTreeMap<String, Json>),
+}
+
+impl Json {
+    /// The field of an object, or None for other values
+    pub(crate) fn field(&self, name: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.get(name),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_i64(&self) -> Option<i64> {
+        match self {
+            Json::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_u64(&self) -> Option<u64> {
+        self.as_i64().and_then(|value| u64::try_from(value).ok())
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+// Written without whitespace, so a value always fits on one line
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(value) => write!(f, "{}", value),
+            Json::String(value) => write!(f, "{}", quote(value)),
+            Json::Array(values) => {
+                let values: Vec<String> = values.iter().map(Json::to_string).collect();
+                write!(f, "[{}]", values.join(","))
+            }
+            Json::Object(fields) => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(name, value)| format!("{}:{}", quote(name), value))
+                    .collect();
+                write!(f, "{{{}}}", fields.join(","))
+            }
+        }
+    }
+}
+
+/// The string as a JSON string in quotes, with line breaks escaped so it
+/// stays on one line
+pub(crate) fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Parse a whole text as one JSON value, the error tells what's wrong and where
+pub(crate) fn parse(text: &str) -> Result<Json, String> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        offset: 0,
+    };
+    let json = parser.value()?;
+    parser.skip_whitespace();
+    if parser.offset < parser.chars.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    Ok(json)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    offset: usize,
+}
+
+impl Parser {
+    fn error(&self, reason: &str) -> String {
+        format!("{} at character {}", reason, self.offset)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .chars
+            .get(self.offset)
+            .map_or(false, |c| c.is_whitespace())
+        {
+            self.offset += 1;
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        let c = self.chars.get(self.offset).copied();
+        self.offset += 1;
+        c
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(self.error(&format!("expected '{}'", expected))),
+        }
+    }
+
+    // The rest of a literal like `true` after its first letter
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        let start = self.offset - 1;
+        let end = start + word.len();
+        if end <= self.chars.len() && self.chars[start..end].iter().copied().eq(word.chars()) {
+            self.offset = end;
+            Ok(value)
+        } else {
+            Err(self.error("expected a value"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        match self.next() {
+            Some('{') => {
+                let mut fields = BTreeMap::new();
+                self.skip_whitespace();
+                if self.chars.get(self.offset) == Some(&'}') {
+                    self.offset += 1;
+                    return Ok(Json::Object(fields));
+                }
+                loop {
+                    let name = match self.next() {
+                        Some('"') => self.string()?,
+                        _ => return Err(self.error("expected a field name")),
+                    };
+                    self.expect(':')?;
+                    fields.insert(name, self.value()?);
+                    match self.next() {
+                        Some(',') => continue,
+                        Some('}') => return Ok(Json::Object(fields)),
+                        _ => return Err(self.error("expected ',' or '}'")),
+                    }
+                }
+            }
+            Some('[') => {
+                let mut values = Vec::new();
+                self.skip_whitespace();
+                if self.chars.get(self.offset) == Some(&']') {
+                    self.offset += 1;
+                    return Ok(Json::Array(values));
+                }
+                loop {
+                    values.push(self.value()?);
+                    match self.next() {
+                        Some(',') => continue,
+                        Some(']') => return Ok(Json::Array(values)),
+                        _ => return Err(self.error("expected ',' or ']'")),
+                    }
+                }
+            }
+            Some('"') => self.string().map(Json::String),
+            Some('t') => self.literal("true", Json::Bool(true)),
+            Some('f') => self.literal("false", Json::Bool(false)),
+            Some('n') => self.literal("null", Json::Null),
+            Some(c) if c.is_ascii_digit() || c == '-' => {
+                let start = self.offset - 1;
+                while self
+                    .chars
+                    .get(self.offset)
+                    .map_or(false, char::is_ascii_digit)
+                {
+                    self.offset += 1;
+                }
+                let digits: String = self.chars[start..self.offset].iter().collect();
+                digits
+                    .parse()
+                    .map(Json::Number)
+                    .map_err(|_| self.error("number out of range"))
+            }
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    // The rest of a string after its opening quote
+    fn string(&mut self) -> Result<String, String> {
+        let mut value = String::new();
+        loop {
+            let c = self.chars.get(self.offset).copied();
+            self.offset += 1;
+            match c {
+                Some('"') => return Ok(value),
+                Some('\\') => {
+                    let escaped = self.chars.get(self.offset).copied();
+                    self.offset += 1;
+                    match escaped {
+                        Some(c @ '"') | Some(c @ '\\') | Some(c @ '/') => value.push(c),
+                        Some('n') => value.push('\n'),
+                        Some('r') => value.push('\r'),
+                        Some('t') => value.push('\t'),
+                        Some('u') => {
+                            let hex: String = self.chars.iter().skip(self.offset).take(4).collect();
+                            let c = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+                            match c {
+                                Some(c) if hex.len() == 4 => value.push(c),
+                                _ => return Err(self.error("unsupported escape")),
+                            }
+                            self.offset += 4;
+                        }
+                        _ => return Err(self.error("unsupported escape")),
+                    }
+                }
+                Some(c) => value.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, quote, Json};
+    use test_case::test_case;
+
+    #[test]
+    fn round_trip() {
+        let text = "{\"id\":7,\"method\":\"setPosition\",\"params\":{\"moves\":[\"e2e4\",\"e7e5\"],\"ok\":true,\"x\":null}}";
+        let json = parse(text).unwrap();
+        assert_eq!(json.field("id").and_then(Json::as_u64), Some(7));
+        assert_eq!(parse("-32700").unwrap().as_i64(), Some(-32700));
+        assert_eq!(json.to_string(), text);
+    }
+
+    #[test]
+    fn escapes() {
+        let json = parse("\"a \\\"b\\\"\\n\\u00e9\"").unwrap();
+        assert_eq!(json.as_str(), Some("a \"b\"\né"));
+        assert_eq!(quote("a \"b\"\n\u{1}"), "\"a \\\"b\\\"\\n\\u0001\"");
+    }
+
+    #[test_case("{\"depth\": 5"; "unterminated object")]
+    #[test_case("tru"; "unknown literal")]
+    #[test_case("[1] 2"; "trailing characters")]
+    #[test_case("-"; "lone minus")]
+    #[test_case("\"\\x\""; "unsupported escape")]
+    fn invalid(text: &str) {
+        assert!(parse(text).is_err());
+    }
+}
@@ -0,0 +1,687 @@
+use crate::agent::AlphaBetaChessAgent;
+use crate::json::{self, quote, Json};
+use crate::search::SearchResult;
+use crate::{fen, pgn, Action, ChessGame, ChessMove, Color};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+// Error codes of the JSON-RPC 2.0 specification
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+const INTERNAL_ERROR: i32 = -32603;
+// Server errors, for requests made while a search runs, searches of finished
+// games, new games past the limit and requests for games that aren't open
+const BUSY: i32 = -32000;
+const GAME_OVER: i32 = -32001;
+const TOO_MANY_GAMES: i32 = -32002;
+const UNKNOWN_GAME: i32 = -32003;
+
+type Output = Arc<Mutex<Box<dyn Write + Send>>>;
+type AgentFactory = Arc<dyn Fn() -> AlphaBetaChessAgent + Send + Sync>;
+
+// The code and message of an error response
+type RpcError = (i32, String);
+
+// A search the worker thread runs and answers
+struct Job {
+    id: Option<Json>,
+    depth: u8,
+    play: bool,
+}
+
+/// An engine speaking JSON-RPC 2.0 over newline-delimited JSON, every
+/// request is one line and so is every response and notification.
+///
+/// The methods are
+/// - `setPosition` with a `fen`, the starting position by default, and
+///   `moves` played from it in UCI notation
+/// - `analyze` searches the position, sending an `info` notification after
+///   every iteration, and answers with the best move, score and principal variation
+/// - `play` searches the position like `analyze` and plays the best move
+/// - `stop` ends the running search, which still answers with what it found so far
+/// - `getBoard` answers with the FEN, the moves played and the legal moves
+///
+/// `analyze` and `play` take an optional `depth`. Searches run on a worker
+/// thread, so `stop` and `getBoard` are answered right away while they run,
+/// and the requests that would change the position get an error instead.
+///
+/// The server can hold several games at once, up to
+/// [`Server::with_max_games`]. Game 0 is open from the start and the methods
+/// above act on it unless they get the number of another game as `game`.
+/// - `newGame` opens a game, from a `fen` and `moves` like `setPosition`,
+///   and answers with its number and board
+/// - `closeGame` stops the game's search and closes it
+/// - `listGames` answers with the number, FEN and state of every open game
+///
+/// Every game has its own worker thread and agent, and so its own
+/// transposition table, so the searches of different games run at the same
+/// time and never see each other's entries. `info` notifications carry the
+/// number of their game.
+pub struct Server {
+    agent: AgentFactory,
+    output: Output,
+    depth: u8,
+    max_games: usize,
+    next_game: AtomicU64,
+    sessions: Mutex<BTreeMap<u64, Session>>,
+}
+
+// An open game of the server and the worker thread searching it
+struct Session {
+    game: Arc<Mutex<ChessGame>>,
+    stop: Arc<AtomicBool>,
+    busy: Arc<AtomicBool>,
+    jobs: Sender<Job>,
+    worker: JoinHandle<()>,
+}
+
+impl Server {
+    /// A server searching to `depth` unless a request asks for another one,
+    /// holding one game at a time.
+    ///
+    /// Every game gets an agent built with `agent` when it's opened, with
+    /// the game's stop flag.
+    pub fn new<F, W>(depth: u8, agent: F, output: W) -> Self
+    where
+        F: Fn() -> AlphaBetaChessAgent + Send + Sync + 'static,
+        W: Write + Send + 'static,
+    {
+        let agent: AgentFactory = Arc::new(agent);
+        let output: Output = Arc::new(Mutex::new(Box::new(output)));
+        let mut sessions = BTreeMap::new();
+        sessions.insert(0, Session::open(0, ChessGame::default(), agent(), &output));
+        Self {
+            agent,
+            output,
+            depth,
+            max_games: 1,
+            next_game: AtomicU64::new(1),
+            sessions: Mutex::new(sessions),
+        }
+    }
+
+    /// Let clients keep up to `max_games` games open at once, game 0 included
+    pub fn with_max_games(mut self, max_games: usize) -> Self {
+        self.max_games = max_games;
+        self
+    }
+
+    /// Answer one line of input. Notifications, requests without an `id`,
+    /// are never answered, not even with an error.
+    pub fn handle(&self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        let request = match json::parse(line) {
+            Ok(request) => request,
+            Err(reason) => {
+                let message = format!("parse error, {}", reason);
+                return send_error(&self.output, &Json::Null, (PARSE_ERROR, message));
+            }
+        };
+        let id = request.field("id").cloned();
+        let params = request.field("params").cloned().unwrap_or(Json::Null);
+        let result = match request.field("method").and_then(Json::as_str) {
+            Some("newGame") => self.new_game(&params),
+            Some("closeGame") => self.close_game(&params),
+            Some("listGames") => Ok(Some(self.list_games())),
+            Some(method @ ("setPosition" | "analyze" | "play" | "stop" | "getBoard")) => {
+                self.game_request(method, id.clone(), &params)
+            }
+            Some(method) => Err((METHOD_NOT_FOUND, format!("unknown method '{}'", method))),
+            None => Err((INVALID_REQUEST, String::from("missing method"))),
+        };
+        match (id, result) {
+            (Some(id), Ok(Some(result))) => send_result(&self.output, &id, &result),
+            (Some(id), Err(error)) => send_error(&self.output, &id, error),
+            _ => {}
+        }
+    }
+
+    /// Wait until the running searches have answered and end the workers
+    pub fn finish(self) {
+        let sessions = self.sessions.into_inner().unwrap();
+        for session in sessions.into_values() {
+            session.finish();
+        }
+    }
+
+    // A request for one of the open games
+    fn game_request(
+        &self,
+        method: &str,
+        id: Option<Json>,
+        params: &Json,
+    ) -> Result<Option<String>, RpcError> {
+        let number = game_number(params)?;
+        let sessions = self.sessions.lock().unwrap();
+        let session = sessions.get(&number).ok_or_else(|| unknown_game(number))?;
+        match method {
+            "setPosition" => session.set_position(params),
+            "analyze" => session.start(id, params, false, self.depth),
+            "play" => session.start(id, params, true, self.depth),
+            "stop" => session.stop(),
+            _ => Ok(Some(board(&session.game.lock().unwrap()))),
+        }
+    }
+
+    fn new_game(&self, params: &Json) -> Result<Option<String>, RpcError> {
+        let game = parse_game(params)?;
+        let mut sessions = self.sessions.lock().unwrap();
+        if sessions.len() >= self.max_games {
+            let message = format!(
+                "{} games are open, the most the server holds, close one first",
+                sessions.len()
+            );
+            return Err((TOO_MANY_GAMES, message));
+        }
+        let number = self.next_game.fetch_add(1, Ordering::SeqCst);
+        let answer = format!("{{\"game\": {}, \"board\": {}}}", number, board(&game));
+        sessions.insert(
+            number,
+            Session::open(number, game, (self.agent)(), &self.output),
+        );
+        Ok(Some(answer))
+    }
+
+    fn close_game(&self, params: &Json) -> Result<Option<String>, RpcError> {
+        let number = game_number(params)?;
+        let session = self
+            .sessions
+            .lock()
+            .unwrap()
+            .remove(&number)
+            .ok_or_else(|| unknown_game(number))?;
+        let searching = session.busy.load(Ordering::SeqCst);
+        session.stop.store(true, Ordering::SeqCst);
+        // the stopped search still answers before the worker ends
+        session.finish();
+        Ok(Some(format!(
+            "{{\"game\": {}, \"stopped\": {}}}",
+            number, searching
+        )))
+    }
+
+    fn list_games(&self) -> String {
+        let games: Vec<String> = self
+            .sessions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(number, session)| {
+                format!(
+                    "{{\"game\": {}, \"fen\": {}, \"searching\": {}}}",
+                    number,
+                    quote(&session.game.lock().unwrap().current_position().to_string()),
+                    session.busy.load(Ordering::SeqCst)
+                )
+            })
+            .collect();
+        format!("{{\"games\": [{}]}}", games.join(", "))
+    }
+}
+
+impl Session {
+    fn open(number: u64, game: ChessGame, agent: AlphaBetaChessAgent, output: &Output) -> Self {
+        let game = Arc::new(Mutex::new(game));
+        let stop = Arc::new(AtomicBool::new(false));
+        let busy = Arc::new(AtomicBool::new(false));
+        let (jobs, received) = mpsc::channel::<Job>();
+        let worker = {
+            let (game, stop, busy) = (game.clone(), stop.clone(), busy.clone());
+            let output = output.clone();
+            thread::spawn(move || {
+                let mut agent = agent.with_stop_flag(stop);
+                for job in received {
+                    agent.set_depth(job.depth);
+                    run(&agent, number, &job, &game, &busy, &output);
+                }
+            })
+        };
+        Self {
+            game,
+            stop,
+            busy,
+            jobs,
+            worker,
+        }
+    }
+
+    fn finish(self) {
+        drop(self.jobs);
+        let _ = self.worker.join();
+    }
+
+    fn check_idle(&self) -> Result<(), RpcError> {
+        if self.busy.load(Ordering::SeqCst) {
+            Err((BUSY, String::from("a search is running, stop it first")))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn set_position(&self, params: &Json) -> Result<Option<String>, RpcError> {
+        self.check_idle()?;
+        let game = parse_game(params)?;
+        let board = board(&game);
+        *self.game.lock().unwrap() = game;
+        Ok(Some(board))
+    }
+
+    // Hand a search to the worker, which answers once it's done
+    fn start(
+        &self,
+        id: Option<Json>,
+        params: &Json,
+        play: bool,
+        default_depth: u8,
+    ) -> Result<Option<String>, RpcError> {
+        self.check_idle()?;
+        let depth = match params.field("depth") {
+            None | Some(Json::Null) => default_depth,
+            Some(depth) => match depth.as_u64() {
+                Some(depth @ 1..=255) => depth as u8,
+                _ => return Err(invalid_params("'depth' isn't a number from 1 to 255")),
+            },
+        };
+        let game = self.game.lock().unwrap();
+        if game.result().is_some() || !game.current_position().has_legal_moves() {
+            return Err((GAME_OVER, String::from("the game is over")));
+        }
+        self.stop.store(false, Ordering::SeqCst);
+        self.busy.store(true, Ordering::SeqCst);
+        let job = Job { id, depth, play };
+        // only fails once the worker has died, and then no search will answer
+        self.jobs.send(job).map_err(|_| {
+            self.busy.store(false, Ordering::SeqCst);
+            (
+                INTERNAL_ERROR,
+                String::from("the game's search thread has stopped"),
+            )
+        })?;
+        Ok(None)
+    }
+
+    fn stop(&self) -> Result<Option<String>, RpcError> {
+        let searching = self.busy.load(Ordering::SeqCst);
+        self.stop.store(true, Ordering::SeqCst);
+        Ok(Some(format!("{{\"stopped\": {}}}", searching)))
+    }
+}
+
+fn invalid_params(message: &str) -> RpcError {
+    (INVALID_PARAMS, message.to_string())
+}
+
+fn unknown_game(number: u64) -> RpcError {
+    (UNKNOWN_GAME, format!("game {} isn't open", number))
+}
+
+// The `game` a request is for, game 0 without one
+fn game_number(params: &Json) -> Result<u64, RpcError> {
+    match params.field("game") {
+        None | Some(Json::Null) => Ok(0),
+        Some(number) => number
+            .as_u64()
+            .ok_or_else(|| invalid_params("'game' isn't a game number")),
+    }
+}
+
+// The game of the `fen` and `moves` of a request
+fn parse_game(params: &Json) -> Result<ChessGame, RpcError> {
+    let mut game = match params.field("fen") {
+        None | Some(Json::Null) => ChessGame::default(),
+        Some(Json::String(text)) => {
+            fen::parse_game(text).map_err(|error| invalid_params(&error.to_string()))?
+        }
+        Some(_) => return Err(invalid_params("'fen' isn't a string")),
+    };
+    let moves = match params.field("moves") {
+        None | Some(Json::Null) => &[],
+        Some(moves) => moves
+            .as_array()
+            .ok_or_else(|| invalid_params("'moves' isn't a list"))?,
+    };
+    for value in moves {
+        let chess_move = value
+            .as_str()
+            .and_then(|text| ChessMove::from_str(text).ok())
+            .ok_or_else(|| invalid_params(&format!("{} isn't a move like e2e4", value)))?;
+        if !game.current_position().legal(chess_move) {
+            return Err(invalid_params(&format!("{} isn't legal", chess_move)));
+        }
+        game.take_action(Action::MakeMove(chess_move));
+    }
+    Ok(game)
+}
+
+fn run(
+    agent: &AlphaBetaChessAgent,
+    number: u64,
+    job: &Job,
+    game: &Mutex<ChessGame>,
+    busy: &AtomicBool,
+    output: &Output,
+) {
+    let mut current = game.lock().unwrap().clone();
+    let result = agent.search_game_with_info(&current, |result| {
+        let info = format!(
+            "{{\"jsonrpc\": \"2.0\", \"method\": \"info\", \"params\": {{\"game\": {}, {}}}}}",
+            number,
+            search_fields(result)
+        );
+        send(output, &info);
+    });
+    let answer = if job.play {
+        let san = pgn::san(&current.current_position(), result.best_move);
+        current.take_action(Action::MakeMove(result.best_move));
+        let answer = format!(
+            "{{\"move\": \"{}\", \"san\": {}, \"score\": {}, \"board\": {}}}",
+            result.best_move,
+            quote(&san),
+            result.score.to_json(),
+            board(&current)
+        );
+        *game.lock().unwrap() = current;
+        answer
+    } else {
+        format!("{{{}}}", search_fields(&result))
+    };
+    // idle before answering, so a request sent on the answer isn't refused
+    busy.store(false, Ordering::SeqCst);
+    if let Some(id) = &job.id {
+        send_result(output, id, &answer);
+    }
+}
+
+fn moves_json(moves: &[ChessMove]) -> String {
+    let moves: Vec<String> = moves.iter().map(|m| format!("\"{}\"", m)).collect();
+    format!("[{}]", moves.join(", "))
+}
+
+// The fields of the JSON object of a search result, without its braces
+fn search_fields(result: &SearchResult) -> String {
+    format!(
+        "\"bestMove\": \"{}\", \"score\": {}, \"bound\": \"{}\", \"depth\": {}, \"nodes\": {}, \"pv\": {}",
+        result.best_move,
+        result.score.to_json(),
+        result.bound.as_str(),
+        result.depth,
+        result.nodes,
+        moves_json(&result.pv)
+    )
+}
+
+fn board(game: &ChessGame) -> String {
+    let position = game.current_position();
+    format!(
+        "{{\"fen\": {}, \"sideToMove\": \"{}\", \"moves\": {}, \"legalMoves\": {}, \"result\": \"{}\"}}",
+        quote(&position.to_string()),
+        match position.side_to_move() {
+            Color::White => "white",
+            Color::Black => "black",
+        },
+        moves_json(&game.moves()),
+        moves_json(&position.legal_moves()),
+        pgn::result_token(game.result())
+    )
+}
+
+fn send(output: &Output, message: &str) {
+    let mut output = output.lock().unwrap();
+    // a client that went away can't be told about it
+    let _ = writeln!(output, "{}", message).and_then(|_| output.flush());
+}
+
+fn send_result(output: &Output, id: &Json, result: &str) {
+    let message = format!(
+        "{{\"jsonrpc\": \"2.0\", \"id\": {}, \"result\": {}}}",
+        id, result
+    );
+    send(output, &message);
+}
+
+fn send_error(output: &Output, id: &Json, (code, message): RpcError) {
+    let message = format!(
+        "{{\"jsonrpc\": \"2.0\", \"id\": {}, \"error\": {{\"code\": {}, \"message\": {}}}}}",
+        id,
+        code,
+        quote(&message)
+    );
+    send(output, &message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Job, Server, Session, INTERNAL_ERROR};
+    use crate::agent;
+    use crate::json::{self, Json};
+    use crate::tt::TranspositionTable;
+    use crate::ChessGame;
+    use std::io::{self, Write};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::mpsc;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            let written = self.0.lock().unwrap().write(bytes)?;
+            // a slow client, the answer is out before the write returns
+            if bytes.windows(8).any(|part| part == b"\"result\"") {
+                thread::sleep(Duration::from_millis(50));
+            }
+            Ok(written)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // Every line the server wrote for the requests, once it's finished
+    fn exchange(requests: &[&str]) -> Vec<Json> {
+        exchange_with_games(1, requests)
+    }
+
+    fn exchange_with_games(max_games: usize, requests: &[&str]) -> Vec<Json> {
+        let buffer = Buffer::default();
+        let server = Server::new(
+            3,
+            || agent::alpha_beta_agent(3, TranspositionTable::new(100_000)),
+            buffer.clone(),
+        )
+        .with_max_games(max_games);
+        for request in requests {
+            server.handle(request);
+        }
+        server.finish();
+        let text = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        text.lines()
+            .map(|line| json::parse(line).unwrap())
+            .collect()
+    }
+
+    fn error_code(response: &Json) -> Option<i64> {
+        response.field("error")?.field("code")?.as_i64()
+    }
+
+    // The response to the request with the id
+    fn response(responses: &[Json], id: i64) -> &Json {
+        responses
+            .iter()
+            .find(|response| response.field("id") == Some(&Json::Number(id)))
+            .unwrap()
+    }
+
+    #[test]
+    fn set_position_and_get_board() {
+        let responses = exchange(&[
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "setPosition", "params": {"moves": ["e2e4", "e7e5"]}}"#,
+            r#"{"jsonrpc": "2.0", "id": "board", "method": "getBoard"}"#,
+        ]);
+        assert_eq!(responses.len(), 2);
+        let board = responses[1].field("result").unwrap();
+        assert_eq!(
+            responses[1].field("id").and_then(Json::as_str),
+            Some("board")
+        );
+        let fen = board.field("fen").and_then(Json::as_str).unwrap();
+        assert!(fen.starts_with("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq -"));
+        assert_eq!(
+            board.field("moves").and_then(Json::as_array).unwrap().len(),
+            2
+        );
+        assert_eq!(
+            board
+                .field("legalMoves")
+                .and_then(Json::as_array)
+                .unwrap()
+                .len(),
+            29
+        );
+    }
+
+    #[test]
+    fn play_makes_the_best_move() {
+        let responses = exchange(&[
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "setPosition", "params": {"fen": "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"}}"#,
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "play", "params": {"depth": 2}}"#,
+        ]);
+        let info = &responses[1];
+        assert_eq!(info.field("method").and_then(Json::as_str), Some("info"));
+        let result = responses.last().unwrap().field("result").unwrap();
+        assert_eq!(result.field("move").and_then(Json::as_str), Some("a1a8"));
+        assert_eq!(result.field("san").and_then(Json::as_str), Some("Ra8#"));
+        let board = result.field("board").unwrap();
+        assert_eq!(board.field("result").and_then(Json::as_str), Some("1-0"));
+    }
+
+    #[test]
+    fn errors() {
+        let responses = exchange(&[
+            "{\"id\": 1",
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "castle"}"#,
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "setPosition", "params": {"fen": "8/8/8 w - - 0 1"}}"#,
+            r#"{"jsonrpc": "2.0", "id": 4, "method": "setPosition", "params": {"moves": ["e2e5"]}}"#,
+            r#"{"jsonrpc": "2.0", "method": "castle"}"#,
+        ]);
+        let codes: Vec<Option<i64>> = responses.iter().map(error_code).collect();
+        assert_eq!(
+            codes,
+            vec![Some(-32700), Some(-32601), Some(-32602), Some(-32602)]
+        );
+        assert_eq!(responses[0].field("id"), Some(&Json::Null));
+    }
+
+    #[test]
+    fn searches_fail_once_the_worker_is_gone() {
+        let (jobs, received) = mpsc::channel::<Job>();
+        drop(received);
+        let session = Session {
+            game: Arc::new(Mutex::new(ChessGame::default())),
+            stop: Arc::new(AtomicBool::new(false)),
+            busy: Arc::new(AtomicBool::new(false)),
+            jobs,
+            worker: thread::spawn(|| {}),
+        };
+        let error = session.start(None, &Json::Null, false, 3).unwrap_err();
+        assert_eq!(error.0, INTERNAL_ERROR);
+        assert!(session.check_idle().is_ok());
+    }
+
+    #[test]
+    fn stop_ends_the_search() {
+        let responses = exchange(&[
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "analyze", "params": {"depth": 64}}"#,
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "setPosition"}"#,
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "stop"}"#,
+        ]);
+        let response = |id| response(&responses, id);
+        assert_eq!(error_code(response(2)), Some(-32000));
+        let stopped = response(3).field("result").unwrap().field("stopped");
+        assert_eq!(stopped, Some(&Json::Bool(true)));
+        let analysis = response(1).field("result").unwrap();
+        assert!(analysis.field("depth").and_then(Json::as_u64).unwrap() < 64);
+    }
+
+    #[test]
+    fn games_are_searched_side_by_side() {
+        let responses = exchange_with_games(
+            2,
+            &[
+                r#"{"jsonrpc": "2.0", "id": 1, "method": "analyze", "params": {"depth": 64}}"#,
+                r#"{"jsonrpc": "2.0", "id": 2, "method": "newGame", "params": {"fen": "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"}}"#,
+                r#"{"jsonrpc": "2.0", "id": 3, "method": "play", "params": {"game": 1, "depth": 2}}"#,
+                r#"{"jsonrpc": "2.0", "id": 4, "method": "listGames"}"#,
+                r#"{"jsonrpc": "2.0", "id": 5, "method": "newGame"}"#,
+                r#"{"jsonrpc": "2.0", "id": 6, "method": "closeGame", "params": {"game": 0}}"#,
+                r#"{"jsonrpc": "2.0", "id": 7, "method": "getBoard"}"#,
+                r#"{"jsonrpc": "2.0", "id": 8, "method": "getBoard", "params": {"game": 1}}"#,
+            ],
+        );
+        let result = |id| response(&responses, id).field("result").unwrap();
+        assert_eq!(result(2).field("game").and_then(Json::as_u64), Some(1));
+        assert_eq!(result(3).field("move").and_then(Json::as_str), Some("a1a8"));
+        let games = result(4).field("games").and_then(Json::as_array).unwrap();
+        let numbers: Vec<Option<u64>> = games
+            .iter()
+            .map(|game| game.field("game").and_then(Json::as_u64))
+            .collect();
+        assert_eq!(numbers, vec![Some(0), Some(1)]);
+        assert_eq!(games[0].field("searching"), Some(&Json::Bool(true)));
+        assert_eq!(error_code(response(&responses, 5)), Some(-32002));
+        assert_eq!(result(6).field("stopped"), Some(&Json::Bool(true)));
+        // the closed game's search still answered
+        assert!(result(1).field("depth").and_then(Json::as_u64).unwrap() < 64);
+        assert_eq!(error_code(response(&responses, 7)), Some(-32003));
+        // the other game stays open, its move may still be on the way so the
+        // board after it is the one of the play answer
+        assert!(result(8).field("fen").is_some());
+        let board = result(3).field("board").unwrap();
+        assert_eq!(board.field("result").and_then(Json::as_str), Some("1-0"));
+        // every info notification tells its game
+        assert!(responses
+            .iter()
+            .filter(|response| response.field("method").and_then(Json::as_str) == Some("info"))
+            .all(|info| info.field("params").unwrap().field("game").is_some()));
+    }
+
+    #[test]
+    fn searches_start_again_as_soon_as_answered() {
+        let buffer = Buffer::default();
+        let server = Server::new(
+            2,
+            || agent::alpha_beta_agent(2, TranspositionTable::new(100_000)),
+            buffer.clone(),
+        );
+        server.handle(r#"{"jsonrpc": "2.0", "id": 1, "method": "analyze"}"#);
+        let answered = || {
+            let text = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+            text.contains("\"id\": 1, \"result\"")
+        };
+        while !answered() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        server.handle(r#"{"jsonrpc": "2.0", "id": 2, "method": "analyze"}"#);
+        server.finish();
+        let text = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let responses: Vec<Json> = text
+            .lines()
+            .map(|line| json::parse(line).unwrap())
+            .collect();
+        assert!(response(&responses, 2).field("result").is_some());
+    }
+}
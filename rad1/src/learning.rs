@@ -0,0 +1,229 @@
+use crate::{ChessGame, Color, GameResult};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+/// Positions reached in the first this many plies of a game are learned
+pub const LEARNING_PLIES: usize = 20;
+// Games a position needs before its results count, one loss is bad luck
+const MIN_GAMES: u32 = 2;
+// Penalty of a position that lost every game, in evaluation units
+const MAX_PENALTY: i16 = 10;
+
+/// How the games through a position went for the side that moved into it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LearnedResults {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl LearnedResults {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// How much less the position is worth to the side that moved into it,
+    /// in evaluation units. Positions that lost more games than they won get
+    /// up to a pawn less, once they were played often enough.
+    pub fn penalty(&self) -> i16 {
+        let games = self.games();
+        if games < MIN_GAMES || self.losses <= self.wins {
+            return 0;
+        }
+        let share = i64::from(self.losses - self.wins) * i64::from(MAX_PENALTY) / i64::from(games);
+        share as i16
+    }
+}
+
+/// The results of the engine's own games, kept for the positions it moved
+/// into early in the game so it can steer away from the openings it keeps
+/// losing with. Positions are keyed by their hash, so transpositions share
+/// their results.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpeningLearning {
+    positions: BTreeMap<u64, LearnedResults>,
+}
+
+/// The reason a learning file couldn't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LearningError {
+    /// A line that isn't a hash followed by wins, draws and losses
+    InvalidLine(String),
+}
+
+impl fmt::Display for LearningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LearningError::InvalidLine(line) => write!(f, "invalid line '{}'", line),
+        }
+    }
+}
+
+impl Error for LearningError {}
+
+impl OpeningLearning {
+    /// The results of the games through the position with the hash
+    pub fn results(&self, hash: u64) -> Option<LearnedResults> {
+        self.positions.get(&hash).copied()
+    }
+
+    /// The penalty of the position with the hash for the side that moved
+    /// into it, see [`LearnedResults::penalty`]
+    pub fn penalty(&self, hash: u64) -> i16 {
+        self.positions.get(&hash).map_or(0, LearnedResults::penalty)
+    }
+
+    /// Number of positions with results
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Learn from a finished game the engine played as `color`: every
+    /// position it moved into in the first [`LEARNING_PLIES`] plies gets the
+    /// result. Games still being played teach nothing.
+    pub fn record_game(&mut self, game: &ChessGame, color: Color) {
+        let winner = match game.result() {
+            Some(GameResult::WhiteCheckmates) | Some(GameResult::BlackResigns) => {
+                Some(Color::White)
+            }
+            Some(GameResult::BlackCheckmates) | Some(GameResult::WhiteResigns) => {
+                Some(Color::Black)
+            }
+            Some(_) => None,
+            None => return,
+        };
+        let mut position = game.start_position();
+        for chess_move in game.moves().into_iter().take(LEARNING_PLIES) {
+            let mover = position.side_to_move();
+            position = position.make_move_new(chess_move);
+            if mover != color {
+                continue;
+            }
+            let results = self.positions.entry(position.get_hash()).or_default();
+            match winner {
+                Some(winner) if winner == color => results.wins += 1,
+                Some(_) => results.losses += 1,
+                None => results.draws += 1,
+            }
+        }
+    }
+
+    /// The results as lines of a hexadecimal hash followed by the wins,
+    /// draws and losses of the side that moved into the position
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("# hash wins draws losses\n");
+        for (hash, results) in &self.positions {
+            text.push_str(&format!(
+                "{:016x} {} {} {}\n",
+                hash, results.wins, results.draws, results.losses
+            ));
+        }
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, LearningError> {
+        let mut learning = Self::default();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || LearningError::InvalidLine(line.to_string());
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (hash, wins, draws, losses) = match fields.as_slice() {
+                [hash, wins, draws, losses] => (hash, wins, draws, losses),
+                _ => return Err(invalid()),
+            };
+            let hash = u64::from_str_radix(hash, 16).map_err(|_| invalid())?;
+            let results = LearnedResults {
+                wins: wins.parse().map_err(|_| invalid())?,
+                draws: draws.parse().map_err(|_| invalid())?,
+                losses: losses.parse().map_err(|_| invalid())?,
+            };
+            learning.positions.insert(hash, results);
+        }
+        Ok(learning)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LearnedResults, LearningError, OpeningLearning};
+    use crate::{Action, ChessGame, ChessMove, Color};
+    use std::str::FromStr;
+    use test_case::test_case;
+
+    // 1. f3 e5 2. g4 Qh4#
+    fn fools_mate() -> ChessGame {
+        let mut game = ChessGame::default();
+        for chess_move in ["f2f3", "e7e5", "g2g4", "d8h4"] {
+            game.take_action(Action::MakeMove(ChessMove::from_str(chess_move).unwrap()));
+        }
+        game
+    }
+
+    #[test_case(0, 0, 1, 0; "too few games")]
+    #[test_case(2, 1, 2, 0; "more wins than losses")]
+    #[test_case(0, 0, 2, 10; "lost every game")]
+    #[test_case(1, 1, 3, 4; "lost more than won")]
+    fn penalties(wins: u32, draws: u32, losses: u32, penalty: i16) {
+        let results = LearnedResults {
+            wins,
+            draws,
+            losses,
+        };
+        assert_eq!(results.penalty(), penalty);
+    }
+
+    #[test]
+    fn games_teach_the_side_that_played_them() {
+        let game = fools_mate();
+        let mut learning = OpeningLearning::default();
+        learning.record_game(&game, Color::White);
+        learning.record_game(&game, Color::White);
+        assert_eq!(learning.len(), 2);
+        let after_f3 = game
+            .start_position()
+            .make_move_new(ChessMove::from_str("f2f3").unwrap());
+        let results = learning.results(after_f3.get_hash()).unwrap();
+        assert_eq!(results.losses, 2);
+        assert_eq!(learning.penalty(after_f3.get_hash()), 10);
+        assert_eq!(learning.penalty(game.start_position().get_hash()), 0);
+        let mate = game.current_position().get_hash();
+        assert!(learning.results(mate).is_none());
+
+        learning.record_game(&game, Color::Black);
+        assert_eq!(learning.len(), 4);
+        assert_eq!(learning.results(mate).unwrap().wins, 1);
+        assert_eq!(learning.penalty(mate), 0);
+    }
+
+    #[test]
+    fn unfinished_games_teach_nothing() {
+        let mut learning = OpeningLearning::default();
+        learning.record_game(&ChessGame::default(), Color::White);
+        assert!(learning.is_empty());
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let mut learning = OpeningLearning::default();
+        learning.record_game(&fools_mate(), Color::Black);
+        let text = learning.to_text();
+        assert_eq!(OpeningLearning::from_text(&text), Ok(learning));
+    }
+
+    #[test_case("12ab 1 0"; "missing losses")]
+    #[test_case("xyz 1 0 0"; "invalid hash")]
+    #[test_case("12ab 1 -1 0"; "negative draws")]
+    fn invalid_text(text: &str) {
+        assert!(matches!(
+            OpeningLearning::from_text(text),
+            Err(LearningError::InvalidLine(_))
+        ));
+    }
+}
@@ -1,21 +1,65 @@
+use crate::backend::{Backend, DefaultBackend as Board};
 use crate::eval::Evaluator;
-use chess::Board;
 use chess::Game;
-use chess::MoveGen;
-use std::cmp::Ordering;
+use std::cmp::{self, Ordering};
 use std::str::FromStr;
 
 pub mod agent;
+pub mod analysis;
+pub mod analysis_cache;
+pub mod arrows;
+pub mod backend;
+pub mod baseline;
+pub mod binpack;
+pub mod correspondence;
+pub mod dataset;
+pub mod epd;
 pub mod eval;
+pub mod evalgraph;
+pub mod features;
+pub mod fen;
+pub mod fmt;
+pub mod game_database;
+#[cfg(feature = "gaviota")]
+pub mod gaviota;
+pub mod jsonrpc;
+pub mod learning;
+pub mod masks;
+pub mod notation;
+pub mod opening_tree;
+pub mod options;
+pub mod pgn;
+pub mod polyglot;
+pub mod repertoire;
+pub mod score;
+pub mod search;
+pub mod strength;
+#[cfg(feature = "syzygy")]
+pub mod syzygy;
+pub mod tbgen;
+pub mod time;
 pub mod tt;
+pub mod tuning;
+pub mod uci;
+pub mod winprob;
 
+mod json;
 mod move_hash;
 mod node;
 
-const EVALUATOR: eval::naive::NaiveEvaluator = eval::naive::NaiveEvaluator {};
+const EVALUATOR: eval::naive::NaiveEvaluator =
+    eval::naive::NaiveEvaluator::with_config(eval::naive::EvaluationConfig::DEFAULT);
 
-// type aliases for now to decouple
-// the engine code from chess library being used
+// How much each piece counts towards the game phase, indexed by `Piece as usize`
+const PHASE_WEIGHTS: [u32; 6] = [0, 1, 1, 2, 4, 0];
+// The phase weight of all the pieces at the start of a game
+const OPENING_PHASE_WEIGHT: u32 = 24;
+// Phases above these values count as the opening and the middlegame
+const OPENING_PHASE: f32 = 0.85;
+const MIDDLEGAME_PHASE: f32 = 0.35;
+
+// type aliases for now to decouple the engine code from the chess library
+// being used, the board itself is behind the backend::Backend trait
 pub type Action = chess::Action;
 pub type PositionStatus = chess::BoardStatus;
 pub type ChessMove = chess::ChessMove;
@@ -27,6 +71,7 @@ pub type File = chess::File;
 pub type ParseError = chess::Error;
 pub type GameResult = chess::GameResult;
 pub type BitBoard = chess::BitBoard;
+pub type CastleRights = chess::CastleRights;
 
 pub const ALL_SQUARES: [Square; 64] = chess::ALL_SQUARES;
 pub const ALL_RANKS: [Rank; 8] = chess::ALL_RANKS;
@@ -34,18 +79,72 @@ pub const ALL_FILES: [File; 8] = chess::ALL_FILES;
 pub const ALL_PIECES: [Piece; 6] = chess::ALL_PIECES;
 pub const PROMOTION_PIECES: [Piece; 4] = chess::PROMOTION_PIECES;
 
+/// Squares seen from one side of the board, so tables written for white
+/// can be used for black too
+pub trait RelativeSquare {
+    /// The square mirrored vertically for black, e.g. e2 for white is e7 for black
+    fn relative_to(self, color: Color) -> Square;
+    /// The rank counted from the color's own side, the first rank is the back rank
+    fn relative_rank(self, color: Color) -> Rank;
+}
+
+impl RelativeSquare for Square {
+    fn relative_to(self, color: Color) -> Square {
+        Square::make_square(self.relative_rank(color), self.get_file())
+    }
+
+    fn relative_rank(self, color: Color) -> Rank {
+        match color {
+            Color::White => self.get_rank(),
+            Color::Black => Rank::from_index(7 - self.get_rank().to_index()),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct ChessGame {
+    start: Board,
+    // the halfmove clock of the start position, which the board doesn't keep
+    start_clock: u32,
     game: Game,
 }
 
-#[derive(Default)]
+#[derive(Clone)]
 pub struct Position {
     board: Board,
+    // the naive evaluator's material and piece-square total from white's
+    // point of view, kept up to date on every move
+    psqt: i16,
+    // the NNUE evaluator's first layer when a search attached it,
+    // kept up to date on every move like psqt
+    accumulator: Option<eval::nnue::Accumulator>,
+}
+
+/// The stage of the game, judged by the material left on the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamePhase {
+    Opening,
+    Middlegame,
+    Endgame,
+}
+
+impl std::fmt::Display for GamePhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GamePhase::Opening => write!(f, "opening"),
+            GamePhase::Middlegame => write!(f, "middlegame"),
+            GamePhase::Endgame => write!(f, "endgame"),
+        }
+    }
 }
 
 impl Default for ChessGame {
     fn default() -> Self {
-        Self { game: Game::new() }
+        Self {
+            start: Board::default(),
+            start_clock: 0,
+            game: Game::new(),
+        }
     }
 }
 
@@ -53,17 +152,85 @@ impl FromStr for ChessGame {
     type Err = ParseError;
 
     fn from_str(fen: &str) -> Result<Self, Self::Err> {
+        let start = Board::from_str(fen)?;
+        let start_clock = fen
+            .split_whitespace()
+            .nth(4)
+            .and_then(|clock| clock.parse().ok())
+            .unwrap_or(0);
         Ok(Self {
-            game: Game::new_with_board(Board::from_str(fen)?),
+            start,
+            start_clock,
+            game: Game::new_with_board(start),
         })
     }
 }
 
+impl FromStr for Position {
+    type Err = ParseError;
+
+    fn from_str(fen: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(Board::from_str(fen)?))
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self::new(Board::default())
+    }
+}
+
+impl std::fmt::Display for Position {
+    /// Formats the position as FEN
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.board)
+    }
+}
+
 impl ChessGame {
+    pub fn start_position(&self) -> Position {
+        Position::new(self.start)
+    }
+
+    /// The moves played since the start position
+    pub fn moves(&self) -> Vec<ChessMove> {
+        self.game
+            .actions()
+            .iter()
+            .filter_map(|action| match action {
+                Action::MakeMove(chess_move) => Some(*chess_move),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Hashes of the positions before the current one, oldest first
+    pub fn previous_hashes(&self) -> Vec<u64> {
+        let mut board = self.start;
+        let mut hashes = Vec::new();
+        for chess_move in self.moves() {
+            hashes.push(board.get_hash());
+            board = board.make_move_new(chess_move);
+        }
+        hashes
+    }
+
     pub fn current_position(&self) -> Position {
-        Position {
-            board: self.game.current_position(),
+        Position::new(self.game.current_position())
+    }
+
+    /// The plies since the last capture or pawn move, counting on from the
+    /// halfmove clock of the start position's FEN
+    pub fn halfmove_clock(&self) -> u32 {
+        let mut board = self.start;
+        let mut clock = self.start_clock;
+        for chess_move in self.moves() {
+            let zeroing = board.piece_on(chess_move.get_source()) == Some(Piece::Pawn)
+                || board.piece_on(chess_move.get_dest()).is_some();
+            clock = if zeroing { 0 } else { clock + 1 };
+            board = board.make_move_new(chess_move);
         }
+        clock
     }
 
     pub fn side_to_move(&self) -> Color {
@@ -82,6 +249,30 @@ impl ChessGame {
         self.game.result()
     }
 
+    /// The number of the current full move, counting on from the start position's
+    pub fn fullmove_number(&self) -> u32 {
+        let start = self.start_position();
+        let black_started = (start.side_to_move() == Color::Black) as u32;
+        pgn::fullmove_number(&start) + (self.moves().len() as u32 + black_started) / 2
+    }
+
+    /// Whether the opponent of the side to move offered a draw that can
+    /// still be accepted, either just now or along with their last move
+    pub fn draw_offered(&self) -> bool {
+        let offer = Action::OfferDraw(!self.side_to_move());
+        self.game
+            .actions()
+            .iter()
+            .rev()
+            .take(2)
+            .any(|action| *action == offer)
+    }
+
+    /// Whether the side to move may claim a draw by threefold repetition or the fifty-move rule
+    pub fn can_declare_draw(&self) -> bool {
+        self.game.can_declare_draw()
+    }
+
     pub fn take_action(&mut self, action: Action) {
         match action {
             Action::MakeMove(chess_move) => self.game.make_move(chess_move),
@@ -94,6 +285,19 @@ impl ChessGame {
 }
 
 impl Position {
+    fn new(board: Board) -> Self {
+        let psqt = board
+            .combined()
+            .into_iter()
+            .map(|square| psqt_value(&board, square))
+            .sum();
+        Self {
+            board,
+            psqt,
+            accumulator: None,
+        }
+    }
+
     pub fn evaluate(&self) -> i16 {
         EVALUATOR.evaluate(self)
     }
@@ -114,18 +318,129 @@ impl Position {
         self.board.legal(chess_move)
     }
 
+    /// Whether the legal move checks the other king, worked out without
+    /// making the move except for castling and en passant captures
+    pub fn gives_check(&self, chess_move: ChessMove) -> bool {
+        self.board.gives_check(chess_move)
+    }
+
+    /// The legal move written in UCI notation like `e2e4` or `e7e8q`, or in
+    /// SAN like `Nf3`, None for anything else
+    pub fn parse_move(&self, text: &str) -> Option<ChessMove> {
+        let text = text.trim();
+        ChessMove::from_str(text)
+            .ok()
+            .filter(|&chess_move| self.legal(chess_move))
+            .or_else(|| pgn::parse_san(self, text))
+    }
+
+    pub fn castle_rights(&self, color: Color) -> CastleRights {
+        self.board.castle_rights(color)
+    }
+
+    /// The square of the pawn that can be captured en passant
+    pub fn en_passant(&self) -> Option<Square> {
+        self.board.en_passant()
+    }
+
     pub fn in_check(&self) -> bool {
         self.board.checkers().popcnt() > 0
     }
 
+    /// The pieces giving check to the side to move
+    pub fn checkers(&self) -> BitBoard {
+        Backend::checkers(&self.board)
+    }
+
+    /// The side to move's pieces that are pinned to its king
+    pub fn pinned(&self) -> BitBoard {
+        Backend::pinned(&self.board)
+    }
+
+    /// The pieces of both colors attacking the square
+    pub fn attacks_to(&self, square: Square) -> BitBoard {
+        self.attackers_of(square, Color::White) | self.attackers_of(square, Color::Black)
+    }
+
+    /// The pieces of the given color attacking the square,
+    /// whether or not moving them would be legal
+    pub fn attackers_of(&self, square: Square, color: Color) -> BitBoard {
+        self.board.attackers_of(square, color)
+    }
+
+    /// The static exchange evaluation of the move: the material the side
+    /// to move wins or loses, in evaluation units, if both sides keep
+    /// capturing on the move's destination with their least valuable piece
+    /// for as long as it pays off. Pieces behind the capturers join in, pins
+    /// and checks are ignored.
+    ///
+    /// Quiet moves are worth 0 when the destination is safe and minus the
+    /// piece when it's lost there, e.g. a knight moving where a pawn takes
+    /// it is worth -30. A move from an empty square is worth 0.
+    pub fn see(&self, chess_move: ChessMove) -> i16 {
+        see(&self.board, &chess_move)
+    }
+
     pub fn make_move_new(&self, chess_move: ChessMove) -> Self {
+        let board = self.board.make_move_new(chess_move);
+        let changed = changed_squares(&self.board, &chess_move);
+        let psqt_change: i16 = changed
+            .into_iter()
+            .map(|square| psqt_value(&board, square) - psqt_value(&self.board, square))
+            .sum();
+        let accumulator = self.accumulator.as_ref().map(|accumulator| {
+            let mut accumulator = accumulator.clone();
+            for square in changed {
+                if let (Some(piece), Some(color)) =
+                    (self.board.piece_on(square), self.board.color_on(square))
+                {
+                    accumulator.remove(piece, color, square);
+                }
+                if let (Some(piece), Some(color)) = (board.piece_on(square), board.color_on(square))
+                {
+                    accumulator.add(piece, color, square);
+                }
+            }
+            accumulator
+        });
         Self {
-            board: self.board.make_move_new(chess_move),
+            board,
+            psqt: self.psqt + psqt_change,
+            accumulator,
         }
     }
 
+    /// The material and piece-square table total of the naive evaluator
+    /// from white's point of view, updated incrementally with every move
+    pub fn psqt(&self) -> i16 {
+        self.psqt
+    }
+
+    /// Returns true if the side to move has nothing but its king and pawns.
+    /// Passing would often beat every legal move in such positions,
+    /// so null-move pruning can't be trusted in them.
+    pub fn is_zugzwang_likely(&self) -> bool {
+        let kings_and_pawns = self.board.pieces(Piece::King) | self.board.pieces(Piece::Pawn);
+        (self.board.color_combined(self.side_to_move()) & !kings_and_pawns).popcnt() == 0
+    }
+
+    /// The NNUE evaluator's first layer, if one was attached with
+    /// [`Position::with_accumulator`], updated with every move from here on
+    pub fn accumulator(&self) -> Option<&eval::nnue::Accumulator> {
+        self.accumulator.as_ref()
+    }
+
+    pub fn with_accumulator(mut self, accumulator: eval::nnue::Accumulator) -> Self {
+        self.accumulator = Some(accumulator);
+        self
+    }
+
     pub fn null_move(&self) -> Option<Self> {
-        self.board.null_move().map(|b| Self { board: b })
+        self.board.null_move().map(|board| Self {
+            board,
+            psqt: self.psqt,
+            accumulator: self.accumulator.clone(),
+        })
     }
 
     pub fn status(&self) -> PositionStatus {
@@ -136,75 +451,597 @@ impl Position {
         self.board.side_to_move()
     }
 
-    pub fn color_combined(&self, color: Color) -> &BitBoard {
-        self.board.color_combined(color)
+    /// The material left on the board, from 1.0 with all the pieces of the
+    /// starting position down to 0.0 with only kings and pawns.
+    /// Minor pieces count once, rooks twice and queens four times.
+    pub fn phase(&self) -> f32 {
+        let weight: u32 = ALL_PIECES
+            .iter()
+            .map(|&piece| PHASE_WEIGHTS[piece as usize] * self.board.pieces(piece).popcnt())
+            .sum();
+        // promotions can add more material than the game started with
+        weight.min(OPENING_PHASE_WEIGHT) as f32 / OPENING_PHASE_WEIGHT as f32
     }
 
-    pub fn pieces(&self, piece: Piece) -> &BitBoard {
-        self.board.pieces(piece)
+    /// The [`GamePhase`] that [`Position::phase`] falls in
+    pub fn game_phase(&self) -> GamePhase {
+        let phase = self.phase();
+        if phase > OPENING_PHASE {
+            GamePhase::Opening
+        } else if phase > MIDDLEGAME_PHASE {
+            GamePhase::Middlegame
+        } else {
+            GamePhase::Endgame
+        }
+    }
+
+    pub fn color_combined(&self, color: Color) -> BitBoard {
+        Backend::color_combined(&self.board, color)
+    }
+
+    pub fn pieces(&self, piece: Piece) -> BitBoard {
+        Backend::pieces(&self.board, piece)
     }
 
     #[inline]
     pub fn legal_moves(&self) -> Vec<ChessMove> {
-        MoveGen::new_legal(&self.board).collect::<Vec<ChessMove>>()
+        self.legal_moves_iter().collect::<Vec<ChessMove>>()
+    }
+
+    /// The legal moves in no particular order, generated as they are iterated
+    #[inline]
+    pub fn legal_moves_iter(&self) -> impl ExactSizeIterator<Item = ChessMove> {
+        self.board.legal_moves()
+    }
+
+    /// Returns true if the side to move has any legal move
+    #[inline]
+    pub fn has_legal_moves(&self) -> bool {
+        self.count_moves() > 0
+    }
+
+    /// The number of legal moves, without generating them
+    #[inline]
+    pub fn count_moves(&self) -> usize {
+        self.legal_moves_iter().len()
+    }
+
+    /// Returns true if the move neither captures nor promotes
+    pub fn is_quiet(&self, chess_move: ChessMove) -> bool {
+        !is_capture(&self.board, &chess_move) && !is_promotion(&chess_move)
     }
 
     #[inline]
     pub fn sorted_moves(&self, best_move: Option<ChessMove>) -> Vec<ChessMove> {
         let mut sorted_moves = Vec::new();
-        let mut move_gen = MoveGen::new_legal(&self.board);
-        if let Some(best_move) = best_move {
-            if self.board.legal(best_move) {
-                move_gen.remove_move(best_move);
-                sorted_moves.push(best_move);
-            }
-        }
-        let mut moves = MoveGen::new_legal(&self.board).collect::<Vec<ChessMove>>();
-        moves.sort_by(|a, b| compare_moves(&self.board, a, b));
-        //moves
-        sorted_moves.append(&mut moves);
+        self.sorted_moves_into(best_move, &mut sorted_moves);
         sorted_moves
     }
 
+    /// Same as [`Position::sorted_moves`] but reuses the given buffer
+    ///
+    /// The moves are generated once: a legal `best_move` is found among them
+    /// and moved to the front, an illegal one is left out.
+    #[inline]
+    pub fn sorted_moves_into(
+        &self,
+        best_move: Option<ChessMove>,
+        sorted_moves: &mut Vec<ChessMove>,
+    ) {
+        sorted_moves.clear();
+        sorted_moves.extend(self.board.legal_moves());
+        let found =
+            best_move.and_then(|best_move| sorted_moves.iter().position(|&m| m == best_move));
+        let start = match found {
+            Some(index) => {
+                // keeps the generation order of the others
+                sorted_moves[..=index].rotate_right(1);
+                1
+            }
+            None => 0,
+        };
+        sorted_moves[start..].sort_by(|a, b| compare_moves(&self.board, a, b));
+    }
+
     #[inline]
     pub fn sorted_captures(&self) -> Vec<ChessMove> {
-        let mut captures = captures(&self.board).collect::<Vec<ChessMove>>();
-        captures.sort_by(|a, b| compare_moves(&self.board, a, b));
+        let mut captures = Vec::new();
+        self.sorted_captures_into(&mut captures);
         captures
     }
+
+    /// Same as [`Position::sorted_captures`] but reuses the given buffer
+    #[inline]
+    pub fn sorted_captures_into(&self, sorted_captures: &mut Vec<ChessMove>) {
+        sorted_captures.clear();
+        sorted_captures.extend(captures(&self.board).filter(|m| is_capture(&self.board, m)));
+        sorted_captures.sort_by(|a, b| compare_moves(&self.board, a, b));
+    }
+}
+
+// Piece values of the static exchange evaluation in evaluation units,
+// indexed by `Piece as usize`. The king is worth more than any exchange so
+// it only takes last.
+const SEE_VALUES: [i16; 6] = [10, 30, 30, 50, 90, 1000];
+
+// The gains of the exchange on the destination from the side to move's point of
+// view, one capture after the other with the least valuable attacker, then
+// settled backwards since either side may stop capturing when it would lose
+fn see<B: Backend>(board: &B, chess_move: &ChessMove) -> i16 {
+    let source = chess_move.get_source();
+    let dest = chess_move.get_dest();
+    let piece = match board.piece_on(source) {
+        Some(piece) => piece,
+        None => return 0,
+    };
+    let mut occupied = board.combined() ^ BitBoard::from_square(source);
+    if is_en_passant(board, chess_move) {
+        occupied ^= BitBoard::from_square(Square::make_square(source.get_rank(), dest.get_file()));
+    }
+    // the piece standing on the destination, to be taken next
+    let mut target = chess_move.get_promotion().unwrap_or(piece);
+    let mut gains = [0i16; 32];
+    gains[0] = captured_piece(board, chess_move).map_or(0, |piece| SEE_VALUES[piece as usize])
+        + chess_move.get_promotion().map_or(0, |promotion| {
+            SEE_VALUES[promotion as usize] - SEE_VALUES[Piece::Pawn as usize]
+        });
+    let mut side = !board.side_to_move();
+    let mut depth = 0;
+    loop {
+        let attackers = board.attackers_through(dest, occupied) & board.color_combined(side);
+        let attacker = ALL_PIECES
+            .iter()
+            .map(|&piece| (piece, attackers & board.pieces(piece)))
+            .find(|(_, squares)| squares.popcnt() > 0);
+        let (piece, squares) = match attacker {
+            Some(attacker) => attacker,
+            None => break,
+        };
+        depth += 1;
+        gains[depth] = SEE_VALUES[target as usize] - gains[depth - 1];
+        // a pawn reaching the last rank takes as a queen
+        let promotes = piece == Piece::Pawn
+            && (dest.get_rank() == Rank::First || dest.get_rank() == Rank::Eighth);
+        if promotes {
+            gains[depth] += SEE_VALUES[Piece::Queen as usize] - SEE_VALUES[Piece::Pawn as usize];
+        }
+        if depth == gains.len() - 1 {
+            break;
+        }
+        occupied ^= BitBoard::from_square(squares.to_square());
+        target = if promotes { Piece::Queen } else { piece };
+        side = !side;
+    }
+    while depth > 0 {
+        gains[depth - 1] = -cmp::max(-gains[depth - 1], gains[depth]);
+        depth -= 1;
+    }
+    gains[0]
 }
 
+// Piece values used to order moves, indexed by `Piece as usize`.
+// The king only ever attacks, and is the last piece to capture with.
+const ORDERING_VALUES: [i16; 6] = [1, 3, 3, 5, 9, 10];
+
+#[inline]
+fn is_capture<B: Backend>(board: &B, chess_move: &ChessMove) -> bool {
+    captured_piece(board, chess_move).is_some()
+}
+
+// The piece taken by the move, an en passant capture takes
+// a pawn even though its destination square is empty
 #[inline]
-fn is_capture(board: &Board, chess_move: &ChessMove) -> bool {
-    let square = BitBoard::from_square(chess_move.get_dest());
-    (square & board.color_combined(!board.side_to_move())) != chess::EMPTY
+fn captured_piece<B: Backend>(board: &B, chess_move: &ChessMove) -> Option<Piece> {
+    let dest = chess_move.get_dest();
+    if board.color_on(dest) == Some(!board.side_to_move()) {
+        board.piece_on(dest)
+    } else if is_en_passant(board, chess_move) {
+        Some(Piece::Pawn)
+    } else {
+        None
+    }
+}
+
+#[inline]
+fn is_en_passant<B: Backend>(board: &B, chess_move: &ChessMove) -> bool {
+    board.piece_on(chess_move.get_source()) == Some(Piece::Pawn)
+        && chess_move.get_source().get_file() != chess_move.get_dest().get_file()
+        && board.piece_on(chess_move.get_dest()).is_none()
+}
+
+// The squares a move may change: its source and destination, the pawn taken
+// en passant and the squares the rook can castle from and to
+fn changed_squares<B: Backend>(board: &B, chess_move: &ChessMove) -> BitBoard {
+    let source = chess_move.get_source();
+    let dest = chess_move.get_dest();
+    let mut squares = BitBoard::from_square(source) | BitBoard::from_square(dest);
+    if is_en_passant(board, chess_move) {
+        squares |= BitBoard::from_square(Square::make_square(source.get_rank(), dest.get_file()));
+    } else if board.piece_on(source) == Some(Piece::King)
+        && (source.get_file().to_index() as i8 - dest.get_file().to_index() as i8).abs() == 2
+    {
+        for &file in &[File::A, File::D, File::F, File::H] {
+            squares |= BitBoard::from_square(Square::make_square(source.get_rank(), file));
+        }
+    }
+    squares
+}
+
+// The naive evaluator's value of whatever stands on the square, from white's point of view
+fn psqt_value<B: Backend>(board: &B, square: Square) -> i16 {
+    match (board.piece_on(square), board.color_on(square)) {
+        (Some(piece), Some(color)) => {
+            eval::naive::NaiveEvaluator::piece_square_value(piece, color, square)
+        }
+        _ => 0,
+    }
 }
 
 fn is_promotion(chess_move: &ChessMove) -> bool {
-    matches!(chess_move.get_promotion(), Some(_))
+    chess_move.get_promotion().is_some()
 }
 
+// Legal moves to squares where something can be captured. This may include
+// quiet moves to the en passant square, so the moves still need to be filtered.
 #[inline]
-fn captures(board: &Board) -> MoveGen {
-    let mut moves = MoveGen::new_legal(board);
-    moves.set_iterator_mask(*board.color_combined(!board.side_to_move()));
-    moves
+fn captures<B: Backend>(board: &B) -> B::Moves {
+    let mut targets = board.color_combined(!board.side_to_move());
+    if let Some(square) = board.en_passant() {
+        targets |= BitBoard::from_square(square.uforward(board.side_to_move()));
+    }
+    board.legal_moves_to(targets)
 }
 
+// Moves are searched in ascending order of this key (MVV-LVA):
+//
+// 1. captures, the most valuable victim first and, for the same victim,
+//    the least valuable attacker first. A capturing pawn that promotes adds
+//    what it gains by promoting to the value of its victim.
+// 2. promotions that don't capture, queen first
+// 3. quiet moves
 #[inline]
-fn capture_score(board: &Board, a: &ChessMove) -> i8 {
-    let values = [1, 3, 3, 5, 9, 0];
-    if is_capture(board, a) {
-        values[board.piece_on(a.get_source()).unwrap() as usize]
-            - values[board.piece_on(a.get_dest()).unwrap() as usize]
-    } else if is_promotion(a) {
-        10
-    } else {
-        i8::MAX
+fn ordering_key<B: Backend>(board: &B, chess_move: &ChessMove) -> i16 {
+    let promotion_gain = chess_move
+        .get_promotion()
+        .map_or(0, |piece| ORDERING_VALUES[piece as usize] - 1);
+    match captured_piece(board, chess_move) {
+        Some(victim) => {
+            let attacker = board.piece_on(chess_move.get_source()).unwrap();
+            // attackers are worth at most 10, so a bigger victim always comes first
+            -16 * (ORDERING_VALUES[victim as usize] + promotion_gain)
+                + ORDERING_VALUES[attacker as usize]
+        }
+        None if is_promotion(chess_move) => ORDERING_VALUES[Piece::Queen as usize] - promotion_gain,
+        None => i16::MAX,
     }
 }
 
 #[inline]
-fn compare_moves(board: &Board, a: &ChessMove, b: &ChessMove) -> Ordering {
-    capture_score(board, a).cmp(&capture_score(board, b))
+fn compare_moves<B: Backend>(board: &B, a: &ChessMove, b: &ChessMove) -> Ordering {
+    ordering_key(board, a).cmp(&ordering_key(board, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RelativeSquare;
+    use super::{
+        captured_piece, Action, ChessGame, ChessMove, Color, GamePhase, Piece, Position, Square,
+        ORDERING_VALUES,
+    };
+    use crate::backend::DefaultBackend;
+    use proptest::prelude::*;
+    use std::str::FromStr;
+    use test_case::test_case;
+
+    #[test]
+    fn halfmove_clock() {
+        let mut game = ChessGame::from_str("4k3/8/8/8/8/8/4P3/R3K3 w - - 12 40").unwrap();
+        assert_eq!(game.halfmove_clock(), 12);
+        for (text, clock) in [
+            ("a1a7", 13),
+            ("e8d8", 14),
+            ("e2e4", 0),
+            ("d8c8", 1),
+            ("a7a8", 2),
+        ] {
+            game.take_action(Action::MakeMove(ChessMove::from_str(text).unwrap()));
+            assert_eq!(game.halfmove_clock(), clock, "after {}", text);
+        }
+        assert_eq!(ChessGame::default().halfmove_clock(), 0);
+    }
+
+    // Starting positions with castling and en passant captures available
+    const STARTS: [&str; 3] = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+    ];
+
+    // The positions of a game from one of the starts, every move picked by
+    // one of the choices until the game ends
+    fn random_game(start: usize, choices: &[usize]) -> Vec<Position> {
+        let mut positions = vec![Position::from_str(STARTS[start]).unwrap()];
+        for choice in choices {
+            let moves = positions.last().unwrap().legal_moves();
+            if moves.is_empty() {
+                break;
+            }
+            let chess_move = moves[choice % moves.len()];
+            positions.push(positions.last().unwrap().make_move_new(chess_move));
+        }
+        positions
+    }
+
+    proptest! {
+        #[test]
+        fn incremental_updates_match_a_full_rehash(
+            start in 0..STARTS.len(),
+            choices in proptest::collection::vec(any::<usize>(), 0..80),
+        ) {
+            for position in random_game(start, &choices) {
+                let rebuilt = Position::from_str(&position.to_string()).unwrap();
+                prop_assert_eq!(position.get_hash(), rebuilt.get_hash(), "{}", position);
+                prop_assert_eq!(position.psqt(), rebuilt.psqt(), "{}", position);
+            }
+        }
+    }
+
+    // (victim, attacker) values of a capture, None for other moves
+    fn victim_and_attacker(position: &Position, chess_move: ChessMove) -> Option<(i16, i16)> {
+        captured_piece(&position.board, &chess_move)
+            .filter(|_| chess_move.get_promotion().is_none())
+            .map(|victim| {
+                let attacker = position.piece_on(chess_move.get_source()).unwrap();
+                (
+                    ORDERING_VALUES[victim as usize],
+                    ORDERING_VALUES[attacker as usize],
+                )
+            })
+    }
+
+    #[test_case("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")]
+    #[test_case("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1")]
+    #[test_case("r3k2r/1p3pp1/p1p4p/3pP3/1PP5/P2P1P2/2qnKQ1P/8 b kq - 7 28")]
+    #[test_case("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8")]
+    #[test_case("2r2rk1/pp3pbp/3p2p1/q1pPn3/2P1P1b1/2N1BP2/PP1QB1PP/2R2RK1 b - - 0 1")]
+    fn most_valuable_victim_least_valuable_attacker(fen: &str) {
+        let position = Position::from_str(fen).unwrap();
+        let moves = position.sorted_moves(None);
+        let captures: Vec<(i16, i16)> = moves
+            .iter()
+            .filter_map(|&m| victim_and_attacker(&position, m))
+            .collect();
+        for pair in captures.windows(2) {
+            let ((victim, attacker), (next_victim, next_attacker)) = (pair[0], pair[1]);
+            assert!(victim > next_victim || (victim == next_victim && attacker <= next_attacker));
+        }
+        // every capture comes before every quiet move
+        let first_quiet = moves.iter().position(|&m| position.is_quiet(m));
+        let last_capture = moves
+            .iter()
+            .rposition(|&m| victim_and_attacker(&position, m).is_some());
+        if let (Some(first_quiet), Some(last_capture)) = (first_quiet, last_capture) {
+            assert!(last_capture < first_quiet);
+        }
+    }
+
+    #[test]
+    fn legal_move_iteration() {
+        let position = Position::default();
+        assert_eq!(position.count_moves(), 20);
+        assert_eq!(position.legal_moves_iter().count(), 20);
+        assert!(position.has_legal_moves());
+        let stalemate = Position::from_str("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(!stalemate.has_legal_moves());
+        assert_eq!(stalemate.count_moves(), 0);
+    }
+
+    #[test]
+    fn best_move_is_sorted_first_once() {
+        let position = Position::default();
+        let best_move = ChessMove::new(Square::G1, Square::F3, None);
+        let moves = position.sorted_moves(Some(best_move));
+        assert_eq!(moves[0], best_move);
+        assert_eq!(moves.len(), 20);
+        assert_eq!(moves.iter().filter(|&&m| m == best_move).count(), 1);
+    }
+
+    #[test_case(
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "e2a6"
+    )]
+    #[test_case(
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "e1g1"
+    )]
+    #[test_case("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", "d7c8q")]
+    #[test_case("8/8/8/7Q/k2Pp3/8/8/3K4 b - d3 0 1", "e4d3")]
+    fn sorted_moves_have_no_duplicates(fen: &str, best_move: &str) {
+        let position = Position::from_str(fen).unwrap();
+        let best_move = ChessMove::from_str(best_move).unwrap();
+        let unsorted = position.legal_moves();
+        let moves = position.sorted_moves(Some(best_move));
+        assert_eq!(moves[0], best_move);
+        assert_eq!(moves.len(), unsorted.len());
+        for &chess_move in &unsorted {
+            assert_eq!(moves.iter().filter(|&&m| m == chess_move).count(), 1);
+        }
+        // the others are ordered as without a best move
+        let without: Vec<ChessMove> = position
+            .sorted_moves(None)
+            .into_iter()
+            .filter(|&m| m != best_move)
+            .collect();
+        assert_eq!(moves[1..], without[..]);
+    }
+
+    #[test]
+    fn illegal_best_moves_are_left_out() {
+        let position = Position::default();
+        let illegal = ChessMove::new(Square::E2, Square::E5, None);
+        let moves = position.sorted_moves(Some(illegal));
+        assert_eq!(moves, position.sorted_moves(None));
+        assert!(!moves.contains(&illegal));
+        // taking en passant would expose the king on the fourth rank
+        let position = Position::from_str("8/8/8/8/k2Pp2Q/8/8/3K4 b - d3 0 1").unwrap();
+        let en_passant = ChessMove::new(Square::E4, Square::D3, None);
+        assert_eq!(
+            position.sorted_moves(Some(en_passant)),
+            position.sorted_moves(None)
+        );
+    }
+
+    #[test]
+    fn checkers_and_pinned_pieces() {
+        // the bishop on b4 checks and the rook on e8 pins the knight on e4
+        let position = Position::from_str("4r1k1/8/8/8/1b2N3/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(position.checkers().collect::<Vec<_>>(), vec![Square::B4]);
+        assert_eq!(position.pinned().collect::<Vec<_>>(), vec![Square::E4]);
+    }
+
+    #[test]
+    fn attackers_of_a_square() {
+        let position = Position::from_str("3rk3/8/2n5/3p4/4P3/2N5/8/3RK3 w - - 0 1").unwrap();
+        let attackers = |square, color| position.attackers_of(square, color).collect::<Vec<_>>();
+        assert_eq!(
+            attackers(Square::D5, Color::White),
+            vec![Square::D1, Square::C3, Square::E4]
+        );
+        // defenders count as attackers of their own pieces
+        assert_eq!(attackers(Square::D5, Color::Black), vec![Square::D8]);
+        // the pawn on d5 blocks the rook on d8
+        assert_eq!(attackers(Square::D4, Color::Black), vec![Square::C6]);
+        assert_eq!(
+            position.attacks_to(Square::D4).collect::<Vec<_>>(),
+            vec![Square::D1, Square::C6]
+        );
+    }
+
+    #[test_case("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5", 10; "free pawn")]
+    #[test_case("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "d3e5", -20; "defended pawn")]
+    #[test_case("4r1k1/8/8/4p3/8/8/4R3/4R1K1 w - - 0 1", "e2e5", 10; "rook behind the capturer")]
+    #[test_case("4k3/8/8/8/4p3/8/8/4K1N1 w - - 0 1", "g1f3", -30; "quiet move into a pawn")]
+    #[test_case("4k3/8/8/8/8/8/8/4K1N1 w - - 0 1", "g1f3", 0; "safe quiet move")]
+    #[test_case("8/4P3/8/8/8/8/8/k3K3 w - - 0 1", "e7e8q", 80; "promotion")]
+    #[test_case("3rk3/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7d8q", 40; "promotion taken back")]
+    #[test_case("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 10; "en passant")]
+    #[test_case("4k3/8/8/3q4/4K3/8/8/8 w - - 0 1", "e4d5", 90; "king takes an undefended queen")]
+    #[test_case("4k3/8/8/3q4/4K3/8/8/8 w - - 0 1", "c4d5", 0; "nothing on the source square")]
+    fn static_exchange_evaluation(fen: &str, uci: &str, expected: i16) {
+        let position = Position::from_str(fen).unwrap();
+        assert_eq!(position.see(ChessMove::from_str(uci).unwrap()), expected);
+    }
+
+    #[test]
+    fn zugzwang_with_only_king_and_pawns() {
+        let position = Position::from_str("8/8/p1k5/8/1PK5/8/8/4q3 w - - 0 1").unwrap();
+        assert!(position.is_zugzwang_likely());
+        let position = Position::from_str("8/8/p1k5/8/1PK5/8/8/4q3 b - - 0 1").unwrap();
+        assert!(!position.is_zugzwang_likely());
+        assert!(!Position::default().is_zugzwang_likely());
+    }
+
+    #[test_case("e2e4", Some("e2e4"); "uci")]
+    #[test_case(" Nf3 ", Some("g1f3"); "san")]
+    #[test_case("e2e5", None; "illegal uci")]
+    #[test_case("Nf4", None; "illegal san")]
+    #[test_case("é2e4", None; "not ascii")]
+    #[test_case("", None; "empty")]
+    fn parse_move(text: &str, expected: Option<&str>) {
+        let expected = expected.map(|uci| ChessMove::from_str(uci).unwrap());
+        assert_eq!(Position::default().parse_move(text), expected);
+    }
+
+    #[test]
+    fn phase_from_material() {
+        assert!((Position::default().phase() - 1.0).abs() < f32::EPSILON);
+        assert_eq!(Position::default().game_phase(), GamePhase::Opening);
+        // queens traded
+        let position =
+            Position::from_str("r1b1kbnr/pppp1ppp/2n5/8/8/5N2/PPP2PPP/RNB1KB1R w KQkq - 0 6")
+                .unwrap();
+        assert!((position.phase() - 16.0 / 24.0).abs() < f32::EPSILON);
+        assert_eq!(position.game_phase(), GamePhase::Middlegame);
+        let position = Position::from_str("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+        assert_eq!(position.game_phase(), GamePhase::Endgame);
+        assert_eq!(GamePhase::Endgame.to_string(), "endgame");
+    }
+
+    #[test]
+    fn pawn_takes_queen_before_queen_takes_pawn() {
+        let position = Position::from_str("4k3/8/8/3q4/4P3/8/8/3QK3 w - - 0 1").unwrap();
+        let pawn_takes_queen = ChessMove::new(Square::E4, Square::D5, None);
+        let queen_takes_queen = ChessMove::new(Square::D1, Square::D5, None);
+        let moves = position.sorted_moves(None);
+        assert_eq!(&moves[..2], &[pawn_takes_queen, queen_takes_queen]);
+    }
+
+    #[test]
+    fn en_passant_is_a_capture() {
+        let position = Position::from_str("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap();
+        let en_passant = ChessMove::new(Square::E5, Square::D6, None);
+        assert!(!position.is_quiet(en_passant));
+        assert_eq!(position.sorted_captures(), vec![en_passant]);
+        assert_eq!(position.sorted_moves(None)[0], en_passant);
+    }
+
+    #[test]
+    fn quiet_moves_to_en_passant_square_are_not_captures() {
+        let position = Position::from_str("4k3/8/8/3pP3/4N3/8/8/4K3 w - d6 0 2").unwrap();
+        let knight_move = ChessMove::new(Square::E4, Square::D6, None);
+        assert!(position.is_quiet(knight_move));
+        assert_eq!(
+            position.sorted_captures(),
+            vec![ChessMove::new(Square::E5, Square::D6, None)]
+        );
+    }
+
+    #[test]
+    fn promotions_are_ordered_by_piece() {
+        let position = Position::from_str("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let moves = position.sorted_moves(None);
+        let promotion = |dest, piece| ChessMove::new(Square::A7, dest, Some(piece));
+        // capture-promotions come before promotions on an empty square
+        assert_eq!(
+            &moves[..2],
+            &[
+                promotion(Square::B8, Piece::Queen),
+                promotion(Square::B8, Piece::Rook),
+            ]
+        );
+        let position_of = |m| moves.iter().position(|&other| other == m).unwrap();
+        assert!(
+            position_of(promotion(Square::A8, Piece::Queen))
+                < position_of(promotion(Square::A8, Piece::Knight))
+        );
+        assert!(
+            position_of(promotion(Square::B8, Piece::Knight))
+                < position_of(promotion(Square::A8, Piece::Queen))
+        );
+    }
+
+    #[test_case("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"; "castling")]
+    #[test_case("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3"; "en passant")]
+    #[test_case("n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1"; "promotions")]
+    fn incremental_psqt(fen: &str) {
+        let position = Position::from_str(fen).unwrap();
+        for first in position.legal_moves_iter() {
+            let child = position.make_move_new(first);
+            for second in child.legal_moves_iter() {
+                let grandchild = child.make_move_new(second);
+                let board = DefaultBackend::from_str(&grandchild.to_string()).unwrap();
+                assert_eq!(grandchild.psqt(), Position::new(board).psqt());
+            }
+        }
+    }
+
+    #[test_case(Square::E2, Color::White, Square::E2)]
+    #[test_case(Square::E2, Color::Black, Square::E7)]
+    #[test_case(Square::A1, Color::Black, Square::A8)]
+    #[test_case(Square::H7, Color::Black, Square::H2)]
+    fn relative_squares(square: Square, color: Color, relative: Square) {
+        assert_eq!(square.relative_to(color), relative);
+        assert_eq!(relative.relative_to(color), square);
+        assert_eq!(square.relative_rank(color), relative.get_rank());
+    }
 }
@@ -0,0 +1,136 @@
+use crate::{BitBoard, Color, File, Square, ALL_SQUARES};
+use lazy_static::lazy_static;
+
+const A_FILE: u64 = 0x0101_0101_0101_0101;
+
+lazy_static! {
+    static ref FILES: [BitBoard; 8] = files();
+    static ref ADJACENT_FILES: [BitBoard; 8] = adjacent_files_table();
+    static ref FORWARD_SPANS: [[BitBoard; 64]; 2] = spans(|square| file(square.get_file()));
+    static ref PASSED_PAWN_SPANS: [[BitBoard; 64]; 2] =
+        spans(|square| file(square.get_file()) | adjacent_files(square.get_file()));
+    static ref KING_ZONES: [BitBoard; 64] = king_zones();
+}
+
+/// Every square of the file
+#[inline]
+pub fn file(file: File) -> BitBoard {
+    FILES[file.to_index()]
+}
+
+/// Every square of the files next to the file
+#[inline]
+pub fn adjacent_files(file: File) -> BitBoard {
+    ADJACENT_FILES[file.to_index()]
+}
+
+/// The squares in front of the square on its file, seen from the color's side
+#[inline]
+pub fn forward_span(color: Color, square: Square) -> BitBoard {
+    FORWARD_SPANS[color.to_index()][square.to_index()]
+}
+
+/// The squares in front of the square on its own and the adjacent files,
+/// a pawn there is passed when no enemy pawn stands in this span
+#[inline]
+pub fn passed_pawn_span(color: Color, square: Square) -> BitBoard {
+    PASSED_PAWN_SPANS[color.to_index()][square.to_index()]
+}
+
+/// The square and the squares around it
+#[inline]
+pub fn king_zone(square: Square) -> BitBoard {
+    KING_ZONES[square.to_index()]
+}
+
+fn files() -> [BitBoard; 8] {
+    let mut files = [BitBoard::new(0); 8];
+    for (index, file) in files.iter_mut().enumerate() {
+        *file = BitBoard::new(A_FILE << index);
+    }
+    files
+}
+
+fn adjacent_files_table() -> [BitBoard; 8] {
+    let mut adjacent = [BitBoard::new(0); 8];
+    for (index, mask) in adjacent.iter_mut().enumerate() {
+        let left = if index > 0 { A_FILE << (index - 1) } else { 0 };
+        let right = if index < 7 { A_FILE << (index + 1) } else { 0 };
+        *mask = BitBoard::new(left | right);
+    }
+    adjacent
+}
+
+// The squares on the ranks in front of the square for the color
+fn ranks_ahead(color: Color, square: Square) -> u64 {
+    let rank = square.get_rank().to_index();
+    match color {
+        Color::White if rank == 7 => 0,
+        Color::White => !0 << (8 * (rank + 1)),
+        Color::Black => (1 << (8 * rank)) - 1,
+    }
+}
+
+// Masks of each color and square limited to the ranks in front of the square
+fn spans(files: impl Fn(Square) -> BitBoard) -> [[BitBoard; 64]; 2] {
+    let mut spans = [[BitBoard::new(0); 64]; 2];
+    for &color in &[Color::White, Color::Black] {
+        for &square in ALL_SQUARES.iter() {
+            spans[color.to_index()][square.to_index()] =
+                files(square) & BitBoard::new(ranks_ahead(color, square));
+        }
+    }
+    spans
+}
+
+fn king_zones() -> [BitBoard; 64] {
+    let mut zones = [BitBoard::new(0); 64];
+    for &square in ALL_SQUARES.iter() {
+        let rank = square.get_rank().to_index() as i8;
+        let file = square.get_file().to_index() as i8;
+        for other in ALL_SQUARES.iter() {
+            let ranks = (other.get_rank().to_index() as i8 - rank).abs();
+            let files = (other.get_file().to_index() as i8 - file).abs();
+            if ranks <= 1 && files <= 1 {
+                zones[square.to_index()] |= BitBoard::from_square(*other);
+            }
+        }
+    }
+    zones
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{adjacent_files, file, forward_span, king_zone, passed_pawn_span};
+    use crate::{BitBoard, Color, File, Square};
+    use test_case::test_case;
+
+    #[test]
+    fn files() {
+        assert_eq!(file(File::C).popcnt(), 8);
+        assert!(file(File::C) & BitBoard::from_square(Square::C5) != BitBoard::new(0));
+        assert_eq!(adjacent_files(File::A), file(File::B));
+        assert_eq!(adjacent_files(File::E), file(File::D) | file(File::F));
+    }
+
+    #[test_case(Color::White, Square::E4, 4, 12)]
+    #[test_case(Color::Black, Square::E4, 3, 9)]
+    #[test_case(Color::White, Square::A7, 1, 2)]
+    #[test_case(Color::White, Square::H8, 0, 0)]
+    #[test_case(Color::Black, Square::H1, 0, 0)]
+    fn spans(color: Color, square: Square, forward: u32, passed: u32) {
+        assert_eq!(forward_span(color, square).popcnt(), forward);
+        assert_eq!(passed_pawn_span(color, square).popcnt(), passed);
+        assert_eq!(
+            forward_span(color, square) & !passed_pawn_span(color, square),
+            BitBoard::new(0)
+        );
+    }
+
+    #[test_case(Square::A1, 4)]
+    #[test_case(Square::E1, 6)]
+    #[test_case(Square::E4, 9)]
+    fn king_zones(square: Square, size: u32) {
+        assert_eq!(king_zone(square).popcnt(), size);
+    }
+}
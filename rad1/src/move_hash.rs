@@ -61,8 +61,17 @@ pub fn get_move(hash: u16) -> ChessMove {
     MOVE_HASH[hash as usize]
 }
 
+// The move of a hash that was read from a file, None when it isn't one
+pub fn checked_get_move(hash: u16) -> Option<ChessMove> {
+    MOVE_HASH.get(hash as usize).copied()
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::{ChessMove, Position, ALL_SQUARES, PROMOTION_PIECES};
+    use proptest::prelude::*;
+    use std::str::FromStr;
+
     #[test]
     fn hash_test() {
         for i in 0..(super::HASH_SIZE) {
@@ -70,4 +79,44 @@ mod tests {
             assert_eq!(super::get_hash(chess_move), i as u16);
         }
     }
+
+    fn any_move() -> impl Strategy<Value = ChessMove> {
+        (0..64usize, 0..64usize, 0..=PROMOTION_PIECES.len()).prop_map(
+            |(source, dest, promotion)| {
+                ChessMove::new(
+                    ALL_SQUARES[source],
+                    ALL_SQUARES[dest],
+                    PROMOTION_PIECES.get(promotion).copied(),
+                )
+            },
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn every_move_round_trips(chess_move in any_move()) {
+            let hash = super::get_hash(chess_move);
+            prop_assert!((hash as usize) < super::HASH_SIZE);
+            prop_assert_eq!(super::get_move(hash), chess_move);
+            prop_assert_eq!(super::checked_get_move(hash), Some(chess_move));
+        }
+
+        // castling, en passant and promotions are all reached from this position
+        #[test]
+        fn legal_moves_round_trip(choices in proptest::collection::vec(any::<usize>(), 0..40)) {
+            let mut position =
+                Position::from_str("r3k2r/1Pppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PpPBBPPP/R3K2R w KQkq - 0 1")
+                    .unwrap();
+            for choice in choices {
+                let moves = position.legal_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                for &chess_move in &moves {
+                    prop_assert_eq!(super::get_move(super::get_hash(chess_move)), chess_move);
+                }
+                position = position.make_move_new(moves[choice % moves.len()]);
+            }
+        }
+    }
 }
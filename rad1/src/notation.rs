@@ -0,0 +1,180 @@
+use crate::{Color, Piece, ALL_PIECES};
+use std::fmt;
+use std::str::FromStr;
+
+// The letters of pawn, knight, bishop, rook, queen and king
+const ENGLISH: [char; 6] = ['P', 'N', 'B', 'R', 'Q', 'K'];
+const GERMAN: [char; 6] = ['B', 'S', 'L', 'T', 'D', 'K'];
+const FRENCH: [char; 6] = ['P', 'C', 'F', 'T', 'D', 'R'];
+const SPANISH: [char; 6] = ['P', 'C', 'A', 'T', 'D', 'R'];
+const ITALIAN: [char; 6] = ['P', 'C', 'A', 'T', 'D', 'R'];
+
+const WHITE_FIGURINES: [char; 6] = ['♙', '♘', '♗', '♖', '♕', '♔'];
+const BLACK_FIGURINES: [char; 6] = ['♟', '♞', '♝', '♜', '♛', '♚'];
+
+/// How pieces are written in moves, variations, PGN and on boards: with
+/// figurines or with the initials of one of the languages.
+///
+/// Only English SAN is standard PGN, games in the other notations are for
+/// people to read. [`pgn::parse_san`](crate::pgn::parse_san) also reads
+/// figurines but not the other languages, whose initials clash with the
+/// English ones: `R` is a rook in English but a king in French.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notation {
+    English,
+    Figurine,
+    German,
+    French,
+    Spanish,
+    Italian,
+}
+
+impl Notation {
+    pub const ALL: [Notation; 6] = [
+        Notation::English,
+        Notation::Figurine,
+        Notation::German,
+        Notation::French,
+        Notation::Spanish,
+        Notation::Italian,
+    ];
+
+    /// The name it's parsed from, e.g. `figurine`
+    pub fn name(self) -> &'static str {
+        match self {
+            Notation::English => "english",
+            Notation::Figurine => "figurine",
+            Notation::German => "german",
+            Notation::French => "french",
+            Notation::Spanish => "spanish",
+            Notation::Italian => "italian",
+        }
+    }
+
+    /// The piece as written in a move by the side, figurines are drawn in
+    /// the side's color and letters are capitals for both
+    pub fn piece(self, piece: Piece, color: Color) -> char {
+        match (self, color) {
+            (Notation::Figurine, Color::White) => WHITE_FIGURINES[piece.to_index()],
+            (Notation::Figurine, Color::Black) => BLACK_FIGURINES[piece.to_index()],
+            _ => self.letters()[piece.to_index()],
+        }
+    }
+
+    /// The piece as drawn on a board, letters are capitals for white and
+    /// small for black like in FEN
+    pub fn board_piece(self, piece: Piece, color: Color) -> String {
+        match (self, color, piece) {
+            // without the text variation selector terminals draw the black
+            // pawn as an emoji, it's the only figurine with one
+            (Notation::Figurine, Color::Black, Piece::Pawn) => String::from("♟\u{fe0e}"),
+            (_, Color::White, _) => self.piece(piece, color).to_string(),
+            (_, Color::Black, _) => self.piece(piece, color).to_ascii_lowercase().to_string(),
+        }
+    }
+
+    /// An English SAN move of the side rewritten in this notation, e.g.
+    /// `Nf3` is `Sf3` in German and `exd8=Q` is `exd8=♕` with figurines
+    pub fn translate(self, san: &str, color: Color) -> String {
+        san.chars()
+            .map(|c| match ENGLISH.iter().position(|&letter| letter == c) {
+                Some(index) => self.piece(ALL_PIECES[index], color),
+                // castling is written with the letter O in every language
+                None => c,
+            })
+            .collect()
+    }
+
+    fn letters(self) -> [char; 6] {
+        match self {
+            Notation::English | Notation::Figurine => ENGLISH,
+            Notation::German => GERMAN,
+            Notation::French => FRENCH,
+            Notation::Spanish => SPANISH,
+            Notation::Italian => ITALIAN,
+        }
+    }
+}
+
+impl Default for Notation {
+    fn default() -> Self {
+        Notation::English
+    }
+}
+
+impl fmt::Display for Notation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Notation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Notation::ALL
+            .iter()
+            .copied()
+            .find(|notation| notation.name() == s)
+            .ok_or_else(|| format!("'{}' isn't a notation", s))
+    }
+}
+
+/// The move with its figurines replaced by English letters
+pub(crate) fn without_figurines(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            match WHITE_FIGURINES
+                .iter()
+                .chain(BLACK_FIGURINES.iter())
+                .position(|&figurine| figurine == c)
+            {
+                Some(index) => ENGLISH[index % 6],
+                None => c,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{without_figurines, Notation};
+    use crate::{Color, Piece};
+    use std::str::FromStr;
+    use test_case::test_case;
+
+    #[test_case(Notation::English, "Nf3", Color::White, "Nf3")]
+    #[test_case(Notation::German, "Nf3", Color::White, "Sf3")]
+    #[test_case(Notation::French, "Qxe7+", Color::White, "Dxe7+")]
+    #[test_case(Notation::Spanish, "Bb5", Color::White, "Ab5")]
+    #[test_case(Notation::German, "exd8=Q#", Color::White, "exd8=D#")]
+    #[test_case(Notation::Figurine, "Nf6", Color::Black, "♞f6")]
+    #[test_case(Notation::Figurine, "exd8=Q", Color::White, "exd8=♕")]
+    #[test_case(Notation::French, "O-O-O", Color::Black, "O-O-O")]
+    #[test_case(Notation::French, "Kg1", Color::White, "Rg1")]
+    fn translated_moves(notation: Notation, san: &str, color: Color, expected: &str) {
+        assert_eq!(notation.translate(san, color), expected);
+    }
+
+    #[test_case(Notation::English, Piece::Knight, Color::Black, "n")]
+    #[test_case(Notation::German, Piece::Pawn, Color::White, "B")]
+    #[test_case(Notation::German, Piece::Queen, Color::Black, "d")]
+    #[test_case(Notation::Figurine, Piece::King, Color::Black, "♚")]
+    fn board_pieces(notation: Notation, piece: Piece, color: Color, expected: &str) {
+        assert_eq!(notation.board_piece(piece, color), expected);
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for notation in Notation::ALL.iter() {
+            assert_eq!(Notation::from_str(notation.name()), Ok(*notation));
+        }
+        assert!(Notation::from_str("klingon").is_err());
+    }
+
+    #[test]
+    fn figurines_become_english_letters() {
+        assert_eq!(without_figurines("♘f3"), "Nf3");
+        assert_eq!(without_figurines("exd1=♛+"), "exd1=Q+");
+    }
+}
@@ -0,0 +1,255 @@
+use crate::dataset::Outcome;
+use crate::json::Json;
+use crate::pgn::{self, PgnGame};
+use crate::{ChessMove, Color, Position};
+use std::cmp;
+use std::collections::BTreeMap;
+
+/// A move of the tree and how the games that played it went
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpeningNode {
+    pub chess_move: ChessMove,
+    pub san: String,
+    pub games: u32,
+    pub white_wins: u32,
+    pub draws: u32,
+    pub black_wins: u32,
+    /// The replies in the order they were first played
+    pub replies: Vec<OpeningNode>,
+}
+
+/// How often the moves of the first plies of a PGN database were played and
+/// how they scored, every position reached with its own moves. Positions
+/// reached by different move orders aren't merged.
+#[derive(Clone)]
+pub struct OpeningTree {
+    pub start: Position,
+    /// Plies of every game that are added
+    pub plies: usize,
+    /// Games added to the tree
+    pub games: u32,
+    /// Games left out for starting from another position or an illegal move
+    pub skipped: u32,
+    /// The moves of the start position in the order they were first played
+    pub moves: Vec<OpeningNode>,
+}
+
+impl OpeningNode {
+    fn new(chess_move: ChessMove, san: String) -> Self {
+        Self {
+            chess_move,
+            san,
+            games: 0,
+            white_wins: 0,
+            draws: 0,
+            black_wins: 0,
+            replies: Vec::new(),
+        }
+    }
+
+    /// The points the side playing the move got per finished game, from 0
+    /// to 1, None when no game that played it was finished
+    pub fn score(&self, mover: Color) -> Option<f64> {
+        let finished = self.white_wins + self.draws + self.black_wins;
+        let wins = match mover {
+            Color::White => self.white_wins,
+            Color::Black => self.black_wins,
+        };
+        if finished == 0 {
+            None
+        } else {
+            Some((f64::from(wins) + 0.5 * f64::from(self.draws)) / f64::from(finished))
+        }
+    }
+
+    fn add(&mut self, outcome: Option<Outcome>) {
+        self.games += 1;
+        match outcome {
+            Some(Outcome::WhiteWins) => self.white_wins += 1,
+            Some(Outcome::Draw) => self.draws += 1,
+            Some(Outcome::BlackWins) => self.black_wins += 1,
+            None => {}
+        }
+    }
+
+    fn to_json(&self, mover: Color) -> Json {
+        let mut fields = BTreeMap::new();
+        fields.insert(
+            String::from("move"),
+            Json::String(self.chess_move.to_string()),
+        );
+        fields.insert(String::from("san"), Json::String(self.san.clone()));
+        fields.insert(String::from("games"), Json::Number(self.games.into()));
+        fields.insert(String::from("white"), Json::Number(self.white_wins.into()));
+        fields.insert(String::from("draws"), Json::Number(self.draws.into()));
+        fields.insert(String::from("black"), Json::Number(self.black_wins.into()));
+        fields.insert(
+            String::from("score"),
+            match self.score(mover) {
+                Some(score) => Json::Number((100.0 * score).round() as i64),
+                None => Json::Null,
+            },
+        );
+        fields.insert(
+            String::from("replies"),
+            Json::Array(
+                most_played(&self.replies)
+                    .into_iter()
+                    .map(|reply| reply.to_json(!mover))
+                    .collect(),
+            ),
+        );
+        Json::Object(fields)
+    }
+}
+
+impl OpeningTree {
+    /// An empty tree from the standard starting position
+    pub fn new(plies: usize) -> Self {
+        Self {
+            start: Position::default(),
+            plies,
+            games: 0,
+            skipped: 0,
+            moves: Vec::new(),
+        }
+    }
+
+    /// Add every game of a PGN database
+    pub fn add_pgn(&mut self, text: &str) {
+        for game in pgn::read_games(text) {
+            self.add_game(&game);
+        }
+    }
+
+    /// Add the first plies of the game, returns false when the game was
+    /// skipped for starting from another position or an illegal move
+    pub fn add_game(&mut self, game: &PgnGame) -> bool {
+        let moves = match (game.start(), game.moves(self.plies)) {
+            (Some(start), Some(moves)) if start.get_hash() == self.start.get_hash() => moves,
+            _ => {
+                self.skipped += 1;
+                return false;
+            }
+        };
+        let outcome = game.outcome();
+        let mut position = self.start.clone();
+        let mut nodes = &mut self.moves;
+        for chess_move in moves {
+            let index = match nodes.iter().position(|node| node.chess_move == chess_move) {
+                Some(index) => index,
+                None => {
+                    let san = pgn::san(&position, chess_move);
+                    nodes.push(OpeningNode::new(chess_move, san));
+                    nodes.len() - 1
+                }
+            };
+            let node = &mut nodes[index];
+            node.add(outcome);
+            position = position.make_move_new(chess_move);
+            nodes = &mut node.replies;
+        }
+        self.games += 1;
+        true
+    }
+
+    /// The moves played after the line from the start, most played first,
+    /// None when the line isn't in the tree
+    pub fn moves_after(&self, line: &[ChessMove]) -> Option<Vec<&OpeningNode>> {
+        let mut nodes = &self.moves;
+        for chess_move in line {
+            nodes = &nodes
+                .iter()
+                .find(|node| node.chess_move == *chess_move)?
+                .replies;
+        }
+        Some(most_played(nodes))
+    }
+
+    /// The tree as one line of JSON: the games added and skipped, and the
+    /// moves of the start position with `move` in UCI notation, `san`, the
+    /// `games` that played it, their `white` wins, `draws` and `black` wins,
+    /// the `score` in percent for the side playing it and its `replies`,
+    /// most played first
+    pub fn to_json(&self) -> String {
+        let mut fields = BTreeMap::new();
+        fields.insert(String::from("games"), Json::Number(self.games.into()));
+        fields.insert(String::from("skipped"), Json::Number(self.skipped.into()));
+        fields.insert(String::from("plies"), Json::Number(self.plies as i64));
+        fields.insert(
+            String::from("moves"),
+            Json::Array(
+                most_played(&self.moves)
+                    .into_iter()
+                    .map(|node| node.to_json(self.start.side_to_move()))
+                    .collect(),
+            ),
+        );
+        Json::Object(fields).to_string()
+    }
+}
+
+// The nodes sorted by the games that played them, the first played first on ties
+fn most_played(nodes: &[OpeningNode]) -> Vec<&OpeningNode> {
+    let mut sorted: Vec<&OpeningNode> = nodes.iter().collect();
+    sorted.sort_by_key(|node| cmp::Reverse(node.games));
+    sorted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OpeningTree;
+    use crate::{ChessMove, Color};
+    use std::str::FromStr;
+
+    const DATABASE: &str = "[Result \"1-0\"]\n\n1. e4 e5 2. Nf3 1-0\n\n[Result \"0-1\"]\n\n1. d4 d5 0-1\n\n[Result \"1/2-1/2\"]\n\n1. e4 c5 1/2-1/2\n\n[Result \"*\"]\n\n1. e4 e5 *\n\n[Result \"1-0\"]\n\n1. e4 e5 2. Ke3 1-0\n\n[FEN \"4k3/8/8/8/8/8/8/4K3 w - - 0 1\"]\n[Result \"1/2-1/2\"]\n\n1. Kd2 1/2-1/2\n";
+
+    fn tree() -> OpeningTree {
+        let mut tree = OpeningTree::new(3);
+        tree.add_pgn(DATABASE);
+        tree
+    }
+
+    #[test]
+    fn moves_are_counted() {
+        let tree = tree();
+        assert_eq!((tree.games, tree.skipped), (4, 2));
+        let moves = tree.moves_after(&[]).unwrap();
+        assert_eq!(moves[0].san, "e4");
+        assert_eq!(moves[0].games, 3);
+        assert_eq!(
+            (moves[0].white_wins, moves[0].draws, moves[0].black_wins),
+            (1, 1, 0)
+        );
+        assert_eq!(moves[0].score(Color::White), Some(0.75));
+        assert_eq!(moves[1].san, "d4");
+        assert_eq!(moves[1].score(Color::White), Some(0.0));
+
+        let e4 = ChessMove::from_str("e2e4").unwrap();
+        let replies = tree.moves_after(&[e4]).unwrap();
+        assert_eq!(replies.len(), 2);
+        assert_eq!((replies[0].san.as_str(), replies[0].games), ("e5", 2));
+        assert_eq!(replies[0].score(Color::Black), Some(0.0));
+        assert!(tree
+            .moves_after(&[ChessMove::from_str("g1f3").unwrap()])
+            .is_none());
+    }
+
+    #[test]
+    fn unfinished_games_have_no_score() {
+        let mut tree = OpeningTree::new(2);
+        tree.add_pgn("[Result \"*\"]\n\n1. c4 *\n");
+        assert_eq!(tree.moves[0].score(Color::White), None);
+        assert!(tree.to_json().contains("\"score\":null"));
+    }
+
+    #[test]
+    fn json() {
+        let mut tree = OpeningTree::new(1);
+        tree.add_pgn("[Result \"1-0\"]\n\n1. e4 e5 1-0\n");
+        assert_eq!(
+            tree.to_json(),
+            "{\"games\":1,\"moves\":[{\"black\":0,\"draws\":0,\"games\":1,\"move\":\"e2e4\",\"replies\":[],\"san\":\"e4\",\"score\":100,\"white\":1}],\"plies\":1,\"skipped\":0}"
+        );
+    }
+}
@@ -0,0 +1,1148 @@
+use crate::eval;
+use crate::eval::naive::EvaluationConfig;
+#[cfg(feature = "gaviota")]
+use crate::gaviota::Gaviota;
+use crate::json::Json;
+use crate::polyglot::Book;
+use crate::search::SearchOptions;
+use crate::strength::{MAX_ELO, MIN_ELO};
+#[cfg(feature = "syzygy")]
+use crate::syzygy::Syzygy;
+use crate::tt::TableMemory;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+const MAX_HASH_MB: usize = 65536;
+const MAX_THREADS: usize = 512;
+const MAX_MULTI_PV: u8 = 64;
+const MAX_MOVE_OVERHEAD_MS: u64 = 5000;
+const MAX_CONTEMPT: i16 = 100;
+const MAX_NPS_LIMIT: u64 = 100_000_000;
+const MAX_MOVE_DELAY_MS: u64 = 60_000;
+const MAX_QSEARCH_PLY: u8 = 64;
+const MAX_QSEARCH_NODES: u64 = 100_000_000;
+const MAX_NULL_MOVE_REDUCTION: u8 = 6;
+const MAX_NNUE_BLEND: u8 = 100;
+const MAX_RESIGN_THRESHOLD: i16 = 1000;
+const MAX_RESIGN_MOVES: u8 = 100;
+const MAX_RESIGN_MIN_MOVE: u32 = 1000;
+const MAX_DRAW_ACCEPT_THRESHOLD: i16 = 1000;
+pub const MAX_SKILL_LEVEL: u8 = 20;
+// The fields of `SearchOptions` that `EngineOptions::set_path` sets
+const SEARCH_PATHS: [&str; 5] = [
+    "null_move",
+    "null_move_reduction",
+    "check_extensions",
+    "killer_moves",
+    "tablebases",
+];
+
+/// How the threads of a search share its work
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitMode {
+    /// Split the moves at the root after the first one
+    Root,
+    /// Young Brothers Wait Concept: split the moves of any node deep enough
+    /// once its first move has been searched, idle threads steal moves from
+    /// the busy ones
+    Ybwc,
+}
+
+/// When the engine gives up a game instead of playing it out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResignPolicy {
+    /// The engine resigns once its score is at or below minus this many
+    /// evaluation units
+    pub threshold: i16,
+    /// Moves in a row the score has to stay that low, 0 never resigns
+    pub moves: u8,
+    /// The first full move the engine may resign on
+    pub min_move: u32,
+}
+
+impl Default for ResignPolicy {
+    fn default() -> Self {
+        Self {
+            threshold: 60,
+            moves: 0,
+            min_move: 20,
+        }
+    }
+}
+
+/// The standard UCI options understood by the engine.
+///
+/// Option names are matched case insensitively, the way UCI asks for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineOptions {
+    /// Size of the transposition table in megabytes
+    pub hash_mb: usize,
+    /// Back the transposition table with huge pages where the system has them
+    pub large_pages: bool,
+    /// Spread the transposition table over every NUMA node
+    pub numa_interleave: bool,
+    /// Threads to split the root moves of a search between, see
+    /// [`AlphaBetaChessAgent::set_options`]
+    ///
+    /// [`AlphaBetaChessAgent::set_options`]: crate::agent::AlphaBetaChessAgent::set_options
+    pub threads: usize,
+    /// Where the moves are split between the threads
+    pub split_mode: SplitMode,
+    /// Keep every search thread on its own core, see
+    /// [`AlphaBetaChessAgent::set_options`]
+    ///
+    /// [`AlphaBetaChessAgent::set_options`]: crate::agent::AlphaBetaChessAgent::set_options
+    pub pin_threads: bool,
+    /// Lines searched and reported, see [`SearchResult::lines`]
+    ///
+    /// [`SearchResult::lines`]: crate::search::SearchResult::lines
+    pub multi_pv: u8,
+    /// The GUI may have the engine think on the opponent's time with `go ponder`
+    pub ponder: bool,
+    /// Time kept in reserve on every move to make up for network or GUI lag
+    pub move_overhead: Duration,
+    /// Strength of the engine from 0 to [`MAX_SKILL_LEVEL`], lower levels search shallower
+    pub skill_level: u8,
+    /// Play at the strength of [`EngineOptions::elo`], see [`StrengthLimit`]
+    ///
+    /// [`StrengthLimit`]: crate::strength::StrengthLimit
+    pub limit_strength: bool,
+    /// The Elo to play at when the strength is limited
+    pub elo: u16,
+    /// How much the engine dislikes draws, in evaluation units
+    pub contempt: i16,
+    /// How much more the engine dislikes repeating positions when it is ahead,
+    /// and likes them when it is behind, in evaluation units
+    pub repetition_contempt: i16,
+    /// Play the moves of the opening book at [`EngineOptions::book_file`]
+    /// while it has some
+    pub own_book: bool,
+    /// A Polyglot opening book, see [`Book`]
+    pub book_file: Option<String>,
+    /// Directories of Syzygy tablebases, see [`Syzygy::open`]
+    #[cfg(feature = "syzygy")]
+    pub syzygy_path: Option<String>,
+    /// Directories of Gaviota tablebases, see [`Gaviota::open`]
+    #[cfg(feature = "gaviota")]
+    pub gaviota_tb_path: Option<String>,
+    /// Key of the evaluator in the [evaluator registry](crate::eval::registry)
+    pub eval_file: String,
+    /// Make searches to a fixed depth reproducible, see [`AlphaBetaChessAgent::set_options`]
+    ///
+    /// [`AlphaBetaChessAgent::set_options`]: crate::agent::AlphaBetaChessAgent::set_options
+    pub deterministic: bool,
+    /// Most nodes searched per second, 0 searches as fast as possible
+    pub nps_limit: u64,
+    /// Least time spent on every move, unless the clock doesn't allow it
+    pub move_delay: Duration,
+    /// Most plies a quiescence search goes past the end of the main search
+    pub qsearch_max_ply: u8,
+    /// Most nodes a single quiescence search visits, 0 for no limit
+    pub qsearch_node_limit: u64,
+    /// Search features to switch off or adjust
+    pub search: SearchOptions,
+    /// The network's share of a hybrid evaluation in percent, see
+    /// [`HybridEvaluator`](crate::eval::hybrid::HybridEvaluator)
+    pub nnue_blend: u8,
+    /// When the agent resigns lost games, see [`ResignPolicy`]
+    pub resign: ResignPolicy,
+    /// The engine accepts a draw offer when its score is at or below this
+    /// many evaluation units and plays on otherwise
+    pub draw_accept_threshold: i16,
+    /// Weights and piece values of the evaluation set on top of its weight
+    /// file by path, in the order they were set, see [`EngineOptions::set_path`].
+    /// They aren't UCI options.
+    pub eval_overrides: Vec<(String, i16)>,
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        Self {
+            hash_mb: 16,
+            large_pages: true,
+            numa_interleave: false,
+            threads: 1,
+            split_mode: SplitMode::Root,
+            pin_threads: false,
+            multi_pv: 1,
+            ponder: false,
+            move_overhead: Duration::from_millis(10),
+            skill_level: MAX_SKILL_LEVEL,
+            limit_strength: false,
+            elo: 1500,
+            contempt: 0,
+            repetition_contempt: 0,
+            own_book: false,
+            book_file: None,
+            #[cfg(feature = "syzygy")]
+            syzygy_path: None,
+            #[cfg(feature = "gaviota")]
+            gaviota_tb_path: None,
+            eval_file: String::from("naive"),
+            deterministic: false,
+            nps_limit: 0,
+            move_delay: Duration::from_millis(0),
+            qsearch_max_ply: 32,
+            qsearch_node_limit: 100_000,
+            search: SearchOptions::default(),
+            nnue_blend: 50,
+            resign: ResignPolicy::default(),
+            draw_accept_threshold: 0,
+            eval_overrides: Vec::new(),
+        }
+    }
+}
+
+/// The reason a `setoption` command couldn't be applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionError {
+    UnknownOption(String),
+    InvalidValue {
+        name: String,
+        value: String,
+    },
+    /// The value names something that couldn't be loaded, like an
+    /// evaluator whose weight file is missing
+    Load {
+        name: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for OptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptionError::UnknownOption(name) => write!(f, "unknown option '{}'", name),
+            OptionError::InvalidValue { name, value } => {
+                write!(f, "invalid value '{}' for option '{}'", value, name)
+            }
+            OptionError::Load { name, reason } => write!(f, "option '{}': {}", name, reason),
+        }
+    }
+}
+
+impl Error for OptionError {}
+
+impl EngineOptions {
+    /// Set an option by its UCI name
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), OptionError> {
+        let invalid = || OptionError::InvalidValue {
+            name: name.to_string(),
+            value: value.to_string(),
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "hash" => self.hash_mb = parse_spin(value, 1, MAX_HASH_MB).ok_or_else(invalid)?,
+            "large pages" => self.large_pages = parse_check(value).ok_or_else(invalid)?,
+            "numa interleave" => self.numa_interleave = parse_check(value).ok_or_else(invalid)?,
+            "threads" => self.threads = parse_spin(value, 1, MAX_THREADS).ok_or_else(invalid)?,
+            "split mode" => {
+                self.split_mode = match value.to_ascii_lowercase().as_str() {
+                    "root" => SplitMode::Root,
+                    "ybwc" => SplitMode::Ybwc,
+                    _ => return Err(invalid()),
+                }
+            }
+            "pin threads" => self.pin_threads = parse_check(value).ok_or_else(invalid)?,
+            "multipv" => self.multi_pv = parse_spin(value, 1, MAX_MULTI_PV).ok_or_else(invalid)?,
+            "ponder" => self.ponder = parse_check(value).ok_or_else(invalid)?,
+            "move overhead" => {
+                let millis = parse_spin(value, 0, MAX_MOVE_OVERHEAD_MS).ok_or_else(invalid)?;
+                self.move_overhead = Duration::from_millis(millis);
+            }
+            "skill level" => {
+                self.skill_level = parse_spin(value, 0, MAX_SKILL_LEVEL).ok_or_else(invalid)?
+            }
+            "uci_limitstrength" => self.limit_strength = parse_check(value).ok_or_else(invalid)?,
+            "uci_elo" => self.elo = parse_spin(value, MIN_ELO, MAX_ELO).ok_or_else(invalid)?,
+            "contempt" => {
+                self.contempt =
+                    parse_spin(value, -MAX_CONTEMPT, MAX_CONTEMPT).ok_or_else(invalid)?
+            }
+            "repetition contempt" => {
+                self.repetition_contempt =
+                    parse_spin(value, -MAX_CONTEMPT, MAX_CONTEMPT).ok_or_else(invalid)?
+            }
+            "ownbook" => self.own_book = parse_check(value).ok_or_else(invalid)?,
+            "book file" => {
+                self.book_file = match value {
+                    "" | "<empty>" => None,
+                    path => {
+                        // read here so a missing book is reported to whoever set it
+                        Book::open(path).map_err(|error| OptionError::Load {
+                            name: name.to_string(),
+                            reason: error.to_string(),
+                        })?;
+                        Some(path.to_string())
+                    }
+                }
+            }
+            #[cfg(feature = "gaviota")]
+            "gaviotatbpath" => {
+                self.gaviota_tb_path = match value {
+                    "" | "<empty>" => None,
+                    path => {
+                        Gaviota::open(path).map_err(|error| OptionError::Load {
+                            name: name.to_string(),
+                            reason: error.to_string(),
+                        })?;
+                        Some(path.to_string())
+                    }
+                }
+            }
+            #[cfg(feature = "syzygy")]
+            "syzygypath" => {
+                self.syzygy_path = match value {
+                    "" | "<empty>" => None,
+                    path => {
+                        Syzygy::open(path).map_err(|error| OptionError::Load {
+                            name: name.to_string(),
+                            reason: error.to_string(),
+                        })?;
+                        Some(path.to_string())
+                    }
+                }
+            }
+            "evalfile" => {
+                if !eval::registry().contains(value) {
+                    return Err(invalid());
+                }
+                // loaded here so a missing file is reported to whoever set it
+                eval::registry()
+                    .create(value)
+                    .map_err(|error| OptionError::Load {
+                        name: name.to_string(),
+                        reason: error.to_string(),
+                    })?;
+                self.eval_file = value.to_string();
+            }
+            "deterministic" => self.deterministic = parse_check(value).ok_or_else(invalid)?,
+            "nps limit" => {
+                self.nps_limit = parse_spin(value, 0, MAX_NPS_LIMIT).ok_or_else(invalid)?
+            }
+            "move delay" => {
+                let millis = parse_spin(value, 0, MAX_MOVE_DELAY_MS).ok_or_else(invalid)?;
+                self.move_delay = Duration::from_millis(millis);
+            }
+            "qsearch max ply" => {
+                self.qsearch_max_ply = parse_spin(value, 0, MAX_QSEARCH_PLY).ok_or_else(invalid)?
+            }
+            "qsearch node limit" => {
+                self.qsearch_node_limit =
+                    parse_spin(value, 0, MAX_QSEARCH_NODES).ok_or_else(invalid)?
+            }
+            "null move" => self.search.null_move = parse_check(value).ok_or_else(invalid)?,
+            "null move reduction" => {
+                self.search.null_move_reduction =
+                    parse_spin(value, 1, MAX_NULL_MOVE_REDUCTION).ok_or_else(invalid)?
+            }
+            "check extensions" => {
+                self.search.check_extensions = parse_check(value).ok_or_else(invalid)?
+            }
+            "killer moves" => self.search.killer_moves = parse_check(value).ok_or_else(invalid)?,
+            "tablebases" => self.search.tablebases = parse_check(value).ok_or_else(invalid)?,
+            "nnue blend" => {
+                self.nnue_blend = parse_spin(value, 0, MAX_NNUE_BLEND).ok_or_else(invalid)?
+            }
+            "resign threshold" => {
+                self.resign.threshold =
+                    parse_spin(value, 1, MAX_RESIGN_THRESHOLD).ok_or_else(invalid)?
+            }
+            "resign moves" => {
+                self.resign.moves = parse_spin(value, 0, MAX_RESIGN_MOVES).ok_or_else(invalid)?
+            }
+            "resign min move" => {
+                self.resign.min_move =
+                    parse_spin(value, 1, MAX_RESIGN_MIN_MOVE).ok_or_else(invalid)?
+            }
+            "draw accept threshold" => {
+                self.draw_accept_threshold =
+                    parse_spin(value, -MAX_DRAW_ACCEPT_THRESHOLD, MAX_DRAW_ACCEPT_THRESHOLD)
+                        .ok_or_else(invalid)?
+            }
+            _ => return Err(OptionError::UnknownOption(name.to_string())),
+        }
+        Ok(())
+    }
+
+    /// Set a search feature or an evaluation weight by a dotted path and a
+    /// value, like `search.null_move_reduction=3` or `eval.opening.knight=31`.
+    ///
+    /// Search paths name a field of [`SearchOptions`] and take the values of
+    /// the UCI option with the same name. Evaluation paths are the paths of
+    /// [`EvaluationConfig::set`] and take a value in evaluation units, they
+    /// are applied to the evaluator when the options are.
+    pub fn set_path(&mut self, assignment: &str) -> Result<(), OptionError> {
+        let (path, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| OptionError::UnknownOption(assignment.to_string()))?;
+        let (path, value) = (path.trim(), value.trim());
+        let invalid = || OptionError::InvalidValue {
+            name: path.to_string(),
+            value: value.to_string(),
+        };
+        match path.split_once('.') {
+            Some(("search", field)) if SEARCH_PATHS.contains(&field) => self
+                .set(&field.replace('_', " "), value)
+                .map_err(|_| invalid()),
+            Some(("eval", eval_path)) => {
+                let value = value.parse().map_err(|_| invalid())?;
+                // the path is checked here, the evaluator only gets it later
+                let mut config = EvaluationConfig::DEFAULT;
+                config
+                    .set(eval_path, value)
+                    .map_err(|_| OptionError::UnknownOption(path.to_string()))?;
+                self.eval_overrides.retain(|(other, _)| other != eval_path);
+                self.eval_overrides.push((eval_path.to_string(), value));
+                Ok(())
+            }
+            _ => Err(OptionError::UnknownOption(path.to_string())),
+        }
+    }
+
+    /// How the transposition table's memory is allocated
+    pub fn table_memory(&self) -> TableMemory {
+        TableMemory {
+            huge_pages: self.large_pages,
+            numa_interleave: self.numa_interleave,
+        }
+    }
+
+    /// Set the options of a config file with one `name = value` line per option,
+    /// blank lines and lines starting with `#` are skipped
+    pub fn load_config(&mut self, config: &str) -> Result<(), OptionError> {
+        for line in config.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once('=') {
+                Some((name, value)) => self.set(name, value)?,
+                None => return Err(OptionError::UnknownOption(line.to_string())),
+            }
+        }
+        Ok(())
+    }
+
+    /// The `option` lines announcing every option to a UCI GUI
+    pub fn uci_declarations() -> Vec<String> {
+        Self::default()
+            .describe()
+            .iter()
+            .map(OptionInfo::uci_declaration)
+            .collect()
+    }
+
+    /// Every UCI option with its value in these options, its type and the
+    /// values it accepts, in the order they are announced to a GUI
+    pub fn describe(&self) -> Vec<OptionInfo> {
+        let split_mode = match self.split_mode {
+            SplitMode::Root => "Root",
+            SplitMode::Ybwc => "YBWC",
+        };
+        #[cfg_attr(not(any(feature = "syzygy", feature = "gaviota")), allow(unused_mut))]
+        let mut options = vec![
+            OptionInfo::spin(
+                "Hash",
+                "tt",
+                self.hash_mb,
+                1,
+                MAX_HASH_MB as i64,
+                "Size of the transposition table in megabytes",
+            ),
+            OptionInfo::check(
+                "Large Pages",
+                "tt",
+                self.large_pages,
+                "Back the transposition table with huge pages where the system has them",
+            ),
+            OptionInfo::check(
+                "NUMA Interleave",
+                "tt",
+                self.numa_interleave,
+                "Spread the transposition table over every NUMA node",
+            ),
+            OptionInfo::spin(
+                "Threads",
+                "threads",
+                self.threads,
+                1,
+                MAX_THREADS as i64,
+                "Threads to split the moves of a search between",
+            ),
+            OptionInfo {
+                name: "Split Mode",
+                group: "threads",
+                option_type: OptionType::Combo(&["Root", "YBWC"]),
+                value: split_mode.to_string(),
+                description: "Split the moves at the root only, or at every node deep enough",
+            },
+            OptionInfo::check(
+                "Pin Threads",
+                "threads",
+                self.pin_threads,
+                "Keep every search thread on its own core",
+            ),
+            OptionInfo::spin(
+                "MultiPV",
+                "search",
+                self.multi_pv,
+                1,
+                MAX_MULTI_PV as i64,
+                "Lines to search and report, the best one first",
+            ),
+            OptionInfo::check(
+                "Ponder",
+                "search",
+                self.ponder,
+                "Let the GUI have the engine think on the opponent's time with go ponder",
+            ),
+            OptionInfo::spin(
+                "Move Overhead",
+                "time",
+                self.move_overhead.as_millis() as u64,
+                0,
+                MAX_MOVE_OVERHEAD_MS as i64,
+                "Milliseconds kept in reserve on every move for network or GUI lag",
+            ),
+            OptionInfo::spin(
+                "Skill Level",
+                "strength",
+                self.skill_level,
+                0,
+                MAX_SKILL_LEVEL as i64,
+                "Strength of the engine, lower levels search shallower",
+            ),
+            OptionInfo::check(
+                "UCI_LimitStrength",
+                "strength",
+                self.limit_strength,
+                "Play at the strength of UCI_Elo",
+            ),
+            OptionInfo::spin(
+                "UCI_Elo",
+                "strength",
+                self.elo,
+                MIN_ELO as i64,
+                MAX_ELO as i64,
+                "The Elo to play at when the strength is limited",
+            ),
+            OptionInfo::spin(
+                "Contempt",
+                "eval",
+                self.contempt,
+                -(MAX_CONTEMPT as i64),
+                MAX_CONTEMPT as i64,
+                "How much the engine dislikes draws, in evaluation units",
+            ),
+            OptionInfo::spin(
+                "Repetition Contempt",
+                "eval",
+                self.repetition_contempt,
+                -(MAX_CONTEMPT as i64),
+                MAX_CONTEMPT as i64,
+                "How much more the engine avoids repetitions when it is ahead, in evaluation units",
+            ),
+            OptionInfo::check(
+                "OwnBook",
+                "book",
+                self.own_book,
+                "Play the moves of the opening book while it has some",
+            ),
+            OptionInfo {
+                name: "Book File",
+                group: "book",
+                option_type: OptionType::String,
+                value: self
+                    .book_file
+                    .clone()
+                    .unwrap_or_else(|| "<empty>".to_string()),
+                description: "A Polyglot opening book",
+            },
+            OptionInfo {
+                name: "EvalFile",
+                group: "eval",
+                option_type: OptionType::String,
+                value: self.eval_file.clone(),
+                description: "Key of the evaluator in the evaluator registry",
+            },
+            OptionInfo::check(
+                "Deterministic",
+                "search",
+                self.deterministic,
+                "Make searches to a fixed depth reproducible",
+            ),
+            OptionInfo::spin(
+                "NPS Limit",
+                "time",
+                self.nps_limit,
+                0,
+                MAX_NPS_LIMIT as i64,
+                "Most nodes searched per second, 0 searches as fast as possible",
+            ),
+            OptionInfo::spin(
+                "Move Delay",
+                "time",
+                self.move_delay.as_millis() as u64,
+                0,
+                MAX_MOVE_DELAY_MS as i64,
+                "Least milliseconds spent on every move, unless the clock doesn't allow it",
+            ),
+            OptionInfo::spin(
+                "QSearch Max Ply",
+                "search",
+                self.qsearch_max_ply,
+                0,
+                MAX_QSEARCH_PLY as i64,
+                "Most plies a quiescence search goes past the end of the main search",
+            ),
+            OptionInfo::spin(
+                "QSearch Node Limit",
+                "search",
+                self.qsearch_node_limit,
+                0,
+                MAX_QSEARCH_NODES as i64,
+                "Most nodes a single quiescence search visits, 0 for no limit",
+            ),
+            OptionInfo::check(
+                "Null Move",
+                "search",
+                self.search.null_move,
+                "Prune a node when passing the move still fails high",
+            ),
+            OptionInfo::spin(
+                "Null Move Reduction",
+                "search",
+                self.search.null_move_reduction,
+                1,
+                MAX_NULL_MOVE_REDUCTION as i64,
+                "Depth reduction of the null-move search",
+            ),
+            OptionInfo::check(
+                "Check Extensions",
+                "search",
+                self.search.check_extensions,
+                "Search one ply deeper when in check",
+            ),
+            OptionInfo::check(
+                "Killer Moves",
+                "search",
+                self.search.killer_moves,
+                "Try the quiet moves that caused a cutoff at the same ply early",
+            ),
+            OptionInfo::check(
+                "Tablebases",
+                "tb",
+                self.search.tablebases,
+                "Score endings of a king and one piece against a lone king with the built-in tablebases",
+            ),
+            OptionInfo::spin(
+                "NNUE Blend",
+                "eval",
+                self.nnue_blend,
+                0,
+                MAX_NNUE_BLEND as i64,
+                "The network's share of a hybrid evaluation in percent",
+            ),
+            OptionInfo::spin(
+                "Resign Threshold",
+                "game",
+                self.resign.threshold,
+                1,
+                MAX_RESIGN_THRESHOLD as i64,
+                "The engine resigns at or below minus this many evaluation units",
+            ),
+            OptionInfo::spin(
+                "Resign Moves",
+                "game",
+                self.resign.moves,
+                0,
+                MAX_RESIGN_MOVES as i64,
+                "Moves in a row the score has to stay that low, 0 never resigns",
+            ),
+            OptionInfo::spin(
+                "Resign Min Move",
+                "game",
+                self.resign.min_move,
+                1,
+                MAX_RESIGN_MIN_MOVE as i64,
+                "The first full move the engine may resign on",
+            ),
+            OptionInfo::spin(
+                "Draw Accept Threshold",
+                "game",
+                self.draw_accept_threshold,
+                -(MAX_DRAW_ACCEPT_THRESHOLD as i64),
+                MAX_DRAW_ACCEPT_THRESHOLD as i64,
+                "Draw offers are accepted at or below this many evaluation units",
+            ),
+        ];
+        // the paths are only declared when the tables can be probed
+        #[cfg(any(feature = "syzygy", feature = "gaviota"))]
+        {
+            let tablebases = options
+                .iter()
+                .position(|option| option.name == "Tablebases")
+                .map_or(options.len(), |index| index + 1);
+            #[cfg(feature = "gaviota")]
+            options.insert(
+                tablebases,
+                OptionInfo {
+                    name: "GaviotaTbPath",
+                    group: "tb",
+                    option_type: OptionType::String,
+                    value: self
+                        .gaviota_tb_path
+                        .clone()
+                        .unwrap_or_else(|| "<empty>".to_string()),
+                    description: "Directories of Gaviota tablebases, whose distances to mate score the positions they hold",
+                },
+            );
+            #[cfg(feature = "syzygy")]
+            options.insert(
+                tablebases,
+                OptionInfo {
+                    name: "SyzygyPath",
+                    group: "tb",
+                    option_type: OptionType::String,
+                    value: self
+                        .syzygy_path
+                        .clone()
+                        .unwrap_or_else(|| "<empty>".to_string()),
+                    description: "Directories of Syzygy tablebases, whose moves are played in the positions they hold",
+                },
+            );
+        }
+        options
+    }
+}
+
+/// The type of a UCI option and the values it accepts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionType {
+    /// `true` or `false`
+    Check,
+    /// A whole number from `min` to `max`
+    Spin {
+        min: i64,
+        max: i64,
+    },
+    /// One of the values, matched case insensitively
+    Combo(&'static [&'static str]),
+    String,
+}
+
+/// One of the [`EngineOptions`] as it is announced to a GUI and listed by
+/// `rad1-cli options`, see [`EngineOptions::describe`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionInfo {
+    /// The UCI name
+    pub name: &'static str,
+    /// What the option is about: `tt`, `threads`, `search`, `tb`, `time`,
+    /// `strength`, `eval`, `book` or `game`
+    pub group: &'static str,
+    pub option_type: OptionType,
+    /// The value as it is written in a `setoption` command
+    pub value: String,
+    pub description: &'static str,
+}
+
+impl OptionInfo {
+    fn check(
+        name: &'static str,
+        group: &'static str,
+        value: bool,
+        description: &'static str,
+    ) -> Self {
+        Self {
+            name,
+            group,
+            option_type: OptionType::Check,
+            value: value.to_string(),
+            description,
+        }
+    }
+
+    fn spin<T: ToString>(
+        name: &'static str,
+        group: &'static str,
+        value: T,
+        min: i64,
+        max: i64,
+        description: &'static str,
+    ) -> Self {
+        Self {
+            name,
+            group,
+            option_type: OptionType::Spin { min, max },
+            value: value.to_string(),
+            description,
+        }
+    }
+
+    /// The `option` line announcing the option to a UCI GUI, with its value
+    /// as the default
+    pub fn uci_declaration(&self) -> String {
+        let declaration = format!("option name {} type", self.name);
+        match &self.option_type {
+            OptionType::Check => format!("{} check default {}", declaration, self.value),
+            OptionType::Spin { min, max } => format!(
+                "{} spin default {} min {} max {}",
+                declaration, self.value, min, max
+            ),
+            OptionType::Combo(values) => format!(
+                "{} combo default {} var {}",
+                declaration,
+                self.value,
+                values.join(" var ")
+            ),
+            OptionType::String => format!("{} string default {}", declaration, self.value),
+        }
+    }
+
+    /// The option as a JSON object with its name, group, type, value,
+    /// description and, for spin and combo options, the values it accepts.
+    /// Values of check and spin options are JSON booleans and numbers.
+    pub fn to_json(&self) -> String {
+        let mut fields = BTreeMap::new();
+        fields.insert(String::from("name"), Json::String(self.name.to_string()));
+        fields.insert(String::from("group"), Json::String(self.group.to_string()));
+        fields.insert(
+            String::from("description"),
+            Json::String(self.description.to_string()),
+        );
+        let (type_name, value) = match &self.option_type {
+            OptionType::Check => ("check", Json::Bool(self.value == "true")),
+            OptionType::Spin { min, max } => {
+                fields.insert(String::from("min"), Json::Number(*min));
+                fields.insert(String::from("max"), Json::Number(*max));
+                // spin values are always written from numbers
+                ("spin", Json::Number(self.value.parse().unwrap_or_default()))
+            }
+            OptionType::Combo(values) => {
+                fields.insert(
+                    String::from("values"),
+                    Json::Array(
+                        values
+                            .iter()
+                            .map(|value| Json::String(value.to_string()))
+                            .collect(),
+                    ),
+                );
+                ("combo", Json::String(self.value.clone()))
+            }
+            OptionType::String => ("string", Json::String(self.value.clone())),
+        };
+        fields.insert(String::from("type"), Json::String(type_name.to_string()));
+        fields.insert(String::from("value"), value);
+        Json::Object(fields).to_string()
+    }
+}
+
+fn parse_spin<T>(value: &str, min: T, max: T) -> Option<T>
+where
+    T: std::str::FromStr + PartialOrd,
+{
+    value
+        .parse()
+        .ok()
+        .filter(|value| *value >= min && *value <= max)
+}
+
+fn parse_check(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EngineOptions, OptionError, OptionType, ResignPolicy, SplitMode, TableMemory};
+    use crate::search::SearchOptions;
+    use std::time::Duration;
+
+    #[test]
+    fn names_are_case_insensitive() {
+        let mut options = EngineOptions::default();
+        options.set("hash", "128").unwrap();
+        options.set("MOVE OVERHEAD", "50").unwrap();
+        options.set("Skill Level", "3").unwrap();
+        options.set("Ponder", "true").unwrap();
+        options.set("deterministic", "true").unwrap();
+        options.set("PIN THREADS", "true").unwrap();
+        assert_eq!(options.hash_mb, 128);
+        assert_eq!(options.move_overhead, Duration::from_millis(50));
+        assert_eq!(options.skill_level, 3);
+        assert!(options.ponder);
+        assert!(options.deterministic);
+        assert!(options.pin_threads);
+    }
+
+    #[test]
+    fn table_memory() {
+        let mut options = EngineOptions::default();
+        options.set("Large Pages", "false").unwrap();
+        options.set("NUMA Interleave", "true").unwrap();
+        let memory = TableMemory {
+            huge_pages: false,
+            numa_interleave: true,
+        };
+        assert_eq!(options.table_memory(), memory);
+    }
+
+    #[test]
+    fn throttling() {
+        let mut options = EngineOptions::default();
+        options.set("NPS Limit", "5000").unwrap();
+        options.set("Move Delay", "1500").unwrap();
+        assert_eq!(options.nps_limit, 5000);
+        assert_eq!(options.move_delay, Duration::from_millis(1500));
+        assert!(options.set("NPS Limit", "-1").is_err());
+    }
+
+    #[test]
+    fn strength_limit() {
+        let mut options = EngineOptions::default();
+        options.set("UCI_LimitStrength", "true").unwrap();
+        options.set("uci_elo", "1200").unwrap();
+        assert!(options.limit_strength);
+        assert_eq!(options.elo, 1200);
+        assert!(options.set("UCI_Elo", "100").is_err());
+    }
+
+    #[test]
+    fn resign_policy() {
+        let mut options = EngineOptions::default();
+        assert_eq!(options.resign.moves, 0);
+        options.set("Resign Threshold", "90").unwrap();
+        options.set("Resign Moves", "3").unwrap();
+        options.set("Resign Min Move", "30").unwrap();
+        let resign = ResignPolicy {
+            threshold: 90,
+            moves: 3,
+            min_move: 30,
+        };
+        assert_eq!(options.resign, resign);
+        assert!(options.set("Resign Threshold", "0").is_err());
+    }
+
+    #[test]
+    fn draw_accept_threshold() {
+        let mut options = EngineOptions::default();
+        assert_eq!(options.draw_accept_threshold, 0);
+        options.set("Draw Accept Threshold", "-15").unwrap();
+        assert_eq!(options.draw_accept_threshold, -15);
+        assert!(options.set("Draw Accept Threshold", "1001").is_err());
+    }
+
+    #[test]
+    fn split_mode() {
+        let mut options = EngineOptions::default();
+        assert_eq!(options.split_mode, SplitMode::Root);
+        options.set("Split Mode", "YBWC").unwrap();
+        assert_eq!(options.split_mode, SplitMode::Ybwc);
+        assert!(options.set("Split Mode", "lazy").is_err());
+    }
+
+    #[test]
+    fn search_features() {
+        let mut options = EngineOptions::default();
+        options.set("Null Move", "false").unwrap();
+        options.set("Null Move Reduction", "2").unwrap();
+        options.set("Check Extensions", "false").unwrap();
+        options.set("Killer Moves", "false").unwrap();
+        options.set("Tablebases", "false").unwrap();
+        let search = SearchOptions {
+            null_move: false,
+            null_move_reduction: 2,
+            check_extensions: false,
+            killer_moves: false,
+            tablebases: false,
+        };
+        assert_eq!(options.search, search);
+        assert!(options.set("Null Move Reduction", "0").is_err());
+    }
+
+    #[test]
+    fn paths() {
+        let mut options = EngineOptions::default();
+        options.set_path("search.null_move_reduction=3").unwrap();
+        options.set_path("search.killer_moves = false").unwrap();
+        options.set_path("eval.opening.knight=31").unwrap();
+        options.set_path("eval.hanging_piece=4").unwrap();
+        options.set_path("eval.opening.knight=32").unwrap();
+        assert_eq!(options.search.null_move_reduction, 3);
+        assert!(!options.search.killer_moves);
+        assert_eq!(
+            options.eval_overrides,
+            vec![
+                ("hanging_piece".to_string(), 4),
+                ("opening.knight".to_string(), 32)
+            ]
+        );
+        assert_eq!(
+            options.set_path("search.null_move_reduction=9"),
+            Err(OptionError::InvalidValue {
+                name: "search.null_move_reduction".to_string(),
+                value: "9".to_string()
+            })
+        );
+        assert!(options.set_path("eval.opening.knight=a lot").is_err());
+        assert_eq!(
+            options.set_path("eval.opening.king=0"),
+            Err(OptionError::UnknownOption("eval.opening.king".to_string()))
+        );
+        assert!(options.set_path("search.hash=64").is_err());
+        assert!(options.set_path("search.null_move").is_err());
+    }
+
+    #[test]
+    fn config_file() {
+        let mut options = EngineOptions::default();
+        let config = "# tuned\nNull Move Reduction = 2\n\nContempt=15\n";
+        options.load_config(config).unwrap();
+        assert_eq!(options.search.null_move_reduction, 2);
+        assert_eq!(options.contempt, 15);
+        assert_eq!(
+            options.load_config("Contempt 15"),
+            Err(OptionError::UnknownOption("Contempt 15".to_string()))
+        );
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let mut options = EngineOptions::default();
+        assert_eq!(
+            options.set("Hash", "0"),
+            Err(OptionError::InvalidValue {
+                name: "Hash".to_string(),
+                value: "0".to_string()
+            })
+        );
+        assert!(options.set("Contempt", "abc").is_err());
+        assert!(options.set("Ponder", "yes").is_err());
+        assert_eq!(options, EngineOptions::default());
+    }
+
+    #[test]
+    fn eval_file_must_be_registered() {
+        let mut options = EngineOptions::default();
+        assert!(options.set("EvalFile", "naive").is_ok());
+        assert!(options.set("EvalFile", "unknown").is_err());
+        assert!(matches!(
+            options.set("EvalFile", "nnue:/nonexistent"),
+            Err(OptionError::Load { .. })
+        ));
+        assert_eq!(options.eval_file, "naive");
+    }
+
+    #[test]
+    fn book_file_must_be_readable() {
+        let mut options = EngineOptions::default();
+        assert!(matches!(
+            options.set("Book File", "/nonexistent.bin"),
+            Err(OptionError::Load { .. })
+        ));
+        assert_eq!(options.book_file, None);
+        options.set("OwnBook", "true").unwrap();
+        options.set("Book File", "<empty>").unwrap();
+        assert!(options.own_book);
+        assert_eq!(options.book_file, None);
+    }
+
+    #[test]
+    #[cfg(feature = "syzygy")]
+    fn syzygy_path_must_be_readable() {
+        let mut options = EngineOptions::default();
+        assert!(matches!(
+            options.set("SyzygyPath", "/nonexistent"),
+            Err(OptionError::Load { .. })
+        ));
+        let directory = std::env::temp_dir();
+        let directory = directory.to_str().unwrap();
+        options.set("SyzygyPath", directory).unwrap();
+        assert_eq!(options.syzygy_path.as_deref(), Some(directory));
+        options.set("SyzygyPath", "<empty>").unwrap();
+        assert_eq!(options.syzygy_path, None);
+    }
+
+    #[test]
+    #[cfg(feature = "gaviota")]
+    fn gaviota_tb_path_must_be_readable() {
+        let mut options = EngineOptions::default();
+        assert!(matches!(
+            options.set("GaviotaTbPath", "/nonexistent"),
+            Err(OptionError::Load { .. })
+        ));
+        let directory = crate::gaviota::TEST_TABLES;
+        options.set("GaviotaTbPath", directory).unwrap();
+        assert_eq!(options.gaviota_tb_path.as_deref(), Some(directory));
+        options.set("GaviotaTbPath", "<empty>").unwrap();
+        assert_eq!(options.gaviota_tb_path, None);
+    }
+
+    #[test]
+    fn unknown_option() {
+        let mut options = EngineOptions::default();
+        assert_eq!(
+            options.set("Style", "Risky"),
+            Err(OptionError::UnknownOption("Style".to_string()))
+        );
+    }
+
+    #[test]
+    fn descriptions_follow_the_options() {
+        let mut options = EngineOptions::default();
+        options.set("Threads", "4").unwrap();
+        options.set("Split Mode", "ybwc").unwrap();
+        let described = options.describe();
+        let threads = described
+            .iter()
+            .find(|info| info.name == "Threads")
+            .unwrap();
+        assert_eq!(threads.group, "threads");
+        assert_eq!(threads.value, "4");
+        assert_eq!(threads.option_type, OptionType::Spin { min: 1, max: 512 });
+        assert_eq!(
+            threads.to_json(),
+            "{\"description\":\"Threads to split the moves of a search between\",\"group\":\"threads\",\"max\":512,\"min\":1,\"name\":\"Threads\",\"type\":\"spin\",\"value\":4}"
+        );
+        let split_mode = described
+            .iter()
+            .find(|info| info.name == "Split Mode")
+            .unwrap();
+        assert_eq!(
+            split_mode.uci_declaration(),
+            "option name Split Mode type combo default YBWC var Root var YBWC"
+        );
+        // every described value is accepted back
+        let mut copy = EngineOptions::default();
+        for info in &described {
+            copy.set(info.name, &info.value).unwrap();
+        }
+        assert_eq!(copy, options);
+    }
+
+    #[test]
+    fn every_option_is_declared() {
+        let declarations = EngineOptions::uci_declarations();
+        let syzygy = usize::from(cfg!(feature = "syzygy"));
+        let gaviota = usize::from(cfg!(feature = "gaviota"));
+        assert_eq!(declarations.len(), 32 + syzygy + gaviota);
+        assert_eq!(
+            declarations[0],
+            "option name Hash type spin default 16 min 1 max 65536"
+        );
+        assert_eq!(
+            declarations[4],
+            "option name Split Mode type combo default Root var Root var YBWC"
+        );
+        assert_eq!(
+            declarations[6],
+            "option name MultiPV type spin default 1 min 1 max 64"
+        );
+    }
+}
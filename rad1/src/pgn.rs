@@ -0,0 +1,459 @@
+use crate::dataset::Outcome;
+use crate::fen;
+use crate::notation::{self, Notation};
+use crate::{Action, ChessGame, ChessMove, Color, GameResult, Piece, Position, PositionStatus};
+use std::cmp;
+
+pub(crate) const STANDARD_START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// The move in Standard Algebraic Notation (SAN), e.g. `Nf3`, `exd5`, `O-O` or `e8=Q#`.
+///
+/// The move has to be legal in the position.
+pub fn san(position: &Position, chess_move: ChessMove) -> String {
+    let source = chess_move.get_source();
+    let dest = chess_move.get_dest();
+    let piece = position
+        .piece_on(source)
+        .expect("no piece on the source square");
+    let mut san = String::new();
+
+    let file_distance = dest.get_file().to_index() as i8 - source.get_file().to_index() as i8;
+    if piece == Piece::King && file_distance.abs() == 2 {
+        san.push_str(if file_distance > 0 { "O-O" } else { "O-O-O" });
+    } else if piece == Piece::Pawn {
+        // pawns capture diagonally, including en passant
+        if file_distance != 0 {
+            san.push(file_char(source));
+            san.push('x');
+        }
+        san.push_str(&dest.to_string());
+        if let Some(promotion) = chess_move.get_promotion() {
+            san.push('=');
+            san.push_str(&promotion.to_string(Color::White));
+        }
+    } else {
+        san.push_str(&piece.to_string(Color::White));
+        san.push_str(&disambiguation(position, chess_move, piece));
+        if position.piece_on(dest).is_some() {
+            san.push('x');
+        }
+        san.push_str(&dest.to_string());
+    }
+
+    let next = position.make_move_new(chess_move);
+    if next.status() == PositionStatus::Checkmate {
+        san.push('#');
+    } else if next.in_check() {
+        san.push('+');
+    }
+    san
+}
+
+fn file_char(square: crate::Square) -> char {
+    (b'a' + square.get_file().to_index() as u8) as char
+}
+
+// The file and/or rank needed to tell the move apart from other moves
+// of the same kind of piece to the same square
+fn disambiguation(position: &Position, chess_move: ChessMove, piece: Piece) -> String {
+    let source = chess_move.get_source();
+    let others: Vec<ChessMove> = position
+        .legal_moves_iter()
+        .filter(|m| {
+            m.get_dest() == chess_move.get_dest()
+                && m.get_source() != source
+                && position.piece_on(m.get_source()) == Some(piece)
+        })
+        .collect();
+    if others.is_empty() {
+        String::new()
+    } else if others
+        .iter()
+        .all(|m| m.get_source().get_file() != source.get_file())
+    {
+        file_char(source).to_string()
+    } else if others
+        .iter()
+        .all(|m| m.get_source().get_rank() != source.get_rank())
+    {
+        (source.get_rank().to_index() + 1).to_string()
+    } else {
+        source.to_string()
+    }
+}
+
+/// The moves of the game in SAN with move numbers, e.g. `1. e4 e5 2. Nf3`
+pub fn movetext(start: &Position, moves: &[ChessMove]) -> String {
+    movetext_in(start, moves, Notation::English)
+}
+
+/// The moves of the game like [`movetext`] in the notation, e.g.
+/// `1. e4 e5 2. Sf3` in German
+pub fn movetext_in(start: &Position, moves: &[ChessMove], notation: Notation) -> String {
+    let mut position = start.clone();
+    let mut fullmove = fullmove_number(start);
+    let mut parts = Vec::with_capacity(moves.len() * 3 / 2);
+    for (i, &chess_move) in moves.iter().enumerate() {
+        match position.side_to_move() {
+            Color::White => parts.push(format!("{}.", fullmove)),
+            Color::Black if i == 0 => parts.push(format!("{}...", fullmove)),
+            Color::Black => {}
+        }
+        let side = position.side_to_move();
+        parts.push(notation.translate(&san(&position, chess_move), side));
+        if side == Color::Black {
+            fullmove += 1;
+        }
+        position = position.make_move_new(chess_move);
+    }
+    parts.join(" ")
+}
+
+/// The PGN result token of a game, `*` while it is still being played
+pub fn result_token(result: Option<GameResult>) -> &'static str {
+    match result {
+        Some(GameResult::WhiteCheckmates) | Some(GameResult::BlackResigns) => "1-0",
+        Some(GameResult::BlackCheckmates) | Some(GameResult::WhiteResigns) => "0-1",
+        Some(GameResult::Stalemate)
+        | Some(GameResult::DrawAccepted)
+        | Some(GameResult::DrawDeclared) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+/// Export the game, finished or not, as PGN
+pub fn to_pgn(game: &ChessGame) -> String {
+    to_pgn_in(game, Notation::English)
+}
+
+/// Export the game as PGN with the moves in the notation, only English is
+/// standard PGN that other programs read
+pub fn to_pgn_in(game: &ChessGame, notation: Notation) -> String {
+    let start = game.start_position();
+    let result = result_token(game.result());
+    let mut pgn = String::new();
+    pgn.push_str("[Event \"?\"]\n");
+    pgn.push_str("[Site \"?\"]\n");
+    pgn.push_str("[White \"?\"]\n");
+    pgn.push_str("[Black \"?\"]\n");
+    pgn.push_str(&format!("[Result \"{}\"]\n", result));
+    let fen = start.to_string();
+    if fen != STANDARD_START {
+        pgn.push_str("[SetUp \"1\"]\n");
+        pgn.push_str(&format!("[FEN \"{}\"]\n", fen));
+    }
+    pgn.push('\n');
+    let movetext = movetext_in(&start, &game.moves(), notation);
+    if !movetext.is_empty() {
+        pgn.push_str(&movetext);
+        pgn.push(' ');
+    }
+    pgn.push_str(result);
+    pgn.push('\n');
+    pgn
+}
+
+/// The legal move of the position written in SAN, check and annotation
+/// symbols like `+`, `#`, `!` or `?` are optional, castling may be
+/// written with zeros and pieces with figurines
+pub fn parse_san(position: &Position, text: &str) -> Option<ChessMove> {
+    let text = notation::without_figurines(text.trim_end_matches(|c| "+#!?".contains(c)))
+        .replace('0', "O");
+    position.legal_moves_iter().find(|&chess_move| {
+        san(position, chess_move).trim_end_matches(|c| c == '+' || c == '#') == text
+    })
+}
+
+/// A game of a PGN database: its tags and the moves of its main line in SAN
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub sans: Vec<String>,
+}
+
+impl PgnGame {
+    /// The value of the first tag with the name
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The result of the `Result` tag, None for unfinished games
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.tag("Result").and_then(Outcome::parse)
+    }
+
+    /// The position of the `FEN` tag, or the standard start without one.
+    /// None if the tag isn't a valid FEN, see [`fen::parse`].
+    pub fn start(&self) -> Option<Position> {
+        fen::parse(self.tag("FEN").unwrap_or(STANDARD_START)).ok()
+    }
+
+    /// The first `plies` moves of the main line, None if one of them isn't legal
+    pub fn moves(&self, plies: usize) -> Option<Vec<ChessMove>> {
+        let mut position = self.start()?;
+        let mut moves = Vec::new();
+        for text in self.sans.iter().take(plies) {
+            let chess_move = parse_san(&position, text)?;
+            position = position.make_move_new(chess_move);
+            moves.push(chess_move);
+        }
+        Some(moves)
+    }
+
+    /// The game of the main line, to be continued with the history of its
+    /// positions for repetitions. None if its start or one of its moves
+    /// isn't valid.
+    pub fn to_game(&self) -> Option<ChessGame> {
+        let mut game = fen::parse_game(self.tag("FEN").unwrap_or(STANDARD_START)).ok()?;
+        for chess_move in self.moves(self.sans.len())? {
+            game.take_action(Action::MakeMove(chess_move));
+        }
+        Some(game)
+    }
+}
+
+/// Read the games of a PGN database, skipping comments, variations and
+/// numeric annotation glyphs. Moves aren't checked until
+/// [`PgnGame::moves`] is called.
+pub fn read_games(text: &str) -> Vec<PgnGame> {
+    let mut games = Vec::new();
+    let mut game = PgnGame::default();
+    let mut movetext = String::new();
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            if !movetext.is_empty() {
+                game.sans = main_line(&movetext);
+                games.push(std::mem::take(&mut game));
+                movetext.clear();
+            }
+            if let Some(tag) = parse_tag(line) {
+                game.tags.push(tag);
+            }
+        } else if !line.is_empty() {
+            movetext.push_str(line);
+            movetext.push('\n');
+        }
+    }
+    if !movetext.is_empty() || !game.tags.is_empty() {
+        game.sans = main_line(&movetext);
+        games.push(game);
+    }
+    games
+}
+
+// A `[Name "Value"]` line
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (name, value) = inner.split_once(' ')?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some((name.to_string(), value.replace("\\\"", "\"")))
+}
+
+// The SAN moves of the movetext outside of comments and variations
+fn main_line(movetext: &str) -> Vec<String> {
+    let mut sans = Vec::new();
+    let mut token = String::new();
+    let mut variations = 0;
+    let mut chars = movetext.chars();
+    while let Some(c) = chars.next() {
+        if !"{;()".contains(c) && !c.is_whitespace() {
+            token.push(c);
+            continue;
+        }
+        if variations == 0 {
+            sans.extend(san_token(&token));
+        }
+        token.clear();
+        match c {
+            '{' => while chars.next().map_or(false, |c| c != '}') {},
+            ';' => while chars.next().map_or(false, |c| c != '\n') {},
+            '(' => variations += 1,
+            ')' => variations = cmp::max(variations, 1) - 1,
+            _ => {}
+        }
+    }
+    if variations == 0 {
+        sans.extend(san_token(&token));
+    }
+    sans
+}
+
+// The move of a movetext token, without its move number like `12.` or `12...`.
+// Results and annotation glyphs aren't moves.
+fn san_token(token: &str) -> Option<String> {
+    if token.starts_with("0-0") {
+        return Some(token.to_string());
+    }
+    if ["1-0", "0-1", "1/2-1/2", "*"].contains(&token) || token.starts_with('$') {
+        return None;
+    }
+    let san = token
+        .trim_start_matches(|c: char| c.is_ascii_digit())
+        .trim_start_matches('.');
+    if san.is_empty() {
+        None
+    } else {
+        Some(san.to_string())
+    }
+}
+
+pub(crate) fn fullmove_number(position: &Position) -> u32 {
+    position
+        .to_string()
+        .split_whitespace()
+        .nth(5)
+        .and_then(|number| number.parse().ok())
+        .unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{movetext, movetext_in, parse_san, read_games, san, to_pgn};
+    use crate::dataset::Outcome;
+    use crate::notation::Notation;
+    use crate::{Action, ChessGame, ChessMove, GameResult, Piece, Position, Square};
+    use std::str::FromStr;
+    use test_case::test_case;
+
+    #[test_case(
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "g1f3",
+        "Nf3"
+    )]
+    #[test_case(
+        "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+        "e4d5",
+        "exd5"
+    )]
+    #[test_case("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1g1", "O-O")]
+    #[test_case("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "e8c8", "O-O-O")]
+    #[test_case("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8", "Ra8#")]
+    #[test_case("4k3/8/8/8/8/8/4K3/R6R w - - 0 1", "a1d1", "Rad1")]
+    #[test_case("4k3/8/8/8/R7/8/8/R3K3 w - - 0 1", "a1a2", "R1a2")]
+    #[test_case("4k3/8/8/4pP2/8/8/8/4K3 w - e6 0 1", "f5e6", "fxe6")]
+    fn san_notation(fen: &str, uci: &str, expected: &str) {
+        let position = Position::from_str(fen).unwrap();
+        let chess_move = ChessMove::from_str(uci).unwrap();
+        assert_eq!(san(&position, chess_move), expected);
+    }
+
+    #[test]
+    fn promotion() {
+        let position = Position::from_str("8/4P1k1/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let chess_move = ChessMove::new(Square::E7, Square::E8, Some(Piece::Queen));
+        assert_eq!(san(&position, chess_move), "e8=Q");
+    }
+
+    #[test]
+    fn movetext_starting_with_black() {
+        let position =
+            Position::from_str("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1")
+                .unwrap();
+        let moves = [
+            ChessMove::new(Square::E7, Square::E5, None),
+            ChessMove::new(Square::G1, Square::F3, None),
+        ];
+        assert_eq!(movetext(&position, &moves), "1... e5 2. Nf3");
+    }
+
+    #[test_case(Notation::German, "1. e4 e5 2. Sf3 Sc6 3. Lb5")]
+    #[test_case(Notation::Figurine, "1. e4 e5 2. ♘f3 ♞c6 3. ♗b5")]
+    fn movetext_in_other_notations(notation: Notation, expected: &str) {
+        let mut position = Position::default();
+        let mut moves = Vec::new();
+        for text in ["e4", "e5", "Nf3", "Nc6", "Bb5"].iter() {
+            let chess_move = parse_san(&position, text).unwrap();
+            position = position.make_move_new(chess_move);
+            moves.push(chess_move);
+        }
+        assert_eq!(
+            movetext_in(&Position::default(), &moves, notation),
+            expected
+        );
+    }
+
+    #[test]
+    fn unfinished_game() {
+        let mut game = ChessGame::default();
+        game.take_action(Action::MakeMove(ChessMove::new(
+            Square::E2,
+            Square::E4,
+            None,
+        )));
+        game.take_action(Action::MakeMove(ChessMove::new(
+            Square::E7,
+            Square::E5,
+            None,
+        )));
+        let pgn = to_pgn(&game);
+        assert!(pgn.contains("[Result \"*\"]"));
+        assert!(!pgn.contains("[FEN"));
+        assert!(pgn.ends_with("\n1. e4 e5 *\n"));
+    }
+
+    #[test]
+    fn game_from_fen() {
+        let game = ChessGame::from_str("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let pgn = to_pgn(&game);
+        assert!(pgn.contains("[FEN \"6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\"]"));
+    }
+
+    #[test_case("Nf3", "g1f3"; "piece move")]
+    #[test_case("e4!?", "e2e4"; "annotated")]
+    #[test_case("Nc3+", "b1c3"; "wrong check symbol")]
+    #[test_case("♘f3", "g1f3"; "figurine")]
+    fn san_parsing(text: &str, uci: &str) {
+        let chess_move = parse_san(&Position::default(), text);
+        assert_eq!(chess_move, Some(ChessMove::from_str(uci).unwrap()));
+    }
+
+    #[test]
+    fn castling_with_zeros() {
+        let position = Position::from_str("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(
+            parse_san(&position, "0-0-0"),
+            Some(ChessMove::new(Square::E1, Square::C1, None))
+        );
+        assert_eq!(parse_san(&position, "Nf3"), None);
+    }
+
+    #[test]
+    fn database() {
+        let text = "[Event \"One\"]\n[Result \"1-0\"]\n\n1. e4 {best by test} e5 (1... c5 2. Nf3) 2. Nf3 $1 Nc6\n3. Bb5 ; the Spanish\na6 1-0\n\n[Event \"Two\"]\n[Result \"*\"]\n\n1.d4 d5 *\n";
+        let games = read_games(text);
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].tag("Event"), Some("One"));
+        assert_eq!(games[0].outcome(), Some(Outcome::WhiteWins));
+        assert_eq!(games[0].sans, vec!["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"]);
+        assert_eq!(games[0].moves(3).unwrap().len(), 3);
+        assert_eq!(games[1].outcome(), None);
+        assert_eq!(games[1].sans, vec!["d4", "d5"]);
+    }
+
+    #[test]
+    fn illegal_moves() {
+        let games = read_games("[Result \"0-1\"]\n\n1. e4 e5 2. Ke3 *\n");
+        assert_eq!(games[0].moves(2).map(|moves| moves.len()), Some(2));
+        assert_eq!(games[0].moves(3), None);
+        assert!(games[0].to_game().is_none());
+    }
+
+    #[test]
+    fn games_are_replayed() {
+        let text = "[Result \"*\"]\n\n1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8 5. Nf3 Nf6 6. Ng1 Ng8 *\n";
+        let game = read_games(text)[0].to_game().unwrap();
+        assert_eq!(game.moves().len(), 12);
+        assert!(game.can_declare_draw());
+        assert_eq!(
+            to_pgn(&game),
+            to_pgn(&read_games(&to_pgn(&game))[0].to_game().unwrap())
+        );
+
+        let text = "[FEN \"6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\"]\n\n1. Ra8# 1-0\n";
+        let game = read_games(text)[0].to_game().unwrap();
+        assert_eq!(game.result(), Some(GameResult::WhiteCheckmates));
+    }
+}
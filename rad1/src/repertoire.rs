@@ -0,0 +1,250 @@
+use crate::agent::AlphaBetaChessAgent;
+use crate::fmt;
+use crate::pgn;
+use crate::score::Score;
+use crate::{ChessMove, Color, Position};
+use std::collections::HashMap;
+
+/// A move of the repertoire with the engine's replies to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepertoireMove {
+    pub chess_move: ChessMove,
+    /// The score of the move for the side playing it
+    pub score: Score,
+    /// The best replies, best first
+    pub replies: Vec<RepertoireMove>,
+    /// The line from the start that reached the position after this move
+    /// first, its replies are only explored there
+    pub transposes_to: Option<Vec<ChessMove>>,
+}
+
+/// The best moves of a position and the best replies to them,
+/// explored with the engine to a fixed breadth and number of plies
+#[derive(Clone)]
+pub struct Repertoire {
+    pub start: Position,
+    /// The best moves in the start position, best first
+    pub moves: Vec<RepertoireMove>,
+}
+
+impl Repertoire {
+    /// Explore the `breadth` best moves of every position up to `plies` moves
+    /// from the start, each position searched by the agent.
+    /// Positions reached again by another line are linked to the first line
+    /// instead of being explored twice.
+    pub fn build(
+        agent: &mut AlphaBetaChessAgent,
+        start: &Position,
+        breadth: usize,
+        plies: usize,
+    ) -> Self {
+        let mut explorer = Explorer {
+            agent,
+            breadth,
+            seen: HashMap::new(),
+        };
+        explorer.seen.insert(start.get_hash(), Vec::new());
+        let moves = explorer.explore(start, &mut Vec::new(), plies);
+        agent.set_excluded_moves(Vec::new());
+        Self {
+            start: start.clone(),
+            moves,
+        }
+    }
+
+    /// The repertoire as PGN, the best move of every position is the main
+    /// line and the others are variations. Every move is followed by the
+    /// score from white's point of view and, for transpositions, the line it
+    /// transposes to.
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::from("[Event \"Repertoire\"]\n[Result \"*\"]\n");
+        let fen = self.start.to_string();
+        if fen != pgn::STANDARD_START {
+            pgn.push_str("[SetUp \"1\"]\n");
+            pgn.push_str(&format!("[FEN \"{}\"]\n", fen));
+        }
+        pgn.push('\n');
+        let mut writer = Writer {
+            start: &self.start,
+            movetext: Vec::new(),
+        };
+        writer.moves(&self.start, pgn::fullmove_number(&self.start), &self.moves);
+        writer.movetext.push("*".to_string());
+        pgn.push_str(&writer.movetext.join(" "));
+        pgn.push('\n');
+        pgn
+    }
+}
+
+struct Explorer<'a> {
+    agent: &'a mut AlphaBetaChessAgent,
+    breadth: usize,
+    // the first line from the start that reached each position
+    seen: HashMap<u64, Vec<ChessMove>>,
+}
+
+impl<'a> Explorer<'a> {
+    fn explore(
+        &mut self,
+        position: &Position,
+        line: &mut Vec<ChessMove>,
+        plies: usize,
+    ) -> Vec<RepertoireMove> {
+        if plies == 0 {
+            return Vec::new();
+        }
+        let mut best = Vec::new();
+        let mut excluded = Vec::new();
+        for _ in 0..self.breadth.min(position.count_moves()) {
+            self.agent.set_excluded_moves(excluded.clone());
+            let result = self.agent.search(position);
+            excluded.push(result.best_move);
+            best.push((result.best_move, result.score));
+        }
+
+        let mut moves = Vec::with_capacity(best.len());
+        for (chess_move, score) in best {
+            let child = position.make_move_new(chess_move);
+            line.push(chess_move);
+            let transposes_to = self.seen.get(&child.get_hash()).cloned();
+            let replies = match transposes_to {
+                Some(_) => Vec::new(),
+                None => {
+                    self.seen.insert(child.get_hash(), line.clone());
+                    self.explore(&child, line, plies - 1)
+                }
+            };
+            line.pop();
+            moves.push(RepertoireMove {
+                chess_move,
+                score,
+                replies,
+                transposes_to,
+            });
+        }
+        moves
+    }
+}
+
+// Builds the movetext of a repertoire
+struct Writer<'a> {
+    start: &'a Position,
+    movetext: Vec<String>,
+}
+
+impl<'a> Writer<'a> {
+    // Append the moves of a position as a main line with variations
+    fn moves(&mut self, position: &Position, fullmove: u32, moves: &[RepertoireMove]) {
+        let main = match moves.first() {
+            Some(main) => main,
+            None => return,
+        };
+        self.one_move(position, fullmove, main);
+        for variation in &moves[1..] {
+            self.movetext.push("(".to_string());
+            self.one_move(position, fullmove, variation);
+            self.replies(position, fullmove, variation);
+            self.movetext.push(")".to_string());
+        }
+        self.replies(position, fullmove, main);
+    }
+
+    // Every move is followed by a comment, so black's moves always get a number
+    fn one_move(&mut self, position: &Position, fullmove: u32, repertoire_move: &RepertoireMove) {
+        match position.side_to_move() {
+            Color::White => self.movetext.push(format!("{}.", fullmove)),
+            Color::Black => self.movetext.push(format!("{}...", fullmove)),
+        }
+        self.movetext
+            .push(pgn::san(position, repertoire_move.chess_move));
+        let score = match position.side_to_move() {
+            Color::White => repertoire_move.score,
+            Color::Black => -repertoire_move.score,
+        };
+        let comment = match &repertoire_move.transposes_to {
+            Some(line) => format!(
+                "{{{} transposes to {}}}",
+                fmt::score(score),
+                pgn::movetext(self.start, line)
+            ),
+            None => format!("{{{}}}", fmt::score(score)),
+        };
+        self.movetext.push(comment);
+    }
+
+    fn replies(&mut self, position: &Position, fullmove: u32, repertoire_move: &RepertoireMove) {
+        let child = position.make_move_new(repertoire_move.chess_move);
+        let fullmove = match position.side_to_move() {
+            Color::White => fullmove,
+            Color::Black => fullmove + 1,
+        };
+        self.moves(&child, fullmove, &repertoire_move.replies);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Repertoire, RepertoireMove};
+    use crate::agent;
+    use crate::score::Score;
+    use crate::tt::TranspositionTable;
+    use crate::{ChessMove, Position, Square};
+    use std::str::FromStr;
+
+    fn count(moves: &[RepertoireMove]) -> usize {
+        moves.iter().map(|m| 1 + count(&m.replies)).sum::<usize>()
+    }
+
+    #[test]
+    fn explores_breadth_and_plies() {
+        let mut agent = agent::alpha_beta_agent(2, TranspositionTable::new(100_000));
+        let repertoire = Repertoire::build(&mut agent, &Position::default(), 2, 2);
+        assert_eq!(repertoire.moves.len(), 2);
+        assert_ne!(
+            repertoire.moves[0].chess_move,
+            repertoire.moves[1].chess_move
+        );
+        assert_eq!(count(&repertoire.moves), 6);
+        assert!(repertoire.moves[0].score >= repertoire.moves[1].score);
+    }
+
+    #[test]
+    fn transpositions_are_linked() {
+        let start = Position::from_str("8/8/8/4k3/8/8/8/K7 w - - 0 1").unwrap();
+        let mut agent = agent::alpha_beta_agent(1, TranspositionTable::new(100_000));
+        let repertoire = Repertoire::build(&mut agent, &start, 8, 3);
+        fn linked(moves: &[RepertoireMove]) -> bool {
+            moves
+                .iter()
+                .any(|m| (m.transposes_to.is_some() && m.replies.is_empty()) || linked(&m.replies))
+        }
+        assert!(linked(&repertoire.moves));
+        assert!(repertoire.to_pgn().contains("transposes to 1."));
+    }
+
+    #[test]
+    fn pgn_with_variations() {
+        let e4 = ChessMove::new(Square::E2, Square::E4, None);
+        let d4 = ChessMove::new(Square::D2, Square::D4, None);
+        let e5 = ChessMove::new(Square::E7, Square::E5, None);
+        let leaf = |chess_move, score| RepertoireMove {
+            chess_move,
+            score,
+            replies: Vec::new(),
+            transposes_to: None,
+        };
+        let repertoire = Repertoire {
+            start: Position::default(),
+            moves: vec![
+                RepertoireMove {
+                    replies: vec![leaf(e5, Score::Eval(-2))],
+                    ..leaf(e4, Score::Eval(3))
+                },
+                leaf(d4, Score::Eval(1)),
+            ],
+        };
+        let pgn = repertoire.to_pgn();
+        assert!(pgn.starts_with("[Event \"Repertoire\"]\n"));
+        assert!(pgn.ends_with("\n1. e4 {+0.30} ( 1. d4 {+0.10} ) 1... e5 {+0.20} *\n"));
+    }
+}
@@ -0,0 +1,307 @@
+use crate::eval::Evaluator;
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Neg;
+
+// The encoded value of checkmating right now
+const MATE: i16 = 30000;
+// Centipawns in one evaluation unit, a pawn is worth 10 units
+const CENTIPAWNS_PER_UNIT: i16 = 10;
+// Encoded values at least this far from zero are mate scores
+const MATE_BOUND: i16 = MATE - u8::MAX as i16;
+// Centipawns of checkmating right now in Stockfish's training data
+const MATE_CENTIPAWNS: i32 = 32000;
+
+/// The value of a position from the side to move's point of view.
+///
+/// The search works with plain `i16` values, mate scores are encoded as
+/// `MATE - distance` so that a quicker mate is always worth more than a
+/// slower one and any mate is worth more than any evaluation.
+/// Mate distances are counted in plies.
+///
+/// Converting to and from `i16` is lossless for every `Score` whose evaluation
+/// lies strictly between the mate bounds, evaluations outside of them are
+/// clamped so they can never be confused with a mate.
+#[derive(Debug, Clone, Copy)]
+pub enum Score {
+    /// The side to move gets checkmated in the given number of plies
+    Mated(u8),
+    /// An evaluation that isn't a forced mate
+    Eval(i16),
+    /// The side to move checkmates in the given number of plies
+    Mate(u8),
+}
+
+impl Score {
+    pub const DRAW: Score = Score::Eval(0);
+
+    /// Translate an evaluator's result for a position `ply` plies from the root.
+    ///
+    /// The evaluator's minimum means the side to move is checkmated and its
+    /// maximum means the side to move has already won.
+    pub fn from_evaluation<E>(evaluator: &E, value: i16, ply: usize) -> Self
+    where
+        E: Evaluator<Result = i16> + ?Sized,
+    {
+        if value <= evaluator.min_value() {
+            Score::Mated(mate_distance(ply))
+        } else if value >= evaluator.max_value() {
+            Score::Mate(mate_distance(ply))
+        } else {
+            Score::Eval(value)
+        }
+    }
+
+    /// Encode a score, found `ply` plies from the root, for the transposition table.
+    ///
+    /// Mate distances are stored relative to the position itself so the entry
+    /// stays correct when the position is reached at a different ply.
+    pub fn to_tt(self, ply: usize) -> i16 {
+        match self {
+            Score::Mated(distance) => Score::Mated(distance.saturating_sub(mate_distance(ply))),
+            Score::Mate(distance) => Score::Mate(distance.saturating_sub(mate_distance(ply))),
+            Score::Eval(_) => self,
+        }
+        .into()
+    }
+
+    /// Decode a transposition table value for a position `ply` plies from the root
+    pub fn from_tt(value: i16, ply: usize) -> Self {
+        match Score::from(value) {
+            Score::Mated(distance) => Score::Mated(distance.saturating_add(mate_distance(ply))),
+            Score::Mate(distance) => Score::Mate(distance.saturating_add(mate_distance(ply))),
+            score => score,
+        }
+    }
+
+    pub fn is_mate(self) -> bool {
+        !matches!(self, Score::Eval(_))
+    }
+
+    /// The number of full moves until mate, negative when the side to move gets mated
+    pub fn mate_in_moves(self) -> Option<i16> {
+        match self {
+            Score::Mated(distance) => Some(-((distance as i16 + 1) / 2)),
+            Score::Eval(_) => None,
+            Score::Mate(distance) => Some((distance as i16 + 1) / 2),
+        }
+    }
+
+    /// The score in the format of a UCI `info score`, `cp <centipawns>` or `mate <moves>`
+    pub fn to_uci(self) -> String {
+        match self.mate_in_moves() {
+            Some(moves) => format!("mate {}", moves),
+            None => format!(
+                "cp {}",
+                i32::from(i16::from(self)) * i32::from(CENTIPAWNS_PER_UNIT)
+            ),
+        }
+    }
+
+    /// The score as a JSON object, `{"cp": <centipawns>}` or `{"mate": <moves>}`
+    pub fn to_json(self) -> String {
+        match self.mate_in_moves() {
+            Some(moves) => format!("{{\"mate\": {}}}", moves),
+            None => format!("{{\"cp\": {}}}", self.to_centipawns()),
+        }
+    }
+
+    /// The score in centipawns the way Stockfish's training data stores it,
+    /// mates as 32000 minus the plies to mate and evaluations kept below that
+    pub fn to_centipawns(self) -> i32 {
+        let mate_bound = MATE_CENTIPAWNS - i32::from(u8::MAX);
+        match self {
+            Score::Mated(distance) => i32::from(distance) - MATE_CENTIPAWNS,
+            Score::Eval(_) => (i32::from(i16::from(self)) * i32::from(CENTIPAWNS_PER_UNIT))
+                .clamp(1 - mate_bound, mate_bound - 1),
+            Score::Mate(distance) => MATE_CENTIPAWNS - i32::from(distance),
+        }
+    }
+}
+
+fn mate_distance(ply: usize) -> u8 {
+    ply.min(u8::MAX as usize) as u8
+}
+
+impl From<Score> for i16 {
+    fn from(score: Score) -> i16 {
+        match score {
+            Score::Mated(distance) => -MATE + distance as i16,
+            Score::Eval(value) => value.clamp(-MATE_BOUND + 1, MATE_BOUND - 1),
+            Score::Mate(distance) => MATE - distance as i16,
+        }
+    }
+}
+
+impl From<i16> for Score {
+    fn from(value: i16) -> Score {
+        if value >= MATE_BOUND {
+            Score::Mate(MATE.saturating_sub(value).max(0) as u8)
+        } else if value <= -MATE_BOUND {
+            Score::Mated(value.saturating_add(MATE).max(0) as u8)
+        } else {
+            Score::Eval(value)
+        }
+    }
+}
+
+impl Neg for Score {
+    type Output = Score;
+
+    fn neg(self) -> Score {
+        match self {
+            Score::Mated(distance) => Score::Mate(distance),
+            Score::Eval(value) => Score::Eval(-value),
+            Score::Mate(distance) => Score::Mated(distance),
+        }
+    }
+}
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Score) -> bool {
+        i16::from(*self) == i16::from(*other)
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Score) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Score) -> Ordering {
+        i16::from(*self).cmp(&i16::from(*other))
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mate_in_moves() {
+            Some(moves) => write!(f, "#{}", moves),
+            None => write!(f, "{}", i16::from(*self)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Score, MATE, MATE_BOUND};
+    use crate::eval::naive_evaluator;
+    use test_case::test_case;
+
+    #[test]
+    fn ordering() {
+        let ordered = [
+            Score::Mated(0),
+            Score::Mated(1),
+            Score::Mated(200),
+            Score::Eval(i16::MIN),
+            Score::Eval(-500),
+            Score::DRAW,
+            Score::Eval(500),
+            Score::Eval(i16::MAX),
+            Score::Mate(200),
+            Score::Mate(1),
+            Score::Mate(0),
+        ];
+        for (i, a) in ordered.iter().enumerate() {
+            for (j, b) in ordered.iter().enumerate() {
+                assert_eq!(a.cmp(b), i.cmp(&j), "{:?} vs {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn i16_round_trip() {
+        for value in -MATE..=MATE {
+            assert_eq!(i16::from(Score::from(value)), value);
+        }
+        for distance in 0..=u8::MAX {
+            assert!(
+                matches!(Score::from(i16::from(Score::Mate(distance))), Score::Mate(d) if d == distance)
+            );
+            assert!(
+                matches!(Score::from(i16::from(Score::Mated(distance))), Score::Mated(d) if d == distance)
+            );
+        }
+    }
+
+    #[test]
+    fn evaluations_are_clamped_below_mates() {
+        assert_eq!(i16::from(Score::Eval(i16::MAX)), MATE_BOUND - 1);
+        assert_eq!(i16::from(Score::Eval(i16::MIN)), -MATE_BOUND + 1);
+        assert!(!Score::from(i16::from(Score::Eval(i16::MAX))).is_mate());
+    }
+
+    #[test]
+    fn negation_switches_perspective() {
+        assert!(matches!(-Score::Mate(3), Score::Mated(3)));
+        assert!(matches!(-Score::Mated(4), Score::Mate(4)));
+        assert_eq!(-Score::Eval(25), Score::Eval(-25));
+        for value in -MATE..=MATE {
+            assert_eq!(i16::from(-Score::from(value)), -value);
+        }
+    }
+
+    #[test_case(0, 0)]
+    #[test_case(3, 7)]
+    #[test_case(10, 10)]
+    fn tt_round_trip(store_ply: usize, distance: u8) {
+        for &score in [
+            Score::Mate(distance),
+            Score::Mated(distance),
+            Score::Eval(42),
+        ]
+        .iter()
+        {
+            let encoded = score.to_tt(store_ply);
+            assert_eq!(Score::from_tt(encoded, store_ply), score);
+        }
+    }
+
+    #[test]
+    fn tt_mates_are_relative_to_position() {
+        // mate found 5 plies from the root in a position 2 plies from the root
+        let encoded = Score::Mate(5).to_tt(2);
+        // is mate in 3 plies from that position and 7 plies when reached at ply 4
+        assert_eq!(Score::from(encoded), Score::Mate(3));
+        assert_eq!(Score::from_tt(encoded, 4), Score::Mate(7));
+    }
+
+    #[test]
+    fn evaluator_bounds_are_mates() {
+        let evaluator = naive_evaluator();
+        assert_eq!(
+            Score::from_evaluation(&evaluator, -30000, 3),
+            Score::Mated(3)
+        );
+        assert_eq!(Score::from_evaluation(&evaluator, 30000, 2), Score::Mate(2));
+        assert_eq!(Score::from_evaluation(&evaluator, 0, 2), Score::DRAW);
+    }
+
+    #[test_case(Score::Mate(1), "#1")]
+    #[test_case(Score::Mate(4), "#2")]
+    #[test_case(Score::Mated(2), "#-1")]
+    #[test_case(Score::Eval(-35), "-35")]
+    fn display(score: Score, expected: &str) {
+        assert_eq!(score.to_string(), expected);
+    }
+
+    #[test_case(Score::Mate(3), "mate 2")]
+    #[test_case(Score::Mated(4), "mate -2")]
+    #[test_case(Score::Eval(-35), "cp -350")]
+    fn uci(score: Score, expected: &str) {
+        assert_eq!(score.to_uci(), expected);
+    }
+
+    #[test_case(Score::Mate(3), 31997)]
+    #[test_case(Score::Mated(4), -31996)]
+    #[test_case(Score::Eval(-35), -350)]
+    #[test_case(Score::Eval(20000), 31744)]
+    fn centipawns(score: Score, expected: i32) {
+        assert_eq!(score.to_centipawns(), expected);
+    }
+}
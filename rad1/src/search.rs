@@ -0,0 +1,299 @@
+use crate::score::Score;
+use crate::ChessMove;
+use std::ops::AddAssign;
+use std::time::Duration;
+
+pub mod blunder;
+pub mod pns;
+pub mod prefetch;
+pub mod progress;
+pub mod trace;
+
+/// Search features that can be switched off or adjusted, to find bugs
+/// and to measure what each of them is worth in matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Prune a node when passing the move still fails high
+    pub null_move: bool,
+    /// Depth reduction of the null-move search, one more when the position is improving
+    pub null_move_reduction: u8,
+    /// Search one ply deeper when in check, once per line
+    pub check_extensions: bool,
+    /// Try the quiet moves that caused a cutoff at the same ply right after the captures
+    pub killer_moves: bool,
+    /// Score positions of a king and one piece against a lone king with the
+    /// [built-in tablebases](crate::tbgen) instead of searching them
+    pub tablebases: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            null_move: true,
+            null_move_reduction: 3,
+            check_extensions: true,
+            killer_moves: true,
+            tablebases: true,
+        }
+    }
+}
+
+/// Counters of the events a search ran into
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SearchStats {
+    /// Quiescence searches cut short by the quiescence ply cap
+    pub q_ply_cutoffs: u64,
+    /// Quiescence searches cut short by the quiescence node limit
+    pub q_node_cutoffs: u64,
+    /// Iterations that took far more nodes than the trend of the ones before
+    /// them predicted, and finished with full windows instead of null windows
+    pub explosions: u64,
+    /// Root searches repeated with a wider window because the score fell
+    /// outside the aspiration window around the iteration before
+    pub aspiration_researches: u64,
+}
+
+impl AddAssign for SearchStats {
+    fn add_assign(&mut self, other: Self) {
+        self.q_ply_cutoffs += other.q_ply_cutoffs;
+        self.q_node_cutoffs += other.q_node_cutoffs;
+        self.explosions += other.explosions;
+        self.aspiration_researches += other.aspiration_researches;
+    }
+}
+
+/// The effort a search spent below one of the root moves, over all its iterations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RootMove {
+    pub chess_move: ChessMove,
+    /// The nodes of the move's subtrees
+    pub nodes: u64,
+    /// The time spent searching the move, summed over the threads that did
+    pub elapsed: Duration,
+}
+
+/// One of the other lines of a MultiPV search, see [`SearchResult::lines`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PvLine {
+    /// The score of the line from the side to move's point of view
+    pub score: Score,
+    /// The moves of the line, starting at the root
+    pub pv: Vec<ChessMove>,
+}
+
+/// How the score of a search relates to the true value of the position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreBound {
+    Exact,
+    /// The root failed high, the position is worth at least the score
+    Lower,
+    /// The root failed low, the position is worth at most the score
+    Upper,
+}
+
+impl ScoreBound {
+    /// The bound of a root value searched with the window from `alpha` to `beta`
+    pub fn of(value: i16, alpha: i16, beta: i16) -> Self {
+        if value <= alpha {
+            ScoreBound::Upper
+        } else if value >= beta {
+            ScoreBound::Lower
+        } else {
+            ScoreBound::Exact
+        }
+    }
+
+    pub fn is_exact(self) -> bool {
+        self == ScoreBound::Exact
+    }
+
+    /// The keyword UCI puts after a bound score, `lowerbound` or
+    /// `upperbound`. UCI has none for exact scores, they are `exact` here.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScoreBound::Exact => "exact",
+            ScoreBound::Lower => "lowerbound",
+            ScoreBound::Upper => "upperbound",
+        }
+    }
+}
+
+/// The outcome of searching a single position
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    /// The best move found
+    pub best_move: ChessMove,
+    /// The evaluation of the position from the side to move's point of view
+    pub score: Score,
+    /// Whether the score is exact or only a bound, see [`ScoreBound`]
+    pub bound: ScoreBound,
+    /// The depth of the last completed iteration
+    pub depth: u8,
+    /// The deepest ply any line reached, including extensions and the quiescence search
+    pub seldepth: u8,
+    /// The principal variation, starting with the best move
+    pub pv: Vec<ChessMove>,
+    /// The number of nodes visited
+    pub nodes: u64,
+    /// How long the search took
+    pub elapsed: Duration,
+    pub stats: SearchStats,
+    /// The best move of every completed iteration, starting at depth 1
+    pub best_moves: Vec<ChessMove>,
+    /// The root moves in the order they were first searched, with the
+    /// nodes and time spent on them
+    pub root_moves: Vec<RootMove>,
+    /// The next best lines when the `MultiPV` option asks for more than
+    /// one, each found without the first moves of the lines before it
+    pub lines: Vec<PvLine>,
+}
+
+impl SearchResult {
+    /// Returns true when the last iteration changed its mind about the best move,
+    /// a sign that a deeper search could pick yet another one
+    pub fn is_unstable(&self) -> bool {
+        match self.best_moves.as_slice() {
+            [.., previous, last] => previous != last,
+            _ => false,
+        }
+    }
+
+    /// The number of times the best move changed from one iteration to the next
+    pub fn best_move_changes(&self) -> usize {
+        self.best_moves.windows(2).filter(|w| w[0] != w[1]).count()
+    }
+
+    /// Nodes searched per second
+    pub fn nps(&self) -> u64 {
+        let micros = self.elapsed.as_micros().max(1);
+        (self.nodes as u128 * 1_000_000 / micros) as u64
+    }
+
+    /// The result as a UCI `info` line, with `lowerbound` or `upperbound`
+    /// after scores that aren't exact
+    pub fn uci_info(&self) -> String {
+        let mut score = self.score.to_uci();
+        if !self.bound.is_exact() {
+            score.push(' ');
+            score.push_str(self.bound.as_str());
+        }
+        format!("info {}", self.uci_fields(&score, &self.pv))
+    }
+
+    /// The `info` lines of the result, one for every line of a MultiPV
+    /// search numbered with `multipv`, or the [`uci_info`](Self::uci_info)
+    /// line alone
+    pub fn uci_infos(&self) -> Vec<String> {
+        if self.lines.is_empty() {
+            return vec![self.uci_info()];
+        }
+        let first = self.uci_info().replacen("info", "info multipv 1", 1);
+        let others = self.lines.iter().enumerate().map(|(index, line)| {
+            let fields = self.uci_fields(&line.score.to_uci(), &line.pv);
+            format!("info multipv {} {}", index + 2, fields)
+        });
+        std::iter::once(first).chain(others).collect()
+    }
+
+    fn uci_fields(&self, score: &str, pv: &[ChessMove]) -> String {
+        let pv: Vec<String> = pv.iter().map(|m| m.to_string()).collect();
+        format!(
+            "depth {} seldepth {} score {} nodes {} nps {} time {} pv {}",
+            self.depth,
+            self.seldepth,
+            score,
+            self.nodes,
+            self.nps(),
+            self.elapsed.as_millis(),
+            pv.join(" ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PvLine, ScoreBound, SearchResult, SearchStats};
+    use crate::score::Score;
+    use crate::{ChessMove, Square};
+    use std::time::Duration;
+    use test_case::test_case;
+
+    #[test]
+    fn uci_info_line() {
+        let e4 = ChessMove::new(Square::E2, Square::E4, None);
+        let e5 = ChessMove::new(Square::E7, Square::E5, None);
+        let result = SearchResult {
+            best_move: e4,
+            score: Score::Eval(3),
+            bound: ScoreBound::Exact,
+            depth: 2,
+            seldepth: 5,
+            pv: vec![e4, e5],
+            nodes: 1500,
+            elapsed: Duration::from_millis(500),
+            stats: SearchStats::default(),
+            best_moves: vec![e4, e4],
+            root_moves: Vec::new(),
+            lines: Vec::new(),
+        };
+        assert_eq!(
+            result.uci_info(),
+            "info depth 2 seldepth 5 score cp 30 nodes 1500 nps 3000 time 500 pv e2e4 e7e5"
+        );
+        let result = SearchResult {
+            bound: ScoreBound::Lower,
+            ..result
+        };
+        assert_eq!(
+            result.uci_info(),
+            "info depth 2 seldepth 5 score cp 30 lowerbound nodes 1500 nps 3000 time 500 pv e2e4 e7e5"
+        );
+        let d4 = ChessMove::new(Square::D2, Square::D4, None);
+        let result = SearchResult {
+            bound: ScoreBound::Exact,
+            lines: vec![PvLine {
+                score: Score::Eval(-1),
+                pv: vec![d4],
+            }],
+            ..result
+        };
+        assert_eq!(
+            result.uci_infos(),
+            vec![
+                "info multipv 1 depth 2 seldepth 5 score cp 30 nodes 1500 nps 3000 time 500 pv e2e4 e7e5",
+                "info multipv 2 depth 2 seldepth 5 score cp -10 nodes 1500 nps 3000 time 500 pv d2d4"
+            ]
+        );
+    }
+
+    #[test_case(-5, ScoreBound::Upper; "fail low")]
+    #[test_case(-4, ScoreBound::Exact; "inside the window")]
+    #[test_case(12, ScoreBound::Lower; "fail high")]
+    fn bounds(value: i16, bound: ScoreBound) {
+        assert_eq!(ScoreBound::of(value, -5, 12), bound);
+    }
+
+    #[test]
+    fn best_move_stability() {
+        let e4 = ChessMove::new(Square::E2, Square::E4, None);
+        let d4 = ChessMove::new(Square::D2, Square::D4, None);
+        let result = |best_moves: Vec<ChessMove>| SearchResult {
+            best_move: *best_moves.last().unwrap(),
+            score: Score::DRAW,
+            bound: ScoreBound::Exact,
+            depth: best_moves.len() as u8,
+            seldepth: 0,
+            pv: Vec::new(),
+            nodes: 0,
+            elapsed: Duration::default(),
+            stats: SearchStats::default(),
+            best_moves,
+            root_moves: Vec::new(),
+            lines: Vec::new(),
+        };
+        assert!(!result(vec![e4]).is_unstable());
+        assert!(!result(vec![d4, e4, e4]).is_unstable());
+        assert!(result(vec![e4, e4, d4]).is_unstable());
+        assert_eq!(result(vec![e4, d4, d4, e4]).best_move_changes(), 2);
+    }
+}
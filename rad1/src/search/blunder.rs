@@ -0,0 +1,85 @@
+use crate::agent::AlphaBetaChessAgent;
+use crate::score::Score;
+use crate::{ChessGame, ChessMove};
+
+/// How a move compares to the best move the engine finds in the same position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveCheck {
+    pub best_move: ChessMove,
+    /// The score of the best move for the side to move
+    pub best: Score,
+    /// The score of the checked move for the side to move
+    pub played: Score,
+}
+
+impl MoveCheck {
+    /// Search the current position of the game once for the best move and,
+    /// if that isn't the checked move, once more with only the checked move.
+    /// The second search finds the positions of the first one in the
+    /// transposition table, so it's mostly done already.
+    ///
+    /// The move has to be legal in the position.
+    pub fn new(agent: &mut AlphaBetaChessAgent, game: &ChessGame, chess_move: ChessMove) -> Self {
+        agent.set_search_moves(Vec::new());
+        let best = agent.search_game(game);
+        let played = if best.best_move == chess_move {
+            best.score
+        } else {
+            agent.set_search_moves(vec![chess_move]);
+            let played = agent.search_game(game).score;
+            agent.set_search_moves(Vec::new());
+            played
+        };
+        Self {
+            best_move: best.best_move,
+            best: best.score,
+            played,
+        }
+    }
+
+    /// How many evaluation units the move gives away compared to the best move.
+    /// Missing a mate or walking into one loses more than any evaluation.
+    pub fn loss(&self) -> i32 {
+        (i32::from(i16::from(self.best)) - i32::from(i16::from(self.played))).max(0)
+    }
+
+    /// Whether the move loses at least `threshold` evaluation units
+    pub fn is_blunder(&self, threshold: i32) -> bool {
+        self.loss() >= threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MoveCheck;
+    use crate::agent;
+    use crate::score::Score;
+    use crate::tt::TranspositionTable;
+    use crate::{ChessGame, ChessMove, Square};
+    use std::str::FromStr;
+    use test_case::test_case;
+
+    #[test_case("a1a8", false; "mates")]
+    #[test_case("a1a2", true; "misses the mate")]
+    fn mate_in_one(played: &str, blunder: bool) {
+        let game = ChessGame::from_str("6k1/5ppp/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+        let mut agent = agent::alpha_beta_agent(3, TranspositionTable::new(100_000));
+        let check = MoveCheck::new(&mut agent, &game, ChessMove::from_str(played).unwrap());
+        assert_eq!(check.best, Score::Mate(1));
+        assert_eq!(check.is_blunder(30), blunder);
+    }
+
+    #[test]
+    fn best_move_loses_nothing() {
+        let game = ChessGame::from_str("4k3/8/8/4n3/8/8/8/4RK2 w - - 0 1").unwrap();
+        let mut agent = agent::alpha_beta_agent(3, TranspositionTable::new(100_000));
+        let best = agent.search_game(&game).best_move;
+        let check = MoveCheck::new(&mut agent, &game, best);
+        assert_eq!(check.best_move, best);
+        assert_eq!(check.loss(), 0);
+        // lets the knight get away
+        let quiet = ChessMove::new(Square::E1, Square::A1, None);
+        let check = MoveCheck::new(&mut agent, &game, quiet);
+        assert!(check.loss() >= 20);
+    }
+}
@@ -0,0 +1,269 @@
+use crate::{ChessMove, Position, PositionStatus};
+
+// Proof and disproof numbers of a node that can't be proven or disproven any more
+const INFINITY: u32 = u32::MAX;
+// Every node keeps its own position so the default limit stays well below a gigabyte
+const DEFAULT_NODE_LIMIT: usize = 200_000;
+
+/// What a proof-number search found out about the position
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Proof {
+    /// The side to move forces mate, the line is a proven one against the
+    /// longest defense in the proof but not necessarily the quickest mate
+    Mate(Vec<ChessMove>),
+    /// There's no forced mate within the move limit
+    NoMate,
+    /// The node limit was reached before the search could decide
+    Unknown,
+}
+
+/// The outcome of a proof-number search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PnsResult {
+    pub proof: Proof,
+    /// The number of nodes in the search tree
+    pub nodes: usize,
+}
+
+/// Proof-number search for forced mates.
+///
+/// Instead of searching every line to a fixed depth it keeps growing the
+/// tree at the node that is cheapest to prove or disprove, which lets it
+/// solve long forcing sequences where most of the defender's moves lose
+/// quickly and alpha-beta would have to search all of them to full depth.
+///
+/// The side to move at the root is the attacker, it has to checkmate within
+/// the given number of its own moves.
+pub struct ProofNumberSearch {
+    max_moves: u8,
+    node_limit: usize,
+}
+
+struct Node {
+    position: Position,
+    chess_move: Option<ChessMove>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    ply: usize,
+    proof: u32,
+    disproof: u32,
+}
+
+impl Node {
+    // the attacker is to move and needs only one of the children to be proven
+    fn is_or_node(&self) -> bool {
+        self.ply % 2 == 0
+    }
+}
+
+struct Tree {
+    nodes: Vec<Node>,
+    max_ply: usize,
+}
+
+impl ProofNumberSearch {
+    /// Search for a mate in at most `max_moves` moves of the side to move
+    pub fn new(max_moves: u8) -> Self {
+        Self {
+            max_moves,
+            node_limit: DEFAULT_NODE_LIMIT,
+        }
+    }
+
+    /// Give up once the tree holds this many nodes
+    pub fn with_node_limit(mut self, node_limit: usize) -> Self {
+        self.node_limit = node_limit.max(1);
+        self
+    }
+
+    pub fn solve(&self, position: &Position) -> PnsResult {
+        let mut tree = Tree {
+            nodes: Vec::new(),
+            max_ply: (usize::from(self.max_moves) * 2).saturating_sub(1),
+        };
+        tree.add(position.clone(), None, None, 0);
+        while tree.nodes[0].proof != 0
+            && tree.nodes[0].disproof != 0
+            && tree.nodes.len() < self.node_limit
+        {
+            let index = tree.most_proving_node();
+            tree.expand(index);
+            tree.update_ancestors(index);
+        }
+        let proof = if tree.nodes[0].proof == 0 {
+            Proof::Mate(tree.proven_line())
+        } else if tree.nodes[0].disproof == 0 {
+            Proof::NoMate
+        } else {
+            Proof::Unknown
+        };
+        PnsResult {
+            proof,
+            nodes: tree.nodes.len(),
+        }
+    }
+}
+
+impl Tree {
+    fn add(
+        &mut self,
+        position: Position,
+        parent: Option<usize>,
+        chess_move: Option<ChessMove>,
+        ply: usize,
+    ) -> usize {
+        let attacker_to_move = ply % 2 == 0;
+        let (proof, disproof) = match position.status() {
+            PositionStatus::Checkmate if attacker_to_move => (INFINITY, 0),
+            PositionStatus::Checkmate => (0, INFINITY),
+            PositionStatus::Stalemate => (INFINITY, 0),
+            PositionStatus::Ongoing if ply >= self.max_ply => (INFINITY, 0),
+            PositionStatus::Ongoing => (1, 1),
+        };
+        self.nodes.push(Node {
+            position,
+            chess_move,
+            parent,
+            children: Vec::new(),
+            ply,
+            proof,
+            disproof,
+        });
+        self.nodes.len() - 1
+    }
+
+    // Follow the children that are cheapest to settle down to a leaf
+    fn most_proving_node(&self) -> usize {
+        let mut index = 0;
+        loop {
+            let node = &self.nodes[index];
+            let child = if node.is_or_node() {
+                node.children.iter().min_by_key(|&&c| self.nodes[c].proof)
+            } else {
+                node.children
+                    .iter()
+                    .min_by_key(|&&c| self.nodes[c].disproof)
+            };
+            match child {
+                Some(&child) => index = child,
+                None => return index,
+            }
+        }
+    }
+
+    fn expand(&mut self, index: usize) {
+        let position = self.nodes[index].position.clone();
+        let ply = self.nodes[index].ply + 1;
+        let children = position
+            .legal_moves_iter()
+            .map(|chess_move| {
+                let child = position.make_move_new(chess_move);
+                self.add(child, Some(index), Some(chess_move), ply)
+            })
+            .collect();
+        self.nodes[index].children = children;
+    }
+
+    fn update_ancestors(&mut self, index: usize) {
+        let mut current = Some(index);
+        while let Some(index) = current {
+            let node = &self.nodes[index];
+            let proofs = node.children.iter().map(|&c| self.nodes[c].proof);
+            let disproofs = node.children.iter().map(|&c| self.nodes[c].disproof);
+            let (proof, disproof) = if node.is_or_node() {
+                (proofs.min().unwrap_or(INFINITY), saturating_sum(disproofs))
+            } else {
+                (saturating_sum(proofs), disproofs.min().unwrap_or(INFINITY))
+            };
+            let node = &mut self.nodes[index];
+            node.proof = proof;
+            node.disproof = disproof;
+            current = node.parent;
+        }
+    }
+
+    // The quickest mate in the proof tree against the defense that delays it the longest
+    fn proven_line(&self) -> Vec<ChessMove> {
+        // children always come after their parent, so going backwards
+        // settles every child before the node itself
+        let mut plies_to_mate: Vec<Option<usize>> = vec![None; self.nodes.len()];
+        for (index, node) in self.nodes.iter().enumerate().rev() {
+            if node.proof != 0 {
+                continue;
+            }
+            let children = node.children.iter().filter_map(|&c| plies_to_mate[c]);
+            plies_to_mate[index] = if node.children.is_empty() {
+                Some(0)
+            } else if node.is_or_node() {
+                children.min().map(|plies| plies + 1)
+            } else {
+                children.max().map(|plies| plies + 1)
+            };
+        }
+        let mut line = Vec::new();
+        let mut index = 0;
+        while let Some(plies) = plies_to_mate[index].filter(|&plies| plies > 0) {
+            index = *self.nodes[index]
+                .children
+                .iter()
+                .find(|&&c| plies_to_mate[c] == Some(plies - 1))
+                .unwrap();
+            line.extend(self.nodes[index].chess_move);
+        }
+        line
+    }
+}
+
+fn saturating_sum(values: impl Iterator<Item = u32>) -> u32 {
+    values.fold(0, u32::saturating_add)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Proof, ProofNumberSearch};
+    use crate::{ChessMove, Position, Square};
+    use std::str::FromStr;
+
+    // Qd8+ Bxd8 Re8#
+    const MATE_IN_TWO: &str = "r1b2k1r/ppp1bppp/8/1B1Q4/5q2/2P5/PPP2PPP/R3R1K1 w - - 1 0";
+
+    #[test]
+    fn finds_mate_in_one() {
+        let position = Position::from_str("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let result = ProofNumberSearch::new(1).solve(&position);
+        assert_eq!(
+            result.proof,
+            Proof::Mate(vec![ChessMove::new(Square::A1, Square::A8, None)])
+        );
+    }
+
+    #[test]
+    fn finds_mate_in_two() {
+        let position = Position::from_str(MATE_IN_TWO).unwrap();
+        let result = ProofNumberSearch::new(2).solve(&position);
+        let line = vec![
+            ChessMove::new(Square::D5, Square::D8, None),
+            ChessMove::new(Square::E7, Square::D8, None),
+            ChessMove::new(Square::E1, Square::E8, None),
+        ];
+        assert_eq!(result.proof, Proof::Mate(line));
+    }
+
+    #[test]
+    fn disproves_mates_beyond_the_limit() {
+        let position = Position::from_str(MATE_IN_TWO).unwrap();
+        assert_eq!(
+            ProofNumberSearch::new(1).solve(&position).proof,
+            Proof::NoMate
+        );
+    }
+
+    #[test]
+    fn gives_up_at_the_node_limit() {
+        let result = ProofNumberSearch::new(5)
+            .with_node_limit(1000)
+            .solve(&Position::default());
+        assert_eq!(result.proof, Proof::Unknown);
+        assert!(result.nodes >= 1000);
+    }
+}
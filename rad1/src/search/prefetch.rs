@@ -0,0 +1,135 @@
+use crate::agent::AlphaBetaChessAgent;
+use crate::{Action, ChessGame, ChessMove};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The replies the side to move most likely plays, best first: the best
+/// move of a search, then the best move of a search without it and so on.
+/// Fewer are returned when the position has fewer legal moves.
+pub fn likely_replies(
+    agent: &mut AlphaBetaChessAgent,
+    game: &ChessGame,
+    count: usize,
+) -> Vec<ChessMove> {
+    let legal_moves = game.current_position().legal_moves().len();
+    let mut replies = Vec::new();
+    while replies.len() < count.min(legal_moves) {
+        agent.set_excluded_moves(replies.clone());
+        replies.push(agent.search_game(game).best_move);
+    }
+    agent.set_excluded_moves(Vec::new());
+    replies
+}
+
+/// Search the position after each reply in turn, so the agent's transposition
+/// table already knows them when the opponent picks one. Unlike pondering,
+/// which only follows the expected reply, every likely reply gets searched.
+///
+/// Stops before the next reply once `stop` is set, give the agent the same
+/// flag to stop the running search too. Returns how many replies were
+/// searched to the end.
+pub fn prefetch(
+    agent: &AlphaBetaChessAgent,
+    game: &ChessGame,
+    replies: &[ChessMove],
+    stop: &AtomicBool,
+) -> usize {
+    let mut searched = 0;
+    for &reply in replies {
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+        let mut after_reply = game.clone();
+        after_reply.take_action(Action::MakeMove(reply));
+        if after_reply.result().is_some() {
+            continue;
+        }
+        agent.search_game(&after_reply);
+        if !stop.load(Ordering::SeqCst) {
+            searched += 1;
+        }
+    }
+    searched
+}
+
+/// Lower the scheduling priority of the calling thread as far as it goes,
+/// so a background search only gets the CPU time nothing else wants.
+/// Returns false if the system refused or doesn't support it.
+#[cfg(target_os = "linux")]
+pub fn lower_thread_priority() -> bool {
+    // SAFETY: gettid can't fail, and on Linux the priority of a thread id
+    // only changes that thread
+    unsafe {
+        let thread = libc::syscall(libc::SYS_gettid) as libc::id_t;
+        libc::setpriority(libc::PRIO_PROCESS, thread, 19) == 0
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn lower_thread_priority() -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{likely_replies, lower_thread_priority, prefetch};
+    use crate::agent;
+    use crate::tt::TranspositionTable;
+    use crate::{Action, ChessGame, ChessMove};
+    use std::str::FromStr;
+    use std::sync::atomic::AtomicBool;
+
+    #[test]
+    fn replies_are_distinct_and_legal() {
+        let game = ChessGame::from_str("4k3/8/8/8/8/8/4q3/4K3 w - - 0 1").unwrap();
+        let mut agent = agent::alpha_beta_agent(3, TranspositionTable::new(100_000));
+        let replies = likely_replies(&mut agent, &game, 5);
+        // the only legal move is taking the queen
+        assert_eq!(replies, vec![ChessMove::from_str("e1e2").unwrap()]);
+
+        let game = ChessGame::default();
+        let replies = likely_replies(&mut agent, &game, 4);
+        assert_eq!(replies.len(), 4);
+        for (index, reply) in replies.iter().enumerate() {
+            assert!(game.current_position().legal(*reply));
+            assert!(!replies[..index].contains(reply));
+        }
+    }
+
+    #[test]
+    fn prefetched_replies_are_searched_faster() {
+        let mut game = ChessGame::default();
+        game.take_action(Action::MakeMove(ChessMove::from_str("e2e4").unwrap()));
+        let mut shallow = agent::alpha_beta_agent(3, TranspositionTable::new(100_000));
+        let replies = likely_replies(&mut shallow, &game, 3);
+
+        let mut after_reply = game.clone();
+        after_reply.take_action(Action::MakeMove(replies[0]));
+        let cold = agent::alpha_beta_agent(5, TranspositionTable::new(100_000));
+        let cold_nodes = cold.search_game(&after_reply).nodes;
+
+        let warm = agent::alpha_beta_agent(5, TranspositionTable::new(100_000));
+        assert_eq!(prefetch(&warm, &game, &replies, &AtomicBool::new(false)), 3);
+        assert!(warm.search_game(&after_reply).nodes < cold_nodes);
+    }
+
+    #[test]
+    fn stops_when_asked() {
+        let game = ChessGame::default();
+        let agent = agent::alpha_beta_agent(3, TranspositionTable::new(100_000));
+        let replies = vec![ChessMove::from_str("e2e4").unwrap()];
+        assert_eq!(prefetch(&agent, &game, &replies, &AtomicBool::new(true)), 0);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn lowers_only_the_calling_thread() {
+        // SAFETY: only reads the priority of the calling thread
+        let priority = || unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+        let before = priority();
+        let lowered = std::thread::spawn(move || (lower_thread_priority(), priority()))
+            .join()
+            .unwrap();
+        assert_eq!(lowered, (true, 19));
+        assert_eq!(priority(), before);
+    }
+}
@@ -0,0 +1,94 @@
+use crate::search::{RootMove, SearchResult};
+use crate::ChessMove;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// What a running search is doing, updated by the search and read from
+/// another thread to show it live, see
+/// [`AlphaBetaChessAgent::with_progress`](crate::agent::AlphaBetaChessAgent::with_progress)
+#[derive(Default)]
+pub struct SearchProgress {
+    nodes: AtomicU64,
+    state: Mutex<ProgressSnapshot>,
+}
+
+/// The state of a running search at one moment
+#[derive(Debug, Clone, Default)]
+pub struct ProgressSnapshot {
+    /// The depth of the running iteration, 0 before the first one
+    pub depth: u8,
+    /// The root move started last, None before the iteration's first one
+    pub current_move: Option<ChessMove>,
+    /// How many root moves the running iteration started so far
+    pub moves_started: usize,
+    /// How many root moves the search considers
+    pub move_count: usize,
+    /// The nodes searched so far, counted in steps of a few thousand while
+    /// an iteration runs and exactly after it completed
+    pub nodes: u64,
+    /// The root moves in the order they were first searched, with the nodes
+    /// and time spent on them over the iterations. A move counts once it
+    /// was searched to the end.
+    pub root_moves: Vec<RootMove>,
+    /// The result of the last completed iteration
+    pub last_iteration: Option<SearchResult>,
+}
+
+impl SearchProgress {
+    /// The state of the search right now
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        let mut snapshot = self.state.lock().unwrap().clone();
+        snapshot.nodes = self.nodes.load(Ordering::Relaxed);
+        snapshot
+    }
+
+    pub(crate) fn start_search(&self, move_count: usize) {
+        self.nodes.store(0, Ordering::Relaxed);
+        *self.state.lock().unwrap() = ProgressSnapshot {
+            move_count,
+            ..ProgressSnapshot::default()
+        };
+    }
+
+    pub(crate) fn start_iteration(&self, depth: u8) {
+        let mut state = self.state.lock().unwrap();
+        state.depth = depth;
+        state.current_move = None;
+        state.moves_started = 0;
+    }
+
+    pub(crate) fn start_root_move(&self, chess_move: ChessMove) {
+        let mut state = self.state.lock().unwrap();
+        state.current_move = Some(chess_move);
+        state.moves_started += 1;
+    }
+
+    pub(crate) fn finish_root_move(&self, chess_move: ChessMove, nodes: u64, elapsed: Duration) {
+        let mut state = self.state.lock().unwrap();
+        match state
+            .root_moves
+            .iter_mut()
+            .find(|root_move| root_move.chess_move == chess_move)
+        {
+            Some(root_move) => {
+                root_move.nodes += nodes;
+                root_move.elapsed += elapsed;
+            }
+            None => state.root_moves.push(RootMove {
+                chess_move,
+                nodes,
+                elapsed,
+            }),
+        }
+    }
+
+    pub(crate) fn add_nodes(&self, nodes: u64) {
+        self.nodes.fetch_add(nodes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn finish_iteration(&self, result: &SearchResult) {
+        self.nodes.store(result.nodes, Ordering::Relaxed);
+        self.state.lock().unwrap().last_iteration = Some(result.clone());
+    }
+}
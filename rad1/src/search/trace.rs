@@ -0,0 +1,214 @@
+use crate::fmt::score;
+use crate::score::Score;
+use crate::search::ScoreBound;
+use crate::{ChessMove, Position};
+use std::cell::RefCell;
+use std::fmt;
+
+/// What the search did at a node of a traced line, with every value from
+/// the point of view of the node's side to move
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceEvent {
+    /// An iteration of the given depth starts at the root
+    Iteration { depth: u8 },
+    /// The search reached the node after the line's move to it, None at the root
+    Node {
+        ply: usize,
+        chess_move: Option<ChessMove>,
+        depth: u8,
+        alpha: Score,
+        beta: Score,
+        evaluation: Score,
+    },
+    /// The side to move is in check, so the node is searched a ply deeper
+    CheckExtension { ply: usize, depth: u8 },
+    /// The transposition table had an entry at least as deep as the node,
+    /// which ends the search of the node when it is exact or a bound outside
+    /// the window and the node isn't forced down the line
+    TableHit {
+        ply: usize,
+        depth: u8,
+        bound: ScoreBound,
+        value: Score,
+        cutoff: bool,
+    },
+    /// The node repeats an earlier position and is scored as a draw
+    Repetition { ply: usize, value: Score },
+    /// The node was looked up in the built-in tablebases
+    Tablebase { ply: usize, value: Score },
+    /// The game is over at the node
+    GameOver { ply: usize, value: Score },
+    /// The node is a leaf, scored by the quiescence search
+    Quiescence { ply: usize, value: Score },
+    /// Passing the move was searched to the depth, pruning the node when it
+    /// still failed high
+    NullMove {
+        ply: usize,
+        depth: u8,
+        value: Score,
+        cutoff: bool,
+    },
+    /// The moves of the node were searched
+    Value {
+        ply: usize,
+        value: Score,
+        bound: ScoreBound,
+    },
+}
+
+impl TraceEvent {
+    /// Plies from the root of the node the event happened at
+    pub fn ply(&self) -> usize {
+        match *self {
+            TraceEvent::Iteration { .. } => 0,
+            TraceEvent::Node { ply, .. }
+            | TraceEvent::CheckExtension { ply, .. }
+            | TraceEvent::TableHit { ply, .. }
+            | TraceEvent::Repetition { ply, .. }
+            | TraceEvent::Tablebase { ply, .. }
+            | TraceEvent::GameOver { ply, .. }
+            | TraceEvent::Quiescence { ply, .. }
+            | TraceEvent::NullMove { ply, .. }
+            | TraceEvent::Value { ply, .. } => ply,
+        }
+    }
+}
+
+impl fmt::Display for TraceEvent {
+    /// One line indented by the ply, e.g. `  e7e5 depth 2 window -0.40 +0.10 eval -0.35`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let indent = "  ".repeat(self.ply());
+        match *self {
+            TraceEvent::Iteration { depth } => write!(f, "iteration {}", depth),
+            TraceEvent::Node {
+                chess_move,
+                depth,
+                alpha,
+                beta,
+                evaluation,
+                ..
+            } => write!(
+                f,
+                "{}{} depth {} window {} {} eval {}",
+                indent,
+                chess_move.map_or(String::from("root"), |m| m.to_string()),
+                depth,
+                window_bound(alpha),
+                window_bound(beta),
+                score(evaluation)
+            ),
+            TraceEvent::CheckExtension { depth, .. } => {
+                write!(f, "{}  in check, extended to depth {}", indent, depth)
+            }
+            TraceEvent::TableHit {
+                depth,
+                bound,
+                value,
+                cutoff,
+                ..
+            } => write!(
+                f,
+                "{}  tt hit depth {} {} {}{}",
+                indent,
+                depth,
+                bound.as_str(),
+                score(value),
+                if cutoff { ", cutoff" } else { "" }
+            ),
+            TraceEvent::Repetition { value, .. } => {
+                write!(f, "{}  repetition {}", indent, score(value))
+            }
+            TraceEvent::Tablebase { value, .. } => {
+                write!(f, "{}  tablebase {}", indent, score(value))
+            }
+            TraceEvent::GameOver { value, .. } => {
+                write!(f, "{}  game over {}", indent, score(value))
+            }
+            TraceEvent::Quiescence { value, .. } => {
+                write!(f, "{}  quiescence {}", indent, score(value))
+            }
+            TraceEvent::NullMove {
+                depth,
+                value,
+                cutoff,
+                ..
+            } => write!(
+                f,
+                "{}  null move depth {} {}{}",
+                indent,
+                depth,
+                score(value),
+                if cutoff { ", pruned" } else { "" }
+            ),
+            TraceEvent::Value { value, bound, .. } => {
+                write!(f, "{}  value {} {}", indent, score(value), bound.as_str())
+            }
+        }
+    }
+}
+
+// The bounds of a full window are shown as infinite instead of as mates
+fn window_bound(bound: Score) -> String {
+    match bound {
+        Score::Mated(0) => String::from("-inf"),
+        Score::Mate(0) => String::from("+inf"),
+        bound => score(bound),
+    }
+}
+
+// The line a search is forced down and what happened along it so far
+pub(crate) struct LineTrace {
+    line: Vec<ChessMove>,
+    // hashes of the root and of the positions after every move of the line
+    hashes: Vec<u64>,
+    events: RefCell<Vec<TraceEvent>>,
+}
+
+impl LineTrace {
+    // The line is cut at its first illegal move
+    pub(crate) fn new(position: &Position, line: &[ChessMove]) -> Self {
+        let mut position = position.clone();
+        let mut hashes = vec![position.get_hash()];
+        let mut legal = Vec::new();
+        for &chess_move in line {
+            if !position.legal(chess_move) {
+                break;
+            }
+            position = position.make_move_new(chess_move);
+            hashes.push(position.get_hash());
+            legal.push(chess_move);
+        }
+        Self {
+            line: legal,
+            hashes,
+            events: RefCell::new(Vec::new()),
+        }
+    }
+
+    // Whether the path from the root to the ply, given by the hashes of its
+    // positions, follows the line
+    pub(crate) fn follows(&self, path: &[u64]) -> bool {
+        path.len() <= self.hashes.len() && self.hashes[..path.len()] == *path
+    }
+
+    // The move of the line at the ply, None past its end
+    pub(crate) fn move_at(&self, ply: usize) -> Option<ChessMove> {
+        self.line.get(ply).copied()
+    }
+
+    // The move of the line leading to the ply, None at the root
+    pub(crate) fn move_to(&self, ply: usize) -> Option<ChessMove> {
+        ply.checked_sub(1).and_then(|ply| self.move_at(ply))
+    }
+
+    pub(crate) fn record(&self, event: TraceEvent) {
+        self.events.borrow_mut().push(event);
+    }
+
+    // Hand the events recorded so far to the callback
+    pub(crate) fn flush(&self, on_event: &mut dyn FnMut(&TraceEvent)) {
+        for event in self.events.borrow_mut().drain(..) {
+            on_event(&event);
+        }
+    }
+}
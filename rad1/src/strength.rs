@@ -0,0 +1,83 @@
+/// Weakest strength `UCI_Elo` can ask for
+pub const MIN_ELO: u16 = 500;
+/// Strongest strength `UCI_Elo` can ask for, above it the engine plays at full strength
+pub const MAX_ELO: u16 = 2600;
+
+// Elo, depth, nodes and chance of a random move in percent at each anchor.
+// Neighboring anchors up to 2000 were spaced by quick self-play, where the
+// stronger one scores around two out of three games, the ones above follow
+// the node counts of deeper iterations. The absolute scale is only a rough
+// guess and isn't measured against rated opponents.
+const ANCHORS: [(u16, u8, u64, u8); 8] = [
+    (500, 1, 200, 30),
+    (800, 1, 500, 20),
+    (1100, 2, 2_000, 12),
+    (1400, 3, 5_000, 7),
+    (1700, 4, 30_000, 4),
+    (2000, 5, 150_000, 2),
+    (2300, 7, 800_000, 1),
+    (2600, 9, 4_000_000, 0),
+];
+
+/// How far the engine searches and how often it errs to play at a given Elo,
+/// set with the `UCI_LimitStrength` and `UCI_Elo` options
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StrengthLimit {
+    /// Deepest iteration of the search
+    pub depth: u8,
+    /// Most nodes searched for a move
+    pub nodes: u64,
+    /// Chance in percent of playing a random legal move instead of the best one
+    pub error_percent: u8,
+}
+
+impl StrengthLimit {
+    /// The limits for the Elo, between the anchors the nodes grow
+    /// geometrically and the depth and error chance linearly
+    pub fn for_elo(elo: u16) -> Self {
+        let elo = elo.clamp(MIN_ELO, MAX_ELO);
+        let upper = ANCHORS
+            .iter()
+            .position(|&(anchor, ..)| anchor >= elo)
+            .unwrap_or(ANCHORS.len() - 1)
+            .max(1);
+        let (low_elo, low_depth, low_nodes, low_error) = ANCHORS[upper - 1];
+        let (high_elo, high_depth, high_nodes, high_error) = ANCHORS[upper];
+        let t = f64::from(elo - low_elo) / f64::from(high_elo - low_elo);
+        let between = |low: f64, high: f64| low + t * (high - low);
+        Self {
+            depth: between(f64::from(low_depth), f64::from(high_depth)).round() as u8,
+            nodes: (low_nodes as f64 * (high_nodes as f64 / low_nodes as f64).powf(t)).round()
+                as u64,
+            error_percent: between(f64::from(low_error), f64::from(high_error)).round() as u8,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StrengthLimit, MAX_ELO, MIN_ELO};
+    use test_case::test_case;
+
+    #[test_case(MIN_ELO, StrengthLimit { depth: 1, nodes: 200, error_percent: 30 }; "weakest")]
+    #[test_case(1400, StrengthLimit { depth: 3, nodes: 5_000, error_percent: 7 }; "anchor")]
+    #[test_case(1550, StrengthLimit { depth: 4, nodes: 12_247, error_percent: 6 }; "between anchors")]
+    #[test_case(MAX_ELO, StrengthLimit { depth: 9, nodes: 4_000_000, error_percent: 0 }; "strongest")]
+    #[test_case(3000, StrengthLimit { depth: 9, nodes: 4_000_000, error_percent: 0 }; "above the range")]
+    fn limits(elo: u16, expected: StrengthLimit) {
+        assert_eq!(StrengthLimit::for_elo(elo), expected);
+    }
+
+    #[test]
+    fn stronger_never_searches_less() {
+        let limits: Vec<StrengthLimit> = (MIN_ELO..=MAX_ELO)
+            .step_by(25)
+            .map(StrengthLimit::for_elo)
+            .collect();
+        for pair in limits.windows(2) {
+            assert!(pair[1].depth >= pair[0].depth);
+            assert!(pair[1].nodes >= pair[0].nodes);
+            assert!(pair[1].error_percent <= pair[0].error_percent);
+        }
+    }
+}
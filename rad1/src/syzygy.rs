@@ -0,0 +1,191 @@
+use crate::{ChessMove, Position};
+use shakmaty::fen::Fen;
+use shakmaty::{CastlingMode, Chess, Position as _, PositionError};
+use shakmaty_syzygy::{Dtz, MaybeRounded, Tablebase};
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+// Plies without a capture or pawn move after which a draw can be claimed
+const FIFTY_MOVES: u32 = 100;
+// Separates the directories of a path, the way Stockfish's SyzygyPath does
+#[cfg(windows)]
+const SEPARATOR: char = ';';
+#[cfg(not(windows))]
+const SEPARATOR: char = ':';
+
+/// Syzygy endgame tablebases, probed through the shakmaty-syzygy crate.
+///
+/// Unlike the [built-in tables](crate::tbgen) they hold up to seven pieces
+/// and store the distance to the next capture or pawn move (DTZ) instead of
+/// the distance to mate, which is what keeps a win under the fifty-move rule.
+pub struct Syzygy {
+    tables: Tablebase<Chess>,
+}
+
+impl Syzygy {
+    /// The tables in the directories of `path`, separated by `:` (`;` on
+    /// Windows). Fails when one of them can't be read.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut tables = Tablebase::new();
+        let path = path.as_ref().to_string_lossy().into_owned();
+        for directory in path.split(SEPARATOR).filter(|d| !d.is_empty()) {
+            tables.add_directory(directory)?;
+        }
+        Ok(Self { tables })
+    }
+
+    /// The most pieces of a position the tables hold, 0 without any tables
+    pub fn max_pieces(&self) -> usize {
+        self.tables.max_pieces()
+    }
+
+    /// The moves of a tablebase position that keep the best outcome the
+    /// tables promise, given the plies since the last capture or pawn move.
+    ///
+    /// A win is only a win when the next capture or pawn move comes before
+    /// the fifty-move rule draws the game, so winning moves are chosen by
+    /// the fewest plies to it, and losing moves by the most. Every move
+    /// keeping a draw is returned, for the search to choose from.
+    ///
+    /// None when the position has castling rights, more pieces than the
+    /// tables hold or a table is missing.
+    pub fn root_moves(&self, position: &Position, halfmove_clock: u32) -> Option<Vec<ChessMove>> {
+        let pieces = position.color_combined(crate::Color::White).popcnt()
+            + position.color_combined(crate::Color::Black).popcnt();
+        if pieces as usize > self.max_pieces() {
+            return None;
+        }
+        let root = to_shakmaty(position, halfmove_clock)?;
+        let mut ranked = Vec::new();
+        for m in root.legal_moves() {
+            let zeroing = m.is_zeroing();
+            let mut after = root.clone();
+            after.play_unchecked(m);
+            let rank = if after.is_checkmate() {
+                MoveRank::Mates
+            } else {
+                let dtz = self.tables.probe_dtz(&after).ok()?;
+                let clock = if zeroing { 0 } else { halfmove_clock + 1 };
+                MoveRank::of(dtz, clock)
+            };
+            let chess_move =
+                ChessMove::from_str(&m.to_uci(CastlingMode::Standard).to_string()).ok()?;
+            ranked.push((rank, chess_move));
+        }
+        let best = ranked.iter().map(|(rank, _)| *rank).max()?;
+        Some(
+            ranked
+                .into_iter()
+                .filter(|(rank, _)| *rank == best)
+                .map(|(_, chess_move)| chess_move)
+                .collect(),
+        )
+    }
+}
+
+// The position in shakmaty's terms, with the halfmove clock rad1's own
+// positions don't keep
+fn to_shakmaty(position: &Position, halfmove_clock: u32) -> Option<Chess> {
+    let fen = position.to_string();
+    let fields: Vec<&str> = fen.split_whitespace().take(4).collect();
+    let fen = format!("{} {} 1", fields.join(" "), halfmove_clock);
+    Fen::from_str(&fen)
+        .ok()?
+        .into_position(CastlingMode::Standard)
+        .or_else(PositionError::ignore_invalid_ep_square)
+        .ok()
+}
+
+// How a root move does from the side to move's point of view, worst first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum MoveRank {
+    // lost before the fifty-move rule can save the game, later is better
+    Loss(u32),
+    // lost, but the fifty-move rule comes first when the other side plays
+    // the fastest win
+    BlessedLoss,
+    Draw,
+    // won, but only if the other side lets the win through before the
+    // fifty-move rule draws the game
+    CursedWin,
+    // won, fewer plies to the next capture or pawn move are better
+    Win(u32),
+    Mates,
+}
+
+impl MoveRank {
+    // The rank of a move from the DTZ of the position after it and the
+    // halfmove clock there. DTZ is from the other side's point of view.
+    fn of(dtz: MaybeRounded<Dtz>, clock: u32) -> Self {
+        // a rounded DTZ can be one ply short
+        let rounding = match dtz {
+            MaybeRounded::Precise(_) => 0,
+            MaybeRounded::Rounded(_) => 1,
+        };
+        let Dtz(plies) = dtz.ignore_rounding();
+        let in_time = clock + plies.unsigned_abs() + rounding <= FIFTY_MOVES;
+        if plies < 0 && in_time {
+            MoveRank::Win(u32::MAX - plies.unsigned_abs())
+        } else if plies < 0 {
+            MoveRank::CursedWin
+        } else if plies > 0 && in_time {
+            MoveRank::Loss(plies.unsigned_abs())
+        } else if plies > 0 {
+            MoveRank::BlessedLoss
+        } else {
+            MoveRank::Draw
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_shakmaty, MoveRank, Syzygy};
+    use crate::Position;
+    use shakmaty::Position as _;
+    use shakmaty_syzygy::{Dtz, MaybeRounded};
+    use std::str::FromStr;
+
+    fn rank(plies: i32, clock: u32) -> MoveRank {
+        MoveRank::of(MaybeRounded::Precise(Dtz(plies)), clock)
+    }
+
+    #[test]
+    fn wins_are_ranked_by_the_plies_to_zeroing() {
+        assert!(rank(-3, 10) > rank(-5, 10));
+        assert!(rank(-99, 1) > rank(0, 0));
+        assert!(MoveRank::Mates > rank(-1, 0));
+    }
+
+    #[test]
+    fn the_fifty_move_rule_turns_wins_into_draws() {
+        assert_eq!(rank(-30, 80), MoveRank::CursedWin);
+        assert_eq!(rank(30, 80), MoveRank::BlessedLoss);
+        assert!(rank(-30, 70) > MoveRank::CursedWin);
+        // the rounded distance may be a ply short
+        assert_eq!(
+            MoveRank::of(MaybeRounded::Rounded(Dtz(-30)), 70),
+            MoveRank::CursedWin
+        );
+        assert!(rank(30, 70) < MoveRank::BlessedLoss);
+        assert!(rank(40, 10) > rank(30, 10));
+    }
+
+    #[test]
+    fn converts_positions_with_their_halfmove_clock() {
+        let position = Position::from_str("8/8/8/8/8/4k3/8/R3K3 w Q - 0 1").unwrap();
+        let converted = to_shakmaty(&position, 37).unwrap();
+        assert_eq!(converted.halfmoves(), 37);
+        assert_eq!(converted.legal_moves().len(), position.legal_moves().len());
+    }
+
+    #[test]
+    fn positions_without_tables_are_not_probed() {
+        let tables = Syzygy::open("").unwrap();
+        assert_eq!(tables.max_pieces(), 0);
+        let position = Position::from_str("8/8/8/8/8/4k3/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(tables.root_moves(&position, 0), None);
+        assert!(Syzygy::open("/nonexistent").is_err());
+    }
+}
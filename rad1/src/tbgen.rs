@@ -0,0 +1,450 @@
+use crate::backend::{Backend, DefaultBackend as Board};
+use crate::score::Score;
+use crate::{BitBoard, Color, Piece, Position, Rank, Square, ALL_SQUARES};
+use lazy_static::lazy_static;
+use std::error::Error;
+use std::fmt;
+
+// Positions of a table: the side to move, the kings and the piece
+const SIZE: usize = 2 * 64 * 64 * 64;
+// Entries of positions that can't be reached, like adjacent kings
+const ILLEGAL: u8 = u8::MAX;
+// Entries of drawn positions, the others store the mate distance plus one
+const DRAW: u8 = 0;
+const MAGIC: &[u8; 8] = b"RAD1TBGN";
+
+lazy_static! {
+    static ref KQK: Tablebase = Tablebase::generate(Piece::Queen);
+    static ref KRK: Tablebase = Tablebase::generate(Piece::Rook);
+    static ref KBK: Tablebase = Tablebase::generate(Piece::Bishop);
+    static ref KNK: Tablebase = Tablebase::generate(Piece::Knight);
+    static ref KPK: Tablebase = Tablebase::generate(Piece::Pawn);
+}
+
+/// Probe the built-in tablebases for a position with a king and a queen,
+/// rook, bishop, knight or pawn against a lone king.
+///
+/// Returns the distance to mate from the side to move's point of view,
+/// or a draw. Tables are generated the first time they are probed.
+/// Castling rights are ignored, so a king and rook that may still castle
+/// can be scored a few plies off.
+pub fn probe(position: &Position) -> Option<Score> {
+    let occupied = position.color_combined(Color::White) | position.color_combined(Color::Black);
+    if occupied.popcnt() != 3 {
+        return None;
+    }
+    let strong = if position.color_combined(Color::White).popcnt() == 2 {
+        Color::White
+    } else {
+        Color::Black
+    };
+    let piece = [
+        Piece::Queen,
+        Piece::Rook,
+        Piece::Bishop,
+        Piece::Knight,
+        Piece::Pawn,
+    ]
+    .iter()
+    .copied()
+    .find(|&piece| position.pieces(piece) != BitBoard::new(0))?;
+    let kings = position.pieces(Piece::King);
+    Some(table(piece).probe(
+        position.side_to_move() == strong,
+        strong,
+        (kings & position.color_combined(strong)).to_square(),
+        (kings & position.color_combined(!strong)).to_square(),
+        position.pieces(piece).to_square(),
+    ))
+}
+
+fn table(piece: Piece) -> &'static Tablebase {
+    match piece {
+        Piece::Queen => &KQK,
+        Piece::Rook => &KRK,
+        Piece::Bishop => &KBK,
+        Piece::Knight => &KNK,
+        _ => &KPK,
+    }
+}
+
+/// The distance to mate of every position of a king and one piece against
+/// a lone king, found by retrograde analysis.
+///
+/// The side with the piece plays white, positions where black has it are
+/// looked up with the board flipped.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Tablebase {
+    piece: Piece,
+    entries: Vec<u8>,
+}
+
+/// The reason a tablebase couldn't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TablebaseError {
+    InvalidMagic,
+    InvalidPiece(u8),
+    InvalidLength { expected: usize, actual: usize },
+}
+
+impl fmt::Display for TablebaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TablebaseError::InvalidMagic => write!(f, "not a rad1 tablebase"),
+            TablebaseError::InvalidPiece(piece) => write!(f, "invalid piece {}", piece),
+            TablebaseError::InvalidLength { expected, actual } => {
+                write!(f, "expected {} bytes but got {}", expected, actual)
+            }
+        }
+    }
+}
+
+impl Error for TablebaseError {}
+
+impl Tablebase {
+    /// Generate the table of a king and the piece against a lone king.
+    ///
+    /// Pawn tables look up the queen and rook tables for promotions,
+    /// so those are generated first if they weren't probed yet.
+    pub fn generate(piece: Piece) -> Self {
+        let mut generator = Generator {
+            piece,
+            entries: vec![DRAW; SIZE],
+        };
+        generator.mark_terminal_positions();
+        generator.retrograde();
+        Self {
+            piece,
+            entries: generator.entries,
+        }
+    }
+
+    pub fn piece(&self) -> Piece {
+        self.piece
+    }
+
+    /// Look up a position by its squares, seen from the side with the piece
+    pub fn probe(
+        &self,
+        strong_to_move: bool,
+        strong: Color,
+        strong_king: Square,
+        weak_king: Square,
+        piece: Square,
+    ) -> Score {
+        let flip = |square: Square| match strong {
+            Color::White => square.to_index(),
+            Color::Black => square.to_index() ^ 56,
+        };
+        let entry = self.entries[index(
+            strong_to_move,
+            flip(strong_king),
+            flip(weak_king),
+            flip(piece),
+        )];
+        match entry {
+            DRAW | ILLEGAL => Score::DRAW,
+            _ if strong_to_move => Score::Mate(entry - 1),
+            _ => Score::Mated(entry - 1),
+        }
+    }
+
+    /// The longest mate in the table in plies
+    pub fn longest_mate(&self) -> u8 {
+        self.entries
+            .iter()
+            .filter(|&&entry| entry != ILLEGAL)
+            .max()
+            .map_or(0, |&entry| entry.saturating_sub(1))
+    }
+
+    /// The table as bytes: a magic number, the piece and one byte per position
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 1 + SIZE);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(self.piece.to_index() as u8);
+        bytes.extend_from_slice(&self.entries);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TablebaseError> {
+        if !bytes.starts_with(MAGIC) {
+            return Err(TablebaseError::InvalidMagic);
+        }
+        let expected = MAGIC.len() + 1 + SIZE;
+        if bytes.len() != expected {
+            return Err(TablebaseError::InvalidLength {
+                expected,
+                actual: bytes.len(),
+            });
+        }
+        let piece = match bytes[MAGIC.len()] {
+            0 => Piece::Pawn,
+            1 => Piece::Knight,
+            2 => Piece::Bishop,
+            3 => Piece::Rook,
+            4 => Piece::Queen,
+            piece => return Err(TablebaseError::InvalidPiece(piece)),
+        };
+        Ok(Self {
+            piece,
+            entries: bytes[MAGIC.len() + 1..].to_vec(),
+        })
+    }
+}
+
+fn index(strong_to_move: bool, strong_king: usize, weak_king: usize, piece: usize) -> usize {
+    (usize::from(!strong_to_move) << 18) | (strong_king << 12) | (weak_king << 6) | piece
+}
+
+// The squares of the position at the index, the side with the piece is white
+fn squares(index: usize) -> (bool, Square, Square, Square) {
+    (
+        index >> 18 == 0,
+        ALL_SQUARES[(index >> 12) & 63],
+        ALL_SQUARES[(index >> 6) & 63],
+        ALL_SQUARES[index & 63],
+    )
+}
+
+// A position reached by a move, in this table or after a promotion in another
+enum Child {
+    Index(usize),
+    Entry(u8),
+}
+
+struct Generator {
+    piece: Piece,
+    entries: Vec<u8>,
+}
+
+impl Generator {
+    // The squares the white piece attacks
+    fn attacks(&self, square: Square, blockers: BitBoard) -> BitBoard {
+        match self.piece {
+            Piece::Queen => {
+                Board::rook_attacks(square, blockers) | Board::bishop_attacks(square, blockers)
+            }
+            Piece::Rook => Board::rook_attacks(square, blockers),
+            Piece::Bishop => Board::bishop_attacks(square, blockers),
+            Piece::Knight => Board::knight_attacks(square),
+            _ => Board::pawn_attacks(square, Color::White),
+        }
+    }
+
+    fn is_legal(&self, index: usize) -> bool {
+        let (strong_to_move, strong_king, weak_king, piece) = squares(index);
+        if strong_king == weak_king || strong_king == piece || weak_king == piece {
+            return false;
+        }
+        if Board::king_attacks(strong_king) & BitBoard::from_square(weak_king) != BitBoard::new(0) {
+            return false;
+        }
+        if self.piece == Piece::Pawn
+            && (piece.get_rank() == Rank::First || piece.get_rank() == Rank::Eighth)
+        {
+            return false;
+        }
+        // the lone king can't be in check with white to move
+        !strong_to_move
+            || self.attacks(piece, BitBoard::from_square(strong_king))
+                & BitBoard::from_square(weak_king)
+                == BitBoard::new(0)
+    }
+
+    // The positions after every legal move, None for a move that draws at once
+    fn children(&self, index: usize) -> Vec<Option<Child>> {
+        let (strong_to_move, strong_king, weak_king, piece) = squares(index);
+        let strong_king_bb = BitBoard::from_square(strong_king);
+        let weak_king_bb = BitBoard::from_square(weak_king);
+        let piece_bb = BitBoard::from_square(piece);
+        let mut children = Vec::new();
+        if strong_to_move {
+            let king_targets =
+                Board::king_attacks(strong_king) & !Board::king_attacks(weak_king) & !piece_bb;
+            for target in king_targets {
+                let child = index_of(false, target, weak_king, piece);
+                children.push(Some(Child::Index(child)));
+            }
+            if self.piece == Piece::Pawn {
+                let occupied = strong_king_bb | weak_king_bb;
+                let mut targets = Vec::new();
+                if let Some(single) = piece.forward(Color::White) {
+                    if BitBoard::from_square(single) & occupied == BitBoard::new(0) {
+                        targets.push(single);
+                        if piece.get_rank() == Rank::Second {
+                            let double = single.uforward(Color::White);
+                            if BitBoard::from_square(double) & occupied == BitBoard::new(0) {
+                                targets.push(double);
+                            }
+                        }
+                    }
+                }
+                for target in targets {
+                    if target.get_rank() == Rank::Eighth {
+                        for promoted in &[KQK.entries.as_slice(), KRK.entries.as_slice()] {
+                            let child = index_of(false, strong_king, weak_king, target);
+                            children.push(Some(Child::Entry(promoted[child])));
+                        }
+                    } else {
+                        let child = index_of(false, strong_king, weak_king, target);
+                        children.push(Some(Child::Index(child)));
+                    }
+                }
+            } else {
+                let targets = self.attacks(piece, strong_king_bb | weak_king_bb)
+                    & !strong_king_bb
+                    & !weak_king_bb;
+                for target in targets {
+                    let child = index_of(false, strong_king, weak_king, target);
+                    children.push(Some(Child::Index(child)));
+                }
+            }
+        } else {
+            let attacked = Board::king_attacks(strong_king) | self.attacks(piece, strong_king_bb);
+            for target in Board::king_attacks(weak_king) & !attacked {
+                if target == piece {
+                    // taking the piece leaves two bare kings
+                    children.push(None);
+                } else {
+                    let child = index_of(true, strong_king, target, piece);
+                    children.push(Some(Child::Index(child)));
+                }
+            }
+        }
+        children
+    }
+
+    fn entry(&self, child: &Option<Child>) -> u8 {
+        match child {
+            Some(Child::Index(index)) => self.entries[*index],
+            Some(Child::Entry(entry)) => *entry,
+            None => DRAW,
+        }
+    }
+
+    // Illegal positions, and the lone king checkmated
+    fn mark_terminal_positions(&mut self) {
+        for index in 0..SIZE {
+            if !self.is_legal(index) {
+                self.entries[index] = ILLEGAL;
+                continue;
+            }
+            let (strong_to_move, strong_king, weak_king, piece) = squares(index);
+            if !strong_to_move && self.children(index).is_empty() {
+                let in_check = self.attacks(piece, BitBoard::from_square(strong_king))
+                    & BitBoard::from_square(weak_king)
+                    != BitBoard::new(0);
+                if in_check {
+                    self.entries[index] = 1;
+                }
+            }
+        }
+    }
+
+    // Decide the positions one mate distance at a time: white wins in n plies
+    // with a move to a loss in n - 1, black loses in n when every move
+    // leads to a win in n - 1 at most
+    fn retrograde(&mut self) {
+        let longest_promotion = if self.piece == Piece::Pawn {
+            KQK.longest_mate().max(KRK.longest_mate())
+        } else {
+            0
+        };
+        let mut distance: u8 = 1;
+        let mut unchanged = 0;
+        while unchanged < 2 || distance <= longest_promotion + 1 {
+            let strong_to_move = distance % 2 == 1;
+            let mut changed = false;
+            for index in 0..SIZE {
+                if self.entries[index] != DRAW || (index >> 18 == 0) != strong_to_move {
+                    continue;
+                }
+                let children = self.children(index);
+                let decided = if strong_to_move {
+                    children.iter().any(|child| self.entry(child) == distance)
+                } else {
+                    !children.is_empty()
+                        && children
+                            .iter()
+                            .all(|child| !matches!(self.entry(child), DRAW | ILLEGAL))
+                };
+                if decided {
+                    self.entries[index] = distance + 1;
+                    changed = true;
+                }
+            }
+            unchanged = if changed { 0 } else { unchanged + 1 };
+            distance += 1;
+        }
+    }
+}
+
+fn index_of(strong_to_move: bool, strong_king: Square, weak_king: Square, piece: Square) -> usize {
+    index(
+        strong_to_move,
+        strong_king.to_index(),
+        weak_king.to_index(),
+        piece.to_index(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{probe, Tablebase, TablebaseError, KRK};
+    use crate::score::Score;
+    use crate::{Piece, Position};
+    use std::str::FromStr;
+    use test_case::test_case;
+
+    #[test_case("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1", Score::Mated(0); "queen mates")]
+    #[test_case("k7/8/1QK5/8/8/8/8/8 b - - 0 1", Score::DRAW; "stalemate")]
+    #[test_case("k7/8/1K6/8/8/8/8/2R5 w - - 0 1", Score::Mate(1); "rook mates in one")]
+    #[test_case("2r5/8/8/8/8/1k6/8/K7 b - - 0 1", Score::Mate(1); "black rook mates in one")]
+    #[test_case("8/8/8/8/8/1k6/8/K1r5 w - - 0 1", Score::Mated(0); "black rook mates")]
+    #[test_case("8/8/8/8/8/2k5/1R6/4K3 b - - 0 1", Score::DRAW; "rook gets taken")]
+    #[test_case("8/8/8/4k3/8/8/3BK3/8 w - - 0 1", Score::DRAW; "bishop can't mate")]
+    #[test_case("8/8/8/8/8/3k4/8/3K2N1 w - - 0 1", Score::DRAW; "knight can't mate")]
+    #[test_case("8/4k3/8/4K3/4P3/8/8/8 w - - 0 1", Score::DRAW; "opposition holds")]
+    #[test_case("k7/8/8/8/8/8/P7/7K w - - 0 1", Score::DRAW; "rook pawn")]
+    fn probes(fen: &str, expected: Score) {
+        let position = Position::from_str(fen).unwrap();
+        assert_eq!(probe(&position), Some(expected));
+    }
+
+    #[test_case("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"; "king in front")]
+    #[test_case("8/3k4/8/4K3/4P3/8/8/8 w - - 0 1"; "king ahead of the pawn")]
+    fn pawn_promotes_and_mates(fen: &str) {
+        let position = Position::from_str(fen).unwrap();
+        assert!(matches!(probe(&position), Some(Score::Mate(_))));
+    }
+
+    #[test]
+    fn only_three_pieces() {
+        let position = Position::from_str("4k3/8/4K3/4P3/8/8/8/7R w - - 0 1").unwrap();
+        assert_eq!(probe(&position), None);
+        assert_eq!(probe(&Position::default()), None);
+    }
+
+    #[test]
+    fn longest_mates() {
+        // 16 moves with the rook, black moves first in the longest line
+        assert_eq!(KRK.longest_mate(), 32);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let bytes = KRK.to_bytes();
+        let table = Tablebase::from_bytes(&bytes).unwrap();
+        assert_eq!(table.piece(), Piece::Rook);
+        assert!(table == *KRK);
+        assert!(matches!(
+            Tablebase::from_bytes(&bytes[..100]),
+            Err(TablebaseError::InvalidLength { actual: 100, .. })
+        ));
+        assert!(matches!(
+            Tablebase::from_bytes(b"not a table"),
+            Err(TablebaseError::InvalidMagic)
+        ));
+    }
+}
@@ -0,0 +1,258 @@
+use crate::ChessMove;
+use std::time::{Duration, Instant};
+
+// Number of moves the remaining time is assumed to be spread over
+const MOVES_HORIZON: u32 = 30;
+// Don't ever plan on using more than this fraction of the remaining time on one move
+const MAX_TIME_FRACTION: u32 = 4;
+// A drop in score (in evaluation units) between iterations that is considered sharp
+const SCORE_DROP_MARGIN: i16 = 10;
+// Number of iterations with an unchanged best move before it is considered dominant
+const STABLE_ITERATIONS: u8 = 4;
+
+/// The time control the engine is playing under.
+///
+/// The engine gets `base` time for the whole game and
+/// gains `increment` after every move it makes.
+/// `move_overhead` is kept in reserve on every move to account for
+/// time lost outside of the search (GUI or network lag).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeControl {
+    base: Duration,
+    increment: Duration,
+    move_overhead: Duration,
+}
+
+impl TimeControl {
+    pub fn new(base: Duration, increment: Duration) -> Self {
+        Self {
+            base,
+            increment,
+            move_overhead: Duration::default(),
+        }
+    }
+
+    pub fn with_move_overhead(mut self, move_overhead: Duration) -> Self {
+        self.move_overhead = move_overhead;
+        self
+    }
+
+    pub fn base(&self) -> Duration {
+        self.base
+    }
+
+    pub fn increment(&self) -> Duration {
+        self.increment
+    }
+
+    pub fn move_overhead(&self) -> Duration {
+        self.move_overhead
+    }
+}
+
+/// Keeps track of the engine's clock over the course of a game
+/// and hands out a [`MoveTimer`] for every move.
+#[derive(Debug, Clone)]
+pub struct TimeManager {
+    control: TimeControl,
+    remaining: Duration,
+}
+
+impl TimeManager {
+    pub fn new(control: TimeControl) -> Self {
+        Self {
+            control,
+            remaining: control.base,
+        }
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.remaining
+    }
+
+    pub fn set_move_overhead(&mut self, move_overhead: Duration) {
+        self.control.move_overhead = move_overhead;
+    }
+
+    /// Allocate time for the next move and start its timer.
+    pub fn start_move(&self) -> MoveTimer {
+        let available = self.remaining.saturating_sub(self.control.move_overhead);
+        let maximum = available / MAX_TIME_FRACTION;
+        let optimum = available / MOVES_HORIZON + self.control.increment * 3 / 4;
+        MoveTimer::new(optimum.min(maximum), maximum)
+    }
+
+    /// Update the clock after a move took `elapsed` time.
+    pub fn finish_move(&mut self, elapsed: Duration) {
+        self.remaining = self.remaining.saturating_sub(elapsed) + self.control.increment;
+    }
+}
+
+/// Decides when to stop iterative deepening for a single move.
+///
+/// The `optimum` time is stretched when the best move keeps changing
+/// or the score drops sharply between iterations, and shortened when one
+/// move has been the best for several iterations in a row.
+/// The search should never run past the `maximum` time.
+#[derive(Debug, Clone)]
+pub struct MoveTimer {
+    start: Instant,
+    optimum: Duration,
+    maximum: Duration,
+    best_move: Option<ChessMove>,
+    score: Option<i16>,
+    best_move_changed: bool,
+    score_dropped: bool,
+    failed_low: bool,
+    stable_iterations: u8,
+}
+
+impl MoveTimer {
+    pub fn new(optimum: Duration, maximum: Duration) -> Self {
+        Self {
+            start: Instant::now(),
+            optimum,
+            maximum,
+            best_move: None,
+            score: None,
+            best_move_changed: false,
+            score_dropped: false,
+            failed_low: false,
+            stable_iterations: 0,
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// The point in time the search must be stopped at, even mid-iteration
+    pub fn deadline(&self) -> Instant {
+        self.start + self.maximum
+    }
+
+    /// Record that the root of the running iteration failed low, its score
+    /// is below the window around the last one. The iteration counts as a
+    /// drop in score whatever score its re-search finds.
+    pub fn record_fail_low(&mut self) {
+        self.failed_low = true;
+    }
+
+    /// Record the result of a completed iterative deepening iteration.
+    pub fn record_iteration(&mut self, best_move: ChessMove, score: i16) {
+        self.best_move_changed = self.best_move.map_or(false, |m| m != best_move);
+        self.score_dropped =
+            self.failed_low || self.score.map_or(false, |s| score < s - SCORE_DROP_MARGIN);
+        self.failed_low = false;
+        if self.best_move_changed || self.best_move.is_none() {
+            self.stable_iterations = 0;
+        } else {
+            self.stable_iterations = self.stable_iterations.saturating_add(1);
+        }
+        self.best_move = Some(best_move);
+        self.score = Some(score);
+    }
+
+    /// The time budget for this move given what the last iterations found
+    pub fn budget(&self) -> Duration {
+        let mut budget = self.optimum;
+        if self.best_move_changed {
+            budget = budget * 3 / 2;
+        }
+        if self.score_dropped {
+            budget = budget * 3 / 2;
+        }
+        if self.stable_iterations >= STABLE_ITERATIONS {
+            budget /= 2;
+        }
+        budget.min(self.maximum)
+    }
+
+    /// Returns true when another iteration should not be started.
+    ///
+    /// An iteration usually takes longer than all of the previous ones combined
+    /// so a new one isn't started once half of the budget has been used.
+    pub fn should_stop(&self) -> bool {
+        self.elapsed() * 2 >= self.budget()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MoveTimer, TimeControl, TimeManager};
+    use crate::{ChessMove, Square};
+    use std::time::Duration;
+
+    #[test]
+    fn allocate_from_base_and_increment() {
+        let control = TimeControl::new(Duration::from_secs(300), Duration::from_secs(2));
+        let timer = TimeManager::new(control).start_move();
+        assert_eq!(timer.budget(), Duration::from_millis(11500));
+        assert_eq!(timer.maximum, Duration::from_secs(75));
+    }
+
+    #[test]
+    fn overhead_is_reserved() {
+        let control = TimeControl::new(Duration::from_secs(4), Duration::from_secs(0))
+            .with_move_overhead(Duration::from_secs(1));
+        let timer = TimeManager::new(control).start_move();
+        assert_eq!(timer.budget(), Duration::from_millis(100));
+        assert_eq!(timer.maximum, Duration::from_millis(750));
+    }
+
+    #[test]
+    fn clock_is_updated_after_move() {
+        let control = TimeControl::new(Duration::from_secs(60), Duration::from_secs(1));
+        let mut manager = TimeManager::new(control);
+        manager.finish_move(Duration::from_secs(5));
+        assert_eq!(manager.remaining(), Duration::from_secs(56));
+    }
+
+    #[test]
+    fn unstable_best_move_extends_budget() {
+        let mut timer = MoveTimer::new(Duration::from_secs(10), Duration::from_secs(100));
+        timer.record_iteration(ChessMove::new(Square::E2, Square::E4, None), 0);
+        assert_eq!(timer.budget(), Duration::from_secs(10));
+        timer.record_iteration(ChessMove::new(Square::D2, Square::D4, None), 0);
+        assert_eq!(timer.budget(), Duration::from_secs(15));
+    }
+
+    #[test]
+    fn score_drop_extends_budget() {
+        let mut timer = MoveTimer::new(Duration::from_secs(10), Duration::from_secs(100));
+        let chess_move = ChessMove::new(Square::E2, Square::E4, None);
+        timer.record_iteration(chess_move, 20);
+        timer.record_iteration(chess_move, -20);
+        assert_eq!(timer.budget(), Duration::from_secs(15));
+    }
+
+    #[test]
+    fn fail_low_extends_budget() {
+        let mut timer = MoveTimer::new(Duration::from_secs(10), Duration::from_secs(100));
+        let chess_move = ChessMove::new(Square::E2, Square::E4, None);
+        timer.record_iteration(chess_move, 20);
+        timer.record_fail_low();
+        timer.record_iteration(chess_move, 15);
+        assert_eq!(timer.budget(), Duration::from_secs(15));
+        timer.record_iteration(chess_move, 15);
+        assert_eq!(timer.budget(), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn dominant_move_shortens_budget() {
+        let mut timer = MoveTimer::new(Duration::from_secs(10), Duration::from_secs(100));
+        let chess_move = ChessMove::new(Square::E2, Square::E4, None);
+        for _ in 0..5 {
+            timer.record_iteration(chess_move, 0);
+        }
+        assert_eq!(timer.budget(), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn budget_never_exceeds_maximum() {
+        let mut timer = MoveTimer::new(Duration::from_secs(10), Duration::from_secs(12));
+        timer.record_iteration(ChessMove::new(Square::E2, Square::E4, None), 20);
+        timer.record_iteration(ChessMove::new(Square::D2, Square::D4, None), -20);
+        assert_eq!(timer.budget(), Duration::from_secs(12));
+    }
+}
@@ -1,14 +1,24 @@
 use crate::move_hash;
-use crate::node::NodeValue;
-use std::cell::RefCell;
-use std::sync::Mutex;
+pub use crate::node::NodeValue;
+use std::alloc::{self, Layout};
+use std::cmp;
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{Mutex, MutexGuard};
 
 use crate::ChessMove;
 use crate::Position;
 
 const CACHE_SIZE: usize = 30000000;
-
-type ThreadCountHash<T> = (u8, EvaluationHash<T>);
+// The smallest table a failed allocation falls back to
+const MIN_CACHE_SIZE: usize = 2 * SHARDS;
+// Number of independently locked parts of the table
+const SHARDS: usize = 64;
+const SNAPSHOT_MAGIC: &[u8; 8] = b"RAD1HASH";
+// Bytes of an entry in a snapshot: hash, depth, node type, value and best move
+const SNAPSHOT_ENTRY_SIZE: usize = 8 + 1 + 1 + 2 + 2;
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 struct EvaluationHash<T> {
@@ -16,20 +26,142 @@ struct EvaluationHash<T> {
     depth: u8,
     value: NodeValue<T>,
     best_move_hash: u16,
+    // the search that stored the entry
+    generation: u8,
+}
+
+impl<T> EvaluationHash<T> {
+    fn is_empty(&self) -> bool {
+        self.hash == 0
+    }
+}
+
+// The two entries of an index. Probing a position only touches its bucket,
+// which is aligned so it never straddles a cache line: with `i16` values
+// two buckets fill a 64-byte line exactly.
+#[derive(Debug, Clone, Copy)]
+#[repr(C, align(32))]
+struct Bucket<T> {
+    deep: EvaluationHash<T>,
+    shallow: EvaluationHash<T>,
+}
+
+/// How the memory of a table is allocated, both are hints the system may ignore
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableMemory {
+    /// Ask for huge pages, transparent huge pages on Linux, so large tables
+    /// need fewer TLB entries
+    pub huge_pages: bool,
+    /// Spread the pages over every NUMA node instead of the one of the
+    /// allocating thread, so threads on every node probe it equally fast
+    pub numa_interleave: bool,
+}
+
+impl Default for TableMemory {
+    fn default() -> Self {
+        Self {
+            huge_pages: true,
+            numa_interleave: false,
+        }
+    }
+}
+
+/// A search of a position stored in the table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableEntry<T> {
+    pub value: NodeValue<T>,
+    pub depth: u8,
+    pub best_move: ChessMove,
+}
+
+/// The entries stored for a position
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Probe<T> {
+    /// The matching entry with the deepest search, the one to use for cutoffs
+    pub deeper: Option<TableEntry<T>>,
+    /// The other entry when both match. It comes from a shallower and
+    /// usually more recent search, so its move is the better guess for ordering.
+    pub shallower: Option<TableEntry<T>>,
+}
+
+impl<T> Probe<T>
+where
+    T: Copy,
+{
+    /// The move to search first, from the shallower entry if there is one
+    pub fn best_move(&self) -> Option<ChessMove> {
+        self.shallower.or(self.deeper).map(|entry| entry.best_move)
+    }
 }
 
+/// Counters of the decisions the table made about where writes go,
+/// collected since the table was created or last cleared
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TableStats {
+    /// Calls to [`TranspositionTable::update_evaluation_and_best_move`]
+    pub writes: u64,
+    /// Writes the depth-preferred (shallow) entry kept out
+    /// because it holds a deeper search
+    pub depth_rejections: u64,
+    /// Writes that replaced a different position searched deeper than the new one
+    pub deeper_overwrites: u64,
+    /// Entries that hold a position
+    pub occupied: usize,
+    /// Average number of searches since the occupied entries were stored
+    pub average_age: f64,
+}
+
+// A slice of the table behind a single lock,
+// slot `i` of shard `s` holds index `i * SHARDS + s` of the table
+struct Shard<T> {
+    buckets: Vec<Bucket<T>>,
+}
+
+/// Caches evaluations and best moves of positions.
+///
+/// Every index has a deep entry, which keeps the shallowest search
+/// (most likely to be hit again), and a shallow entry, which keeps the
+/// deepest search. Writes are synchronous so an entry is visible to the
+/// search as soon as it is stored, and the table is split into shards with
+/// their own lock instead of locking every entry separately.
 pub struct TranspositionTable<T> {
     cache_size: u64,
-    deep_cache: Vec<Mutex<RefCell<ThreadCountHash<T>>>>,
-    shallow_cache: Vec<Mutex<RefCell<EvaluationHash<T>>>>,
+    shards: Vec<Mutex<Shard<T>>>,
+    generation: AtomicU8,
+    writes: AtomicU64,
+    depth_rejections: AtomicU64,
+    deeper_overwrites: AtomicU64,
+}
+
+/// There wasn't enough memory for a table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocationError {
+    /// The number of positions the table was asked to hold
+    pub cache_size: usize,
+    /// The bytes it would have needed
+    pub bytes: usize,
 }
 
+impl fmt::Display for AllocationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "not enough memory for a transposition table of {} positions ({} MB)",
+            self.cache_size,
+            self.bytes / (1024 * 1024)
+        )
+    }
+}
+
+impl Error for AllocationError {}
+
+// The default table falls back to a smaller one like `default_or_smaller`
 impl<T> Default for TranspositionTable<T>
 where
     T: Copy + Default,
 {
     fn default() -> Self {
-        Self::new(CACHE_SIZE)
+        Self::default_or_smaller().0
     }
 }
 
@@ -37,76 +169,213 @@ impl<T> TranspositionTable<T>
 where
     T: Copy + Default,
 {
+    /// Create a table holding `cache_size` positions, panics when there
+    /// isn't enough memory for it
     pub fn new(cache_size: usize) -> Self {
+        Self::try_new(cache_size).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Create a table holding `cache_size` positions, or report the memory
+    /// it needs when the allocator can't provide it.
+    ///
+    /// Systems that overcommit memory may hand out more than they have and
+    /// end the process once it's used, so this can't catch every shortage.
+    pub fn try_new(cache_size: usize) -> Result<Self, AllocationError> {
+        Self::try_new_in(cache_size, TableMemory::default())
+    }
+
+    fn try_new_in(cache_size: usize, memory: TableMemory) -> Result<Self, AllocationError> {
+        let error = AllocationError {
+            cache_size,
+            bytes: Self::required_memory(cache_size),
+        };
         let size = cache_size / 2;
-        let mut deep_cache = Vec::with_capacity(size);
-        let mut shallow_cache = Vec::with_capacity(size);
-        for _ in 0..size {
-            shallow_cache.push(Mutex::default());
-            let deep_value = EvaluationHash {
-                hash: 0,
-                depth: 255,
-                value: NodeValue::default(),
-                best_move_hash: 0,
-            };
-            deep_cache.push(Mutex::new(RefCell::new((0, deep_value))));
+        let shard_size = (size + SHARDS - 1) / SHARDS;
+        let mut shards = Vec::with_capacity(SHARDS);
+        for _ in 0..SHARDS {
+            let buckets = try_filled_vec(shard_size, empty_bucket(), memory).ok_or(error)?;
+            shards.push(Mutex::new(Shard { buckets }));
         }
-        Self {
+        Ok(Self {
             cache_size: size as u64,
-            deep_cache,
-            shallow_cache,
+            shards,
+            generation: AtomicU8::new(0),
+            writes: AtomicU64::new(0),
+            depth_rejections: AtomicU64::new(0),
+            deeper_overwrites: AtomicU64::new(0),
+        })
+    }
+
+    /// Create a table holding `cache_size` positions, or halve the size
+    /// until there is enough memory. The error tells why the requested size
+    /// didn't fit, if it didn't.
+    pub fn new_or_smaller(cache_size: usize) -> (Self, Option<AllocationError>) {
+        Self::new_or_smaller_in(cache_size, TableMemory::default())
+    }
+
+    fn new_or_smaller_in(
+        cache_size: usize,
+        memory: TableMemory,
+    ) -> (Self, Option<AllocationError>) {
+        let mut size = cache_size;
+        let mut first_error = None;
+        loop {
+            match Self::try_new_in(size, memory) {
+                Ok(table) => return (table, first_error),
+                Err(error) if size > MIN_CACHE_SIZE => {
+                    first_error = first_error.or(Some(error));
+                    size = cmp::max(size / 2, MIN_CACHE_SIZE);
+                }
+                Err(error) => panic!("{}", error),
+            }
+        }
+    }
+
+    /// The table of the default size, about 30 million positions, or a
+    /// smaller one like [`TranspositionTable::new_or_smaller`]
+    pub fn default_or_smaller() -> (Self, Option<AllocationError>) {
+        Self::new_or_smaller(CACHE_SIZE)
+    }
+
+    /// Create a table that takes up roughly the given number of megabytes,
+    /// or less when there isn't enough memory
+    pub fn with_megabytes(megabytes: usize) -> Self {
+        Self::with_memory(megabytes, TableMemory::default())
+    }
+
+    /// Like [`TranspositionTable::with_megabytes`], allocating the table
+    /// the given way
+    pub fn with_memory(megabytes: usize, memory: TableMemory) -> Self {
+        let cache_size = megabytes * 1024 * 1024 / Self::bytes_per_position();
+        Self::new_or_smaller_in(cache_size, memory).0
+    }
+
+    /// The memory a position takes up in the table, in bytes
+    pub fn bytes_per_position() -> usize {
+        mem::size_of::<Bucket<T>>() / 2
+    }
+
+    /// The bytes of the entries of a table holding `cache_size` positions
+    pub fn required_memory(cache_size: usize) -> usize {
+        let shard_size = (cache_size / 2 + SHARDS - 1) / SHARDS;
+        (2 * SHARDS * shard_size).saturating_mul(Self::bytes_per_position())
+    }
+
+    /// The bytes the entries of the table take up
+    pub fn memory_usage(&self) -> usize {
+        Self::required_memory(2 * self.cache_size as usize)
+    }
+
+    /// The number of positions the table can hold
+    pub fn capacity(&self) -> usize {
+        2 * self.cache_size as usize
+    }
+
+    // Lock the shard holding the position's entries
+    // and return it with the slot of the entries in it
+    fn entry(&self, hash: u64) -> (MutexGuard<'_, Shard<T>>, usize) {
+        let index = (hash % self.cache_size) as usize;
+        let shard = self.shards[index % SHARDS].lock().unwrap();
+        (shard, index / SHARDS)
+    }
+
+    /// Mark the start of a new search, entries stored by earlier searches age by one
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Statistics about the writes to the table and the age of its entries
+    pub fn stats(&self) -> TableStats {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let mut occupied = 0;
+        let mut total_age = 0u64;
+        for shard in &self.shards {
+            let shard = shard.lock().unwrap();
+            for value in shard
+                .buckets
+                .iter()
+                .flat_map(|bucket| [&bucket.deep, &bucket.shallow])
+            {
+                if !value.is_empty() {
+                    occupied += 1;
+                    total_age += generation.wrapping_sub(value.generation) as u64;
+                }
+            }
+        }
+        TableStats {
+            writes: self.writes.load(Ordering::Relaxed),
+            depth_rejections: self.depth_rejections.load(Ordering::Relaxed),
+            deeper_overwrites: self.deeper_overwrites.load(Ordering::Relaxed),
+            occupied,
+            average_age: if occupied == 0 {
+                0.0
+            } else {
+                total_age as f64 / occupied as f64
+            },
+        }
+    }
+
+    /// Forget every stored position and reset the statistics
+    pub fn clear(&self) {
+        self.writes.store(0, Ordering::Relaxed);
+        self.depth_rejections.store(0, Ordering::Relaxed);
+        self.deeper_overwrites.store(0, Ordering::Relaxed);
+        for shard in &self.shards {
+            let mut shard = shard.lock().unwrap();
+            for bucket in shard.buckets.iter_mut() {
+                *bucket = empty_bucket();
+            }
         }
     }
 
     pub fn best_move(&self, position: &Position) -> Option<ChessMove> {
         let hash = position.get_hash();
+        let (shard, slot) = self.entry(hash);
+        let bucket = &shard.buckets[slot];
         // try from shallow cache first
-        {
-            let guard = self.shallow_cache[(hash % self.cache_size) as usize]
-                .lock()
-                .unwrap();
-            let value = guard.borrow();
-            if value.hash == hash && value.best_move_hash != 0 {
-                return Some(move_hash::get_move(value.best_move_hash));
-            }
+        let value = &bucket.shallow;
+        if value.hash == hash && value.best_move_hash != 0 {
+            return Some(move_hash::get_move(value.best_move_hash));
         }
         // try from deep cache second
-        {
-            let guard = self.deep_cache[(hash % self.cache_size) as usize]
-                .lock()
-                .unwrap();
-            let value = guard.borrow();
-            if value.1.hash == hash && value.1.best_move_hash != 0 {
-                return Some(move_hash::get_move(value.1.best_move_hash));
-            }
+        let value = &bucket.deep;
+        if value.hash == hash && value.best_move_hash != 0 {
+            return Some(move_hash::get_move(value.best_move_hash));
         }
         // otherwise return None
         None
     }
 
-    pub fn get_evaluation_and_depth(&self, position: &Position) -> Option<(NodeValue<T>, u8)> {
+    /// Look up both entries of the position, see [`Probe`]
+    pub fn get_evaluation_and_depth(&self, position: &Position) -> Probe<T> {
         let hash = position.get_hash();
-        // try from deep cache first
-        {
-            let guard = self.deep_cache[(hash % self.cache_size) as usize]
-                .lock()
-                .unwrap();
-            let value = guard.borrow();
-            if value.1.hash == hash && value.1.best_move_hash != 0 {
-                return Some((value.1.value, value.1.depth));
-            }
-        }
-        // try from shallow cache second
-        {
-            let guard = self.shallow_cache[(hash % self.cache_size) as usize]
-                .lock()
-                .unwrap();
-            let value = guard.borrow();
+        let (shard, slot) = self.entry(hash);
+        let matching = |value: &EvaluationHash<T>| {
             if value.hash == hash && value.best_move_hash != 0 {
-                return Some((value.value, value.depth));
+                Some(TableEntry {
+                    value: value.value,
+                    depth: value.depth,
+                    best_move: move_hash::get_move(value.best_move_hash),
+                })
+            } else {
+                None
             }
+        };
+        let bucket = &shard.buckets[slot];
+        match (matching(&bucket.shallow), matching(&bucket.deep)) {
+            (Some(first), Some(second)) if second.depth > first.depth => Probe {
+                deeper: Some(second),
+                shallower: Some(first),
+            },
+            (Some(first), second) => Probe {
+                deeper: Some(first),
+                shallower: second,
+            },
+            (None, second) => Probe {
+                deeper: second,
+                shallower: None,
+            },
         }
-        None
     }
 
     pub fn update_evaluation_and_best_move(
@@ -116,45 +385,223 @@ where
         node: NodeValue<T>,
         best_move: Option<ChessMove>,
     ) {
-        let hash = position.get_hash();
+        self.store(
+            position.get_hash(),
+            depth,
+            node,
+            best_move.map(move_hash::get_hash),
+        );
+    }
+
+    fn store(&self, hash: u64, depth: u8, node: NodeValue<T>, best_move_hash: Option<u16>) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let (mut shard, slot) = self.entry(hash);
+        self.writes.fetch_add(1, Ordering::Relaxed);
         // update shallow cache
         {
-            let guard = self.shallow_cache[(hash % self.cache_size) as usize]
-                .lock()
-                .unwrap();
-            let mut value = guard.borrow_mut();
+            let value = &mut shard.buckets[slot].shallow;
             if value.depth <= depth {
                 value.depth = depth;
                 value.hash = hash;
                 value.value = node;
-                if let Some(chess_move) = best_move {
-                    value.best_move_hash = move_hash::get_hash(chess_move);
+                value.generation = generation;
+                if let Some(best_move_hash) = best_move_hash {
+                    value.best_move_hash = best_move_hash;
                 }
+            } else {
+                self.depth_rejections.fetch_add(1, Ordering::Relaxed);
             }
         }
         // update deep cache
         {
-            let guard = self.deep_cache[(hash % self.cache_size) as usize]
-                .lock()
-                .unwrap();
-            let mut value = guard.borrow_mut();
-            if value.1.depth >= depth {
-                value.1.depth = depth;
-                value.1.hash = hash;
-                value.1.value = node;
-                if let Some(chess_move) = best_move {
-                    value.1.best_move_hash = move_hash::get_hash(chess_move);
+            let value = &mut shard.buckets[slot].deep;
+            if value.depth >= depth {
+                if !value.is_empty() && value.hash != hash && value.depth > depth {
+                    self.deeper_overwrites.fetch_add(1, Ordering::Relaxed);
+                }
+                value.depth = depth;
+                value.hash = hash;
+                value.value = node;
+                value.generation = generation;
+                if let Some(best_move_hash) = best_move_hash {
+                    value.best_move_hash = best_move_hash;
+                }
+            }
+        }
+    }
+}
+
+/// The reason a snapshot of a table couldn't be restored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    InvalidMagic,
+    /// The entries don't add up to whole entries
+    InvalidLength(usize),
+    InvalidNodeType(u8),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::InvalidMagic => write!(f, "not a rad1 hash snapshot"),
+            SnapshotError::InvalidLength(length) => {
+                write!(f, "{} bytes isn't a whole number of entries", length)
+            }
+            SnapshotError::InvalidNodeType(node) => write!(f, "invalid node type {}", node),
+        }
+    }
+}
+
+impl Error for SnapshotError {}
+
+impl TranspositionTable<i16> {
+    /// The stored positions as bytes, to continue a long analysis later
+    /// with [`TranspositionTable::restore`]
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        for shard in &self.shards {
+            let shard = shard.lock().unwrap();
+            for value in shard
+                .buckets
+                .iter()
+                .flat_map(|bucket| [&bucket.deep, &bucket.shallow])
+            {
+                if value.is_empty() {
+                    continue;
                 }
+                let (node, stored) = match value.value {
+                    NodeValue::Principal { value } => (0, value),
+                    NodeValue::All { value } => (1, value),
+                    NodeValue::Cut { value } => (2, value),
+                };
+                bytes.extend_from_slice(&value.hash.to_le_bytes());
+                bytes.push(value.depth);
+                bytes.push(node);
+                bytes.extend_from_slice(&stored.to_le_bytes());
+                bytes.extend_from_slice(&value.best_move_hash.to_le_bytes());
             }
         }
+        bytes
+    }
+
+    /// Store the positions of a snapshot, the table doesn't need to have
+    /// the size of the one the snapshot was taken of.
+    /// Returns the number of entries in the snapshot.
+    pub fn restore(&self, bytes: &[u8]) -> Result<usize, SnapshotError> {
+        if !bytes.starts_with(SNAPSHOT_MAGIC) {
+            return Err(SnapshotError::InvalidMagic);
+        }
+        let entries = &bytes[SNAPSHOT_MAGIC.len()..];
+        if entries.len() % SNAPSHOT_ENTRY_SIZE != 0 {
+            return Err(SnapshotError::InvalidLength(entries.len()));
+        }
+        for entry in entries.chunks(SNAPSHOT_ENTRY_SIZE) {
+            let mut hash = [0; 8];
+            hash.copy_from_slice(&entry[..8]);
+            let value = i16::from_le_bytes([entry[10], entry[11]]);
+            let node = match entry[9] {
+                0 => NodeValue::pv_node(value),
+                1 => NodeValue::all_node(value),
+                2 => NodeValue::cut_node(value),
+                node => return Err(SnapshotError::InvalidNodeType(node)),
+            };
+            let best_move_hash = u16::from_le_bytes([entry[12], entry[13]]);
+            self.store(
+                u64::from_le_bytes(hash),
+                entry[8],
+                node,
+                Some(best_move_hash),
+            );
+        }
+        Ok(entries.len() / SNAPSHOT_ENTRY_SIZE)
+    }
+}
+
+// A vector of `len` copies of `value`, or None when the allocator can't
+// provide the memory, where `vec!` would abort the process. The memory is
+// advised before it's written, which is when the system picks its pages.
+fn try_filled_vec<E: Copy>(len: usize, value: E, memory: TableMemory) -> Option<Vec<E>> {
+    if len == 0 || mem::size_of::<E>() == 0 {
+        return Some(vec![value; len]);
+    }
+    let layout = Layout::array::<E>(len).ok()?;
+    // SAFETY: the layout isn't zero-sized, and all `len` elements are written
+    // before the vector takes over the memory with the layout it was allocated with
+    unsafe {
+        let pointer = alloc::alloc(layout) as *mut E;
+        if pointer.is_null() {
+            return None;
+        }
+        advise(pointer as *mut u8, layout.size(), memory);
+        for index in 0..len {
+            pointer.add(index).write(value);
+        }
+        Some(Vec::from_raw_parts(pointer, len, len))
+    }
+}
+
+// Pass the memory hints for the pages of a freshly allocated block to the
+// kernel. Failures are ignored, the table works the same without them.
+#[cfg(target_os = "linux")]
+fn advise(pointer: *mut u8, size: usize, memory: TableMemory) {
+    use std::os::raw::{c_int, c_ulong, c_void};
+
+    const MPOL_INTERLEAVE: c_int = 3;
+    // SAFETY: sysconf has no preconditions
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+    // only whole pages of the block can be advised
+    let start = (pointer as usize + page_size - 1) / page_size * page_size;
+    let end = (pointer as usize + size) / page_size * page_size;
+    if end <= start {
+        return;
+    }
+    let (start, length) = (start as *mut c_void, end - start);
+    // SAFETY: the range lies within the block, which is allocated and not yet
+    // in use, and neither call changes its contents
+    unsafe {
+        if memory.huge_pages {
+            libc::madvise(start, length, libc::MADV_HUGEPAGE);
+        }
+        if memory.numa_interleave {
+            // every node, the kernel leaves out the ones that don't exist
+            let nodes: c_ulong = !0;
+            libc::syscall(
+                libc::SYS_mbind,
+                start,
+                length,
+                MPOL_INTERLEAVE,
+                &nodes as *const c_ulong,
+                mem::size_of::<c_ulong>() * 8,
+                0,
+            );
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn advise(_pointer: *mut u8, _size: usize, _memory: TableMemory) {}
+
+// The deep entry is replaced by any shallower search, so it starts out as deep as possible
+fn empty_bucket<T: Default>() -> Bucket<T> {
+    let deep = EvaluationHash {
+        hash: 0,
+        depth: 255,
+        value: NodeValue::default(),
+        best_move_hash: 0,
+        generation: 0,
+    };
+    Bucket {
+        deep,
+        shallow: EvaluationHash::default(),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::TranspositionTable;
+    use super::{Bucket, Probe, SnapshotError, TableMemory, TableStats, TranspositionTable};
     use crate::node::NodeValue;
     use crate::{ChessMove, Position, Square};
+    use std::str::FromStr;
 
     #[test]
     fn insert_new_value() {
@@ -166,9 +613,9 @@ mod tests {
             NodeValue::pv_node(50),
             Some(ChessMove::new(Square::E2, Square::E4, None)),
         );
-        let (eval, depth) = tt.get_evaluation_and_depth(&position).unwrap();
-        assert_eq!(depth, 1);
-        assert_eq!(eval, NodeValue::pv_node(50));
+        let entry = tt.get_evaluation_and_depth(&position).deeper.unwrap();
+        assert_eq!(entry.depth, 1);
+        assert_eq!(entry.value, NodeValue::pv_node(50));
 
         let chess_move = tt.best_move(&position).unwrap();
         assert_eq!(chess_move, ChessMove::new(Square::E2, Square::E4, None));
@@ -191,9 +638,17 @@ mod tests {
             NodeValue::pv_node(100),
             Some(ChessMove::new(Square::D2, Square::D4, None)),
         );
-        let (eval, depth) = tt.get_evaluation_and_depth(&position).unwrap();
-        assert_eq!(depth, 1);
-        assert_eq!(eval, NodeValue::pv_node(50));
+        let probe = tt.get_evaluation_and_depth(&position);
+        let deeper = probe.deeper.unwrap();
+        assert_eq!(deeper.depth, 8);
+        assert_eq!(deeper.value, NodeValue::pv_node(100));
+        let shallower = probe.shallower.unwrap();
+        assert_eq!(shallower.depth, 1);
+        assert_eq!(shallower.value, NodeValue::pv_node(50));
+        assert_eq!(
+            probe.best_move(),
+            Some(ChessMove::new(Square::E2, Square::E4, None))
+        );
 
         let chess_move = tt.best_move(&position).unwrap();
         assert_eq!(chess_move, ChessMove::new(Square::D2, Square::D4, None));
@@ -216,11 +671,130 @@ mod tests {
             NodeValue::pv_node(100),
             Some(ChessMove::new(Square::D2, Square::D4, None)),
         );
-        let (eval, depth) = tt.get_evaluation_and_depth(&position).unwrap();
-        assert_eq!(depth, 0);
-        assert_eq!(eval, NodeValue::pv_node(100));
+        let probe = tt.get_evaluation_and_depth(&position);
+        let deeper = probe.deeper.unwrap();
+        assert_eq!(deeper.depth, 1);
+        assert_eq!(deeper.value, NodeValue::pv_node(50));
+        let shallower = probe.shallower.unwrap();
+        assert_eq!(shallower.depth, 0);
+        assert_eq!(shallower.value, NodeValue::pv_node(100));
+        assert_eq!(
+            probe.best_move(),
+            Some(ChessMove::new(Square::D2, Square::D4, None))
+        );
 
         let chess_move = tt.best_move(&position).unwrap();
         assert_eq!(chess_move, ChessMove::new(Square::E2, Square::E4, None));
     }
+
+    #[test]
+    fn clear_forgets_positions() {
+        let tt = TranspositionTable::new(1000);
+        let position = Position::default();
+        tt.update_evaluation_and_best_move(
+            &position,
+            3,
+            NodeValue::pv_node(50),
+            Some(ChessMove::new(Square::E2, Square::E4, None)),
+        );
+        tt.clear();
+        assert_eq!(tt.get_evaluation_and_depth(&position), Probe::default());
+        assert_eq!(tt.best_move(&position), None);
+    }
+
+    #[test]
+    fn stats_count_replacements() {
+        let tt = TranspositionTable::new(2);
+        let position = Position::default();
+        let other = Position::from_str("8/8/4k3/8/2p5/8/B2K4/8 w - - 0 1").unwrap();
+        tt.update_evaluation_and_best_move(&position, 6, NodeValue::pv_node(50), None);
+        // the shallow entry keeps the deeper search
+        tt.update_evaluation_and_best_move(&position, 2, NodeValue::pv_node(40), None);
+        tt.new_search();
+        tt.new_search();
+        // both positions share the only slot of the table
+        tt.update_evaluation_and_best_move(&other, 1, NodeValue::pv_node(10), None);
+        let stats = tt.stats();
+        assert_eq!(stats.writes, 3);
+        assert_eq!(stats.depth_rejections, 2);
+        assert_eq!(stats.deeper_overwrites, 1);
+        assert_eq!(stats.occupied, 2);
+        assert!((stats.average_age - 1.0).abs() < f64::EPSILON);
+        tt.clear();
+        assert_eq!(tt.stats(), TableStats::default());
+    }
+
+    #[test]
+    fn size_in_megabytes() {
+        let small = TranspositionTable::<i16>::with_megabytes(1);
+        let large = TranspositionTable::<i16>::with_megabytes(4);
+        assert!(small.capacity() > 0);
+        assert!(large.capacity() >= 4 * small.capacity() - 2);
+    }
+
+    #[test]
+    fn memory_usage() {
+        let tt = TranspositionTable::<i16>::new(1 << 16);
+        let per_position = TranspositionTable::<i16>::bytes_per_position();
+        assert_eq!(tt.memory_usage(), tt.capacity() * per_position);
+        let megabyte = TranspositionTable::<i16>::with_megabytes(1).memory_usage();
+        assert!(megabyte <= 1024 * 1024 + 2 * 64 * per_position);
+    }
+
+    #[test]
+    fn buckets_fit_cache_lines() {
+        assert_eq!(std::mem::size_of::<Bucket<i16>>(), 32);
+        assert_eq!(64 % std::mem::align_of::<Bucket<i16>>(), 0);
+        let memory = TableMemory {
+            huge_pages: true,
+            numa_interleave: true,
+        };
+        let tt = TranspositionTable::with_memory(4, memory);
+        let position = Position::default();
+        let e4 = ChessMove::new(Square::E2, Square::E4, None);
+        tt.update_evaluation_and_best_move(&position, 3, NodeValue::pv_node(5), Some(e4));
+        assert_eq!(tt.best_move(&position), Some(e4));
+        assert_eq!(
+            tt.get_evaluation_and_depth(&position).deeper.unwrap().depth,
+            3
+        );
+    }
+
+    #[test]
+    fn reports_failed_allocations() {
+        // far beyond any address space
+        let cache_size = usize::MAX / 4;
+        let error = TranspositionTable::<i16>::try_new(cache_size)
+            .err()
+            .unwrap();
+        assert_eq!(error.cache_size, cache_size);
+        assert_eq!(
+            error.bytes,
+            TranspositionTable::<i16>::required_memory(cache_size)
+        );
+        let (tt, error) = TranspositionTable::<i16>::new_or_smaller(1000);
+        assert_eq!((tt.capacity(), error), (1000, None));
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let tt = TranspositionTable::new(1000);
+        let position = Position::default();
+        let e4 = ChessMove::new(Square::E2, Square::E4, None);
+        tt.update_evaluation_and_best_move(&position, 7, NodeValue::cut_node(-12), Some(e4));
+        let snapshot = tt.snapshot();
+
+        let restored = TranspositionTable::new(5000);
+        assert_eq!(restored.restore(&snapshot), Ok(2));
+        let entry = restored.get_evaluation_and_depth(&position).deeper.unwrap();
+        assert_eq!(entry.depth, 7);
+        assert_eq!(entry.value, NodeValue::cut_node(-12));
+        assert_eq!(entry.best_move, e4);
+
+        assert_eq!(
+            restored.restore(&snapshot[..snapshot.len() - 1]),
+            Err(SnapshotError::InvalidLength(27))
+        );
+        assert_eq!(restored.restore(b"hash"), Err(SnapshotError::InvalidMagic));
+    }
 }
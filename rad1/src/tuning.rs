@@ -0,0 +1,457 @@
+use crate::agent::{AlphaBetaChessAgent, ChessAgent};
+use crate::eval::naive::{EvaluationConfig, NaiveEvaluator};
+use crate::options::{EngineOptions, OptionError};
+use crate::tt::TranspositionTable;
+use crate::{ChessGame, Color, GameResult};
+use rand::Rng;
+use std::str::FromStr;
+
+// Gain sequence a / (k + 1 + A)^0.602 and perturbation c / (k + 1)^0.101
+// of SPSA as recommended by Spall, c is the step of each parameter
+const LEARNING_RATE: f64 = 2.0;
+const STABILITY: f64 = 10.0;
+const LEARNING_DECAY: f64 = 0.602;
+const PERTURBATION_DECAY: f64 = 0.101;
+// Entries in the transposition table of each engine, the games are shallow
+const TT_SIZE: usize = 100_000;
+// Largest change of a weight by one mutation of the genetic tuner
+const MUTATION: i16 = 2;
+// Games still going after this many plies are scored as draws
+const MAX_PLIES: usize = 200;
+
+/// Balanced positions a few moves into common openings, so self-play games differ
+pub const OPENINGS: [&str; 8] = [
+    "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+    "rnbqkbnr/pp2pppp/3p4/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 3",
+    "rnbqkbnr/ppp2ppp/4p3/3p4/3PP3/8/PPP2PPP/RNBQKBNR w KQkq - 0 3",
+    "rnbqkbnr/pp2pppp/2p5/3p4/3PP3/8/PPP2PPP/RNBQKBNR w KQkq - 0 3",
+    "rnbqkbnr/ppp2ppp/4p3/3p4/2PP4/8/PP2PPPP/RNBQKBNR w KQkq - 0 3",
+    "rnbqkb1r/pppppp1p/5np1/8/2PP4/8/PP2PPPP/RNBQKBNR w KQkq - 0 3",
+    "rnbqkb1r/pppp1ppp/5n2/4p3/2P5/2N5/PP1PPPPP/R1BQKBNR w KQkq - 2 3",
+    "rnbqkb1r/ppp1pppp/5n2/3p4/8/5NP1/PPPPPP1P/RNBQKB1R w KQkq - 2 3",
+];
+
+/// A numeric engine option to tune
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    /// The UCI name of the option
+    pub name: &'static str,
+    pub value: f64,
+    pub min: f64,
+    pub max: f64,
+    /// How far the value is moved either way when comparing two engines
+    pub step: f64,
+}
+
+impl Parameter {
+    pub fn new(name: &'static str, value: f64, min: f64, max: f64, step: f64) -> Self {
+        Self {
+            name,
+            value,
+            min,
+            max,
+            step,
+        }
+    }
+
+    fn clamp(&self, value: f64) -> f64 {
+        value.max(self.min).min(self.max)
+    }
+}
+
+/// The search constants that can be tuned, starting from their defaults
+pub fn search_parameters() -> Vec<Parameter> {
+    let defaults = EngineOptions::default();
+    vec![
+        Parameter::new(
+            "Null Move Reduction",
+            f64::from(defaults.search.null_move_reduction),
+            1.0,
+            6.0,
+            1.0,
+        ),
+        Parameter::new(
+            "QSearch Max Ply",
+            f64::from(defaults.qsearch_max_ply),
+            4.0,
+            64.0,
+            4.0,
+        ),
+    ]
+}
+
+/// Tuning of engine options by
+/// [SPSA](https://www.chessprogramming.org/SPSA) with self-play.
+///
+/// Every iteration moves all parameters a step in random directions, plays
+/// the engine moved one way against the engine moved the other way and
+/// moves the parameters towards the winner. The steps get smaller over
+/// the iterations so the values settle.
+pub struct Spsa {
+    parameters: Vec<Parameter>,
+    depth: u8,
+    game_pairs: usize,
+    iteration: u32,
+}
+
+impl Spsa {
+    /// Tune the parameters with games searched to the given depth,
+    /// fails if one of the parameters isn't a numeric engine option
+    pub fn new(parameters: Vec<Parameter>, depth: u8) -> Result<Self, OptionError> {
+        engine_options(parameters.iter().map(|p| (p.name, p.value)))?;
+        Ok(Self {
+            parameters,
+            depth,
+            game_pairs: 1,
+            iteration: 0,
+        })
+    }
+
+    /// Play this many openings with both colors every iteration
+    pub fn with_game_pairs(mut self, game_pairs: usize) -> Self {
+        self.game_pairs = game_pairs.max(1);
+        self
+    }
+
+    pub fn parameters(&self) -> &[Parameter] {
+        &self.parameters
+    }
+
+    /// Run one iteration, returns the share of the points the engine with
+    /// the parameters moved forward scored
+    pub fn step(&mut self) -> f64 {
+        let mut rng = rand::thread_rng();
+        let k = f64::from(self.iteration);
+        let gain = LEARNING_RATE / (k + 1.0 + STABILITY).powf(LEARNING_DECAY);
+        let perturbation = 1.0 / (k + 1.0).powf(PERTURBATION_DECAY);
+        let directions: Vec<f64> = self
+            .parameters
+            .iter()
+            .map(|_| if rng.gen() { 1.0 } else { -1.0 })
+            .collect();
+        let forward = self.shifted_options(&directions, perturbation);
+        let backward = self.shifted_options(&directions, -perturbation);
+        let score = self.play_match(&forward, &backward);
+        for (parameter, direction) in self.parameters.iter_mut().zip(&directions) {
+            let change = gain * parameter.step * (2.0 * score - 1.0) * direction;
+            parameter.value = parameter.clamp(parameter.value + change);
+        }
+        self.iteration += 1;
+        score
+    }
+
+    /// The parameters as a config file for [`EngineOptions::load_config`]
+    pub fn config(&self) -> String {
+        self.parameters
+            .iter()
+            .map(|p| format!("{} = {}\n", p.name, p.value.round()))
+            .collect()
+    }
+
+    fn shifted_options(&self, directions: &[f64], shift: f64) -> EngineOptions {
+        let values = self
+            .parameters
+            .iter()
+            .zip(directions)
+            .map(|(p, direction)| (p.name, p.clamp(p.value + shift * p.step * direction)));
+        engine_options(values).expect("the parameters were checked by Spsa::new")
+    }
+
+    // Share of the points of the first engine, every opening is played with both colors
+    fn play_match(&self, first: &EngineOptions, second: &EngineOptions) -> f64 {
+        let mut points = 0.0;
+        for pair in 0..self.game_pairs {
+            let index = self.iteration as usize * self.game_pairs + pair;
+            let opening = OPENINGS[index % OPENINGS.len()];
+            points += play_game(opening, &self.agent(first), &self.agent(second));
+            points += 1.0 - play_game(opening, &self.agent(second), &self.agent(first));
+        }
+        points / (2 * self.game_pairs) as f64
+    }
+
+    fn agent(&self, options: &EngineOptions) -> AlphaBetaChessAgent {
+        let mut agent = AlphaBetaChessAgent::new(self.depth, TranspositionTable::new(TT_SIZE));
+        agent.set_options(options.clone());
+        agent
+    }
+}
+
+/// Tuning of the naive evaluator's weights with a genetic algorithm.
+///
+/// Every generation plays a round robin between the configs of the
+/// population, keeps the better half and replaces the rest with mutated
+/// crossovers of the survivors. The fitness is the score in actual games,
+/// which suits weights like the threat bonuses that matter for the moves
+/// they lead to more than for how well the evaluation predicts results.
+pub struct GeneticTuner {
+    population: Vec<EvaluationConfig>,
+    depth: u8,
+    generation: u32,
+}
+
+impl GeneticTuner {
+    /// A population of at least two configs, the default weights and mutations
+    /// of them, playing games searched to the given depth
+    pub fn new(size: usize, depth: u8) -> Self {
+        let mut rng = rand::thread_rng();
+        let mut population = vec![EvaluationConfig::DEFAULT];
+        while population.len() < size.max(2) {
+            population.push(mutate(EvaluationConfig::DEFAULT, &mut rng));
+        }
+        Self {
+            population,
+            depth,
+            generation: 0,
+        }
+    }
+
+    /// The configs of the population, best first once a generation was played
+    pub fn population(&self) -> &[EvaluationConfig] {
+        &self.population
+    }
+
+    /// Play one generation, returns the configs ranked by their share of the
+    /// points in the round robin
+    pub fn generation(&mut self) -> Vec<(EvaluationConfig, f64)> {
+        let size = self.population.len();
+        let mut points = vec![0.0; size];
+        let mut pair = self.generation as usize;
+        for first in 0..size {
+            for second in first + 1..size {
+                let opening = OPENINGS[pair % OPENINGS.len()];
+                let white = play_game(opening, &self.agent(first), &self.agent(second));
+                let black = play_game(opening, &self.agent(second), &self.agent(first));
+                points[first] += white + 1.0 - black;
+                points[second] += 1.0 - white + black;
+                pair += 1;
+            }
+        }
+        let games = (2 * (size - 1)) as f64;
+        let mut ranked: Vec<(EvaluationConfig, f64)> = self
+            .population
+            .iter()
+            .zip(points)
+            .map(|(&config, points)| (config, points / games))
+            .collect();
+        ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+        let mut rng = rand::thread_rng();
+        let survivors: Vec<EvaluationConfig> = ranked
+            .iter()
+            .take((size + 1) / 2)
+            .map(|&(config, _)| config)
+            .collect();
+        self.population = survivors.clone();
+        while self.population.len() < size {
+            let mother = survivors[rng.gen_range(0..survivors.len())];
+            let father = survivors[rng.gen_range(0..survivors.len())];
+            let child = crossover(mother, father, &mut rng);
+            self.population.push(mutate(child, &mut rng));
+        }
+        self.generation += 1;
+        ranked
+    }
+
+    fn agent(&self, index: usize) -> AlphaBetaChessAgent {
+        let evaluator = NaiveEvaluator::with_config(self.population[index]);
+        AlphaBetaChessAgent::new(self.depth, TranspositionTable::new(TT_SIZE))
+            .with_evaluator(Box::new(evaluator))
+    }
+}
+
+/// What one weighted term of the naive evaluator is worth in games
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ablation {
+    /// Name of the term in [`EvaluationConfig::NAMES`]
+    pub term: &'static str,
+    /// Share of the points the full config scored against the config
+    /// with the term's weight set to zero
+    pub score: f64,
+}
+
+impl Ablation {
+    /// The Elo difference the score stands for, positive when the term helps.
+    /// Sweeps are counted as a score of one game less so the difference stays finite.
+    pub fn elo(&self, games: usize) -> f64 {
+        let margin = 0.5 / games.max(1) as f64;
+        let score = self.score.max(margin).min(1.0 - margin);
+        400.0 * (score / (1.0 - score)).log10()
+    }
+}
+
+/// Measure every term of the config by playing `game_pairs` pairs of games,
+/// each opening once with either color, against the config without the term.
+/// `on_term` is called after each term. The terms are returned ranked, the
+/// one whose removal costs the most first.
+pub fn ablate<F>(
+    config: EvaluationConfig,
+    depth: u8,
+    game_pairs: usize,
+    mut on_term: F,
+) -> Vec<Ablation>
+where
+    F: FnMut(&Ablation),
+{
+    let agent = |config| {
+        AlphaBetaChessAgent::new(depth, TranspositionTable::new(TT_SIZE))
+            .with_evaluator(Box::new(NaiveEvaluator::with_config(config)))
+    };
+    let mut ablations: Vec<Ablation> = EvaluationConfig::NAMES
+        .iter()
+        .enumerate()
+        .map(|(index, &term)| {
+            let mut weights = config.weights();
+            weights[index] = 0;
+            let without = EvaluationConfig::from_weights(weights);
+            let mut points = 0.0;
+            for pair in 0..game_pairs {
+                let opening = OPENINGS[pair % OPENINGS.len()];
+                points += play_game(opening, &agent(config), &agent(without));
+                points += 1.0 - play_game(opening, &agent(without), &agent(config));
+            }
+            let ablation = Ablation {
+                term,
+                score: points / (2 * game_pairs.max(1)) as f64,
+            };
+            on_term(&ablation);
+            ablation
+        })
+        .collect();
+    ablations.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+    ablations
+}
+
+// Every weight from one of the parents
+fn crossover(
+    mother: EvaluationConfig,
+    father: EvaluationConfig,
+    rng: &mut impl Rng,
+) -> EvaluationConfig {
+    let mut weights = mother.weights();
+    for (weight, &other) in weights.iter_mut().zip(father.weights().iter()) {
+        if rng.gen() {
+            *weight = other;
+        }
+    }
+    EvaluationConfig::from_weights(weights)
+}
+
+// Change about half of the weights a little, weights don't go below zero
+fn mutate(config: EvaluationConfig, rng: &mut impl Rng) -> EvaluationConfig {
+    let mut weights = config.weights();
+    for weight in weights.iter_mut() {
+        if rng.gen() {
+            *weight = (*weight + rng.gen_range(-MUTATION..=MUTATION)).max(0);
+        }
+    }
+    EvaluationConfig::from_weights(weights)
+}
+
+fn engine_options<'a>(
+    values: impl Iterator<Item = (&'a str, f64)>,
+) -> Result<EngineOptions, OptionError> {
+    let mut options = EngineOptions::default();
+    for (name, value) in values {
+        options.set(name, &value.round().to_string())?;
+    }
+    Ok(options)
+}
+
+// Play the game from the FEN to its end, scored for white with 1 for a win
+// and 0.5 for a draw, games that can be claimed drawn or go on for too long are draws
+fn play_game(fen: &str, white: &dyn ChessAgent, black: &dyn ChessAgent) -> f64 {
+    let mut game = ChessGame::from_str(fen).unwrap();
+    for _ in 0..MAX_PLIES {
+        match game.result() {
+            Some(GameResult::WhiteCheckmates) | Some(GameResult::BlackResigns) => return 1.0,
+            Some(GameResult::BlackCheckmates) | Some(GameResult::WhiteResigns) => return 0.0,
+            Some(_) => return 0.5,
+            None if game.can_declare_draw() => return 0.5,
+            None => {}
+        }
+        let agent = match game.side_to_move() {
+            Color::White => white,
+            Color::Black => black,
+        };
+        game.take_action(agent.get_action(&game));
+    }
+    0.5
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ablate, play_game, search_parameters, Ablation, GeneticTuner, Parameter, Spsa};
+    use crate::agent::AlphaBetaChessAgent;
+    use crate::eval::naive::EvaluationConfig;
+    use crate::options::EngineOptions;
+    use crate::tt::TranspositionTable;
+
+    #[test]
+    fn parameters_must_be_engine_options() {
+        assert!(Spsa::new(search_parameters(), 1).is_ok());
+        let unknown = Parameter::new("Unknown", 1.0, 0.0, 2.0, 1.0);
+        assert!(Spsa::new(vec![unknown], 1).is_err());
+    }
+
+    #[test]
+    fn games_are_scored_for_white() {
+        let white = AlphaBetaChessAgent::new(2, TranspositionTable::new(1000));
+        let black = AlphaBetaChessAgent::new(2, TranspositionTable::new(1000));
+        let mate_in_one = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        assert_eq!(play_game(mate_in_one, &white, &black), 1.0);
+        let bare_kings = "4k3/8/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(play_game(bare_kings, &white, &black), 0.5);
+    }
+
+    #[test]
+    fn tuned_parameters_stay_in_bounds() {
+        let mut spsa = Spsa::new(search_parameters(), 1).unwrap();
+        let score = spsa.step();
+        assert!((0.0..=1.0).contains(&score));
+        for parameter in spsa.parameters() {
+            assert!(parameter.value >= parameter.min && parameter.value <= parameter.max);
+        }
+        let mut options = EngineOptions::default();
+        assert_eq!(options.load_config(&spsa.config()), Ok(()));
+    }
+
+    #[test]
+    fn round_robin_ranks_the_population() {
+        let mut tuner = GeneticTuner::new(3, 1);
+        let ranked = tuner.generation();
+        assert_eq!(ranked.len(), 3);
+        assert!(ranked.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        // every game hands out one point
+        let shares: f64 = ranked.iter().map(|&(_, share)| share).sum();
+        assert!((shares - 1.5).abs() < 1e-9);
+        assert_eq!(tuner.population().len(), 3);
+        assert_eq!(tuner.population()[0], ranked[0].0);
+    }
+
+    #[test]
+    fn ablation_ranks_every_term() {
+        let mut measured = Vec::new();
+        let ranked = ablate(EvaluationConfig::DEFAULT, 1, 1, |ablation| {
+            measured.push(ablation.term)
+        });
+        assert_eq!(measured, EvaluationConfig::NAMES.to_vec());
+        assert_eq!(ranked.len(), EvaluationConfig::NAMES.len());
+        assert!(ranked.windows(2).all(|pair| pair[0].score >= pair[1].score));
+    }
+
+    #[test]
+    fn ablation_elo() {
+        let even = Ablation {
+            term: "pawn_fork",
+            score: 0.5,
+        };
+        assert!(even.elo(10).abs() < 1e-9);
+        let sweep = Ablation { score: 1.0, ..even };
+        assert!(sweep.elo(10).is_finite() && sweep.elo(10) > 0.0);
+        let loss = Ablation {
+            score: 0.25,
+            ..even
+        };
+        assert!((loss.elo(10) + 190.8).abs() < 0.1);
+    }
+}
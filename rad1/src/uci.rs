@@ -0,0 +1,576 @@
+use crate::agent::{AlphaBetaChessAgent, MAX_DEPTH};
+use crate::options::EngineOptions;
+use crate::search::SearchResult;
+use crate::time::TimeControl;
+use crate::{fen, Action, ChessGame, ChessMove, Color};
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+type Output = Arc<Mutex<Box<dyn Write + Send>>>;
+
+// The work the worker thread does in the order it was asked for
+enum Job {
+    Configure(EngineOptions),
+    NewGame,
+    Ready,
+    Search(ChessGame, Go),
+}
+
+// The limits of a `go` command
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Go {
+    depth: Option<u8>,
+    move_time: Option<Duration>,
+    white_time: Option<Duration>,
+    black_time: Option<Duration>,
+    white_increment: Duration,
+    black_increment: Duration,
+    infinite: bool,
+    ponder: bool,
+    search_moves: Vec<ChessMove>,
+}
+
+/// An engine speaking the [UCI](https://www.wbec-ridderkerk.nl/html/UCIProtocol.html)
+/// protocol, for GUIs like Cute Chess and Arena.
+///
+/// The commands are `uci`, `isready`, `setoption`, `ucinewgame`, `position`,
+/// `go`, `stop`, `ponderhit` and `quit`. `go` takes `depth`, `movetime`, the
+/// clocks `wtime`, `btime`, `winc` and `binc`, `infinite`, `ponder` and
+/// `searchmoves`, the other limits are ignored. Without a depth or any time
+/// the search goes to the depth the engine was made with.
+///
+/// Searches run on a worker thread that prints an `info` line after every
+/// iteration and `bestmove` at the end, so `stop` is handled while they run.
+/// `go infinite` keeps its `bestmove` until it is stopped, like UCI asks for.
+/// `go ponder` searches the same way until `ponderhit` or `stop`, after a
+/// `ponderhit` the position is searched again on the clock of the `go`.
+pub struct Engine {
+    options: EngineOptions,
+    game: ChessGame,
+    output: Output,
+    stop: Arc<AtomicBool>,
+    busy: Arc<AtomicBool>,
+    // the opponent played the move the running `go ponder` is searching after
+    ponder_hit: Arc<AtomicBool>,
+    pondering: bool,
+    jobs: Sender<Job>,
+    worker: JoinHandle<()>,
+}
+
+impl Engine {
+    /// An engine searching with `agent` to `depth` when `go` doesn't limit
+    /// the search, announcing and starting with `options`. The agent is
+    /// given the options and the engine's stop flag.
+    pub fn new<W>(depth: u8, options: EngineOptions, agent: AlphaBetaChessAgent, output: W) -> Self
+    where
+        W: Write + Send + 'static,
+    {
+        let output: Output = Arc::new(Mutex::new(Box::new(output)));
+        let stop = Arc::new(AtomicBool::new(false));
+        let busy = Arc::new(AtomicBool::new(false));
+        let ponder_hit = Arc::new(AtomicBool::new(false));
+        let (jobs, received) = mpsc::channel::<Job>();
+        let worker = {
+            let (output, stop, busy) = (output.clone(), stop.clone(), busy.clone());
+            let ponder_hit = ponder_hit.clone();
+            let options = options.clone();
+            thread::spawn(move || {
+                let mut agent = agent.with_stop_flag(stop.clone());
+                agent.set_options(options);
+                for job in received {
+                    match job {
+                        Job::Configure(options) => agent.set_options(options),
+                        Job::NewGame => agent.clear_hash(),
+                        Job::Ready => send(&output, "readyok"),
+                        Job::Search(game, go) => {
+                            let flags = (&*stop, &*ponder_hit);
+                            let result = search(&mut agent, depth, &game, &go, flags, &output);
+                            // idle before the GUI hears of it, so its next `go` is taken
+                            busy.store(false, Ordering::SeqCst);
+                            send(&output, &best_move(&result));
+                        }
+                    }
+                }
+            })
+        };
+        Self {
+            options,
+            game: ChessGame::default(),
+            output,
+            stop,
+            busy,
+            ponder_hit,
+            pondering: false,
+            jobs,
+            worker,
+        }
+    }
+
+    /// Handle one line of input, returns false once it was `quit`
+    pub fn handle(&mut self, line: &str) -> bool {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            None => {}
+            Some("uci") => self.identify(),
+            Some("isready") => {
+                // a running search can't hold up the answer, the jobs queued
+                // before it otherwise have to be done
+                if self.busy.load(Ordering::SeqCst) {
+                    send(&self.output, "readyok");
+                } else {
+                    self.jobs.send(Job::Ready).unwrap();
+                }
+            }
+            Some("setoption") => self.set_option(&words.collect::<Vec<_>>()),
+            Some("ucinewgame") => {
+                self.game = ChessGame::default();
+                self.jobs.send(Job::NewGame).unwrap();
+            }
+            Some("position") => match parse_position(&words.collect::<Vec<_>>()) {
+                Ok(game) => self.game = game,
+                Err(reason) => self.info_string(&reason),
+            },
+            Some("go") => match parse_go(&words.collect::<Vec<_>>()) {
+                Ok(go) => self.go(go),
+                Err(reason) => self.info_string(&reason),
+            },
+            Some("stop") => self.stop.store(true, Ordering::SeqCst),
+            Some("ponderhit") => {
+                if self.pondering && self.busy.load(Ordering::SeqCst) {
+                    self.ponder_hit.store(true, Ordering::SeqCst);
+                    self.stop.store(true, Ordering::SeqCst);
+                }
+                self.pondering = false;
+            }
+            Some("quit") => {
+                self.stop.store(true, Ordering::SeqCst);
+                return false;
+            }
+            // the engine has no debug output or copy protection
+            Some("debug" | "register") => {}
+            Some(command) => self.info_string(&format!("unknown command '{}'", command)),
+        }
+        true
+    }
+
+    /// Wait until the running search has printed its best move and end the worker
+    pub fn finish(self) {
+        drop(self.jobs);
+        let _ = self.worker.join();
+    }
+
+    fn identify(&self) {
+        send(
+            &self.output,
+            &format!("id name rad1 {}", env!("CARGO_PKG_VERSION")),
+        );
+        send(
+            &self.output,
+            &format!("id author {}", env!("CARGO_PKG_AUTHORS")),
+        );
+        for info in self.options.describe() {
+            send(&self.output, &info.uci_declaration());
+        }
+        send(&self.output, "uciok");
+    }
+
+    // `setoption name <name> value <value>`, names and values may have spaces
+    fn set_option(&mut self, words: &[&str]) {
+        let (name, value) = match words.split_first() {
+            Some((&"name", rest)) => match rest.iter().position(|&word| word == "value") {
+                Some(index) => (rest[..index].join(" "), rest[index + 1..].join(" ")),
+                None => (rest.join(" "), String::new()),
+            },
+            _ => return self.info_string("setoption needs a name"),
+        };
+        match self.options.set(&name, &value) {
+            Ok(()) => self
+                .jobs
+                .send(Job::Configure(self.options.clone()))
+                .unwrap(),
+            Err(error) => self.info_string(&error.to_string()),
+        }
+    }
+
+    fn go(&mut self, go: Go) {
+        if self.busy.load(Ordering::SeqCst) {
+            return self.info_string("a search is running, stop it first");
+        }
+        let position = self.game.current_position();
+        if self.game.result().is_some() || !position.has_legal_moves() {
+            return send(&self.output, "bestmove 0000");
+        }
+        self.stop.store(false, Ordering::SeqCst);
+        self.ponder_hit.store(false, Ordering::SeqCst);
+        self.busy.store(true, Ordering::SeqCst);
+        self.pondering = go.ponder;
+        self.jobs.send(Job::Search(self.game.clone(), go)).unwrap();
+    }
+
+    fn info_string(&self, text: &str) {
+        send(&self.output, &format!("info string {}", text));
+    }
+}
+
+// `startpos` or `fen <fen>`, then optionally `moves` and the moves played
+fn parse_position(words: &[&str]) -> Result<ChessGame, String> {
+    let moves_at = words
+        .iter()
+        .position(|&word| word == "moves")
+        .unwrap_or(words.len());
+    let mut game = match words.split_first() {
+        Some((&"startpos", _)) => ChessGame::default(),
+        Some((&"fen", fen)) => {
+            fen::parse_game(&fen[..moves_at - 1].join(" ")).map_err(|error| error.to_string())?
+        }
+        _ => return Err(String::from("position needs startpos or a fen")),
+    };
+    for word in words.iter().skip(moves_at + 1) {
+        let chess_move = ChessMove::from_str(word)
+            .ok()
+            .filter(|&m| game.current_position().legal(m))
+            .ok_or_else(|| format!("{} isn't a legal move", word))?;
+        game.take_action(Action::MakeMove(chess_move));
+    }
+    Ok(game)
+}
+
+fn parse_go(words: &[&str]) -> Result<Go, String> {
+    let mut go = Go::default();
+    let mut words = words.iter();
+    while let Some(&word) = words.next() {
+        let mut number = || {
+            words
+                .next()
+                .and_then(|value| value.parse::<u64>().ok())
+                .ok_or_else(|| format!("{} needs a number", word))
+        };
+        let millis = |number: u64| Duration::from_millis(number);
+        match word {
+            "depth" => go.depth = Some(number()?.clamp(1, MAX_DEPTH as u64) as u8),
+            "movetime" => go.move_time = Some(millis(number()?)),
+            "wtime" => go.white_time = Some(millis(number()?)),
+            "btime" => go.black_time = Some(millis(number()?)),
+            "winc" => go.white_increment = millis(number()?),
+            "binc" => go.black_increment = millis(number()?),
+            // limits the engine doesn't keep to, with their number
+            "movestogo" | "nodes" | "mate" => {
+                number()?;
+            }
+            "infinite" => go.infinite = true,
+            // the moves go on until the next limit, which isn't a move
+            "searchmoves" => {
+                while let Some(chess_move) = words
+                    .clone()
+                    .next()
+                    .and_then(|value| ChessMove::from_str(value).ok())
+                {
+                    go.search_moves.push(chess_move);
+                    words.next();
+                }
+            }
+            "ponder" => go.ponder = true,
+            _ => return Err(format!("unknown go limit '{}'", word)),
+        }
+    }
+    Ok(go)
+}
+
+// Search the game within the limits of the `go`, returns once `bestmove`
+// may be sent. The flags are the engine's stop flag and its `ponderhit`.
+fn search(
+    agent: &mut AlphaBetaChessAgent,
+    default_depth: u8,
+    game: &ChessGame,
+    go: &Go,
+    (stop, ponder_hit): (&AtomicBool, &AtomicBool),
+    output: &Output,
+) -> SearchResult {
+    let (time, increment) = match game.current_position().side_to_move() {
+        Color::White => (go.white_time, go.white_increment),
+        Color::Black => (go.black_time, go.black_increment),
+    };
+    let time_control = time.map(|time| {
+        TimeControl::new(time, increment).with_move_overhead(agent.options().move_overhead)
+    });
+    // a pondering search has the whole of the opponent's time
+    let waits = go.infinite || go.ponder;
+    let unlimited = waits || time_control.is_some() || go.move_time.is_some();
+    agent.set_depth(match go.depth {
+        Some(depth) => depth,
+        None if unlimited => MAX_DEPTH,
+        None => default_depth,
+    });
+    // a clock under `go infinite` is only there to be ignored
+    agent.set_time_control(time_control.filter(|_| !waits));
+    agent.set_move_time(go.move_time.filter(|_| !waits));
+    agent.set_search_moves(go.search_moves.clone());
+    let result = agent.search_game_with_info(game, |result| {
+        for info in result.uci_infos() {
+            send(output, &info);
+        }
+    });
+    while waits && !stop.load(Ordering::SeqCst) {
+        thread::sleep(Duration::from_millis(5));
+    }
+    if go.ponder && ponder_hit.swap(false, Ordering::SeqCst) {
+        stop.store(false, Ordering::SeqCst);
+        let go = Go {
+            ponder: false,
+            ..go.clone()
+        };
+        return search(agent, default_depth, game, &go, (stop, ponder_hit), output);
+    }
+    // a search stopped while the root was searched again has only a bound
+    if !result.bound.is_exact() {
+        send(output, &result.uci_info());
+    }
+    result
+}
+
+// `bestmove`, with the reply the engine expects to ponder on
+fn best_move(result: &SearchResult) -> String {
+    match result.pv.get(1) {
+        Some(ponder) if result.pv[0] == result.best_move => {
+            format!("bestmove {} ponder {}", result.best_move, ponder)
+        }
+        _ => format!("bestmove {}", result.best_move),
+    }
+}
+
+fn send(output: &Output, message: &str) {
+    let mut output = output.lock().unwrap();
+    // a GUI that went away can't be told about it
+    let _ = writeln!(output, "{}", message).and_then(|_| output.flush());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_go, parse_position, Engine, Go};
+    use crate::agent;
+    use crate::options::EngineOptions;
+    use crate::tt::TranspositionTable;
+    use crate::ChessMove;
+    use std::io::{self, Write};
+    use std::str::FromStr;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Buffer {
+        fn lines(&self) -> Vec<String> {
+            let text = String::from_utf8(self.0.lock().unwrap().clone()).unwrap();
+            text.lines().map(String::from).collect()
+        }
+    }
+
+    impl Write for Buffer {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            let written = self.0.lock().unwrap().write(bytes)?;
+            // a slow GUI, the best move is out before the write returns
+            if bytes.starts_with(b"bestmove") {
+                thread::sleep(Duration::from_millis(50));
+            }
+            Ok(written)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn engine(buffer: &Buffer) -> Engine {
+        Engine::new(
+            3,
+            EngineOptions::default(),
+            agent::alpha_beta_agent(3, TranspositionTable::new(100_000)),
+            buffer.clone(),
+        )
+    }
+
+    // Every line the engine wrote for the commands, once it's finished
+    fn exchange(commands: &[&str]) -> Vec<String> {
+        let buffer = Buffer::default();
+        let mut engine = engine(&buffer);
+        for command in commands {
+            if !engine.handle(command) {
+                break;
+            }
+        }
+        engine.finish();
+        buffer.lines()
+    }
+
+    fn best_moves(lines: &[String]) -> usize {
+        lines
+            .iter()
+            .filter(|line| line.starts_with("bestmove"))
+            .count()
+    }
+
+    #[test]
+    fn handshake() {
+        let lines = exchange(&["uci", "isready"]);
+        assert!(lines[0].starts_with("id name rad1"));
+        assert!(lines[1].starts_with("id author"));
+        let options = lines
+            .iter()
+            .filter(|line| line.starts_with("option "))
+            .count();
+        assert_eq!(options, EngineOptions::uci_declarations().len());
+        assert_eq!(lines[lines.len() - 2], "uciok");
+        assert_eq!(lines[lines.len() - 1], "readyok");
+    }
+
+    #[test]
+    fn go_finds_the_mate() {
+        let lines = exchange(&[
+            "position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",
+            "go depth 2",
+        ]);
+        assert!(lines[0].starts_with("info depth 1"));
+        assert_eq!(lines.last().unwrap(), "bestmove a1a8");
+    }
+
+    #[test]
+    fn multi_pv_lines() {
+        let lines = exchange(&[
+            "setoption name MultiPV value 3",
+            "position fen 6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1",
+            "go depth 2",
+        ]);
+        let last_depth: Vec<&String> = lines
+            .iter()
+            .filter(|line| line.starts_with("info multipv") && line.contains(" depth 2 "))
+            .collect();
+        assert_eq!(last_depth.len(), 3);
+        assert!(last_depth[0].starts_with("info multipv 1 depth 2"));
+        assert!(last_depth[0].ends_with("pv a1a8"));
+        assert!(last_depth[2].starts_with("info multipv 3 depth 2"));
+        assert_eq!(lines.last().unwrap(), "bestmove a1a8");
+    }
+
+    #[test]
+    fn moves_after_the_position() {
+        let lines = exchange(&["position startpos moves f2f3 e7e5 g2g4", "go depth 2"]);
+        assert_eq!(lines.last().unwrap(), "bestmove d8h4");
+    }
+
+    #[test]
+    fn clocks_and_search_moves() {
+        let lines = exchange(&[
+            "position startpos",
+            "go wtime 200 btime 200 winc 10 binc 10 searchmoves a2a3 h2h3",
+        ]);
+        let best_move = lines.last().unwrap();
+        assert!(best_move.starts_with("bestmove a2a3") || best_move.starts_with("bestmove h2h3"));
+    }
+
+    #[test]
+    fn stop_ends_an_infinite_search() {
+        let lines = exchange(&[
+            "position startpos",
+            "go infinite",
+            "stop",
+            "quit",
+            "isready",
+        ]);
+        // the engine quit before it got ready
+        assert!(lines.last().unwrap().starts_with("bestmove"));
+        assert!(!lines.iter().any(|line| line == "readyok"));
+    }
+
+    #[test]
+    fn go_is_taken_as_soon_as_the_best_move_is_out() {
+        let buffer = Buffer::default();
+        let mut engine = engine(&buffer);
+        engine.handle("go depth 2");
+        while best_moves(&buffer.lines()) == 0 {
+            thread::sleep(Duration::from_millis(1));
+        }
+        engine.handle("go depth 2");
+        engine.finish();
+        let lines = buffer.lines();
+        assert_eq!(best_moves(&lines), 2);
+        assert!(!lines.iter().any(|line| line.starts_with("info string")));
+    }
+
+    #[test]
+    fn ponder_waits_for_ponderhit() {
+        let buffer = Buffer::default();
+        let mut engine = engine(&buffer);
+        engine.handle("position startpos moves e2e4");
+        engine.handle("go ponder wtime 50 btime 50");
+        thread::sleep(Duration::from_millis(200));
+        // the clock only runs once the opponent played the move
+        assert_eq!(best_moves(&buffer.lines()), 0);
+        engine.handle("ponderhit");
+        engine.finish();
+        let lines = buffer.lines();
+        assert_eq!(best_moves(&lines), 1);
+        assert!(lines.last().unwrap().starts_with("bestmove"));
+    }
+
+    #[test]
+    fn stop_ends_pondering() {
+        let lines = exchange(&["position startpos", "go ponder wtime 50 btime 50", "stop"]);
+        assert_eq!(best_moves(&lines), 1);
+    }
+
+    #[test]
+    fn mistakes_are_reported() {
+        let lines = exchange(&[
+            "setoption name Hash value lots",
+            "setoption name Skill Level value 3",
+            "position startpos moves e2e5",
+            "go sideways",
+            "castle",
+            "position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1 moves a1a8",
+            "go depth 2",
+        ]);
+        assert_eq!(lines.len(), 5);
+        assert!(lines[..4]
+            .iter()
+            .all(|line| line.starts_with("info string")));
+        // the game is over after the mate
+        assert_eq!(lines[4], "bestmove 0000");
+    }
+
+    #[test]
+    fn go_limits() {
+        let go = parse_go(&[
+            "wtime",
+            "1000",
+            "binc",
+            "20",
+            "searchmoves",
+            "e2e4",
+            "d2d4",
+            "depth",
+            "9",
+        ]);
+        assert_eq!(
+            go,
+            Ok(Go {
+                depth: Some(9),
+                white_time: Some(Duration::from_millis(1000)),
+                black_increment: Duration::from_millis(20),
+                search_moves: vec![
+                    ChessMove::from_str("e2e4").unwrap(),
+                    ChessMove::from_str("d2d4").unwrap()
+                ],
+                ..Go::default()
+            })
+        );
+        assert!(parse_go(&["depth"]).is_err());
+        assert!(parse_position(&["fen", "8/8/8", "w"]).is_err());
+    }
+}
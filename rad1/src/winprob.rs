@@ -0,0 +1,420 @@
+use crate::binpack::Entry;
+use crate::score::Score;
+use crate::{GamePhase, Position};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+// Scores beyond this many centipawns are mates or tablebase wins, which say
+// nothing about how evaluations turn into results
+const MAX_FIT_CENTIPAWNS: i32 = 2000;
+// A phase needs this many decided and undecided samples to be fitted
+const MIN_FIT_SAMPLES: usize = 50;
+const NEWTON_STEPS: usize = 50;
+
+const PHASES: [GamePhase; 3] = [
+    GamePhase::Opening,
+    GamePhase::Middlegame,
+    GamePhase::Endgame,
+];
+
+/// The chance of winning with an evaluation in one game phase, a logistic
+/// curve `1 / (1 + e^(offset - slope * pawns))`. Losing is the chance the
+/// opponent wins with the negated evaluation and drawing the rest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhaseFit {
+    /// How fast the chance grows with every pawn of the evaluation
+    pub slope: f64,
+    /// How many pawns, times the slope, an even chance of winning takes
+    pub offset: f64,
+}
+
+impl PhaseFit {
+    fn win(&self, centipawns: i32) -> f64 {
+        logistic(self.slope * f64::from(centipawns) / 100.0 - self.offset)
+    }
+}
+
+/// The chances of the side to move to win, draw or lose
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wdl {
+    pub win: f64,
+    pub draw: f64,
+    pub loss: f64,
+}
+
+impl Wdl {
+    /// The points the side to move can expect from the game
+    pub fn expected_score(&self) -> f64 {
+        self.win + self.draw / 2.0
+    }
+
+    /// The chances of the opponent
+    pub fn flip(&self) -> Self {
+        Self {
+            win: self.loss,
+            draw: self.draw,
+            loss: self.win,
+        }
+    }
+}
+
+impl fmt::Display for Wdl {
+    /// Formats the chances as percentages, like `W 38% D 45% L 17%`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "W {:.0}% D {:.0}% L {:.0}%",
+            100.0 * self.win,
+            100.0 * self.draw,
+            100.0 * self.loss
+        )
+    }
+}
+
+/// Turns the engine's evaluations into chances of winning, drawing and
+/// losing, fitted to the results of rad1's own self-play games instead of
+/// borrowing another engine's scaling. Every game phase has its own curve,
+/// the same evaluation is worth more in an endgame than with all the pieces
+/// on the board.
+///
+/// The default model was fitted by `rad1-cli calibrate`, which fits a new
+/// one for other evaluators and depths. Models are kept as text, a line of
+/// phase, slope and offset for every phase.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WinModel {
+    // indexed like `PHASES`
+    fits: [PhaseFit; 3],
+}
+
+impl Default for WinModel {
+    /// Fitted to 256 self-play games of the default evaluator at depth 4
+    fn default() -> Self {
+        Self {
+            fits: [
+                PhaseFit {
+                    slope: 0.3953,
+                    offset: 0.5393,
+                },
+                PhaseFit {
+                    slope: 0.5613,
+                    offset: 0.6319,
+                },
+                PhaseFit {
+                    slope: 0.6433,
+                    offset: 1.1694,
+                },
+            ],
+        }
+    }
+}
+
+/// The reason a win probability model couldn't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WinModelError {
+    /// A line that isn't a phase followed by two numbers
+    InvalidLine(String),
+    MissingPhase(GamePhase),
+}
+
+impl fmt::Display for WinModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WinModelError::InvalidLine(line) => write!(f, "invalid line '{}'", line),
+            WinModelError::MissingPhase(phase) => write!(f, "no curve for the {}", phase),
+        }
+    }
+}
+
+impl Error for WinModelError {}
+
+impl WinModel {
+    pub fn fit_of(&self, phase: GamePhase) -> PhaseFit {
+        self.fits[phase_index(phase)]
+    }
+
+    /// The chances of the side to move with the score in centipawns, see
+    /// [`Score::to_centipawns`]
+    pub fn wdl(&self, phase: GamePhase, centipawns: i32) -> Wdl {
+        let fit = self.fit_of(phase);
+        let (win, loss) = (fit.win(centipawns), fit.win(-centipawns));
+        // a negative offset would let winning and losing add up to more than certain
+        let draw = (1.0 - (win + loss)).max(0.0);
+        let total = (win + loss) + draw;
+        Wdl {
+            win: win / total,
+            draw: draw / total,
+            loss: loss / total,
+        }
+    }
+
+    /// The chances of the side to move in the position with its score,
+    /// mates are certain
+    pub fn position_wdl(&self, position: &Position, score: Score) -> Wdl {
+        match score {
+            Score::Mate(_) => Wdl {
+                win: 1.0,
+                draw: 0.0,
+                loss: 0.0,
+            },
+            Score::Mated(_) => Wdl {
+                win: 0.0,
+                draw: 0.0,
+                loss: 1.0,
+            },
+            Score::Eval(_) => self.wdl(position.game_phase(), score.to_centipawns()),
+        }
+    }
+
+    /// The evaluation in centipawns the side to move needs for the chance of
+    /// winning, to turn a chance into an adjudication or resign threshold
+    pub fn centipawns_for(&self, phase: GamePhase, win: f64) -> i32 {
+        let fit = self.fit_of(phase);
+        let win = win.clamp(f64::EPSILON, 1.0 - f64::EPSILON);
+        let pawns = ((win / (1.0 - win)).ln() + fit.offset) / fit.slope;
+        (100.0 * pawns).round() as i32
+    }
+
+    /// Fit a curve for every phase to the scores and results of self-play
+    /// games, see [`binpack::self_play`]. Phases with too few decided or
+    /// drawn positions keep the curve of the default model.
+    ///
+    /// Every position is a sample of the side to move winning with its
+    /// score and of the opponent winning with the negated score, both
+    /// fitted by logistic regression.
+    ///
+    /// [`binpack::self_play`]: crate::binpack::self_play
+    pub fn fit(entries: &[Entry]) -> Self {
+        let mut model = Self::default();
+        for (index, &phase) in PHASES.iter().enumerate() {
+            let samples: Vec<(f64, bool)> = entries
+                .iter()
+                .filter(|entry| entry.position.game_phase() == phase)
+                .filter(|entry| entry.score.abs() < MAX_FIT_CENTIPAWNS)
+                .flat_map(|entry| {
+                    let pawns = f64::from(entry.score) / 100.0;
+                    vec![(pawns, entry.result == 1), (-pawns, entry.result == -1)]
+                })
+                .collect();
+            let wins = samples.iter().filter(|&&(_, win)| win).count();
+            if wins >= MIN_FIT_SAMPLES && samples.len() - wins >= MIN_FIT_SAMPLES {
+                model.fits[index] = fit_logistic(&samples, model.fits[index]);
+            }
+        }
+        model
+    }
+}
+
+impl fmt::Display for WinModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# phase slope offset")?;
+        for (phase, fit) in PHASES.iter().zip(&self.fits) {
+            writeln!(f, "{} {:.4} {:.4}", phase, fit.slope, fit.offset)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for WinModel {
+    type Err = WinModelError;
+
+    /// Reads the lines written by [`WinModel`]'s `Display`, empty lines and
+    /// lines starting with `#` are skipped
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut fits = [None; 3];
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || WinModelError::InvalidLine(line.to_string());
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (phase, slope, offset) = match fields.as_slice() {
+                [phase, slope, offset] => (*phase, *slope, *offset),
+                _ => return Err(invalid()),
+            };
+            let index = PHASES
+                .iter()
+                .position(|known| known.to_string() == phase)
+                .ok_or_else(invalid)?;
+            let fit = PhaseFit {
+                slope: slope.parse().map_err(|_| invalid())?,
+                offset: offset.parse().map_err(|_| invalid())?,
+            };
+            if !(fit.slope > 0.0 && fit.offset.is_finite()) {
+                return Err(invalid());
+            }
+            fits[index] = Some(fit);
+        }
+        let mut model = Self::default();
+        for (index, fit) in fits.iter().enumerate() {
+            model.fits[index] = fit.ok_or(WinModelError::MissingPhase(PHASES[index]))?;
+        }
+        Ok(model)
+    }
+}
+
+/// How well a move kept the chances of the side that played it, from 0 to
+/// 100: the share of the expected score before the move that is left after
+/// it. `after` are the chances of the opponent, who is to move then.
+pub fn move_accuracy(before: Wdl, after: Wdl) -> f64 {
+    let (before, after) = (before.expected_score(), after.flip().expected_score());
+    if before <= 0.0 {
+        return 100.0;
+    }
+    100.0 * (after / before).min(1.0)
+}
+
+fn logistic(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+fn phase_index(phase: GamePhase) -> usize {
+    match phase {
+        GamePhase::Opening => 0,
+        GamePhase::Middlegame => 1,
+        GamePhase::Endgame => 2,
+    }
+}
+
+// Maximum likelihood slope and offset of the chance of the samples' flag
+// given their pawns, by Newton's method starting from the fit given
+fn fit_logistic(samples: &[(f64, bool)], start: PhaseFit) -> PhaseFit {
+    let (mut slope, mut offset) = (start.slope, start.offset);
+    for _ in 0..NEWTON_STEPS {
+        // gradient and Hessian of the log-likelihood in (slope, -offset)
+        let (mut g0, mut g1, mut h00, mut h01, mut h11) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for &(pawns, won) in samples {
+            let p = logistic(slope * pawns - offset);
+            let error = if won { 1.0 } else { 0.0 } - p;
+            let weight = p * (1.0 - p);
+            g0 += error * pawns;
+            g1 += error;
+            h00 += weight * pawns * pawns;
+            h01 += weight * pawns;
+            h11 += weight;
+        }
+        let determinant = h00 * h11 - h01 * h01;
+        if determinant.abs() < f64::EPSILON {
+            break;
+        }
+        let step_slope = (h11 * g0 - h01 * g1) / determinant;
+        let step_bias = (h00 * g1 - h01 * g0) / determinant;
+        slope += step_slope;
+        offset -= step_bias;
+        if step_slope.abs() < 1e-9 && step_bias.abs() < 1e-9 {
+            break;
+        }
+    }
+    if slope > 0.0 && slope.is_finite() && offset.is_finite() {
+        PhaseFit { slope, offset }
+    } else {
+        start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{move_accuracy, PhaseFit, WinModel, WinModelError};
+    use crate::binpack::Entry;
+    use crate::score::Score;
+    use crate::{ChessMove, GamePhase, Position};
+    use std::str::FromStr;
+
+    #[test]
+    fn chances_add_up() {
+        let model = WinModel::default();
+        for &phase in &[
+            GamePhase::Opening,
+            GamePhase::Middlegame,
+            GamePhase::Endgame,
+        ] {
+            let even = model.wdl(phase, 0);
+            assert!((even.win - even.loss).abs() < 1e-9);
+            let ahead = model.wdl(phase, 150);
+            assert!(ahead.win > even.win && ahead.loss < even.loss);
+            assert!((ahead.win + ahead.draw + ahead.loss - 1.0).abs() < 1e-9);
+            assert_eq!(model.wdl(phase, -150), ahead.flip());
+            let threshold = model.centipawns_for(phase, 0.9);
+            assert!((model.wdl(phase, threshold).win - 0.9).abs() < 0.01);
+        }
+        let position = Position::default();
+        assert_eq!(model.position_wdl(&position, Score::Mate(3)).win, 1.0);
+        assert_eq!(model.position_wdl(&position, Score::Mated(2)).loss, 1.0);
+    }
+
+    #[test]
+    fn fits_self_play_results() {
+        // a hundred games for every score, won and lost as often as a known curve says
+        let fit = PhaseFit {
+            slope: 2.0,
+            offset: 1.0,
+        };
+        let mut entries = Vec::new();
+        for score in (-300..=300).step_by(20) {
+            let wins = (100.0 * fit.win(score)).round() as usize;
+            let losses = (100.0 * fit.win(-score)).round() as usize;
+            for game in 0..100 {
+                entries.push(Entry {
+                    position: Position::default(),
+                    best_move: ChessMove::from_str("e2e4").unwrap(),
+                    score,
+                    ply: 0,
+                    halfmove_clock: 0,
+                    result: if game < wins {
+                        1
+                    } else if game >= 100 - losses {
+                        -1
+                    } else {
+                        0
+                    },
+                });
+            }
+        }
+        let model = WinModel::fit(&entries);
+        let fitted = model.fit_of(GamePhase::Opening);
+        assert!((fitted.slope - 2.0).abs() < 0.1, "{:?}", fitted);
+        assert!((fitted.offset - 1.0).abs() < 0.1, "{:?}", fitted);
+        // no endgame positions, the default curve is kept
+        assert_eq!(
+            model.fit_of(GamePhase::Endgame),
+            WinModel::default().fit_of(GamePhase::Endgame)
+        );
+    }
+
+    #[test]
+    fn text() {
+        let model = WinModel::default();
+        assert_eq!(WinModel::from_str(&model.to_string()), Ok(model));
+        let model =
+            WinModel::from_str("opening 1 2\nmiddlegame 1.5 2\n# a comment\nendgame 2 0.5\n")
+                .unwrap();
+        assert_eq!(
+            model.fit_of(GamePhase::Endgame),
+            PhaseFit {
+                slope: 2.0,
+                offset: 0.5
+            }
+        );
+        assert_eq!(
+            WinModel::from_str("opening 1 2\nendgame 2 0.5"),
+            Err(WinModelError::MissingPhase(GamePhase::Middlegame))
+        );
+        assert_eq!(
+            WinModel::from_str("opening 1"),
+            Err(WinModelError::InvalidLine(String::from("opening 1")))
+        );
+        assert!(WinModel::from_str("opening -1 2").is_err());
+    }
+
+    #[test]
+    fn accuracy_is_the_expected_score_kept() {
+        let model = WinModel::default();
+        let before = model.wdl(GamePhase::Middlegame, 100);
+        // the opponent is to move after the move, an equal score for them keeps everything
+        assert_eq!(move_accuracy(before, before.flip()), 100.0);
+        let blunder = model.wdl(GamePhase::Middlegame, 500);
+        assert!(move_accuracy(before, blunder) < 20.0);
+        let lost = model.wdl(GamePhase::Middlegame, -500);
+        assert_eq!(move_accuracy(lost, lost.flip()), 100.0);
+    }
+}
@@ -0,0 +1,82 @@
+//! Mate scores go through the transposition table relative to the position
+//! they were found in, so entries stored at one ply and read at another,
+//! or in a later iteration or search, still count the plies from the root.
+
+use rad1::agent::AlphaBetaChessAgent;
+use rad1::score::Score;
+use rad1::search::pns::{Proof, ProofNumberSearch};
+use rad1::tt::TranspositionTable;
+use rad1::Position;
+use std::str::FromStr;
+use test_case::test_case;
+
+// 1. Nf6+ gxf6 2. Bxf7#
+const MATE_IN_TWO: &str = "r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1";
+// 1... Qg6+ 2. Kg4 Qf5+ 3. Kh5 Qh3#
+const MATE_IN_THREE: &str = "2r3k1/p4p2/3Rp2p/1p2P1pK/8/1P4P1/P3Q2P/1q6 b - - 0 1";
+
+fn agent(depth: u8) -> AlphaBetaChessAgent {
+    AlphaBetaChessAgent::new(depth, TranspositionTable::new(1_000_000))
+}
+
+fn position(fen: &str) -> Position {
+    Position::from_str(fen).unwrap()
+}
+
+#[test_case(Score::Mate(5), 3, 1, Score::Mate(3); "mate read closer to the root")]
+#[test_case(Score::Mate(5), 1, 4, Score::Mate(8); "mate read further from the root")]
+#[test_case(Score::Mated(6), 6, 0, Score::Mated(0); "mated read at the root")]
+#[test_case(Score::Mated(4), 2, 3, Score::Mated(5); "mated read further from the root")]
+#[test_case(Score::Eval(-12), 3, 7, Score::Eval(-12); "evaluations stay as they are")]
+fn mate_distances_follow_the_ply(stored: Score, stored_at: usize, read_at: usize, read: Score) {
+    assert_eq!(Score::from_tt(stored.to_tt(stored_at), read_at), read);
+}
+
+#[test_case(MATE_IN_TWO, 3; "mate in two")]
+#[test_case(MATE_IN_THREE, 5; "mate in three")]
+fn mates_keep_their_distance_in_every_iteration(fen: &str, plies: u8) {
+    let mut scores = Vec::new();
+    let result = agent(8).search_with_info(&position(fen), |result| scores.push(result.score));
+    assert_eq!(result.score, Score::Mate(plies));
+    // once the mate is found, deeper iterations reading the entries of the
+    // shallower ones see the same mate
+    let first_mate = scores.iter().position(|score| score.is_mate()).unwrap();
+    assert!(scores[first_mate..]
+        .iter()
+        .all(|&score| score == Score::Mate(plies)));
+}
+
+#[test_case(MATE_IN_TWO, 3; "mate in two")]
+#[test_case(MATE_IN_THREE, 5; "mate in three")]
+fn reused_tables_keep_mate_distances(fen: &str, plies: u8) {
+    let agent = agent(8);
+    let root = position(fen);
+    let first = agent.search(&root);
+    assert_eq!(agent.search(&root).score, Score::Mate(plies));
+
+    // the entries below the first move were stored a ply further from the root
+    let after_first_move = root.make_move_new(first.best_move);
+    assert_eq!(
+        agent.search(&after_first_move).score,
+        Score::Mated(plies - 1)
+    );
+    let after_reply = after_first_move.make_move_new(first.pv[1]);
+    assert_eq!(agent.search(&after_reply).score, Score::Mate(plies - 2));
+}
+
+#[test]
+fn reused_tables_find_no_false_mates() {
+    // bounds read the wrong way round used to turn into a mate in three
+    let root = position("r3k2r/1p3pp1/p1p4p/3pP3/1PP5/P2P1P2/2qnKQ1P/8 b kq - 0 1");
+    assert!(matches!(
+        ProofNumberSearch::new(3).solve(&root).proof,
+        Proof::NoMate
+    ));
+    let agent = agent(6);
+    for _ in 0..3 {
+        let mut scores = Vec::new();
+        let result = agent.search_with_info(&root, |result| scores.push(result.score));
+        assert!(!result.score.is_mate());
+        assert!(scores.iter().all(|score| !score.is_mate()));
+    }
+}